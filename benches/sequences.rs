@@ -0,0 +1,148 @@
+// Push, pop, iteration and sorting costs of the crate's sequences next to
+// `Vec` and `VecDeque`, at a few sizes. Run with `cargo bench`; reports land
+// in `target/criterion`.
+
+use std::collections::VecDeque;
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use rust01::chunked_seq::ChunkedSeq;
+use rust01::doubly_list::LinkedList;
+use rust01::list::List;
+use rust01::sequence::Sequence;
+use rust01::unrolled_list::UnrolledList;
+
+const SIZES: [u64; 3] = [100, 1_000, 10_000];
+
+fn filled<S: Sequence<u64> + Default>(n: u64) -> S {
+    let mut seq = S::default();
+    (0..n).for_each(|i| seq.push_front(i));
+    seq
+}
+
+// The same four workloads for any sequence, under `name` in each group
+fn bench_sequence<S: Sequence<u64> + Default>(c: &mut Criterion, name: &str) {
+    for n in SIZES {
+        c.benchmark_group("push_front").bench_with_input(BenchmarkId::new(name, n), &n, |b, &n| {
+            b.iter(|| filled::<S>(black_box(n)))
+        });
+        c.benchmark_group("push_pop_front").bench_with_input(BenchmarkId::new(name, n), &n, |b, &n| {
+            let mut seq = filled::<S>(n);
+            b.iter(|| {
+                seq.push_front(black_box(n));
+                seq.pop_front()
+            })
+        });
+        c.benchmark_group("drain_front").bench_with_input(BenchmarkId::new(name, n), &n, |b, &n| {
+            b.iter_batched(
+                || filled::<S>(n),
+                |mut seq| while seq.pop_front().is_some() {},
+                criterion::BatchSize::SmallInput,
+            )
+        });
+        c.benchmark_group("iterate").bench_with_input(BenchmarkId::new(name, n), &n, |b, &n| {
+            let seq = filled::<S>(n);
+            b.iter(|| seq.iter().copied().sum::<u64>())
+        });
+    }
+}
+
+// `Vec` only has a cheap back, so it works that end instead
+fn bench_vec(c: &mut Criterion) {
+    for n in SIZES {
+        c.benchmark_group("push_front").bench_with_input(BenchmarkId::new("Vec (back)", n), &n, |b, &n| {
+            b.iter(|| (0..black_box(n)).collect::<Vec<_>>())
+        });
+        c.benchmark_group("push_pop_front").bench_with_input(BenchmarkId::new("Vec (back)", n), &n, |b, &n| {
+            let mut vec: Vec<_> = (0..n).collect();
+            b.iter(|| {
+                vec.push(black_box(n));
+                vec.pop()
+            })
+        });
+        c.benchmark_group("drain_front").bench_with_input(BenchmarkId::new("Vec (back)", n), &n, |b, &n| {
+            b.iter_batched(
+                || (0..n).collect::<Vec<_>>(),
+                |mut vec| while vec.pop().is_some() {},
+                criterion::BatchSize::SmallInput,
+            )
+        });
+        c.benchmark_group("iterate").bench_with_input(BenchmarkId::new("Vec", n), &n, |b, &n| {
+            let vec: Vec<_> = (0..n).collect();
+            b.iter(|| vec.iter().copied().sum::<u64>())
+        });
+    }
+}
+
+// `List::sort` and its radix sort next to `Vec::sort`, on input in various
+// states of order. The list's natural merge sort should come close to
+// linear on the first three, and the radix sort win on the narrow keys.
+fn bench_sort(c: &mut Criterion) {
+    let mut state = 0x2545f4914f6cdd1d_u64;
+    let mut random = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    for n in SIZES {
+        let mut nearly: Vec<u64> = (0..n).collect();
+        for _ in 0..n / 100 {
+            nearly.swap((random() % n) as usize, (random() % n) as usize);
+        }
+        let inputs = [
+            ("sorted", (0..n).collect::<Vec<_>>()),
+            ("reversed", (0..n).rev().collect()),
+            ("nearly sorted", nearly),
+            ("random", (0..n).map(|_| random()).collect()),
+            ("random below 1000", (0..n).map(|_| random() % 1000).collect()),
+        ];
+        for (shape, input) in inputs {
+            let mut group = c.benchmark_group(format!("sort {shape}"));
+            group.bench_with_input(BenchmarkId::new("List", n), &input, |b, input| {
+                // Handing the result back leaves dropping it out of the timing
+                b.iter_batched(
+                    || List::from(input.clone()),
+                    |mut list| {
+                        list.sort();
+                        list
+                    },
+                    criterion::BatchSize::SmallInput,
+                )
+            });
+            group.bench_with_input(BenchmarkId::new("List (radix)", n), &input, |b, input| {
+                b.iter_batched(
+                    || List::from(input.clone()),
+                    |mut list| {
+                        list.sort_unstable_by_key_radix(|&x| x);
+                        list
+                    },
+                    criterion::BatchSize::SmallInput,
+                )
+            });
+            group.bench_with_input(BenchmarkId::new("Vec", n), &input, |b, input| {
+                b.iter_batched(
+                    || input.clone(),
+                    |mut vec| {
+                        vec.sort();
+                        vec
+                    },
+                    criterion::BatchSize::SmallInput,
+                )
+            });
+        }
+    }
+}
+
+fn sequences(c: &mut Criterion) {
+    bench_vec(c);
+    bench_sequence::<VecDeque<u64>>(c, "VecDeque");
+    bench_sequence::<List<u64>>(c, "List");
+    bench_sequence::<LinkedList<u64>>(c, "LinkedList");
+    bench_sequence::<UnrolledList<u64, 32>>(c, "UnrolledList");
+    bench_sequence::<ChunkedSeq<u64>>(c, "ChunkedSeq");
+    bench_sort(c);
+}
+
+criterion_group!(benches, sequences);
+criterion_main!(benches);
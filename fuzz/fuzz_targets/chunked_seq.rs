@@ -0,0 +1,61 @@
+#![no_main]
+
+// Runs one arbitrary script of end pushes and pops, splits and splices on a
+// `ChunkedSeq`, alongside a `Vec` doing the same, and checks that they agree
+// after every step. Run with `cargo fuzz run chunked_seq`.
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use rust01::chunked_seq::ChunkedSeq;
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    PushFront(u8),
+    PushBack(u16),
+    PopFront,
+    PopBack,
+    // Split at the index (modulo one past the length), then put the pieces
+    // back with `count` copies of `val` spliced in between
+    Splice { at: u16, val: u8, count: u8 },
+    // Split at the index and swap the two halves
+    Rotate(u16),
+    Get(u16),
+}
+
+fuzz_target!(|ops: Vec<Op>| {
+    let mut seq = ChunkedSeq::new();
+    let mut model: Vec<u16> = Vec::new();
+    for op in ops {
+        match op {
+            Op::PushFront(val) => {
+                seq.push_front(u16::from(val));
+                model.insert(0, u16::from(val));
+            }
+            Op::PushBack(val) => {
+                seq.push_back(val);
+                model.push(val);
+            }
+            Op::PopFront => assert_eq!(seq.pop_front(), (!model.is_empty()).then(|| model.remove(0))),
+            Op::PopBack => assert_eq!(seq.pop_back(), model.pop()),
+            Op::Splice { at, val, count } => {
+                let at = usize::from(at) % (model.len() + 1);
+                let mut rest = seq.split_off(at);
+                let mut middle: ChunkedSeq<u16> = std::iter::repeat_n(u16::from(val), usize::from(count)).collect();
+                seq.append(&mut middle);
+                seq.append(&mut rest);
+                assert!(middle.is_empty() && rest.is_empty());
+                model.splice(at..at, std::iter::repeat_n(u16::from(val), usize::from(count)));
+            }
+            Op::Rotate(at) => {
+                let at = usize::from(at) % (model.len() + 1);
+                let mut back = seq.split_off(at);
+                back.append(&mut seq);
+                seq = back;
+                model.rotate_left(at);
+            }
+            Op::Get(index) => assert_eq!(seq.get(usize::from(index)), model.get(usize::from(index))),
+        }
+        assert_eq!(seq.len(), model.len());
+        assert!(seq.iter().eq(model.iter()));
+    }
+});
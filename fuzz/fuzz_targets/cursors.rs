@@ -0,0 +1,264 @@
+#![no_main]
+
+// Runs one arbitrary script of end pushes and pops and cursor edits on each
+// list with a cursor, alongside a `Vec` doing the same, and checks that they
+// agree after every step. Run with `cargo fuzz run cursors`.
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use rust01::doubly_list::LinkedList;
+use rust01::gap_buffer::GapBuffer;
+use rust01::list::List;
+use rust01::sequence::SeqCursor;
+use rust01::unrolled_list::UnrolledList;
+
+#[derive(Arbitrary, Debug)]
+enum CursorOp {
+    Next,
+    Prev,
+    Take,
+    InsertAfter(u8),
+    InsertBefore(u8),
+}
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    PushFront(u8),
+    PushBack(u8),
+    PopFront,
+    PopBack,
+    // A cursor from the front running the edits, then dropped
+    Cursor(Vec<CursorOp>),
+}
+
+// The lists under test, behind one interface
+trait Target: Default {
+    fn push_front(&mut self, val: u8);
+    fn push_back(&mut self, val: u8);
+    fn pop_front(&mut self) -> Option<u8>;
+    fn pop_back(&mut self) -> Option<u8>;
+    fn len(&self) -> usize;
+    fn contents(&self) -> Vec<u8>;
+    fn cursor(&mut self) -> impl SeqCursor<u8> + '_;
+}
+
+// A cursor over a `Vec`, following the `SeqCursor` rules. `None` is the
+// ghost position.
+struct Model<'a> {
+    vals: &'a mut Vec<u8>,
+    pos: Option<usize>,
+}
+
+impl SeqCursor<u8> for Model<'_> {
+    fn peek_mut(&mut self) -> Option<&mut u8> {
+        self.vals.get_mut(self.pos?)
+    }
+
+    fn next(&mut self) -> Option<&mut u8> {
+        self.pos = match self.pos {
+            None => (!self.vals.is_empty()).then_some(0),
+            Some(i) => (i + 1 < self.vals.len()).then_some(i + 1),
+        };
+        self.peek_mut()
+    }
+
+    fn prev(&mut self) -> Option<&mut u8> {
+        self.pos = match self.pos {
+            None => self.vals.len().checked_sub(1),
+            Some(i) => i.checked_sub(1),
+        };
+        self.peek_mut()
+    }
+
+    fn take(&mut self) -> Option<u8> {
+        let i = self.pos?;
+        let val = self.vals.remove(i);
+        self.pos = if i < self.vals.len() { Some(i) } else { i.checked_sub(1) };
+        Some(val)
+    }
+
+    fn insert_after(&mut self, element: u8) {
+        self.vals.insert(self.pos.map_or(0, |i| i + 1), element);
+    }
+
+    fn insert_before(&mut self, element: u8) {
+        match self.pos {
+            Some(i) => {
+                self.vals.insert(i, element);
+                self.pos = Some(i + 1);
+            }
+            None => self.vals.push(element),
+        }
+    }
+}
+
+fn run<L: Target>(ops: &[Op]) {
+    let mut list = L::default();
+    let mut model = Vec::new();
+    for op in ops {
+        match op {
+            Op::PushFront(val) => {
+                list.push_front(*val);
+                model.insert(0, *val);
+            }
+            Op::PushBack(val) => {
+                list.push_back(*val);
+                model.push(*val);
+            }
+            Op::PopFront => assert_eq!(list.pop_front(), (!model.is_empty()).then(|| model.remove(0))),
+            Op::PopBack => assert_eq!(list.pop_back(), model.pop()),
+            Op::Cursor(edits) => {
+                let pos = (!model.is_empty()).then_some(0);
+                let mut expected = Model { vals: &mut model, pos };
+                let mut cursor = list.cursor();
+                for edit in edits {
+                    match edit {
+                        CursorOp::Next => assert_eq!(cursor.next(), expected.next()),
+                        CursorOp::Prev => assert_eq!(cursor.prev(), expected.prev()),
+                        CursorOp::Take => assert_eq!(cursor.take(), expected.take()),
+                        CursorOp::InsertAfter(val) => {
+                            cursor.insert_after(*val);
+                            expected.insert_after(*val);
+                        }
+                        CursorOp::InsertBefore(val) => {
+                            cursor.insert_before(*val);
+                            expected.insert_before(*val);
+                        }
+                    }
+                    assert_eq!(cursor.peek_mut(), expected.peek_mut());
+                }
+            }
+        }
+        assert_eq!(list.len(), model.len());
+        assert_eq!(list.contents(), model);
+    }
+}
+
+impl Target for List<u8> {
+    fn push_front(&mut self, val: u8) {
+        self.push_front(val);
+    }
+
+    fn push_back(&mut self, val: u8) {
+        rust01::sequence::Sequence::push_back(self, val);
+    }
+
+    fn pop_front(&mut self) -> Option<u8> {
+        self.pop_front()
+    }
+
+    fn pop_back(&mut self) -> Option<u8> {
+        rust01::sequence::Sequence::pop_back(self)
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn contents(&self) -> Vec<u8> {
+        self.iter().copied().collect()
+    }
+
+    fn cursor(&mut self) -> impl SeqCursor<u8> + '_ {
+        self.cursor_front()
+    }
+}
+
+impl Target for LinkedList<u8> {
+    fn push_front(&mut self, val: u8) {
+        self.push_front(val);
+    }
+
+    fn push_back(&mut self, val: u8) {
+        self.push_back(val);
+    }
+
+    fn pop_front(&mut self) -> Option<u8> {
+        self.pop_front()
+    }
+
+    fn pop_back(&mut self) -> Option<u8> {
+        self.pop_back()
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn contents(&self) -> Vec<u8> {
+        self.iter().copied().collect()
+    }
+
+    fn cursor(&mut self) -> impl SeqCursor<u8> + '_ {
+        self.cursor_front()
+    }
+}
+
+impl Target for GapBuffer<u8> {
+    fn push_front(&mut self, val: u8) {
+        self.insert(0, val);
+    }
+
+    fn push_back(&mut self, val: u8) {
+        self.push_back(val);
+    }
+
+    fn pop_front(&mut self) -> Option<u8> {
+        self.remove(0)
+    }
+
+    fn pop_back(&mut self) -> Option<u8> {
+        self.pop_back()
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn contents(&self) -> Vec<u8> {
+        self.iter().copied().collect()
+    }
+
+    fn cursor(&mut self) -> impl SeqCursor<u8> + '_ {
+        self.cursor_front()
+    }
+}
+
+impl<const B: usize> Target for UnrolledList<u8, B> {
+    fn push_front(&mut self, val: u8) {
+        self.push_front(val);
+    }
+
+    fn push_back(&mut self, val: u8) {
+        self.push_back(val);
+    }
+
+    fn pop_front(&mut self) -> Option<u8> {
+        self.pop_front()
+    }
+
+    fn pop_back(&mut self) -> Option<u8> {
+        self.pop_back()
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn contents(&self) -> Vec<u8> {
+        self.iter().copied().collect()
+    }
+
+    fn cursor(&mut self) -> impl SeqCursor<u8> + '_ {
+        self.cursor_front()
+    }
+}
+
+fuzz_target!(|ops: Vec<Op>| {
+    run::<List<u8>>(&ops);
+    run::<LinkedList<u8>>(&ops);
+    run::<GapBuffer<u8>>(&ops);
+    // Small nodes, so splits and merges happen often
+    run::<UnrolledList<u8, 2>>(&ops);
+    run::<UnrolledList<u8, 5>>(&ops);
+});
@@ -0,0 +1,204 @@
+use std::hash::Hash;
+
+use crate::cache::{Cache, Lists};
+use crate::heap_size::HeapSize;
+use crate::trace;
+
+// Entries seen once and entries seen again, then the keys recently evicted
+// from each, all oldest first
+const T1: usize = 0;
+const T2: usize = 1;
+const B1: usize = 2;
+const B2: usize = 3;
+
+/// A cache using ARC, the adaptive replacement cache policy of Megiddo and
+/// Modha.
+///
+/// Entries used once live in T1 and entries used more than once in T2, both
+/// LRU lists, and each has a list of ghosts, B1 and B2, remembering about as
+/// many recently evicted keys. A target size `p` for T1 decides which list
+/// gives up an entry when the cache is full. A miss on a B1 ghost means T1
+/// was evicting too early, so `p` grows; a miss on a B2 ghost shrinks it. The
+/// cache thereby tunes itself between favoring recency and frequency, and
+/// like 2Q resists one-off scans, with no parameters beyond its capacity.
+pub struct ArcCache<K, V> {
+    lists: Lists<K, V, 4>,
+    capacity: usize,
+    // Target size of T1
+    p: usize,
+}
+
+impl<K: Hash + Eq + Clone, V> ArcCache<K, V> {
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "cache capacity must be positive");
+        ArcCache { lists: Lists::new(), capacity, p: 0 }
+    }
+
+    /// The current target size for the entries seen only once.
+    pub fn recency_target(&self) -> usize {
+        self.p
+    }
+
+    // Evict from T1 or T2 to make room, remembering the key as a ghost
+    fn replace(&mut self, ghost_hit_in_b2: bool) -> Option<(K, V)> {
+        let t1 = self.lists.len(T1);
+        let (from, to) = if t1 > 0 && (t1 > self.p || (ghost_hit_in_b2 && t1 == self.p)) { (T1, B1) } else { (T2, B2) };
+        let (key, value) = self.lists.pop_front(from)?;
+        trace::event("ArcCache", "evict", self.len());
+        self.lists.push_back(to, key.clone(), None);
+        Some((key, value?))
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Cache<K, V> for ArcCache<K, V> {
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn len(&self) -> usize {
+        self.lists.len(T1) + self.lists.len(T2)
+    }
+
+    fn get(&mut self, key: &K) -> Option<&V> {
+        match self.lists.list_of(key)? {
+            T1 | T2 => self.lists.move_to_back(key, T2, None),
+            _ => None,
+        }
+    }
+
+    fn peek(&self, key: &K) -> Option<&V> {
+        self.lists.value(key)
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<(K, V)> {
+        let full = self.len() == self.capacity;
+        let (b1, b2) = (self.lists.len(B1), self.lists.len(B2));
+        match self.lists.list_of(&key) {
+            Some(T1 | T2) => {
+                self.lists.move_to_back(&key, T2, Some(value));
+                None
+            }
+            Some(B1) => {
+                self.p = (self.p + (b2 / b1).max(1)).min(self.capacity);
+                let evicted = if full { self.replace(false) } else { None };
+                self.lists.move_to_back(&key, T2, Some(value));
+                evicted
+            }
+            Some(_) => {
+                self.p = self.p.saturating_sub((b1 / b2).max(1));
+                let evicted = if full { self.replace(true) } else { None };
+                self.lists.move_to_back(&key, T2, Some(value));
+                evicted
+            }
+            None => {
+                let mut evicted = None;
+                let t1 = self.lists.len(T1);
+                if t1 + b1 == self.capacity {
+                    if t1 < self.capacity {
+                        self.lists.pop_front(B1);
+                        if full {
+                            evicted = self.replace(false);
+                        }
+                    } else {
+                        // B1 is empty and T1 fills the cache: drop its oldest
+                        // entry outright
+                        let (key, value) = self.lists.pop_front(T1)?;
+                        trace::event("ArcCache", "evict", self.len());
+                        evicted = Some((key, value?));
+                    }
+                } else if t1 + b1 < self.capacity && self.len() + b1 + b2 >= self.capacity {
+                    if self.len() + b1 + b2 == 2 * self.capacity {
+                        self.lists.pop_front(B2);
+                    }
+                    if full {
+                        evicted = self.replace(false);
+                    }
+                }
+                self.lists.push_back(T1, key, Some(value));
+                evicted
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.lists.remove(key)?.1
+    }
+
+    fn clear(&mut self) {
+        self.lists = Lists::new();
+        self.p = 0;
+    }
+}
+
+impl<K: HeapSize, V: HeapSize> HeapSize for ArcCache<K, V> {
+    fn heap_size(&self) -> usize {
+        self.lists.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cache::Cache;
+    use crate::rng::XorShift64;
+
+    use super::ArcCache;
+
+    #[test]
+    fn test_basic_ops() {
+        let mut cache = ArcCache::new(3);
+        for i in 0..3 {
+            assert_eq!(cache.insert(i, i * 10), None);
+        }
+        assert_eq!(cache.get(&1), Some(&10));
+        // 0 is the oldest entry seen only once
+        assert_eq!(cache.insert(3, 30), Some((0, 0)));
+        assert_eq!(cache.peek(&0), None);
+        assert_eq!(cache.insert(1, 11), None);
+        assert_eq!(cache.get(&1), Some(&11));
+        assert_eq!(cache.remove(&2), Some(20));
+        assert_eq!(cache.len(), 2);
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_ghost_hits_adapt_target() {
+        let mut cache = ArcCache::new(4);
+        for i in 0..4 {
+            cache.insert(i, ());
+        }
+        // Make 0 and 1 frequent, then push 2 and 3 out into B1
+        cache.get(&0);
+        cache.get(&1);
+        cache.insert(4, ());
+        cache.insert(5, ());
+        assert_eq!(cache.recency_target(), 0);
+        // A hit on a B1 ghost: recency deserved more room
+        let evicted = cache.insert(2, ());
+        assert!(evicted.is_some());
+        assert_eq!(cache.recency_target(), 1);
+        assert!(cache.contains_key(&2));
+    }
+
+    #[test]
+    fn test_invariants_under_random_load() {
+        let mut rng = XorShift64::new();
+        let mut cache = ArcCache::new(16);
+        for _ in 0..20_000 {
+            // A small hot set mixed with a wide range of cold keys
+            let key = if rng.next_u64().is_multiple_of(2) { rng.next_u64() % 8 } else { rng.next_u64() % 1000 };
+            if cache.get(&key).is_none() {
+                let evicted = cache.insert(key, key * 2);
+                assert!(evicted.is_none_or(|(k, v)| v == k * 2 && k != key));
+            }
+            assert!(cache.len() <= 16);
+            assert!(cache.recency_target() <= 16);
+            let ghosts = cache.lists.len(super::B1) + cache.lists.len(super::B2);
+            assert!(cache.len() + ghosts <= 32);
+            assert!(cache.lists.len(super::T1) + cache.lists.len(super::B1) <= 16);
+        }
+        // The hot keys are in the cache
+        assert!((0..8).filter(|key| cache.contains_key(key)).count() >= 6);
+    }
+}
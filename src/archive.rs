@@ -0,0 +1,336 @@
+// `rkyv` support, behind the `rkyv` feature.
+//
+// Every sequence archives as an `ArchivedVec` of its elements in order, so
+// a snapshot can be memory-mapped and read as a plain slice whatever
+// structure wrote it. `HashMap` archives as rkyv's own hash map, which can
+// be looked up in place, and `SortedVecMap` as a vector of entries sorted
+// by key, which can be binary searched in place.
+
+use std::hash::Hash;
+
+use rkyv::collections::swiss_table::{ArchivedHashMap, HashMapResolver};
+use rkyv::collections::util::Entry;
+use rkyv::rancor::{Fallible, Source};
+use rkyv::ser::{Allocator, Writer};
+use rkyv::vec::{ArchivedVec, VecResolver};
+use rkyv::{Archive, Deserialize, Place, Serialize};
+
+use crate::chunked_seq::ChunkedSeq;
+use crate::doubly_list::LinkedList;
+use crate::gap_buffer::GapBuffer;
+use crate::hash_map::HashMap;
+use crate::list::List;
+use crate::sorted_vec_map::SortedVecMap;
+use crate::unrolled_list::UnrolledList;
+
+// Same as rkyv uses for the standard `HashMap`
+const LOAD_FACTOR: (usize, usize) = (7, 8);
+
+// rkyv writes a vector from an iterator it can run twice and knows the
+// length of, which not every sequence's iterator is, so gather references
+// first
+fn serialize_seq<'a, T, S>(iter: impl Iterator<Item = &'a T>, serializer: &mut S) -> Result<VecResolver, S::Error>
+where
+    T: Serialize<S> + 'a,
+    S: Fallible + Allocator + Writer + ?Sized,
+{
+    let refs: Vec<&T> = iter.collect();
+    ArchivedVec::<T::Archived>::serialize_from_iter::<T, _, _>(refs.iter().copied(), serializer)
+}
+
+fn deserialize_seq<T, D>(archived: &ArchivedVec<T::Archived>, deserializer: &mut D) -> Result<Vec<T>, D::Error>
+where
+    T: Archive,
+    T::Archived: Deserialize<T, D>,
+    D: Fallible + ?Sized,
+{
+    archived.iter().map(|val| val.deserialize(deserializer)).collect()
+}
+
+impl<T: Archive> Archive for List<T> {
+    type Archived = ArchivedVec<T::Archived>;
+    type Resolver = VecResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        ArchivedVec::resolve_from_len(self.len(), resolver, out);
+    }
+}
+
+impl<T: Serialize<S>, S: Fallible + Allocator + Writer + ?Sized> Serialize<S> for List<T> {
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        serialize_seq(self.iter(), serializer)
+    }
+}
+
+impl<T, D> Deserialize<List<T>, D> for ArchivedVec<T::Archived>
+where
+    T: Archive,
+    T::Archived: Deserialize<T, D>,
+    D: Fallible + ?Sized,
+{
+    fn deserialize(&self, deserializer: &mut D) -> Result<List<T>, D::Error> {
+        let mut list = List::new();
+        for val in deserialize_seq(self, deserializer)?.into_iter().rev() {
+            list.push_front(val);
+        }
+        Ok(list)
+    }
+}
+
+impl<T: Archive> Archive for LinkedList<T> {
+    type Archived = ArchivedVec<T::Archived>;
+    type Resolver = VecResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        ArchivedVec::resolve_from_len(self.len(), resolver, out);
+    }
+}
+
+impl<T: Serialize<S>, S: Fallible + Allocator + Writer + ?Sized> Serialize<S> for LinkedList<T> {
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        serialize_seq(self.iter(), serializer)
+    }
+}
+
+impl<T, D> Deserialize<LinkedList<T>, D> for ArchivedVec<T::Archived>
+where
+    T: Archive,
+    T::Archived: Deserialize<T, D>,
+    D: Fallible + ?Sized,
+{
+    fn deserialize(&self, deserializer: &mut D) -> Result<LinkedList<T>, D::Error> {
+        let mut list = LinkedList::new();
+        for val in deserialize_seq(self, deserializer)? {
+            list.push_back(val);
+        }
+        Ok(list)
+    }
+}
+
+impl<T: Archive, const B: usize> Archive for UnrolledList<T, B> {
+    type Archived = ArchivedVec<T::Archived>;
+    type Resolver = VecResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        ArchivedVec::resolve_from_len(self.len(), resolver, out);
+    }
+}
+
+impl<T: Serialize<S>, S: Fallible + Allocator + Writer + ?Sized, const B: usize> Serialize<S> for UnrolledList<T, B> {
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        serialize_seq(self.iter(), serializer)
+    }
+}
+
+impl<T, D, const B: usize> Deserialize<UnrolledList<T, B>, D> for ArchivedVec<T::Archived>
+where
+    T: Archive,
+    T::Archived: Deserialize<T, D>,
+    D: Fallible + ?Sized,
+{
+    fn deserialize(&self, deserializer: &mut D) -> Result<UnrolledList<T, B>, D::Error> {
+        Ok(deserialize_seq(self, deserializer)?.into_iter().collect())
+    }
+}
+
+impl<T: Archive> Archive for GapBuffer<T> {
+    type Archived = ArchivedVec<T::Archived>;
+    type Resolver = VecResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        ArchivedVec::resolve_from_len(self.len(), resolver, out);
+    }
+}
+
+impl<T: Serialize<S>, S: Fallible + Allocator + Writer + ?Sized> Serialize<S> for GapBuffer<T> {
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        serialize_seq(self.iter(), serializer)
+    }
+}
+
+impl<T, D> Deserialize<GapBuffer<T>, D> for ArchivedVec<T::Archived>
+where
+    T: Archive,
+    T::Archived: Deserialize<T, D>,
+    D: Fallible + ?Sized,
+{
+    fn deserialize(&self, deserializer: &mut D) -> Result<GapBuffer<T>, D::Error> {
+        Ok(GapBuffer::from(deserialize_seq(self, deserializer)?))
+    }
+}
+
+impl<T: Archive + Clone> Archive for ChunkedSeq<T> {
+    type Archived = ArchivedVec<T::Archived>;
+    type Resolver = VecResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        ArchivedVec::resolve_from_len(self.len(), resolver, out);
+    }
+}
+
+impl<T: Serialize<S> + Clone, S: Fallible + Allocator + Writer + ?Sized> Serialize<S> for ChunkedSeq<T> {
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        serialize_seq(self.iter(), serializer)
+    }
+}
+
+impl<T, D> Deserialize<ChunkedSeq<T>, D> for ArchivedVec<T::Archived>
+where
+    T: Archive + Clone,
+    T::Archived: Deserialize<T, D>,
+    D: Fallible + ?Sized,
+{
+    fn deserialize(&self, deserializer: &mut D) -> Result<ChunkedSeq<T>, D::Error> {
+        Ok(deserialize_seq(self, deserializer)?.into_iter().collect())
+    }
+}
+
+impl<K, V: Archive> Archive for HashMap<K, V>
+where
+    K: Archive + Hash + Eq,
+    K::Archived: Hash + Eq,
+{
+    type Archived = ArchivedHashMap<K::Archived, V::Archived>;
+    type Resolver = HashMapResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        ArchivedHashMap::resolve_from_len(self.len(), LOAD_FACTOR, resolver, out);
+    }
+}
+
+impl<K, V, S> Serialize<S> for HashMap<K, V>
+where
+    K: Serialize<S> + Hash + Eq,
+    K::Archived: Hash + Eq,
+    V: Serialize<S>,
+    S: Fallible + Allocator + Writer + ?Sized,
+    S::Error: Source,
+{
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        let entries: Vec<(&K, &V)> = self.iter().collect();
+        ArchivedHashMap::<K::Archived, V::Archived>::serialize_from_iter::<_, _, _, K, V, _>(entries.iter().copied(), LOAD_FACTOR, serializer)
+    }
+}
+
+impl<K, V, D> Deserialize<HashMap<K, V>, D> for ArchivedHashMap<K::Archived, V::Archived>
+where
+    K: Archive + Hash + Eq,
+    K::Archived: Deserialize<K, D> + Hash + Eq,
+    V: Archive,
+    V::Archived: Deserialize<V, D>,
+    D: Fallible + ?Sized,
+{
+    fn deserialize(&self, deserializer: &mut D) -> Result<HashMap<K, V>, D::Error> {
+        let mut map = HashMap::with_capacity(self.len());
+        for (key, value) in self.iter() {
+            map.insert(key.deserialize(deserializer)?, value.deserialize(deserializer)?);
+        }
+        Ok(map)
+    }
+}
+
+impl<K: Archive + Ord, V: Archive> Archive for SortedVecMap<K, V> {
+    type Archived = ArchivedVec<Entry<K::Archived, V::Archived>>;
+    type Resolver = VecResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        ArchivedVec::resolve_from_len(self.len(), resolver, out);
+    }
+}
+
+impl<K, V, S> Serialize<S> for SortedVecMap<K, V>
+where
+    K: Serialize<S> + Ord,
+    V: Serialize<S>,
+    S: Fallible + Allocator + Writer + ?Sized,
+{
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        use rkyv::collections::util::EntryAdapter;
+
+        let entries: Vec<EntryAdapter<&K, &V, K, V>> = self.iter().map(|(key, value)| EntryAdapter::new(key, value)).collect();
+        ArchivedVec::serialize_from_slice(&entries, serializer)
+    }
+}
+
+impl<K, V, D> Deserialize<SortedVecMap<K, V>, D> for ArchivedVec<Entry<K::Archived, V::Archived>>
+where
+    K: Archive + Ord,
+    K::Archived: Deserialize<K, D>,
+    V: Archive,
+    V::Archived: Deserialize<V, D>,
+    D: Fallible + ?Sized,
+{
+    fn deserialize(&self, deserializer: &mut D) -> Result<SortedVecMap<K, V>, D::Error> {
+        let mut map = SortedVecMap::with_capacity(self.len());
+        // Already in key order, so every insert lands at the end
+        for entry in self.iter() {
+            map.insert(entry.key.deserialize(deserializer)?, entry.value.deserialize(deserializer)?);
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rkyv::collections::swiss_table::ArchivedHashMap;
+    use rkyv::collections::util::Entry;
+    use rkyv::rancor::Error;
+    use rkyv::vec::ArchivedVec;
+    use rkyv::{Archived, access, from_bytes, to_bytes};
+
+    use crate::chunked_seq::ChunkedSeq;
+    use crate::doubly_list::LinkedList;
+    use crate::gap_buffer::GapBuffer;
+    use crate::hash_map::HashMap;
+    use crate::list::List;
+    use crate::sorted_vec_map::SortedVecMap;
+    use crate::unrolled_list::UnrolledList;
+
+    #[test]
+    fn test_sequences_archive_as_slices() {
+        let mut list = List::new();
+        (0..100).rev().for_each(|i| list.push_front(i));
+        let bytes = to_bytes::<Error>(&list).unwrap();
+        // Read in place, without deserializing
+        let archived = access::<ArchivedVec<Archived<u32>>, Error>(&bytes).unwrap();
+        assert!(archived.iter().map(|val| val.to_native()).eq(0..100));
+        let back: List<u32> = from_bytes::<_, Error>(&bytes).unwrap();
+        assert!(back.iter().copied().eq(0..100));
+
+        // Every sequence writes the same bytes for the same elements
+        let mut linked = LinkedList::new();
+        (0..100).for_each(|i| linked.push_back(i));
+        assert_eq!(to_bytes::<Error>(&linked).unwrap().as_slice(), bytes.as_slice());
+        let unrolled: UnrolledList<u32, 8> = (0..100).collect();
+        assert_eq!(to_bytes::<Error>(&unrolled).unwrap().as_slice(), bytes.as_slice());
+        let mut buffer: GapBuffer<u32> = (0..100).collect();
+        buffer.move_gap(40);
+        assert_eq!(to_bytes::<Error>(&buffer).unwrap().as_slice(), bytes.as_slice());
+        let seq: ChunkedSeq<u32> = (0..100).collect();
+        assert_eq!(to_bytes::<Error>(&seq).unwrap().as_slice(), bytes.as_slice());
+
+        let back: LinkedList<u32> = from_bytes::<_, Error>(&bytes).unwrap();
+        assert!(back.iter().copied().eq(0..100));
+        let back: ChunkedSeq<u32> = from_bytes::<_, Error>(&bytes).unwrap();
+        assert_eq!(back, seq);
+    }
+
+    #[test]
+    fn test_maps() {
+        let map: HashMap<String, u32> = (0..50).map(|i| (format!("key{i}"), i)).collect();
+        let bytes = to_bytes::<Error>(&map).unwrap();
+        let archived = access::<ArchivedHashMap<Archived<String>, Archived<u32>>, Error>(&bytes).unwrap();
+        assert_eq!(archived.get("key7").map(|val| val.to_native()), Some(7));
+        assert_eq!(archived.len(), 50);
+        let back: HashMap<String, u32> = from_bytes::<_, Error>(&bytes).unwrap();
+        assert_eq!(back.get("key49"), Some(&49));
+
+        let map: SortedVecMap<u32, String> = (0..50).rev().map(|i| (i * 2, i.to_string())).collect();
+        let bytes = to_bytes::<Error>(&map).unwrap();
+        let archived = access::<ArchivedVec<Entry<Archived<u32>, Archived<String>>>, Error>(&bytes).unwrap();
+        let found = archived.binary_search_by_key(&84, |entry| entry.key.to_native()).unwrap();
+        assert_eq!(archived[found].value, "42");
+        let back: SortedVecMap<u32, String> = from_bytes::<_, Error>(&bytes).unwrap();
+        assert!(back.iter().eq(map.iter()));
+    }
+}
@@ -0,0 +1,274 @@
+use std::fmt::Debug;
+use std::ops::{Index, IndexMut};
+
+use crate::fallible::AllocError;
+use crate::heap_size::{HeapSize, payload};
+
+// Size of the first chunk; each later chunk doubles
+const FIRST_CHUNK: usize = 16;
+
+/// Handle to a value allocated in an `Arena`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ArenaId(usize);
+
+enum Slot<T> {
+    Occupied(T),
+    // Next slot in the free list, if reuse is on
+    Vacant(Option<ArenaId>),
+}
+
+/// Bump allocation of many values of one type.
+///
+/// Slots are carved off the end of the newest chunk in order, and chunks
+/// double in size, so `n` allocations cost O(log n) heap allocations and
+/// nothing is ever moved. Dropping the arena or calling `reset` frees
+/// everything at once.
+///
+/// Freeing an individual value leaves its slot empty. An arena built with
+/// `with_reuse` chains those slots into a free list and hands them out
+/// before bumping; a plain arena leaves them unused until the next `reset`.
+pub struct Arena<T> {
+    chunks: Vec<Vec<Slot<T>>>,
+    // Slots handed out so far, including freed ones
+    bumped: usize,
+    len: usize,
+    reuse: bool,
+    free: Option<ArenaId>,
+}
+
+/// Chunk number and offset within it of the slot at `index`.
+fn locate(index: usize) -> (usize, usize) {
+    let q = index / FIRST_CHUNK + 1;
+    let chunk = (usize::BITS - 1 - q.leading_zeros()) as usize;
+    (chunk, index - FIRST_CHUNK * ((1 << chunk) - 1))
+}
+
+impl<T> Arena<T> {
+    pub const fn new() -> Self {
+        Arena { chunks: Vec::new(), bumped: 0, len: 0, reuse: false, free: None }
+    }
+
+    /// An arena that reuses freed slots.
+    pub const fn with_reuse() -> Self {
+        Arena { chunks: Vec::new(), bumped: 0, len: 0, reuse: true, free: None }
+    }
+
+    /// Number of live values.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of heap allocations made for slots.
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    fn slot(&self, id: ArenaId) -> Option<&Slot<T>> {
+        if id.0 >= self.bumped {
+            return None;
+        }
+        let (chunk, offset) = locate(id.0);
+        Some(&self.chunks[chunk][offset])
+    }
+
+    fn slot_mut(&mut self, id: ArenaId) -> Option<&mut Slot<T>> {
+        if id.0 >= self.bumped {
+            return None;
+        }
+        let (chunk, offset) = locate(id.0);
+        Some(&mut self.chunks[chunk][offset])
+    }
+
+    pub fn alloc(&mut self, value: T) -> ArenaId {
+        self.len += 1;
+        if let Some(id) = self.free {
+            let slot = self.slot_mut(id).unwrap();
+            let Slot::Vacant(next) = std::mem::replace(slot, Slot::Occupied(value)) else {
+                unreachable!("free list points at an occupied slot");
+            };
+            self.free = next;
+            return id;
+        }
+        let (chunk, _) = locate(self.bumped);
+        if chunk == self.chunks.len() {
+            self.chunks.push(Vec::with_capacity(FIRST_CHUNK << chunk));
+        }
+        self.chunks[chunk].push(Slot::Occupied(value));
+        self.bumped += 1;
+        ArenaId(self.bumped - 1)
+    }
+
+    /// `alloc`, but returning an error instead of aborting if a new chunk
+    /// is needed and can't be allocated.
+    pub fn try_alloc(&mut self, value: T) -> Result<ArenaId, AllocError> {
+        let (chunk, _) = locate(self.bumped);
+        if self.free.is_none() && chunk == self.chunks.len() {
+            let mut slots = Vec::new();
+            slots.try_reserve_exact(FIRST_CHUNK << chunk)?;
+            self.chunks.try_reserve(1)?;
+            self.chunks.push(slots);
+        }
+        Ok(self.alloc(value))
+    }
+
+    /// Free one value, returning it if it was live.
+    pub fn free(&mut self, id: ArenaId) -> Option<T> {
+        let next = if self.reuse { self.free } else { None };
+        let slot = self.slot_mut(id)?;
+        if let Slot::Vacant(_) = slot {
+            return None;
+        }
+        let Slot::Occupied(value) = std::mem::replace(slot, Slot::Vacant(next)) else {
+            unreachable!();
+        };
+        if self.reuse {
+            self.free = Some(id);
+        }
+        self.len -= 1;
+        Some(value)
+    }
+
+    pub fn get(&self, id: ArenaId) -> Option<&T> {
+        match self.slot(id)? {
+            Slot::Occupied(value) => Some(value),
+            Slot::Vacant(_) => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, id: ArenaId) -> Option<&mut T> {
+        match self.slot_mut(id)? {
+            Slot::Occupied(value) => Some(value),
+            Slot::Vacant(_) => None,
+        }
+    }
+
+    /// Free every value at once, keeping only the first chunk's buffer.
+    pub fn reset(&mut self) {
+        self.chunks.truncate(1);
+        if let Some(first) = self.chunks.first_mut() {
+            first.clear();
+        }
+        self.bumped = 0;
+        self.len = 0;
+        self.free = None;
+    }
+
+    /// Every live value with its id, in allocation order.
+    pub fn iter(&self) -> impl Iterator<Item = (ArenaId, &T)> {
+        self.chunks.iter().flatten().enumerate().filter_map(|(i, slot)| match slot {
+            Slot::Occupied(value) => Some((ArenaId(i), value)),
+            Slot::Vacant(_) => None,
+        })
+    }
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Index<ArenaId> for Arena<T> {
+    type Output = T;
+
+    fn index(&self, id: ArenaId) -> &T {
+        self.get(id).expect("invalid arena id")
+    }
+}
+
+impl<T> IndexMut<ArenaId> for Arena<T> {
+    fn index_mut(&mut self, id: ArenaId) -> &mut T {
+        self.get_mut(id).expect("invalid arena id")
+    }
+}
+
+impl<T: Debug> Debug for Arena<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Arena<T> {
+    fn heap_size(&self) -> usize {
+        let slots: usize = self.chunks.iter().map(Vec::capacity).sum();
+        self.chunks.capacity() * size_of::<Vec<Slot<T>>>()
+            + slots * size_of::<Slot<T>>()
+            + payload(self.iter().map(|(_, val)| val))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{locate, Arena};
+
+    #[test]
+    fn test_locate() {
+        assert_eq!(locate(0), (0, 0));
+        assert_eq!(locate(15), (0, 15));
+        assert_eq!(locate(16), (1, 0));
+        assert_eq!(locate(47), (1, 31));
+        assert_eq!(locate(48), (2, 0));
+    }
+
+    #[test]
+    fn test_alloc_get_free() {
+        let mut arena = Arena::new();
+        let a = arena.alloc("a");
+        let b = arena.alloc("b");
+        assert_eq!(arena[a], "a");
+        arena[b] = "B";
+        assert_eq!(arena.get(b), Some(&"B"));
+        assert_eq!(arena.len(), 2);
+
+        assert_eq!(arena.free(a), Some("a"));
+        assert_eq!(arena.free(a), None);
+        assert_eq!(arena.get(a), None);
+        // Without reuse the slot stays empty
+        let c = arena.alloc("c");
+        assert_ne!(c, a);
+        assert_eq!(arena.iter().map(|(_, v)| *v).collect::<Vec<_>>(), vec!["B", "c"]);
+    }
+
+    #[test]
+    fn test_reuse() {
+        let mut arena = Arena::with_reuse();
+        let ids: Vec<_> = (0..4).map(|i| arena.alloc(i)).collect();
+        arena.free(ids[1]);
+        arena.free(ids[2]);
+        assert_eq!(arena.alloc(20), ids[2]);
+        assert_eq!(arena.alloc(10), ids[1]);
+        assert_eq!(arena.len(), 4);
+    }
+
+    #[test]
+    fn test_try_alloc() {
+        let mut arena = Arena::with_reuse();
+        let ids: Vec<_> = (0..20).map(|i| arena.try_alloc(i).unwrap()).collect();
+        assert_eq!(arena.chunk_count(), 2);
+        arena.free(ids[3]);
+        assert_eq!(arena.try_alloc(30), Ok(ids[3]));
+        assert!(ids.iter().enumerate().all(|(i, &id)| arena[id] == if i == 3 { 30 } else { i }));
+    }
+
+    #[test]
+    fn test_few_allocations() {
+        let mut arena = Arena::new();
+        for i in 0..1_000_000 {
+            arena.alloc(i);
+        }
+        assert!(arena.chunk_count() <= 16);
+        let (last, &value) = arena.iter().last().unwrap();
+        assert_eq!(value, 999_999);
+        assert_eq!(arena[last], 999_999);
+
+        arena.reset();
+        assert!(arena.is_empty());
+        assert_eq!(arena.get(last), None);
+        let id = arena.alloc(7);
+        assert_eq!(arena[id], 7);
+    }
+}
@@ -0,0 +1,280 @@
+use std::fmt::Debug;
+
+use crate::arena::{Arena, ArenaId};
+use crate::heap_size::HeapSize;
+
+struct Node<T> {
+    data: T,
+    prev: Option<ArenaId>,
+    next: Option<ArenaId>,
+}
+
+/// A doubly linked list whose nodes live in an `Arena` instead of separate
+/// heap allocations.
+///
+/// Links are arena ids rather than `Rc`s, so there is no reference counting
+/// or `RefCell` borrowing, and building a list of n elements takes O(log n)
+/// allocations. Popped nodes go back on the arena's free list for the next
+/// push to reuse.
+pub struct ArenaList<T> {
+    nodes: Arena<Node<T>>,
+    head: Option<ArenaId>,
+    tail: Option<ArenaId>,
+}
+
+impl<T> ArenaList<T> {
+    pub const fn new() -> Self {
+        ArenaList { nodes: Arena::with_reuse(), head: None, tail: None }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Number of heap allocations backing the nodes.
+    pub fn allocations(&self) -> usize {
+        self.nodes.chunk_count()
+    }
+
+    pub fn push_front(&mut self, val: T) {
+        let id = self.nodes.alloc(Node { data: val, prev: None, next: self.head });
+        match self.head {
+            Some(old_head) => self.nodes[old_head].prev = Some(id),
+            None => self.tail = Some(id),
+        }
+        self.head = Some(id);
+    }
+
+    pub fn push_back(&mut self, val: T) {
+        let id = self.nodes.alloc(Node { data: val, prev: self.tail, next: None });
+        match self.tail {
+            Some(old_tail) => self.nodes[old_tail].next = Some(id),
+            None => self.head = Some(id),
+        }
+        self.tail = Some(id);
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        let node = self.nodes.free(self.head?).unwrap();
+        self.head = node.next;
+        match self.head {
+            Some(head) => self.nodes[head].prev = None,
+            None => self.tail = None,
+        }
+        Some(node.data)
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        let node = self.nodes.free(self.tail?).unwrap();
+        self.tail = node.prev;
+        match self.tail {
+            Some(tail) => self.nodes[tail].next = None,
+            None => self.head = None,
+        }
+        Some(node.data)
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.head.map(|id| &self.nodes[id].data)
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.tail.map(|id| &self.nodes[id].data)
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        self.head.map(|id| &mut self.nodes[id].data)
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        self.tail.map(|id| &mut self.nodes[id].data)
+    }
+
+    /// Move the elements into a fresh arena sized for them, in list order,
+    /// so a list that shrank after a burst gives back the chunks it no
+    /// longer needs. O(n).
+    pub fn compact(&mut self) {
+        let mut old = std::mem::take(self);
+        while let Some(val) = old.pop_front() {
+            self.push_back(val);
+        }
+    }
+
+    /// Drop every element, releasing the nodes in one go.
+    pub fn clear(&mut self) {
+        self.nodes.reset();
+        self.head = None;
+        self.tail = None;
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { list: self, front: self.head, back: self.tail, remaining: self.len() }
+    }
+}
+
+impl<T> Default for ArenaList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Iter<'a, T> {
+    list: &'a ArenaList<T>,
+    front: Option<ArenaId>,
+    back: Option<ArenaId>,
+    // Stops front and back from crossing
+    remaining: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = &self.list.nodes[self.front?];
+        self.front = node.next;
+        self.remaining -= 1;
+        Some(&node.data)
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = &self.list.nodes[self.back?];
+        self.back = node.prev;
+        self.remaining -= 1;
+        Some(&node.data)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a ArenaList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T> FromIterator<T> for ArenaList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = ArenaList::new();
+        for val in iter {
+            list.push_back(val);
+        }
+        list
+    }
+}
+
+impl<T: Debug> Debug for ArenaList<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Node<T> {
+    fn heap_size(&self) -> usize {
+        self.data.heap_size()
+    }
+}
+
+impl<T: HeapSize> HeapSize for ArenaList<T> {
+    fn heap_size(&self) -> usize {
+        self.nodes.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ArenaList;
+
+    #[test]
+    fn test_push_pop() {
+        let mut list = ArenaList::new();
+        list.push_back(2);
+        list.push_front(1);
+        list.push_back(3);
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.front(), Some(&1));
+        assert_eq!(list.back(), Some(&3));
+
+        assert_eq!(list.pop_front(), Some(1));
+        assert_eq!(list.pop_back(), Some(3));
+        assert_eq!(list.pop_back(), Some(2));
+        assert_eq!(list.pop_back(), None);
+        assert_eq!(list.pop_front(), None);
+        assert!(list.is_empty());
+
+        list.push_back(4);
+        assert_eq!(list.front(), list.back());
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut list: ArenaList<i32> = (1..=5).collect();
+        *list.front_mut().unwrap() = 10;
+        *list.back_mut().unwrap() = 50;
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), vec![10, 2, 3, 4, 50]);
+        assert_eq!(list.iter().rev().copied().collect::<Vec<_>>(), vec![50, 4, 3, 2, 10]);
+
+        let mut iter = list.iter();
+        assert_eq!(iter.next(), Some(&10));
+        assert_eq!(iter.next_back(), Some(&50));
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.next_back(), Some(&4));
+        assert_eq!(iter.next(), Some(&3));
+        assert_eq!(iter.next_back(), None);
+        assert_eq!(format!("{:?}", list), "[10, 2, 3, 4, 50]");
+    }
+
+    #[test]
+    fn test_node_reuse() {
+        let mut list = ArenaList::new();
+        for round in 0..100 {
+            for i in 0..50 {
+                list.push_back(round * 50 + i);
+            }
+            for _ in 0..50 {
+                list.pop_front();
+            }
+        }
+        // Popped nodes are reused instead of growing the arena
+        assert!(list.allocations() <= 3);
+    }
+
+    #[test]
+    fn test_compact() {
+        let mut list: ArenaList<i32> = (0..10_000).collect();
+        for _ in 0..9990 {
+            list.pop_front();
+        }
+        assert!(list.allocations() > 5);
+        list.compact();
+        assert_eq!(list.allocations(), 1);
+        assert!(list.iter().copied().eq(9990..10_000));
+        assert_eq!(list.back(), Some(&9999));
+    }
+
+    #[test]
+    fn test_million_nodes() {
+        let mut list = ArenaList::new();
+        for i in 0..1_000_000 {
+            list.push_back(i);
+        }
+        assert_eq!(list.len(), 1_000_000);
+        assert!(list.allocations() <= 16);
+        assert_eq!(list.back(), Some(&999_999));
+        list.clear();
+        assert!(list.is_empty());
+        assert_eq!(list.front(), None);
+    }
+}
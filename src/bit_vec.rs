@@ -0,0 +1,319 @@
+use std::fmt::Debug;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign};
+
+use crate::error::{CollectionError, check_index};
+use crate::heap_size::HeapSize;
+
+const WORD_BITS: usize = 64;
+
+/// A growable sequence of bits packed 64 to a word.
+///
+/// Bits past `len` in the last word are always kept zero, so whole words can
+/// be compared, counted and combined directly.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct BitVec {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl BitVec {
+    pub const fn new() -> Self {
+        BitVec { words: Vec::new(), len: 0 }
+    }
+
+    pub fn with_capacity(bits: usize) -> Self {
+        BitVec { words: Vec::with_capacity(bits.div_ceil(WORD_BITS)), len: 0 }
+    }
+
+    /// A vector of `len` copies of `bit`.
+    pub fn from_elem(len: usize, bit: bool) -> Self {
+        let fill = if bit { u64::MAX } else { 0 };
+        let mut vec = BitVec { words: vec![fill; len.div_ceil(WORD_BITS)], len };
+        vec.clear_tail();
+        vec
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.words.clear();
+        self.len = 0;
+    }
+
+    /// The underlying words, least significant bit first.
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    fn clear_tail(&mut self) {
+        let used = self.len % WORD_BITS;
+        if used != 0 {
+            *self.words.last_mut().unwrap() &= (1 << used) - 1;
+        }
+    }
+
+    pub fn push(&mut self, bit: bool) {
+        if self.len.is_multiple_of(WORD_BITS) {
+            self.words.push(0);
+        }
+        self.len += 1;
+        self.set(self.len - 1, bit);
+    }
+
+    pub fn pop(&mut self) -> Option<bool> {
+        let bit = self.get(self.len.checked_sub(1)?)?;
+        self.set(self.len - 1, false);
+        self.len -= 1;
+        if self.len.is_multiple_of(WORD_BITS) {
+            self.words.pop();
+        }
+        Some(bit)
+    }
+
+    pub fn get(&self, index: usize) -> Option<bool> {
+        if index >= self.len {
+            return None;
+        }
+        Some(self.words[index / WORD_BITS] >> (index % WORD_BITS) & 1 == 1)
+    }
+
+    /// Panics if `index` is out of bounds.
+    pub fn set(&mut self, index: usize, bit: bool) {
+        assert!(index < self.len, "index {index} out of bounds for BitVec of length {}", self.len);
+        let word = &mut self.words[index / WORD_BITS];
+        let mask = 1 << (index % WORD_BITS);
+        if bit {
+            *word |= mask;
+        } else {
+            *word &= !mask;
+        }
+    }
+
+    /// `set`, but returning an error if `index` is out of bounds.
+    pub fn try_set(&mut self, index: usize, bit: bool) -> Result<(), CollectionError> {
+        check_index(index, self.len)?;
+        self.set(index, bit);
+        Ok(())
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn count_zeros(&self) -> usize {
+        self.len - self.count_ones()
+    }
+
+    /// Every bit in order.
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len).map(|i| self.get(i).unwrap())
+    }
+
+    /// Indices of the set bits in ascending order, skipping over zero words.
+    pub fn ones(&self) -> Ones<'_> {
+        Ones { words: &self.words, index: 0, word: self.words.first().copied().unwrap_or(0) }
+    }
+
+    fn zip_words(&mut self, other: &BitVec, f: impl Fn(u64, u64) -> u64) {
+        assert_eq!(self.len, other.len, "bitwise operation on BitVecs of different lengths");
+        for (a, &b) in self.words.iter_mut().zip(&other.words) {
+            *a = f(*a, b);
+        }
+    }
+}
+
+pub struct Ones<'a> {
+    words: &'a [u64],
+    index: usize,
+    // The current word with already yielded bits cleared
+    word: u64,
+}
+
+impl Iterator for Ones<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.word == 0 {
+            self.index += 1;
+            self.word = *self.words.get(self.index)?;
+        }
+        let bit = self.word.trailing_zeros() as usize;
+        self.word &= self.word - 1;
+        Some(self.index * WORD_BITS + bit)
+    }
+}
+
+// Bitwise operators combine two vectors of equal length and panic otherwise.
+
+impl BitAndAssign<&BitVec> for BitVec {
+    fn bitand_assign(&mut self, rhs: &BitVec) {
+        self.zip_words(rhs, |a, b| a & b);
+    }
+}
+
+impl BitOrAssign<&BitVec> for BitVec {
+    fn bitor_assign(&mut self, rhs: &BitVec) {
+        self.zip_words(rhs, |a, b| a | b);
+    }
+}
+
+impl BitXorAssign<&BitVec> for BitVec {
+    fn bitxor_assign(&mut self, rhs: &BitVec) {
+        self.zip_words(rhs, |a, b| a ^ b);
+    }
+}
+
+impl BitAnd for &BitVec {
+    type Output = BitVec;
+
+    fn bitand(self, rhs: &BitVec) -> BitVec {
+        let mut out = self.clone();
+        out &= rhs;
+        out
+    }
+}
+
+impl BitOr for &BitVec {
+    type Output = BitVec;
+
+    fn bitor(self, rhs: &BitVec) -> BitVec {
+        let mut out = self.clone();
+        out |= rhs;
+        out
+    }
+}
+
+impl BitXor for &BitVec {
+    type Output = BitVec;
+
+    fn bitxor(self, rhs: &BitVec) -> BitVec {
+        let mut out = self.clone();
+        out ^= rhs;
+        out
+    }
+}
+
+impl FromIterator<bool> for BitVec {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        let mut vec = BitVec::new();
+        for bit in iter {
+            vec.push(bit);
+        }
+        vec
+    }
+}
+
+impl Debug for BitVec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "BitVec[")?;
+        for bit in self.iter() {
+            write!(f, "{}", bit as u8)?;
+        }
+        write!(f, "]")
+    }
+}
+
+impl HeapSize for BitVec {
+    fn heap_size(&self) -> usize {
+        self.words.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::CollectionError;
+
+    use super::BitVec;
+
+    fn bits(s: &str) -> BitVec {
+        s.chars().map(|c| c == '1').collect()
+    }
+
+    #[test]
+    fn test_push_pop() {
+        let mut vec = BitVec::new();
+        assert!(vec.is_empty());
+        assert_eq!(vec.pop(), None);
+        for i in 0..200 {
+            vec.push(i % 3 == 0);
+        }
+        assert_eq!(vec.len(), 200);
+        assert_eq!(vec.words().len(), 4);
+        for i in (0..200).rev() {
+            assert_eq!(vec.pop(), Some(i % 3 == 0));
+        }
+        assert!(vec.is_empty());
+        assert!(vec.words().is_empty());
+    }
+
+    #[test]
+    fn test_get_set() {
+        let mut vec = BitVec::from_elem(130, false);
+        vec.set(0, true);
+        vec.set(64, true);
+        vec.set(129, true);
+        assert_eq!(vec.get(0), Some(true));
+        assert_eq!(vec.get(1), Some(false));
+        assert_eq!(vec.get(64), Some(true));
+        assert_eq!(vec.get(129), Some(true));
+        assert_eq!(vec.get(130), None);
+        vec.set(64, false);
+        assert_eq!(vec.get(64), Some(false));
+        assert_eq!(vec.count_ones(), 2);
+        assert_eq!(vec.count_zeros(), 128);
+        assert_eq!(vec.try_set(130, true), Err(CollectionError::IndexOutOfBounds { index: 130, len: 130 }));
+        assert_eq!(vec.try_set(1, true), Ok(()));
+        assert_eq!(vec.count_ones(), 3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_set_out_of_bounds() {
+        BitVec::from_elem(10, true).set(10, false);
+    }
+
+    #[test]
+    fn test_from_elem_clears_tail() {
+        let vec = BitVec::from_elem(70, true);
+        assert_eq!(vec.count_ones(), 70);
+        assert_eq!(vec, (0..70).map(|_| true).collect());
+    }
+
+    #[test]
+    fn test_bitwise() {
+        let a = bits("1100101");
+        let b = bits("1010011");
+        assert_eq!(&a & &b, bits("1000001"));
+        assert_eq!(&a | &b, bits("1110111"));
+        assert_eq!(&a ^ &b, bits("0110110"));
+
+        let mut c = a.clone();
+        c ^= &a;
+        assert_eq!(c.count_ones(), 0);
+        assert_eq!(format!("{:?}", a), "BitVec[1100101]");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_bitwise_length_mismatch() {
+        let _ = &bits("101") & &bits("10");
+    }
+
+    #[test]
+    fn test_ones() {
+        let mut vec = BitVec::from_elem(300, false);
+        let set = [0, 5, 63, 64, 200, 299];
+        for i in set {
+            vec.set(i, true);
+        }
+        assert_eq!(vec.ones().collect::<Vec<_>>(), set);
+        assert_eq!(BitVec::new().ones().next(), None);
+        assert_eq!(vec.iter().filter(|&b| b).count(), set.len());
+    }
+}
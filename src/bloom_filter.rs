@@ -0,0 +1,163 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::marker::PhantomData;
+
+use crate::bit_vec::BitVec;
+use crate::heap_size::HeapSize;
+
+/// A probabilistic set: `contains` never gives a false negative, and gives a
+/// false positive with a probability that depends on how full the filter is.
+///
+/// Each item sets `hashes` bits chosen by double hashing a single 64-bit
+/// hash. Hashing is deterministic, so filters built with the same parameters
+/// agree on every item's bits and can be unioned.
+pub struct BloomFilter<T: ?Sized> {
+    bits: BitVec,
+    hashes: u32,
+    _marker: PhantomData<fn(&T)>,
+}
+
+impl<T: Hash + ?Sized> BloomFilter<T> {
+    /// A filter sized to give roughly `false_positive_rate` after
+    /// `expected_items` insertions.
+    ///
+    /// Panics unless `0 < false_positive_rate < 1`.
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        assert!(
+            false_positive_rate > 0.0 && false_positive_rate < 1.0,
+            "false positive rate must be between 0 and 1"
+        );
+        let n = expected_items.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-n * false_positive_rate.ln() / (ln2 * ln2)).ceil();
+        let hashes = (bits / n * ln2).round().max(1.0);
+        Self::with_params(bits as usize, hashes as u32)
+    }
+
+    /// A filter with exactly `bits` bits and `hashes` hash functions.
+    ///
+    /// Panics if either is zero.
+    pub fn with_params(bits: usize, hashes: u32) -> Self {
+        assert!(bits > 0 && hashes > 0, "bloom filter needs at least one bit and one hash");
+        BloomFilter { bits: BitVec::from_elem(bits, false), hashes, _marker: PhantomData }
+    }
+
+    pub fn num_bits(&self) -> usize {
+        self.bits.len()
+    }
+
+    pub fn num_hashes(&self) -> u32 {
+        self.hashes
+    }
+
+    fn indices(&self, item: &T) -> impl Iterator<Item = usize> + use<T> {
+        let mut hasher = DefaultHasher::new();
+        item.hash(&mut hasher);
+        let hash = hasher.finish();
+        let (h1, h2) = (hash & 0xffff_ffff, hash >> 32 | 1);
+        let m = self.bits.len() as u64;
+        (0..self.hashes as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % m) as usize)
+    }
+
+    pub fn insert(&mut self, item: &T) {
+        for i in self.indices(item) {
+            self.bits.set(i, true);
+        }
+    }
+
+    /// Whether `item` may have been inserted. `false` is always correct.
+    pub fn contains(&self, item: &T) -> bool {
+        self.indices(item).all(|i| self.bits.get(i).unwrap())
+    }
+
+    pub fn clear(&mut self) {
+        self.bits = BitVec::from_elem(self.bits.len(), false);
+    }
+
+    /// Add everything in `other`, which must have the same parameters.
+    ///
+    /// Panics if the number of bits or hashes differs.
+    pub fn union(&mut self, other: &BloomFilter<T>) {
+        assert_eq!(self.hashes, other.hashes, "bloom filters use different numbers of hashes");
+        assert_eq!(self.bits.len(), other.bits.len(), "bloom filters have different sizes");
+        self.bits |= &other.bits;
+    }
+
+    /// The chance that `contains` returns true for an item never inserted,
+    /// estimated from the fraction of bits set.
+    pub fn false_positive_rate(&self) -> f64 {
+        let fill = self.bits.count_ones() as f64 / self.bits.len() as f64;
+        fill.powi(self.hashes as i32)
+    }
+}
+
+impl<T: ?Sized> HeapSize for BloomFilter<T> {
+    fn heap_size(&self) -> usize {
+        self.bits.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heap_size::HeapSize;
+
+    use super::BloomFilter;
+
+    #[test]
+    fn test_no_false_negatives() {
+        let mut filter = BloomFilter::new(1000, 0.01);
+        for i in 0..1000 {
+            filter.insert(&i);
+        }
+        assert!((0..1000).all(|i| filter.contains(&i)));
+    }
+
+    #[test]
+    fn test_false_positive_rate() {
+        let mut filter = BloomFilter::new(10_000, 0.01);
+        assert_eq!(filter.num_hashes(), 7);
+        assert!(filter.num_bits() > 90_000 && filter.num_bits() < 100_000);
+        for i in 0..10_000u32 {
+            filter.insert(&i);
+        }
+        let false_positives = (10_000..110_000u32).filter(|i| filter.contains(i)).count();
+        assert!(false_positives < 2_000, "{false_positives} false positives");
+        assert!(filter.false_positive_rate() < 0.02);
+    }
+
+    #[test]
+    fn test_unsized_items() {
+        let mut filter: BloomFilter<str> = BloomFilter::new(10, 0.001);
+        filter.insert("apple");
+        filter.insert("banana");
+        assert!(filter.contains("apple"));
+        assert!(!filter.contains("cherry"));
+        filter.clear();
+        assert!(!filter.contains("apple"));
+        assert_eq!(filter.false_positive_rate(), 0.0);
+    }
+
+    #[test]
+    fn test_union() {
+        let mut a = BloomFilter::with_params(1024, 3);
+        let mut b = BloomFilter::with_params(1024, 3);
+        a.insert(&"left");
+        b.insert(&"right");
+        assert!(!a.contains(&"right"));
+        a.union(&b);
+        assert!(a.contains(&"left"));
+        assert!(a.contains(&"right"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_union_mismatched() {
+        let mut a: BloomFilter<i32> = BloomFilter::with_params(1024, 3);
+        a.union(&BloomFilter::with_params(1024, 4));
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let filter = BloomFilter::<str>::with_params(1000, 3);
+        assert_eq!(filter.heap_size(), 1000usize.div_ceil(64) * 8);
+    }
+}
@@ -0,0 +1,498 @@
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds, RangeFull};
+
+use crate::doubly_list::{LinkedList, NodeRef};
+use crate::heap_size::HeapSize;
+
+struct Leaf<K, V> {
+    keys: Vec<K>,
+    vals: Vec<V>,
+}
+
+struct Internal<K, V> {
+    // Child `i + 1` holds the keys greater than or equal to `keys[i]`.
+    keys: Vec<K>,
+    children: Vec<Child<K, V>>,
+}
+
+enum Child<K, V> {
+    Internal(Box<Internal<K, V>>),
+    Leaf(NodeRef<Leaf<K, V>>),
+}
+
+/// The separator key and new right sibling produced by splitting a node.
+type Split<K, V> = (K, Child<K, V>);
+
+/// Borrow a leaf for as long as the tree that owns it.
+fn leaf<K, V>(node: &NodeRef<Leaf<K, V>>) -> &Leaf<K, V> {
+    // This is safe because leaves are only ever mutated through a mutable
+    // reference to the tree, which cannot coexist with this borrow.
+    unsafe { &(*node.as_ptr()).data }
+}
+
+impl<K, V> Child<K, V> {
+    fn len(&self) -> usize {
+        match self {
+            Child::Internal(node) => node.keys.len(),
+            Child::Leaf(node) => node.borrow().data.keys.len(),
+        }
+    }
+}
+
+impl<K: Ord + Clone, V> Child<K, V> {
+    fn insert(&mut self, leaves: &mut LinkedList<Leaf<K, V>>, key: K, val: V, max: usize) -> (Option<V>, Option<Split<K, V>>) {
+        match self {
+            Child::Leaf(node) => {
+                let mut n = node.borrow_mut();
+                let leaf = &mut n.data;
+                match leaf.keys.binary_search(&key) {
+                    Ok(i) => return (Some(std::mem::replace(&mut leaf.vals[i], val)), None),
+                    Err(i) => {
+                        leaf.keys.insert(i, key);
+                        leaf.vals.insert(i, val);
+                    }
+                }
+                if leaf.keys.len() <= max {
+                    return (None, None);
+                }
+                let mid = leaf.keys.len() / 2;
+                let right = Leaf { keys: leaf.keys.split_off(mid), vals: leaf.vals.split_off(mid) };
+                let sep = right.keys[0].clone();
+                drop(n);
+                let right = leaves.insert_after_node(node, right);
+                (None, Some((sep, Child::Leaf(right))))
+            }
+            Child::Internal(node) => {
+                let i = node.keys.partition_point(|k| *k <= key);
+                let (old, split) = node.children[i].insert(leaves, key, val, max);
+                if let Some((sep, right)) = split {
+                    node.keys.insert(i, sep);
+                    node.children.insert(i + 1, right);
+                }
+                if node.keys.len() <= max {
+                    return (old, None);
+                }
+                let mid = node.keys.len() / 2;
+                let keys = node.keys.split_off(mid + 1);
+                let children = node.children.split_off(mid + 1);
+                let sep = node.keys.pop().unwrap();
+                (old, Some((sep, Child::Internal(Box::new(Internal { keys, children })))))
+            }
+        }
+    }
+
+    fn remove(&mut self, leaves: &mut LinkedList<Leaf<K, V>>, key: &K, min: usize) -> Option<V> {
+        match self {
+            Child::Leaf(node) => {
+                let leaf = &mut node.borrow_mut().data;
+                let i = leaf.keys.binary_search(key).ok()?;
+                leaf.keys.remove(i);
+                Some(leaf.vals.remove(i))
+            }
+            Child::Internal(node) => {
+                let i = node.keys.partition_point(|k| k <= key);
+                let removed = node.children[i].remove(leaves, key, min)?;
+                node.fix_child(leaves, i, min);
+                Some(removed)
+            }
+        }
+    }
+}
+
+impl<K: Ord, V> Child<K, V> {
+    // Check occupancy, key order within `lower..upper`, and that leaves are
+    // all at one depth, pushing each leaf in key order onto `leaves`
+    #[cfg(any(test, debug_assertions))]
+    fn validate<'a>(&'a self, depth: usize, (min, max): (usize, usize), lower: Option<&K>, upper: Option<&K>, leaves: &mut Vec<(usize, &'a Leaf<K, V>)>) {
+        let is_root = depth == 0;
+        assert!(self.len() <= max && (is_root || self.len() >= min), "node holds {} keys", self.len());
+        let keys = match self {
+            Child::Leaf(node) => &leaf(node).keys,
+            Child::Internal(node) => &node.keys,
+        };
+        assert!(keys.is_sorted_by(|a, b| a < b), "keys out of order");
+        if let (Some(lower), Some(first)) = (lower, keys.first()) {
+            assert!(lower <= first, "key below its separator");
+        }
+        if let (Some(upper), Some(last)) = (upper, keys.last()) {
+            assert!(last < upper, "key not below its separator");
+        }
+        match self {
+            Child::Leaf(node) => {
+                assert_eq!(leaf(node).vals.len(), keys.len(), "keys and values out of step");
+                leaves.push((depth, leaf(node)));
+            }
+            Child::Internal(node) => {
+                assert!(!keys.is_empty() && node.children.len() == keys.len() + 1, "wrong number of children");
+                for (i, child) in node.children.iter().enumerate() {
+                    let lower = if i == 0 { lower } else { Some(&keys[i - 1]) };
+                    child.validate(depth + 1, (min, max), lower, keys.get(i).or(upper), leaves);
+                }
+            }
+        }
+    }
+}
+
+impl<K: Ord + Clone, V> Internal<K, V> {
+    /// Restore the minimum occupancy of child `i` by borrowing from a sibling
+    /// or merging with one.
+    fn fix_child(&mut self, leaves: &mut LinkedList<Leaf<K, V>>, i: usize, min: usize) {
+        if self.children[i].len() >= min {
+            return;
+        }
+        let borrow_left = i > 0 && self.children[i - 1].len() > min;
+        let borrow_right = !borrow_left && i + 1 < self.children.len() && self.children[i + 1].len() > min;
+        if !borrow_left && !borrow_right {
+            let j = if i > 0 { i - 1 } else { i };
+            self.merge_children(leaves, j);
+            return;
+        }
+        let (l, r) = if borrow_left { (i - 1, i) } else { (i, i + 1) };
+        let (left, right) = self.children.split_at_mut(r);
+        match (&mut left[l], &mut right[0]) {
+            (Child::Leaf(left), Child::Leaf(right)) => {
+                let left = &mut left.borrow_mut().data;
+                let right = &mut right.borrow_mut().data;
+                if borrow_left {
+                    right.keys.insert(0, left.keys.pop().unwrap());
+                    right.vals.insert(0, left.vals.pop().unwrap());
+                } else {
+                    left.keys.push(right.keys.remove(0));
+                    left.vals.push(right.vals.remove(0));
+                }
+                self.keys[l] = right.keys[0].clone();
+            }
+            (Child::Internal(left), Child::Internal(right)) => {
+                if borrow_left {
+                    let key = std::mem::replace(&mut self.keys[l], left.keys.pop().unwrap());
+                    right.keys.insert(0, key);
+                    right.children.insert(0, left.children.pop().unwrap());
+                } else {
+                    let key = std::mem::replace(&mut self.keys[l], right.keys.remove(0));
+                    left.keys.push(key);
+                    left.children.push(right.children.remove(0));
+                }
+            }
+            _ => unreachable!("siblings are always at the same level"),
+        }
+    }
+
+    /// Merge child `i + 1` into child `i`, dropping the separator between them.
+    fn merge_children(&mut self, leaves: &mut LinkedList<Leaf<K, V>>, i: usize) {
+        let sep = self.keys.remove(i);
+        let right = self.children.remove(i + 1);
+        match (&mut self.children[i], right) {
+            (Child::Leaf(left), Child::Leaf(right)) => {
+                let right = leaves.remove_node(right).unwrap();
+                let left = &mut left.borrow_mut().data;
+                left.keys.extend(right.keys);
+                left.vals.extend(right.vals);
+            }
+            (Child::Internal(left), Child::Internal(right)) => {
+                left.keys.push(sep);
+                left.keys.extend(right.keys);
+                left.children.extend(right.children);
+            }
+            _ => unreachable!("siblings are always at the same level"),
+        }
+    }
+}
+
+/// An ordered map implemented as a B+ tree: entries live only in the leaves,
+/// which are chained together in a `LinkedList` so full scans and range
+/// queries walk the leaves sequentially instead of re-descending the tree.
+///
+/// `B` is the branching factor, which also bounds the entries per leaf.
+pub struct BPlusTree<K, V, const B: usize> {
+    root: Child<K, V>,
+    leaves: LinkedList<Leaf<K, V>>,
+    len: usize,
+}
+
+impl<K: Ord + Clone, V, const B: usize> BPlusTree<K, V, B> {
+    const MAX_KEYS: usize = B - 1;
+    const MIN_KEYS: usize = (B - 1) / 2;
+
+    pub fn new() -> Self {
+        const { assert!(B >= 3, "B+ tree branching factor must be at least 3") };
+        let mut leaves = LinkedList::new();
+        let root = Child::Leaf(leaves.push_back_node(Leaf { keys: Vec::new(), vals: Vec::new() }));
+        BPlusTree { root, leaves, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Panic unless the tree is balanced with every node but the root at
+    /// least half full, keys are in order, the leaf chain links the leaves
+    /// in key order, and `len` is right. Only in debug builds and tests.
+    #[cfg(any(test, debug_assertions))]
+    pub fn validate(&self) {
+        let mut leaves = Vec::new();
+        self.root.validate(0, (Self::MIN_KEYS, Self::MAX_KEYS), None, None, &mut leaves);
+        assert!(leaves.iter().all(|(depth, _)| *depth == leaves[0].0), "leaves at different depths");
+        self.leaves.validate();
+        assert!(leaves.iter().map(|(_, leaf)| *leaf as *const Leaf<K, V>).eq(self.leaves.iter().map(|leaf| leaf as *const _)), "leaf chain out of order");
+        assert_eq!(leaves.iter().map(|(_, leaf)| leaf.keys.len()).sum::<usize>(), self.len, "map length is out of date");
+    }
+
+    /// Insert a key-value pair, returning the previous value if the key was
+    /// already present.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let (old, split) = self.root.insert(&mut self.leaves, key, value, Self::MAX_KEYS);
+        if let Some((sep, right)) = split {
+            let left = std::mem::replace(&mut self.root, Child::Internal(Box::new(Internal { keys: Vec::new(), children: Vec::new() })));
+            self.root = Child::Internal(Box::new(Internal { keys: vec![sep], children: vec![left, right] }));
+        }
+        if old.is_none() {
+            self.len += 1;
+        }
+        old
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let removed = self.root.remove(&mut self.leaves, key, Self::MIN_KEYS)?;
+        if let Child::Internal(node) = &mut self.root
+            && node.keys.is_empty()
+        {
+            self.root = node.children.pop().unwrap();
+        }
+        self.len -= 1;
+        Some(removed)
+    }
+
+    fn find_leaf(&self, key: &K) -> &NodeRef<Leaf<K, V>> {
+        let mut child = &self.root;
+        loop {
+            match child {
+                Child::Internal(node) => child = &node.children[node.keys.partition_point(|k| k <= key)],
+                Child::Leaf(node) => return node,
+            }
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let leaf = leaf(self.find_leaf(key));
+        let i = leaf.keys.binary_search(key).ok()?;
+        Some(&leaf.vals[i])
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        self.range(..)
+    }
+
+    /// Iterate in key order over the entries whose keys fall within `range`.
+    /// After locating the first leaf, the scan simply follows the leaf chain.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> Range<'_, K, V, R> {
+        let (node, index) = match range.start_bound() {
+            Bound::Included(start) => {
+                let node = self.find_leaf(start);
+                (node.clone(), leaf(node).keys.partition_point(|k| k < start))
+            }
+            Bound::Excluded(start) => {
+                let node = self.find_leaf(start);
+                (node.clone(), leaf(node).keys.partition_point(|k| k <= start))
+            }
+            Bound::Unbounded => (self.leaves.front_node().unwrap(), 0),
+        };
+        Range { node: Some(node), index, range, _tree: PhantomData }
+    }
+}
+
+impl<K: Ord + Clone, V, const B: usize> Default for BPlusTree<K, V, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// In-order iterator over the entries of a B+ tree within a key range.
+pub struct Range<'a, K, V, R> {
+    node: Option<NodeRef<Leaf<K, V>>>,
+    index: usize,
+    range: R,
+    _tree: PhantomData<&'a Leaf<K, V>>,
+}
+
+pub type Iter<'a, K, V> = Range<'a, K, V, RangeFull>;
+
+impl<'a, K: Ord, V, R: RangeBounds<K>> Iterator for Range<'a, K, V, R> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let node = self.node.as_ref()?;
+            // The leaf is owned by the tree, which outlives 'a.
+            let current: &'a Leaf<K, V> = unsafe { &(*node.as_ptr()).data };
+            if self.index >= current.keys.len() {
+                let next = node.borrow().next();
+                self.node = next;
+                self.index = 0;
+                continue;
+            }
+            let key = &current.keys[self.index];
+            let in_range = match self.range.end_bound() {
+                Bound::Included(end) => key <= end,
+                Bound::Excluded(end) => key < end,
+                Bound::Unbounded => true,
+            };
+            if !in_range {
+                self.node = None;
+                return None;
+            }
+            let val = &current.vals[self.index];
+            self.index += 1;
+            return Some((key, val));
+        }
+    }
+}
+
+impl<'a, K: Ord + Clone, V, const B: usize> IntoIterator for &'a BPlusTree<K, V, B> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K: Ord + Clone + Debug, V: Debug, const B: usize> Debug for BPlusTree<K, V, B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: HeapSize, V: HeapSize> HeapSize for Leaf<K, V> {
+    fn heap_size(&self) -> usize {
+        self.keys.heap_size() + self.vals.heap_size()
+    }
+}
+
+impl<K: HeapSize, V: HeapSize> HeapSize for Internal<K, V> {
+    fn heap_size(&self) -> usize {
+        self.keys.heap_size() + self.children.heap_size()
+    }
+}
+
+impl<K: HeapSize, V: HeapSize> HeapSize for Child<K, V> {
+    fn heap_size(&self) -> usize {
+        match self {
+            Child::Internal(node) => node.heap_size(),
+            // Counted as a node of the leaf list
+            Child::Leaf(_) => 0,
+        }
+    }
+}
+
+impl<K: HeapSize, V: HeapSize, const B: usize> HeapSize for BPlusTree<K, V, B> {
+    fn heap_size(&self) -> usize {
+        self.root.heap_size() + self.leaves.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heap_size::HeapSize;
+
+    use super::BPlusTree;
+
+    fn keys<'a>(iter: impl Iterator<Item = (&'a i32, &'a ())>) -> Vec<i32> {
+        iter.map(|(k, _)| *k).collect()
+    }
+
+    #[test]
+    fn test_new() {
+        let tree: BPlusTree<i32, i32, 4> = BPlusTree::new();
+        assert_eq!(tree.len(), 0);
+        assert!(tree.is_empty());
+        assert!(tree.iter().next().is_none());
+        assert!(tree.get(&1).is_none());
+    }
+
+    #[test]
+    fn test_insert_get() {
+        let mut tree: BPlusTree<i32, i32, 3> = BPlusTree::new();
+        for i in 0..100 {
+            assert_eq!(tree.insert((i * 7) % 100, i), None);
+        }
+        assert_eq!(tree.len(), 100);
+        for i in 0..100 {
+            assert_eq!(tree.get(&((i * 7) % 100)), Some(&i));
+        }
+        assert_eq!(tree.insert(0, -1), Some(0));
+        assert_eq!(tree.get(&0), Some(&-1));
+        assert_eq!(tree.len(), 100);
+    }
+
+    #[test]
+    fn test_iter_follows_leaves() {
+        let mut tree: BPlusTree<i32, (), 4> = BPlusTree::new();
+        for i in (0..500).rev() {
+            tree.insert(i, ());
+        }
+        assert_eq!(keys(tree.iter()), (0..500).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_range() {
+        let mut tree: BPlusTree<i32, (), 3> = BPlusTree::new();
+        for i in 0..50 {
+            tree.insert(i * 2, ());
+        }
+        assert_eq!(keys(tree.range(10..20)), vec![10, 12, 14, 16, 18]);
+        assert_eq!(keys(tree.range(9..=20)), vec![10, 12, 14, 16, 18, 20]);
+        assert_eq!(keys(tree.range(95..)), vec![96, 98]);
+        assert_eq!(keys(tree.range(..3)), vec![0, 2]);
+        assert!(tree.range(200..).next().is_none());
+        assert!(tree.range(11..12).next().is_none());
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut tree: BPlusTree<i32, (), 4> = BPlusTree::new();
+        for i in 0..300 {
+            tree.insert(i, ());
+        }
+        for i in (0..300).step_by(3) {
+            assert_eq!(tree.remove(&i), Some(()));
+            tree.validate();
+        }
+        assert_eq!(tree.remove(&0), None);
+        assert_eq!(tree.len(), 200);
+        assert_eq!(keys(tree.iter()), (0..300).filter(|i| i % 3 != 0).collect::<Vec<_>>());
+
+        for i in 0..300 {
+            tree.remove(&i);
+        }
+        tree.validate();
+        assert!(tree.is_empty());
+        assert!(tree.iter().next().is_none());
+
+        tree.insert(7, ());
+        assert_eq!(keys(tree.iter()), vec![7]);
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let mut tree = BPlusTree::<u64, String, 4>::new();
+        let empty = tree.heap_size();
+        for i in 0..100 {
+            tree.insert(i, i.to_string());
+        }
+        let full = tree.heap_size();
+        assert!(full >= empty + 100 * size_of::<(u64, String)>() + 190);
+        for i in 0..100 {
+            tree.remove(&i);
+        }
+        assert!(tree.heap_size() < full);
+    }
+}
@@ -0,0 +1,556 @@
+use std::fmt::Debug;
+use std::ops::{Bound, RangeBounds, RangeFull};
+
+use crate::dot::{DotWriter, ToDot};
+use crate::heap_size::HeapSize;
+use crate::pretty::{PrettyPrint, outline};
+use crate::trace;
+
+struct Node<K, V> {
+    keys: Vec<K>,
+    vals: Vec<V>,
+    // Empty for leaves, otherwise always `keys.len() + 1` entries.
+    children: Vec<Node<K, V>>,
+}
+
+/// The median entry and new right sibling produced by splitting a node.
+type Split<K, V> = (K, V, Node<K, V>);
+
+impl<K: Ord, V> Node<K, V> {
+    const fn new() -> Self {
+        Node { keys: Vec::new(), vals: Vec::new(), children: Vec::new() }
+    }
+
+    fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// Split an overfull node around its median, returning the median entry
+    /// and the new right sibling.
+    fn split(&mut self) -> Split<K, V> {
+        let mid = self.keys.len() / 2;
+        let right = Node {
+            keys: self.keys.split_off(mid + 1),
+            vals: self.vals.split_off(mid + 1),
+            children: if self.is_leaf() { Vec::new() } else { self.children.split_off(mid + 1) },
+        };
+        let key = self.keys.pop().unwrap();
+        let val = self.vals.pop().unwrap();
+        (key, val, right)
+    }
+
+    /// Merge child `i + 1` and the separator at `i` into child `i`.
+    fn merge_children(&mut self, i: usize) {
+        let right = self.children.remove(i + 1);
+        let key = self.keys.remove(i);
+        let val = self.vals.remove(i);
+        let left = &mut self.children[i];
+        left.keys.push(key);
+        left.vals.push(val);
+        left.keys.extend(right.keys);
+        left.vals.extend(right.vals);
+        left.children.extend(right.children);
+    }
+
+    /// Restore the minimum occupancy of child `i` by borrowing from a sibling
+    /// or merging with one.
+    fn fix_child(&mut self, i: usize, min_keys: usize) {
+        if self.children[i].keys.len() >= min_keys {
+            return;
+        }
+        if i > 0 && self.children[i - 1].keys.len() > min_keys {
+            // Rotate right through the separator
+            let left = &mut self.children[i - 1];
+            let key = left.keys.pop().unwrap();
+            let val = left.vals.pop().unwrap();
+            let child = left.children.pop();
+            let key = std::mem::replace(&mut self.keys[i - 1], key);
+            let val = std::mem::replace(&mut self.vals[i - 1], val);
+            let node = &mut self.children[i];
+            node.keys.insert(0, key);
+            node.vals.insert(0, val);
+            if let Some(child) = child {
+                node.children.insert(0, child);
+            }
+        } else if i + 1 < self.children.len() && self.children[i + 1].keys.len() > min_keys {
+            // Rotate left through the separator
+            let right = &mut self.children[i + 1];
+            let key = right.keys.remove(0);
+            let val = right.vals.remove(0);
+            let child = if right.is_leaf() { None } else { Some(right.children.remove(0)) };
+            let key = std::mem::replace(&mut self.keys[i], key);
+            let val = std::mem::replace(&mut self.vals[i], val);
+            let node = &mut self.children[i];
+            node.keys.push(key);
+            node.vals.push(val);
+            if let Some(child) = child {
+                node.children.push(child);
+            }
+        } else if i > 0 {
+            self.merge_children(i - 1);
+        } else {
+            self.merge_children(i);
+        }
+    }
+
+    // Check occupancy, that keys are sorted and within the separators above,
+    // and that every leaf is `height` levels down. Returns the number of
+    // entries in the subtree.
+    #[cfg(any(test, debug_assertions))]
+    fn validate(&self, height: usize, is_root: bool, (min_keys, max_keys): (usize, usize), lower: Option<&K>, upper: Option<&K>) -> usize {
+        assert_eq!(self.vals.len(), self.keys.len(), "keys and values out of step");
+        assert!(self.keys.len() <= max_keys && (is_root || self.keys.len() >= min_keys), "node holds {} keys", self.keys.len());
+        assert!(self.keys.is_sorted_by(|a, b| a < b), "keys out of order");
+        if let (Some(lower), Some(first)) = (lower, self.keys.first()) {
+            assert!(lower < first, "key not above its separator");
+        }
+        if let (Some(upper), Some(last)) = (upper, self.keys.last()) {
+            assert!(last < upper, "key not below its separator");
+        }
+        if self.is_leaf() {
+            assert_eq!(height, 0, "leaves at different depths");
+            return self.keys.len();
+        }
+        assert!(height > 0 && !self.keys.is_empty(), "branch without keys");
+        assert_eq!(self.children.len(), self.keys.len() + 1, "wrong number of children");
+        let mut count = self.keys.len();
+        for (i, child) in self.children.iter().enumerate() {
+            let lower = if i == 0 { lower } else { Some(&self.keys[i - 1]) };
+            let upper = self.keys.get(i).or(upper);
+            count += child.validate(height - 1, false, (min_keys, max_keys), lower, upper);
+        }
+        count
+    }
+
+    fn insert(&mut self, key: K, val: V, max_keys: usize) -> (Option<V>, Option<Split<K, V>>) {
+        let i = match self.keys.binary_search(&key) {
+            Ok(i) => return (Some(std::mem::replace(&mut self.vals[i], val)), None),
+            Err(i) => i,
+        };
+        if self.is_leaf() {
+            self.keys.insert(i, key);
+            self.vals.insert(i, val);
+        } else {
+            let (old, split) = self.children[i].insert(key, val, max_keys);
+            if old.is_some() {
+                return (old, None);
+            }
+            if let Some((key, val, right)) = split {
+                self.keys.insert(i, key);
+                self.vals.insert(i, val);
+                self.children.insert(i + 1, right);
+            }
+        }
+        if self.keys.len() > max_keys {
+            (None, Some(self.split()))
+        } else {
+            (None, None)
+        }
+    }
+
+    fn remove(&mut self, key: &K, min_keys: usize) -> Option<(K, V)> {
+        match self.keys.binary_search(key) {
+            Ok(i) if self.is_leaf() => Some((self.keys.remove(i), self.vals.remove(i))),
+            Ok(i) => {
+                // Replace with the in-order predecessor, then rebalance
+                let (pk, pv) = self.children[i].remove_max(min_keys);
+                let key = std::mem::replace(&mut self.keys[i], pk);
+                let val = std::mem::replace(&mut self.vals[i], pv);
+                self.fix_child(i, min_keys);
+                Some((key, val))
+            }
+            Err(_) if self.is_leaf() => None,
+            Err(i) => {
+                let removed = self.children[i].remove(key, min_keys);
+                if removed.is_some() {
+                    self.fix_child(i, min_keys);
+                }
+                removed
+            }
+        }
+    }
+
+    fn remove_max(&mut self, min_keys: usize) -> (K, V) {
+        if self.is_leaf() {
+            return (self.keys.pop().unwrap(), self.vals.pop().unwrap());
+        }
+        let last = self.children.len() - 1;
+        let max = self.children[last].remove_max(min_keys);
+        self.fix_child(last, min_keys);
+        max
+    }
+}
+
+/// An ordered map implemented as a B-tree with `B` as the branching factor:
+/// every node has at most `B` children (`B - 1` keys), and every node except
+/// the root keeps at least `(B - 1) / 2` keys.
+///
+/// Varying `B` makes it easy to compare how fanout affects cache behavior.
+pub struct BTreeMap<K, V, const B: usize> {
+    root: Node<K, V>,
+    len: usize,
+}
+
+impl<K: Ord, V, const B: usize> BTreeMap<K, V, B> {
+    const MAX_KEYS: usize = B - 1;
+    const MIN_KEYS: usize = (B - 1) / 2;
+
+    pub const fn new() -> Self {
+        const { assert!(B >= 3, "B-tree branching factor must be at least 3") };
+        BTreeMap { root: Node::new(), len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Panic unless every node but the root is at least half full, keys are
+    /// in order, all leaves are at the same depth, and `len` is right. Only
+    /// in debug builds and tests.
+    #[cfg(any(test, debug_assertions))]
+    pub fn validate(&self) {
+        let count = self.root.validate(self.height() - 1, true, (Self::MIN_KEYS, Self::MAX_KEYS), None, None);
+        assert_eq!(count, self.len, "map length is out of date");
+    }
+
+    pub fn clear(&mut self) {
+        self.root = Node::new();
+        self.len = 0;
+    }
+
+    /// Number of levels in the tree, counting the root. An empty map has
+    /// height 1.
+    pub fn height(&self) -> usize {
+        let mut height = 1;
+        let mut node = &self.root;
+        while let Some(child) = node.children.first() {
+            height += 1;
+            node = child;
+        }
+        height
+    }
+
+    /// Insert a key-value pair, returning the previous value if the key was
+    /// already present.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let (old, split) = self.root.insert(key, value, Self::MAX_KEYS);
+        if old.is_none() {
+            self.len += 1;
+        }
+        if let Some((key, val, right)) = split {
+            let left = std::mem::replace(&mut self.root, Node::new());
+            self.root.keys.push(key);
+            self.root.vals.push(val);
+            self.root.children.push(left);
+            self.root.children.push(right);
+            trace::event("BTreeMap", "split_root", self.len);
+        }
+        old
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let (_, val) = self.root.remove(key, Self::MIN_KEYS)?;
+        self.len -= 1;
+        if self.root.keys.is_empty() && !self.root.is_leaf() {
+            self.root = self.root.children.pop().unwrap();
+            trace::event("BTreeMap", "collapse_root", self.len);
+        }
+        Some(val)
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let mut node = &self.root;
+        loop {
+            match node.keys.binary_search(key) {
+                Ok(i) => return Some(&node.vals[i]),
+                Err(_) if node.is_leaf() => return None,
+                Err(i) => node = &node.children[i],
+            }
+        }
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let mut node = &mut self.root;
+        loop {
+            match node.keys.binary_search(key) {
+                Ok(i) => return Some(&mut node.vals[i]),
+                Err(_) if node.is_leaf() => return None,
+                Err(i) => node = &mut node.children[i],
+            }
+        }
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        let mut node = &self.root;
+        while let Some(child) = node.children.first() {
+            node = child;
+        }
+        Some((node.keys.first()?, node.vals.first()?))
+    }
+
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        let mut node = &self.root;
+        while let Some(child) = node.children.last() {
+            node = child;
+        }
+        Some((node.keys.last()?, node.vals.last()?))
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        self.range(..)
+    }
+
+    /// Iterate in key order over the entries whose keys fall within `range`.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> Range<'_, K, V, R> {
+        let mut stack = Vec::new();
+        let mut node = &self.root;
+        loop {
+            let pos = match range.start_bound() {
+                Bound::Included(start) => node.keys.partition_point(|k| k < start),
+                Bound::Excluded(start) => node.keys.partition_point(|k| k <= start),
+                Bound::Unbounded => 0,
+            };
+            stack.push((node, pos));
+            if node.is_leaf() {
+                break;
+            }
+            node = &node.children[pos];
+        }
+        Range { stack, range }
+    }
+}
+
+impl<K: Ord, V, const B: usize> Default for BTreeMap<K, V, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// In-order iterator over the entries of a B-tree within a key range.
+pub struct Range<'a, K, V, R> {
+    // Each entry is a node and the index of the next key to yield from it.
+    stack: Vec<(&'a Node<K, V>, usize)>,
+    range: R,
+}
+
+pub type Iter<'a, K, V> = Range<'a, K, V, RangeFull>;
+
+impl<'a, K: Ord, V, R: RangeBounds<K>> Iterator for Range<'a, K, V, R> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (node, i) = self.stack.last_mut()?;
+            let node = *node;
+            if *i >= node.keys.len() {
+                self.stack.pop();
+                continue;
+            }
+            let idx = *i;
+            *i += 1;
+            let key = &node.keys[idx];
+            let in_range = match self.range.end_bound() {
+                Bound::Included(end) => key <= end,
+                Bound::Excluded(end) => key < end,
+                Bound::Unbounded => true,
+            };
+            if !in_range {
+                self.stack.clear();
+                return None;
+            }
+            if !node.is_leaf() {
+                let mut child = &node.children[idx + 1];
+                loop {
+                    self.stack.push((child, 0));
+                    let Some(first) = child.children.first() else {
+                        break;
+                    };
+                    child = first;
+                }
+            }
+            return Some((key, &node.vals[idx]));
+        }
+    }
+}
+
+impl<'a, K: Ord, V, const B: usize> IntoIterator for &'a BTreeMap<K, V, B> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K: Ord + Debug, V: Debug, const B: usize> Debug for BTreeMap<K, V, B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: HeapSize, V: HeapSize> HeapSize for Node<K, V> {
+    fn heap_size(&self) -> usize {
+        self.keys.heap_size() + self.vals.heap_size() + self.children.heap_size()
+    }
+}
+
+impl<K: HeapSize, V: HeapSize, const B: usize> HeapSize for BTreeMap<K, V, B> {
+    fn heap_size(&self) -> usize {
+        self.root.heap_size()
+    }
+}
+
+// Write a subtree and return the id of its root
+fn write_dot<K: Debug, V>(dot: &mut DotWriter, node: &Node<K, V>) -> Result<usize, std::fmt::Error> {
+    let keys: Vec<_> = node.keys.iter().map(|key| format!("{key:?}")).collect();
+    let id = dot.node(&keys.join(" | "))?;
+    for child in &node.children {
+        let child = write_dot(dot, child)?;
+        dot.edge(id, child, "")?;
+    }
+    Ok(id)
+}
+
+/// One box per node listing its keys, so underfull and overfull nodes stand
+/// out.
+impl<K: Debug, V, const B: usize> ToDot for BTreeMap<K, V, B> {
+    fn write_dot(&self, out: &mut dyn std::fmt::Write) -> std::fmt::Result {
+        let mut dot = DotWriter::new(out)?;
+        write_dot(&mut dot, &self.root)?;
+        dot.finish()
+    }
+}
+
+impl<K: Debug, V, const B: usize> PrettyPrint for BTreeMap<K, V, B> {
+    fn pretty_print(&self) -> String {
+        outline(&self.root, |node| &node.children, |node| format!("{:?}", node.keys))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dot::ToDot;
+    use crate::pretty::PrettyPrint;
+
+    use super::BTreeMap;
+
+    #[test]
+    fn test_new() {
+        let map: BTreeMap<i32, i32, 4> = BTreeMap::new();
+        assert_eq!(map.len(), 0);
+        assert!(map.is_empty());
+        assert_eq!(map.height(), 1);
+        assert!(map.iter().next().is_none());
+    }
+
+    #[test]
+    fn test_insert_get() {
+        let mut map: BTreeMap<i32, i32, 3> = BTreeMap::new();
+        for i in 0..100 {
+            assert_eq!(map.insert(i, i * 2), None);
+        }
+        assert_eq!(map.len(), 100);
+        assert!(map.height() > 1);
+        for i in 0..100 {
+            assert_eq!(map.get(&i), Some(&(i * 2)));
+        }
+        assert_eq!(map.get(&100), None);
+
+        assert_eq!(map.insert(50, 0), Some(100));
+        assert_eq!(map.len(), 100);
+        *map.get_mut(&50).unwrap() += 1;
+        assert_eq!(map.get(&50), Some(&1));
+    }
+
+    #[test]
+    fn test_iter_sorted() {
+        let mut map: BTreeMap<i32, (), 5> = BTreeMap::new();
+        for i in (0..200).rev() {
+            map.insert((i * 37) % 200, ());
+        }
+        let keys: Vec<_> = map.iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, (0..200).collect::<Vec<_>>());
+        assert_eq!(map.first_key_value(), Some((&0, &())));
+        assert_eq!(map.last_key_value(), Some((&199, &())));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut map: BTreeMap<i32, i32, 4> = BTreeMap::new();
+        for i in 0..300 {
+            map.insert(i, i);
+        }
+        for i in (0..300).step_by(3) {
+            assert_eq!(map.remove(&i), Some(i));
+            map.validate();
+        }
+        assert_eq!(map.remove(&0), None);
+        assert_eq!(map.len(), 200);
+        let keys: Vec<_> = map.iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, (0..300).filter(|i| i % 3 != 0).collect::<Vec<_>>());
+
+        for i in 0..300 {
+            map.remove(&i);
+        }
+        map.validate();
+        assert!(map.is_empty());
+        assert_eq!(map.height(), 1);
+    }
+
+    #[test]
+    fn test_range() {
+        let mut map: BTreeMap<i32, (), 3> = BTreeMap::new();
+        for i in 0..50 {
+            map.insert(i * 2, ());
+        }
+        let keys = |r: Vec<(&i32, &())>| r.into_iter().map(|(k, _)| *k).collect::<Vec<_>>();
+        assert_eq!(keys(map.range(10..20).collect()), vec![10, 12, 14, 16, 18]);
+        assert_eq!(keys(map.range(9..=20).collect()), vec![10, 12, 14, 16, 18, 20]);
+        assert_eq!(keys(map.range(95..).collect()), vec![96, 98]);
+        assert_eq!(keys(map.range(..3).collect()), vec![0, 2]);
+        assert!(map.range(200..).next().is_none());
+        assert!(map.range(11..12).next().is_none());
+    }
+
+    #[test]
+    fn test_branching_factors_agree() {
+        let mut small: BTreeMap<u32, u32, 3> = BTreeMap::new();
+        let mut large: BTreeMap<u32, u32, 64> = BTreeMap::new();
+        for i in 0..1000u32 {
+            let k = i.wrapping_mul(2654435761) % 1000;
+            small.insert(k, i);
+            large.insert(k, i);
+        }
+        assert!(small.height() > large.height());
+        assert!(small.iter().eq(large.iter()));
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let mut map: BTreeMap<i32, (), 3> = BTreeMap::new();
+        for i in 1..=5 {
+            map.insert(i, ());
+        }
+        let dot = map.to_dot();
+        assert!(dot.contains("n0 [label=\"2 | 4\"];"));
+        assert!(dot.contains("n3 [label=\"5\"];"));
+        assert_eq!(dot.matches("n0 -> ").count(), 3);
+    }
+
+    #[test]
+    fn test_pretty_print() {
+        let mut map: BTreeMap<i32, (), 3> = BTreeMap::new();
+        for i in 1..=5 {
+            map.insert(i, ());
+        }
+        assert_eq!(map.pretty_print(), "[2, 4]\n├── [1]\n├── [3]\n└── [5]\n");
+    }
+}
@@ -0,0 +1,325 @@
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::ptr::NonNull;
+
+use bumpalo::Bump;
+
+use crate::doubly_list::LinkedList;
+use crate::heap_size::{HeapSize, payload};
+use crate::list::List;
+use crate::sequence::Sequence;
+
+type Link<T> = Option<NonNull<Node<T>>>;
+
+struct Node<T> {
+    // Uninitialized once popped and waiting on the spare list
+    val: MaybeUninit<T>,
+    prev: Link<T>,
+    next: Link<T>,
+}
+
+/// A doubly linked list whose nodes are allocated from a `bumpalo` arena.
+///
+/// Allocating a node is a pointer bump, and nothing is ever freed node by
+/// node: the memory goes back all at once when the arena is reset or
+/// dropped, which the `'bump` lifetime guarantees happens only after the
+/// list is gone. Popped nodes are kept on a spare list and reused by later
+/// pushes, so a queue that keeps pushing and popping doesn't grow the arena
+/// without bound.
+///
+/// The arena never runs destructors, so the list drops whatever elements it
+/// still holds itself.
+///
+/// `LinkedList::new_in` and `List::new_in` make one too, for code that
+/// starts from either of those and wants its nodes in an arena. Both lists
+/// keep their nodes behind `Rc` or `Box`, which can't point into an arena on
+/// stable, so what they return is a `BumpList`.
+pub struct BumpList<'bump, T> {
+    bump: &'bump Bump,
+    head: Link<T>,
+    tail: Link<T>,
+    // Popped nodes, linked through `next`
+    spare: Link<T>,
+    len: usize,
+    marker: PhantomData<T>,
+}
+
+impl<'bump, T> BumpList<'bump, T> {
+    /// An empty list that allocates its nodes from `bump`.
+    pub fn new_in(bump: &'bump Bump) -> Self {
+        BumpList { bump, head: None, tail: None, spare: None, len: 0, marker: PhantomData }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn alloc(&mut self, val: T, prev: Link<T>, next: Link<T>) -> NonNull<Node<T>> {
+        let node = Node { val: MaybeUninit::new(val), prev, next };
+        match self.spare {
+            Some(spare) => {
+                // Safety: spare nodes are arena memory no one else points to
+                unsafe {
+                    self.spare = spare.as_ref().next;
+                    spare.as_ptr().write(node);
+                }
+                spare
+            }
+            None => NonNull::from(self.bump.alloc(node)),
+        }
+    }
+
+    // Move the element out of an unlinked node and keep the node for reuse
+    fn release(&mut self, node: NonNull<Node<T>>) -> T {
+        // Safety: the node is unlinked and its element still initialized
+        unsafe {
+            let node = &mut *node.as_ptr();
+            node.next = self.spare;
+            self.spare = Some(NonNull::from(&mut *node));
+            node.val.assume_init_read()
+        }
+    }
+
+    pub fn push_front(&mut self, val: T) {
+        let node = self.alloc(val, None, self.head);
+        match self.head {
+            // Safety: linked nodes are live arena memory owned by the list
+            Some(head) => unsafe { (*head.as_ptr()).prev = Some(node) },
+            None => self.tail = Some(node),
+        }
+        self.head = Some(node);
+        self.len += 1;
+    }
+
+    pub fn push_back(&mut self, val: T) {
+        let node = self.alloc(val, self.tail, None);
+        match self.tail {
+            Some(tail) => unsafe { (*tail.as_ptr()).next = Some(node) },
+            None => self.head = Some(node),
+        }
+        self.tail = Some(node);
+        self.len += 1;
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        let head = self.head?;
+        self.head = unsafe { head.as_ref().next };
+        match self.head {
+            Some(next) => unsafe { (*next.as_ptr()).prev = None },
+            None => self.tail = None,
+        }
+        self.len -= 1;
+        Some(self.release(head))
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        let tail = self.tail?;
+        self.tail = unsafe { tail.as_ref().prev };
+        match self.tail {
+            Some(prev) => unsafe { (*prev.as_ptr()).next = None },
+            None => self.head = None,
+        }
+        self.len -= 1;
+        Some(self.release(tail))
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.head.map(|node| unsafe { (*node.as_ptr()).val.assume_init_ref() })
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.tail.map(|node| unsafe { (*node.as_ptr()).val.assume_init_ref() })
+    }
+
+    /// Drop every element, keeping the nodes for reuse.
+    pub fn clear(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { curr: self.head, left: self.len, list: PhantomData }
+    }
+}
+
+impl<T> LinkedList<T> {
+    /// An empty `BumpList` that allocates its nodes from `bump`.
+    pub fn new_in(bump: &Bump) -> BumpList<'_, T> {
+        BumpList::new_in(bump)
+    }
+}
+
+impl<T> List<T> {
+    /// An empty `BumpList` that allocates its nodes from `bump`. It has
+    /// the same `push_front` and `pop_front`, with `front` in place of
+    /// `peek_front`.
+    pub fn new_in(bump: &Bump) -> BumpList<'_, T> {
+        BumpList::new_in(bump)
+    }
+}
+
+impl<T> Drop for BumpList<'_, T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T> Sequence<T> for BumpList<'_, T> {
+    type Iter<'a>
+        = Iter<'a, T>
+    where
+        Self: 'a,
+        T: 'a;
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn push_front(&mut self, val: T) {
+        self.push_front(val);
+    }
+
+    fn push_back(&mut self, val: T) {
+        self.push_back(val);
+    }
+
+    fn pop_front(&mut self) -> Option<T> {
+        self.pop_front()
+    }
+
+    fn pop_back(&mut self) -> Option<T> {
+        self.pop_back()
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
+    }
+}
+
+pub struct Iter<'a, T> {
+    curr: Link<T>,
+    left: usize,
+    list: PhantomData<&'a T>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.curr?;
+        // Safety: the list is borrowed for 'a, so its nodes stay linked
+        let node = unsafe { &*node.as_ptr() };
+        self.curr = node.next;
+        self.left -= 1;
+        Some(unsafe { node.val.assume_init_ref() })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.left, Some(self.left))
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<'a, T> IntoIterator for &'a BumpList<'_, T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Debug> Debug for BumpList<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Counts the elements' payloads only, since the nodes belong to the arena.
+impl<T: HeapSize> HeapSize for BumpList<'_, T> {
+    fn heap_size(&self) -> usize {
+        payload(self.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use bumpalo::Bump;
+
+    use crate::doubly_list::LinkedList;
+    use crate::list::List;
+
+    use super::BumpList;
+
+    #[test]
+    fn test_push_pop() {
+        let bump = Bump::new();
+        let mut list = BumpList::new_in(&bump);
+        assert!(list.is_empty());
+        list.push_back(2);
+        list.push_front(1);
+        list.push_back(3);
+        assert_eq!(format!("{list:?}"), "[1, 2, 3]");
+        assert_eq!((list.front(), list.back()), (Some(&1), Some(&3)));
+        assert_eq!(list.pop_back(), Some(3));
+        assert_eq!(list.pop_front(), Some(1));
+        assert_eq!(list.pop_front(), Some(2));
+        assert_eq!((list.pop_front(), list.pop_back()), (None, None));
+        list.push_back(4);
+        assert_eq!(list.iter().len(), 1);
+    }
+
+    #[test]
+    fn test_nodes_reused() {
+        let bump = Bump::new();
+        let mut list = BumpList::new_in(&bump);
+        for i in 0..100 {
+            list.push_back(i);
+        }
+        let used = bump.allocated_bytes();
+        // A queue workload runs entirely on the spare nodes
+        for i in 100..10_000 {
+            list.push_back(i);
+            assert_eq!(list.pop_front(), Some(i - 100));
+        }
+        assert_eq!(bump.allocated_bytes(), used);
+        assert!(list.iter().copied().eq(9900..10_000));
+    }
+
+    #[test]
+    fn test_new_in() {
+        let bump = Bump::new();
+        let mut list = LinkedList::new_in(&bump);
+        list.push_back(1);
+        list.push_front(0);
+        assert!(list.iter().copied().eq(0..2));
+        let mut stack = List::new_in(&bump);
+        stack.push_front("a");
+        stack.push_front("b");
+        assert_eq!(stack.front(), Some(&"b"));
+        assert_eq!(stack.pop_front(), Some("b"));
+        assert!(bump.allocated_bytes() > 0);
+    }
+
+    #[test]
+    fn test_drops_elements() {
+        let val = Rc::new(());
+        let bump = Bump::new();
+        {
+            let mut list = BumpList::new_in(&bump);
+            for _ in 0..10 {
+                list.push_front(val.clone());
+            }
+            drop(list.pop_back());
+            assert_eq!(Rc::strong_count(&val), 10);
+        }
+        assert_eq!(Rc::strong_count(&val), 1);
+    }
+}
@@ -0,0 +1,143 @@
+use std::hash::Hash;
+
+use crate::doubly_list::{LinkedList, NodeRef};
+use crate::hash_map::HashMap;
+use crate::heap_size::HeapSize;
+
+/// The operations shared by the crate's fixed-capacity caches, so callers can
+/// pick an eviction policy without changing the code that uses the cache.
+///
+/// `get` counts as a use and may reorder the cache's internal lists, while
+/// `peek` and `contains_key` only look.
+pub trait Cache<K, V> {
+    /// The most entries the cache holds.
+    fn capacity(&self) -> usize;
+
+    /// Number of entries currently held.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&mut self, key: &K) -> Option<&V>;
+
+    fn peek(&self, key: &K) -> Option<&V>;
+
+    fn contains_key(&self, key: &K) -> bool {
+        self.peek(key).is_some()
+    }
+
+    /// Insert or replace the value for `key`, returning the entry evicted to
+    /// make room, if any.
+    fn insert(&mut self, key: K, value: V) -> Option<(K, V)>;
+
+    fn remove(&mut self, key: &K) -> Option<V>;
+
+    /// Drop every entry and any history the policy keeps.
+    fn clear(&mut self);
+}
+
+struct Entry<K, V> {
+    // `None` for keys a policy only remembers
+    value: Option<V>,
+    list: usize,
+    // The key's node in its list
+    node: NodeRef<K>,
+}
+
+/// Keys spread over `N` lists, each ordered oldest first, with one hash map
+/// from every key to its value and its node, so a key can be found, moved to
+/// the back of any list, or removed in O(1). The building block of the cache
+/// policies that juggle several queues of entries and of evicted keys.
+pub(crate) struct Lists<K, V, const N: usize> {
+    map: HashMap<K, Entry<K, V>>,
+    lists: [LinkedList<K>; N],
+    lens: [usize; N],
+}
+
+impl<K: Hash + Eq + Clone, V, const N: usize> Lists<K, V, N> {
+    pub(crate) fn new() -> Self {
+        Lists { map: HashMap::new(), lists: std::array::from_fn(|_| LinkedList::new()), lens: [0; N] }
+    }
+
+    pub(crate) fn len(&self, list: usize) -> usize {
+        self.lens[list]
+    }
+
+    /// The list holding `key`, if any.
+    pub(crate) fn list_of(&self, key: &K) -> Option<usize> {
+        self.map.get(key).map(|entry| entry.list)
+    }
+
+    pub(crate) fn value(&self, key: &K) -> Option<&V> {
+        self.map.get(key)?.value.as_ref()
+    }
+
+    pub(crate) fn value_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.map.get_mut(key)?.value.as_mut()
+    }
+
+    /// Add a key that isn't in any list to the back of `list`.
+    pub(crate) fn push_back(&mut self, list: usize, key: K, value: Option<V>) {
+        let node = self.lists[list].push_back_node(key.clone());
+        self.lens[list] += 1;
+        self.map.insert(key, Entry { value, list, node });
+    }
+
+    /// Move `key` to the back of `list`, from whichever list it is in,
+    /// replacing its value if `value` is given. Returns the value.
+    ///
+    /// Panics if `key` isn't in any list.
+    pub(crate) fn move_to_back(&mut self, key: &K, list: usize, value: Option<V>) -> Option<&V> {
+        let entry = self.map.get_mut(key).expect("key in no list");
+        if let Some(value) = value {
+            entry.value = Some(value);
+        }
+        if entry.list == list {
+            self.lists[list].move_to_back(&entry.node);
+        } else {
+            self.lists[entry.list].remove_node(entry.node.clone());
+            self.lens[entry.list] -= 1;
+            entry.node = self.lists[list].push_back_node(key.clone());
+            entry.list = list;
+            self.lens[list] += 1;
+        }
+        entry.value.as_ref()
+    }
+
+    /// Take `key` out of its list, returning the list and the value.
+    pub(crate) fn remove(&mut self, key: &K) -> Option<(usize, Option<V>)> {
+        let entry = self.map.remove(key)?;
+        self.lists[entry.list].remove_node(entry.node);
+        self.lens[entry.list] -= 1;
+        Some((entry.list, entry.value))
+    }
+
+    /// The oldest key in `list`.
+    pub(crate) fn front(&self, list: usize) -> Option<K> {
+        let node = self.lists[list].front_node()?;
+        let key = node.borrow().data.clone();
+        Some(key)
+    }
+
+    /// Take the oldest key out of `list`, with its value.
+    pub(crate) fn pop_front(&mut self, list: usize) -> Option<(K, Option<V>)> {
+        let key = self.front(list)?;
+        let (_, value) = self.remove(&key)?;
+        Some((key, value))
+    }
+}
+
+// The key's node is counted by the list it sits on
+impl<K, V: HeapSize> HeapSize for Entry<K, V> {
+    fn heap_size(&self) -> usize {
+        self.value.heap_size()
+    }
+}
+
+impl<K: HeapSize, V: HeapSize, const N: usize> HeapSize for Lists<K, V, N> {
+    fn heap_size(&self) -> usize {
+        self.map.heap_size() + self.lists.heap_size()
+    }
+}
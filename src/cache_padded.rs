@@ -0,0 +1,21 @@
+use std::ops::{Deref, DerefMut};
+
+/// Aligns a value to its own cache line, so atomics written by different
+/// threads (a queue's head and tail, say) don't false-share a line.
+#[repr(align(64))]
+#[derive(Default)]
+pub(crate) struct CachePadded<T>(pub(crate) T);
+
+impl<T> Deref for CachePadded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for CachePadded<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
@@ -0,0 +1,298 @@
+use std::ops::Range;
+
+use crate::error::{CollectionError, check_range};
+use crate::heap_size::HeapSize;
+
+/// The Cartesian tree of a sequence: a binary tree on its indices that is
+/// heap-ordered by value, with the minimum at the root, and whose in-order
+/// traversal gives back the indices in order. Ties go to the leftmost index.
+///
+/// The minimum of any range `i..j` is at the lowest common ancestor of `i`
+/// and `j - 1`, which turns range-minimum queries into LCA queries. The
+/// `euler_tour` is the usual input for answering those in O(1) after linear
+/// preprocessing, for example with a sparse table or a `SegmentTree` over
+/// the depths.
+///
+/// The tree only stores the shape, as parent and child indices, not the
+/// values it was built from.
+pub struct CartesianTree {
+    root: Option<usize>,
+    parent: Vec<Option<usize>>,
+    left: Vec<Option<usize>>,
+    right: Vec<Option<usize>>,
+    depth: Vec<usize>,
+}
+
+impl CartesianTree {
+    /// Build the tree of `values` in O(n), keeping the right spine on a stack.
+    pub fn new<T: Ord>(values: &[T]) -> Self {
+        let n = values.len();
+        let mut parent = vec![None; n];
+        let mut left = vec![None; n];
+        let mut right = vec![None; n];
+        let mut spine: Vec<usize> = Vec::new();
+        for i in 0..n {
+            // Everything on the spine larger than the new value becomes its
+            // left subtree
+            let mut last = None;
+            while let Some(&top) = spine.last()
+                && values[top] > values[i]
+            {
+                last = spine.pop();
+            }
+            if let Some(child) = last {
+                left[i] = Some(child);
+                parent[child] = Some(i);
+            }
+            if let Some(&top) = spine.last() {
+                right[top] = Some(i);
+                parent[i] = Some(top);
+            }
+            spine.push(i);
+        }
+
+        let root = spine.first().copied();
+        let mut depth = vec![0; n];
+        // Parents can come after their children, so go top-down from the root
+        let mut stack: Vec<usize> = root.into_iter().collect();
+        while let Some(node) = stack.pop() {
+            for child in [left[node], right[node]].into_iter().flatten() {
+                depth[child] = depth[node] + 1;
+                stack.push(child);
+            }
+        }
+        CartesianTree { root, parent, left, right, depth }
+    }
+
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// Panic unless this is the Cartesian tree of `values`: the links agree
+    /// with each other, the in-order walk gives the indices in order, every
+    /// child's value is above its parent's (or equal, for a right child),
+    /// and the depths are right. The tree doesn't keep the values, so they
+    /// are passed in. Only in debug builds and tests.
+    #[cfg(any(test, debug_assertions))]
+    pub fn validate<T: Ord>(&self, values: &[T]) {
+        assert_eq!(values.len(), self.len(), "tree and values differ in length");
+        assert!(self.root.is_none_or(|root| self.parent[root].is_none()), "root has a parent");
+        // Iterative in-order walk, since the tree can be O(n) deep
+        let mut next = 0;
+        let mut stack = Vec::new();
+        let mut node = self.root;
+        while node.is_some() || !stack.is_empty() {
+            while let Some(n) = node {
+                stack.push(n);
+                node = self.left[n];
+            }
+            let n = stack.pop().unwrap();
+            assert_eq!(n, next, "in-order walk out of order");
+            next += 1;
+            if let Some(child) = self.left[n] {
+                assert!(values[child] > values[n], "left child not above its parent");
+            }
+            if let Some(child) = self.right[n] {
+                assert!(values[child] >= values[n], "right child below its parent");
+            }
+            for child in [self.left[n], self.right[n]].into_iter().flatten() {
+                assert_eq!(self.parent[child], Some(n), "parent link disagrees with child link");
+                assert_eq!(self.depth[child], self.depth[n] + 1, "wrong depth");
+            }
+            node = self.right[n];
+        }
+        assert_eq!(next, self.len(), "nodes unreachable from the root");
+    }
+
+    /// The index of the minimum value.
+    pub fn root(&self) -> Option<usize> {
+        self.root
+    }
+
+    pub fn parent(&self, node: usize) -> Option<usize> {
+        self.parent[node]
+    }
+
+    pub fn left(&self, node: usize) -> Option<usize> {
+        self.left[node]
+    }
+
+    pub fn right(&self, node: usize) -> Option<usize> {
+        self.right[node]
+    }
+
+    /// Distance from the root, which has depth 0.
+    pub fn depth(&self, node: usize) -> usize {
+        self.depth[node]
+    }
+
+    /// The lowest common ancestor of `a` and `b`, by walking up from the
+    /// deeper one, in O(depth).
+    pub fn lca(&self, mut a: usize, mut b: usize) -> usize {
+        while a != b {
+            if self.depth[a] >= self.depth[b] {
+                a = self.parent[a].unwrap();
+            } else {
+                b = self.parent[b].unwrap();
+            }
+        }
+        a
+    }
+
+    /// The index of the leftmost minimum value in `range`.
+    ///
+    /// Panics if `range` is empty or out of bounds.
+    pub fn range_min(&self, range: Range<usize>) -> usize {
+        assert!(range.start < range.end && range.end <= self.len(), "invalid range {range:?} for {} elements", self.len());
+        self.lca(range.start, range.end - 1)
+    }
+
+    /// `range_min`, but returning an error if `range` is empty or out of
+    /// bounds. An empty range is reported at its start.
+    pub fn try_range_min(&self, range: Range<usize>) -> Result<usize, CollectionError> {
+        check_range(&range, self.len())?;
+        if range.is_empty() {
+            return Err(CollectionError::IndexOutOfBounds { index: range.start, len: self.len() });
+        }
+        Ok(self.range_min(range))
+    }
+
+    /// Walk the tree from the root, yielding each node with its depth every
+    /// time the walk is at it: on the way down, and again after returning
+    /// from each child. That is 2n - 1 entries, and the shallowest entry
+    /// between any two nodes' first visits is their lowest common ancestor.
+    pub fn euler_tour(&self) -> EulerTour<'_> {
+        EulerTour { tree: self, stack: self.root.map(|root| (root, Visit::Enter)).into_iter().collect() }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Visit {
+    Enter,
+    Left,
+    Right,
+    Leave,
+}
+
+/// Iterator over the Euler tour of a `CartesianTree`, as `(node, depth)`.
+pub struct EulerTour<'a> {
+    tree: &'a CartesianTree,
+    // Each node on the current path with what to do there next
+    stack: Vec<(usize, Visit)>,
+}
+
+impl Iterator for EulerTour<'_> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (node, visit) = self.stack.last_mut()?;
+            let node = *node;
+            match *visit {
+                Visit::Enter => {
+                    *visit = Visit::Left;
+                    return Some((node, self.tree.depth[node]));
+                }
+                Visit::Left => {
+                    *visit = Visit::Right;
+                    if let Some(child) = self.tree.left[node] {
+                        self.stack.push((child, Visit::Enter));
+                    }
+                }
+                Visit::Right => {
+                    *visit = Visit::Leave;
+                    if let Some(child) = self.tree.right[node] {
+                        self.stack.push((child, Visit::Enter));
+                    }
+                }
+                Visit::Leave => {
+                    self.stack.pop();
+                    // Back at the parent
+                    let &(parent, _) = self.stack.last()?;
+                    return Some((parent, self.tree.depth[parent]));
+                }
+            }
+        }
+    }
+}
+
+impl HeapSize for CartesianTree {
+    fn heap_size(&self) -> usize {
+        self.parent.heap_size() + self.left.heap_size() + self.right.heap_size() + self.depth.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::CollectionError;
+    use crate::rng::XorShift64;
+
+    use super::CartesianTree;
+
+    #[test]
+    fn test_shape() {
+        // As index:value
+        //        2:1
+        //       /   \
+        //     0:3    4:2
+        //       \    /  \
+        //       1:9 3:7 5:8
+        let tree = CartesianTree::new(&[3, 9, 1, 7, 2, 8]);
+        tree.validate(&[3, 9, 1, 7, 2, 8]);
+        assert_eq!(tree.root(), Some(2));
+        assert_eq!((tree.left(2), tree.right(2)), (Some(0), Some(4)));
+        assert_eq!((tree.left(0), tree.right(0)), (None, Some(1)));
+        assert_eq!((tree.left(4), tree.right(4)), (Some(3), Some(5)));
+        assert_eq!(tree.parent(2), None);
+        assert_eq!(tree.parent(3), Some(4));
+        assert_eq!(tree.depth(5), 2);
+
+        let tour: Vec<_> = tree.euler_tour().map(|(node, _)| node).collect();
+        assert_eq!(tour, vec![2, 0, 1, 0, 2, 4, 3, 4, 5, 4, 2]);
+        assert_eq!(tour.len(), 2 * tree.len() - 1);
+    }
+
+    #[test]
+    fn test_empty_and_ties() {
+        let tree = CartesianTree::new::<i32>(&[]);
+        assert!(tree.is_empty());
+        assert_eq!(tree.root(), None);
+        assert_eq!(tree.euler_tour().next(), None);
+
+        // Equal values: the leftmost is the ancestor
+        let tree = CartesianTree::new(&[5, 5, 5]);
+        tree.validate(&[5, 5, 5]);
+        assert_eq!(tree.root(), Some(0));
+        assert_eq!(tree.range_min(1..3), 1);
+        assert_eq!(tree.try_range_min(1..3), Ok(1));
+        assert_eq!(tree.try_range_min(2..4), Err(CollectionError::IndexOutOfBounds { index: 4, len: 3 }));
+        assert_eq!(tree.try_range_min(1..1), Err(CollectionError::IndexOutOfBounds { index: 1, len: 3 }));
+    }
+
+    #[test]
+    fn test_range_min_against_brute_force() {
+        let mut rng = XorShift64::new();
+        let values: Vec<u64> = (0..300).map(|_| rng.next_u64() % 50).collect();
+        let tree = CartesianTree::new(&values);
+        tree.validate(&values);
+        for _ in 0..2000 {
+            let a = (rng.next_u64() % 300) as usize;
+            let b = (rng.next_u64() % 300) as usize;
+            let range = a.min(b)..a.max(b) + 1;
+            let expected = range.clone().min_by_key(|&i| (values[i], i)).unwrap();
+            assert_eq!(tree.range_min(range), expected);
+        }
+
+        // Each step of the tour moves to a parent or a child
+        let tour: Vec<_> = tree.euler_tour().collect();
+        assert_eq!(tour.len(), 2 * values.len() - 1);
+        for pair in tour.windows(2) {
+            let ((a, da), (b, db)) = (pair[0], pair[1]);
+            assert!(tree.parent(a) == Some(b) && db + 1 == da || tree.parent(b) == Some(a) && da + 1 == db);
+        }
+    }
+}
@@ -0,0 +1,733 @@
+use std::fmt::Debug;
+use std::ops::Index;
+use std::rc::Rc;
+
+use crate::error::{CollectionError, check_position};
+use crate::heap_size::{HeapSize, rc_size};
+use crate::sequence::Sequence;
+use crate::trace::Timed;
+
+// Most elements in a leaf and most children of a branch. A node with fewer
+// than half that many is underfull, which only the root may be.
+const CHUNK: usize = 32;
+const WIDTH: usize = 16;
+
+enum Node<T> {
+    Leaf(Vec<T>),
+    // sizes[i] is the number of elements under children[..=i]
+    Branch { sizes: Vec<usize>, children: Vec<Rc<Node<T>>> },
+}
+
+// A tree whose leaves are all `height` levels below the root
+struct Tree<T> {
+    root: Option<Rc<Node<T>>>,
+    height: u32,
+}
+
+impl<T: Clone> Node<T> {
+    fn branch(children: Vec<Rc<Node<T>>>) -> Rc<Node<T>> {
+        let sizes = children
+            .iter()
+            .scan(0, |total, child| {
+                *total += child.len();
+                Some(*total)
+            })
+            .collect();
+        Rc::new(Node::Branch { sizes, children })
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Node::Leaf(elems) => elems.len(),
+            Node::Branch { sizes, .. } => *sizes.last().unwrap(),
+        }
+    }
+
+    fn children(&self) -> &Vec<Rc<Node<T>>> {
+        match self {
+            Node::Branch { children, .. } => children,
+            Node::Leaf(_) => unreachable!("leaf above the bottom level"),
+        }
+    }
+
+    fn underfull(&self) -> bool {
+        match self {
+            Node::Leaf(elems) => elems.len() < CHUNK / 2,
+            Node::Branch { children, .. } => children.len() < WIDTH / 2,
+        }
+    }
+
+    // Check every leaf is at the same depth, only the root is underfull,
+    // and the size totals add up. Returns the number of elements.
+    #[cfg(any(test, debug_assertions))]
+    fn validate(&self, height: u32, is_root: bool) -> usize {
+        assert!(is_root || !self.underfull(), "underfull node below the root");
+        match self {
+            Node::Leaf(elems) => {
+                assert!(height == 0 && !elems.is_empty() && elems.len() <= CHUNK, "malformed leaf");
+                elems.len()
+            }
+            Node::Branch { sizes, children } => {
+                assert!(height > 0 && children.len() <= WIDTH && (children.len() >= 2 || !is_root), "malformed branch");
+                let mut total = 0;
+                for (child, size) in children.iter().zip(sizes) {
+                    total += child.validate(height - 1, false);
+                    assert_eq!(total, *size, "stale subtree size");
+                }
+                total
+            }
+        }
+    }
+}
+
+/// One node holding `items`, or two holding half each if they don't fit.
+fn pack<T: Clone, E>(mut items: Vec<E>, cap: usize, make: fn(Vec<E>) -> Rc<Node<T>>) -> (Rc<Node<T>>, Option<Rc<Node<T>>>) {
+    if items.len() <= cap {
+        return (make(items), None);
+    }
+    let right = items.split_off(items.len() / 2);
+    (make(items), Some(make(right)))
+}
+
+fn leaf<T>(elems: Vec<T>) -> Rc<Node<T>> {
+    Rc::new(Node::Leaf(elems))
+}
+
+/// Join the trees rooted at `a` and `b`, of heights `ha` and `hb`, into one
+/// or two nodes at the greater height. The shorter tree is hung off the
+/// taller one's spine at its own height, merging with its neighbor there if
+/// either is underfull, and nodes that overflow split on the way back up.
+fn join<T: Clone>(a: Rc<Node<T>>, ha: u32, b: Rc<Node<T>>, hb: u32) -> (Rc<Node<T>>, Option<Rc<Node<T>>>) {
+    if ha == hb {
+        if !a.underfull() && !b.underfull() {
+            return (a, Some(b));
+        }
+        return match (a.as_ref(), b.as_ref()) {
+            (Node::Leaf(x), Node::Leaf(y)) => pack(x.iter().chain(y).cloned().collect(), CHUNK, leaf),
+            (Node::Branch { children: x, .. }, Node::Branch { children: y, .. }) => {
+                pack(x.iter().chain(y).cloned().collect(), WIDTH, Node::branch)
+            }
+            _ => unreachable!("nodes at the same height of different kinds"),
+        };
+    }
+    let mut children = if ha > hb { a.children().clone() } else { b.children().clone() };
+    if ha > hb {
+        let last = children.pop().unwrap();
+        let (x, y) = join(last, ha - 1, b, hb);
+        children.push(x);
+        children.extend(y);
+    } else {
+        let first = children.remove(0);
+        let (x, y) = join(a, ha, first, hb - 1);
+        children.splice(0..0, std::iter::once(x).chain(y));
+    }
+    pack(children, WIDTH, Node::branch)
+}
+
+impl<T: Clone> Tree<T> {
+    const fn empty() -> Self {
+        Tree { root: None, height: 0 }
+    }
+
+    fn len(&self) -> usize {
+        self.root.as_ref().map_or(0, |root| root.len())
+    }
+
+    /// The tree rooted at `root`, dropping branches with a single child off
+    /// the top.
+    fn new(mut root: Rc<Node<T>>, mut height: u32) -> Self {
+        while let Node::Branch { children, .. } = root.as_ref()
+            && children.len() == 1
+        {
+            root = children[0].clone();
+            height -= 1;
+        }
+        Tree { root: Some(root), height }
+    }
+
+    fn of_leaf(elems: Vec<T>) -> Self {
+        if elems.is_empty() {
+            return Tree::empty();
+        }
+        Tree::new(leaf(elems), 0)
+    }
+
+    fn of_children(children: &[Rc<Node<T>>], height: u32) -> Self {
+        if children.is_empty() {
+            return Tree::empty();
+        }
+        Tree::new(Node::branch(children.to_vec()), height)
+    }
+
+    fn concat(self, other: Self) -> Self {
+        let (a, b) = match (&self.root, &other.root) {
+            (None, _) => return other,
+            (_, None) => return self,
+            (Some(a), Some(b)) => (a.clone(), b.clone()),
+        };
+        let height = self.height.max(other.height);
+        match join(a, self.height, b, other.height) {
+            (x, Some(y)) => Tree { root: Some(Node::branch(vec![x, y])), height: height + 1 },
+            (x, None) => Tree::new(x, height),
+        }
+    }
+
+    /// The trees of the first `at` elements and of the rest.
+    fn split(self, at: usize) -> (Self, Self) {
+        let Some(root) = &self.root else {
+            return (Tree::empty(), Tree::empty());
+        };
+        if at == 0 {
+            return (Tree::empty(), self);
+        }
+        if at >= root.len() {
+            return (self, Tree::empty());
+        }
+        match root.as_ref() {
+            Node::Leaf(elems) => (Tree::of_leaf(elems[..at].to_vec()), Tree::of_leaf(elems[at..].to_vec())),
+            Node::Branch { sizes, children } => {
+                let k = sizes.partition_point(|&size| size <= at);
+                let before = if k == 0 { 0 } else { sizes[k - 1] };
+                let left = Tree::of_children(&children[..k], self.height);
+                let right = Tree::of_children(&children[k + 1..], self.height);
+                let (mid_left, mid_right) = Tree { root: Some(children[k].clone()), height: self.height - 1 }.split(at - before);
+                (left.concat(mid_left), mid_right.concat(right))
+            }
+        }
+    }
+
+    fn get(&self, mut index: usize) -> Option<&T> {
+        let mut node = self.root.as_ref()?;
+        loop {
+            match node.as_ref() {
+                Node::Leaf(elems) => return elems.get(index),
+                Node::Branch { sizes, children } => {
+                    let k = sizes.partition_point(|&size| size <= index);
+                    index -= if k == 0 { 0 } else { sizes[k - 1] };
+                    node = children.get(k)?;
+                }
+            }
+        }
+    }
+
+    fn first_leaf_len(&self) -> usize {
+        let mut node = self.root.as_ref().unwrap();
+        while let Node::Branch { children, .. } = node.as_ref() {
+            node = &children[0];
+        }
+        node.len()
+    }
+
+    fn last_leaf_len(&self) -> usize {
+        let mut node = self.root.as_ref().unwrap();
+        while let Node::Branch { children, .. } = node.as_ref() {
+            node = children.last().unwrap();
+        }
+        node.len()
+    }
+
+    // The elements of a tree that is a single leaf
+    fn into_leaf(self) -> Vec<T> {
+        let root = self.root.unwrap();
+        match Rc::try_unwrap(root) {
+            Ok(Node::Leaf(elems)) => elems,
+            Ok(Node::Branch { .. }) => unreachable!("expected a single leaf"),
+            Err(shared) => match shared.as_ref() {
+                Node::Leaf(elems) => elems.clone(),
+                Node::Branch { .. } => unreachable!("expected a single leaf"),
+            },
+        }
+    }
+}
+
+impl<T> Clone for Tree<T> {
+    fn clone(&self) -> Self {
+        Tree { root: self.root.clone(), height: self.height }
+    }
+}
+
+/// A sequence of chunks of up to 32 elements, kept in a relaxed B-tree.
+///
+/// Each branch records the running total of elements under its children,
+/// so unlike `PVector`'s radix trie the chunks needn't be full and lookups
+/// binary-search those totals on the way down, O(log n). That relaxation is
+/// what lets two sequences be concatenated in O(log n), by hanging the
+/// shorter tree off the other's spine and merging underfull nodes where they
+/// meet, and lets `split_off` cut one in O(log n) as well. Pushes and pops at
+/// either end go to a small buffer at that end, which only moves into or out
+/// of the tree a whole chunk at a time, so they take O(1) amortized.
+///
+/// Clones share every node, and edits copy only the path they change.
+pub struct ChunkedSeq<T> {
+    // The first elements, in reverse order
+    head: Vec<T>,
+    tree: Tree<T>,
+    tail: Vec<T>,
+}
+
+impl<T: Clone> ChunkedSeq<T> {
+    pub const fn new() -> Self {
+        ChunkedSeq { head: Vec::new(), tree: Tree::empty(), tail: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.head.len() + self.tree.len() + self.tail.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Panic unless the tree is balanced with only the root underfull, its
+    /// cached sizes add up, and the chunks buffered at either end are short
+    /// of a full chunk. Only in debug builds and tests.
+    #[cfg(any(test, debug_assertions))]
+    pub fn validate(&self) {
+        if let Some(root) = &self.tree.root {
+            root.validate(self.tree.height, true);
+        }
+        assert!(self.head.len() < CHUNK && self.tail.len() < CHUNK, "full chunk left at an end");
+    }
+
+    /// Rebuild the tree out of full chunks. Splits and appends leave
+    /// chunks down to half full, so this can cut the number of chunks by
+    /// up to half. O(n), and the elements are cloned, since the old chunks
+    /// may be shared with clones of the sequence.
+    pub fn compact(&mut self) {
+        *self = self.iter().cloned().collect();
+    }
+
+    /// `List::par_sort` for chunked sequences. Nodes are shared through `Rc`s
+    /// that can't leave this thread, so the elements are cloned out into one
+    /// buffer, sorted there with rayon's parallel merge sort, and the
+    /// sequence rebuilt from it. Stable.
+    #[cfg(feature = "rayon")]
+    pub fn par_sort(&mut self)
+    where
+        T: Ord + Send,
+    {
+        self.par_sort_by(T::cmp);
+    }
+
+    /// `par_sort`, ordering by `cmp`
+    #[cfg(feature = "rayon")]
+    pub fn par_sort_by<F: Fn(&T, &T) -> std::cmp::Ordering + Sync>(&mut self, cmp: F)
+    where
+        T: Send,
+    {
+        use rayon::slice::ParallelSliceMut;
+
+        let mut elems: Vec<T> = self.iter().cloned().collect();
+        elems.par_sort_by(cmp);
+        *self = elems.into_iter().collect();
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index < self.head.len() {
+            return self.head.get(self.head.len() - 1 - index);
+        }
+        let index = index - self.head.len();
+        let tree_len = self.tree.len();
+        if index < tree_len { self.tree.get(index) } else { self.tail.get(index - tree_len) }
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.get(self.len().checked_sub(1)?)
+    }
+
+    pub fn push_back(&mut self, val: T) {
+        self.tail.push(val);
+        if self.tail.len() == CHUNK {
+            let chunk = std::mem::take(&mut self.tail);
+            self.tree = std::mem::replace(&mut self.tree, Tree::empty()).concat(Tree::of_leaf(chunk));
+        }
+    }
+
+    pub fn push_front(&mut self, val: T) {
+        self.head.push(val);
+        if self.head.len() == CHUNK {
+            let mut chunk = std::mem::take(&mut self.head);
+            chunk.reverse();
+            self.tree = Tree::of_leaf(chunk).concat(std::mem::replace(&mut self.tree, Tree::empty()));
+        }
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.tail.is_empty() && self.tree.root.is_some() {
+            // Bring the last chunk out of the tree
+            let at = self.tree.len() - self.tree.last_leaf_len();
+            let (rest, last) = std::mem::replace(&mut self.tree, Tree::empty()).split(at);
+            self.tree = rest;
+            self.tail = last.into_leaf();
+        }
+        if self.tail.is_empty() && !self.head.is_empty() {
+            return Some(self.head.remove(0));
+        }
+        self.tail.pop()
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.head.is_empty() && self.tree.root.is_some() {
+            let at = self.tree.first_leaf_len();
+            let (first, rest) = std::mem::replace(&mut self.tree, Tree::empty()).split(at);
+            self.tree = rest;
+            self.head = first.into_leaf();
+            self.head.reverse();
+        }
+        if self.head.is_empty() && !self.tail.is_empty() {
+            return Some(self.tail.remove(0));
+        }
+        self.head.pop()
+    }
+
+    // Move both end buffers into the tree, leaving everything in it
+    fn flush(&mut self) -> Tree<T> {
+        let mut head = std::mem::take(&mut self.head);
+        head.reverse();
+        let tree = std::mem::replace(&mut self.tree, Tree::empty());
+        Tree::of_leaf(head).concat(tree).concat(Tree::of_leaf(std::mem::take(&mut self.tail)))
+    }
+
+    /// Move every element of `other` onto the end of `self`, in O(log n).
+    pub fn append(&mut self, other: &mut Self) {
+        let timed = Timed::start();
+        let tree = self.flush();
+        self.tree = tree.concat(other.flush());
+        timed.finish("ChunkedSeq", "append", self.len());
+    }
+
+    /// Split off the elements from `at` on into a new sequence, in O(log n).
+    ///
+    /// Panics if `at > len`.
+    pub fn split_off(&mut self, at: usize) -> Self {
+        assert!(at <= self.len(), "split index {at} out of bounds for length {}", self.len());
+        let timed = Timed::start();
+        let (left, right) = self.flush().split(at);
+        self.tree = left;
+        timed.finish("ChunkedSeq", "split_off", self.len());
+        ChunkedSeq { head: Vec::new(), tree: right, tail: Vec::new() }
+    }
+
+    /// `split_off`, but returning an error if `at > len`.
+    pub fn try_split_off(&mut self, at: usize) -> Result<Self, CollectionError> {
+        check_position(at, self.len())?;
+        Ok(self.split_off(at))
+    }
+
+    pub fn clear(&mut self) {
+        *self = ChunkedSeq::new();
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            head: self.head.iter().rev(),
+            stack: self.tree.root.iter().collect(),
+            leaf: [].iter(),
+            tail: self.tail.iter(),
+            len: self.len(),
+        }
+    }
+}
+
+impl<T: Clone> Clone for ChunkedSeq<T> {
+    fn clone(&self) -> Self {
+        ChunkedSeq { head: self.head.clone(), tree: self.tree.clone(), tail: self.tail.clone() }
+    }
+}
+
+impl<T: Clone> Default for ChunkedSeq<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> Index<usize> for ChunkedSeq<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        self.get(index).expect("index out of bounds")
+    }
+}
+
+impl<T: Clone> Sequence<T> for ChunkedSeq<T> {
+    type Iter<'a>
+        = Iter<'a, T>
+    where
+        T: 'a;
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn push_front(&mut self, val: T) {
+        self.push_front(val);
+    }
+
+    fn push_back(&mut self, val: T) {
+        self.push_back(val);
+    }
+
+    fn pop_front(&mut self) -> Option<T> {
+        self.pop_front()
+    }
+
+    fn pop_back(&mut self) -> Option<T> {
+        self.pop_back()
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
+    }
+
+    #[cfg(any(test, debug_assertions))]
+    fn validate(&self) {
+        self.validate();
+    }
+}
+
+pub struct Iter<'a, T> {
+    head: std::iter::Rev<std::slice::Iter<'a, T>>,
+    // Subtrees still to visit, the next one on top
+    stack: Vec<&'a Rc<Node<T>>>,
+    leaf: std::slice::Iter<'a, T>,
+    tail: std::slice::Iter<'a, T>,
+    len: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let val = self.head.next().or_else(|| loop {
+            if let Some(val) = self.leaf.next() {
+                return Some(val);
+            }
+            match self.stack.pop().map(Rc::as_ref) {
+                Some(Node::Leaf(elems)) => self.leaf = elems.iter(),
+                Some(Node::Branch { children, .. }) => self.stack.extend(children.iter().rev()),
+                None => return self.tail.next(),
+            }
+        })?;
+        self.len -= 1;
+        Some(val)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<'a, T: Clone> IntoIterator for &'a ChunkedSeq<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Clone> FromIterator<T> for ChunkedSeq<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut seq = ChunkedSeq::new();
+        for val in iter {
+            seq.push_back(val);
+        }
+        seq
+    }
+}
+
+impl<T: Clone + PartialEq> PartialEq for ChunkedSeq<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Clone + Eq> Eq for ChunkedSeq<T> {}
+
+impl<T: Clone + Debug> Debug for ChunkedSeq<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Node<T> {
+    fn heap_size(&self) -> usize {
+        match self {
+            Node::Leaf(elems) => elems.heap_size(),
+            Node::Branch { sizes, children } => {
+                sizes.heap_size()
+                    + children.capacity() * size_of::<Rc<Node<T>>>()
+                    + children.iter().map(|child| rc_size::<Node<T>>() + child.heap_size()).sum::<usize>()
+            }
+        }
+    }
+}
+
+/// Counts every node the sequence can reach, including ones it shares with
+/// its clones.
+impl<T: HeapSize> HeapSize for ChunkedSeq<T> {
+    fn heap_size(&self) -> usize {
+        let tree = self.tree.root.as_ref().map_or(0, |root| rc_size::<Node<T>>() + root.heap_size());
+        self.head.heap_size() + tree + self.tail.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use crate::error::CollectionError;
+    use crate::rng::XorShift64;
+
+    use super::{CHUNK, ChunkedSeq, Node};
+
+    #[test]
+    fn test_push_pop_both_ends() {
+        let mut seq = ChunkedSeq::new();
+        for i in 0..500 {
+            seq.push_back(i);
+            seq.push_front(-i - 1);
+        }
+        seq.validate();
+        assert_eq!(seq.len(), 1000);
+        assert_eq!((seq[0], seq[499], seq[500], seq[999]), (-500, -1, 0, 499));
+        assert!(seq.iter().copied().eq(-500..500));
+        for i in (0..500).rev() {
+            assert_eq!(seq.pop_back(), Some(i));
+        }
+        assert_eq!(seq.pop_back(), Some(-1));
+        assert_eq!(seq.pop_front(), Some(-500));
+        seq.validate();
+        assert_eq!(seq.len(), 498);
+        while seq.pop_front().is_some() {}
+        assert!(seq.is_empty());
+        assert_eq!(seq.first(), None);
+    }
+
+    #[test]
+    fn test_append_and_split() {
+        let mut a: ChunkedSeq<_> = (0..1000).collect();
+        let mut b: ChunkedSeq<_> = (1000..1010).collect();
+        a.append(&mut b);
+        a.validate();
+        assert!(b.is_empty());
+        assert!(a.iter().copied().eq(0..1010));
+
+        let mut right = a.split_off(333);
+        a.validate();
+        right.validate();
+        assert!(a.iter().copied().eq(0..333));
+        assert!(right.iter().copied().eq(333..1010));
+        let rest = right.split_off(right.len());
+        assert!(rest.is_empty());
+        right.append(&mut a);
+        assert_eq!((right[0], right[676], right[677]), (333, 1009, 0));
+        assert_eq!(right.try_split_off(1011).map(|_| ()), Err(CollectionError::IndexOutOfBounds { index: 1011, len: 1010 }));
+        assert_eq!(right.try_split_off(1000).map(|rest| rest.len()), Ok(10));
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn test_par_sort() {
+        let mut seq: ChunkedSeq<(i32, usize)> = (0..10_000).map(|i| ((i * 7919) % 100, i as usize)).collect();
+        let copy = seq.clone();
+        seq.par_sort_by(|a, b| a.0.cmp(&b.0));
+        seq.validate();
+        // Equal keys keep their order, and the copy sharing the old nodes
+        // is left as it was
+        assert!(seq.iter().zip(seq.iter().skip(1)).all(|(a, b)| a.0 < b.0 || (a.0 == b.0 && a.1 < b.1)));
+        assert_eq!(copy.first(), Some(&(0, 0)));
+        assert_eq!(copy.get(1), Some(&(19, 1)));
+
+        let mut seq: ChunkedSeq<i32> = [3, 1, 2].into_iter().collect();
+        seq.par_sort();
+        assert!(seq.iter().copied().eq([1, 2, 3]));
+    }
+
+    #[test]
+    fn test_compact() {
+        fn leaves<T: Clone>(node: &Node<T>) -> usize {
+            match node {
+                Node::Leaf(_) => 1,
+                Node::Branch { children, .. } => children.iter().map(|child| leaves(child)).sum(),
+            }
+        }
+
+        // Cut it up at points that leave underfull chunks either side
+        let mut seq = ChunkedSeq::new();
+        let mut rest: ChunkedSeq<_> = (0..2000).collect();
+        while rest.len() > 20 {
+            let tail = rest.split_off(20);
+            seq.append(&mut rest);
+            rest = tail;
+        }
+        seq.append(&mut rest);
+        let before = leaves(seq.tree.root.as_ref().unwrap());
+        seq.compact();
+        seq.validate();
+        assert!(leaves(seq.tree.root.as_ref().unwrap()) < before);
+        assert_eq!(leaves(seq.tree.root.as_ref().unwrap()), 2000 / CHUNK);
+        assert!(seq.iter().copied().eq(0..2000));
+    }
+
+    #[test]
+    fn test_clones_are_independent() {
+        let seq: ChunkedSeq<_> = (0..200).collect();
+        let mut copy = seq.clone();
+        copy.split_off(100);
+        copy.push_front(-1);
+        assert_eq!(seq.len(), 200);
+        assert!(seq.iter().copied().eq(0..200));
+        assert_eq!(copy.len(), 101);
+    }
+
+    #[test]
+    fn test_random_concat_split() {
+        let mut rng = XorShift64::new();
+        let mut seqs: Vec<(ChunkedSeq<u64>, VecDeque<u64>)> = Vec::new();
+        let mut next = 0;
+        for _ in 0..400 {
+            match rng.next_u64() % 4 {
+                0 | 1 => {
+                    let n = rng.next_u64() % 300;
+                    let vals: VecDeque<_> = (next..next + n).collect();
+                    next += n;
+                    seqs.push((vals.iter().copied().collect(), vals));
+                }
+                2 if seqs.len() >= 2 => {
+                    let (mut b, mut model_b) = seqs.swap_remove(rng.next_u64() as usize % seqs.len());
+                    let i = rng.next_u64() as usize % seqs.len();
+                    let (a, model_a) = &mut seqs[i];
+                    a.append(&mut b);
+                    model_a.append(&mut model_b);
+                }
+                _ if !seqs.is_empty() => {
+                    let i = rng.next_u64() as usize % seqs.len();
+                    let (seq, model) = &mut seqs[i];
+                    let at = rng.next_u64() as usize % (seq.len() + 1);
+                    let right = seq.split_off(at);
+                    let model_right = model.split_off(at);
+                    seqs.push((right, model_right));
+                }
+                _ => {}
+            }
+            for (seq, model) in &seqs {
+                seq.validate();
+                assert_eq!(seq.len(), model.len());
+            }
+        }
+        for (seq, model) in &seqs {
+            assert!(seq.iter().eq(model.iter()));
+            if !model.is_empty() {
+                let i = rng.next_u64() as usize % model.len();
+                assert_eq!(seq[i], model[i]);
+            }
+        }
+    }
+}
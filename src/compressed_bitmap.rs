@@ -0,0 +1,503 @@
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::iter::Peekable;
+
+use crate::bit_vec::BitVec;
+use crate::heap_size::HeapSize;
+
+// Containers with more values than this switch from a sorted array (2 bytes
+// per value) to a bitmap (a fixed 8 KiB)
+const ARRAY_MAX: usize = 4096;
+const CONTAINER_BITS: usize = 1 << 16;
+
+/// The low 16 bits of the values sharing one high 16-bit key.
+#[derive(Clone)]
+enum Container {
+    Array(Vec<u16>),
+    Bitmap(BitVec),
+    // (start, length - 1) of each run of consecutive values
+    Run(Vec<(u16, u16)>),
+}
+
+impl Container {
+    /// An array or bitmap container, whichever suits the number of values.
+    fn from_sorted(values: Vec<u16>) -> Self {
+        if values.len() <= ARRAY_MAX {
+            return Container::Array(values);
+        }
+        let mut bits = BitVec::from_elem(CONTAINER_BITS, false);
+        for v in values {
+            bits.set(v as usize, true);
+        }
+        Container::Bitmap(bits)
+    }
+
+    fn from_bitmap(bits: BitVec) -> Self {
+        if bits.count_ones() <= ARRAY_MAX {
+            Container::Array(bits.ones().map(|v| v as u16).collect())
+        } else {
+            Container::Bitmap(bits)
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Container::Array(values) => values.len(),
+            Container::Bitmap(bits) => bits.count_ones(),
+            Container::Run(runs) => runs.iter().map(|&(_, len)| len as usize + 1).sum(),
+        }
+    }
+
+    /// Check that an array is strictly ascending and within `ARRAY_MAX`, a
+    /// bitmap spans 2^16 bits with more than `ARRAY_MAX` set, and runs are
+    /// ascending with gaps between them.
+    #[cfg(any(test, debug_assertions))]
+    fn validate(&self) {
+        match self {
+            Container::Array(values) => {
+                assert!(values.len() <= ARRAY_MAX, "array over ARRAY_MAX");
+                assert!(values.is_sorted_by(|a, b| a < b), "array not strictly ascending");
+            }
+            Container::Bitmap(bits) => {
+                assert_eq!(bits.len(), CONTAINER_BITS, "bitmap of the wrong length");
+                assert!(bits.count_ones() > ARRAY_MAX, "bitmap sparse enough for an array");
+            }
+            Container::Run(runs) => {
+                assert!(runs.iter().all(|&(start, len)| start.checked_add(len).is_some()), "run past the container");
+                assert!(runs.is_sorted_by(|a, b| a.0 as u32 + a.1 as u32 + 1 < b.0 as u32), "runs overlapping or touching");
+            }
+        }
+    }
+
+    fn contains(&self, v: u16) -> bool {
+        match self {
+            Container::Array(values) => values.binary_search(&v).is_ok(),
+            Container::Bitmap(bits) => bits.get(v as usize).unwrap(),
+            Container::Run(runs) => {
+                let i = runs.partition_point(|&(start, _)| start <= v);
+                i > 0 && v - runs[i - 1].0 <= runs[i - 1].1
+            }
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = u16> + '_> {
+        match self {
+            Container::Array(values) => Box::new(values.iter().copied()),
+            Container::Bitmap(bits) => Box::new(bits.ones().map(|v| v as u16)),
+            Container::Run(runs) => Box::new(runs.iter().flat_map(|&(start, len)| start..=start + len)),
+        }
+    }
+
+    /// Run containers are read-only; turn one back into an array or bitmap
+    /// before modifying it.
+    fn unpack_runs(&mut self) {
+        if let Container::Run(_) = self {
+            *self = Container::from_sorted(self.iter().collect());
+        }
+    }
+
+    fn insert(&mut self, v: u16) -> bool {
+        self.unpack_runs();
+        match self {
+            Container::Array(values) => {
+                let Err(i) = values.binary_search(&v) else {
+                    return false;
+                };
+                values.insert(i, v);
+                if values.len() > ARRAY_MAX {
+                    let values = std::mem::take(values);
+                    *self = Container::from_sorted(values);
+                }
+                true
+            }
+            Container::Bitmap(bits) => {
+                let present = bits.get(v as usize).unwrap();
+                bits.set(v as usize, true);
+                !present
+            }
+            Container::Run(_) => unreachable!(),
+        }
+    }
+
+    fn remove(&mut self, v: u16) -> bool {
+        self.unpack_runs();
+        match self {
+            Container::Array(values) => {
+                let Ok(i) = values.binary_search(&v) else {
+                    return false;
+                };
+                values.remove(i);
+                true
+            }
+            Container::Bitmap(bits) => {
+                if !bits.get(v as usize).unwrap() {
+                    return false;
+                }
+                bits.set(v as usize, false);
+                let bits = std::mem::take(bits);
+                *self = Container::from_bitmap(bits);
+                true
+            }
+            Container::Run(_) => unreachable!(),
+        }
+    }
+
+    fn union(&self, other: &Container) -> Container {
+        match (self, other) {
+            (Container::Bitmap(a), Container::Bitmap(b)) => Container::Bitmap(a | b),
+            (Container::Bitmap(bits), other) | (other, Container::Bitmap(bits)) => {
+                let mut bits = bits.clone();
+                for v in other.iter() {
+                    bits.set(v as usize, true);
+                }
+                Container::Bitmap(bits)
+            }
+            (a, b) => Container::from_sorted(SortedMerge::new(a.iter(), b.iter(), false).collect()),
+        }
+    }
+
+    fn intersection(&self, other: &Container) -> Container {
+        match (self, other) {
+            (Container::Bitmap(a), Container::Bitmap(b)) => Container::from_bitmap(a & b),
+            (Container::Bitmap(bits), other) | (other, Container::Bitmap(bits)) => {
+                Container::from_sorted(other.iter().filter(|&v| bits.get(v as usize).unwrap()).collect())
+            }
+            (a, b) => Container::from_sorted(SortedMerge::new(a.iter(), b.iter(), true).collect()),
+        }
+    }
+
+    /// Re-encode as whichever of array, bitmap or runs takes the least space.
+    fn optimize(&mut self) {
+        let mut runs: Vec<(u16, u16)> = Vec::new();
+        for v in self.iter() {
+            match runs.last_mut() {
+                Some((start, len)) if *start as u32 + *len as u32 + 1 == v as u32 => *len += 1,
+                _ => runs.push((v, 0)),
+            }
+        }
+        if 4 * runs.len() < Self::packed_size(self.len()) {
+            *self = Container::Run(runs);
+        } else {
+            *self = Container::from_sorted(self.iter().collect());
+        }
+    }
+
+    // Bytes used by an array or bitmap holding `len` values
+    fn packed_size(len: usize) -> usize {
+        if len <= ARRAY_MAX { 2 * len } else { CONTAINER_BITS / 8 }
+    }
+
+    fn size_in_bytes(&self) -> usize {
+        match self {
+            Container::Array(values) => 2 * values.len(),
+            Container::Bitmap(_) => CONTAINER_BITS / 8,
+            Container::Run(runs) => 4 * runs.len(),
+        }
+    }
+}
+
+/// Union or intersection of two ascending iterators.
+struct SortedMerge<A: Iterator, B: Iterator> {
+    a: Peekable<A>,
+    b: Peekable<B>,
+    intersect: bool,
+}
+
+impl<A: Iterator<Item = u16>, B: Iterator<Item = u16>> SortedMerge<A, B> {
+    fn new(a: A, b: B, intersect: bool) -> Self {
+        SortedMerge { a: a.peekable(), b: b.peekable(), intersect }
+    }
+}
+
+impl<A: Iterator<Item = u16>, B: Iterator<Item = u16>> Iterator for SortedMerge<A, B> {
+    type Item = u16;
+
+    fn next(&mut self) -> Option<u16> {
+        loop {
+            let ord = match (self.a.peek(), self.b.peek()) {
+                (Some(a), Some(b)) => a.cmp(b),
+                _ if self.intersect => return None,
+                (Some(_), None) => Ordering::Less,
+                (None, _) => return self.b.next(),
+            };
+            let v = match ord {
+                Ordering::Less => self.a.next(),
+                Ordering::Greater => self.b.next(),
+                Ordering::Equal => {
+                    self.b.next();
+                    return self.a.next();
+                }
+            };
+            if !self.intersect {
+                return v;
+            }
+        }
+    }
+}
+
+/// A set of `u32` values compressed in the style of Roaring bitmaps.
+///
+/// Values are grouped by their high 16 bits, and each group's low 16 bits are
+/// stored in the most compact of three containers: a sorted array for sparse
+/// groups, a 65536-bit `BitVec` for dense ones, or (after `optimize`) a list of
+/// runs for long stretches of consecutive values. Union and intersection work
+/// container by container, with word-wise bit operations between bitmaps.
+#[derive(Clone, Default)]
+pub struct CompressedBitmap {
+    // Sorted by key, no empty containers
+    containers: Vec<(u16, Container)>,
+}
+
+fn split(value: u32) -> (u16, u16) {
+    ((value >> 16) as u16, value as u16)
+}
+
+impl CompressedBitmap {
+    pub const fn new() -> Self {
+        CompressedBitmap { containers: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.containers.iter().map(|(_, c)| c.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.containers.is_empty()
+    }
+
+    /// Panic unless the containers are in strictly ascending key order, none
+    /// is empty, and each is well-formed for its kind. Only in debug builds
+    /// and tests.
+    #[cfg(any(test, debug_assertions))]
+    pub fn validate(&self) {
+        assert!(self.containers.is_sorted_by(|a, b| a.0 < b.0), "containers out of order");
+        for (_, c) in &self.containers {
+            assert!(c.len() > 0, "empty container");
+            c.validate();
+        }
+    }
+
+    fn find(&self, key: u16) -> Result<usize, usize> {
+        self.containers.binary_search_by_key(&key, |(k, _)| *k)
+    }
+
+    /// Add `value`, returning whether it was newly added.
+    pub fn insert(&mut self, value: u32) -> bool {
+        let (key, low) = split(value);
+        match self.find(key) {
+            Ok(i) => self.containers[i].1.insert(low),
+            Err(i) => {
+                self.containers.insert(i, (key, Container::Array(vec![low])));
+                true
+            }
+        }
+    }
+
+    /// Remove `value`, returning whether it was present.
+    pub fn remove(&mut self, value: u32) -> bool {
+        let (key, low) = split(value);
+        let Ok(i) = self.find(key) else {
+            return false;
+        };
+        let removed = self.containers[i].1.remove(low);
+        if self.containers[i].1.len() == 0 {
+            self.containers.remove(i);
+        }
+        removed
+    }
+
+    pub fn contains(&self, value: u32) -> bool {
+        let (key, low) = split(value);
+        self.find(key).is_ok_and(|i| self.containers[i].1.contains(low))
+    }
+
+    /// Values in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        self.containers
+            .iter()
+            .flat_map(|(key, c)| c.iter().map(move |low| (*key as u32) << 16 | low as u32))
+    }
+
+    pub fn union(&self, other: &CompressedBitmap) -> CompressedBitmap {
+        let mut containers = Vec::new();
+        let (mut a, mut b) = (self.containers.iter().peekable(), other.containers.iter().peekable());
+        loop {
+            let next = match (a.peek(), b.peek()) {
+                (Some((ka, ca)), Some((kb, cb))) => match ka.cmp(kb) {
+                    Ordering::Less => a.next().cloned(),
+                    Ordering::Greater => b.next().cloned(),
+                    Ordering::Equal => {
+                        let merged = (*ka, ca.union(cb));
+                        a.next();
+                        b.next();
+                        Some(merged)
+                    }
+                },
+                (Some(_), None) => a.next().cloned(),
+                (None, _) => b.next().cloned(),
+            };
+            match next {
+                Some(entry) => containers.push(entry),
+                None => break,
+            }
+        }
+        CompressedBitmap { containers }
+    }
+
+    pub fn intersection(&self, other: &CompressedBitmap) -> CompressedBitmap {
+        let mut containers = Vec::new();
+        for (key, a) in &self.containers {
+            if let Ok(i) = other.find(*key) {
+                let both = a.intersection(&other.containers[i].1);
+                if both.len() > 0 {
+                    containers.push((*key, both));
+                }
+            }
+        }
+        CompressedBitmap { containers }
+    }
+
+    /// Re-encode every container in its most compact form, including run
+    /// containers for stretches of consecutive values. Inserting into or
+    /// removing from a run container unpacks it again.
+    pub fn optimize(&mut self) {
+        for (_, c) in &mut self.containers {
+            c.optimize();
+        }
+    }
+
+    /// Approximate bytes used by the containers' contents.
+    pub fn size_in_bytes(&self) -> usize {
+        self.containers.iter().map(|(_, c)| 2 + c.size_in_bytes()).sum()
+    }
+}
+
+impl FromIterator<u32> for CompressedBitmap {
+    fn from_iter<I: IntoIterator<Item = u32>>(iter: I) -> Self {
+        let mut bitmap = CompressedBitmap::new();
+        for value in iter {
+            bitmap.insert(value);
+        }
+        bitmap
+    }
+}
+
+impl Debug for CompressedBitmap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl HeapSize for Container {
+    fn heap_size(&self) -> usize {
+        match self {
+            Container::Array(values) => values.heap_size(),
+            Container::Bitmap(bits) => bits.heap_size(),
+            Container::Run(runs) => runs.heap_size(),
+        }
+    }
+}
+
+impl HeapSize for CompressedBitmap {
+    fn heap_size(&self) -> usize {
+        self.containers.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heap_size::HeapSize;
+
+    use super::CompressedBitmap;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_insert_remove() {
+        let mut bitmap = CompressedBitmap::new();
+        assert!(bitmap.is_empty());
+        assert!(bitmap.insert(5));
+        assert!(bitmap.insert(70_000));
+        assert!(bitmap.insert(u32::MAX));
+        assert!(!bitmap.insert(5));
+        assert_eq!(bitmap.len(), 3);
+        assert!(bitmap.contains(70_000));
+        assert!(!bitmap.contains(70_001));
+        assert_eq!(bitmap.iter().collect::<Vec<_>>(), vec![5, 70_000, u32::MAX]);
+
+        assert!(bitmap.remove(70_000));
+        assert!(!bitmap.remove(70_000));
+        assert!(!bitmap.remove(123));
+        assert_eq!(bitmap.iter().collect::<Vec<_>>(), vec![5, u32::MAX]);
+    }
+
+    #[test]
+    fn test_dense_container() {
+        // Crosses the array limit and back
+        let mut bitmap: CompressedBitmap = (0..10_000).map(|i| i * 3).collect();
+        assert_eq!(bitmap.len(), 10_000);
+        assert_eq!(bitmap.size_in_bytes(), 2 + 8192);
+        bitmap.validate();
+        assert!(bitmap.contains(2997));
+        assert!(!bitmap.contains(2998));
+        for i in 0..9_000 {
+            assert!(bitmap.remove(i * 3));
+        }
+        assert_eq!(bitmap.len(), 1000);
+        assert_eq!(bitmap.size_in_bytes(), 2 + 2000);
+        bitmap.validate();
+        assert_eq!(bitmap.iter().next(), Some(27_000));
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a: CompressedBitmap = (0..20_000).filter(|i| i % 2 == 0).chain(100_000..100_010).collect();
+        let b: CompressedBitmap = (0..20_000).filter(|i| i % 3 == 0).chain(200_000..200_005).collect();
+        let expected_union: BTreeSet<u32> = a.iter().chain(b.iter()).collect();
+        let expected_both: BTreeSet<u32> = a.iter().filter(|&v| b.contains(v)).collect();
+
+        let union = a.union(&b);
+        union.validate();
+        assert_eq!(union.iter().collect::<Vec<_>>(), expected_union.into_iter().collect::<Vec<_>>());
+        let both = a.intersection(&b);
+        both.validate();
+        assert_eq!(both.iter().collect::<Vec<_>>(), expected_both.into_iter().collect::<Vec<_>>());
+        assert!(both.iter().all(|v| v % 6 == 0 && v < 20_000));
+    }
+
+    #[test]
+    fn test_optimize_runs() {
+        let mut bitmap: CompressedBitmap = (1000..60_000).chain(70_000..70_003).collect();
+        let before = bitmap.size_in_bytes();
+        bitmap.optimize();
+        assert!(bitmap.size_in_bytes() < before / 100);
+        bitmap.validate();
+        assert_eq!(bitmap.len(), 59_003);
+        assert!(bitmap.contains(1000));
+        assert!(bitmap.contains(59_999));
+        assert!(!bitmap.contains(60_000));
+        assert!(!bitmap.contains(999));
+
+        let other: CompressedBitmap = (59_990..60_010).collect();
+        assert_eq!(bitmap.intersection(&other).len(), 10);
+        assert_eq!(bitmap.union(&other).len(), 59_013);
+
+        // Modifying a run container unpacks it
+        assert!(bitmap.remove(30_000));
+        assert!(!bitmap.contains(30_000));
+        bitmap.validate();
+        assert_eq!(bitmap.len(), 59_002);
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let mut bitmap = CompressedBitmap::new();
+        assert_eq!(bitmap.heap_size(), 0);
+        bitmap.insert(1);
+        let array = bitmap.heap_size();
+        for i in 0..10_000 {
+            bitmap.insert(i * 3);
+        }
+        // Dense enough for a bitmap container of 2^16 bits
+        assert!(bitmap.heap_size() > array + 8000);
+    }
+}
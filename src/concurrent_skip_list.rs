@@ -0,0 +1,463 @@
+use std::fmt::Debug;
+use std::ops::{Bound, RangeBounds};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::thread;
+
+use crate::heap_size::{HeapSize, rc_size};
+
+const MAX_LEVEL: usize = 20;
+
+type Link<K, V> = RwLock<Option<Arc<Node<K, V>>>>;
+
+struct Node<K, V> {
+    // `None` only for the head sentinel, which sorts before every key
+    key: Option<K>,
+    value: Mutex<Option<V>>,
+    next: Vec<Link<K, V>>,
+    // Held while changing this node's outgoing links or removing it
+    lock: Mutex<()>,
+    // Logically removed
+    marked: AtomicBool,
+    // Linked in at every level
+    fully_linked: AtomicBool,
+}
+
+impl<K: Ord, V> Node<K, V> {
+    fn new(key: Option<K>, value: Option<V>, height: usize) -> Arc<Self> {
+        Arc::new(Node {
+            key,
+            value: Mutex::new(value),
+            next: (0..height).map(|_| RwLock::new(None)).collect(),
+            lock: Mutex::new(()),
+            marked: AtomicBool::new(false),
+            fully_linked: AtomicBool::new(false),
+        })
+    }
+
+    fn next(&self, level: usize) -> Option<Arc<Node<K, V>>> {
+        self.next[level].read().unwrap().clone()
+    }
+
+    fn is_before(&self, key: &K) -> bool {
+        self.key.as_ref().is_none_or(|k| k < key)
+    }
+
+    fn is_marked(&self) -> bool {
+        self.marked.load(Ordering::Acquire)
+    }
+}
+
+fn same<K, V>(a: &Option<Arc<Node<K, V>>>, b: &Option<Arc<Node<K, V>>>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// An ordered map that any number of threads can read and update at once.
+///
+/// This is the lazy skip list of Herlihy, Lev, Luchangco and Shavit. Lookups
+/// and traversals take no node locks, only a brief read lock on each link
+/// they follow. An insert or remove locks just the predecessors it splices
+/// at, checks they are still unmarked and still point where the search said,
+/// and retries the search otherwise, yielding first so the writer it lost
+/// to can finish. A node is first marked as removed and then unlinked, so
+/// readers racing with a remove see the key either present or absent, never
+/// half removed. Nodes are reference counted, so one that a
+/// reader is standing on stays valid after it is unlinked.
+///
+/// Values are handed out as clones, since they may change or be removed as
+/// soon as the map's locks are released.
+pub struct ConcurrentSkipListMap<K, V> {
+    head: Arc<Node<K, V>>,
+    len: AtomicUsize,
+    seed: AtomicU64,
+}
+
+impl<K: Ord, V: Clone> ConcurrentSkipListMap<K, V> {
+    pub fn new() -> Self {
+        ConcurrentSkipListMap {
+            head: Node::new(None, None, MAX_LEVEL),
+            len: AtomicUsize::new(0),
+            seed: AtomicU64::new(0x2545_f491_4f6c_dd1d),
+        }
+    }
+
+    /// Number of entries. Only a snapshot while other threads are writing.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Geometric with p = 1/2, from a shared splitmix64 sequence
+    fn random_height(&self) -> usize {
+        let mut z = self.seed.fetch_add(0x9e37_79b9_7f4a_7c15, Ordering::Relaxed);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z.trailing_ones() as usize + 1).min(MAX_LEVEL)
+    }
+
+    /// The last node before `key` and the node after it, at every level,
+    /// plus the highest level at which a node with `key` was found.
+    #[allow(clippy::type_complexity)]
+    fn find(&self, key: &K) -> (Vec<Arc<Node<K, V>>>, Vec<Option<Arc<Node<K, V>>>>, Option<usize>) {
+        let mut preds = vec![self.head.clone(); MAX_LEVEL];
+        let mut succs = vec![None; MAX_LEVEL];
+        let mut found = None;
+        let mut pred = self.head.clone();
+        for level in (0..MAX_LEVEL).rev() {
+            let mut curr = pred.next(level);
+            while let Some(node) = curr.clone().filter(|node| node.is_before(key)) {
+                curr = node.next(level);
+                pred = node;
+            }
+            if found.is_none() && curr.as_ref().is_some_and(|node| node.key.as_ref() == Some(key)) {
+                found = Some(level);
+            }
+            preds[level] = pred.clone();
+            succs[level] = curr;
+        }
+        (preds, succs, found)
+    }
+
+    /// Lock each distinct predecessor below `height`, bottom up, and check
+    /// that none has been removed or relinked since the search. Every
+    /// writer locks in that order, so they can't deadlock.
+    fn lock_preds<'a>(
+        preds: &'a [Arc<Node<K, V>>],
+        height: usize,
+        still_valid: impl Fn(usize, &Node<K, V>) -> bool,
+    ) -> Option<Vec<MutexGuard<'a, ()>>> {
+        let mut guards = Vec::new();
+        for level in 0..height {
+            let pred = &preds[level];
+            if level == 0 || !Arc::ptr_eq(pred, &preds[level - 1]) {
+                guards.push(pred.lock.lock().unwrap());
+            }
+            if pred.is_marked() || !still_valid(level, pred) {
+                return None;
+            }
+        }
+        Some(guards)
+    }
+
+    /// Insert or replace the value for `key`, returning the old one.
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        let height = self.random_height();
+        loop {
+            let (preds, succs, found) = self.find(&key);
+            if let Some(level) = found {
+                let node = succs[level].clone().unwrap();
+                if !node.is_marked() {
+                    // Another insert of this key may still be linking it in
+                    while !node.fully_linked.load(Ordering::Acquire) {
+                        thread::yield_now();
+                    }
+                    let mut slot = node.value.lock().unwrap();
+                    // A remove may have claimed it while we waited
+                    if !node.is_marked() {
+                        return slot.replace(value);
+                    }
+                }
+                // A remove is unlinking it; let it finish
+                thread::yield_now();
+                continue;
+            }
+            let Some(guards) = Self::lock_preds(&preds, height, |level, pred| {
+                succs[level].as_ref().is_none_or(|succ| !succ.is_marked()) && same(&pred.next(level), &succs[level])
+            }) else {
+                thread::yield_now();
+                continue;
+            };
+            let node = Node::new(Some(key), Some(value), height);
+            for (link, succ) in node.next.iter().zip(&succs) {
+                *link.write().unwrap() = succ.clone();
+            }
+            for (level, pred) in preds.iter().enumerate().take(height) {
+                *pred.next[level].write().unwrap() = Some(node.clone());
+            }
+            node.fully_linked.store(true, Ordering::Release);
+            self.len.fetch_add(1, Ordering::Relaxed);
+            drop(guards);
+            return None;
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let (_, succs, found) = self.find(key);
+        let node = succs[found?].clone()?;
+        if !node.fully_linked.load(Ordering::Acquire) || node.is_marked() {
+            return None;
+        }
+        node.value.lock().unwrap().clone()
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn remove(&self, key: &K) -> Option<V> {
+        // Marking the node claims it. Writers check for the mark after
+        // locking a predecessor, so once it is set nobody links anything
+        // after the victim and its links stay put until we unlink it.
+        let mut victim: Option<Arc<Node<K, V>>> = None;
+        loop {
+            let (preds, succs, found) = self.find(key);
+            let victim = match &victim {
+                Some(node) => node,
+                None => {
+                    let node = succs[found?].clone()?;
+                    // Only a fully linked node found at its top level is safe
+                    // to take; anything else is mid-insert or being removed
+                    if !node.fully_linked.load(Ordering::Acquire) || found != Some(node.next.len() - 1) {
+                        return None;
+                    }
+                    let _guard = node.lock.lock().unwrap();
+                    if node.is_marked() {
+                        return None;
+                    }
+                    node.marked.store(true, Ordering::Release);
+                    drop(_guard);
+                    victim.insert(node)
+                }
+            };
+            let height = victim.next.len();
+            let Some(guards) = Self::lock_preds(&preds, height, |level, pred| {
+                pred.next(level).is_some_and(|next| Arc::ptr_eq(&next, victim))
+            }) else {
+                thread::yield_now();
+                continue;
+            };
+            for level in (0..height).rev() {
+                *preds[level].next[level].write().unwrap() = victim.next(level);
+            }
+            drop(guards);
+            self.len.fetch_sub(1, Ordering::Relaxed);
+            return victim.value.lock().unwrap().take();
+        }
+    }
+
+    /// Entries with keys in `range`, in order.
+    ///
+    /// The iterator is weakly consistent: it never yields a key twice or out
+    /// of order, and sees every entry present for its whole lifetime, but
+    /// may or may not see entries inserted or removed while it runs.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> Range<'_, K, V, R> {
+        let curr = match range.start_bound() {
+            Bound::Unbounded => self.head.next(0),
+            Bound::Included(start) | Bound::Excluded(start) => {
+                let (preds, _, _) = self.find(start);
+                preds[0].next(0)
+            }
+        };
+        Range { curr, range, _map: self }
+    }
+
+    pub fn iter(&self) -> Range<'_, K, V, (Bound<K>, Bound<K>)> {
+        self.range((Bound::Unbounded, Bound::Unbounded))
+    }
+}
+
+impl<K: Ord, V: Clone> Default for ConcurrentSkipListMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> Drop for ConcurrentSkipListMap<K, V> {
+    // Unlink front to back so dropping a long chain of nodes doesn't recurse.
+    // Each node's other references come from earlier nodes, which have
+    // already been cleared by the time it is reached.
+    fn drop(&mut self) {
+        for link in &self.head.next[1..] {
+            link.write().unwrap().take();
+        }
+        let mut curr = self.head.next[0].write().unwrap().take();
+        while let Some(node) = curr {
+            for link in &node.next[1..] {
+                link.write().unwrap().take();
+            }
+            curr = node.next[0].write().unwrap().take();
+        }
+    }
+}
+
+pub struct Range<'a, K, V, R> {
+    curr: Option<Arc<Node<K, V>>>,
+    range: R,
+    // Ties the iterator to the map so the map can't be dropped under it
+    _map: &'a ConcurrentSkipListMap<K, V>,
+}
+
+impl<K: Ord + Clone, V: Clone, R: RangeBounds<K>> Iterator for Range<'_, K, V, R> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let node = self.curr.take()?;
+            self.curr = node.next(0);
+            let key = node.key.as_ref().unwrap();
+            let past_end = match self.range.end_bound() {
+                Bound::Included(end) => key > end,
+                Bound::Excluded(end) => key >= end,
+                Bound::Unbounded => false,
+            };
+            if past_end {
+                self.curr = None;
+                return None;
+            }
+            if !self.range.contains(key) || node.is_marked() || !node.fully_linked.load(Ordering::Acquire) {
+                continue;
+            }
+            if let Some(value) = node.value.lock().unwrap().clone() {
+                return Some((key.clone(), value));
+            }
+        }
+    }
+}
+
+impl<K: Ord + Clone + Debug, V: Clone + Debug> Debug for ConcurrentSkipListMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Walks the bottom level, so only a snapshot while other threads are
+/// writing. Includes the head sentinel.
+impl<K: HeapSize, V: HeapSize> HeapSize for ConcurrentSkipListMap<K, V> {
+    fn heap_size(&self) -> usize {
+        let mut size = 0;
+        let mut curr = Some(self.head.clone());
+        while let Some(node) = curr {
+            size += rc_size::<Node<K, V>>()
+                + node.next.capacity() * size_of::<Link<K, V>>()
+                + node.key.heap_size()
+                + node.value.lock().unwrap().heap_size();
+            curr = node.next[0].read().unwrap().clone();
+        }
+        size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+
+    use crate::heap_size::HeapSize;
+
+    use super::ConcurrentSkipListMap;
+
+    #[test]
+    fn test_insert_get_remove() {
+        let map = ConcurrentSkipListMap::new();
+        assert_eq!(map.insert(5, "five"), None);
+        assert_eq!(map.insert(1, "one"), None);
+        assert_eq!(map.insert(3, "three"), None);
+        assert_eq!(map.insert(3, "THREE"), Some("three"));
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get(&3), Some("THREE"));
+        assert_eq!(map.get(&4), None);
+
+        assert_eq!(map.remove(&3), Some("THREE"));
+        assert_eq!(map.remove(&3), None);
+        assert!(!map.contains_key(&3));
+        assert_eq!(map.len(), 2);
+        assert_eq!(format!("{:?}", map), r#"{1: "one", 5: "five"}"#);
+    }
+
+    #[test]
+    fn test_range() {
+        let map = ConcurrentSkipListMap::new();
+        for i in (0..100).rev() {
+            map.insert(i, i * 10);
+        }
+        assert_eq!(map.range(10..13).collect::<Vec<_>>(), vec![(10, 100), (11, 110), (12, 120)]);
+        assert_eq!(map.range(97..).map(|(k, _)| k).collect::<Vec<_>>(), vec![97, 98, 99]);
+        assert_eq!(map.range(..=1).count(), 2);
+        assert!(map.iter().map(|(k, _)| k).eq(0..100));
+    }
+
+    #[test]
+    fn test_concurrent_writers() {
+        let map = Arc::new(ConcurrentSkipListMap::new());
+        let writers: Vec<_> = (0..4)
+            .map(|t| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    // Every thread inserts 0..2000; odd threads remove the
+                    // multiples of 3 again
+                    for i in 0..2000 {
+                        map.insert(i, t);
+                        if t % 2 == 1 && i % 3 == 0 {
+                            map.remove(&i);
+                        }
+                    }
+                })
+            })
+            .collect();
+        let reader = {
+            let map = map.clone();
+            thread::spawn(move || {
+                for _ in 0..20 {
+                    let keys: Vec<_> = map.iter().map(|(k, _)| k).collect();
+                    assert!(keys.windows(2).all(|w| w[0] < w[1]));
+                }
+            })
+        };
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        reader.join().unwrap();
+
+        let keys: Vec<_> = map.iter().map(|(k, _)| k).collect();
+        assert_eq!(keys.len(), map.len());
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+        assert!((0..2000).filter(|i| i % 3 != 0).all(|i| map.contains_key(&i)));
+    }
+
+    #[test]
+    fn test_racing_removes() {
+        let map = Arc::new(ConcurrentSkipListMap::new());
+        for i in 0..5000 {
+            map.insert(i, i);
+        }
+        let removers: Vec<_> = (0..4)
+            .map(|_| {
+                let map = map.clone();
+                thread::spawn(move || (0..5000).filter(|i| map.remove(i).is_some()).count())
+            })
+            .collect();
+        let removed: usize = removers.into_iter().map(|r| r.join().unwrap()).sum();
+        // Each key is removed by exactly one thread
+        assert_eq!(removed, 5000);
+        assert!(map.is_empty());
+        assert_eq!(map.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_long_map_drop() {
+        let map = ConcurrentSkipListMap::new();
+        for i in 0..100_000 {
+            map.insert(i, ());
+        }
+        drop(map);
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let map = ConcurrentSkipListMap::new();
+        let empty = map.heap_size();
+        map.insert(1, String::from("abc"));
+        assert!(map.heap_size() > empty + 3);
+        // A removed node is unlinked and no longer counted
+        map.remove(&1);
+        assert_eq!(map.heap_size(), empty);
+    }
+}
@@ -0,0 +1,216 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::marker::PhantomData;
+
+use crate::heap_size::HeapSize;
+
+/// Approximate frequency counts for a stream of items in fixed space.
+///
+/// Each of `depth` rows hashes an item to one of `width` counters. Estimates
+/// take the smallest of an item's counters, so they never undercount, and
+/// with `width = e / epsilon` and `depth = ln(1 / delta)` they overcount by
+/// more than `epsilon * total` with probability at most `delta`.
+///
+/// In conservative-update mode an addition only raises counters as far as the
+/// item's new estimate instead of adding to all of them, which tightens
+/// estimates for infrequent items. Merged sketches remain upper bounds either
+/// way.
+pub struct CountMinSketch<T: ?Sized> {
+    counters: Vec<u64>,
+    width: usize,
+    depth: usize,
+    total: u64,
+    conservative: bool,
+    _marker: PhantomData<fn(&T)>,
+}
+
+impl<T: Hash + ?Sized> CountMinSketch<T> {
+    /// Panics if `width` or `depth` is zero.
+    pub fn new(width: usize, depth: usize) -> Self {
+        assert!(width > 0 && depth > 0, "count-min sketch needs at least one row and column");
+        CountMinSketch {
+            counters: vec![0; width * depth],
+            width,
+            depth,
+            total: 0,
+            conservative: false,
+            _marker: PhantomData,
+        }
+    }
+
+    /// A sketch whose estimates exceed the true count by more than
+    /// `epsilon * total` with probability at most `delta`.
+    pub fn with_error(epsilon: f64, delta: f64) -> Self {
+        let width = (std::f64::consts::E / epsilon).ceil() as usize;
+        let depth = (1.0 / delta).ln().ceil() as usize;
+        Self::new(width, depth.max(1))
+    }
+
+    /// Switch to conservative updates for all later additions.
+    pub fn conservative(mut self) -> Self {
+        self.conservative = true;
+        self
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Sum of all counts added.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    fn cells(&self, item: &T) -> impl Iterator<Item = usize> + use<T> {
+        let width = self.width;
+        let mut hasher = DefaultHasher::new();
+        item.hash(&mut hasher);
+        let hash = hasher.finish();
+        (0..self.depth).map(move |row| {
+            let mut hasher = DefaultHasher::new();
+            hasher.write_u64(hash);
+            hasher.write_usize(row);
+            row * width + (hasher.finish() % width as u64) as usize
+        })
+    }
+
+    pub fn add(&mut self, item: &T, count: u64) {
+        self.total += count;
+        if self.conservative {
+            let target = self.estimate(item) + count;
+            for cell in self.cells(item) {
+                self.counters[cell] = self.counters[cell].max(target);
+            }
+        } else {
+            for cell in self.cells(item) {
+                self.counters[cell] += count;
+            }
+        }
+    }
+
+    pub fn increment(&mut self, item: &T) {
+        self.add(item, 1);
+    }
+
+    /// An upper bound on how many times `item` has been added.
+    pub fn estimate(&self, item: &T) -> u64 {
+        self.cells(item).map(|cell| self.counters[cell]).min().unwrap()
+    }
+
+    /// Add the counts from `other`, which must have the same dimensions.
+    ///
+    /// Panics if the width or depth differs.
+    pub fn merge(&mut self, other: &CountMinSketch<T>) {
+        assert!(
+            self.width == other.width && self.depth == other.depth,
+            "merged sketches must have the same dimensions"
+        );
+        for (a, b) in self.counters.iter_mut().zip(&other.counters) {
+            *a += b;
+        }
+        self.total += other.total;
+    }
+
+    pub fn clear(&mut self) {
+        self.counters.fill(0);
+        self.total = 0;
+    }
+}
+
+impl<T: ?Sized> HeapSize for CountMinSketch<T> {
+    fn heap_size(&self) -> usize {
+        self.counters.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heap_size::HeapSize;
+
+    use super::CountMinSketch;
+
+    // Item i occurs 1000 / (i + 1) times
+    fn zipf_stream() -> Vec<u32> {
+        (0..1000u32).flat_map(|i| std::iter::repeat_n(i, 1000 / (i as usize + 1))).collect()
+    }
+
+    #[test]
+    fn test_exact_when_sparse() {
+        let mut sketch = CountMinSketch::new(1000, 5);
+        sketch.add("a", 3);
+        sketch.increment("b");
+        sketch.increment("a");
+        assert_eq!(sketch.estimate("a"), 4);
+        assert_eq!(sketch.estimate("b"), 1);
+        assert_eq!(sketch.estimate("c"), 0);
+        assert_eq!(sketch.total(), 5);
+        sketch.clear();
+        assert_eq!(sketch.estimate("a"), 0);
+    }
+
+    #[test]
+    fn test_error_bound() {
+        let mut sketch = CountMinSketch::with_error(0.001, 0.01);
+        assert_eq!(sketch.width(), 2719);
+        assert_eq!(sketch.depth(), 5);
+        let stream = zipf_stream();
+        for item in &stream {
+            sketch.increment(item);
+        }
+        let slack = (0.001 * sketch.total() as f64) as u64;
+        for i in 0..1000u32 {
+            let truth = 1000 / (i as u64 + 1);
+            let estimate = sketch.estimate(&i);
+            assert!(estimate >= truth);
+            assert!(estimate <= truth + slack, "item {i}: {estimate} vs {truth}");
+        }
+    }
+
+    #[test]
+    fn test_conservative_is_tighter() {
+        let mut plain = CountMinSketch::new(64, 3);
+        let mut conservative = CountMinSketch::new(64, 3).conservative();
+        for item in zipf_stream() {
+            plain.increment(&item);
+            conservative.increment(&item);
+        }
+        let (mut plain_err, mut conservative_err) = (0, 0);
+        for i in 0..1000u32 {
+            let truth = 1000 / (i as u64 + 1);
+            assert!(conservative.estimate(&i) >= truth);
+            assert!(conservative.estimate(&i) <= plain.estimate(&i));
+            plain_err += plain.estimate(&i) - truth;
+            conservative_err += conservative.estimate(&i) - truth;
+        }
+        assert!(conservative_err < plain_err);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut a = CountMinSketch::new(100, 4);
+        let mut b = CountMinSketch::new(100, 4);
+        a.add(&1, 10);
+        b.add(&1, 5);
+        b.add(&2, 7);
+        a.merge(&b);
+        assert_eq!(a.estimate(&1), 15);
+        assert_eq!(a.estimate(&2), 7);
+        assert_eq!(a.total(), 22);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_merge_mismatched() {
+        let mut a: CountMinSketch<i32> = CountMinSketch::new(100, 4);
+        a.merge(&CountMinSketch::new(100, 5));
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let sketch = CountMinSketch::<str>::new(100, 4);
+        assert_eq!(sketch.heap_size(), 400 * size_of::<u64>());
+    }
+}
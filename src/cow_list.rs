@@ -0,0 +1,324 @@
+use std::fmt::Debug;
+use std::rc::Rc;
+
+use crate::error::{CollectionError, check_position};
+use crate::heap_size::{HeapSize, payload, rc_size};
+
+#[derive(Clone)]
+struct Node<T> {
+    data: T,
+    next: Option<Rc<Node<T>>>,
+}
+
+/// A singly linked list whose clones share their nodes.
+///
+/// Cloning copies one pointer. Mutating through a clone goes through
+/// `Rc::make_mut`, so only the nodes from the head down to the one being
+/// changed are copied, and only if they are still shared; everything after
+/// it stays shared with the other clones. Pushing and popping at the front
+/// never copy anything but the popped element.
+pub struct CowList<T> {
+    head: Option<Rc<Node<T>>>,
+    len: usize,
+}
+
+impl<T> CowList<T> {
+    pub const fn new() -> Self {
+        CowList { head: None, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push_front(&mut self, val: T) {
+        let next = self.head.take();
+        self.head = Some(Rc::new(Node { data: val, next }));
+        self.len += 1;
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.head.as_ref().map(|node| &node.data)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.iter().nth(index)
+    }
+
+    pub fn clear(&mut self) {
+        *self = CowList::new();
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { curr: self.head.as_deref() }
+    }
+}
+
+impl<T: Clone> CowList<T> {
+    /// The link pointing at node `index`, unsharing every node before it.
+    fn link_mut(&mut self, index: usize) -> &mut Option<Rc<Node<T>>> {
+        let mut link = &mut self.head;
+        for _ in 0..index {
+            link = &mut Rc::make_mut(link.as_mut().unwrap()).next;
+        }
+        link
+    }
+
+    /// Remove the first element. Copies it only if another clone still
+    /// shares its node.
+    pub fn pop_front(&mut self) -> Option<T> {
+        let head = self.head.take()?;
+        let node = Rc::try_unwrap(head).unwrap_or_else(|shared| (*shared).clone());
+        self.head = node.next;
+        self.len -= 1;
+        Some(node.data)
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        self.get_mut(0)
+    }
+
+    /// Mutable access to element `index`, copying the nodes up to and
+    /// including it if they are shared.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index >= self.len {
+            return None;
+        }
+        let link = self.link_mut(index);
+        Some(&mut Rc::make_mut(link.as_mut().unwrap()).data)
+    }
+
+    /// Insert `val` so it becomes element `index`.
+    ///
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, val: T) {
+        assert!(index <= self.len, "insertion index {index} out of bounds for length {}", self.len);
+        let link = self.link_mut(index);
+        let next = link.take();
+        *link = Some(Rc::new(Node { data: val, next }));
+        self.len += 1;
+    }
+
+    /// `insert`, but returning an error if `index > len`.
+    pub fn try_insert(&mut self, index: usize, val: T) -> Result<(), CollectionError> {
+        check_position(index, self.len)?;
+        self.insert(index, val);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        if index >= self.len {
+            return None;
+        }
+        let link = self.link_mut(index);
+        let node = link.take().unwrap();
+        let node = Rc::try_unwrap(node).unwrap_or_else(|shared| (*shared).clone());
+        *link = node.next;
+        self.len -= 1;
+        Some(node.data)
+    }
+}
+
+impl<T> Clone for CowList<T> {
+    fn clone(&self) -> Self {
+        CowList { head: self.head.clone(), len: self.len }
+    }
+}
+
+impl<T> Drop for CowList<T> {
+    // Unlink iteratively so long lists don't overflow the stack, stopping at
+    // the first node another clone still holds
+    fn drop(&mut self) {
+        let mut next = self.head.take();
+        while let Some(node) = next {
+            match Rc::try_unwrap(node) {
+                Ok(mut node) => next = node.next.take(),
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+impl<T> Default for CowList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Iter<'a, T> {
+    curr: Option<&'a Node<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.curr?;
+        self.curr = node.next.as_deref();
+        Some(&node.data)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a CowList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T> FromIterator<T> for CowList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let items: Vec<T> = iter.into_iter().collect();
+        let mut list = CowList::new();
+        for val in items.into_iter().rev() {
+            list.push_front(val);
+        }
+        list
+    }
+}
+
+impl<T: PartialEq> PartialEq for CowList<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for CowList<T> {}
+
+impl<T: Debug> Debug for CowList<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Counts every node the list can reach, including ones it shares with
+/// other lists.
+impl<T: HeapSize> HeapSize for CowList<T> {
+    fn heap_size(&self) -> usize {
+        self.len * rc_size::<Node<T>>() + payload(self.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::error::CollectionError;
+    use crate::heap_size::{HeapSize, rc_size};
+
+    use super::{CowList, Node};
+
+    fn node_at<T>(list: &CowList<T>, index: usize) -> &Rc<Node<T>> {
+        let mut node = list.head.as_ref().unwrap();
+        for _ in 0..index {
+            node = node.next.as_ref().unwrap();
+        }
+        node
+    }
+
+    #[test]
+    fn test_push_pop() {
+        let mut list = CowList::new();
+        list.push_front(2);
+        list.push_front(1);
+        assert_eq!(list.len(), 2);
+        assert_eq!(list.front(), Some(&1));
+        assert_eq!(list.pop_front(), Some(1));
+        assert_eq!(list.pop_front(), Some(2));
+        assert_eq!(list.pop_front(), None);
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn test_clone_shares_until_mutated() {
+        let original: CowList<i32> = (0..6).collect();
+        let mut copy = original.clone();
+        assert!(Rc::ptr_eq(node_at(&original, 0), node_at(&copy, 0)));
+
+        *copy.get_mut(2).unwrap() = 20;
+        assert_eq!(original.iter().copied().collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(copy.iter().copied().collect::<Vec<_>>(), vec![0, 1, 20, 3, 4, 5]);
+        // The spine up to the change was copied, the rest is still shared
+        assert!(!Rc::ptr_eq(node_at(&original, 2), node_at(&copy, 2)));
+        assert!(Rc::ptr_eq(node_at(&original, 3), node_at(&copy, 3)));
+
+        // Once unshared, further edits to the same prefix copy nothing
+        let before = Rc::as_ptr(node_at(&copy, 1));
+        *copy.front_mut().unwrap() = 10;
+        *copy.get_mut(1).unwrap() = 11;
+        assert_eq!(Rc::as_ptr(node_at(&copy, 1)), before);
+        assert_eq!(copy.get_mut(6), None);
+    }
+
+    #[test]
+    fn test_insert_remove() {
+        let original: CowList<char> = "abcd".chars().collect();
+        let mut copy = original.clone();
+        copy.insert(2, 'x');
+        copy.insert(5, 'z');
+        assert_eq!(copy.remove(0), Some('a'));
+        assert_eq!(copy.remove(9), None);
+        assert_eq!(copy.iter().collect::<String>(), "bxcdz");
+        assert_eq!(copy.len(), 5);
+        assert_eq!(original.iter().collect::<String>(), "abcd");
+        assert_eq!(copy.get(1), Some(&'x'));
+
+        let mut popped = original.clone();
+        assert_eq!(popped.pop_front(), Some('a'));
+        assert_eq!(original.front(), Some(&'a'));
+        assert_ne!(popped, original);
+        popped.push_front('a');
+        assert_eq!(popped, original);
+        assert_eq!(popped.try_insert(5, 'e'), Err(CollectionError::IndexOutOfBounds { index: 5, len: 4 }));
+        assert_eq!(popped.try_insert(4, 'e'), Ok(()));
+        assert_eq!(popped.iter().collect::<String>(), "abcde");
+    }
+
+    #[test]
+    fn test_undo_snapshots() {
+        let mut history = Vec::new();
+        let mut doc: CowList<u32> = (0..1000).collect();
+        for i in 0..100 {
+            history.push(doc.clone());
+            *doc.get_mut(i).unwrap() += 1000;
+        }
+        assert_eq!(doc.iter().take(3).copied().collect::<Vec<_>>(), vec![1000, 1001, 1002]);
+        assert_eq!(history[50].get(49), Some(&1049));
+        assert_eq!(history[50].get(50), Some(&50));
+        drop(history);
+        assert_eq!(doc.len(), 1000);
+    }
+
+    #[test]
+    fn test_long_list_drop() {
+        let list: CowList<u32> = (0..1_000_000).collect();
+        let copy = list.clone();
+        drop(list);
+        assert_eq!(copy.len(), 1_000_000);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_insert_out_of_bounds() {
+        let mut list: CowList<i32> = CowList::new();
+        list.insert(1, 0);
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let mut list = CowList::new();
+        list.push_front(String::from("ab"));
+        list.push_front(String::from("c"));
+        assert_eq!(list.heap_size(), 2 * rc_size::<Node<String>>() + 3);
+        // A copy counts the nodes it shares too
+        let mut copy = list.clone();
+        copy.pop_front();
+        assert_eq!(copy.heap_size(), rc_size::<Node<String>>() + 2);
+    }
+}
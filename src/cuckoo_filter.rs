@@ -0,0 +1,220 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::marker::PhantomData;
+
+use crate::heap_size::HeapSize;
+use crate::rng::XorShift64;
+
+const BUCKET_SIZE: usize = 4;
+// Marks an empty slot; real fingerprints are never zero
+const EMPTY: u16 = 0;
+
+/// Returned by `CuckooFilter::insert` when no slot could be freed within the
+/// eviction limit. The filter is left exactly as it was.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Full;
+
+/// A probabilistic set supporting deletion, storing a 16-bit fingerprint of
+/// each item in one of two candidate buckets of four slots.
+///
+/// The two buckets for a fingerprint are `i` and `i ^ hash(fingerprint)`, so
+/// either can be found from the other without the original item. When both
+/// are full, a random resident is kicked to its alternate bucket, repeating up
+/// to `max_kicks` times. If that fails every kick is undone and `insert`
+/// returns `Full`, so nothing already stored is lost.
+///
+/// Only remove items that were inserted: removing anything else may delete
+/// another item's matching fingerprint.
+pub struct CuckooFilter<T: ?Sized> {
+    buckets: Vec<[u16; BUCKET_SIZE]>,
+    len: usize,
+    max_kicks: usize,
+    rng: XorShift64,
+    _marker: PhantomData<fn(&T)>,
+}
+
+impl<T: Hash + ?Sized> CuckooFilter<T> {
+    /// A filter able to hold about `capacity` items.
+    pub fn new(capacity: usize) -> Self {
+        Self::with_max_kicks(capacity, 500)
+    }
+
+    /// A filter able to hold about `capacity` items, giving up on an insert
+    /// after `max_kicks` evictions.
+    pub fn with_max_kicks(capacity: usize, max_kicks: usize) -> Self {
+        // Bucket counts are a power of two so alternate indices can be
+        // computed by masking; leave headroom since inserts start failing
+        // around 95% load
+        let buckets = (capacity * 100 / 95).div_ceil(BUCKET_SIZE).max(1).next_power_of_two();
+        CuckooFilter {
+            buckets: vec![[EMPTY; BUCKET_SIZE]; buckets],
+            len: 0,
+            max_kicks,
+            rng: XorShift64::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of fingerprint slots.
+    pub fn capacity(&self) -> usize {
+        self.buckets.len() * BUCKET_SIZE
+    }
+
+    pub fn load_factor(&self) -> f64 {
+        self.len as f64 / self.capacity() as f64
+    }
+
+    fn index_and_fingerprint(&self, item: &T) -> (usize, u16) {
+        let mut hasher = DefaultHasher::new();
+        item.hash(&mut hasher);
+        let hash = hasher.finish();
+        let fingerprint = (hash >> 48) as u16;
+        (hash as usize & (self.buckets.len() - 1), fingerprint.max(1))
+    }
+
+    fn alt_index(&self, i: usize, fingerprint: u16) -> usize {
+        let hash = (fingerprint as u64).wrapping_mul(0x5bd1_e995_9e37_79b9) >> 32;
+        (i ^ hash as usize) & (self.buckets.len() - 1)
+    }
+
+    fn put(&mut self, i: usize, fingerprint: u16) -> bool {
+        let Some(slot) = self.buckets[i].iter_mut().find(|f| **f == EMPTY) else {
+            return false;
+        };
+        *slot = fingerprint;
+        self.len += 1;
+        true
+    }
+
+    /// Add `item`. Inserting the same item twice stores it twice.
+    pub fn insert(&mut self, item: &T) -> Result<(), Full> {
+        let (i1, mut fingerprint) = self.index_and_fingerprint(item);
+        let i2 = self.alt_index(i1, fingerprint);
+        if self.put(i1, fingerprint) || self.put(i2, fingerprint) {
+            return Ok(());
+        }
+        let mut i = if self.rng.next_u64() & 1 == 0 { i1 } else { i2 };
+        let mut path = Vec::new();
+        for _ in 0..self.max_kicks {
+            let slot = (self.rng.next_u64() % BUCKET_SIZE as u64) as usize;
+            std::mem::swap(&mut fingerprint, &mut self.buckets[i][slot]);
+            path.push((i, slot));
+            i = self.alt_index(i, fingerprint);
+            if self.put(i, fingerprint) {
+                return Ok(());
+            }
+        }
+        // Put every evicted fingerprint back where it came from
+        for (i, slot) in path.into_iter().rev() {
+            std::mem::swap(&mut fingerprint, &mut self.buckets[i][slot]);
+        }
+        Err(Full)
+    }
+
+    /// Whether `item` may have been inserted. `false` is always correct.
+    pub fn contains(&self, item: &T) -> bool {
+        let (i1, fingerprint) = self.index_and_fingerprint(item);
+        let i2 = self.alt_index(i1, fingerprint);
+        self.buckets[i1].contains(&fingerprint) || self.buckets[i2].contains(&fingerprint)
+    }
+
+    /// Remove one copy of `item`, returning whether a matching fingerprint
+    /// was found.
+    pub fn remove(&mut self, item: &T) -> bool {
+        let (i1, fingerprint) = self.index_and_fingerprint(item);
+        let i2 = self.alt_index(i1, fingerprint);
+        for i in [i1, i2] {
+            if let Some(slot) = self.buckets[i].iter_mut().find(|f| **f == fingerprint) {
+                *slot = EMPTY;
+                self.len -= 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn clear(&mut self) {
+        self.buckets.fill([EMPTY; BUCKET_SIZE]);
+        self.len = 0;
+    }
+}
+
+impl<T: ?Sized> HeapSize for CuckooFilter<T> {
+    fn heap_size(&self) -> usize {
+        self.buckets.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CuckooFilter, Full};
+
+    #[test]
+    fn test_insert_contains_remove() {
+        let mut filter = CuckooFilter::new(100);
+        assert!(filter.is_empty());
+        for i in 0..100 {
+            assert_eq!(filter.insert(&i), Ok(()));
+        }
+        assert_eq!(filter.len(), 100);
+        assert!((0..100).all(|i| filter.contains(&i)));
+
+        for i in 0..50 {
+            assert!(filter.remove(&i));
+        }
+        assert_eq!(filter.len(), 50);
+        assert!((50..100).all(|i| filter.contains(&i)));
+        assert!((0..50).filter(|i| filter.contains(i)).count() < 5);
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let mut filter = CuckooFilter::new(10);
+        filter.insert("x").unwrap();
+        filter.insert("x").unwrap();
+        assert_eq!(filter.len(), 2);
+        assert!(filter.remove("x"));
+        assert!(filter.contains("x"));
+        assert!(filter.remove("x"));
+        assert!(!filter.contains("x"));
+        assert!(!filter.remove("x"));
+    }
+
+    #[test]
+    fn test_false_positive_rate() {
+        let mut filter = CuckooFilter::new(10_000);
+        for i in 0..10_000u32 {
+            filter.insert(&i).unwrap();
+        }
+        let false_positives = (10_000..110_000u32).filter(|i| filter.contains(i)).count();
+        assert!(false_positives < 100, "{false_positives} false positives");
+    }
+
+    #[test]
+    fn test_full_is_graceful() {
+        let mut filter = CuckooFilter::with_max_kicks(64, 50);
+        let mut inserted = Vec::new();
+        for i in 0..1000u32 {
+            match filter.insert(&i) {
+                Ok(()) => inserted.push(i),
+                Err(Full) => break,
+            }
+        }
+        assert!(inserted.len() < 1000);
+        assert!(filter.load_factor() > 0.5);
+        assert_eq!(filter.len(), inserted.len());
+        // A failed insert must not lose anything already stored
+        assert!(inserted.iter().all(|i| filter.contains(i)));
+
+        filter.clear();
+        assert!(filter.is_empty());
+        assert!(!inserted.iter().any(|i| filter.contains(i)));
+    }
+}
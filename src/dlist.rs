@@ -0,0 +1,278 @@
+use std::fmt::Debug;
+
+use crate::heap_size::HeapSize;
+use crate::list::List;
+
+enum Tree<T> {
+    Leaf(Vec<T>),
+    // The elements of the left tree, then those of the right
+    Join(Box<Tree<T>>, Box<Tree<T>>),
+}
+
+/// A builder for long sequences out of many fragments, where appending or
+/// prepending a whole fragment takes O(1) and the finished sequence is
+/// produced in one O(n) pass.
+///
+/// Like the difference lists of functional languages it postpones the
+/// copying: each append or prepend just joins the two sides under a new
+/// node, so building from k fragments never recopies earlier ones, as
+/// repeatedly concatenating `Vec`s or `List`s would. Single elements pushed
+/// at the back go straight into the last fragment.
+pub struct DList<T> {
+    root: Tree<T>,
+    len: usize,
+}
+
+impl<T> DList<T> {
+    pub const fn new() -> Self {
+        DList { root: Tree::Leaf(Vec::new()), len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn take_root(&mut self) -> Tree<T> {
+        self.len = 0;
+        std::mem::replace(&mut self.root, Tree::Leaf(Vec::new()))
+    }
+
+    pub fn push_back(&mut self, val: T) {
+        self.len += 1;
+        match &mut self.root {
+            Tree::Leaf(vals) => vals.push(val),
+            Tree::Join(_, right) => match right.as_mut() {
+                Tree::Leaf(vals) => vals.push(val),
+                Tree::Join(..) => {
+                    let root = std::mem::replace(&mut self.root, Tree::Leaf(Vec::new()));
+                    self.root = Tree::Join(Box::new(root), Box::new(Tree::Leaf(vec![val])));
+                }
+            },
+        }
+    }
+
+    pub fn push_front(&mut self, val: T) {
+        self.prepend(DList::from(vec![val]));
+    }
+
+    /// Add all of `other` after the current elements, in O(1).
+    pub fn append(&mut self, mut other: DList<T>) {
+        if other.is_empty() {
+            return;
+        }
+        let len = self.len + other.len;
+        let right = other.take_root();
+        self.root = if self.is_empty() { right } else { Tree::Join(Box::new(self.take_root()), Box::new(right)) };
+        self.len = len;
+    }
+
+    /// Add all of `other` before the current elements, in O(1).
+    pub fn prepend(&mut self, mut other: DList<T>) {
+        std::mem::swap(self, &mut other);
+        self.append(other);
+    }
+
+    /// Every element in order, in a `Vec`.
+    pub fn into_vec(mut self) -> Vec<T> {
+        let mut out = Vec::with_capacity(self.len);
+        let mut stack = vec![self.take_root()];
+        while let Some(tree) = stack.pop() {
+            match tree {
+                Tree::Leaf(vals) => out.extend(vals),
+                Tree::Join(left, right) => {
+                    stack.push(*right);
+                    stack.push(*left);
+                }
+            }
+        }
+        out
+    }
+
+    /// Every element in order, in a `List`. Walks the fragments from the
+    /// back, since a `List` only grows at the front.
+    pub fn into_list(mut self) -> List<T> {
+        let mut list = List::new();
+        let mut stack = vec![self.take_root()];
+        while let Some(tree) = stack.pop() {
+            match tree {
+                Tree::Leaf(vals) => {
+                    for val in vals.into_iter().rev() {
+                        list.push_front(val);
+                    }
+                }
+                Tree::Join(left, right) => {
+                    stack.push(*left);
+                    stack.push(*right);
+                }
+            }
+        }
+        list
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { stack: vec![&self.root], leaf: [].iter(), len: self.len }
+    }
+}
+
+impl<T> Drop for DList<T> {
+    // Take the tree apart iteratively, as a long run of pushes at the front
+    // builds one deep spine of joins
+    fn drop(&mut self) {
+        let mut stack = vec![self.take_root()];
+        while let Some(tree) = stack.pop() {
+            if let Tree::Join(left, right) = tree {
+                stack.push(*left);
+                stack.push(*right);
+            }
+        }
+    }
+}
+
+impl<T> Default for DList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<Vec<T>> for DList<T> {
+    /// A list of one fragment, without copying it.
+    fn from(vals: Vec<T>) -> Self {
+        DList { len: vals.len(), root: Tree::Leaf(vals) }
+    }
+}
+
+impl<T> FromIterator<T> for DList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl<T> Extend<T> for DList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.append(iter.into_iter().collect());
+    }
+}
+
+pub struct Iter<'a, T> {
+    // Subtrees still to visit, the next one on top
+    stack: Vec<&'a Tree<T>>,
+    leaf: std::slice::Iter<'a, T>,
+    len: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(val) = self.leaf.next() {
+                self.len -= 1;
+                return Some(val);
+            }
+            match self.stack.pop()? {
+                Tree::Leaf(vals) => self.leaf = vals.iter(),
+                Tree::Join(left, right) => {
+                    self.stack.push(right);
+                    self.stack.push(left);
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<'a, T> IntoIterator for &'a DList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Debug> Debug for DList<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Tree<T> {
+    fn heap_size(&self) -> usize {
+        match self {
+            Tree::Leaf(elems) => elems.heap_size(),
+            Tree::Join(left, right) => left.heap_size() + right.heap_size(),
+        }
+    }
+}
+
+impl<T: HeapSize> HeapSize for DList<T> {
+    fn heap_size(&self) -> usize {
+        self.root.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DList;
+
+    #[test]
+    fn test_push_and_fragments() {
+        let mut list = DList::new();
+        assert!(list.is_empty());
+        list.push_back(3);
+        list.push_front(2);
+        list.push_back(4);
+        list.append(DList::from(vec![5, 6]));
+        list.prepend((0..2).collect());
+        list.append(DList::new());
+        list.push_back(7);
+        assert_eq!(list.len(), 8);
+        assert_eq!(list.iter().len(), 8);
+        assert_eq!(format!("{list:?}"), "[0, 1, 2, 3, 4, 5, 6, 7]");
+        assert_eq!(list.into_vec(), (0..8).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_into_list() {
+        let mut list: DList<_> = (3..6).collect();
+        list.prepend(DList::from(vec![1, 2]));
+        list.extend(6..8);
+        let list = list.into_list();
+        assert_eq!(list.len(), 7);
+        assert_eq!(list.into_iter().collect::<Vec<_>>(), (1..8).collect::<Vec<_>>());
+        assert!(DList::<i32>::new().into_list().is_empty());
+    }
+
+    #[test]
+    fn test_many_small_fragments() {
+        // Alternate sides so the tree is deep on both
+        let mut list = DList::new();
+        for i in 0..100_000 {
+            if i % 2 == 0 {
+                list.push_front(-i);
+            } else {
+                list.append(DList::from(vec![i]));
+            }
+        }
+        let vals = list.into_vec();
+        assert_eq!(vals.len(), 100_000);
+        assert_eq!((vals[0], vals[49_999], vals[50_000], vals[99_999]), (-99_998, 0, 1, 99_999));
+        assert!(vals.windows(2).all(|w| w[0] < w[1]));
+
+        let mut deep = DList::new();
+        for i in 0..200_000 {
+            deep.push_front(i);
+        }
+        assert_eq!(deep.iter().next(), Some(&199_999));
+        drop(deep);
+    }
+}
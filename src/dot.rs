@@ -0,0 +1,87 @@
+use std::fmt::{self, Write};
+
+/// Export of a tree's shape as a Graphviz `digraph`, for looking at how a
+/// structure balanced itself, e.g. with `dot -Tsvg`.
+///
+/// Each node is labelled with its keys followed by whatever the structure
+/// balances on, such as a treap's priorities and subtree sizes. Edges to
+/// children of binary trees are labelled `L` and `R`.
+pub trait ToDot {
+    fn write_dot(&self, out: &mut dyn Write) -> fmt::Result;
+
+    fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        self.write_dot(&mut dot).unwrap();
+        dot
+    }
+}
+
+/// Writes the statements of a `digraph`, numbering nodes as they are added.
+pub(crate) struct DotWriter<'a> {
+    out: &'a mut dyn Write,
+    nodes: usize,
+}
+
+impl<'a> DotWriter<'a> {
+    pub(crate) fn new(out: &'a mut dyn Write) -> Result<Self, fmt::Error> {
+        writeln!(out, "digraph {{")?;
+        writeln!(out, "    node [shape=box];")?;
+        Ok(DotWriter { out, nodes: 0 })
+    }
+
+    /// Add a node and return its id. Lines of the label are centred.
+    pub(crate) fn node(&mut self, label: &str) -> Result<usize, fmt::Error> {
+        let id = self.nodes;
+        self.nodes += 1;
+        writeln!(self.out, "    n{id} [label=\"{}\"];", escape(label))?;
+        Ok(id)
+    }
+
+    pub(crate) fn edge(&mut self, from: usize, to: usize, label: &str) -> fmt::Result {
+        if label.is_empty() {
+            writeln!(self.out, "    n{from} -> n{to};")
+        } else {
+            writeln!(self.out, "    n{from} -> n{to} [label=\"{}\"];", escape(label))
+        }
+    }
+
+    pub(crate) fn finish(self) -> fmt::Result {
+        writeln!(self.out, "}}")
+    }
+}
+
+// Quote a label for a DOT string, keeping line breaks
+fn escape(label: &str) -> String {
+    let mut escaped = String::with_capacity(label.len());
+    for c in label.chars() {
+        match c {
+            '"' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DotWriter;
+
+    #[test]
+    fn test_writer() {
+        let mut out = String::new();
+        let mut dot = DotWriter::new(&mut out).unwrap();
+        let root = dot.node("\"a\"\nb\\c").unwrap();
+        let child = dot.node("d").unwrap();
+        dot.edge(root, child, "L").unwrap();
+        dot.edge(child, root, "").unwrap();
+        dot.finish().unwrap();
+        assert_eq!(
+            out,
+            "digraph {\n    node [shape=box];\n    n0 [label=\"\\\"a\\\"\\nb\\\\c\"];\n    n1 [label=\"d\"];\n    n0 -> n1 [label=\"L\"];\n    n1 -> n0;\n}\n"
+        );
+    }
+}
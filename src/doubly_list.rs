@@ -1,71 +1,248 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
 use std::fmt::Debug;
+use std::marker::PhantomData;
 use std::rc::{Rc, Weak};
 
-type NodeRef<T> = Rc<RefCell<Node<T>>>;
+use crate::heap_size::{HeapSize, payload, rc_size};
+use crate::list::List;
+use crate::node_pool::NodePool;
+use crate::pretty::{PrettyPrint, arrows};
+use crate::sequence::{LendingCursor, SeqCursor, Sequence};
+use crate::trace;
+use crate::unrolled_list::{self, UnrolledList};
+
+pub(crate) type NodeRef<T> = Rc<RefCell<Node<T>>>;
 type WeakNodeRef<T> = Weak<RefCell<Node<T>>>;
 
-struct Node<T> {
-    data: T,
+// Storage of a list with several elements per node. The node capacity is
+// set at runtime by `with_node_capacity`, so `B` is 0 to rule out `new`.
+type Chunks<T> = UnrolledList<T, 0>;
+
+/// A pool of spare nodes that one or more `LinkedList<T>`s can share.
+pub type LinkedListPool<T> = NodePool<Rc<RefCell<Node<T>>>>;
+
+/// A node of a `LinkedList`, only public so a `LinkedListPool` can name it.
+pub struct Node<T> {
+    pub(crate) data: T,
     next: Option<NodeRef<T>>,
     prev: Option<WeakNodeRef<T>>,
 }
 
-pub struct LinkedList<T: Debug> {
+impl<T> Node<T> {
+    pub(crate) fn next(&self) -> Option<NodeRef<T>> {
+        self.next.clone()
+    }
+
+    #[cfg(feature = "std_cursor")]
+    pub(crate) fn prev(&self) -> Option<NodeRef<T>> {
+        self.prev.as_ref().and_then(Weak::upgrade)
+    }
+}
+
+/// A doubly linked list, by default with one element per node.
+///
+/// A list made by `with_chunk_size` instead keeps up to that many elements
+/// next to each other in each node, for read-heavy use where following a
+/// link per element costs too much. It has the same cursor API, but no node
+/// pool, `split` and `chunk_by` move the elements one by one, and
+/// `search_sorted` walks from the front.
+pub struct LinkedList<T> {
     head: Option<NodeRef<T>>,
     tail: Option<WeakNodeRef<T>>,
+    len: usize,
+    pool: Option<Rc<LinkedListPool<T>>>,
+    // Every few nodes in list order, for `search_sorted` to binary search
+    // over. Inserts only widen the gaps between them, so they survive those,
+    // but anything that takes nodes out drops them.
+    hints: Cell<Vec<WeakNodeRef<T>>>,
+    // The elements of a chunked list, in which case the fields above are
+    // left empty
+    chunks: Option<Chunks<T>>,
 }
 
-impl<T: Debug> LinkedList<T> {
-    pub fn new() -> Self {
-        LinkedList { head: None, tail: None }
+impl<T> LinkedList<T> {
+    pub const fn new() -> Self {
+        LinkedList { head: None, tail: None, len: 0, pool: None, hints: Cell::new(Vec::new()), chunks: None }
+    }
+
+    /// An empty list that stores up to `chunk_size` elements per node. A
+    /// chunk size of 1 gives the usual list.
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn with_chunk_size(chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size must be positive");
+        let mut list = Self::new();
+        if chunk_size > 1 {
+            list.chunks = Some(Chunks::with_node_capacity(chunk_size));
+        }
+        list
+    }
+
+    /// Most elements a node holds: 1 unless the list was made with
+    /// `with_chunk_size`.
+    pub fn chunk_size(&self) -> usize {
+        self.chunks.as_ref().map_or(1, Chunks::node_capacity)
+    }
+
+    /// An empty list that takes its nodes from `pool` and returns them there
+    pub fn with_pool(pool: Rc<LinkedListPool<T>>) -> Self {
+        LinkedList { head: None, tail: None, len: 0, pool: Some(pool), hints: Cell::new(Vec::new()), chunks: None }
+    }
+
+    /// An empty list with a pool of its own, so popped nodes are kept and
+    /// reused by later pushes. Suits queues that push and pop heavily.
+    pub fn pooled() -> Self {
+        Self::with_pool(Rc::new(LinkedListPool::default()))
+    }
+
+    /// Free the spare nodes kept by the list's pool, if it has one. A pool
+    /// shared with other lists loses its spares for all of them.
+    pub fn shrink_pool(&self) {
+        if let Some(pool) = &self.pool {
+            pool.shrink();
+        }
+    }
+
+    /// Number of spare nodes the list's pool holds.
+    pub fn pool_spare_count(&self) -> usize {
+        self.pool.as_ref().map_or(0, |pool| pool.spare_count())
+    }
+
+    fn new_node(&self, node: Node<T>) -> NodeRef<T> {
+        let node = RefCell::new(node);
+        match &self.pool {
+            Some(pool) => pool.alloc(node),
+            None => Rc::new(node),
+        }
+    }
+
+    // Take the element out of an unlinked node, recycling the node if there
+    // is a pool. Returns `None` if other handles to the node are still alive.
+    fn release(&self, node: NodeRef<T>) -> Option<T> {
+        self.hints.take();
+        let node = match &self.pool {
+            Some(pool) => pool.free(node)?,
+            None => Rc::into_inner(node)?,
+        };
+        Some(node.into_inner().data)
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.as_ref().map_or(self.len, Chunks::len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Panic unless every node links back to the one before it, the tail is
+    /// the last node, `len` is right, and the search hints are nodes of the
+    /// list in order. Only in debug builds, tests and Kani proofs.
+    #[cfg(any(test, debug_assertions, kani))]
+    pub fn validate(&self) {
+        if let Some(chunks) = &self.chunks {
+            assert!(self.head.is_none() && self.tail.is_none() && self.len == 0, "chunked list has nodes of its own");
+            chunks.validate();
+            return;
+        }
+        let same = |a: Option<NodeRef<T>>, b: &Option<NodeRef<T>>| match (a, b) {
+            (Some(a), Some(b)) => Rc::ptr_eq(&a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        let hints = self.hints.take();
+        let mut hint = hints.iter().peekable();
+        let mut count = 0;
+        let mut prev = None;
+        let mut curr = self.head.clone();
+        while let Some(node) = curr {
+            assert!(same(node.borrow().prev.as_ref().and_then(Weak::upgrade), &prev), "node {count} has a stale prev link");
+            if hint.peek().is_some_and(|hint| Weak::ptr_eq(hint, &Rc::downgrade(&node))) {
+                hint.next();
+            }
+            count += 1;
+            curr = node.borrow().next.clone();
+            prev = Some(node);
+        }
+        assert!(same(self.tail.as_ref().and_then(Weak::upgrade), &prev), "tail isn't the last node");
+        assert_eq!(count, self.len, "list length is out of date");
+        assert!(hint.next().is_none(), "search hints are out of date");
+        self.hints.set(hints);
     }
 
     pub fn push_front(&mut self, val: T) {
+        if let Some(chunks) = &mut self.chunks {
+            chunks.push_front(val);
+            trace::event("LinkedList", "push_front", chunks.len());
+            return;
+        }
+        self.len += 1;
+        trace::event("LinkedList", "push_front", self.len);
         let Some(old_head) = self.head.take() else {
-            let node = Rc::new(RefCell::new(Node { data: val, next: None, prev: None }));
+            let node = self.new_node(Node { data: val, next: None, prev: None });
             self.tail = Some(Rc::downgrade(&node));
             self.head = Some(node);
             return;
         };
-        let new_head = Rc::new(RefCell::new(Node {
+        let new_head = self.new_node(Node {
             data: val,
             next: Some(old_head.clone()),
             prev: None,
-        }));
+        });
         old_head.borrow_mut().prev = Some(Rc::downgrade(&new_head));
         self.head = Some(new_head);
     }
 
     pub fn pop_front(&mut self) -> Option<T> {
+        if let Some(chunks) = &mut self.chunks {
+            let val = chunks.pop_front()?;
+            trace::event("LinkedList", "pop_front", chunks.len());
+            return Some(val);
+        }
         let old_head = self.head.take()?;
+        self.len -= 1;
+        trace::event("LinkedList", "pop_front", self.len);
         self.head = old_head.borrow_mut().next.take();
         if let Some(h) = &self.head {
             h.borrow_mut().prev = None;
         } else {
             self.tail = None;
         }
-        Some(Rc::into_inner(old_head)?.into_inner().data)
+        self.release(old_head)
     }
 
     pub fn push_back(&mut self, val: T) {
+        if let Some(chunks) = &mut self.chunks {
+            chunks.push_back(val);
+            trace::event("LinkedList", "push_back", chunks.len());
+            return;
+        }
+        self.len += 1;
+        trace::event("LinkedList", "push_back", self.len);
         let Some(old_tail) = self.tail.take().and_then(|w| w.upgrade()) else {
-            let node = Rc::new(RefCell::new(Node { data: val, next: None, prev: None }));
+            let node = self.new_node(Node { data: val, next: None, prev: None });
             self.tail = Some(Rc::downgrade(&node));
             self.head = Some(node);
             return;
         };
-        let new_tail = Rc::new(RefCell::new(Node {
+        let new_tail = self.new_node(Node {
             data: val,
             next: None,
             prev: Some(Rc::downgrade(&old_tail)),
-        }));
+        });
         old_tail.borrow_mut().next = Some(new_tail.clone());
         self.tail = Some(Rc::downgrade(&new_tail));
     }
 
     pub fn pop_back(&mut self) -> Option<T> {
+        if let Some(chunks) = &mut self.chunks {
+            let val = chunks.pop_back()?;
+            trace::event("LinkedList", "pop_back", chunks.len());
+            return Some(val);
+        }
         let old_tail = self.tail.take().and_then(|w| w.upgrade())?;
+        self.len -= 1;
+        trace::event("LinkedList", "pop_back", self.len);
         self.tail = old_tail.borrow_mut().prev.take();
         if let Some(weak) = &self.tail {
             if let Some(t) = weak.upgrade() {
@@ -74,61 +251,539 @@ impl<T: Debug> LinkedList<T> {
         } else {
             self.head = None;
         }
-        Some(Rc::into_inner(old_tail)?.into_inner().data)
+        self.release(old_tail)
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { curr: self.head.clone(), left: self.len, list: PhantomData, chunks: self.chunks.as_ref().map(Chunks::iter) }
+    }
+
+    /// Break the list into the runs of elements between those matching
+    /// `pred`, like `slice::split`. The matches are dropped, and `n` of them
+    /// make `n + 1` lists, empty ones included. Each run is cut off in O(1)
+    /// once its separator is found, or moved element by element on a chunked
+    /// list.
+    pub fn split<F: FnMut(&T) -> bool>(self, pred: F) -> Split<T, F> {
+        Split { rest: Some(self), pred }
+    }
+
+    /// Break the list into its maximal runs of neighbours for which `eq`
+    /// holds, like `slice::chunk_by`. `eq` is passed each pair in list
+    /// order. Each run is cut off in O(1) once its end is found, or moved
+    /// element by element on a chunked list.
+    pub fn chunk_by<F: FnMut(&T, &T) -> bool>(self, eq: F) -> ChunkBy<T, F> {
+        ChunkBy { rest: self, eq }
+    }
+
+    /// A cursor on the first element not less than `key`, or on the ghost
+    /// position if there is none, in a list sorted in ascending order. So
+    /// it is on `key` if the list holds it, and otherwise where `key` would
+    /// be inserted with `insert_before`, which makes a sorted list an
+    /// ordered set.
+    ///
+    /// Binary searches hints the list keeps to every `log n`th node, then
+    /// walks the rest of the way, for O(log n) steps. The first search after
+    /// anything takes nodes out of the list rebuilds the hints, in O(n), and
+    /// so does one that has to walk far because many inserts went into one
+    /// gap. A chunked list keeps no hints, and is walked from the front.
+    pub fn search_sorted<Q: Ord + ?Sized>(&mut self, key: &Q) -> Cursor<'_, T>
+    where
+        T: std::borrow::Borrow<Q>,
+    {
+        if self.chunks.is_some() {
+            let mut cursor = self.cursor_front();
+            while cursor.peek_mut().is_some_and(|val| <T as std::borrow::Borrow<Q>>::borrow(val) < key) {
+                cursor.next();
+            }
+            return cursor;
+        }
+        let below = |node: &NodeRef<T>| <T as std::borrow::Borrow<Q>>::borrow(&node.borrow().data) < key;
+        let stride = self.len.max(2).ilog2() as usize;
+        let mut hints = self.hints.take();
+        if hints.is_empty() {
+            let mut node = self.head.clone();
+            let mut i = 0;
+            while let Some(curr) = node {
+                if i % stride == 0 {
+                    hints.push(Rc::downgrade(&curr));
+                }
+                node = curr.borrow().next.clone();
+                i += 1;
+            }
+        }
+        // Start from the last hint before `key`, all of which are still in
+        // the list
+        let start = hints.partition_point(|hint| below(&hint.upgrade().unwrap()));
+        let mut node = match start {
+            0 => self.head.clone(),
+            _ => hints[start - 1].upgrade(),
+        };
+        let mut walked = 0;
+        loop {
+            let next = match &node {
+                Some(curr) if below(curr) => curr.borrow().next.clone(),
+                _ => break,
+            };
+            node = next;
+            walked += 1;
+        }
+        if walked > 4 * stride {
+            hints.clear();
+        }
+        self.hints.set(hints);
+        Cursor(CursorRepr::Nodes { list: self, current: node })
     }
 
     pub fn cursor_front(&mut self) -> Cursor<'_, T> {
-        let current = self.head.clone();
-        Cursor {
-            list: self,
-            current: current,
+        if self.chunks.is_none() {
+            let current = self.head.clone();
+            return Cursor(CursorRepr::Nodes { list: self, current });
+        }
+        Cursor(CursorRepr::Chunks(self.chunks.as_mut().unwrap().cursor_front()))
+    }
+
+    // Node handles let other structures in the crate keep direct references
+    // into the list and edit it in O(1) without a cursor.
+
+    pub(crate) fn front_node(&self) -> Option<NodeRef<T>> {
+        self.head.clone()
+    }
+
+    /// Append an element and return a handle to its node
+    pub(crate) fn push_back_node(&mut self, val: T) -> NodeRef<T> {
+        self.push_back(val);
+        self.tail.as_ref().and_then(|w| w.upgrade()).unwrap()
+    }
+
+    /// Insert an element right after `node` and return a handle to the new node
+    pub(crate) fn insert_after_node(&mut self, node: &NodeRef<T>, val: T) -> NodeRef<T> {
+        self.len += 1;
+        let next = node.borrow_mut().next.take();
+        let new_node = self.new_node(Node {
+            data: val,
+            next: next.clone(),
+            prev: Some(Rc::downgrade(node)),
+        });
+        match next {
+            Some(next) => next.borrow_mut().prev = Some(Rc::downgrade(&new_node)),
+            None => self.tail = Some(Rc::downgrade(&new_node)),
+        }
+        node.borrow_mut().next = Some(new_node.clone());
+        new_node
+    }
+
+    /// Unlink `node` from the list and return its element. Returns `None` if
+    /// other handles to the node are still alive.
+    pub(crate) fn remove_node(&mut self, node: NodeRef<T>) -> Option<T> {
+        self.len -= 1;
+        self.unlink(&node);
+        self.release(node)
+    }
+
+    /// Move `node` to the back of the list, as an LRU list does on each use
+    pub(crate) fn move_to_back(&mut self, node: &NodeRef<T>) {
+        self.unlink(node);
+        match self.tail.take().and_then(|w| w.upgrade()) {
+            Some(old_tail) => {
+                node.borrow_mut().prev = Some(Rc::downgrade(&old_tail));
+                old_tail.borrow_mut().next = Some(node.clone());
+            }
+            None => self.head = Some(node.clone()),
+        }
+        self.tail = Some(Rc::downgrade(node));
+    }
+
+    #[cfg(feature = "std_cursor")]
+    pub(crate) fn back_node(&self) -> Option<NodeRef<T>> {
+        self.tail.as_ref().and_then(Weak::upgrade)
+    }
+
+    /// Cut the list after `node`, or before the front if `node` is `None`,
+    /// and return the part after the cut. `at` is the number of elements
+    /// before the cut.
+    pub(crate) fn split_after_node(&mut self, node: Option<&NodeRef<T>>, at: usize) -> LinkedList<T> {
+        let next = match node {
+            Some(node) => node.borrow_mut().next.take(),
+            None => self.head.take(),
+        };
+        let Some(next) = next else {
+            return LinkedList { head: None, tail: None, len: 0, pool: self.pool.clone(), hints: Cell::default(), chunks: None };
+        };
+        self.hints.take();
+        next.borrow_mut().prev = None;
+        let back = LinkedList { head: Some(next), tail: self.tail.take(), len: self.len - at, pool: self.pool.clone(), hints: Cell::default(), chunks: None };
+        self.tail = node.map(Rc::downgrade);
+        self.len = at;
+        back
+    }
+
+    /// Link the whole of `other` in after `node`, or at the front if `node`
+    /// is `None`, in O(1). A chunked `other` is first moved into nodes in
+    /// O(n).
+    #[cfg(feature = "std_cursor")]
+    pub(crate) fn splice_after_node(&mut self, node: Option<&NodeRef<T>>, mut other: LinkedList<T>) {
+        if let Some(chunks) = other.chunks.take() {
+            other = chunks.into();
+        }
+        let Some(first) = other.head.take() else {
+            return;
+        };
+        let last = other.back_node().unwrap();
+        self.len += other.len;
+        let next = match node {
+            Some(node) => node.borrow_mut().next.replace(first.clone()),
+            None => self.head.replace(first.clone()),
+        };
+        first.borrow_mut().prev = node.map(Rc::downgrade);
+        match &next {
+            Some(next) => next.borrow_mut().prev = Some(Rc::downgrade(&last)),
+            None => self.tail = Some(Rc::downgrade(&last)),
+        }
+        last.borrow_mut().next = next;
+    }
+
+    fn unlink(&mut self, node: &NodeRef<T>) {
+        self.hints.take();
+        let next = node.borrow_mut().next.take();
+        let prev = node.borrow_mut().prev.take().and_then(|w| w.upgrade());
+        match &next {
+            Some(next) => next.borrow_mut().prev = prev.as_ref().map(Rc::downgrade),
+            None => self.tail = prev.as_ref().map(Rc::downgrade),
+        }
+        match prev {
+            Some(prev) => prev.borrow_mut().next = next,
+            None => self.head = next,
+        }
+    }
+}
+
+impl<T> Default for LinkedList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Unlinks the nodes one at a time, as dropping `head` as it is would recurse
+// once per node and overflow the stack on long lists
+impl<T> Drop for LinkedList<T> {
+    fn drop(&mut self) {
+        let mut curr = self.head.take();
+        while let Some(node) = curr {
+            curr = node.borrow_mut().next.take();
+        }
+    }
+}
+
+impl<T> Sequence<T> for LinkedList<T> {
+    type Iter<'a>
+        = Iter<'a, T>
+    where
+        T: 'a;
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn push_front(&mut self, val: T) {
+        self.push_front(val);
+    }
+
+    fn push_back(&mut self, val: T) {
+        self.push_back(val);
+    }
+
+    fn pop_front(&mut self) -> Option<T> {
+        self.pop_front()
+    }
+
+    fn pop_back(&mut self) -> Option<T> {
+        self.pop_back()
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
+    }
+
+    #[cfg(any(test, debug_assertions))]
+    fn validate(&self) {
+        self.validate();
+    }
+}
+
+pub struct Iter<'a, T> {
+    curr: Option<NodeRef<T>>,
+    left: usize,
+    list: PhantomData<&'a LinkedList<T>>,
+    // Used instead of the fields above on a chunked list
+    chunks: Option<unrolled_list::Iter<'a, T, 0>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(chunks) = &mut self.chunks {
+            return chunks.next();
+        }
+        let node = self.curr.take()?;
+        self.curr = node.borrow().next.clone();
+        self.left -= 1;
+        // This is safe because the list, which keeps the node alive, is
+        // borrowed shared for as long as the reference, so nothing can
+        // mutate the node meanwhile.
+        Some(unsafe { &(*node.as_ptr()).data })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.chunks {
+            Some(chunks) => chunks.size_hint(),
+            None => (self.left, Some(self.left)),
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<'a, T> IntoIterator for &'a LinkedList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Split<T, F> {
+    // What is left after the last separator, or `None` once it is yielded
+    rest: Option<LinkedList<T>>,
+    pred: F,
+}
+
+impl<T, F: FnMut(&T) -> bool> Iterator for Split<T, F> {
+    type Item = LinkedList<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.rest.as_mut()?;
+        if rest.chunks.is_some() {
+            let mut run = LinkedList::with_chunk_size(rest.chunk_size());
+            while let Some(val) = rest.pop_front() {
+                if (self.pred)(&val) {
+                    return Some(run);
+                }
+                run.push_back(val);
+            }
+            self.rest = None;
+            return Some(run);
+        }
+        let mut node = rest.front_node();
+        let mut at = 0;
+        while let Some(curr) = node {
+            if (self.pred)(&curr.borrow().data) {
+                let after = rest.split_after_node(Some(&curr), at + 1);
+                rest.remove_node(curr);
+                return Some(std::mem::replace(rest, after));
+            }
+            node = curr.borrow().next();
+            at += 1;
+        }
+        self.rest.take()
+    }
+}
+
+pub struct ChunkBy<T, F> {
+    rest: LinkedList<T>,
+    eq: F,
+}
+
+impl<T, F: FnMut(&T, &T) -> bool> Iterator for ChunkBy<T, F> {
+    type Item = LinkedList<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(chunks) = &mut self.rest.chunks {
+            let mut run = Chunks::with_node_capacity(chunks.node_capacity());
+            run.push_back(chunks.pop_front()?);
+            while let Some(next) = chunks.front()
+                && (self.eq)(run.back().unwrap(), next)
+            {
+                run.push_back(chunks.pop_front().unwrap());
+            }
+            let mut list = LinkedList::new();
+            list.chunks = Some(run);
+            return Some(list);
+        }
+        let mut last = self.rest.front_node()?;
+        let mut len = 1;
+        loop {
+            let next = last.borrow().next();
+            match next {
+                Some(next) if (self.eq)(&last.borrow().data, &next.borrow().data) => {
+                    last = next;
+                    len += 1;
+                }
+                _ => break,
+            }
         }
+        let after = self.rest.split_after_node(Some(&last), len);
+        Some(std::mem::replace(&mut self.rest, after))
     }
 }
 
-pub struct Cursor<'a, T: Debug> {
-    list: &'a mut LinkedList<T>,
-    current: Option<NodeRef<T>>,
+pub struct Cursor<'a, T>(CursorRepr<'a, T>);
+
+enum CursorRepr<'a, T> {
+    Nodes { list: &'a mut LinkedList<T>, current: Option<NodeRef<T>> },
+    // The chunked storage's own cursor has the same semantics, so calls go to it
+    Chunks(unrolled_list::Cursor<'a, T, 0>),
+}
+
+fn node_data_mut<T>(current: &mut Option<NodeRef<T>>) -> Option<&mut T> {
+    current.as_deref().and_then(|cell| {
+        // This is safe because we have exclusive access to the list
+        // through the mutable reference in the cursor.
+        unsafe { cell.as_ptr().as_mut() }
+    }).map(|node| &mut node.data)
 }
 
 // the cursor is expected to act as if it is at the position of an element
 // and it also has to work with and be able to insert into an empty list.
-impl<T: Debug> Cursor<'_, T> {
+// Off either end it sits on a "ghost" position, from which `next` moves to
+// the front and `prev` to the back.
+impl<T> Cursor<'_, T> {
     /// Take a mutable reference to the current element
     pub fn peek_mut(&mut self) -> Option<&mut T> {
-        self.current.as_deref().and_then(|cell| {
-            // This is safe because we have exclusive access to the list
-            // through the mutable reference in the cursor.
-            unsafe { cell.as_ptr().as_mut() }
-        }).map(|node| &mut node.data)
+        match &mut self.0 {
+            CursorRepr::Nodes { current, .. } => node_data_mut(current),
+            CursorRepr::Chunks(cursor) => cursor.peek_mut(),
+        }
     }
 
     /// Move one position forward (towards the back) and
     /// return a reference to the new position
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Option<&mut T> {
-        self.current = self.current.as_ref().and_then(|node| node.borrow().next.clone());
-        self.peek_mut()
+        let (list, current) = match &mut self.0 {
+            CursorRepr::Nodes { list, current } => (list, current),
+            CursorRepr::Chunks(cursor) => return cursor.next(),
+        };
+        *current = match current {
+            Some(node) => node.borrow().next.clone(),
+            None => list.head.clone(),
+        };
+        node_data_mut(current)
     }
 
     /// Move one position backward (towards the front) and
     /// return a reference to the new position
     pub fn prev(&mut self) -> Option<&mut T> {
-        todo!()
+        let (list, current) = match &mut self.0 {
+            CursorRepr::Nodes { list, current } => (list, current),
+            CursorRepr::Chunks(cursor) => return cursor.prev(),
+        };
+        let prev = match current {
+            Some(node) => node.borrow().prev.clone(),
+            None => list.tail.clone(),
+        };
+        *current = prev.and_then(|w| w.upgrade());
+        node_data_mut(current)
     }
 
     /// Remove and return the element at the current position and move the cursor
     /// to the neighboring element that's closest to the back. This can be
     /// either the next or previous position.
     pub fn take(&mut self) -> Option<T> {
-        todo!()
+        let (list, current) = match &mut self.0 {
+            CursorRepr::Nodes { list, current } => (list, current),
+            CursorRepr::Chunks(cursor) => return cursor.take(),
+        };
+        let node = current.take()?;
+        let next = node.borrow().next.clone();
+        let prev = node.borrow().prev.as_ref().and_then(|w| w.upgrade());
+        *current = next.or(prev);
+        list.remove_node(node)
+    }
+
+    /// Insert an element after the current one, or at the front if the
+    /// cursor is on the ghost position. The cursor does not move.
+    pub fn insert_after(&mut self, element: T) {
+        let (list, current) = match &mut self.0 {
+            CursorRepr::Nodes { list, current } => (list, current),
+            CursorRepr::Chunks(cursor) => return cursor.insert_after(element),
+        };
+        match current {
+            Some(node) => {
+                list.insert_after_node(node, element);
+            }
+            None => list.push_front(element),
+        }
+    }
+
+    /// Insert an element before the current one, or at the back if the
+    /// cursor is on the ghost position. The cursor does not move.
+    pub fn insert_before(&mut self, element: T) {
+        let (list, current) = match &mut self.0 {
+            CursorRepr::Nodes { list, current } => (list, current),
+            CursorRepr::Chunks(cursor) => return cursor.insert_before(element),
+        };
+        let Some(node) = current else {
+            list.push_back(element);
+            return;
+        };
+        let prev = node.borrow().prev.as_ref().and_then(|w| w.upgrade());
+        match prev {
+            Some(prev) => {
+                list.insert_after_node(&prev, element);
+            }
+            None => list.push_front(element),
+        }
+    }
+}
+
+impl<T> SeqCursor<T> for Cursor<'_, T> {
+    fn peek_mut(&mut self) -> Option<&mut T> {
+        self.peek_mut()
     }
 
-    pub fn insert_after(&mut self, _element: T) {
-        todo!()
+    fn next(&mut self) -> Option<&mut T> {
+        self.next()
     }
 
-    pub fn insert_before(&mut self, _element: T) {
-        todo!()
+    fn prev(&mut self) -> Option<&mut T> {
+        self.prev()
+    }
+
+    fn take(&mut self) -> Option<T> {
+        self.take()
+    }
+
+    fn insert_after(&mut self, element: T) {
+        self.insert_after(element);
+    }
+
+    fn insert_before(&mut self, element: T) {
+        self.insert_before(element);
+    }
+}
+
+impl<T> LendingCursor for Cursor<'_, T> {
+    type Elem = T;
+
+    type Item<'a>
+        = &'a mut T
+    where
+        Self: 'a;
+
+    fn peek(&mut self) -> Option<Self::Item<'_>> {
+        self.peek_mut()
+    }
+
+    fn move_next(&mut self) -> bool {
+        self.next();
+        true
+    }
+
+    fn move_prev(&mut self) -> bool {
+        self.prev();
+        true
     }
 }
 
@@ -143,6 +798,9 @@ impl<T: Debug> Debug for Node<T> {
 }
 impl<T: Debug> Debug for LinkedList<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(chunks) = &self.chunks {
+            return Debug::fmt(chunks, f);
+        }
         let mut debug_list = f.debug_list();
         let mut current = self.head.as_ref().map(|node| node.clone());
         while let Some(node) = current {
@@ -153,9 +811,89 @@ impl<T: Debug> Debug for LinkedList<T> {
     }
 }
 
+impl<T> From<Vec<T>> for LinkedList<T> {
+    fn from(vec: Vec<T>) -> Self {
+        let mut list = LinkedList::new();
+        for val in vec {
+            list.push_back(val);
+        }
+        list
+    }
+}
+
+impl<T> From<VecDeque<T>> for LinkedList<T> {
+    fn from(deque: VecDeque<T>) -> Self {
+        let mut list = LinkedList::new();
+        for val in deque {
+            list.push_back(val);
+        }
+        list
+    }
+}
+
+impl<T> From<List<T>> for LinkedList<T> {
+    fn from(singly: List<T>) -> Self {
+        let mut list = LinkedList::new();
+        for val in singly {
+            list.push_back(val);
+        }
+        list
+    }
+}
+
+impl<T> From<LinkedList<T>> for Vec<T> {
+    fn from(mut list: LinkedList<T>) -> Self {
+        let mut vec = Vec::with_capacity(list.len());
+        while let Some(val) = list.pop_front() {
+            vec.push(val);
+        }
+        vec
+    }
+}
+
+impl<T> From<LinkedList<T>> for VecDeque<T> {
+    fn from(mut list: LinkedList<T>) -> Self {
+        let mut deque = VecDeque::with_capacity(list.len());
+        while let Some(val) = list.pop_front() {
+            deque.push_back(val);
+        }
+        deque
+    }
+}
+
+impl<T> From<LinkedList<T>> for List<T> {
+    fn from(mut list: LinkedList<T>) -> Self {
+        let mut singly = List::new();
+        while let Some(val) = list.pop_back() {
+            singly.push_front(val);
+        }
+        singly
+    }
+}
+
+impl<T: HeapSize> HeapSize for LinkedList<T> {
+    fn heap_size(&self) -> usize {
+        if let Some(chunks) = &self.chunks {
+            return chunks.heap_size();
+        }
+        self.len * rc_size::<RefCell<Node<T>>>() + payload(self.iter())
+    }
+}
+
+impl<T: Debug> PrettyPrint for LinkedList<T> {
+    fn pretty_print(&self) -> String {
+        arrows(self.iter(), " <-> ")
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::LinkedList;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+
+    use crate::list::List;
+
+    use super::{LinkedList, LinkedListPool};
 
     #[test]
     fn test_push_back() {
@@ -204,8 +942,10 @@ mod tests {
 
         assert_eq!(list.pop_front(), Some(1));
         assert_eq!(list.pop_back(), Some(3));
+        list.validate();
         assert_eq!(list.pop_front(), Some(2));
         assert_eq!(list.pop_back(), None);
+        list.validate();
     }
 
     #[test]
@@ -318,4 +1058,293 @@ mod tests {
 
         assert_eq!(cursor.next(), None);
     }
+
+    #[test]
+    fn test_pool_reuses_nodes() {
+        let pool = Rc::new(LinkedListPool::new(4));
+        let mut list = LinkedList::with_pool(pool.clone());
+        for i in 0..6 {
+            list.push_back(i);
+        }
+        assert_eq!(list.pop_front(), Some(0));
+        assert_eq!(list.pop_back(), Some(5));
+        assert_eq!(pool.spare_count(), 2);
+
+        list.push_front(-1);
+        assert_eq!(pool.spare_count(), 1);
+        let node = list.push_back_node(6);
+        assert_eq!(pool.spare_count(), 0);
+        // Unlinked, but still shared with `node`, so not recycled
+        let copy = node.clone();
+        assert_eq!(list.remove_node(copy), None);
+        assert_eq!(pool.spare_count(), 0);
+
+        while list.pop_front().is_some() {}
+        assert_eq!(pool.spare_count(), 4);
+    }
+
+    #[test]
+    fn test_pooled() {
+        let mut list = LinkedList::pooled();
+        for i in 0..100 {
+            list.push_back(i);
+        }
+        for i in 100..1000 {
+            // Each push reuses the node the pop just gave back
+            assert_eq!(list.pop_front(), Some(i - 100));
+            list.push_back(i);
+            assert_eq!(list.pool_spare_count(), 0);
+        }
+        while list.pop_front().is_some() {}
+        assert_eq!(list.pool_spare_count(), 100);
+        list.shrink_pool();
+        assert_eq!(list.pool_spare_count(), 0);
+        assert_eq!(LinkedList::<i32>::new().pool_spare_count(), 0);
+    }
+
+    #[test]
+    fn test_split() {
+        let list = LinkedList::from(vec!["a", ",", "b", "c", ",", ","]);
+        let pieces: Vec<LinkedList<_>> = list.split(|&s| s == ",").collect();
+        pieces.iter().for_each(LinkedList::validate);
+        let pieces: Vec<Vec<_>> = pieces.into_iter().map(Vec::from).collect();
+        assert_eq!(pieces, [vec!["a"], vec!["b", "c"], vec![], vec![]]);
+        assert_eq!(LinkedList::from(vec![1, 2]).split(|_| false).map(|l| l.len()).collect::<Vec<_>>(), [2]);
+    }
+
+    #[test]
+    fn test_chunk_by() {
+        let list = LinkedList::from(vec!["apple", "avocado", "banana", "cherry", "cranberry"]);
+        let runs: Vec<LinkedList<_>> = list.chunk_by(|a, b| a[..1] == b[..1]).collect();
+        runs.iter().for_each(LinkedList::validate);
+        let runs: Vec<Vec<_>> = runs.into_iter().map(Vec::from).collect();
+        assert_eq!(runs, [vec!["apple", "avocado"], vec!["banana"], vec!["cherry", "cranberry"]]);
+        assert_eq!(LinkedList::<i32>::new().chunk_by(|_, _| true).count(), 0);
+    }
+
+    #[test]
+    fn test_drop_long() {
+        let list = LinkedList::from(vec![0u8; 3_000_000]);
+        drop(list);
+    }
+
+    #[test]
+    fn test_search_sorted() {
+        let mut list = LinkedList::from((0..1000).map(|i| i * 2).collect::<Vec<_>>());
+        assert_eq!(list.search_sorted(&500).peek_mut(), Some(&mut 500));
+        assert_eq!(list.search_sorted(&501).peek_mut(), Some(&mut 502));
+        assert_eq!(list.search_sorted(&-1).peek_mut(), Some(&mut 0));
+        assert_eq!(list.search_sorted(&2000).peek_mut(), None);
+        list.validate();
+
+        // As an ordered set: insert what isn't there yet
+        for i in (0..2000).rev().filter(|i| i % 3 == 0) {
+            let mut cursor = list.search_sorted(&i);
+            if cursor.peek_mut() != Some(&mut i.clone()) {
+                cursor.insert_before(i);
+            }
+        }
+        list.validate();
+        assert_eq!(list.len(), 1000 + 333);
+        assert!(list.iter().zip(list.iter().skip(1)).all(|(a, b)| a < b));
+        assert_eq!(list.search_sorted(&998).take(), Some(998));
+        list.validate();
+        assert_eq!(list.search_sorted(&998).peek_mut(), Some(&mut 999));
+        assert_eq!(list.search_sorted(&1998).next(), None);
+
+        // Many inserts into one gap
+        let mut list = LinkedList::from(vec![0, 1000]);
+        for i in 1..1000 {
+            list.search_sorted(&i).insert_before(i);
+        }
+        list.validate();
+        assert!(list.iter().copied().eq(0..=1000));
+
+        let mut words = LinkedList::from(["ant", "bee", "cat"].map(String::from).to_vec());
+        assert_eq!(words.search_sorted("bat").peek_mut().map(|s| s.as_str()), Some("bee"));
+        assert_eq!(LinkedList::<i32>::new().search_sorted(&1).peek_mut(), None);
+    }
+
+    #[test]
+    fn test_chunked() {
+        let mut list = LinkedList::with_chunk_size(4);
+        assert_eq!(list.chunk_size(), 4);
+        for i in 0..10 {
+            list.push_back(i);
+            list.push_front(-i);
+        }
+        list.validate();
+        assert_eq!(list.len(), 20);
+        assert_eq!(list.iter().len(), 20);
+        assert_eq!(list.pop_front(), Some(-9));
+        assert_eq!(list.pop_back(), Some(9));
+        assert!(list.iter().copied().eq((-8..=0).chain(0..9)));
+        let mut small = LinkedList::with_chunk_size(3);
+        small.push_back(1);
+        small.push_back(2);
+        assert_eq!(format!("{small:?}"), "[1, 2]");
+        while list.pop_back().is_some() {}
+        assert!(list.is_empty());
+        list.validate();
+
+        assert_eq!(LinkedList::<i32>::with_chunk_size(1).chunk_size(), 1);
+        assert_eq!(LinkedList::<i32>::new().chunk_size(), 1);
+    }
+
+    #[test]
+    fn test_chunked_cursor() {
+        // The same edits through the cursor of a plain and a chunked list
+        let mut plain = LinkedList::new();
+        let mut chunked = LinkedList::with_chunk_size(3);
+        for list in [&mut plain, &mut chunked] {
+            let mut cursor = list.cursor_front();
+            for i in 0..200 {
+                match i % 7 {
+                    0 | 1 => cursor.insert_before(i),
+                    2 => cursor.insert_after(i),
+                    3 => {
+                        cursor.next();
+                    }
+                    4 => {
+                        cursor.prev();
+                    }
+                    5 => {
+                        cursor.take();
+                    }
+                    _ => {
+                        if let Some(val) = cursor.peek_mut() {
+                            *val += 1000;
+                        }
+                    }
+                }
+            }
+            list.validate();
+        }
+        assert!(!plain.is_empty());
+        assert!(chunked.iter().eq(plain.iter()));
+
+        let mut list = LinkedList::with_chunk_size(2);
+        let mut cursor = list.cursor_front();
+        assert_eq!(cursor.peek_mut(), None);
+        cursor.insert_after(1);
+        assert_eq!(cursor.next(), Some(&mut 1));
+        assert_eq!(cursor.next(), None);
+        assert_eq!(cursor.prev(), Some(&mut 1));
+        assert_eq!(cursor.take(), Some(1));
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn test_chunked_split_and_search() {
+        let mut list = LinkedList::with_chunk_size(2);
+        for s in ["a", ",", "b", "c", ",", ","] {
+            list.push_back(s);
+        }
+        let pieces: Vec<LinkedList<_>> = list.split(|&s| s == ",").collect();
+        pieces.iter().for_each(LinkedList::validate);
+        assert!(pieces.iter().all(|piece| piece.chunk_size() == 2));
+        let pieces: Vec<Vec<_>> = pieces.into_iter().map(Vec::from).collect();
+        assert_eq!(pieces, [vec!["a"], vec!["b", "c"], vec![], vec![]]);
+
+        let mut list = LinkedList::with_chunk_size(4);
+        for s in ["apple", "avocado", "banana", "cherry", "cranberry"] {
+            list.push_back(s);
+        }
+        let runs: Vec<LinkedList<_>> = list.chunk_by(|a, b| a[..1] == b[..1]).collect();
+        runs.iter().for_each(LinkedList::validate);
+        assert!(runs.iter().all(|run| run.chunk_size() == 4));
+        let runs: Vec<Vec<_>> = runs.into_iter().map(Vec::from).collect();
+        assert_eq!(runs, [vec!["apple", "avocado"], vec!["banana"], vec!["cherry", "cranberry"]]);
+
+        let mut list = LinkedList::with_chunk_size(8);
+        for i in 0..100 {
+            list.push_back(i * 2);
+        }
+        assert_eq!(list.search_sorted(&51).peek_mut(), Some(&mut 52));
+        list.search_sorted(&51).insert_before(51);
+        assert_eq!(list.search_sorted(&1000).peek_mut(), None);
+        list.validate();
+        assert_eq!(list.len(), 101);
+        assert!(list.iter().zip(list.iter().skip(1)).all(|(a, b)| a < b));
+    }
+
+    #[test]
+    fn test_conversions() {
+        // Round the whole matrix, keeping the order throughout
+        let list = LinkedList::from(vec![1, 2, 3]);
+        let singly = List::from(list);
+        let deque = VecDeque::from(singly);
+        let list = LinkedList::from(deque);
+        list.validate();
+        let vec = Vec::from(list);
+        let singly = List::from(vec);
+        let list = LinkedList::from(singly);
+        let deque = VecDeque::from(list);
+        let singly = List::from(deque);
+        assert_eq!(Vec::from(singly), vec![1, 2, 3]);
+
+        let vec: Vec<i32> = LinkedList::from(Vec::new()).into();
+        assert!(vec.is_empty());
+    }
+}
+
+// Proofs for the Kani model checker, run with `cargo kani`. Each one checks
+// every choice of operation up to the given number of steps, rather than
+// the handful of cases the tests try.
+#[cfg(kani)]
+mod proofs {
+    use super::LinkedList;
+
+    #[kani::proof]
+    #[kani::unwind(6)]
+    fn push_pop_keeps_links() {
+        let mut list = LinkedList::new();
+        for _ in 0..4 {
+            let val: u8 = kani::any();
+            match kani::any::<u8>() % 4 {
+                0 => {
+                    list.push_front(val);
+                    assert_eq!(list.pop_front(), Some(val));
+                    list.push_front(val);
+                }
+                1 => {
+                    list.push_back(val);
+                    assert_eq!(list.pop_back(), Some(val));
+                    list.push_back(val);
+                }
+                2 => {
+                    list.pop_front();
+                }
+                _ => {
+                    list.pop_back();
+                }
+            }
+            list.validate();
+        }
+    }
+
+    #[kani::proof]
+    #[kani::unwind(7)]
+    fn cursor_edits_keep_links() {
+        let mut list = LinkedList::new();
+        list.push_back(0u8);
+        list.push_back(1);
+        let mut cursor = list.cursor_front();
+        for _ in 0..3 {
+            match kani::any::<u8>() % 5 {
+                0 => {
+                    cursor.next();
+                }
+                1 => {
+                    cursor.prev();
+                }
+                2 => {
+                    cursor.take();
+                }
+                3 => cursor.insert_after(kani::any()),
+                _ => cursor.insert_before(kani::any()),
+            }
+        }
+        list.validate();
+    }
 }
@@ -0,0 +1,166 @@
+use crate::error::{CollectionError, check_index};
+use crate::heap_size::HeapSize;
+
+/// Disjoint-set union (union-find) over the elements `0..len`.
+///
+/// Uses path compression and union by rank, so any sequence of operations
+/// runs in near-constant amortized time per operation.
+pub struct Dsu {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+    size: Vec<usize>,
+    components: usize,
+}
+
+impl Dsu {
+    /// Create `n` singleton sets.
+    pub fn new(n: usize) -> Self {
+        Dsu { parent: (0..n).collect(), rank: vec![0; n], size: vec![1; n], components: n }
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// Number of disjoint sets.
+    pub fn components(&self) -> usize {
+        self.components
+    }
+
+    /// Add a new singleton set and return its element.
+    pub fn push(&mut self) -> usize {
+        let x = self.parent.len();
+        self.parent.push(x);
+        self.rank.push(0);
+        self.size.push(1);
+        self.components += 1;
+        x
+    }
+
+    /// Representative of the set containing `x`.
+    ///
+    /// Panics if `x` is out of bounds.
+    pub fn find(&mut self, x: usize) -> usize {
+        let mut root = x;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut x = x;
+        while self.parent[x] != root {
+            x = std::mem::replace(&mut self.parent[x], root);
+        }
+        root
+    }
+
+    /// `find`, but returning an error if `x` is out of bounds.
+    pub fn try_find(&mut self, x: usize) -> Result<usize, CollectionError> {
+        check_index(x, self.len())?;
+        Ok(self.find(x))
+    }
+
+    /// Merge the sets containing `a` and `b`. Returns `false` if they were
+    /// already the same set.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        if self.rank[a] < self.rank[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+        if self.rank[a] == self.rank[b] {
+            self.rank[a] += 1;
+        }
+        self.components -= 1;
+        true
+    }
+
+    pub fn same_set(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// Number of elements in the set containing `x`.
+    pub fn size_of(&mut self, x: usize) -> usize {
+        let root = self.find(x);
+        self.size[root]
+    }
+}
+
+impl HeapSize for Dsu {
+    fn heap_size(&self) -> usize {
+        self.parent.heap_size() + self.rank.heap_size() + self.size.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::CollectionError;
+    use crate::heap_size::HeapSize;
+
+    use super::Dsu;
+
+    #[test]
+    fn test_new() {
+        let mut dsu = Dsu::new(5);
+        assert_eq!(dsu.len(), 5);
+        assert_eq!(dsu.components(), 5);
+        for i in 0..5 {
+            assert_eq!(dsu.find(i), i);
+            assert_eq!(dsu.size_of(i), 1);
+        }
+        assert!(Dsu::new(0).is_empty());
+    }
+
+    #[test]
+    fn test_union() {
+        let mut dsu = Dsu::new(6);
+        assert!(dsu.union(0, 1));
+        assert!(dsu.union(2, 3));
+        assert!(dsu.union(1, 3));
+        assert!(!dsu.union(0, 2));
+        assert_eq!(dsu.components(), 3);
+
+        assert!(dsu.same_set(0, 3));
+        assert!(!dsu.same_set(0, 4));
+        assert_eq!(dsu.size_of(2), 4);
+        assert_eq!(dsu.size_of(5), 1);
+        assert_eq!(dsu.try_find(2), Ok(dsu.find(0)));
+        assert_eq!(dsu.try_find(6), Err(CollectionError::IndexOutOfBounds { index: 6, len: 6 }));
+    }
+
+    #[test]
+    fn test_push() {
+        let mut dsu = Dsu::new(2);
+        let x = dsu.push();
+        assert_eq!(x, 2);
+        assert_eq!(dsu.components(), 3);
+        dsu.union(0, x);
+        assert!(dsu.same_set(x, 0));
+        assert_eq!(dsu.components(), 2);
+    }
+
+    #[test]
+    fn test_long_chain() {
+        let n = 10_000;
+        let mut dsu = Dsu::new(n);
+        for i in 1..n {
+            dsu.union(i - 1, i);
+        }
+        assert_eq!(dsu.components(), 1);
+        assert_eq!(dsu.size_of(0), n);
+        assert!(dsu.same_set(0, n - 1));
+    }
+
+    #[test]
+    fn test_heap_size() {
+        // Parents and sizes, plus one byte of rank each
+        assert_eq!(Dsu::new(4).heap_size(), 4 * 8 + 4 * 8 + 4);
+    }
+}
@@ -0,0 +1,85 @@
+use std::fmt::Display;
+use std::ops::Range;
+
+use crate::fallible::AllocError;
+
+/// Why a `try_` operation could not be carried out, for the positional and
+/// handle-based APIs that otherwise panic on a bad argument.
+///
+/// The container is left as it was whenever one of these is returned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CollectionError {
+    /// An index or position past the end of the container.
+    IndexOutOfBounds { index: usize, len: usize },
+    /// The container can't hold any more elements.
+    CapacityExceeded,
+    /// A handle whose element has been removed.
+    StaleHandle,
+    /// The allocator could not provide the memory the operation needed.
+    AllocationFailed,
+}
+
+impl Display for CollectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CollectionError::IndexOutOfBounds { index, len } => write!(f, "index {index} out of bounds for length {len}"),
+            CollectionError::CapacityExceeded => f.write_str("capacity exceeded"),
+            CollectionError::StaleHandle => f.write_str("stale handle"),
+            CollectionError::AllocationFailed => f.write_str("memory allocation failed"),
+        }
+    }
+}
+
+impl std::error::Error for CollectionError {}
+
+impl From<AllocError> for CollectionError {
+    fn from(_: AllocError) -> Self {
+        CollectionError::AllocationFailed
+    }
+}
+
+/// `Ok` if `index` names an element of a container of length `len`.
+pub(crate) fn check_index(index: usize, len: usize) -> Result<(), CollectionError> {
+    if index < len { Ok(()) } else { Err(CollectionError::IndexOutOfBounds { index, len }) }
+}
+
+/// `Ok` if `index` is a position to insert or split at, which includes the
+/// end.
+pub(crate) fn check_position(index: usize, len: usize) -> Result<(), CollectionError> {
+    if index <= len { Ok(()) } else { Err(CollectionError::IndexOutOfBounds { index, len }) }
+}
+
+/// `Ok` if `range` is a possibly empty run of elements of a container of
+/// length `len`. A reversed range is reported at its start.
+pub(crate) fn check_range(range: &Range<usize>, len: usize) -> Result<(), CollectionError> {
+    check_position(range.end, len)?;
+    if range.start <= range.end { Ok(()) } else { Err(CollectionError::IndexOutOfBounds { index: range.start, len }) }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+
+    use crate::fallible::AllocError;
+
+    use super::{CollectionError, check_index, check_position, check_range};
+
+    #[test]
+    fn test_checks() {
+        assert_eq!(check_index(2, 3), Ok(()));
+        assert_eq!(check_index(3, 3), Err(CollectionError::IndexOutOfBounds { index: 3, len: 3 }));
+        assert_eq!(check_position(3, 3), Ok(()));
+        assert_eq!(check_position(4, 3), Err(CollectionError::IndexOutOfBounds { index: 4, len: 3 }));
+        assert_eq!(check_range(&(1..3), 3), Ok(()));
+        assert_eq!(check_range(&(3..3), 3), Ok(()));
+        assert_eq!(check_range(&(1..4), 3), Err(CollectionError::IndexOutOfBounds { index: 4, len: 3 }));
+        assert_eq!(check_range(&Range { start: 2, end: 1 }, 3), Err(CollectionError::IndexOutOfBounds { index: 2, len: 3 }));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(CollectionError::IndexOutOfBounds { index: 5, len: 2 }.to_string(), "index 5 out of bounds for length 2");
+        assert_eq!(CollectionError::from(AllocError), CollectionError::AllocationFailed);
+        assert_eq!(CollectionError::AllocationFailed.to_string(), AllocError.to_string());
+    }
+}
@@ -0,0 +1,70 @@
+use std::alloc::{Layout, alloc};
+use std::collections::TryReserveError;
+use std::fmt::Display;
+
+/// The allocator could not provide the memory an operation needed.
+///
+/// Returned by the `try_` variants of the containers' growing operations,
+/// such as `List::try_push_front` or `HashMap::try_insert`, which leave the
+/// container as it was instead of aborting the process. Only containers
+/// whose nodes are `Box`es or `Vec`s have them: there is no fallible way to
+/// allocate an `Rc` on stable Rust.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AllocError;
+
+impl Display for AllocError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("memory allocation failed")
+    }
+}
+
+impl std::error::Error for AllocError {}
+
+impl From<TryReserveError> for AllocError {
+    fn from(_: TryReserveError) -> Self {
+        AllocError
+    }
+}
+
+/// `Box::new`, but returning an error if the allocation fails.
+pub(crate) fn try_box<T>(val: T) -> Result<Box<T>, AllocError> {
+    let layout = Layout::new::<T>();
+    if layout.size() == 0 {
+        // Boxing a zero-sized value never allocates
+        return Ok(Box::new(val));
+    }
+    // Safety: the layout has a non-zero size
+    let ptr = unsafe { alloc(layout) }.cast::<T>();
+    if ptr.is_null() {
+        return Err(AllocError);
+    }
+    // Safety: the pointer came from the global allocator with `T`'s layout,
+    // as `Box::from_raw` requires, and is written before it is read
+    unsafe {
+        ptr.write(val);
+        Ok(Box::from_raw(ptr))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::{AllocError, try_box};
+
+    #[test]
+    fn test_try_box() {
+        assert_eq!(*try_box(7u64).unwrap(), 7);
+        assert_eq!(*try_box(()).unwrap(), ());
+        let val = Rc::new(());
+        drop(try_box(val.clone()).unwrap());
+        assert_eq!(Rc::strong_count(&val), 1);
+    }
+
+    #[test]
+    fn test_from_try_reserve_error() {
+        let err = Vec::<u8>::new().try_reserve(usize::MAX).unwrap_err();
+        assert_eq!(AllocError::from(err), AllocError);
+        assert_eq!(AllocError.to_string(), "memory allocation failed");
+    }
+}
@@ -0,0 +1,151 @@
+use std::ffi::c_void;
+
+use crate::doubly_list::LinkedList;
+
+// A C interface to `LinkedList`, for the `ffi` feature. The list holds
+// untyped pointers that stay owned by the caller, and C code only ever sees
+// it through an opaque handle. Every function taking a handle requires one
+// returned by `linked_list_new` and not yet passed to `linked_list_free`.
+
+/// The list behind a C handle.
+pub struct FfiLinkedList {
+    list: LinkedList<*mut c_void>,
+}
+
+/// Called by `linked_list_for_each` with each element in turn and the
+/// caller's `ctx`. Returning false stops the walk.
+pub type LinkedListVisitor = extern "C" fn(elem: *mut c_void, ctx: *mut c_void) -> bool;
+
+/// A new, empty list. Free it with `linked_list_free`.
+#[unsafe(no_mangle)]
+pub extern "C" fn linked_list_new() -> *mut FfiLinkedList {
+    Box::into_raw(Box::new(FfiLinkedList { list: LinkedList::new() }))
+}
+
+/// Free a list. The elements themselves are left alone. Null is ignored.
+///
+/// # Safety
+///
+/// `list` must be null or a live handle, which is invalid afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn linked_list_free(list: *mut FfiLinkedList) {
+    if !list.is_null() {
+        drop(unsafe { Box::from_raw(list) });
+    }
+}
+
+/// # Safety
+///
+/// `list` must be a live handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn linked_list_len(list: *const FfiLinkedList) -> usize {
+    unsafe { (*list).list.len() }
+}
+
+/// # Safety
+///
+/// `list` must be a live handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn linked_list_push_front(list: *mut FfiLinkedList, elem: *mut c_void) {
+    unsafe { (*list).list.push_front(elem) }
+}
+
+/// # Safety
+///
+/// `list` must be a live handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn linked_list_push_back(list: *mut FfiLinkedList, elem: *mut c_void) {
+    unsafe { (*list).list.push_back(elem) }
+}
+
+/// Pop the front element into `*out`. Returns false, leaving `*out` alone,
+/// if the list is empty, since a null element is a valid one.
+///
+/// # Safety
+///
+/// `list` must be a live handle and `out` valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn linked_list_pop_front(list: *mut FfiLinkedList, out: *mut *mut c_void) -> bool {
+    unsafe { write_popped((*list).list.pop_front(), out) }
+}
+
+/// Pop the back element into `*out`, as with `linked_list_pop_front`.
+///
+/// # Safety
+///
+/// `list` must be a live handle and `out` valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn linked_list_pop_back(list: *mut FfiLinkedList, out: *mut *mut c_void) -> bool {
+    unsafe { write_popped((*list).list.pop_back(), out) }
+}
+
+unsafe fn write_popped(elem: Option<*mut c_void>, out: *mut *mut c_void) -> bool {
+    match elem {
+        Some(elem) => {
+            unsafe { out.write(elem) };
+            true
+        }
+        None => false,
+    }
+}
+
+/// Call `visit` on each element from front to back, until it returns false.
+/// Returns whether every element was visited. The list must not be changed
+/// from inside `visit`.
+///
+/// # Safety
+///
+/// `list` must be a live handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn linked_list_for_each(list: *const FfiLinkedList, visit: LinkedListVisitor, ctx: *mut c_void) -> bool {
+    unsafe { (*list).list.iter().all(|&elem| visit(elem, ctx)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::c_void;
+    use std::ptr;
+
+    use super::{
+        linked_list_for_each, linked_list_free, linked_list_len, linked_list_new, linked_list_pop_back, linked_list_pop_front,
+        linked_list_push_back, linked_list_push_front,
+    };
+
+    // Sums the elements, read as integers, into `ctx`, stopping after 3
+    extern "C" fn sum_up_to_three(elem: *mut c_void, ctx: *mut c_void) -> bool {
+        let sum = unsafe { &mut *ctx.cast::<usize>() };
+        *sum += elem as usize;
+        elem as usize != 3
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let elems = [1usize, 2, 3, 4].map(|i| i as *mut c_void);
+        unsafe {
+            let list = linked_list_new();
+            linked_list_push_back(list, elems[1]);
+            linked_list_push_back(list, elems[2]);
+            linked_list_push_front(list, elems[0]);
+            linked_list_push_back(list, elems[3]);
+            assert_eq!(linked_list_len(list), 4);
+
+            let mut sum = 0usize;
+            assert!(!linked_list_for_each(list, sum_up_to_three, (&raw mut sum).cast()));
+            assert_eq!(sum, 6);
+
+            let mut out = ptr::null_mut();
+            assert!(linked_list_pop_back(list, &mut out));
+            assert_eq!(out, elems[3]);
+            assert!(linked_list_pop_front(list, &mut out));
+            assert_eq!(out, elems[0]);
+            linked_list_push_front(list, ptr::null_mut());
+            assert!(linked_list_pop_front(list, &mut out));
+            assert!(out.is_null());
+            while linked_list_pop_front(list, &mut out) {}
+            assert_eq!(linked_list_len(list), 0);
+            assert!(linked_list_for_each(list, sum_up_to_three, (&raw mut sum).cast()));
+            linked_list_free(list);
+            linked_list_free(ptr::null_mut());
+        }
+    }
+}
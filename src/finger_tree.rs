@@ -0,0 +1,708 @@
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use crate::heap_size::{HeapSize, rc_size};
+use crate::sliding_window::Monoid;
+
+/// How a `FingerTree` measures its elements: a value for each element, and a
+/// monoid to combine the values of a run of elements into one. Every subtree
+/// caches its combined value, which is what lets a tree be searched and split
+/// by position, by priority, or by anything else a monoid can summarize.
+pub trait Measure<T> {
+    type Value: Clone;
+    type Monoid: Monoid<Self::Value>;
+
+    fn measure(item: &T) -> Self::Value;
+}
+
+/// Measures a sequence by its length, making a `FingerTree` an indexable
+/// sequence.
+pub struct Size;
+
+impl Monoid<usize> for Size {
+    fn identity() -> usize {
+        0
+    }
+
+    fn combine(a: &usize, b: &usize) -> usize {
+        a + b
+    }
+}
+
+impl<T> Measure<T> for Size {
+    type Value = usize;
+    type Monoid = Size;
+
+    fn measure(_: &T) -> usize {
+        1
+    }
+}
+
+// An element, or a node of two or three items one level down. Items at the
+// top level are elements, those in the middle tree are nodes of elements,
+// and so on down.
+#[derive(Clone)]
+enum Item<T, V> {
+    Leaf(T),
+    Node(Rc<Node<T, V>>),
+}
+
+struct Node<T, V> {
+    measure: V,
+    items: Vec<Item<T, V>>,
+}
+
+#[derive(Clone)]
+enum Tree<T, V> {
+    Empty,
+    Single(Item<T, V>),
+    Deep(Rc<Deep<T, V>>),
+}
+
+// One to four items at each end, called digits, around a tree of nodes
+#[derive(Clone)]
+struct Deep<T, V> {
+    measure: V,
+    prefix: Vec<Item<T, V>>,
+    middle: Tree<T, V>,
+    suffix: Vec<Item<T, V>>,
+}
+
+type MItem<T, M> = Item<T, <M as Measure<T>>::Value>;
+type MTree<T, M> = Tree<T, <M as Measure<T>>::Value>;
+// The items before one, that one, and the items after
+type SplitItems<T, M> = (Vec<MItem<T, M>>, MItem<T, M>, Vec<MItem<T, M>>);
+
+/// A persistent sequence as a 2-3 finger tree, after Hinze and Paterson.
+///
+/// The ends of the sequence sit in short buffers at the top, and each level
+/// further down holds nodes of the level above, so pushes and pops at either
+/// end take O(1) amortized and two trees concatenate in O(log n). Every
+/// subtree also caches the combined measure of its elements under `M`, so
+/// `split` can find in O(log n) the point where a running measure first
+/// satisfies a predicate. With `Size` that point is an index; with a
+/// maximum it is the first element reaching some priority.
+///
+/// Clones share every node, and edits copy only the O(log n) nodes they
+/// change.
+pub struct FingerTree<T, M: Measure<T>> {
+    tree: MTree<T, M>,
+    measure: PhantomData<M>,
+}
+
+impl<T: Clone, M: Measure<T>> FingerTree<T, M> {
+    pub fn new() -> Self {
+        Self::from_tree(Tree::Empty)
+    }
+
+    fn from_tree(tree: MTree<T, M>) -> Self {
+        FingerTree { tree, measure: PhantomData }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        matches!(self.tree, Tree::Empty)
+    }
+
+    /// The combined measure of every element.
+    pub fn measure(&self) -> M::Value {
+        Self::tree_measure(&self.tree)
+    }
+
+    fn combine(a: &M::Value, b: &M::Value) -> M::Value {
+        M::Monoid::combine(a, b)
+    }
+
+    fn item_measure(item: &MItem<T, M>) -> M::Value {
+        match item {
+            Item::Leaf(val) => M::measure(val),
+            Item::Node(node) => node.measure.clone(),
+        }
+    }
+
+    fn items_measure(items: &[MItem<T, M>]) -> M::Value {
+        items.iter().fold(M::Monoid::identity(), |acc, item| Self::combine(&acc, &Self::item_measure(item)))
+    }
+
+    fn tree_measure(tree: &MTree<T, M>) -> M::Value {
+        match tree {
+            Tree::Empty => M::Monoid::identity(),
+            Tree::Single(item) => Self::item_measure(item),
+            Tree::Deep(deep) => deep.measure.clone(),
+        }
+    }
+
+    fn node(items: Vec<MItem<T, M>>) -> MItem<T, M> {
+        Item::Node(Rc::new(Node { measure: Self::items_measure(&items), items }))
+    }
+
+    fn node_items(item: MItem<T, M>) -> Vec<MItem<T, M>> {
+        match item {
+            Item::Node(node) => Rc::try_unwrap(node).map_or_else(|node| node.items.clone(), |node| node.items),
+            Item::Leaf(_) => unreachable!("element below the top level"),
+        }
+    }
+
+    fn deep(prefix: Vec<MItem<T, M>>, middle: MTree<T, M>, suffix: Vec<MItem<T, M>>) -> MTree<T, M> {
+        let measure = Self::combine(&Self::combine(&Self::items_measure(&prefix), &Self::tree_measure(&middle)), &Self::items_measure(&suffix));
+        Tree::Deep(Rc::new(Deep { measure, prefix, middle, suffix }))
+    }
+
+    fn from_items(items: Vec<MItem<T, M>>) -> MTree<T, M> {
+        items.into_iter().fold(Tree::Empty, Self::push_back_tree)
+    }
+
+    fn push_front_tree(tree: MTree<T, M>, item: MItem<T, M>) -> MTree<T, M> {
+        match tree {
+            Tree::Empty => Tree::Single(item),
+            Tree::Single(other) => Self::deep(vec![item], Tree::Empty, vec![other]),
+            Tree::Deep(deep) => {
+                let Deep { mut prefix, mut middle, suffix, .. } = Rc::unwrap_or_clone(deep);
+                if prefix.len() == 4 {
+                    // Push the last three down a level as one node
+                    let rest = prefix.split_off(1);
+                    middle = Self::push_front_tree(middle, Self::node(rest));
+                }
+                prefix.insert(0, item);
+                Self::deep(prefix, middle, suffix)
+            }
+        }
+    }
+
+    fn push_back_tree(tree: MTree<T, M>, item: MItem<T, M>) -> MTree<T, M> {
+        match tree {
+            Tree::Empty => Tree::Single(item),
+            Tree::Single(other) => Self::deep(vec![other], Tree::Empty, vec![item]),
+            Tree::Deep(deep) => {
+                let Deep { prefix, mut middle, mut suffix, .. } = Rc::unwrap_or_clone(deep);
+                if suffix.len() == 4 {
+                    let rest = suffix.drain(..3).collect();
+                    middle = Self::push_back_tree(middle, Self::node(rest));
+                }
+                suffix.push(item);
+                Self::deep(prefix, middle, suffix)
+            }
+        }
+    }
+
+    fn view_front(tree: MTree<T, M>) -> Option<(MItem<T, M>, MTree<T, M>)> {
+        match tree {
+            Tree::Empty => None,
+            Tree::Single(item) => Some((item, Tree::Empty)),
+            Tree::Deep(deep) => {
+                let Deep { mut prefix, middle, suffix, .. } = Rc::unwrap_or_clone(deep);
+                let first = prefix.remove(0);
+                Some((first, Self::deep_l(prefix, middle, suffix)))
+            }
+        }
+    }
+
+    fn view_back(tree: MTree<T, M>) -> Option<(MTree<T, M>, MItem<T, M>)> {
+        match tree {
+            Tree::Empty => None,
+            Tree::Single(item) => Some((Tree::Empty, item)),
+            Tree::Deep(deep) => {
+                let Deep { prefix, middle, mut suffix, .. } = Rc::unwrap_or_clone(deep);
+                let last = suffix.pop().unwrap();
+                Some((Self::deep_r(prefix, middle, suffix), last))
+            }
+        }
+    }
+
+    // A deep tree whose prefix may be empty, refilled from the middle
+    fn deep_l(prefix: Vec<MItem<T, M>>, middle: MTree<T, M>, suffix: Vec<MItem<T, M>>) -> MTree<T, M> {
+        if !prefix.is_empty() {
+            return Self::deep(prefix, middle, suffix);
+        }
+        match Self::view_front(middle) {
+            Some((node, middle)) => Self::deep(Self::node_items(node), middle, suffix),
+            None => Self::from_items(suffix),
+        }
+    }
+
+    // A deep tree whose suffix may be empty, refilled from the middle
+    fn deep_r(prefix: Vec<MItem<T, M>>, middle: MTree<T, M>, suffix: Vec<MItem<T, M>>) -> MTree<T, M> {
+        if !suffix.is_empty() {
+            return Self::deep(prefix, middle, suffix);
+        }
+        match Self::view_back(middle) {
+            Some((middle, node)) => Self::deep(prefix, middle, Self::node_items(node)),
+            None => Self::from_items(prefix),
+        }
+    }
+
+    // Group 2 to 12 items into nodes of two or three
+    fn nodes(items: Vec<MItem<T, M>>) -> Vec<MItem<T, M>> {
+        let mut left = items.len();
+        let mut items = items.into_iter();
+        let mut nodes = Vec::new();
+        while left > 0 {
+            let k = if left == 2 || left == 4 { 2 } else { 3 };
+            nodes.push(Self::node(items.by_ref().take(k).collect()));
+            left -= k;
+        }
+        nodes
+    }
+
+    // `a`, then `mid`, then `b`
+    fn concat(a: MTree<T, M>, mid: Vec<MItem<T, M>>, b: MTree<T, M>) -> MTree<T, M> {
+        match (a, b) {
+            (Tree::Empty, b) => mid.into_iter().rev().fold(b, Self::push_front_tree),
+            (a, Tree::Empty) => mid.into_iter().fold(a, Self::push_back_tree),
+            (Tree::Single(item), b) => Self::push_front_tree(Self::concat(Tree::Empty, mid, b), item),
+            (a, Tree::Single(item)) => Self::push_back_tree(Self::concat(a, mid, Tree::Empty), item),
+            (Tree::Deep(a), Tree::Deep(b)) => {
+                let a = Rc::unwrap_or_clone(a);
+                let b = Rc::unwrap_or_clone(b);
+                let mut items = a.suffix;
+                items.extend(mid);
+                items.extend(b.prefix);
+                Self::deep(a.prefix, Self::concat(a.middle, Self::nodes(items), b.middle), b.suffix)
+            }
+        }
+    }
+
+    // Split `items` around the first one at which `pred` holds, or the last
+    fn split_items<F: Fn(&M::Value) -> bool>(pred: &F, mut acc: M::Value, mut items: Vec<MItem<T, M>>) -> SplitItems<T, M> {
+        let mut at = items.len() - 1;
+        for (i, item) in items.iter().enumerate() {
+            acc = Self::combine(&acc, &Self::item_measure(item));
+            if pred(&acc) {
+                at = i;
+                break;
+            }
+        }
+        let after = items.split_off(at + 1);
+        let item = items.pop().unwrap();
+        (items, item, after)
+    }
+
+    // Split a non-empty tree around the item at which `pred` first holds,
+    // starting from the measure `acc` of everything before the tree
+    fn split_tree<F: Fn(&M::Value) -> bool>(pred: &F, acc: M::Value, tree: MTree<T, M>) -> (MTree<T, M>, MItem<T, M>, MTree<T, M>) {
+        let deep = match tree {
+            Tree::Empty => unreachable!("splitting an empty tree"),
+            Tree::Single(item) => return (Tree::Empty, item, Tree::Empty),
+            Tree::Deep(deep) => Rc::unwrap_or_clone(deep),
+        };
+        let Deep { prefix, middle, suffix, .. } = deep;
+        let after_prefix = Self::combine(&acc, &Self::items_measure(&prefix));
+        if pred(&after_prefix) {
+            let (before, item, after) = Self::split_items(pred, acc, prefix);
+            return (Self::from_items(before), item, Self::deep_l(after, middle, suffix));
+        }
+        let after_middle = Self::combine(&after_prefix, &Self::tree_measure(&middle));
+        if pred(&after_middle) {
+            let (middle_before, node, middle_after) = Self::split_tree(pred, after_prefix.clone(), middle);
+            let acc = Self::combine(&after_prefix, &Self::tree_measure(&middle_before));
+            let (before, item, after) = Self::split_items(pred, acc, Self::node_items(node));
+            return (Self::deep_r(prefix, middle_before, before), item, Self::deep_l(after, middle_after, suffix));
+        }
+        let (before, item, after) = Self::split_items(pred, after_middle, suffix);
+        (Self::deep_r(prefix, middle, before), item, Self::from_items(after))
+    }
+
+    pub fn push_front(&mut self, val: T) {
+        self.tree = Self::push_front_tree(std::mem::replace(&mut self.tree, Tree::Empty), Item::Leaf(val));
+    }
+
+    pub fn push_back(&mut self, val: T) {
+        self.tree = Self::push_back_tree(std::mem::replace(&mut self.tree, Tree::Empty), Item::Leaf(val));
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        let (item, rest) = Self::view_front(std::mem::replace(&mut self.tree, Tree::Empty))?;
+        self.tree = rest;
+        match item {
+            Item::Leaf(val) => Some(val),
+            Item::Node(_) => unreachable!("node at the top level"),
+        }
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        let (rest, item) = Self::view_back(std::mem::replace(&mut self.tree, Tree::Empty))?;
+        self.tree = rest;
+        match item {
+            Item::Leaf(val) => Some(val),
+            Item::Node(_) => unreachable!("node at the top level"),
+        }
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.iter().next()
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        let mut item = match &self.tree {
+            Tree::Empty => return None,
+            Tree::Single(item) => item,
+            Tree::Deep(deep) => deep.suffix.last().unwrap(),
+        };
+        loop {
+            match item {
+                Item::Leaf(val) => return Some(val),
+                Item::Node(node) => item = node.items.last().unwrap(),
+            }
+        }
+    }
+
+    /// Move every element of `other` onto the end of `self`, in O(log n).
+    pub fn append(&mut self, other: Self) {
+        self.tree = Self::concat(std::mem::replace(&mut self.tree, Tree::Empty), Vec::new(), other.tree);
+    }
+
+    /// Split the sequence before the first element at which the measure of
+    /// everything up to and including it satisfies `pred`. If there is no
+    /// such element, the second half is empty.
+    ///
+    /// `pred` must be monotonic: once true for some prefix, it must be true
+    /// for every longer one.
+    pub fn split<F: Fn(&M::Value) -> bool>(&self, pred: F) -> (Self, Self) {
+        if self.is_empty() || !pred(&self.measure()) {
+            return (self.clone(), Self::new());
+        }
+        let (before, item, after) = Self::split_tree(&pred, M::Monoid::identity(), self.tree.clone());
+        (Self::from_tree(before), Self::from_tree(Self::push_front_tree(after, item)))
+    }
+
+    /// The first element at which the measure of everything up to and
+    /// including it satisfies `pred`, found without splitting. `pred` must be
+    /// monotonic, as for `split`.
+    pub fn find<F: Fn(&M::Value) -> bool>(&self, pred: F) -> Option<&T> {
+        let mut acc = M::Monoid::identity();
+        let mut tree = &self.tree;
+        let mut items: &[MItem<T, M>];
+        // Narrow down to the digit or node holding the element, then descend
+        loop {
+            match tree {
+                Tree::Empty => return None,
+                Tree::Single(item) => {
+                    items = std::slice::from_ref(item);
+                    break;
+                }
+                Tree::Deep(deep) => {
+                    let after_prefix = Self::combine(&acc, &Self::items_measure(&deep.prefix));
+                    if pred(&after_prefix) {
+                        items = &deep.prefix;
+                        break;
+                    }
+                    let after_middle = Self::combine(&after_prefix, &Self::tree_measure(&deep.middle));
+                    if pred(&after_middle) {
+                        acc = after_prefix;
+                        tree = &deep.middle;
+                        continue;
+                    }
+                    acc = after_middle;
+                    items = &deep.suffix;
+                    break;
+                }
+            }
+        }
+        loop {
+            let mut found = None;
+            for item in items {
+                let next = Self::combine(&acc, &Self::item_measure(item));
+                if pred(&next) {
+                    found = Some(item);
+                    break;
+                }
+                acc = next;
+            }
+            match found? {
+                Item::Leaf(val) => return Some(val),
+                Item::Node(node) => items = &node.items,
+            }
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T, M::Value> {
+        Iter { stack: vec![Frame::Tree(&self.tree)] }
+    }
+}
+
+#[cfg(any(test, debug_assertions))]
+impl<T: Clone, M: Measure<T>> FingerTree<T, M>
+where
+    M::Value: PartialEq + Debug,
+{
+    /// Panic unless every digit holds one to four items, every node two or
+    /// three, elements sit only at the top level, and the cached measures
+    /// are right. Only in debug builds and tests.
+    pub fn validate(&self) {
+        Self::validate_tree(&self.tree, 0);
+    }
+
+    // Check a tree whose items are `depth` levels down, returning its measure
+    fn validate_tree(tree: &MTree<T, M>, depth: usize) -> M::Value {
+        match tree {
+            Tree::Empty => M::Monoid::identity(),
+            Tree::Single(item) => Self::validate_item(item, depth),
+            Tree::Deep(deep) => {
+                assert!((1..=4).contains(&deep.prefix.len()) && (1..=4).contains(&deep.suffix.len()), "digit of the wrong size");
+                let measure = Self::combine(
+                    &Self::combine(&Self::validate_items(&deep.prefix, depth), &Self::validate_tree(&deep.middle, depth + 1)),
+                    &Self::validate_items(&deep.suffix, depth),
+                );
+                assert_eq!(deep.measure, measure, "stale deep measure");
+                measure
+            }
+        }
+    }
+
+    fn validate_items(items: &[MItem<T, M>], depth: usize) -> M::Value {
+        items.iter().fold(M::Monoid::identity(), |acc, item| Self::combine(&acc, &Self::validate_item(item, depth)))
+    }
+
+    fn validate_item(item: &MItem<T, M>, depth: usize) -> M::Value {
+        match item {
+            Item::Leaf(val) => {
+                assert_eq!(depth, 0, "element below the top level");
+                M::measure(val)
+            }
+            Item::Node(node) => {
+                assert!(depth > 0, "node at the top level");
+                assert!((2..=3).contains(&node.items.len()), "node of {} items", node.items.len());
+                let measure = Self::validate_items(&node.items, depth - 1);
+                assert_eq!(node.measure, measure, "stale node measure");
+                measure
+            }
+        }
+    }
+}
+
+impl<T: Clone> FingerTree<T, Size> {
+    pub fn len(&self) -> usize {
+        self.measure()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.find(|&len| len > index)
+    }
+
+    /// The first `at` elements and the rest.
+    pub fn split_at(&self, at: usize) -> (Self, Self) {
+        self.split(|&len| len > at)
+    }
+}
+
+impl<T: Clone, M: Measure<T>> Clone for FingerTree<T, M> {
+    fn clone(&self) -> Self {
+        Self::from_tree(self.tree.clone())
+    }
+}
+
+impl<T: Clone, M: Measure<T>> Default for FingerTree<T, M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+enum Frame<'a, T, V> {
+    Tree(&'a Tree<T, V>),
+    Item(&'a Item<T, V>),
+}
+
+pub struct Iter<'a, T, V> {
+    // What is still to visit, the next part on top
+    stack: Vec<Frame<'a, T, V>>,
+}
+
+impl<'a, T, V> Iterator for Iter<'a, T, V> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.pop()? {
+                Frame::Item(Item::Leaf(val)) => return Some(val),
+                Frame::Item(Item::Node(node)) => self.stack.extend(node.items.iter().rev().map(Frame::Item)),
+                Frame::Tree(Tree::Empty) => {}
+                Frame::Tree(Tree::Single(item)) => self.stack.push(Frame::Item(item)),
+                Frame::Tree(Tree::Deep(deep)) => {
+                    self.stack.extend(deep.suffix.iter().rev().map(Frame::Item));
+                    self.stack.push(Frame::Tree(&deep.middle));
+                    self.stack.extend(deep.prefix.iter().rev().map(Frame::Item));
+                }
+            }
+        }
+    }
+}
+
+impl<'a, T: Clone, M: Measure<T>> IntoIterator for &'a FingerTree<T, M> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, M::Value>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Clone, M: Measure<T>> FromIterator<T> for FingerTree<T, M> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut tree = Self::new();
+        for val in iter {
+            tree.push_back(val);
+        }
+        tree
+    }
+}
+
+impl<T: Clone + Debug, M: Measure<T>> Debug for FingerTree<T, M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: HeapSize, V> HeapSize for Item<T, V> {
+    fn heap_size(&self) -> usize {
+        match self {
+            Item::Leaf(elem) => elem.heap_size(),
+            Item::Node(node) => rc_size::<Node<T, V>>() + node.items.heap_size(),
+        }
+    }
+}
+
+impl<T: HeapSize, V> HeapSize for Tree<T, V> {
+    fn heap_size(&self) -> usize {
+        match self {
+            Tree::Empty => 0,
+            Tree::Single(item) => item.heap_size(),
+            Tree::Deep(deep) => {
+                rc_size::<Deep<T, V>>() + deep.prefix.heap_size() + deep.middle.heap_size() + deep.suffix.heap_size()
+            }
+        }
+    }
+}
+
+/// Counts every node the tree can reach, including ones it shares with
+/// other versions. Measures are counted at their inline size only.
+impl<T: HeapSize, M: Measure<T>> HeapSize for FingerTree<T, M> {
+    fn heap_size(&self) -> usize {
+        self.tree.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use crate::heap_size::HeapSize;
+    use crate::rng::XorShift64;
+    use crate::sliding_window::Max;
+
+    use super::{FingerTree, Measure, Size};
+
+    type Seq<T> = FingerTree<T, Size>;
+
+    // Measures by the largest element, for priority searches
+    struct Largest;
+
+    impl Measure<i64> for Largest {
+        type Value = i64;
+        type Monoid = Max;
+
+        fn measure(item: &i64) -> i64 {
+            *item
+        }
+    }
+
+    #[test]
+    fn test_deque_ops() {
+        let mut seq = Seq::new();
+        assert!(seq.is_empty());
+        let mut model = VecDeque::new();
+        let mut rng = XorShift64::new();
+        for i in 0..5000 {
+            match rng.next_u64() % 4 {
+                0 => {
+                    seq.push_front(i);
+                    model.push_front(i);
+                }
+                1 => {
+                    seq.push_back(i);
+                    model.push_back(i);
+                }
+                2 => assert_eq!(seq.pop_front(), model.pop_front()),
+                _ => assert_eq!(seq.pop_back(), model.pop_back()),
+            }
+            assert_eq!(seq.len(), model.len());
+            assert_eq!((seq.front(), seq.back()), (model.front(), model.back()));
+            if i % 100 == 0 {
+                seq.validate();
+            }
+        }
+        seq.validate();
+        assert!(seq.iter().eq(model.iter()));
+    }
+
+    #[test]
+    fn test_index_split_concat() {
+        let seq: Seq<_> = (0..1000).collect();
+        assert_eq!(seq.len(), 1000);
+        assert!((0..1000).all(|i| seq.get(i) == Some(&i)));
+        assert_eq!(seq.get(1000), None);
+
+        seq.validate();
+        let (left, right) = seq.split_at(400);
+        left.validate();
+        right.validate();
+        assert!(left.iter().copied().eq(0..400));
+        assert!(right.iter().copied().eq(400..1000));
+        let (all, none) = seq.split_at(1000);
+        assert_eq!((all.len(), none.len()), (1000, 0));
+
+        let mut joined = right.clone();
+        joined.append(left.clone());
+        joined.validate();
+        assert!(joined.iter().copied().eq((400..1000).chain(0..400)));
+        assert_eq!(joined.get(600), Some(&0));
+        // The originals are untouched
+        assert_eq!((left.len(), right.len(), seq.len()), (400, 600, 1000));
+        assert_eq!(format!("{:?}", left.split_at(3).0), "[0, 1, 2]");
+    }
+
+    #[test]
+    fn test_random_split_concat() {
+        let mut rng = XorShift64::new();
+        let mut seq: Seq<_> = (0..2000).collect();
+        let mut model: Vec<_> = (0..2000).collect();
+        for _ in 0..300 {
+            let at = rng.next_u64() as usize % (model.len() + 1);
+            let (left, right) = seq.split_at(at);
+            let model_right = model.split_off(at);
+            assert!(left.iter().eq(model.iter()) && right.iter().eq(model_right.iter()));
+            // Swap the halves
+            seq = right;
+            seq.append(left);
+            model = model_right.into_iter().chain(model).collect();
+            assert_eq!(seq.len(), model.len());
+            seq.validate();
+        }
+        assert!(seq.iter().eq(model.iter()));
+    }
+
+    #[test]
+    fn test_priority_measure() {
+        let tree: FingerTree<i64, Largest> = [3, 1, 4, 1, 5, 9, 2, 6].into_iter().collect();
+        assert_eq!(tree.measure(), 9);
+        assert_eq!(tree.find(|&max| max >= 5), Some(&5));
+        assert_eq!(tree.find(|&max| max >= 10), None);
+        let (before, from) = tree.split(|&max| max >= 9);
+        assert_eq!(before.iter().copied().collect::<Vec<_>>(), vec![3, 1, 4, 1, 5]);
+        assert_eq!(from.measure(), 9);
+        before.validate();
+        from.validate();
+        assert_eq!(from.front(), Some(&9));
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let mut seq = Seq::new();
+        assert_eq!(seq.heap_size(), 0);
+        seq.push_back(String::from("abc"));
+        assert_eq!(seq.heap_size(), 3);
+        for i in 0..100 {
+            seq.push_back(i.to_string());
+        }
+        assert!(seq.heap_size() > 100 * size_of::<String>());
+    }
+}
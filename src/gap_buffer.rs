@@ -0,0 +1,497 @@
+use std::fmt::Debug;
+
+use crate::error::{CollectionError, check_position};
+use crate::fallible::AllocError;
+use crate::heap_size::{HeapSize, payload};
+use crate::sequence::{LendingCursor, SeqCursor};
+
+/// A sequence stored in one buffer with a movable gap of free slots.
+///
+/// Insertions and removals at the gap are O(1); moving the gap costs time
+/// proportional to the distance moved. Edits that stay close together, like
+/// typing in an editor, are therefore cheap.
+pub struct GapBuffer<T> {
+    // Slots in `gap_start..gap_end` are always `None`, all others `Some`.
+    buf: Vec<Option<T>>,
+    gap_start: usize,
+    gap_end: usize,
+}
+
+impl<T> GapBuffer<T> {
+    pub const fn new() -> Self {
+        GapBuffer { buf: Vec::new(), gap_start: 0, gap_end: 0 }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        GapBuffer {
+            buf: std::iter::repeat_with(|| None).take(capacity).collect(),
+            gap_start: 0,
+            gap_end: capacity,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.buf.len() - (self.gap_end - self.gap_start)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Index of the element right after the gap.
+    pub fn gap_position(&self) -> usize {
+        self.gap_start
+    }
+
+    /// Panic unless the gap lies within the buffer and exactly its slots are
+    /// empty. Only in debug builds and tests.
+    #[cfg(any(test, debug_assertions))]
+    pub fn validate(&self) {
+        assert!(self.gap_start <= self.gap_end && self.gap_end <= self.buf.len(), "gap out of bounds");
+        for (i, slot) in self.buf.iter().enumerate() {
+            let in_gap = (self.gap_start..self.gap_end).contains(&i);
+            assert_eq!(slot.is_none(), in_gap, "slot {i} is {}", if in_gap { "filled inside the gap" } else { "empty outside the gap" });
+        }
+    }
+
+    fn physical(&self, index: usize) -> usize {
+        if index < self.gap_start {
+            index
+        } else {
+            index + (self.gap_end - self.gap_start)
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len() {
+            return None;
+        }
+        self.buf[self.physical(index)].as_ref()
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index >= self.len() {
+            return None;
+        }
+        let i = self.physical(index);
+        self.buf[i].as_mut()
+    }
+
+    /// Move the gap so that it starts at `index`.
+    ///
+    /// Panics if `index > len`.
+    pub fn move_gap(&mut self, index: usize) {
+        assert!(index <= self.len(), "gap index (is {index}) should be <= len (is {})", self.len());
+        while self.gap_start > index {
+            self.gap_start -= 1;
+            self.gap_end -= 1;
+            self.buf.swap(self.gap_start, self.gap_end);
+        }
+        while self.gap_start < index {
+            self.buf.swap(self.gap_start, self.gap_end);
+            self.gap_start += 1;
+            self.gap_end += 1;
+        }
+    }
+
+    // How many slots the next `grow` adds
+    fn growth(&self) -> usize {
+        self.buf.len().max(8)
+    }
+
+    fn grow(&mut self) {
+        let extra = self.growth();
+        self.buf.splice(self.gap_end..self.gap_end, std::iter::repeat_with(|| None).take(extra));
+        self.gap_end += extra;
+    }
+
+    /// Insert an element at `index`, moving the gap there first.
+    ///
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, val: T) {
+        self.move_gap(index);
+        if self.gap_start == self.gap_end {
+            self.grow();
+        }
+        self.buf[self.gap_start] = Some(val);
+        self.gap_start += 1;
+    }
+
+    /// `move_gap`, but returning an error if `index > len`.
+    pub fn try_move_gap(&mut self, index: usize) -> Result<(), CollectionError> {
+        check_position(index, self.len())?;
+        self.move_gap(index);
+        Ok(())
+    }
+
+    /// `insert`, but returning an error if `index > len`, or if the gap has
+    /// to grow and the allocation fails. The elements are unchanged either
+    /// way, though the gap may have moved.
+    pub fn try_insert(&mut self, index: usize, val: T) -> Result<(), CollectionError> {
+        check_position(index, self.len())?;
+        Ok(self.try_insert_at(index, val)?)
+    }
+
+    // `try_insert` with an index known to be in bounds
+    fn try_insert_at(&mut self, index: usize, val: T) -> Result<(), AllocError> {
+        self.move_gap(index);
+        if self.gap_start == self.gap_end {
+            // With the room reserved up front, growing can't allocate
+            self.buf.try_reserve_exact(self.growth())?;
+            self.grow();
+        }
+        self.buf[self.gap_start] = Some(val);
+        self.gap_start += 1;
+        Ok(())
+    }
+
+    /// Remove and return the element at `index`, moving the gap there first.
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        if index >= self.len() {
+            return None;
+        }
+        self.move_gap(index);
+        let val = self.buf[self.gap_end].take();
+        self.gap_end += 1;
+        val
+    }
+
+    pub fn push_back(&mut self, val: T) {
+        self.insert(self.len(), val);
+    }
+
+    pub fn try_push_back(&mut self, val: T) -> Result<(), AllocError> {
+        self.try_insert_at(self.len(), val)
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.remove(self.len().checked_sub(1)?)
+    }
+
+    pub fn clear(&mut self) {
+        self.buf.clear();
+        self.gap_start = 0;
+        self.gap_end = 0;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.buf[..self.gap_start].iter().chain(&self.buf[self.gap_end..]).flatten()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        let (front, back) = self.buf.split_at_mut(self.gap_end);
+        front[..self.gap_start].iter_mut().chain(back).flatten()
+    }
+
+    pub fn cursor_front(&mut self) -> Cursor<'_, T> {
+        let current = if self.is_empty() { None } else { Some(0) };
+        Cursor { buffer: self, current }
+    }
+}
+
+impl<T> Default for GapBuffer<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<Vec<T>> for GapBuffer<T> {
+    fn from(vec: Vec<T>) -> Self {
+        let buf: Vec<Option<T>> = vec.into_iter().map(Some).collect();
+        let len = buf.len();
+        GapBuffer { buf, gap_start: len, gap_end: len }
+    }
+}
+
+impl<T> From<GapBuffer<T>> for Vec<T> {
+    fn from(buffer: GapBuffer<T>) -> Self {
+        buffer.buf.into_iter().flatten().collect()
+    }
+}
+
+impl<T> FromIterator<T> for GapBuffer<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        GapBuffer::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl<T: Debug> Debug for GapBuffer<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// A cursor over a gap buffer with the same interface as the linked list
+/// cursor. Edits made through the cursor move the gap to the cursor, so a run
+/// of nearby edits only shifts elements once.
+///
+/// When the cursor is not on any element it sits on a "ghost" position
+/// between the back and the front of the buffer.
+pub struct Cursor<'a, T> {
+    buffer: &'a mut GapBuffer<T>,
+    current: Option<usize>,
+}
+
+impl<T> Cursor<'_, T> {
+    /// Index of the current element, or `None` on the ghost position
+    pub fn index(&self) -> Option<usize> {
+        self.current
+    }
+
+    /// Take a mutable reference to the current element
+    pub fn peek_mut(&mut self) -> Option<&mut T> {
+        self.buffer.get_mut(self.current?)
+    }
+
+    /// Move one position forward (towards the back) and
+    /// return a reference to the new position
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<&mut T> {
+        let len = self.buffer.len();
+        self.current = match self.current {
+            Some(i) if i + 1 < len => Some(i + 1),
+            Some(_) => None,
+            None if len > 0 => Some(0),
+            None => None,
+        };
+        self.peek_mut()
+    }
+
+    /// Move one position backward (towards the front) and
+    /// return a reference to the new position
+    pub fn prev(&mut self) -> Option<&mut T> {
+        self.current = match self.current {
+            Some(i) => i.checked_sub(1),
+            None => self.buffer.len().checked_sub(1),
+        };
+        self.peek_mut()
+    }
+
+    /// Remove and return the element at the current position and move the cursor
+    /// to the neighboring element that's closest to the back. This can be
+    /// either the next or previous position.
+    pub fn take(&mut self) -> Option<T> {
+        let i = self.current?;
+        let val = self.buffer.remove(i);
+        let len = self.buffer.len();
+        self.current = if i < len { Some(i) } else { len.checked_sub(1) };
+        val
+    }
+
+    /// Insert after the current element, or at the front when on the ghost
+    /// position
+    pub fn insert_after(&mut self, element: T) {
+        match self.current {
+            Some(i) => self.buffer.insert(i + 1, element),
+            None => self.buffer.insert(0, element),
+        }
+    }
+
+    /// Insert before the current element, or at the back when on the ghost
+    /// position
+    pub fn insert_before(&mut self, element: T) {
+        match self.current {
+            Some(i) => {
+                self.buffer.insert(i, element);
+                self.current = Some(i + 1);
+            }
+            None => self.buffer.push_back(element),
+        }
+    }
+}
+
+impl<T> SeqCursor<T> for Cursor<'_, T> {
+    fn peek_mut(&mut self) -> Option<&mut T> {
+        self.peek_mut()
+    }
+
+    fn next(&mut self) -> Option<&mut T> {
+        self.next()
+    }
+
+    fn prev(&mut self) -> Option<&mut T> {
+        self.prev()
+    }
+
+    fn take(&mut self) -> Option<T> {
+        self.take()
+    }
+
+    fn insert_after(&mut self, element: T) {
+        self.insert_after(element);
+    }
+
+    fn insert_before(&mut self, element: T) {
+        self.insert_before(element);
+    }
+}
+
+impl<T> LendingCursor for Cursor<'_, T> {
+    type Elem = T;
+
+    type Item<'a>
+        = &'a mut T
+    where
+        Self: 'a;
+
+    fn peek(&mut self) -> Option<Self::Item<'_>> {
+        self.peek_mut()
+    }
+
+    fn move_next(&mut self) -> bool {
+        self.next();
+        true
+    }
+
+    fn move_prev(&mut self) -> bool {
+        self.prev();
+        true
+    }
+}
+
+impl<T: HeapSize> HeapSize for GapBuffer<T> {
+    fn heap_size(&self) -> usize {
+        self.buf.capacity() * size_of::<Option<T>>() + payload(self.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::CollectionError;
+
+    use super::GapBuffer;
+
+    fn contents(buffer: &GapBuffer<i32>) -> Vec<i32> {
+        buffer.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_new() {
+        let buffer: GapBuffer<i32> = GapBuffer::new();
+        assert_eq!(buffer.len(), 0);
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.get(0), None);
+    }
+
+    #[test]
+    fn test_insert_remove() {
+        let mut buffer = GapBuffer::new();
+        buffer.insert(0, 1);
+        buffer.insert(1, 3);
+        buffer.insert(1, 2);
+        buffer.insert(0, 0);
+        assert_eq!(contents(&buffer), vec![0, 1, 2, 3]);
+        assert_eq!(buffer.gap_position(), 1);
+
+        assert_eq!(buffer.remove(2), Some(2));
+        assert_eq!(buffer.remove(5), None);
+        assert_eq!(contents(&buffer), vec![0, 1, 3]);
+        assert_eq!(buffer.get(2), Some(&3));
+        assert_eq!(buffer.len(), 3);
+    }
+
+    #[test]
+    fn test_move_gap() {
+        let mut buffer: GapBuffer<i32> = (0..10).collect();
+        for i in [0, 10, 5, 3, 7] {
+            buffer.move_gap(i);
+            assert_eq!(buffer.gap_position(), i);
+            assert_eq!(contents(&buffer), (0..10).collect::<Vec<_>>());
+        }
+        *buffer.get_mut(7).unwrap() = 70;
+        assert_eq!(buffer.get(7), Some(&70));
+    }
+
+    #[test]
+    fn test_vec_conversion() {
+        let buffer = GapBuffer::from(vec![1, 2, 3]);
+        assert_eq!(buffer.len(), 3);
+        let mut buffer = buffer;
+        buffer.insert(1, 10);
+        buffer.push_back(4);
+        let vec: Vec<i32> = buffer.into();
+        assert_eq!(vec, vec![1, 10, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_growth() {
+        let mut buffer = GapBuffer::with_capacity(2);
+        for i in 0..100 {
+            buffer.insert(i / 2, i);
+        }
+        assert_eq!(buffer.len(), 100);
+        assert!(buffer.capacity() >= 100);
+        for item in buffer.iter_mut() {
+            *item += 1;
+        }
+        let last = buffer.get(99).copied();
+        assert_eq!(buffer.pop_back(), last);
+        assert_eq!(buffer.len(), 99);
+    }
+
+    #[test]
+    fn test_try_insert() {
+        let mut buffer = GapBuffer::new();
+        let mut expected = Vec::new();
+        for i in 0..100 {
+            assert_eq!(buffer.try_insert(i / 2, i), Ok(()));
+            expected.insert(i / 2, i);
+        }
+        assert!(buffer.iter().eq(expected.iter()));
+        assert_eq!(buffer.try_push_back(100), Ok(()));
+        assert_eq!(buffer.len(), 101);
+        assert_eq!(buffer.try_insert(102, 0), Err(CollectionError::IndexOutOfBounds { index: 102, len: 101 }));
+        assert_eq!(buffer.try_move_gap(102), Err(CollectionError::IndexOutOfBounds { index: 102, len: 101 }));
+        assert_eq!(buffer.try_move_gap(101), Ok(()));
+        assert_eq!(buffer.len(), 101);
+    }
+
+    #[test]
+    fn test_cursor_next_prev() {
+        let mut buffer = GapBuffer::from(vec![1, 2, 3]);
+        let mut cursor = buffer.cursor_front();
+        assert_eq!(cursor.peek_mut(), Some(&mut 1));
+        assert_eq!(cursor.next(), Some(&mut 2));
+        assert_eq!(cursor.next(), Some(&mut 3));
+        assert_eq!(cursor.next(), None);
+        assert_eq!(cursor.prev(), Some(&mut 3));
+        assert_eq!(cursor.prev(), Some(&mut 2));
+        assert_eq!(cursor.prev(), Some(&mut 1));
+        assert_eq!(cursor.prev(), None);
+        assert_eq!(cursor.next(), Some(&mut 1));
+    }
+
+    #[test]
+    fn test_cursor_empty() {
+        let mut buffer: GapBuffer<i32> = GapBuffer::new();
+        let mut cursor = buffer.cursor_front();
+        assert_eq!(cursor.peek_mut(), None);
+        assert_eq!(cursor.next(), None);
+        assert_eq!(cursor.take(), None);
+        cursor.insert_after(1);
+        cursor.insert_before(2);
+        assert_eq!(cursor.next(), Some(&mut 1));
+        assert_eq!(contents(&buffer), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_cursor_edit() {
+        let mut buffer = GapBuffer::from(vec![1, 2, 3]);
+        let mut cursor = buffer.cursor_front();
+        cursor.next();
+        cursor.insert_before(10);
+        cursor.insert_after(20);
+        assert_eq!(cursor.peek_mut(), Some(&mut 2));
+        assert_eq!(cursor.take(), Some(2));
+        assert_eq!(cursor.peek_mut(), Some(&mut 20));
+        cursor.next();
+        assert_eq!(cursor.take(), Some(3));
+        assert_eq!(cursor.peek_mut(), Some(&mut 20));
+        buffer.validate();
+        assert_eq!(contents(&buffer), vec![1, 10, 20]);
+    }
+}
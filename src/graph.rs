@@ -0,0 +1,601 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt::Debug;
+use std::ops::Add;
+
+use crate::doubly_list::LinkedList;
+use crate::error::{CollectionError, check_index};
+use crate::heap_size::HeapSize;
+use crate::list::List;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EdgeId(usize);
+
+impl NodeId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+impl EdgeId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+struct NodeSlot<N> {
+    data: N,
+    // Outgoing edges as (target, edge). Undirected edges appear in the
+    // lists of both endpoints.
+    adj: List<(NodeId, EdgeId)>,
+}
+
+struct EdgeSlot<E> {
+    from: NodeId,
+    to: NodeId,
+    data: E,
+}
+
+/// A directed or undirected graph with node data `N` and edge data `E`.
+///
+/// Each node keeps its adjacency as a `List`, so adding an edge is O(1) and
+/// neighbor iteration walks that list (most recently added edge first).
+/// Node and edge ids stay valid until the node or edge is removed and are
+/// never reused.
+pub struct Graph<N, E> {
+    nodes: Vec<Option<NodeSlot<N>>>,
+    edges: Vec<Option<EdgeSlot<E>>>,
+    directed: bool,
+    node_count: usize,
+    edge_count: usize,
+}
+
+impl<N, E> Graph<N, E> {
+    pub fn new_directed() -> Self {
+        Graph { nodes: Vec::new(), edges: Vec::new(), directed: true, node_count: 0, edge_count: 0 }
+    }
+
+    pub fn new_undirected() -> Self {
+        Graph { directed: false, ..Self::new_directed() }
+    }
+
+    pub fn is_directed(&self) -> bool {
+        self.directed
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.node_count
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edge_count
+    }
+
+    pub fn add_node(&mut self, data: N) -> NodeId {
+        self.nodes.push(Some(NodeSlot { data, adj: List::new() }));
+        self.node_count += 1;
+        NodeId(self.nodes.len() - 1)
+    }
+
+    fn slot_mut(&mut self, id: NodeId) -> &mut NodeSlot<N> {
+        self.nodes.get_mut(id.0).and_then(Option::as_mut).expect("no such node")
+    }
+
+    /// Add an edge from `from` to `to` (in both directions for undirected
+    /// graphs).
+    ///
+    /// Panics if either node does not exist.
+    pub fn add_edge(&mut self, from: NodeId, to: NodeId, data: E) -> EdgeId {
+        assert!(self.contains_node(to), "no such node");
+        let id = EdgeId(self.edges.len());
+        self.slot_mut(from).adj.push_front((to, id));
+        if !self.directed && from != to {
+            self.slot_mut(to).adj.push_front((from, id));
+        }
+        self.edges.push(Some(EdgeSlot { from, to, data }));
+        self.edge_count += 1;
+        id
+    }
+
+    /// `add_edge`, but returning an error if either node does not exist:
+    /// `IndexOutOfBounds` for an id this graph never handed out, and
+    /// `StaleHandle` for a removed node.
+    pub fn try_add_edge(&mut self, from: NodeId, to: NodeId, data: E) -> Result<EdgeId, CollectionError> {
+        for id in [from, to] {
+            check_index(id.0, self.nodes.len())?;
+            if !self.contains_node(id) {
+                return Err(CollectionError::StaleHandle);
+            }
+        }
+        Ok(self.add_edge(from, to, data))
+    }
+
+    pub fn remove_edge(&mut self, id: EdgeId) -> Option<E> {
+        let edge = self.edges.get_mut(id.0)?.take()?;
+        self.slot_mut(edge.from).adj.retain(|&(_, e)| e != id);
+        if !self.directed {
+            self.slot_mut(edge.to).adj.retain(|&(_, e)| e != id);
+        }
+        self.edge_count -= 1;
+        Some(edge.data)
+    }
+
+    /// Remove a node along with every edge touching it. Finding incoming
+    /// edges takes a scan over all edges.
+    pub fn remove_node(&mut self, id: NodeId) -> Option<N> {
+        self.node(id)?;
+        let incident: Vec<EdgeId> = self
+            .edges
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.as_ref().is_some_and(|e| e.from == id || e.to == id))
+            .map(|(i, _)| EdgeId(i))
+            .collect();
+        for edge in incident {
+            self.remove_edge(edge);
+        }
+        self.node_count -= 1;
+        self.nodes[id.0].take().map(|slot| slot.data)
+    }
+
+    pub fn contains_node(&self, id: NodeId) -> bool {
+        self.node(id).is_some()
+    }
+
+    pub fn node(&self, id: NodeId) -> Option<&N> {
+        self.nodes.get(id.0)?.as_ref().map(|slot| &slot.data)
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> Option<&mut N> {
+        self.nodes.get_mut(id.0)?.as_mut().map(|slot| &mut slot.data)
+    }
+
+    pub fn edge(&self, id: EdgeId) -> Option<&E> {
+        self.edges.get(id.0)?.as_ref().map(|edge| &edge.data)
+    }
+
+    pub fn edge_mut(&mut self, id: EdgeId) -> Option<&mut E> {
+        self.edges.get_mut(id.0)?.as_mut().map(|edge| &mut edge.data)
+    }
+
+    pub fn edge_endpoints(&self, id: EdgeId) -> Option<(NodeId, NodeId)> {
+        self.edges.get(id.0)?.as_ref().map(|edge| (edge.from, edge.to))
+    }
+
+    /// Ids of all nodes, in insertion order.
+    pub fn node_ids(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes.iter().enumerate().filter(|(_, n)| n.is_some()).map(|(i, _)| NodeId(i))
+    }
+
+    /// Ids of all edges, in insertion order.
+    pub fn edge_ids(&self) -> impl Iterator<Item = EdgeId> + '_ {
+        self.edges.iter().enumerate().filter(|(_, e)| e.is_some()).map(|(i, _)| EdgeId(i))
+    }
+
+    /// Nodes reachable from `id` over one edge.
+    ///
+    /// Panics if the node does not exist.
+    pub fn neighbors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.edges_from(id).map(|(to, _, _)| to)
+    }
+
+    /// Edges leaving `id` as `(target, edge id, edge data)`.
+    ///
+    /// Panics if the node does not exist.
+    pub fn edges_from(&self, id: NodeId) -> impl Iterator<Item = (NodeId, EdgeId, &E)> + '_ {
+        let slot = self.nodes.get(id.0).and_then(Option::as_ref).expect("no such node");
+        slot.adj.iter().map(|&(to, e)| (to, e, self.edge(e).unwrap()))
+    }
+
+    /// Visit every node reachable from `start` in breadth-first order.
+    ///
+    /// Panics if the node does not exist.
+    pub fn bfs<F: FnMut(NodeId, &N)>(&self, start: NodeId, mut visit: F) {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut queue = LinkedList::new();
+        seen[start.0] = true;
+        queue.push_back(start);
+        while let Some(id) = queue.pop_front() {
+            visit(id, self.node(id).unwrap());
+            for next in self.neighbors(id) {
+                if !std::mem::replace(&mut seen[next.0], true) {
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    /// Visit every node reachable from `start` in depth-first preorder.
+    ///
+    /// Panics if the node does not exist.
+    pub fn dfs<F: FnMut(NodeId, &N)>(&self, start: NodeId, mut visit: F) {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut stack = List::new();
+        stack.push_front(start);
+        while let Some(id) = stack.pop_front() {
+            if std::mem::replace(&mut seen[id.0], true) {
+                continue;
+            }
+            visit(id, self.node(id).unwrap());
+            for next in self.neighbors(id) {
+                if !seen[next.0] {
+                    stack.push_front(next);
+                }
+            }
+        }
+    }
+}
+
+/// Distances and shortest-path predecessors from a single source, as
+/// computed by `Graph::dijkstra` or `Graph::bellman_ford`.
+#[derive(Debug)]
+pub struct ShortestPaths<W> {
+    source: NodeId,
+    dist: HashMap<NodeId, W>,
+    prev: HashMap<NodeId, (NodeId, EdgeId)>,
+}
+
+impl<W: Copy> ShortestPaths<W> {
+    pub fn source(&self) -> NodeId {
+        self.source
+    }
+
+    /// Distance from the source, or `None` if `to` is unreachable.
+    pub fn distance(&self, to: NodeId) -> Option<W> {
+        self.dist.get(&to).copied()
+    }
+
+    /// Distances to every reachable node, the source included.
+    pub fn distances(&self) -> &HashMap<NodeId, W> {
+        &self.dist
+    }
+
+    /// Nodes on a shortest path from the source to `to`, both ends included.
+    pub fn path_to(&self, to: NodeId) -> Option<Vec<NodeId>> {
+        self.dist.get(&to)?;
+        let mut path = vec![to];
+        let mut curr = to;
+        while let Some(&(prev, _)) = self.prev.get(&curr) {
+            path.push(prev);
+            curr = prev;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// Edges on a shortest path from the source to `to`.
+    pub fn edges_to(&self, to: NodeId) -> Option<Vec<EdgeId>> {
+        self.dist.get(&to)?;
+        let mut edges = Vec::new();
+        let mut curr = to;
+        while let Some(&(prev, edge)) = self.prev.get(&curr) {
+            edges.push(edge);
+            curr = prev;
+        }
+        edges.reverse();
+        Some(edges)
+    }
+}
+
+/// Returned by `Graph::bellman_ford` when a negative-weight cycle is
+/// reachable from the source, so shortest paths are undefined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NegativeCycle;
+
+impl<N, E> Graph<N, E> {
+    /// Single-source shortest paths with non-negative edge weights given by
+    /// `weight`, in O((V + E) log V).
+    ///
+    /// Uses a binary heap with lazy deletion: a node may be pushed more than
+    /// once and stale entries are skipped when popped.
+    pub fn dijkstra<W, F>(&self, source: NodeId, weight: F) -> ShortestPaths<W>
+    where
+        W: Copy + Ord + Default + Add<Output = W>,
+        F: Fn(&E) -> W,
+    {
+        let mut paths = ShortestPaths { source, dist: HashMap::new(), prev: HashMap::new() };
+        let mut done = vec![false; self.nodes.len()];
+        let mut heap = BinaryHeap::new();
+        paths.dist.insert(source, W::default());
+        heap.push(Reverse((W::default(), source)));
+        while let Some(Reverse((d, id))) = heap.pop() {
+            if std::mem::replace(&mut done[id.0], true) {
+                continue;
+            }
+            for (to, edge, data) in self.edges_from(id) {
+                let nd = d + weight(data);
+                if paths.dist.get(&to).is_none_or(|&old| nd < old) {
+                    paths.dist.insert(to, nd);
+                    paths.prev.insert(to, (id, edge));
+                    heap.push(Reverse((nd, to)));
+                }
+            }
+        }
+        paths
+    }
+
+    /// Single-source shortest paths allowing negative edge weights, in
+    /// O(V * E). Fails if a negative cycle is reachable from `source`.
+    ///
+    /// In an undirected graph every negative edge forms such a cycle.
+    pub fn bellman_ford<W, F>(&self, source: NodeId, weight: F) -> Result<ShortestPaths<W>, NegativeCycle>
+    where
+        W: Copy + Ord + Default + Add<Output = W>,
+        F: Fn(&E) -> W,
+    {
+        let mut paths = ShortestPaths { source, dist: HashMap::new(), prev: HashMap::new() };
+        paths.dist.insert(source, W::default());
+        let relax = |paths: &mut ShortestPaths<W>| {
+            let mut changed = false;
+            for id in self.node_ids() {
+                let Some(d) = paths.distance(id) else { continue };
+                for (to, edge, data) in self.edges_from(id) {
+                    let nd = d + weight(data);
+                    if paths.dist.get(&to).is_none_or(|&old| nd < old) {
+                        paths.dist.insert(to, nd);
+                        paths.prev.insert(to, (id, edge));
+                        changed = true;
+                    }
+                }
+            }
+            changed
+        };
+        for _ in 1..self.node_count {
+            if !relax(&mut paths) {
+                return Ok(paths);
+            }
+        }
+        // Anything still improving after V - 1 rounds lies on or behind a
+        // negative cycle
+        if relax(&mut paths) { Err(NegativeCycle) } else { Ok(paths) }
+    }
+}
+
+impl<N: Debug, E: Debug> Debug for Graph<N, E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Graph")
+            .field("directed", &self.directed)
+            .field("nodes", &self.node_ids().map(|id| (id.0, self.node(id).unwrap())).collect::<Vec<_>>())
+            .field(
+                "edges",
+                &self
+                    .edge_ids()
+                    .map(|id| {
+                        let (from, to) = self.edge_endpoints(id).unwrap();
+                        (from.0, to.0, self.edge(id).unwrap())
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl HeapSize for NodeId {}
+
+impl HeapSize for EdgeId {}
+
+impl<N: HeapSize> HeapSize for NodeSlot<N> {
+    fn heap_size(&self) -> usize {
+        self.data.heap_size() + self.adj.heap_size()
+    }
+}
+
+impl<E: HeapSize> HeapSize for EdgeSlot<E> {
+    fn heap_size(&self) -> usize {
+        self.data.heap_size()
+    }
+}
+
+impl<N: HeapSize, E: HeapSize> HeapSize for Graph<N, E> {
+    fn heap_size(&self) -> usize {
+        self.nodes.heap_size() + self.edges.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::CollectionError;
+    use crate::heap_size::HeapSize;
+
+    use super::{Graph, NegativeCycle, NodeId};
+
+    fn sorted(iter: impl Iterator<Item = NodeId>) -> Vec<NodeId> {
+        let mut v: Vec<_> = iter.collect();
+        v.sort();
+        v
+    }
+
+    #[test]
+    fn test_add_nodes_edges() {
+        let mut g = Graph::new_directed();
+        let a = g.add_node("a");
+        let b = g.add_node("b");
+        let c = g.add_node("c");
+        let ab = g.add_edge(a, b, 1);
+        g.add_edge(a, c, 2);
+        g.add_edge(c, a, 3);
+
+        assert!(g.is_directed());
+        assert_eq!(g.node_count(), 3);
+        assert_eq!(g.edge_count(), 3);
+        assert_eq!(g.node(b), Some(&"b"));
+        assert_eq!(g.edge(ab), Some(&1));
+        assert_eq!(g.edge_endpoints(ab), Some((a, b)));
+        assert_eq!(sorted(g.neighbors(a)), vec![b, c]);
+        assert_eq!(sorted(g.neighbors(b)), vec![]);
+        assert_eq!(sorted(g.neighbors(c)), vec![a]);
+    }
+
+    #[test]
+    fn test_undirected() {
+        let mut g = Graph::new_undirected();
+        let a = g.add_node(());
+        let b = g.add_node(());
+        let e = g.add_edge(a, b, "ab");
+        assert_eq!(sorted(g.neighbors(a)), vec![b]);
+        assert_eq!(sorted(g.neighbors(b)), vec![a]);
+
+        assert_eq!(g.remove_edge(e), Some("ab"));
+        assert_eq!(g.remove_edge(e), None);
+        assert_eq!(g.edge_count(), 0);
+        assert_eq!(g.neighbors(a).count(), 0);
+        assert_eq!(g.neighbors(b).count(), 0);
+    }
+
+    #[test]
+    fn test_remove_node() {
+        let mut g = Graph::new_directed();
+        let a = g.add_node(1);
+        let b = g.add_node(2);
+        let c = g.add_node(3);
+        g.add_edge(a, b, ());
+        g.add_edge(b, c, ());
+        g.add_edge(c, b, ());
+        g.add_edge(a, c, ());
+
+        assert_eq!(g.remove_node(b), Some(2));
+        assert_eq!(g.remove_node(b), None);
+        assert_eq!(g.node_count(), 2);
+        assert_eq!(g.edge_count(), 1);
+        assert_eq!(sorted(g.neighbors(a)), vec![c]);
+        assert_eq!(g.neighbors(c).count(), 0);
+        assert_eq!(sorted(g.node_ids()), vec![a, c]);
+
+        assert_eq!(g.try_add_edge(a, b, ()), Err(CollectionError::StaleHandle));
+        assert_eq!(g.try_add_edge(NodeId(3), a, ()), Err(CollectionError::IndexOutOfBounds { index: 3, len: 3 }));
+        assert_eq!(g.edge_count(), 1);
+        let edge = g.try_add_edge(c, a, ()).unwrap();
+        assert_eq!(g.edge_endpoints(edge), Some((c, a)));
+    }
+
+    #[test]
+    fn test_bfs_dfs() {
+        // 0 - 1 - 3
+        //  \     /
+        //   2 - 4    5 (unreachable)
+        let mut g = Graph::new_undirected();
+        let ids: Vec<_> = (0..6).map(|i| g.add_node(i)).collect();
+        for (x, y) in [(0, 1), (0, 2), (1, 3), (2, 4), (3, 4)] {
+            g.add_edge(ids[x], ids[y], ());
+        }
+
+        let mut order = Vec::new();
+        g.bfs(ids[0], |_, &n| order.push(n));
+        assert_eq!(order.len(), 5);
+        assert_eq!(order[0], 0);
+        let mut level1 = order[1..3].to_vec();
+        level1.sort();
+        assert_eq!(level1, vec![1, 2]);
+
+        let mut order = Vec::new();
+        g.dfs(ids[0], |_, &n| order.push(n));
+        assert_eq!(order.len(), 5);
+        assert_eq!(order[0], 0);
+        assert!(!order.contains(&5));
+    }
+
+    #[test]
+    fn test_self_loop_and_mutation() {
+        let mut g = Graph::new_undirected();
+        let a = g.add_node(String::from("a"));
+        let e = g.add_edge(a, a, 5);
+        assert_eq!(g.neighbors(a).collect::<Vec<_>>(), vec![a]);
+        *g.edge_mut(e).unwrap() += 1;
+        g.node_mut(a).unwrap().push('!');
+        assert_eq!(g.edges_from(a).map(|(_, _, w)| *w).collect::<Vec<_>>(), vec![6]);
+        assert_eq!(g.node(a).map(String::as_str), Some("a!"));
+        assert_eq!(g.remove_node(a), Some(String::from("a!")));
+        assert_eq!(g.edge_count(), 0);
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        let mut g = Graph::new_directed();
+        let [a, b, c, d, e] = [(); 5].map(|_| g.add_node(()));
+        g.add_edge(a, b, 1);
+        g.add_edge(a, c, 4);
+        g.add_edge(c, b, 1);
+        g.add_edge(b, d, 2);
+        let cd = g.add_edge(c, d, 7);
+
+        let paths = g.dijkstra(a, |&w| w);
+        assert_eq!(paths.source(), a);
+        assert_eq!(paths.distance(a), Some(0));
+        assert_eq!(paths.distance(b), Some(1));
+        assert_eq!(paths.distance(c), Some(4));
+        assert_eq!(paths.distance(d), Some(3));
+        assert_eq!(paths.distance(e), None);
+        assert_eq!(paths.distances().len(), 4);
+        assert_eq!(paths.path_to(d), Some(vec![a, b, d]));
+        assert_eq!(paths.path_to(a), Some(vec![a]));
+        assert_eq!(paths.path_to(e), None);
+
+        let paths = g.dijkstra(c, |&w| w);
+        assert_eq!(paths.distance(d), Some(3));
+        assert_eq!(paths.path_to(d), Some(vec![c, b, d]));
+        assert_eq!(paths.distance(a), None);
+
+        *g.edge_mut(cd).unwrap() = 1;
+        let paths = g.dijkstra(c, |&w| w);
+        assert_eq!(paths.edges_to(d), Some(vec![cd]));
+    }
+
+    #[test]
+    fn test_bellman_ford() {
+        let mut g = Graph::new_directed();
+        let [a, b, c, d] = [(); 4].map(|_| g.add_node(()));
+        g.add_edge(a, b, 4);
+        g.add_edge(a, c, 5);
+        g.add_edge(c, b, -3);
+        g.add_edge(b, d, 2);
+
+        let paths = g.bellman_ford(a, |&w| w).unwrap();
+        assert_eq!(paths.distance(b), Some(2));
+        assert_eq!(paths.distance(d), Some(4));
+        assert_eq!(paths.path_to(d), Some(vec![a, c, b, d]));
+
+        // A negative cycle not reachable from the source doesn't matter
+        let x = g.add_node(());
+        let y = g.add_node(());
+        g.add_edge(x, y, -1);
+        g.add_edge(y, x, -1);
+        assert!(g.bellman_ford(a, |&w| w).is_ok());
+
+        g.add_edge(d, c, -5);
+        assert_eq!(g.bellman_ford(a, |&w| w).unwrap_err(), NegativeCycle);
+    }
+
+    #[test]
+    fn test_shortest_paths_agree() {
+        let mut g = Graph::new_undirected();
+        let ids: Vec<_> = (0..30).map(|_| g.add_node(())).collect();
+        for i in 0..30usize {
+            for j in [i * 7 % 30, i * 11 % 30, (i + 1) % 30] {
+                g.add_edge(ids[i], ids[j], (i * 13 + j * 5) % 17);
+            }
+        }
+        let fast = g.dijkstra(ids[0], |&w| w);
+        let slow = g.bellman_ford(ids[0], |&w| w).unwrap();
+        assert_eq!(fast.distances(), slow.distances());
+        for &id in &ids {
+            let path = fast.edges_to(id).unwrap();
+            let total: usize = path.iter().map(|&e| *g.edge(e).unwrap()).sum();
+            assert_eq!(Some(total), fast.distance(id));
+        }
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let mut g = Graph::new_undirected();
+        let a = g.add_node(String::from("a"));
+        let b = g.add_node(String::from("bc"));
+        let before = g.heap_size();
+        g.add_edge(a, b, 1u32);
+        // An undirected edge sits in both adjacency lists
+        assert!(g.heap_size() >= before + 2 * size_of::<(NodeId, super::EdgeId)>());
+        assert!(before >= 3);
+    }
+}
@@ -0,0 +1,667 @@
+use std::borrow::Borrow;
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash, RandomState};
+
+use crate::fallible::AllocError;
+use crate::heap_size::HeapSize;
+use crate::trace::Timed;
+
+struct Bucket<K, V> {
+    hash: u64,
+    key: K,
+    value: V,
+}
+
+/// A hash map using open addressing with Robin Hood linear probing.
+///
+/// Every key sits at or after its home slot (its hash masked to the power of
+/// two table size). On insert, an entry that has probed further than the
+/// resident of a slot takes that slot and the resident moves on, which keeps
+/// probe lengths short and even. Lookups stop as soon as they pass a resident
+/// closer to its home than the key would be. Removal shifts the following
+/// entries back one slot instead of leaving tombstones.
+///
+/// The table grows (doubling) whenever an insert would push the load factor
+/// above `max_load_factor`, 0.9 by default.
+pub struct HashMap<K, V> {
+    buckets: Vec<Option<Bucket<K, V>>>,
+    len: usize,
+    max_load_factor: f64,
+    hasher: RandomState,
+}
+
+const MIN_SLOTS: usize = 8;
+
+fn empty_buckets<K, V>(slots: usize) -> Vec<Option<Bucket<K, V>>> {
+    (0..slots).map(|_| None).collect()
+}
+
+impl<K, V> HashMap<K, V> {
+    pub fn new() -> Self {
+        HashMap { buckets: Vec::new(), len: 0, max_load_factor: 0.9, hasher: RandomState::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of entries the map can hold before it next grows.
+    pub fn capacity(&self) -> usize {
+        (self.buckets.len() as f64 * self.max_load_factor) as usize
+    }
+
+    /// Fraction of slots currently occupied.
+    pub fn load_factor(&self) -> f64 {
+        if self.buckets.is_empty() { 0.0 } else { self.len as f64 / self.buckets.len() as f64 }
+    }
+
+    pub fn max_load_factor(&self) -> f64 {
+        self.max_load_factor
+    }
+
+    pub fn clear(&mut self) {
+        self.buckets.iter_mut().for_each(|b| *b = None);
+        self.len = 0;
+    }
+
+    fn mask(&self) -> usize {
+        self.buckets.len() - 1
+    }
+
+    fn probe_distance(&self, index: usize, hash: u64) -> usize {
+        index.wrapping_sub(hash as usize) & self.mask()
+    }
+
+    /// Longest distance any entry sits from its home slot.
+    pub fn max_probe_distance(&self) -> usize {
+        self.buckets
+            .iter()
+            .enumerate()
+            .filter_map(|(i, b)| b.as_ref().map(|b| self.probe_distance(i, b.hash)))
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn bucket_mut(&mut self, index: usize) -> &mut Bucket<K, V> {
+        self.buckets[index].as_mut().unwrap()
+    }
+
+    /// Remove the entry at `index`, shifting the run of displaced entries
+    /// after it back by one slot.
+    fn remove_at(&mut self, mut index: usize) -> Bucket<K, V> {
+        let removed = self.buckets[index].take().unwrap();
+        loop {
+            let next = (index + 1) & self.mask();
+            let displaced = self.buckets[next].as_ref().is_some_and(|b| self.probe_distance(next, b.hash) > 0);
+            if !displaced {
+                break;
+            }
+            self.buckets[index] = self.buckets[next].take();
+            index = next;
+        }
+        self.len -= 1;
+        removed
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter { buckets: self.buckets.iter() }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut { buckets: self.buckets.iter_mut() }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, v)| v)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
+        self.iter_mut().map(|(_, v)| v)
+    }
+}
+
+impl<K: Hash + Eq, V> HashMap<K, V> {
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut map = Self::new();
+        map.reserve(capacity);
+        map
+    }
+
+    /// Set the load factor above which the table grows, growing it now if
+    /// it is already above it. Lower values trade memory for shorter probes.
+    ///
+    /// Panics unless `0 < max_load_factor <= 1`.
+    pub fn set_max_load_factor(&mut self, max_load_factor: f64) {
+        assert!(
+            max_load_factor > 0.0 && max_load_factor <= 1.0,
+            "max load factor must be in (0, 1]"
+        );
+        self.max_load_factor = max_load_factor;
+        self.reserve(0);
+    }
+
+    /// Panic if the entry count is off, the table is over its load factor,
+    /// or an entry's stored hash is stale or it sits where a lookup wouldn't
+    /// find it. Only in debug builds and tests.
+    #[cfg(any(test, debug_assertions))]
+    pub fn validate(&self) {
+        assert!(self.buckets.is_empty() || self.buckets.len().is_power_of_two(), "table size isn't a power of two");
+        assert_eq!(self.buckets.iter().flatten().count(), self.len, "map length is out of date");
+        assert!(self.len <= self.capacity(), "table is over its load factor");
+        for (i, bucket) in self.buckets.iter().enumerate() {
+            if let Some(b) = bucket {
+                assert_eq!(b.hash, self.hasher.hash_one(&b.key), "stale hash in slot {i}");
+                assert_eq!(self.find(b.hash, &b.key), Some(i), "entry in slot {i} is out of place");
+            }
+        }
+    }
+
+    /// Make room for at least `additional` more entries without growing.
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = self.len + additional;
+        if needed <= self.capacity() && !self.buckets.is_empty() {
+            return;
+        }
+        let slots = self.slots_for(needed).expect("capacity overflow");
+        if slots != self.buckets.len() {
+            self.resize(slots);
+        }
+    }
+
+    /// `reserve`, but returning an error instead of aborting if the larger
+    /// table can't be allocated. The map is unchanged on error.
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), AllocError> {
+        let needed = self.len.checked_add(additional).ok_or(AllocError)?;
+        if needed <= self.capacity() && !self.buckets.is_empty() {
+            return Ok(());
+        }
+        let slots = self.slots_for(needed).ok_or(AllocError)?;
+        if slots != self.buckets.len() {
+            let mut buckets = Vec::new();
+            buckets.try_reserve_exact(slots)?;
+            buckets.resize_with(slots, || None);
+            self.rehash(buckets);
+        }
+        Ok(())
+    }
+
+    // Smallest table, no smaller than the current one, that holds `needed`
+    // entries. `None` if its size would overflow.
+    fn slots_for(&self, needed: usize) -> Option<usize> {
+        let mut slots = self.buckets.len().max(MIN_SLOTS);
+        while (slots as f64 * self.max_load_factor) < needed as f64 {
+            slots = slots.checked_mul(2)?;
+        }
+        Some(slots)
+    }
+
+    /// Shrink the table to the smallest size that fits the current entries.
+    pub fn shrink_to_fit(&mut self) {
+        let mut slots = MIN_SLOTS;
+        while (slots as f64 * self.max_load_factor) < self.len as f64 {
+            slots *= 2;
+        }
+        if slots < self.buckets.len() {
+            self.resize(slots);
+        }
+    }
+
+    fn resize(&mut self, slots: usize) {
+        self.rehash(empty_buckets(slots));
+    }
+
+    // Move every entry into `buckets`, which must be empty
+    fn rehash(&mut self, buckets: Vec<Option<Bucket<K, V>>>) {
+        let timed = Timed::start();
+        let old = std::mem::replace(&mut self.buckets, buckets);
+        self.len = 0;
+        for bucket in old.into_iter().flatten() {
+            self.place(bucket);
+        }
+        timed.finish("HashMap", "rehash", self.len);
+    }
+
+    fn find<Q: Hash + Eq + ?Sized>(&self, hash: u64, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+    {
+        if self.buckets.is_empty() {
+            return None;
+        }
+        let mut index = hash as usize & self.mask();
+        let mut dist = 0;
+        while let Some(b) = &self.buckets[index] {
+            if self.probe_distance(index, b.hash) < dist {
+                return None;
+            }
+            if b.hash == hash && b.key.borrow() == key {
+                return Some(index);
+            }
+            index = (index + 1) & self.mask();
+            dist += 1;
+        }
+        None
+    }
+
+    /// Robin Hood insert of a key known to be absent, into a table with room
+    /// for it. Returns where the new entry ended up.
+    fn place(&mut self, bucket: Bucket<K, V>) -> usize {
+        let mut index = bucket.hash as usize & self.mask();
+        let mut carry = bucket;
+        let mut dist = 0;
+        let mut placed = None;
+        loop {
+            let mask = self.mask();
+            let slot = &mut self.buckets[index];
+            match slot {
+                None => {
+                    *slot = Some(carry);
+                    self.len += 1;
+                    return placed.unwrap_or(index);
+                }
+                Some(resident) => {
+                    let resident_dist = index.wrapping_sub(resident.hash as usize) & mask;
+                    if resident_dist < dist {
+                        std::mem::swap(resident, &mut carry);
+                        placed.get_or_insert(index);
+                        dist = resident_dist;
+                    }
+                }
+            }
+            index = (index + 1) & mask;
+            dist += 1;
+        }
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => Some(entry.insert(value)),
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                None
+            }
+        }
+    }
+
+    /// `insert`, but returning an error and leaving the map unchanged if
+    /// the table has to grow and the allocation fails.
+    pub fn try_insert(&mut self, key: K, value: V) -> Result<Option<V>, AllocError> {
+        self.try_reserve(1)?;
+        Ok(self.insert(key, value))
+    }
+
+    pub fn get<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+    {
+        let index = self.find(self.hasher.hash_one(key), key)?;
+        self.buckets[index].as_ref().map(|b| &b.value)
+    }
+
+    pub fn get_mut<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+    {
+        let index = self.find(self.hasher.hash_one(key), key)?;
+        Some(&mut self.bucket_mut(index).value)
+    }
+
+    pub fn contains_key<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        self.find(self.hasher.hash_one(key), key).is_some()
+    }
+
+    pub fn remove<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+    {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+
+    pub fn remove_entry<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+    {
+        let index = self.find(self.hasher.hash_one(key), key)?;
+        let bucket = self.remove_at(index);
+        Some((bucket.key, bucket.value))
+    }
+
+    /// Remove every entry for which `f` returns false. Rebuilds the table in
+    /// place, so each entry is visited exactly once.
+    pub fn retain<F: FnMut(&K, &mut V) -> bool>(&mut self, mut f: F) {
+        let slots = self.buckets.len();
+        let old = std::mem::replace(&mut self.buckets, empty_buckets(slots));
+        self.len = 0;
+        for mut bucket in old.into_iter().flatten() {
+            if f(&bucket.key, &mut bucket.value) {
+                self.place(bucket);
+            }
+        }
+    }
+
+    /// The entry for `key`, for in-place insertion or update. May grow the
+    /// table so a vacant entry is guaranteed room.
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        self.reserve(1);
+        let hash = self.hasher.hash_one(&key);
+        match self.find(hash, &key) {
+            Some(index) => Entry::Occupied(OccupiedEntry { map: self, index }),
+            None => Entry::Vacant(VacantEntry { map: self, hash, key }),
+        }
+    }
+}
+
+impl<K, V> Default for HashMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub enum Entry<'a, K, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+pub struct OccupiedEntry<'a, K, V> {
+    map: &'a mut HashMap<K, V>,
+    index: usize,
+}
+
+pub struct VacantEntry<'a, K, V> {
+    map: &'a mut HashMap<K, V>,
+    hash: u64,
+    key: K,
+}
+
+impl<'a, K: Hash + Eq, V> Entry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => &entry.key,
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, K, V> OccupiedEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.map.buckets[self.index].as_ref().unwrap().key
+    }
+
+    pub fn get(&self) -> &V {
+        &self.map.buckets[self.index].as_ref().unwrap().value
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.map.bucket_mut(self.index).value
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.map.bucket_mut(self.index).value
+    }
+
+    /// Replace the value, returning the old one.
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> V {
+        self.map.remove_at(self.index).value
+    }
+}
+
+impl<'a, K: Hash + Eq, V> VacantEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn insert(self, value: V) -> &'a mut V {
+        let index = self.map.place(Bucket { hash: self.hash, key: self.key, value });
+        &mut self.map.bucket_mut(index).value
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    buckets: std::slice::Iter<'a, Option<Bucket<K, V>>>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.buckets.by_ref().flatten().next().map(|b| (&b.key, &b.value))
+    }
+}
+
+pub struct IterMut<'a, K, V> {
+    buckets: std::slice::IterMut<'a, Option<Bucket<K, V>>>,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.buckets.by_ref().flatten().next().map(|b| (&b.key, &mut b.value))
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a HashMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K: Hash + Eq, V> FromIterator<(K, V)> for HashMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = HashMap::new();
+        for (k, v) in iter {
+            map.insert(k, v);
+        }
+        map
+    }
+}
+
+impl<K: Debug, V: Debug> Debug for HashMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: HeapSize, V: HeapSize> HeapSize for HashMap<K, V> {
+    fn heap_size(&self) -> usize {
+        self.buckets.capacity() * size_of::<Option<Bucket<K, V>>>()
+            + self.iter().map(|(key, value)| key.heap_size() + value.heap_size()).sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fallible::AllocError;
+    use crate::heap_size::HeapSize;
+
+    use super::{Entry, HashMap};
+
+    #[test]
+    fn test_insert_get_remove() {
+        let mut map = HashMap::new();
+        assert!(map.is_empty());
+        assert_eq!(map.get(&1), None);
+        assert_eq!(map.insert(1, "one"), None);
+        assert_eq!(map.insert(2, "two"), None);
+        assert_eq!(map.insert(1, "ONE"), Some("one"));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&1), Some(&"ONE"));
+        assert!(map.contains_key(&2));
+
+        *map.get_mut(&2).unwrap() = "TWO";
+        assert_eq!(map.remove(&2), Some("TWO"));
+        assert_eq!(map.remove(&2), None);
+        assert_eq!(map.len(), 1);
+        assert!(!map.contains_key(&2));
+    }
+
+    #[test]
+    fn test_borrowed_keys() {
+        let mut map: HashMap<String, usize> = HashMap::new();
+        map.insert("apple".to_string(), 1);
+        assert_eq!(map.get("apple"), Some(&1));
+        assert_eq!(map.remove_entry("apple"), Some(("apple".to_string(), 1)));
+    }
+
+    #[test]
+    fn test_growth() {
+        let mut map = HashMap::new();
+        for i in 0..10_000 {
+            map.insert(i, i * 2);
+        }
+        assert_eq!(map.len(), 10_000);
+        assert!(map.load_factor() <= 0.9);
+        assert!((0..10_000).all(|i| map.get(&i) == Some(&(i * 2))));
+        assert_eq!(map.iter().count(), 10_000);
+
+        for i in 0..9_990 {
+            map.remove(&i);
+        }
+        map.shrink_to_fit();
+        assert_eq!(map.capacity(), 14);
+        assert_eq!(map.values().copied().max(), Some(19_998));
+    }
+
+    #[test]
+    fn test_try_insert() {
+        let mut map = HashMap::new();
+        for i in 0..100 {
+            assert_eq!(map.try_insert(i, i), Ok(None));
+        }
+        assert_eq!(map.try_insert(5, 50), Ok(Some(5)));
+        let capacity = map.capacity();
+        assert_eq!(map.try_reserve(usize::MAX), Err(AllocError));
+        assert_eq!(map.try_reserve(usize::MAX / 2), Err(AllocError));
+        assert_eq!(map.capacity(), capacity);
+        assert_eq!(map.len(), 100);
+        assert_eq!(map.get(&5), Some(&50));
+    }
+
+    #[test]
+    fn test_load_factor_tuning() {
+        let mut map: HashMap<u32, ()> = (0..1500).map(|i| (i, ())).collect();
+        assert_eq!(map.capacity(), 1843);
+        map.set_max_load_factor(0.5);
+        assert_eq!(map.capacity(), 2048);
+        assert!(map.load_factor() <= 0.5);
+        assert!((0..1500).all(|i| map.contains_key(&i)));
+
+        let with_capacity: HashMap<u32, ()> = HashMap::with_capacity(100);
+        assert!(with_capacity.capacity() >= 100);
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut counts = HashMap::new();
+        for word in "the cat and the hat and the bat".split(' ') {
+            *counts.entry(word).or_insert(0) += 1;
+        }
+        assert_eq!(counts.get("the"), Some(&3));
+        assert_eq!(counts.get("and"), Some(&2));
+        assert_eq!(counts.get("cat"), Some(&1));
+
+        counts.entry("cat").and_modify(|c| *c += 10).or_default();
+        counts.entry("dog").and_modify(|c| *c += 10).or_default();
+        assert_eq!(counts.get("cat"), Some(&11));
+        assert_eq!(counts.get("dog"), Some(&0));
+
+        match counts.entry("hat") {
+            Entry::Occupied(entry) => {
+                assert_eq!(entry.key(), &"hat");
+                assert_eq!(entry.remove(), 1);
+            }
+            Entry::Vacant(_) => panic!("expected hat to be present"),
+        }
+        assert!(!counts.contains_key("hat"));
+        assert!(matches!(counts.entry("hat"), Entry::Vacant(_)));
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut map: HashMap<u32, u32> = HashMap::new();
+        for i in 0..200 {
+            map.insert(i, i);
+        }
+        map.retain(|&k, v| {
+            *v += 1;
+            k % 4 == 0
+        });
+        assert_eq!(map.len(), 50);
+        assert!((0..200).all(|i| map.get(&i).copied() == if i % 4 == 0 { Some(i + 1) } else { None }));
+    }
+
+    #[test]
+    fn test_matches_std() {
+        let mut map = HashMap::new();
+        let mut expected = std::collections::HashMap::new();
+        for i in 0..5000u64 {
+            let key = i.wrapping_mul(0x9e37_79b9) % 700;
+            if i % 3 == 0 {
+                assert_eq!(map.remove(&key), expected.remove(&key));
+            } else {
+                assert_eq!(map.insert(key, i), expected.insert(key, i));
+            }
+        }
+        map.validate();
+        assert_eq!(map.len(), expected.len());
+        for (k, v) in &expected {
+            assert_eq!(map.get(k), Some(v));
+        }
+        // Robin Hood keeps probes short even at high load
+        assert!(map.max_probe_distance() < 32);
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let mut map: HashMap<u32, Vec<u8>> = HashMap::new();
+        assert_eq!(map.heap_size(), 0);
+        map.insert(1, Vec::with_capacity(100));
+        let table = map.heap_size() - 100;
+        assert_eq!(table % map.buckets.len(), 0);
+        map.insert(2, vec![0; 50]);
+        assert_eq!(map.heap_size(), table + 150);
+    }
+}
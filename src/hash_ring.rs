@@ -0,0 +1,181 @@
+use std::fmt::Debug;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use crate::heap_size::HeapSize;
+use crate::sorted_vec_map::SortedVecMap;
+
+const DEFAULT_VNODES: usize = 100;
+
+fn hash_of<T: Hash + ?Sized>(item: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    item.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A consistent hashing ring that assigns keys to nodes, such as cache
+/// shards or servers.
+///
+/// Each node is hashed to many points ("virtual nodes") on a ring of `u64`
+/// hashes, and a key belongs to the node owning the first point at or after
+/// the key's hash. Adding or removing a node only moves the keys between its
+/// points and their predecessors, about 1/n of them, where rehashing modulo
+/// the node count would move nearly all of them. More virtual nodes spread
+/// the keys more evenly, at the cost of a bigger ring.
+///
+/// Hashing is deterministic, so separate processes with the same nodes agree
+/// on where every key goes.
+pub struct HashRing<N> {
+    // Sorted by hash, for finding the successor of a key's hash
+    ring: SortedVecMap<u64, N>,
+    nodes: Vec<N>,
+    vnodes: usize,
+}
+
+impl<N: Hash + Eq + Clone> HashRing<N> {
+    /// A ring placing each node at `vnodes` points.
+    ///
+    /// Panics if `vnodes` is zero.
+    pub fn new(vnodes: usize) -> Self {
+        assert!(vnodes > 0, "each node needs at least one virtual node");
+        HashRing { ring: SortedVecMap::new(), nodes: Vec::new(), vnodes }
+    }
+
+    /// Number of nodes, not counting virtual nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn vnodes(&self) -> usize {
+        self.vnodes
+    }
+
+    pub fn contains_node(&self, node: &N) -> bool {
+        self.nodes.contains(node)
+    }
+
+    /// The nodes in the order they were added.
+    pub fn nodes(&self) -> impl ExactSizeIterator<Item = &N> {
+        self.nodes.iter()
+    }
+
+    // Where the virtual nodes of `node` sit on the ring
+    fn points(&self, node: &N) -> impl Iterator<Item = u64> {
+        (0..self.vnodes).map(move |i| hash_of(&(node, i)))
+    }
+
+    /// Add `node`, returning false if it was already on the ring.
+    pub fn add_node(&mut self, node: N) -> bool {
+        if self.contains_node(&node) {
+            return false;
+        }
+        for point in self.points(&node).collect::<Vec<_>>() {
+            // On the rare collision the node already there keeps the point
+            if !self.ring.contains_key(&point) {
+                self.ring.insert(point, node.clone());
+            }
+        }
+        self.nodes.push(node);
+        true
+    }
+
+    /// Remove `node`, returning false if it wasn't on the ring. Only the keys
+    /// it owned move, each to the next node around the ring.
+    pub fn remove_node(&mut self, node: &N) -> bool {
+        let Some(i) = self.nodes.iter().position(|n| n == node) else {
+            return false;
+        };
+        self.nodes.remove(i);
+        for point in self.points(node).collect::<Vec<_>>() {
+            if self.ring.get(&point) == Some(node) {
+                self.ring.remove(&point);
+            }
+        }
+        true
+    }
+
+    /// The node `key` belongs to, or `None` if the ring is empty.
+    pub fn node_for<K: Hash + ?Sized>(&self, key: &K) -> Option<&N> {
+        let hash = hash_of(key);
+        // Wrap around past the last point
+        let (_, node) = self.ring.range(hash..).next().or_else(|| self.ring.first_key_value())?;
+        Some(node)
+    }
+}
+
+impl<N: Hash + Eq + Clone> Default for HashRing<N> {
+    fn default() -> Self {
+        Self::new(DEFAULT_VNODES)
+    }
+}
+
+impl<N: Hash + Eq + Clone> FromIterator<N> for HashRing<N> {
+    fn from_iter<I: IntoIterator<Item = N>>(iter: I) -> Self {
+        let mut ring = Self::default();
+        for node in iter {
+            ring.add_node(node);
+        }
+        ring
+    }
+}
+
+impl<N: Debug> Debug for HashRing<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HashRing").field("nodes", &self.nodes).field("vnodes", &self.vnodes).finish()
+    }
+}
+
+impl<N: HeapSize> HeapSize for HashRing<N> {
+    fn heap_size(&self) -> usize {
+        self.ring.heap_size() + self.nodes.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HashRing;
+
+    #[test]
+    fn test_empty_and_single() {
+        let mut ring = HashRing::new(10);
+        assert_eq!(ring.node_for("key"), None);
+        assert!(ring.add_node("a"));
+        assert!(!ring.add_node("a"));
+        assert!((0..100).all(|i| ring.node_for(&i) == Some(&"a")));
+        assert!(ring.remove_node(&"a"));
+        assert!(!ring.remove_node(&"a"));
+        assert!(ring.is_empty());
+        assert_eq!(ring.node_for("key"), None);
+    }
+
+    #[test]
+    fn test_balance() {
+        let ring: HashRing<_> = (0..4).collect();
+        let mut counts = [0; 4];
+        for key in 0..40_000 {
+            counts[*ring.node_for(&key).unwrap()] += 1;
+        }
+        // With 100 virtual nodes each, every node gets close to a quarter
+        assert!(counts.iter().all(|&count| (7_000..13_000).contains(&count)), "{counts:?}");
+    }
+
+    #[test]
+    fn test_minimal_remapping() {
+        let mut ring: HashRing<_> = ["a", "b", "c", "d"].into_iter().collect();
+        let before: Vec<_> = (0..10_000).map(|key| *ring.node_for(&key).unwrap()).collect();
+
+        ring.add_node("e");
+        let after: Vec<_> = (0..10_000).map(|key| *ring.node_for(&key).unwrap()).collect();
+        // Keys only ever move to the new node, and roughly a fifth of them do
+        let moved = before.iter().zip(&after).filter(|(b, a)| b != a).count();
+        assert!(before.iter().zip(&after).all(|(b, a)| b == a || *a == "e"));
+        assert!((1_000..3_000).contains(&moved), "{moved}");
+
+        // Removing it again puts every key back
+        ring.remove_node(&"e");
+        assert!((0..10_000).zip(&before).all(|(key, node)| ring.node_for(&key) == Some(node)));
+    }
+}
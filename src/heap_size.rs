@@ -0,0 +1,182 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, VecDeque};
+use std::ops::Range;
+
+/// Heap memory owned by a value, so a service can account for what each of
+/// its caches and queues holds.
+///
+/// `heap_size` is the number of bytes the value owns on the heap, not
+/// counting its own `size_of`. Containers count their buffers and node
+/// allocations, spare capacity included, plus the `heap_size` of every
+/// element they hold. Memory a container only shares, such as a `NodePool`
+/// behind an `Rc` or a `bumpalo` arena, is left out.
+///
+/// The default of 0 is right for types that own no heap memory, so an
+/// element type can opt in with an empty `impl`, and override `heap_size`
+/// only if its payload is worth counting.
+///
+/// Every container implements it except `LazyList`, whose unforced cells
+/// are closures of unknown size, and the `wasm`, `python` and `ffi` wrappers,
+/// whose elements live on the JavaScript or Python heap or behind the
+/// caller's pointers. The lock-free queues count their buffers but not the
+/// elements in them, which other threads may be moving.
+pub trait HeapSize {
+    fn heap_size(&self) -> usize {
+        0
+    }
+}
+
+/// Total `heap_size` of some elements, for containers to add to the size of
+/// their own allocations.
+pub(crate) fn payload<'a, T: HeapSize + 'a>(elems: impl IntoIterator<Item = &'a T>) -> usize {
+    elems.into_iter().map(HeapSize::heap_size).sum()
+}
+
+/// Size of the allocation behind an `Rc<T>` or an `Arc<T>`: the value and its
+/// two counts.
+pub(crate) fn rc_size<T>() -> usize {
+    size_of::<T>() + 2 * size_of::<usize>()
+}
+
+impl HeapSize for u8 {}
+impl HeapSize for u16 {}
+impl HeapSize for u32 {}
+impl HeapSize for u64 {}
+impl HeapSize for u128 {}
+impl HeapSize for usize {}
+impl HeapSize for i8 {}
+impl HeapSize for i16 {}
+impl HeapSize for i32 {}
+impl HeapSize for i64 {}
+impl HeapSize for i128 {}
+impl HeapSize for isize {}
+impl HeapSize for f32 {}
+impl HeapSize for f64 {}
+impl HeapSize for bool {}
+impl HeapSize for char {}
+impl HeapSize for () {}
+impl HeapSize for &str {}
+
+impl HeapSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T: HeapSize + ?Sized> HeapSize for Box<T> {
+    fn heap_size(&self) -> usize {
+        size_of_val(&**self) + (**self).heap_size()
+    }
+}
+
+impl<T: HeapSize> HeapSize for [T] {
+    fn heap_size(&self) -> usize {
+        payload(self)
+    }
+}
+
+impl<T: HeapSize, const N: usize> HeapSize for [T; N] {
+    fn heap_size(&self) -> usize {
+        payload(self)
+    }
+}
+
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + payload(self)
+    }
+}
+
+impl<T: HeapSize> HeapSize for VecDeque<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + payload(self)
+    }
+}
+
+impl<T: HeapSize> HeapSize for BinaryHeap<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + payload(self)
+    }
+}
+
+/// The standard library doesn't expose its tables' layout, so this counts
+/// one `(K, V)` slot per unit of capacity and leaves out the control bytes.
+impl<K: HeapSize, V: HeapSize, S> HeapSize for HashMap<K, V, S> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<(K, V)>() + self.iter().map(|(key, value)| key.heap_size() + value.heap_size()).sum::<usize>()
+    }
+}
+
+/// The standard library doesn't expose its nodes, so this counts one
+/// `(K, V)` slot per entry, a lower bound on what the nodes take.
+impl<K: HeapSize, V: HeapSize> HeapSize for BTreeMap<K, V> {
+    fn heap_size(&self) -> usize {
+        self.len() * size_of::<(K, V)>() + self.iter().map(|(key, value)| key.heap_size() + value.heap_size()).sum::<usize>()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Option<T> {
+    fn heap_size(&self) -> usize {
+        self.as_ref().map_or(0, HeapSize::heap_size)
+    }
+}
+
+impl<A: HeapSize, B: HeapSize> HeapSize for (A, B) {
+    fn heap_size(&self) -> usize {
+        self.0.heap_size() + self.1.heap_size()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Reverse<T> {
+    fn heap_size(&self) -> usize {
+        self.0.heap_size()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Range<T> {
+    fn heap_size(&self) -> usize {
+        self.start.heap_size() + self.end.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap, VecDeque};
+
+    use super::HeapSize;
+
+    #[test]
+    fn test_std_types() {
+        assert_eq!(5u64.heap_size(), 0);
+        assert_eq!(String::with_capacity(10).heap_size(), 10);
+        let vec: Vec<u32> = Vec::with_capacity(8);
+        assert_eq!(vec.heap_size(), 32);
+        let strings = vec![String::from("ab"), String::from("cde")];
+        assert_eq!(strings.heap_size(), 2 * size_of::<String>() + 5);
+        let boxed: Box<[u16]> = vec![1, 2, 3].into_boxed_slice();
+        assert_eq!(boxed.heap_size(), 6);
+        assert_eq!(Box::new(String::from("x")).heap_size(), size_of::<String>() + 1);
+        let deque: VecDeque<(u8, Option<String>)> = VecDeque::new();
+        assert_eq!(deque.heap_size(), 0);
+    }
+
+    #[test]
+    fn test_std_maps() {
+        let mut map: HashMap<u32, String> = HashMap::with_capacity(4);
+        map.insert(1, String::from("abc"));
+        assert_eq!(map.heap_size(), map.capacity() * size_of::<(u32, String)>() + 3);
+        let tree: BTreeMap<u8, String> = [(1, String::from("ab")), (2, String::new())].into();
+        assert_eq!(tree.heap_size(), 2 * size_of::<(u8, String)>() + 2);
+    }
+
+    #[test]
+    fn test_opt_in() {
+        // Counted as its inline size only
+        struct Point {
+            _x: i32,
+        }
+        impl HeapSize for Point {}
+        let points = vec![Point { _x: 1 }, Point { _x: 2 }];
+        assert_eq!(points.heap_size(), points.capacity() * 4);
+    }
+}
@@ -0,0 +1,529 @@
+use std::fmt::Debug;
+
+use crate::error::CollectionError;
+use crate::heap_size::{HeapSize, payload};
+use crate::sequence::Sequence;
+
+// Stands in for a missing link, so a link is 4 bytes rather than 8
+const NIL: u32 = u32::MAX;
+
+struct Node<T> {
+    // `None` while the slot is on the free list
+    val: Option<T>,
+    prev: u32,
+    // The next free slot, while on the free list
+    next: u32,
+}
+
+/// Position of an element in an `IndexList`.
+///
+/// Stays valid until the element is removed; after that the slot may be
+/// handed out again, so an old handle can end up naming a new element.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ListIndex(u32);
+
+/// A doubly linked list whose nodes all live in one `Vec`, linked by `u32`
+/// indices.
+///
+/// The links give the same O(1) edits anywhere a handle points as a
+/// `LinkedList`: inserting next to an element, removing it, or moving it to
+/// another place in the list. But there is no allocation per node and no
+/// reference counting, nodes sit next to each other in memory, and a node
+/// costs its element plus 8 bytes of links. Removed slots go on a free list
+/// for later inserts to reuse.
+///
+/// Holds at most `u32::MAX - 1` elements.
+pub struct IndexList<T> {
+    nodes: Vec<Node<T>>,
+    head: u32,
+    tail: u32,
+    free: u32,
+    len: usize,
+}
+
+impl<T> IndexList<T> {
+    pub const fn new() -> Self {
+        IndexList { nodes: Vec::new(), head: NIL, tail: NIL, free: NIL, len: 0 }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        IndexList { nodes: Vec::with_capacity(capacity), ..Self::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of elements the list can hold before it reallocates.
+    pub fn capacity(&self) -> usize {
+        self.nodes.capacity()
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.head = NIL;
+        self.tail = NIL;
+        self.free = NIL;
+        self.len = 0;
+    }
+
+    fn link(index: u32) -> Option<ListIndex> {
+        (index != NIL).then_some(ListIndex(index))
+    }
+
+    fn node(&self, index: ListIndex) -> &Node<T> {
+        self.try_node(index).expect("stale index list handle")
+    }
+
+    fn try_node(&self, index: ListIndex) -> Result<&Node<T>, CollectionError> {
+        self.nodes.get(index.0 as usize).filter(|node| node.val.is_some()).ok_or(CollectionError::StaleHandle)
+    }
+
+    // Fill a free slot, or a new one, with an unlinked node
+    fn alloc(&mut self, val: T) -> u32 {
+        let node = Node { val: Some(val), prev: NIL, next: NIL };
+        self.len += 1;
+        if self.free != NIL {
+            let index = self.free;
+            self.free = self.nodes[index as usize].next;
+            self.nodes[index as usize] = node;
+            return index;
+        }
+        assert!(self.nodes.len() < NIL as usize, "index list is full");
+        self.nodes.push(node);
+        (self.nodes.len() - 1) as u32
+    }
+
+    // Link an unlinked node in between `prev` and `next`, either of which
+    // may be NIL for the ends
+    fn link_between(&mut self, index: u32, prev: u32, next: u32) {
+        self.nodes[index as usize].prev = prev;
+        self.nodes[index as usize].next = next;
+        match prev {
+            NIL => self.head = index,
+            prev => self.nodes[prev as usize].next = index,
+        }
+        match next {
+            NIL => self.tail = index,
+            next => self.nodes[next as usize].prev = index,
+        }
+    }
+
+    fn unlink(&mut self, index: u32) {
+        let Node { prev, next, .. } = self.nodes[index as usize];
+        match prev {
+            NIL => self.head = next,
+            prev => self.nodes[prev as usize].next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            next => self.nodes[next as usize].prev = prev,
+        }
+    }
+
+    pub fn push_front(&mut self, val: T) -> ListIndex {
+        let index = self.alloc(val);
+        self.link_between(index, NIL, self.head);
+        ListIndex(index)
+    }
+
+    pub fn push_back(&mut self, val: T) -> ListIndex {
+        let index = self.alloc(val);
+        self.link_between(index, self.tail, NIL);
+        ListIndex(index)
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.remove(Self::link(self.head)?)
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.remove(Self::link(self.tail)?)
+    }
+
+    /// Insert `val` right after the element at `index`. Panics if `index`
+    /// has been removed.
+    pub fn insert_after(&mut self, index: ListIndex, val: T) -> ListIndex {
+        let next = self.node(index).next;
+        let new = self.alloc(val);
+        self.link_between(new, index.0, next);
+        ListIndex(new)
+    }
+
+    /// `insert_after`, but returning an error if `index` has been removed.
+    pub fn try_insert_after(&mut self, index: ListIndex, val: T) -> Result<ListIndex, CollectionError> {
+        self.try_node(index)?;
+        Ok(self.insert_after(index, val))
+    }
+
+    /// Insert `val` right before the element at `index`. Panics if `index`
+    /// has been removed.
+    pub fn insert_before(&mut self, index: ListIndex, val: T) -> ListIndex {
+        let prev = self.node(index).prev;
+        let new = self.alloc(val);
+        self.link_between(new, prev, index.0);
+        ListIndex(new)
+    }
+
+    /// `insert_before`, but returning an error if `index` has been removed.
+    pub fn try_insert_before(&mut self, index: ListIndex, val: T) -> Result<ListIndex, CollectionError> {
+        self.try_node(index)?;
+        Ok(self.insert_before(index, val))
+    }
+
+    /// Remove and return the element at `index`, or `None` if it has
+    /// already been removed.
+    pub fn remove(&mut self, index: ListIndex) -> Option<T> {
+        let val = self.nodes.get_mut(index.0 as usize)?.val.take()?;
+        self.unlink(index.0);
+        self.nodes[index.0 as usize].next = self.free;
+        self.free = index.0;
+        self.len -= 1;
+        Some(val)
+    }
+
+    /// Move the element at `index` to the front. Panics if `index` has been
+    /// removed.
+    pub fn move_to_front(&mut self, index: ListIndex) {
+        self.node(index);
+        if self.head != index.0 {
+            self.unlink(index.0);
+            self.link_between(index.0, NIL, self.head);
+        }
+    }
+
+    /// `move_to_front`, but returning an error if `index` has been removed.
+    pub fn try_move_to_front(&mut self, index: ListIndex) -> Result<(), CollectionError> {
+        self.try_node(index)?;
+        self.move_to_front(index);
+        Ok(())
+    }
+
+    /// Move the element at `index` to the back, as an LRU list does on each
+    /// use. Panics if `index` has been removed.
+    pub fn move_to_back(&mut self, index: ListIndex) {
+        self.node(index);
+        if self.tail != index.0 {
+            self.unlink(index.0);
+            self.link_between(index.0, self.tail, NIL);
+        }
+    }
+
+    /// `move_to_back`, but returning an error if `index` has been removed.
+    pub fn try_move_to_back(&mut self, index: ListIndex) -> Result<(), CollectionError> {
+        self.try_node(index)?;
+        self.move_to_back(index);
+        Ok(())
+    }
+
+    /// Move the element at `index` to just after the one at `after`. Panics
+    /// if either has been removed.
+    pub fn move_after(&mut self, index: ListIndex, after: ListIndex) {
+        self.node(index);
+        self.node(after);
+        if index != after {
+            self.unlink(index.0);
+            let next = self.nodes[after.0 as usize].next;
+            self.link_between(index.0, after.0, next);
+        }
+    }
+
+    /// `move_after`, but returning an error if either has been removed.
+    pub fn try_move_after(&mut self, index: ListIndex, after: ListIndex) -> Result<(), CollectionError> {
+        self.try_node(index)?;
+        self.try_node(after)?;
+        self.move_after(index, after);
+        Ok(())
+    }
+
+    pub fn get(&self, index: ListIndex) -> Option<&T> {
+        self.nodes.get(index.0 as usize)?.val.as_ref()
+    }
+
+    pub fn get_mut(&mut self, index: ListIndex) -> Option<&mut T> {
+        self.nodes.get_mut(index.0 as usize)?.val.as_mut()
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.get(self.first()?)
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.get(self.last()?)
+    }
+
+    pub fn first(&self) -> Option<ListIndex> {
+        Self::link(self.head)
+    }
+
+    pub fn last(&self) -> Option<ListIndex> {
+        Self::link(self.tail)
+    }
+
+    /// The element after the one at `index`. Panics if `index` has been
+    /// removed.
+    pub fn next(&self, index: ListIndex) -> Option<ListIndex> {
+        Self::link(self.node(index).next)
+    }
+
+    /// `next`, but returning an error if `index` has been removed.
+    pub fn try_next(&self, index: ListIndex) -> Result<Option<ListIndex>, CollectionError> {
+        Ok(Self::link(self.try_node(index)?.next))
+    }
+
+    /// The element before the one at `index`. Panics if `index` has been
+    /// removed.
+    pub fn prev(&self, index: ListIndex) -> Option<ListIndex> {
+        Self::link(self.node(index).prev)
+    }
+
+    /// `prev`, but returning an error if `index` has been removed.
+    pub fn try_prev(&self, index: ListIndex) -> Result<Option<ListIndex>, CollectionError> {
+        Ok(Self::link(self.try_node(index)?.prev))
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { list: self, front: self.head, back: self.tail, remaining: self.len }
+    }
+
+    /// Panic unless the nodes are doubly linked from head to tail, the free
+    /// list holds exactly the empty slots, and `len` is right. Only in debug
+    /// builds and tests.
+    #[cfg(any(test, debug_assertions))]
+    pub fn validate(&self) {
+        let mut count = 0;
+        let (mut prev, mut curr) = (NIL, self.head);
+        while curr != NIL {
+            let node = &self.nodes[curr as usize];
+            assert!(node.val.is_some() && node.prev == prev, "node {count} is free or has a stale prev link");
+            count += 1;
+            (prev, curr) = (curr, node.next);
+        }
+        assert_eq!(self.tail, prev, "tail isn't the last node");
+        assert_eq!(count, self.len, "list length is out of date");
+        let mut free = 0;
+        let mut curr = self.free;
+        while curr != NIL {
+            assert!(self.nodes[curr as usize].val.is_none(), "occupied slot on the free list");
+            free += 1;
+            curr = self.nodes[curr as usize].next;
+        }
+        assert_eq!(count + free, self.nodes.len(), "slots missing from the free list");
+    }
+}
+
+impl<T> Default for IndexList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Sequence<T> for IndexList<T> {
+    type Iter<'a>
+        = Iter<'a, T>
+    where
+        T: 'a;
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn push_front(&mut self, val: T) {
+        self.push_front(val);
+    }
+
+    fn push_back(&mut self, val: T) {
+        self.push_back(val);
+    }
+
+    fn pop_front(&mut self) -> Option<T> {
+        self.pop_front()
+    }
+
+    fn pop_back(&mut self) -> Option<T> {
+        self.pop_back()
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
+    }
+
+    #[cfg(any(test, debug_assertions))]
+    fn validate(&self) {
+        self.validate();
+    }
+}
+
+pub struct Iter<'a, T> {
+    list: &'a IndexList<T>,
+    front: u32,
+    back: u32,
+    // Stops front and back from crossing
+    remaining: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = &self.list.nodes[self.front as usize];
+        self.front = node.next;
+        self.remaining -= 1;
+        node.val.as_ref()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = &self.list.nodes[self.back as usize];
+        self.back = node.prev;
+        self.remaining -= 1;
+        node.val.as_ref()
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<'a, T> IntoIterator for &'a IndexList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T> FromIterator<T> for IndexList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = IndexList::new();
+        for val in iter {
+            list.push_back(val);
+        }
+        list
+    }
+}
+
+impl<T: Debug> Debug for IndexList<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: HeapSize> HeapSize for IndexList<T> {
+    fn heap_size(&self) -> usize {
+        self.nodes.capacity() * size_of::<Node<T>>() + payload(self.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::CollectionError;
+    use crate::heap_size::HeapSize;
+
+    use super::{IndexList, Node};
+
+    #[test]
+    fn test_push_pop() {
+        let mut list = IndexList::new();
+        assert!(list.is_empty());
+        list.push_back(2);
+        list.push_front(1);
+        list.push_back(3);
+        assert_eq!(format!("{list:?}"), "[1, 2, 3]");
+        assert_eq!((list.front(), list.back()), (Some(&1), Some(&3)));
+        assert_eq!(list.iter().rev().copied().collect::<Vec<_>>(), vec![3, 2, 1]);
+        assert_eq!(list.pop_back(), Some(3));
+        assert_eq!(list.pop_front(), Some(1));
+        assert_eq!(list.pop_front(), Some(2));
+        assert_eq!((list.pop_front(), list.pop_back()), (None, None));
+        list.validate();
+    }
+
+    #[test]
+    fn test_handles() {
+        let mut list: IndexList<char> = "bd".chars().collect();
+        let b = list.first().unwrap();
+        let d = list.next(b).unwrap();
+        let c = list.insert_after(b, 'c');
+        let a = list.insert_before(b, 'a');
+        let e = list.insert_after(d, 'e');
+        list.validate();
+        assert_eq!(list.iter().collect::<String>(), "abcde");
+        assert_eq!((list.prev(a), list.next(e), list.last()), (None, None, Some(e)));
+
+        *list.get_mut(c).unwrap() = 'C';
+        list.move_to_front(e);
+        list.move_to_back(a);
+        list.move_after(b, d);
+        list.validate();
+        assert_eq!(list.iter().collect::<String>(), "eCdba");
+
+        assert_eq!(list.remove(d), Some('d'));
+        assert_eq!(list.remove(d), None);
+        assert_eq!(list.get(d), None);
+        list.validate();
+        assert_eq!(list.iter().collect::<String>(), "eCba");
+
+        assert_eq!(list.try_insert_after(d, 'x'), Err(CollectionError::StaleHandle));
+        assert_eq!(list.try_insert_before(d, 'x'), Err(CollectionError::StaleHandle));
+        assert_eq!(list.try_move_to_front(d), Err(CollectionError::StaleHandle));
+        assert_eq!(list.try_move_to_back(d), Err(CollectionError::StaleHandle));
+        assert_eq!(list.try_move_after(b, d), Err(CollectionError::StaleHandle));
+        assert_eq!(list.try_next(d), Err(CollectionError::StaleHandle));
+        assert_eq!(list.try_prev(d), Err(CollectionError::StaleHandle));
+        list.validate();
+        assert_eq!(list.iter().collect::<String>(), "eCba");
+
+        let f = list.try_insert_before(e, 'f').unwrap();
+        list.try_move_after(f, a).unwrap();
+        list.try_move_to_front(a).unwrap();
+        list.try_move_to_back(e).unwrap();
+        assert_eq!((list.try_prev(e), list.try_next(a)), (Ok(Some(f)), Ok(Some(c))));
+        let g = list.try_insert_after(e, 'g').unwrap();
+        assert_eq!(list.get(g), Some(&'g'));
+        list.validate();
+        assert_eq!(list.iter().collect::<String>(), "aCbfeg");
+    }
+
+    #[test]
+    #[should_panic(expected = "stale index list handle")]
+    fn test_stale_handle() {
+        let mut list = IndexList::new();
+        let a = list.push_back(1);
+        list.remove(a);
+        list.insert_after(a, 2);
+    }
+
+    #[test]
+    fn test_slots_reused() {
+        let mut list = IndexList::with_capacity(100);
+        for i in 0..100 {
+            list.push_back(i);
+        }
+        // A queue workload runs entirely on freed slots
+        for i in 100..10_000 {
+            assert_eq!(list.pop_front(), Some(i - 100));
+            list.push_back(i);
+        }
+        list.validate();
+        assert_eq!(list.capacity(), 100);
+        assert_eq!(list.heap_size(), 100 * size_of::<Node<i32>>());
+        assert!(list.iter().copied().eq(9900..10_000));
+        list.clear();
+        assert!(list.is_empty());
+    }
+}
@@ -0,0 +1,262 @@
+use std::fmt::Debug;
+use std::ops::Range;
+
+use crate::heap_size::HeapSize;
+use crate::treap::Treap;
+
+/// A set of points stored as disjoint half-open ranges.
+///
+/// Inserting a range coalesces it with every range it overlaps or touches, and
+/// removing a range trims or splits the ranges it cuts through, so the stored
+/// ranges are always sorted, non-empty, and separated by gaps. They are kept
+/// in a `Treap` from start to end, and updates split it around the affected
+/// span and merge it back together.
+pub struct IntervalSet<K> {
+    ranges: Treap<K, K>,
+}
+
+impl<K: Ord + Clone> IntervalSet<K> {
+    pub fn new() -> Self {
+        IntervalSet { ranges: Treap::new() }
+    }
+
+    /// Number of disjoint ranges.
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+    }
+
+    /// Add every point in `range`. Empty ranges are ignored.
+    pub fn insert(&mut self, range: Range<K>) {
+        if range.is_empty() {
+            return;
+        }
+        let Range { mut start, mut end } = range;
+        let (mut left, rest) = std::mem::take(&mut self.ranges).split(&start);
+        if let Some((s, e)) = left.last_key_value()
+            && *e >= start
+        {
+            start = s.clone();
+            end = end.max(e.clone());
+            left.remove(&start);
+        }
+        // Everything starting inside the new range gets absorbed
+        let (inside, mut right) = rest.split(&end);
+        if let Some((_, e)) = inside.last_key_value() {
+            end = end.max(e.clone());
+        }
+        if let Some((s, _)) = right.first_key_value()
+            && *s == end
+        {
+            let s = s.clone();
+            end = right.remove(&s).unwrap();
+        }
+        left.insert(start, end);
+        self.ranges = left.merge(right);
+    }
+
+    /// Remove every point in `range`, splitting a stored range in two if
+    /// `range` falls strictly inside it.
+    pub fn remove(&mut self, range: Range<K>) {
+        if range.is_empty() {
+            return;
+        }
+        let mut remnant = None;
+        let (mut left, rest) = std::mem::take(&mut self.ranges).split(&range.start);
+        if let Some((s, e)) = left.last_key_value()
+            && *e > range.start
+        {
+            if *e > range.end {
+                remnant = Some(e.clone());
+            }
+            let s = s.clone();
+            left.insert(s, range.start.clone());
+        }
+        let (inside, right) = rest.split(&range.end);
+        if let Some((_, e)) = inside.last_key_value()
+            && *e > range.end
+        {
+            remnant = Some(e.clone());
+        }
+        self.ranges = left.merge(right);
+        if let Some(end) = remnant {
+            self.ranges.insert(range.end, end);
+        }
+    }
+
+    pub fn contains(&self, point: &K) -> bool {
+        self.ranges.floor(point).is_some_and(|(_, end)| point < end)
+    }
+
+    /// The stored range containing `point`, if any.
+    pub fn range_containing(&self, point: &K) -> Option<Range<K>> {
+        let (start, end) = self.ranges.floor(point)?;
+        (point < end).then(|| start.clone()..end.clone())
+    }
+
+    /// The stored ranges in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = Range<K>> + '_ {
+        self.ranges.iter().map(|(s, e)| s.clone()..e.clone())
+    }
+
+    /// The maximal sub-ranges of `within` not covered by the set, in
+    /// ascending order.
+    pub fn gaps(&self, within: Range<K>) -> Vec<Range<K>> {
+        let mut out = Vec::new();
+        let mut pos = within.start;
+        for (s, e) in self.ranges.iter() {
+            if *s >= within.end {
+                break;
+            }
+            if *e <= pos {
+                continue;
+            }
+            if *s > pos {
+                out.push(pos.clone()..s.clone());
+            }
+            pos = e.clone();
+        }
+        if pos < within.end {
+            out.push(pos..within.end);
+        }
+        out
+    }
+}
+
+impl<K: Ord + Clone> Default for IntervalSet<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord + Clone> FromIterator<Range<K>> for IntervalSet<K> {
+    fn from_iter<I: IntoIterator<Item = Range<K>>>(iter: I) -> Self {
+        let mut set = IntervalSet::new();
+        for range in iter {
+            set.insert(range);
+        }
+        set
+    }
+}
+
+impl<K: Ord + Clone + Debug> Debug for IntervalSet<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<K: HeapSize> HeapSize for IntervalSet<K> {
+    fn heap_size(&self) -> usize {
+        self.ranges.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IntervalSet;
+    use std::ops::Range;
+
+    fn ranges(set: &IntervalSet<i32>) -> Vec<Range<i32>> {
+        set.iter().collect()
+    }
+
+    #[test]
+    fn test_insert_coalesces() {
+        let mut set = IntervalSet::new();
+        set.insert(10..20);
+        set.insert(30..40);
+        set.insert(5..5);
+        assert_eq!(ranges(&set), vec![10..20, 30..40]);
+
+        // Touching ranges merge
+        set.insert(20..25);
+        assert_eq!(ranges(&set), vec![10..25, 30..40]);
+
+        // Overlapping on both sides
+        set.insert(22..32);
+        assert_eq!(ranges(&set), vec![10..40]);
+
+        // Swallowing several ranges
+        set.insert(50..60);
+        set.insert(70..80);
+        set.insert(45..100);
+        assert_eq!(ranges(&set), vec![10..40, 45..100]);
+
+        // Already covered
+        set.insert(12..18);
+        assert_eq!(ranges(&set), vec![10..40, 45..100]);
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn test_remove_splits() {
+        let mut set = IntervalSet::new();
+        set.insert(0..100);
+        set.remove(40..60);
+        assert_eq!(ranges(&set), vec![0..40, 60..100]);
+
+        set.remove(30..70);
+        assert_eq!(ranges(&set), vec![0..30, 70..100]);
+
+        set.remove(0..10);
+        set.remove(95..200);
+        assert_eq!(ranges(&set), vec![10..30, 70..95]);
+
+        set.remove(30..70);
+        assert_eq!(ranges(&set), vec![10..30, 70..95]);
+
+        set.remove(-5..500);
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn test_contains() {
+        let set: IntervalSet<i32> = [0..10, 20..30].into_iter().collect();
+        assert!(set.contains(&0));
+        assert!(set.contains(&9));
+        assert!(!set.contains(&10));
+        assert!(!set.contains(&-1));
+        assert!(set.contains(&25));
+        assert!(!set.contains(&30));
+        assert_eq!(set.range_containing(&5), Some(0..10));
+        assert_eq!(set.range_containing(&15), None);
+    }
+
+    #[test]
+    fn test_gaps() {
+        let set: IntervalSet<i32> = [10..20, 30..40, 50..60].into_iter().collect();
+        assert_eq!(set.gaps(0..100), vec![0..10, 20..30, 40..50, 60..100]);
+        assert_eq!(set.gaps(15..35), vec![20..30]);
+        assert_eq!(set.gaps(12..18), vec![]);
+        assert_eq!(set.gaps(22..25), vec![22..25]);
+        assert_eq!(IntervalSet::new().gaps(1..2), vec![1..2]);
+    }
+
+    #[test]
+    fn test_matches_naive() {
+        let mut set = IntervalSet::new();
+        let mut naive = [false; 220];
+        for i in 0..300usize {
+            let start = (i * 37) % 190;
+            let end = start + (i * 13) % 17;
+            if i % 3 == 0 {
+                set.remove(start..end);
+            } else {
+                set.insert(start..end);
+            }
+            naive[start..end].fill(i % 3 != 0);
+        }
+        for (p, &covered) in naive.iter().enumerate() {
+            assert_eq!(set.contains(&p), covered, "point {p}");
+        }
+        let ranges: Vec<_> = set.iter().collect();
+        assert!(ranges.windows(2).all(|w| w[0].end < w[1].start));
+    }
+}
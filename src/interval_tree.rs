@@ -0,0 +1,395 @@
+use std::fmt::Debug;
+use std::ops::Range;
+
+use crate::dot::{DotWriter, ToDot};
+use crate::heap_size::HeapSize;
+use crate::pretty::{PrettyPrint, sideways};
+use crate::rng::XorShift64;
+
+type Link<K, V> = Option<Box<Node<K, V>>>;
+
+struct Node<K, V> {
+    interval: Range<K>,
+    value: V,
+    // Largest interval end anywhere in this subtree.
+    max_end: K,
+    priority: u64,
+    left: Link<K, V>,
+    right: Link<K, V>,
+}
+
+impl<K: Ord + Clone, V> Node<K, V> {
+    fn update(&mut self) {
+        let mut max_end = &self.interval.end;
+        for child in [&self.left, &self.right].into_iter().flatten() {
+            max_end = max_end.max(&child.max_end);
+        }
+        self.max_end = max_end.clone();
+    }
+
+    fn key(&self) -> (&K, &K) {
+        (&self.interval.start, &self.interval.end)
+    }
+}
+
+/// Check that intervals are in order and within `lower..=upper`, that no
+/// node has a higher priority than its parent, and that `max_end` is right.
+/// Returns the number of nodes in the subtree.
+#[cfg(any(test, debug_assertions))]
+fn validate<K: Ord + Clone, V>(link: &Link<K, V>, lower: Option<(&K, &K)>, upper: Option<(&K, &K)>, max_priority: u64) -> usize {
+    let Some(node) = link else {
+        return 0;
+    };
+    assert!(lower.is_none_or(|lower| lower <= node.key()) && upper.is_none_or(|upper| node.key() <= upper), "intervals out of order");
+    assert!(node.priority <= max_priority, "priority above its parent's");
+    let count = 1 + validate(&node.left, lower, Some(node.key()), node.priority)
+        + validate(&node.right, Some(node.key()), upper, node.priority);
+    let max_end = [&node.left, &node.right].into_iter().flatten().map(|child| &child.max_end).fold(&node.interval.end, Ord::max);
+    assert!(node.max_end == *max_end, "stale max_end");
+    count
+}
+
+/// Split into the intervals ordered before `key` and the rest.
+fn split<K: Ord + Clone, V>(link: Link<K, V>, key: (&K, &K)) -> (Link<K, V>, Link<K, V>) {
+    let Some(mut node) = link else {
+        return (None, None);
+    };
+    if node.key() < key {
+        let (less, rest) = split(node.right.take(), key);
+        node.right = less;
+        node.update();
+        (Some(node), rest)
+    } else {
+        let (less, rest) = split(node.left.take(), key);
+        node.left = rest;
+        node.update();
+        (less, Some(node))
+    }
+}
+
+fn merge<K: Ord + Clone, V>(left: Link<K, V>, right: Link<K, V>) -> Link<K, V> {
+    match (left, right) {
+        (None, right) => right,
+        (left, None) => left,
+        (Some(mut l), Some(mut r)) => {
+            if l.priority > r.priority {
+                l.right = merge(l.right.take(), Some(r));
+                l.update();
+                Some(l)
+            } else {
+                r.left = merge(Some(l), r.left.take());
+                r.update();
+                Some(r)
+            }
+        }
+    }
+}
+
+fn remove<K: Ord + Clone, V>(link: &mut Link<K, V>, interval: &Range<K>) -> Option<V> {
+    let node = link.as_mut()?;
+    let removed = match (&interval.start, &interval.end).cmp(&node.key()) {
+        std::cmp::Ordering::Less => remove(&mut node.left, interval),
+        std::cmp::Ordering::Greater => remove(&mut node.right, interval),
+        std::cmp::Ordering::Equal => {
+            let mut node = link.take()?;
+            *link = merge(node.left.take(), node.right.take());
+            return Some(node.value);
+        }
+    };
+    if removed.is_some() {
+        node.update();
+    }
+    removed
+}
+
+/// Collect, in order, the intervals that end after `after` and whose start
+/// satisfies `start_ok`, which must hold for a prefix of the start order.
+fn search<'a, K: Ord, V>(
+    link: &'a Link<K, V>,
+    after: &K,
+    start_ok: &dyn Fn(&K) -> bool,
+    out: &mut Vec<(&'a Range<K>, &'a V)>,
+) {
+    let Some(node) = link else {
+        return;
+    };
+    if node.max_end <= *after {
+        return;
+    }
+    search(&node.left, after, start_ok, out);
+    if !start_ok(&node.interval.start) {
+        return;
+    }
+    if node.interval.end > *after {
+        out.push((&node.interval, &node.value));
+    }
+    search(&node.right, after, start_ok, out);
+}
+
+/// A map from half-open intervals `[start, end)` to values, answering
+/// "which intervals contain this point" and "which intervals overlap this
+/// range" in O(log n + k) expected time for k results.
+///
+/// Intervals are kept in a treap ordered by start, with every node also
+/// tracking the largest end in its subtree so whole subtrees that end too
+/// early can be skipped. The same interval may be inserted more than once.
+pub struct IntervalTree<K, V> {
+    root: Link<K, V>,
+    len: usize,
+    rng: XorShift64,
+}
+
+impl<K: Ord + Clone, V> IntervalTree<K, V> {
+    pub fn new() -> Self {
+        IntervalTree { root: None, len: 0, rng: XorShift64::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Panic unless the intervals are in order, the nodes are heap-ordered
+    /// by priority, every `max_end` is right, and `len` is right. Only in
+    /// debug builds and tests.
+    #[cfg(any(test, debug_assertions))]
+    pub fn validate(&self) {
+        assert_eq!(validate(&self.root, None, None, u64::MAX), self.len, "tree length is out of date");
+    }
+
+    /// Panics if `interval.start > interval.end`.
+    pub fn insert(&mut self, interval: Range<K>, value: V) {
+        assert!(interval.start <= interval.end, "interval start must not be after its end");
+        let (less, rest) = split(self.root.take(), (&interval.start, &interval.end));
+        let node = Box::new(Node {
+            max_end: interval.end.clone(),
+            interval,
+            value,
+            priority: self.rng.next_u64(),
+            left: None,
+            right: None,
+        });
+        self.root = merge(merge(less, Some(node)), rest);
+        self.len += 1;
+    }
+
+    /// Remove one occurrence of `interval`, returning its value.
+    pub fn remove(&mut self, interval: &Range<K>) -> Option<V> {
+        let removed = remove(&mut self.root, interval)?;
+        self.len -= 1;
+        Some(removed)
+    }
+
+    /// All intervals containing `point`, ordered by start.
+    pub fn containing(&self, point: &K) -> Vec<(&Range<K>, &V)> {
+        let mut out = Vec::new();
+        search(&self.root, point, &|start| start <= point, &mut out);
+        out
+    }
+
+    /// All intervals overlapping `range`, ordered by start.
+    pub fn overlapping(&self, range: &Range<K>) -> Vec<(&Range<K>, &V)> {
+        let mut out = Vec::new();
+        search(&self.root, &range.start, &|start| *start < range.end, &mut out);
+        out
+    }
+
+    /// All intervals ordered by start.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        let mut iter = Iter { stack: Vec::new() };
+        iter.push_left(self.root.as_deref());
+        iter
+    }
+}
+
+impl<K: Ord + Clone, V> Default for IntervalTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// In-order iterator over the intervals of an interval tree.
+pub struct Iter<'a, K, V> {
+    stack: Vec<&'a Node<K, V>>,
+}
+
+impl<'a, K, V> Iter<'a, K, V> {
+    fn push_left(&mut self, mut link: Option<&'a Node<K, V>>) {
+        while let Some(node) = link {
+            self.stack.push(node);
+            link = node.left.as_deref();
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a Range<K>, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.push_left(node.right.as_deref());
+        Some((&node.interval, &node.value))
+    }
+}
+
+impl<K: Ord + Clone + Debug, V: Debug> Debug for IntervalTree<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+// Write a subtree and return the id of its root
+fn write_dot<K: Debug, V>(dot: &mut DotWriter, node: &Node<K, V>) -> Result<usize, std::fmt::Error> {
+    let label = format!("{:?}\nmax end {:?}\npriority {}", node.interval, node.max_end, node.priority);
+    let id = dot.node(&label)?;
+    for (child, side) in [(&node.left, "L"), (&node.right, "R")] {
+        if let Some(child) = child {
+            let child = write_dot(dot, child)?;
+            dot.edge(id, child, side)?;
+        }
+    }
+    Ok(id)
+}
+
+/// Labels each node with its interval, the largest end in its subtree and
+/// its priority.
+impl<K: Debug, V> ToDot for IntervalTree<K, V> {
+    fn write_dot(&self, out: &mut dyn std::fmt::Write) -> std::fmt::Result {
+        let mut dot = DotWriter::new(out)?;
+        if let Some(root) = &self.root {
+            write_dot(&mut dot, root)?;
+        }
+        dot.finish()
+    }
+}
+
+impl<K: Debug, V> PrettyPrint for IntervalTree<K, V> {
+    fn pretty_print(&self) -> String {
+        sideways(self.root.as_deref(), |node| [node.left.as_deref(), node.right.as_deref()], |node| format!("{:?}", node.interval))
+    }
+}
+
+impl<K: HeapSize, V: HeapSize> HeapSize for Node<K, V> {
+    fn heap_size(&self) -> usize {
+        self.interval.heap_size()
+            + self.value.heap_size()
+            + self.max_end.heap_size()
+            + self.left.heap_size()
+            + self.right.heap_size()
+    }
+}
+
+impl<K: HeapSize, V: HeapSize> HeapSize for IntervalTree<K, V> {
+    fn heap_size(&self) -> usize {
+        self.root.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heap_size::HeapSize;
+
+    use super::IntervalTree;
+    use std::ops::Range;
+
+    fn values<'a>(found: Vec<(&Range<i32>, &'a &'a str)>) -> Vec<&'a str> {
+        found.into_iter().map(|(_, v)| *v).collect()
+    }
+
+    fn calendar() -> IntervalTree<i32, &'static str> {
+        let mut tree = IntervalTree::new();
+        tree.insert(9..10, "standup");
+        tree.insert(9..12, "focus");
+        tree.insert(12..13, "lunch");
+        tree.insert(14..16, "review");
+        tree.insert(15..17, "1:1");
+        tree
+    }
+
+    #[test]
+    fn test_new() {
+        let tree: IntervalTree<i32, ()> = IntervalTree::new();
+        assert_eq!(tree.len(), 0);
+        assert!(tree.is_empty());
+        assert!(tree.containing(&0).is_empty());
+    }
+
+    #[test]
+    fn test_containing() {
+        let tree = calendar();
+        assert_eq!(tree.len(), 5);
+        assert_eq!(values(tree.containing(&9)), vec!["standup", "focus"]);
+        assert_eq!(values(tree.containing(&10)), vec!["focus"]);
+        assert_eq!(values(tree.containing(&12)), vec!["lunch"]);
+        assert_eq!(values(tree.containing(&13)), Vec::<&str>::new());
+        assert_eq!(values(tree.containing(&15)), vec!["review", "1:1"]);
+        assert_eq!(values(tree.containing(&17)), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_overlapping() {
+        let tree = calendar();
+        assert_eq!(values(tree.overlapping(&(11..15))), vec!["focus", "lunch", "review"]);
+        assert_eq!(values(tree.overlapping(&(13..14))), Vec::<&str>::new());
+        assert_eq!(values(tree.overlapping(&(0..100))).len(), 5);
+        assert_eq!(values(tree.overlapping(&(16..20))), vec!["1:1"]);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut tree = calendar();
+        assert_eq!(tree.remove(&(9..12)), Some("focus"));
+        assert_eq!(tree.remove(&(9..12)), None);
+        assert_eq!(tree.len(), 4);
+        tree.validate();
+        assert_eq!(values(tree.containing(&11)), Vec::<&str>::new());
+        assert_eq!(values(tree.containing(&9)), vec!["standup"]);
+    }
+
+    #[test]
+    fn test_duplicates_and_order() {
+        let mut tree = IntervalTree::new();
+        tree.insert(5..8, 1);
+        tree.insert(1..3, 2);
+        tree.insert(5..8, 3);
+        let starts: Vec<_> = tree.iter().map(|(r, _)| r.start).collect();
+        assert_eq!(starts, vec![1, 5, 5]);
+        tree.validate();
+        assert_eq!(tree.containing(&6).len(), 2);
+    }
+
+    #[test]
+    fn test_matches_naive() {
+        let mut tree = IntervalTree::new();
+        let mut naive = Vec::new();
+        for i in 0..300 {
+            let start = (i * 37) % 1000;
+            let interval = start..start + (i * 13) % 50 + 1;
+            tree.insert(interval.clone(), i);
+            naive.push(interval);
+        }
+        tree.validate();
+        for i in (0..300).step_by(3) {
+            let start = (i * 37) % 1000;
+            let interval = start..start + (i * 13) % 50 + 1;
+            assert_eq!(tree.remove(&interval), Some(i));
+            naive.retain(|r| *r != interval);
+        }
+        tree.validate();
+        for p in (0..1100).step_by(7) {
+            let expected = naive.iter().filter(|r| r.contains(&p)).count();
+            assert_eq!(tree.containing(&p).len(), expected);
+        }
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let mut tree = IntervalTree::new();
+        tree.insert(1..3, String::from("ab"));
+        tree.insert(2..5, String::from("cde"));
+        assert_eq!(tree.heap_size(), 2 * size_of::<super::Node<i32, String>>() + 5);
+    }
+}
@@ -0,0 +1,286 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fmt::{Display, Write};
+use std::hash::Hash;
+
+use crate::list::List;
+
+/// Adapters for the crate's iterators, which walk linked storage and so can
+/// not use the slice adapters from `std`, such as `slice::chunks`.
+///
+/// Implemented for every iterator.
+pub trait IterExt: Iterator + Sized {
+    /// Consecutive runs of `size` elements, collected into `Vec`s, with a
+    /// shorter last run if the elements don't divide evenly. Panics if
+    /// `size` is 0.
+    fn chunks(self, size: usize) -> Chunks<Self> {
+        assert!(size > 0, "chunk size must be non-zero");
+        Chunks { iter: self, size }
+    }
+
+    /// Each element paired with the one after it, so `n` elements give
+    /// `n - 1` pairs.
+    fn pairs(self) -> Pairs<Self>
+    where
+        Self::Item: Clone,
+    {
+        Pairs { iter: self, last: None }
+    }
+
+    /// Items built by calling `f` on the iterator for as long as it returns
+    /// `Some`, taking however many elements it needs each time.
+    fn batching<B, F: FnMut(&mut Self) -> Option<B>>(self, f: F) -> Batching<Self, F> {
+        Batching { iter: self, f }
+    }
+
+    /// How many times each distinct element occurs.
+    fn counts(self) -> HashMap<Self::Item, usize>
+    where
+        Self::Item: Hash + Eq,
+    {
+        let mut counts = HashMap::new();
+        for item in self {
+            *counts.entry(item).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// The first occurrence of each distinct element, in order. Keeps a
+    /// copy of each one seen.
+    fn unique(self) -> Unique<Self>
+    where
+        Self::Item: Clone + Hash + Eq,
+    {
+        Unique { iter: self, seen: HashSet::new() }
+    }
+
+    /// The elements in ascending order, in a `List`. The sort is stable.
+    fn sorted_into_list(self) -> List<Self::Item>
+    where
+        Self::Item: Ord,
+    {
+        let mut items: Vec<_> = self.collect();
+        items.sort();
+        List::from(items)
+    }
+
+    /// The elements formatted with `Display` and separated by `sep`.
+    fn join(self, sep: &str) -> String
+    where
+        Self::Item: Display,
+    {
+        let mut out = String::new();
+        for (i, item) in self.enumerate() {
+            if i > 0 {
+                out.push_str(sep);
+            }
+            write!(out, "{item}").unwrap();
+        }
+        out
+    }
+}
+
+impl<I: Iterator> IterExt for I {}
+
+/// Another name for `IterExt`, for code that imports it for the list
+/// helpers `counts`, `unique`, `sorted_into_list` and `join`.
+pub use IterExt as ListIterExt;
+
+pub struct Unique<I: Iterator> {
+    iter: I,
+    seen: HashSet<I::Item>,
+}
+
+impl<I: Iterator<Item: Clone + Hash + Eq>> Iterator for Unique<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.by_ref().find(|item| self.seen.insert(item.clone()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.iter.size_hint();
+        ((lower > 0 && self.seen.is_empty()) as usize, upper)
+    }
+}
+
+/// The elements of every source in one sorted stream, given that each source
+/// is sorted, as in the merge step of an external sort. Equal elements come
+/// out in the order of their sources. Takes O(log k) per element for `k`
+/// sources, holding one element of each in a heap.
+pub fn kmerge<I>(sources: impl IntoIterator<Item = I>) -> KMerge<I::IntoIter>
+where
+    I: IntoIterator<Item: Ord>,
+{
+    let mut iters = Vec::new();
+    let mut heap = BinaryHeap::new();
+    for (i, source) in sources.into_iter().enumerate() {
+        let mut iter = source.into_iter();
+        if let Some(item) = iter.next() {
+            heap.push(Reverse((item, i)));
+        }
+        iters.push(iter);
+    }
+    KMerge { iters, heap }
+}
+
+pub struct Chunks<I> {
+    iter: I,
+    size: usize,
+}
+
+impl<I: Iterator> Iterator for Chunks<I> {
+    type Item = Vec<I::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk: Vec<_> = self.iter.by_ref().take(self.size).collect();
+        (!chunk.is_empty()).then_some(chunk)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.iter.size_hint();
+        (lower.div_ceil(self.size), upper.map(|upper| upper.div_ceil(self.size)))
+    }
+}
+
+pub struct Pairs<I: Iterator> {
+    iter: I,
+    // The second element of the last pair, which is the first of the next
+    last: Option<I::Item>,
+}
+
+impl<I: Iterator<Item: Clone>> Iterator for Pairs<I> {
+    type Item = (I::Item, I::Item);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = match self.last.take() {
+            Some(last) => last,
+            None => self.iter.next()?,
+        };
+        let second = self.iter.next()?;
+        self.last = Some(second.clone());
+        Some((first, second))
+    }
+}
+
+pub struct Batching<I, F> {
+    iter: I,
+    f: F,
+}
+
+impl<B, I: Iterator, F: FnMut(&mut I) -> Option<B>> Iterator for Batching<I, F> {
+    type Item = B;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        (self.f)(&mut self.iter)
+    }
+}
+
+pub struct KMerge<I: Iterator> {
+    iters: Vec<I>,
+    // The next element of each source that has one, with the source's index
+    heap: BinaryHeap<Reverse<(I::Item, usize)>>,
+}
+
+impl<I: Iterator<Item: Ord>> Iterator for KMerge<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((item, i)) = self.heap.pop()?;
+        if let Some(next) = self.iters[i].next() {
+            self.heap.push(Reverse((next, i)));
+        }
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iters.iter().fold((self.heap.len(), Some(self.heap.len())), |(lower, upper), iter| {
+            let (l, u) = iter.size_hint();
+            (lower.saturating_add(l), upper.zip(u).and_then(|(a, b)| a.checked_add(b)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::doubly_list::LinkedList;
+    use crate::list::List;
+
+    use super::{IterExt, kmerge};
+
+    #[test]
+    fn test_chunks() {
+        let mut list = LinkedList::new();
+        (1..=7).for_each(|i| list.push_back(i));
+        let chunks: Vec<_> = list.iter().copied().chunks(3).collect();
+        assert_eq!(chunks, [vec![1, 2, 3], vec![4, 5, 6], vec![7]]);
+        assert_eq!(list.iter().chunks(3).size_hint(), (3, Some(3)));
+        assert_eq!(list.iter().chunks(7).count(), 1);
+        assert_eq!(LinkedList::<i32>::new().iter().chunks(2).next(), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty_chunks() {
+        [1].iter().chunks(0);
+    }
+
+    #[test]
+    fn test_pairs() {
+        let mut list = List::new();
+        [9, 4, 6, 1].iter().rev().for_each(|&i| list.push_front(i));
+        let steps: Vec<i32> = list.iter().pairs().map(|(a, b)| b - a).collect();
+        assert_eq!(steps, [-5, 2, -5]);
+        assert_eq!([1].iter().pairs().next(), None);
+    }
+
+    #[test]
+    fn test_counts_unique() {
+        let list = LinkedList::from(vec!["b", "a", "b", "c", "a", "b"]);
+        let counts = list.iter().counts();
+        assert_eq!((counts.len(), counts[&"a"], counts[&"b"]), (3, 2, 3));
+        assert!(list.iter().unique().eq(&["b", "a", "c"]));
+        assert_eq!([1, 1, 1].iter().unique().size_hint(), (1, Some(3)));
+    }
+
+    #[test]
+    fn test_sorted_into_list_join() {
+        let list = LinkedList::from(vec![3, 1, 2]);
+        let sorted = list.iter().sorted_into_list();
+        sorted.validate();
+        assert_eq!(sorted.iter().join(" <= "), "1 <= 2 <= 3");
+        assert_eq!(List::from(vec!["x"]).into_iter().join(", "), "x");
+        assert_eq!(List::<i32>::new().into_iter().join(", "), "");
+    }
+
+    #[test]
+    fn test_kmerge() {
+        let lists = [vec![1, 4, 7], vec![2, 5], vec![], vec![0, 3, 6, 9]].map(List::from);
+        assert_eq!(kmerge(lists).collect::<Vec<_>>(), [0, 1, 2, 3, 4, 5, 6, 7, 9]);
+        let lists = [LinkedList::from(vec!["b", "d"]), LinkedList::from(vec!["a", "c", "e"])];
+        assert!(kmerge(&lists).copied().eq(["a", "b", "c", "d", "e"]));
+
+        // Sorting in runs and merging them, external sort style
+        let list = LinkedList::from(vec![8, 3, 5, 1, 9, 2, 7, 3]);
+        let runs: Vec<Vec<i32>> = list.iter().copied().chunks(3).map(|mut run| { run.sort(); run }).collect();
+        let merged = kmerge(runs);
+        assert_eq!(merged.size_hint(), (8, Some(8)));
+        assert_eq!(merged.collect::<Vec<_>>(), [1, 2, 3, 3, 5, 7, 8, 9]);
+        assert_eq!(kmerge(Vec::<Vec<i32>>::new()).next(), None);
+    }
+
+    #[test]
+    fn test_batching() {
+        // Length-prefixed runs
+        let mut list = LinkedList::new();
+        [2, 10, 20, 0, 1, 30].iter().for_each(|&i| list.push_back(i));
+        let runs: Vec<Vec<i32>> = list
+            .iter()
+            .batching(|iter| {
+                let len = *iter.next()?;
+                Some(iter.take(len as usize).copied().collect())
+            })
+            .collect();
+        assert_eq!(runs, [vec![10, 20], vec![], vec![30]]);
+    }
+}
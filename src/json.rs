@@ -0,0 +1,241 @@
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use serde::de::{DeserializeOwned, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::btree::BTreeMap;
+use crate::chunked_seq::ChunkedSeq;
+use crate::doubly_list::LinkedList;
+use crate::gap_buffer::GapBuffer;
+use crate::hash_map::HashMap;
+use crate::list::List;
+use crate::sorted_vec_map::SortedVecMap;
+use crate::treap::Treap;
+use crate::unrolled_list::UnrolledList;
+
+/// Dumping a structure to JSON and loading it back, for eyeballing its
+/// contents while debugging and for keeping test fixtures in files.
+///
+/// Sequences are JSON arrays of their elements in order, and maps are JSON
+/// objects, whose keys must therefore serialize as strings or numbers.
+/// Anything with serde impls gets these for free, including every sequence
+/// and map in the crate when the `json` feature is on.
+pub trait Json: Serialize + DeserializeOwned {
+    /// The value as indented JSON. Fails if it holds a map whose keys JSON
+    /// can't represent.
+    fn to_json_pretty(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> Json for T {}
+
+// Reads a JSON object into its entries in the order they appear, leaving
+// it to each map to insert them
+struct Entries<K, V>(Vec<(K, V)>);
+
+impl<'de, K: Deserialize<'de>, V: Deserialize<'de>> Deserialize<'de> for Entries<K, V> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EntriesVisitor<K, V>(PhantomData<(K, V)>);
+
+        impl<'de, K: Deserialize<'de>, V: Deserialize<'de>> Visitor<'de> for EntriesVisitor<K, V> {
+            type Value = Entries<K, V>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a map")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(Entries(entries))
+            }
+        }
+
+        deserializer.deserialize_map(EntriesVisitor(PhantomData))
+    }
+}
+
+impl<T: Serialize> Serialize for List<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for List<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut list = List::new();
+        for val in Vec::deserialize(deserializer)?.into_iter().rev() {
+            list.push_front(val);
+        }
+        Ok(list)
+    }
+}
+
+impl<T: Serialize> Serialize for LinkedList<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for LinkedList<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut list = LinkedList::new();
+        for val in Vec::deserialize(deserializer)? {
+            list.push_back(val);
+        }
+        Ok(list)
+    }
+}
+
+impl<T: Serialize, const B: usize> Serialize for UnrolledList<T, B> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de, T: Deserialize<'de>, const B: usize> Deserialize<'de> for UnrolledList<T, B> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Vec::deserialize(deserializer)?.into_iter().collect())
+    }
+}
+
+impl<T: Serialize> Serialize for GapBuffer<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for GapBuffer<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Vec::deserialize(deserializer)?.into_iter().collect())
+    }
+}
+
+impl<T: Serialize + Clone> Serialize for ChunkedSeq<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de, T: Deserialize<'de> + Clone> Deserialize<'de> for ChunkedSeq<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Vec::deserialize(deserializer)?.into_iter().collect())
+    }
+}
+
+impl<K: Serialize, V: Serialize> Serialize for HashMap<K, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+impl<'de, K: Deserialize<'de> + Hash + Eq, V: Deserialize<'de>> Deserialize<'de> for HashMap<K, V> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Entries::deserialize(deserializer)?.0.into_iter().collect())
+    }
+}
+
+impl<K: Serialize + Ord, V: Serialize> Serialize for SortedVecMap<K, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+impl<'de, K: Deserialize<'de> + Ord, V: Deserialize<'de>> Deserialize<'de> for SortedVecMap<K, V> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Entries::deserialize(deserializer)?.0.into_iter().collect())
+    }
+}
+
+impl<K: Serialize + Ord, V: Serialize, const B: usize> Serialize for BTreeMap<K, V, B> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+impl<'de, K: Deserialize<'de> + Ord, V: Deserialize<'de>, const B: usize> Deserialize<'de> for BTreeMap<K, V, B> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut map = BTreeMap::new();
+        for (key, val) in Entries::deserialize(deserializer)?.0 {
+            map.insert(key, val);
+        }
+        Ok(map)
+    }
+}
+
+impl<K: Serialize + Ord, V: Serialize> Serialize for Treap<K, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+impl<'de, K: Deserialize<'de> + Ord, V: Deserialize<'de>> Deserialize<'de> for Treap<K, V> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut map = Treap::new();
+        for (key, val) in Entries::deserialize(deserializer)?.0 {
+            map.insert(key, val);
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::btree::BTreeMap;
+    use crate::chunked_seq::ChunkedSeq;
+    use crate::doubly_list::LinkedList;
+    use crate::gap_buffer::GapBuffer;
+    use crate::hash_map::HashMap;
+    use crate::list::List;
+    use crate::sorted_vec_map::SortedVecMap;
+    use crate::treap::Treap;
+    use crate::unrolled_list::UnrolledList;
+
+    use super::Json;
+
+    #[test]
+    fn test_sequences() {
+        let mut list = List::new();
+        (1..4).rev().for_each(|i| list.push_front(i));
+        let json = list.to_json_pretty().unwrap();
+        assert_eq!(json, "[\n  1,\n  2,\n  3\n]");
+
+        // Any sequence loads any other's dump
+        let list = LinkedList::<i32>::from_json(&json).unwrap();
+        let list = UnrolledList::<i32, 2>::from_json(&list.to_json_pretty().unwrap()).unwrap();
+        let buffer = GapBuffer::<i32>::from_json(&list.to_json_pretty().unwrap()).unwrap();
+        let seq = ChunkedSeq::<i32>::from_json(&buffer.to_json_pretty().unwrap()).unwrap();
+        assert!(seq.iter().copied().eq(1..4));
+        assert!(List::<i32>::from_json("[1, \"a\"]").is_err());
+    }
+
+    #[test]
+    fn test_maps() {
+        let map: SortedVecMap<String, u32> = [("b", 2), ("a", 1)].map(|(key, val)| (key.to_string(), val)).into_iter().collect();
+        let json = map.to_json_pretty().unwrap();
+        assert_eq!(json, "{\n  \"a\": 1,\n  \"b\": 2\n}");
+
+        let hash_map = HashMap::<String, u32>::from_json(&json).unwrap();
+        assert_eq!((hash_map.len(), hash_map.get("b")), (2, Some(&2)));
+        let btree = BTreeMap::<String, u32, 4>::from_json(&hash_map.to_json_pretty().unwrap()).unwrap();
+        let treap = Treap::<String, u32>::from_json(&btree.to_json_pretty().unwrap()).unwrap();
+        assert_eq!(treap.to_json_pretty().unwrap(), json);
+
+        // Number keys are written as strings and read back as numbers
+        let map: SortedVecMap<u32, bool> = (0..3).map(|i| (i, i % 2 == 0)).collect();
+        let copy = SortedVecMap::<u32, bool>::from_json(&map.to_json_pretty().unwrap()).unwrap();
+        assert!(copy.iter().eq(map.iter()));
+        // Tuples can't be object keys
+        let map: SortedVecMap<(u8, u8), ()> = [((1, 2), ())].into_iter().collect();
+        assert!(map.to_json_pretty().is_err());
+    }
+}
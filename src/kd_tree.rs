@@ -0,0 +1,221 @@
+use crate::heap_size::HeapSize;
+
+/// A K-dimensional tree over points in `K`-dimensional space, built in bulk
+/// from a set of points.
+///
+/// The tree is stored implicitly: the points are rearranged so that in each
+/// subslice the middle point is the splitting node for that level, with the
+/// lower half to its left and the upper half to its right. This needs no
+/// per-node allocation.
+pub struct KdTree<const K: usize> {
+    points: Vec<[f64; K]>,
+}
+
+fn build<const K: usize>(points: &mut [[f64; K]], depth: usize) {
+    if points.len() <= 1 {
+        return;
+    }
+    let axis = depth % K;
+    let mid = points.len() / 2;
+    points.select_nth_unstable_by(mid, |a, b| a[axis].total_cmp(&b[axis]));
+    let (left, right) = points.split_at_mut(mid);
+    build(left, depth + 1);
+    build(&mut right[1..], depth + 1);
+}
+
+/// Check that in every subslice, the points left of the middle one are no
+/// greater on that level's axis and the points right of it no less.
+#[cfg(any(test, debug_assertions))]
+fn validate<const K: usize>(points: &[[f64; K]], depth: usize) {
+    if points.len() <= 1 {
+        return;
+    }
+    let axis = depth % K;
+    let mid = points.len() / 2;
+    let split = points[mid][axis];
+    assert!(points[..mid].iter().all(|p| p[axis].total_cmp(&split).is_le()), "point left of the split is above it");
+    assert!(points[mid + 1..].iter().all(|p| p[axis].total_cmp(&split).is_ge()), "point right of the split is below it");
+    validate(&points[..mid], depth + 1);
+    validate(&points[mid + 1..], depth + 1);
+}
+
+fn distance_sq<const K: usize>(a: &[f64; K], b: &[f64; K]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+impl<const K: usize> KdTree<K> {
+    /// Build a balanced tree over `points` in O(n log n).
+    pub fn build(mut points: Vec<[f64; K]>) -> Self {
+        const { assert!(K > 0, "k-d tree needs at least one dimension") };
+        build(&mut points, 0);
+        KdTree { points }
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Panic unless every splitting point divides its subslice on that
+    /// level's axis. Only in debug builds and tests.
+    #[cfg(any(test, debug_assertions))]
+    pub fn validate(&self) {
+        validate(&self.points, 0);
+    }
+
+    /// The point closest to `query` by Euclidean distance, along with that
+    /// distance.
+    pub fn nearest(&self, query: &[f64; K]) -> Option<(&[f64; K], f64)> {
+        let mut best = None;
+        Self::nearest_in(&self.points, 0, query, &mut best);
+        best.map(|(point, dist_sq)| (point, dist_sq.sqrt()))
+    }
+
+    fn nearest_in<'a>(points: &'a [[f64; K]], depth: usize, query: &[f64; K], best: &mut Option<(&'a [f64; K], f64)>) {
+        if points.is_empty() {
+            return;
+        }
+        let mid = points.len() / 2;
+        let point = &points[mid];
+        let dist_sq = distance_sq(point, query);
+        if best.is_none_or(|(_, best_sq)| dist_sq < best_sq) {
+            *best = Some((point, dist_sq));
+        }
+        let axis = depth % K;
+        let diff = query[axis] - point[axis];
+        let (near, far) = if diff < 0.0 {
+            (&points[..mid], &points[mid + 1..])
+        } else {
+            (&points[mid + 1..], &points[..mid])
+        };
+        Self::nearest_in(near, depth + 1, query, best);
+        // Only cross the splitting plane if it is closer than the best so far
+        if best.is_none_or(|(_, best_sq)| diff * diff < best_sq) {
+            Self::nearest_in(far, depth + 1, query, best);
+        }
+    }
+
+    /// All points inside the axis-aligned box spanned by `min` and `max`,
+    /// bounds included.
+    pub fn range(&self, min: &[f64; K], max: &[f64; K]) -> Vec<&[f64; K]> {
+        let mut out = Vec::new();
+        Self::range_in(&self.points, 0, min, max, &mut out);
+        out
+    }
+
+    fn range_in<'a>(points: &'a [[f64; K]], depth: usize, min: &[f64; K], max: &[f64; K], out: &mut Vec<&'a [f64; K]>) {
+        if points.is_empty() {
+            return;
+        }
+        let mid = points.len() / 2;
+        let point = &points[mid];
+        if (0..K).all(|i| min[i] <= point[i] && point[i] <= max[i]) {
+            out.push(point);
+        }
+        let axis = depth % K;
+        if min[axis] <= point[axis] {
+            Self::range_in(&points[..mid], depth + 1, min, max, out);
+        }
+        if point[axis] <= max[axis] {
+            Self::range_in(&points[mid + 1..], depth + 1, min, max, out);
+        }
+    }
+
+    /// All points within Euclidean distance `radius` of `center`.
+    pub fn within_radius(&self, center: &[f64; K], radius: f64) -> Vec<&[f64; K]> {
+        let min = center.map(|x| x - radius);
+        let max = center.map(|x| x + radius);
+        let mut out = self.range(&min, &max);
+        out.retain(|p| distance_sq(p, center) <= radius * radius);
+        out
+    }
+
+    /// The points in the tree's internal order.
+    pub fn points(&self) -> &[[f64; K]] {
+        &self.points
+    }
+}
+
+impl<const K: usize> HeapSize for KdTree<K> {
+    fn heap_size(&self) -> usize {
+        self.points.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KdTree;
+
+    fn grid() -> Vec<[f64; 2]> {
+        let mut points = Vec::new();
+        for x in 0..10 {
+            for y in 0..10 {
+                points.push([x as f64, y as f64]);
+            }
+        }
+        points
+    }
+
+    #[test]
+    fn test_empty() {
+        let tree: KdTree<3> = KdTree::build(Vec::new());
+        assert!(tree.is_empty());
+        assert!(tree.nearest(&[0.0; 3]).is_none());
+        assert!(tree.range(&[0.0; 3], &[1.0; 3]).is_empty());
+    }
+
+    #[test]
+    fn test_nearest() {
+        let tree = KdTree::build(grid());
+        assert_eq!(tree.len(), 100);
+        tree.validate();
+        let (point, dist) = tree.nearest(&[3.2, 6.9]).unwrap();
+        assert_eq!(point, &[3.0, 7.0]);
+        assert!((dist - (0.04f64 + 0.01).sqrt()).abs() < 1e-9);
+
+        let (point, dist) = tree.nearest(&[-5.0, -5.0]).unwrap();
+        assert_eq!(point, &[0.0, 0.0]);
+        assert!((dist - 50f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_range() {
+        let tree = KdTree::build(grid());
+        let mut found = tree.range(&[2.0, 3.0], &[4.0, 4.5]);
+        found.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(found, vec![&[2.0, 3.0], &[2.0, 4.0], &[3.0, 3.0], &[3.0, 4.0], &[4.0, 3.0], &[4.0, 4.0]]);
+        assert!(tree.range(&[20.0, 20.0], &[30.0, 30.0]).is_empty());
+    }
+
+    #[test]
+    fn test_within_radius() {
+        let tree = KdTree::build(grid());
+        let found = tree.within_radius(&[5.0, 5.0], 1.0);
+        assert_eq!(found.len(), 5);
+    }
+
+    #[test]
+    fn test_nearest_matches_naive() {
+        let points: Vec<[f64; 3]> = (0..500)
+            .map(|i| {
+                let i = i as f64;
+                [(i * 0.37).sin() * 10.0, (i * 1.91).cos() * 10.0, (i * 0.13) % 7.0]
+            })
+            .collect();
+        let tree = KdTree::build(points.clone());
+        tree.validate();
+        for j in 0..50 {
+            let j = j as f64;
+            let query = [(j * 2.3).cos() * 12.0, (j * 0.7).sin() * 12.0, j % 7.0];
+            let naive = points
+                .iter()
+                .map(|p| p.iter().zip(&query).map(|(a, b)| (a - b) * (a - b)).sum::<f64>().sqrt())
+                .fold(f64::INFINITY, f64::min);
+            let (_, dist) = tree.nearest(&query).unwrap();
+            assert!((dist - naive).abs() < 1e-9);
+        }
+    }
+}
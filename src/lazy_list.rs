@@ -0,0 +1,310 @@
+use std::cell::{Cell, OnceCell};
+use std::fmt::Debug;
+use std::rc::Rc;
+
+// An element and the rest of the list, or `None` at the end
+type Forced<T> = Option<(T, LazyList<T>)>;
+
+type Thunk<T> = Box<dyn FnOnce() -> Forced<T>>;
+
+struct Node<T> {
+    forced: OnceCell<Forced<T>>,
+    // Taken and run the first time the node is forced
+    thunk: Cell<Option<Thunk<T>>>,
+}
+
+/// A singly linked list whose cells are only computed when first needed.
+///
+/// Each cell holds a closure producing the next element and the rest of the
+/// list. Forcing a cell runs the closure once and keeps the result, so every
+/// clone sees the same elements and nothing is computed twice. Since nothing
+/// past the last forced cell exists yet, lists can be infinite, and `map`,
+/// `filter` and `take` build new lazy lists without forcing anything.
+///
+/// Closures must be `'static`, as they are stored in the list.
+pub struct LazyList<T> {
+    node: Rc<Node<T>>,
+}
+
+impl<T: 'static> LazyList<T> {
+    /// A list computed by `thunk` when first forced.
+    pub fn new<F: FnOnce() -> Forced<T> + 'static>(thunk: F) -> Self {
+        LazyList { node: Rc::new(Node { forced: OnceCell::new(), thunk: Cell::new(Some(Box::new(thunk))) }) }
+    }
+
+    // An already forced list
+    fn forced(forced: Forced<T>) -> Self {
+        LazyList { node: Rc::new(Node { forced: OnceCell::from(forced), thunk: Cell::new(None) }) }
+    }
+
+    pub fn empty() -> Self {
+        Self::forced(None)
+    }
+
+    /// `head` in front of `tail`.
+    pub fn cons(head: T, tail: LazyList<T>) -> Self {
+        Self::forced(Some((head, tail)))
+    }
+
+    /// The elements produced by calling `f` on a state until it returns
+    /// `None`, each call made only when its element is needed.
+    pub fn unfold<S: 'static, F: Fn(S) -> Option<(T, S)> + 'static>(state: S, f: F) -> Self {
+        fn unfold<T: 'static, S: 'static, F: Fn(S) -> Option<(T, S)> + 'static>(state: S, f: Rc<F>) -> LazyList<T> {
+            LazyList::new(move || {
+                let (val, state) = f(state)?;
+                Some((val, unfold(state, f)))
+            })
+        }
+        unfold(state, Rc::new(f))
+    }
+
+    /// The infinite list `seed`, `f(seed)`, `f(f(seed))`, ...
+    pub fn iterate<F: Fn(&T) -> T + 'static>(seed: T, f: F) -> Self
+    where
+        T: Clone,
+    {
+        Self::unfold(seed, move |val| {
+            let next = f(&val);
+            Some((val, next))
+        })
+    }
+
+    /// The elements of `iter`, each pulled from it when first needed.
+    pub fn from_iter_lazily<I: Iterator<Item = T> + 'static>(iter: I) -> Self {
+        Self::unfold(Rc::new(Cell::new(Some(iter))), |iter: Rc<Cell<Option<I>>>| {
+            let mut it = iter.take()?;
+            let val = it.next();
+            iter.set(Some(it));
+            Some((val?, iter))
+        })
+    }
+
+    // Run the thunk if this is the first time
+    fn force(&self) -> Option<&(T, LazyList<T>)> {
+        self.node.forced.get_or_init(|| (self.node.thunk.take().expect("lazy list forced while being computed"))()).as_ref()
+    }
+
+    /// Whether the first cell has been computed yet.
+    pub fn is_forced(&self) -> bool {
+        self.node.forced.get().is_some()
+    }
+
+    /// Forces the first cell.
+    pub fn is_empty(&self) -> bool {
+        self.force().is_none()
+    }
+
+    /// The first element, forcing the first cell.
+    pub fn head(&self) -> Option<&T> {
+        self.force().map(|(head, _)| head)
+    }
+
+    /// Everything after the first element, forcing the first cell.
+    pub fn tail(&self) -> Option<&LazyList<T>> {
+        self.force().map(|(_, tail)| tail)
+    }
+
+    /// Element `index`, forcing every cell up to it.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.iter().nth(index)
+    }
+
+    /// Iterate over the elements, forcing each cell as it is reached. Never
+    /// ends on an infinite list.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { curr: Some(self) }
+    }
+
+    /// The list of `f` applied to each element, computed as it is forced.
+    pub fn map<U: 'static, F: Fn(&T) -> U + 'static>(&self, f: F) -> LazyList<U> {
+        fn map<T: 'static, U: 'static, F: Fn(&T) -> U + 'static>(list: LazyList<T>, f: Rc<F>) -> LazyList<U> {
+            LazyList::new(move || {
+                let (head, tail) = list.force()?;
+                Some((f(head), map(tail.clone(), f)))
+            })
+        }
+        map(self.clone(), Rc::new(f))
+    }
+
+    /// The list of elements matching `pred`. Forcing a cell of it forces the
+    /// original up to the next match, so on an infinite list with no more
+    /// matches it never returns.
+    pub fn filter<F: Fn(&T) -> bool + 'static>(&self, pred: F) -> LazyList<T>
+    where
+        T: Clone,
+    {
+        fn filter<T: Clone + 'static, F: Fn(&T) -> bool + 'static>(list: LazyList<T>, pred: Rc<F>) -> LazyList<T> {
+            LazyList::new(move || {
+                let mut list = &list;
+                loop {
+                    let (head, tail) = list.force()?;
+                    if pred(head) {
+                        return Some((head.clone(), filter(tail.clone(), pred)));
+                    }
+                    list = tail;
+                }
+            })
+        }
+        filter(self.clone(), Rc::new(pred))
+    }
+
+    /// The list of the first `n` elements.
+    pub fn take(&self, n: usize) -> LazyList<T>
+    where
+        T: Clone,
+    {
+        if n == 0 {
+            return Self::empty();
+        }
+        let list = self.clone();
+        LazyList::new(move || {
+            let (head, tail) = list.force()?;
+            Some((head.clone(), tail.take(n - 1)))
+        })
+    }
+}
+
+impl<T> Clone for LazyList<T> {
+    fn clone(&self) -> Self {
+        LazyList { node: self.node.clone() }
+    }
+}
+
+impl<T> Drop for LazyList<T> {
+    // Unlink forced cells iteratively so long lists don't overflow the
+    // stack, stopping at the first cell another clone still holds
+    fn drop(&mut self) {
+        let unlink = |list: &mut LazyList<T>| Rc::get_mut(&mut list.node)?.forced.take()?.map(|(_, tail)| tail);
+        let mut next = unlink(self);
+        while let Some(mut list) = next {
+            next = unlink(&mut list);
+        }
+    }
+}
+
+impl<T: 'static> Default for LazyList<T> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+pub struct Iter<'a, T> {
+    curr: Option<&'a LazyList<T>>,
+}
+
+impl<'a, T: 'static> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (head, tail) = self.curr?.force()?;
+        self.curr = Some(tail);
+        Some(head)
+    }
+}
+
+impl<'a, T: 'static> IntoIterator for &'a LazyList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: 'static> FromIterator<T> for LazyList<T> {
+    /// Collects eagerly, as `iter` may borrow; see `from_iter_lazily`.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let items: Vec<T> = iter.into_iter().collect();
+        items.into_iter().rev().fold(Self::empty(), |tail, val| Self::cons(val, tail))
+    }
+}
+
+impl<T: Debug> Debug for LazyList<T> {
+    /// Shows only the forced elements, without forcing any more.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut list = f.debug_list();
+        let mut curr = self;
+        loop {
+            match curr.node.forced.get() {
+                Some(Some((head, tail))) => {
+                    list.entry(head);
+                    curr = tail;
+                }
+                Some(None) => return list.finish(),
+                None => return list.finish_non_exhaustive(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::LazyList;
+
+    #[test]
+    fn test_finite() {
+        let list: LazyList<_> = (1..=3).collect();
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(list.head(), Some(&1));
+        assert_eq!(list.tail().unwrap().head(), Some(&2));
+        assert_eq!(list.get(2), Some(&3));
+        assert_eq!(list.get(3), None);
+        assert!(LazyList::<i32>::empty().is_empty());
+        assert_eq!(format!("{list:?}"), "[1, 2, 3]");
+    }
+
+    #[test]
+    fn test_memoized() {
+        let calls = Rc::new(Cell::new(0));
+        let counter = calls.clone();
+        let naturals = LazyList::unfold(0, move |n| {
+            counter.set(counter.get() + 1);
+            Some((n, n + 1))
+        });
+        assert_eq!(calls.get(), 0);
+        assert!(!naturals.is_forced());
+        let shared = naturals.clone();
+        assert_eq!(naturals.get(4), Some(&4));
+        assert_eq!(calls.get(), 5);
+        // Clones share the computed cells
+        assert_eq!(shared.iter().take(5).sum::<u64>(), 10);
+        assert_eq!(calls.get(), 5);
+        assert_eq!(format!("{shared:?}"), "[0, 1, 2, 3, 4, ..]");
+    }
+
+    #[test]
+    fn test_combinators_on_infinite() {
+        let naturals = LazyList::iterate(0u64, |n| n + 1);
+        let odd_squares = naturals.map(|n| n * n).filter(|n| n % 2 == 1);
+        let first: Vec<_> = odd_squares.take(4).iter().copied().collect();
+        assert_eq!(first, vec![1, 9, 25, 49]);
+        // Building them forced nothing beyond what was asked for
+        assert_eq!(format!("{naturals:?}"), "[0, 1, 2, 3, 4, 5, 6, 7, ..]");
+        assert!(naturals.take(0).is_empty());
+
+        let fib = LazyList::unfold((0u64, 1u64), |(a, b)| Some((a, (b, a + b))));
+        assert_eq!(fib.get(50), Some(&12_586_269_025));
+    }
+
+    #[test]
+    fn test_from_iter_lazily() {
+        let pulled = Rc::new(Cell::new(0));
+        let counter = pulled.clone();
+        let list = LazyList::from_iter_lazily((0..).inspect(move |_| counter.set(counter.get() + 1)));
+        assert_eq!(pulled.get(), 0);
+        assert_eq!(list.get(2), Some(&2));
+        assert_eq!(pulled.get(), 3);
+        let short = LazyList::from_iter_lazily(0..2);
+        assert_eq!(short.iter().count(), 2);
+    }
+
+    #[test]
+    fn test_long_drop() {
+        let list = LazyList::iterate(0, |n| n + 1);
+        assert_eq!(list.get(200_000), Some(&200_000));
+        drop(list);
+    }
+}
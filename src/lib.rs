@@ -0,0 +1,91 @@
+pub mod list;
+pub mod doubly_list;
+pub mod treap;
+pub mod btree;
+pub mod bplus_tree;
+pub mod trie;
+pub mod radix_trie;
+pub mod tst;
+pub mod suffix_automaton;
+pub mod rope;
+pub mod gap_buffer;
+pub mod dsu;
+pub mod segment_tree;
+pub mod interval_tree;
+pub mod kd_tree;
+pub mod quadtree;
+pub mod rtree;
+pub mod graph;
+pub mod interval_set;
+pub mod bit_vec;
+pub mod rank_select;
+pub mod compressed_bitmap;
+pub mod bloom_filter;
+pub mod cuckoo_filter;
+pub mod count_min;
+pub mod hash_map;
+pub mod multimap;
+pub mod multiset;
+pub mod slab;
+pub mod slot_map;
+pub mod arena;
+pub mod arena_list;
+pub mod index_list;
+pub mod unrolled_list;
+pub mod pvector;
+pub mod cow_list;
+pub mod spsc_ring;
+pub mod mpmc_queue;
+pub mod work_stealing;
+pub mod concurrent_skip_list;
+pub mod sharded_map;
+pub mod sync;
+pub mod sliding_window;
+pub mod window_median;
+pub mod sorted_vec_map;
+pub mod node_pool;
+pub mod fallible;
+pub mod error;
+pub mod snapshot;
+pub mod heap_size;
+pub mod dot;
+pub mod pretty;
+pub mod order_statistic_set;
+pub mod skew_heap;
+pub mod cartesian_tree;
+pub mod cache;
+pub mod two_q_cache;
+pub mod arc_cache;
+pub mod hash_ring;
+pub mod priority_expiry_map;
+pub mod lazy_list;
+pub mod zipper;
+pub mod dlist;
+pub mod rle_list;
+pub mod chunked_seq;
+pub mod order_list;
+pub mod finger_tree;
+pub mod sequence;
+pub mod iter_ext;
+pub mod simd;
+#[cfg(feature = "bumpalo")]
+pub mod bump_list;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "std_cursor")]
+pub mod std_cursor;
+
+mod cache_padded;
+mod rng;
+mod trace;
+
+#[cfg(feature = "rkyv")]
+mod archive;
+#[cfg(test)]
+mod model;
@@ -1,35 +1,128 @@
-struct Node<T> {
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::fallible::{AllocError, try_box};
+use crate::heap_size::{HeapSize, payload};
+use crate::node_pool::NodePool;
+use crate::pretty::{PrettyPrint, arrows};
+use crate::sequence::{LendingCursor, SeqCursor, Sequence};
+use crate::trace;
+
+/// A node of a `List`, only public so a `ListPool` can name it.
+pub struct Node<T> {
     data: T,
     next: Option<Box<Node<T>>>,
 }
 
+/// A pool of spare nodes that one or more `List<T>`s can share.
+pub type ListPool<T> = NodePool<Box<Node<T>>>;
+
+/// A singly linked list.
+///
+/// Each element lives in its own heap-allocated node and stays at the same
+/// address for as long as it is in the list, however the list itself is
+/// moved or grown. `PinnedList` builds on that to hand out pinned
+/// references.
 pub struct List<T> {
     head: Option<Box<Node<T>>>,
     len: usize,
+    pool: Option<Arc<ListPool<T>>>,
 }
 
 impl<T> List<T> {
-    pub fn new() -> Self {
-        List{head: None, len: 0}
+    pub const fn new() -> Self {
+        List{head: None, len: 0, pool: None}
+    }
+
+    /// An empty list that takes its nodes from `pool` and returns them there
+    pub fn with_pool(pool: Arc<ListPool<T>>) -> Self {
+        List{head: None, len: 0, pool: Some(pool)}
+    }
+
+    /// An empty list with a pool of its own, so popped nodes are kept and
+    /// reused by later pushes. Suits queues that push and pop heavily.
+    pub fn pooled() -> Self {
+        Self::with_pool(Arc::new(ListPool::default()))
+    }
+
+    /// Free the spare nodes kept by the list's pool, if it has one. A pool
+    /// shared with other lists loses its spares for all of them.
+    pub fn shrink_pool(&self) {
+        if let Some(pool) = &self.pool {
+            pool.shrink();
+        }
+    }
+
+    /// Number of spare nodes the list's pool holds.
+    pub fn pool_spare_count(&self) -> usize {
+        self.pool.as_ref().map_or(0, |pool| pool.spare_count())
+    }
+
+    // Take the element out of an unlinked node, recycling the node if there
+    // is a pool. Not a method, so callers can hold a borrow of the links.
+    fn release(pool: Option<&ListPool<T>>, node: Box<Node<T>>) -> T {
+        match pool {
+            Some(pool) => pool.free(node).unwrap().data,
+            None => node.data,
+        }
+    }
+
+    fn new_node(&self, node: Node<T>) -> Box<Node<T>> {
+        match &self.pool {
+            Some(pool) => pool.alloc(node),
+            None => Box::new(node),
+        }
+    }
+
+    fn try_new_node(&self, node: Node<T>) -> Result<Box<Node<T>>, AllocError> {
+        match &self.pool {
+            Some(pool) => pool.try_alloc(node),
+            None => try_box(node),
+        }
     }
 
     pub fn len(&self) -> usize {
         self.len
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Panic if `len` doesn't match the number of linked nodes. Only in
+    /// debug builds, tests and Kani proofs.
+    #[cfg(any(test, debug_assertions, kani))]
+    pub fn validate(&self) {
+        assert_eq!(self.iter().count(), self.len, "list length is out of date");
+    }
+
     pub fn push_front(&mut self, val: T) {
         self.len += 1;
+        trace::event("List", "push_front", self.len);
         let old_head = std::mem::take(&mut self.head);
-        self.head = Some(Box::new(Node{data: val, next: old_head}));
+        self.head = Some(self.new_node(Node{data: val, next: old_head}));
+    }
+
+    /// `push_front`, but leaving the list unchanged and returning an error if
+    /// the node can't be allocated.
+    pub fn try_push_front(&mut self, val: T) -> Result<(), AllocError> {
+        let mut node = self.try_new_node(Node{data: val, next: None})?;
+        node.next = self.head.take();
+        self.head = Some(node);
+        self.len += 1;
+        trace::event("List", "push_front", self.len);
+        Ok(())
     }
 
     pub fn pop_front(&mut self) -> Option<T> {
-        let Some(head) = std::mem::take(&mut self.head) else {
-            return None;
-        };
+        let mut head = std::mem::take(&mut self.head)?;
         self.len -= 1;
-        self.head = head.next;
-        Some(head.data)
+        trace::event("List", "pop_front", self.len);
+        self.head = head.next.take();
+        Some(Self::release(self.pool.as_deref(), head))
     }
 
     pub fn peek_front(&self) -> Option<&T> {
@@ -41,10 +134,165 @@ impl<T> List<T> {
     }
 
     pub fn clear(&mut self) {
-        self.head = None;
+        if self.pool.is_some() {
+            while self.pop_front().is_some() {}
+        }
+        self.free_nodes();
+    }
+
+    // Drops the nodes one at a time, as dropping `head` as it is would
+    // recurse once per node and overflow the stack on long lists
+    fn free_nodes(&mut self) {
+        let mut curr = self.head.take();
+        while let Some(mut node) = curr {
+            curr = node.next.take();
+        }
         self.len = 0;
     }
 
+    /// Keep only the elements for which `f` returns true, preserving order
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        let mut curr = &mut self.head;
+        loop {
+            match curr {
+                None => break,
+                Some(node) if !f(&node.data) => {
+                    let mut node = curr.take().unwrap();
+                    *curr = node.next.take();
+                    self.len -= 1;
+                    Self::release(self.pool.as_deref(), node);
+                }
+                Some(node) => curr = &mut node.next,
+            }
+        }
+    }
+
+    /// Remove and return the first element for which `f` returns true
+    pub fn remove_first<F: FnMut(&T) -> bool>(&mut self, mut f: F) -> Option<T> {
+        let mut curr = &mut self.head;
+        loop {
+            match curr {
+                None => return None,
+                Some(node) if f(&node.data) => {
+                    let mut node = curr.take()?;
+                    *curr = node.next.take();
+                    self.len -= 1;
+                    return Some(Self::release(self.pool.as_deref(), node));
+                }
+                Some(node) => curr = &mut node.next,
+            }
+        }
+    }
+
+    /// Sort the list in ascending order, stably.
+    ///
+    /// A natural merge sort: the list is cut into the runs already in order,
+    /// with strictly descending ones reversed, and those are merged. Sorted
+    /// and reversed input take one pass, nearly sorted input close to it,
+    /// and anything else O(n log n). Nodes are relinked, never moved or
+    /// reallocated.
+    pub fn sort(&mut self)
+    where
+        T: Ord,
+    {
+        self.sort_by(T::cmp);
+    }
+
+    /// `sort`, ordering by `key`
+    pub fn sort_by_key<K: Ord, F: FnMut(&T) -> K>(&mut self, mut key: F) {
+        self.sort_by(|a, b| key(a).cmp(&key(b)));
+    }
+
+    /// `sort`, ordering by `cmp`
+    pub fn sort_by<F: FnMut(&T, &T) -> Ordering>(&mut self, mut cmp: F) {
+        self.head = sort_chain(self.head.take(), &mut cmp);
+    }
+
+    /// `sort`, spread over rayon's thread pool. The list is cut into chunks
+    /// of a few thousand elements, those are sorted in parallel, and the
+    /// sorted chunks merged pairwise, also in parallel, by relinking. Only
+    /// lists of millions gain enough to make up for the thread handoffs.
+    #[cfg(feature = "rayon")]
+    pub fn par_sort(&mut self)
+    where
+        T: Ord + Send,
+    {
+        self.par_sort_by(T::cmp);
+    }
+
+    /// `par_sort`, ordering by `cmp`
+    #[cfg(feature = "rayon")]
+    pub fn par_sort_by<F: Fn(&T, &T) -> Ordering + Sync>(&mut self, cmp: F)
+    where
+        T: Send,
+    {
+        use rayon::prelude::*;
+
+        let mut chunks = Vec::new();
+        let mut rest = self.head.take();
+        while rest.is_some() {
+            let mut tail = &mut rest;
+            for _ in 0..PAR_SORT_CHUNK {
+                match tail {
+                    Some(node) => tail = &mut node.next,
+                    None => break,
+                }
+            }
+            let after = tail.take();
+            chunks.push(std::mem::replace(&mut rest, after));
+        }
+        // `reduce_with` only ever merges neighbours, left one first, so the
+        // sort stays stable
+        self.head = chunks
+            .into_par_iter()
+            .map(|chunk| sort_chain(chunk, &mut |a, b| cmp(a, b)))
+            .reduce_with(|left, right| merge(left, right, &mut |a, b| cmp(a, b)))
+            .flatten();
+    }
+
+    /// Sort the list by an integer key, with an LSD radix sort: one pass
+    /// per byte of key that differs between elements, each dealing the
+    /// nodes into 256 buckets and linking the buckets back together. That
+    /// beats `sort` several times over on long lists whose keys span only a
+    /// few bytes, but not on keys spread over all eight.
+    ///
+    /// `key` is called on each element once per pass. Elements with equal
+    /// keys happen to keep their order, but that isn't promised.
+    pub fn sort_unstable_by_key_radix<K: RadixKey, F: FnMut(&T) -> K>(&mut self, mut key: F) {
+        let Some(first) = self.head.as_deref() else {
+            return;
+        };
+        // Bits set in here differ between some elements
+        let first_key = key(&first.data).radix_key();
+        let mut differing = 0;
+        let mut curr = first.next.as_deref();
+        while let Some(node) = curr {
+            differing |= key(&node.data).radix_key() ^ first_key;
+            curr = node.next.as_deref();
+        }
+
+        let mut buckets: Vec<Link<T>> = (0..256).map(|_| None).collect();
+        for shift in (0..64).step_by(8).filter(|shift| (differing >> shift) & 0xff != 0) {
+            // Pushing onto the buckets' fronts reverses each one, and pushing
+            // them back onto the list's front from the last bucket reverses
+            // them again, so every pass keeps the order from the last.
+            let mut rest = self.head.take();
+            while let Some(mut node) = rest {
+                rest = node.next.take();
+                let bucket = &mut buckets[((key(&node.data).radix_key() >> shift) & 0xff) as usize];
+                node.next = bucket.take();
+                *bucket = Some(node);
+            }
+            for bucket in buckets.iter_mut().rev() {
+                while let Some(mut node) = bucket.take() {
+                    *bucket = node.next.take();
+                    node.next = self.head.take();
+                    self.head = Some(node);
+                }
+            }
+        }
+    }
+
     pub fn iter(&self) -> Iter<'_, T> {
         Iter { curr: self.head.as_deref() }
     }
@@ -53,8 +301,417 @@ impl<T> List<T> {
         IterMut { curr: self.head.as_deref_mut() }
     }
 
-    pub fn into_iter(self) -> IntoIter<T> {
-        IntoIter { list: self }
+    /// Break the list into the runs of elements between those matching
+    /// `pred`, like `slice::split`. The matches are dropped, and `n` of them
+    /// make `n + 1` lists, empty ones included. The nodes are relinked
+    /// rather than copied.
+    pub fn split<F: FnMut(&T) -> bool>(self, pred: F) -> Split<T, F> {
+        Split { rest: Some(self), pred }
+    }
+
+    /// Break the list into its maximal runs of neighbours for which `eq`
+    /// holds, like `slice::chunk_by`. `eq` is passed each pair in list
+    /// order. The nodes are relinked rather than copied.
+    pub fn chunk_by<F: FnMut(&T, &T) -> bool>(self, eq: F) -> ChunkBy<T, F> {
+        ChunkBy { rest: self, eq }
+    }
+
+    pub fn cursor_front(&mut self) -> Cursor<'_, T> {
+        let index = if self.is_empty() { None } else { Some(0) };
+        let link: *mut _ = &mut self.head;
+        Cursor { list: self, link, index }
+    }
+}
+
+impl<T> Default for List<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for List<T> {
+    fn drop(&mut self) {
+        self.free_nodes();
+    }
+}
+
+type Link<T> = Option<Box<Node<T>>>;
+
+/// An integer key for `List::sort_unstable_by_key_radix`.
+pub trait RadixKey: Copy {
+    /// The key as a `u64` that orders the same way
+    fn radix_key(self) -> u64;
+}
+
+impl RadixKey for u8 { fn radix_key(self) -> u64 { self as u64 } }
+impl RadixKey for u16 { fn radix_key(self) -> u64 { self as u64 } }
+impl RadixKey for u32 { fn radix_key(self) -> u64 { self as u64 } }
+impl RadixKey for u64 { fn radix_key(self) -> u64 { self } }
+impl RadixKey for usize { fn radix_key(self) -> u64 { self as u64 } }
+// Flipping the sign bit puts the negatives first
+impl RadixKey for i8 { fn radix_key(self) -> u64 { (self as u8 ^ (1 << 7)) as u64 } }
+impl RadixKey for i16 { fn radix_key(self) -> u64 { (self as u16 ^ (1 << 15)) as u64 } }
+impl RadixKey for i32 { fn radix_key(self) -> u64 { (self as u32 ^ (1 << 31)) as u64 } }
+impl RadixKey for i64 { fn radix_key(self) -> u64 { self as u64 ^ (1 << 63) } }
+impl RadixKey for isize { fn radix_key(self) -> u64 { (self as i64).radix_key() } }
+
+// Length of the chunks `List::par_sort_by` sorts on one thread
+#[cfg(feature = "rayon")]
+const PAR_SORT_CHUNK: usize = 4096;
+
+// Sort a chain with a natural merge sort, as `List::sort_by` describes
+fn sort_chain<T, F: FnMut(&T, &T) -> Ordering>(mut rest: Link<T>, cmp: &mut F) -> Link<T> {
+    // Pending runs, each with its length. Merging whenever a run is no
+    // longer than the one after it keeps lengths halving down the stack,
+    // so merges stay balanced and the stack O(log n) deep.
+    let mut runs: Vec<(Link<T>, usize)> = Vec::new();
+    while let Some(mut first) = rest {
+        rest = first.next.take();
+        let mut len = 1;
+        let run = if rest.as_ref().is_some_and(|next| cmp(&next.data, &first.data) == Ordering::Less) {
+            // Strictly descending, so reversing it can't reorder equals
+            let mut run = first;
+            while rest.as_ref().is_some_and(|next| cmp(&next.data, &run.data) == Ordering::Less) {
+                let mut node = rest.take().unwrap();
+                rest = node.next.take();
+                node.next = Some(run);
+                run = node;
+                len += 1;
+            }
+            Some(run)
+        } else {
+            let mut run = Some(first);
+            let mut last = run.as_mut().unwrap();
+            while rest.as_ref().is_some_and(|next| cmp(&next.data, &last.data) != Ordering::Less) {
+                let mut node = rest.take().unwrap();
+                rest = node.next.take();
+                last = last.next.insert(node);
+                len += 1;
+            }
+            run
+        };
+        runs.push((run, len));
+        while let [.., (_, left), (_, right)] = runs[..] && left <= right {
+            let (right, right_len) = runs.pop().unwrap();
+            let (left, left_len) = runs.pop().unwrap();
+            runs.push((merge(left, right, cmp), left_len + right_len));
+        }
+    }
+    runs.into_iter().rev().map(|(run, _)| run).reduce(|right, left| merge(left, right, cmp)).flatten()
+}
+
+// Merge two sorted chains into one, taking from `left` on ties
+fn merge<T, F: FnMut(&T, &T) -> Ordering>(mut left: Link<T>, mut right: Link<T>, cmp: &mut F) -> Link<T> {
+    let mut head = None;
+    let mut tail = &mut head;
+    while let (Some(l), Some(r)) = (&left, &right) {
+        let src = if cmp(&r.data, &l.data) == Ordering::Less { &mut right } else { &mut left };
+        let mut node = src.take().unwrap();
+        *src = node.next.take();
+        tail = &mut tail.insert(node).next;
+    }
+    *tail = left.or(right);
+    head
+}
+
+/// The back of a singly linked list is a walk away, so `push_back` and
+/// `pop_back` take O(n) here.
+impl<T> Sequence<T> for List<T> {
+    type Iter<'a>
+        = Iter<'a, T>
+    where
+        T: 'a;
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn push_front(&mut self, val: T) {
+        self.push_front(val);
+    }
+
+    fn push_back(&mut self, val: T) {
+        let node = self.new_node(Node{data: val, next: None});
+        let mut curr = &mut self.head;
+        while let Some(next) = curr {
+            curr = &mut next.next;
+        }
+        *curr = Some(node);
+        self.len += 1;
+    }
+
+    fn pop_front(&mut self) -> Option<T> {
+        self.pop_front()
+    }
+
+    fn pop_back(&mut self) -> Option<T> {
+        let mut curr = &mut self.head;
+        while curr.as_ref()?.next.is_some() {
+            curr = &mut curr.as_mut().unwrap().next;
+        }
+        let node = curr.take()?;
+        self.len -= 1;
+        Some(Self::release(self.pool.as_deref(), node))
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
+    }
+
+    #[cfg(any(test, debug_assertions))]
+    fn validate(&self) {
+        self.validate();
+    }
+}
+
+/// A cursor over a `List`.
+///
+/// Like the crate's other cursors it can sit on a "ghost" position between
+/// the back and the front. Stepping forward, taking and inserting are O(1),
+/// but a singly linked list can't step back, so `prev` walks from the
+/// front, as does inserting at the back from the ghost.
+pub struct Cursor<'a, T> {
+    list: &'a mut List<T>,
+    // The link holding the current node: the head, or the previous node's
+    // `next`. Nodes are boxed, so it stays put while the list changes
+    // around it.
+    link: *mut Option<Box<Node<T>>>,
+    index: Option<usize>,
+}
+
+impl<T> Cursor<'_, T> {
+    /// Index of the current element, or `None` on the ghost position
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    // Walk from the front to the element at `index`, or the ghost position
+    fn seek(&mut self, index: Option<usize>) {
+        let mut link = &mut self.list.head;
+        for _ in 0..index.unwrap_or(0) {
+            link = &mut link.as_mut().unwrap().next;
+        }
+        self.link = link;
+        self.index = index;
+    }
+
+    fn link(&mut self) -> &mut Option<Box<Node<T>>> {
+        // This is safe because `link` points into the list, which the cursor
+        // borrows mutably, and is kept pointing at a live link
+        unsafe { &mut *self.link }
+    }
+
+    /// Take a mutable reference to the current element
+    pub fn peek_mut(&mut self) -> Option<&mut T> {
+        self.index?;
+        self.link().as_mut().map(|node| &mut node.data)
+    }
+
+    /// Move one position forward (towards the back) and
+    /// return a reference to the new position
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<&mut T> {
+        match self.index {
+            Some(i) => {
+                let next: *mut _ = &mut self.link().as_mut().unwrap().next;
+                self.link = next;
+                self.index = self.link().is_some().then_some(i + 1);
+            }
+            None if !self.list.is_empty() => self.seek(Some(0)),
+            None => {}
+        }
+        self.peek_mut()
+    }
+
+    /// Move one position backward (towards the front) and
+    /// return a reference to the new position. Takes O(n).
+    pub fn prev(&mut self) -> Option<&mut T> {
+        let index = match self.index {
+            Some(i) => i.checked_sub(1),
+            None => self.list.len.checked_sub(1),
+        };
+        self.seek(index);
+        self.peek_mut()
+    }
+
+    /// Remove and return the current element, moving the cursor to the next
+    /// element, or to the previous one if it was the back.
+    pub fn take(&mut self) -> Option<T> {
+        let i = self.index?;
+        let link = self.link();
+        let mut node = link.take().unwrap();
+        *link = node.next.take();
+        let at_back = link.is_none();
+        self.list.len -= 1;
+        if at_back {
+            self.seek(i.checked_sub(1));
+        }
+        Some(List::release(self.list.pool.as_deref(), node))
+    }
+
+    /// Insert an element after the current one, or at the front if the
+    /// cursor is on the ghost position. The cursor does not move.
+    pub fn insert_after(&mut self, element: T) {
+        if self.index.is_none() {
+            self.list.push_front(element);
+            return;
+        }
+        let mut new_node = self.list.new_node(Node{data: element, next: None});
+        let node = self.link().as_mut().unwrap();
+        new_node.next = node.next.take();
+        node.next = Some(new_node);
+        self.list.len += 1;
+    }
+
+    /// Insert an element before the current one, or at the back if the
+    /// cursor is on the ghost position. The cursor does not move.
+    pub fn insert_before(&mut self, element: T) {
+        let Some(i) = self.index else {
+            Sequence::push_back(&mut *self.list, element);
+            return;
+        };
+        let mut new_node = self.list.new_node(Node{data: element, next: None});
+        let link = self.link();
+        new_node.next = link.take();
+        let new_node = link.insert(new_node);
+        self.link = &mut new_node.next;
+        self.index = Some(i + 1);
+        self.list.len += 1;
+    }
+}
+
+impl<T> SeqCursor<T> for Cursor<'_, T> {
+    fn peek_mut(&mut self) -> Option<&mut T> {
+        self.peek_mut()
+    }
+
+    fn next(&mut self) -> Option<&mut T> {
+        self.next()
+    }
+
+    fn prev(&mut self) -> Option<&mut T> {
+        self.prev()
+    }
+
+    fn take(&mut self) -> Option<T> {
+        self.take()
+    }
+
+    fn insert_after(&mut self, element: T) {
+        self.insert_after(element);
+    }
+
+    fn insert_before(&mut self, element: T) {
+        self.insert_before(element);
+    }
+}
+
+impl<T> LendingCursor for Cursor<'_, T> {
+    type Elem = T;
+
+    type Item<'a>
+        = &'a mut T
+    where
+        Self: 'a;
+
+    fn peek(&mut self) -> Option<Self::Item<'_>> {
+        self.peek_mut()
+    }
+
+    // There is always somewhere to move to, if only the ghost
+    fn move_next(&mut self) -> bool {
+        self.next();
+        true
+    }
+
+    fn move_prev(&mut self) -> bool {
+        self.prev();
+        true
+    }
+}
+
+/// A `List` that never moves an element once it has been pushed, so it can
+/// hand out `Pin<&mut T>`s to them. Elements that point into themselves, or
+/// whose address has been given to foreign code, stay valid until they are
+/// dropped, which happens in place when they are removed or the list is
+/// dropped.
+///
+/// There is no `pop` that returns the element, since that would move it.
+/// As with `List`, the back is O(n) to reach.
+pub struct PinnedList<T> {
+    // Never has a pool, which would move elements out of their nodes
+    list: List<T>,
+}
+
+// Safety, for every `Pin::new_unchecked` below: nothing moves an element out
+// of its node while it is in the list, and removing it drops it in place
+impl<T> PinnedList<T> {
+    pub const fn new() -> Self {
+        PinnedList { list: List::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.len == 0
+    }
+
+    pub fn push_front_pinned(&mut self, val: T) -> Pin<&mut T> {
+        self.list.push_front(val);
+        self.front_mut().unwrap()
+    }
+
+    pub fn push_back_pinned(&mut self, val: T) -> Pin<&mut T> {
+        let mut link = &mut self.list.head;
+        while let Some(node) = link {
+            link = &mut node.next;
+        }
+        let node = link.insert(Box::new(Node{data: val, next: None}));
+        self.list.len += 1;
+        unsafe { Pin::new_unchecked(&mut node.data) }
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.list.peek_front()
+    }
+
+    pub fn front_mut(&mut self) -> Option<Pin<&mut T>> {
+        self.list.peek_front_mut().map(|val| unsafe { Pin::new_unchecked(val) })
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.iter().last()
+    }
+
+    pub fn back_mut(&mut self) -> Option<Pin<&mut T>> {
+        self.iter_pinned().last()
+    }
+
+    /// Drop the front element where it is. Returns whether there was one.
+    pub fn remove_front(&mut self) -> bool {
+        let Some(mut head) = self.list.head.take() else {
+            return false;
+        };
+        self.list.head = head.next.take();
+        self.list.len -= 1;
+        true
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.list.iter()
+    }
+
+    /// Pinned references to every element, front to back.
+    pub fn iter_pinned(&mut self) -> impl Iterator<Item = Pin<&mut T>> {
+        self.list.iter_mut().map(|val| unsafe { Pin::new_unchecked(val) })
+    }
+}
+
+impl<T> Default for PinnedList<T> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -66,9 +723,7 @@ impl<'a, T> Iterator for Iter<'a, T> {
     type Item = &'a T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let Some(node) = self.curr else {
-            return None;
-        };
+        let node = self.curr?;
         self.curr = node.next.as_deref();
         Some(&node.data)
     }
@@ -82,9 +737,7 @@ impl<'a, T> Iterator for IterMut<'a, T> {
     type Item = &'a mut T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let Some(node) = std::mem::take(&mut self.curr) else {
-            return None;
-        };
+        let node = std::mem::take(&mut self.curr)?;
         self.curr = node.next.as_deref_mut();
         Some(&mut node.data)
     }
@@ -94,6 +747,60 @@ pub struct IntoIter<T> {
     list: List<T>
 }
 
+pub struct Split<T, F> {
+    // What is left after the last separator, or `None` once it is yielded
+    rest: Option<List<T>>,
+    pred: F,
+}
+
+pub struct ChunkBy<T, F> {
+    rest: List<T>,
+    eq: F,
+}
+
+impl<T, F: FnMut(&T, &T) -> bool> Iterator for ChunkBy<T, F> {
+    type Item = List<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &mut self.rest;
+        let mut node = rest.head.take()?;
+        rest.head = node.next.take();
+        rest.len -= 1;
+        let mut piece = List { head: None, len: 1, pool: rest.pool.clone() };
+        let mut tail = piece.head.insert(node);
+        while rest.head.as_ref().is_some_and(|next| (self.eq)(&tail.data, &next.data)) {
+            let mut next = rest.head.take().unwrap();
+            rest.head = next.next.take();
+            rest.len -= 1;
+            piece.len += 1;
+            tail = tail.next.insert(next);
+        }
+        Some(piece)
+    }
+}
+
+impl<T, F: FnMut(&T) -> bool> Iterator for Split<T, F> {
+    type Item = List<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.rest.as_mut()?;
+        let mut piece = List { head: None, len: 0, pool: rest.pool.clone() };
+        let mut tail = &mut piece.head;
+        while let Some(mut node) = rest.head.take() {
+            rest.head = node.next.take();
+            rest.len -= 1;
+            if (self.pred)(&node.data) {
+                List::release(rest.pool.as_deref(), node);
+                return Some(piece);
+            }
+            piece.len += 1;
+            tail = &mut tail.insert(node).next;
+        }
+        self.rest = None;
+        Some(piece)
+    }
+}
+
 impl<T> Iterator for IntoIter<T> {
     type Item = T;
 
@@ -111,8 +818,72 @@ impl<T> IntoIterator for List<T> {
     }
 }
 
+impl<T> From<Vec<T>> for List<T> {
+    fn from(vec: Vec<T>) -> Self {
+        let mut list = List::new();
+        for val in vec.into_iter().rev() {
+            list.push_front(val);
+        }
+        list
+    }
+}
+
+impl<T> From<VecDeque<T>> for List<T> {
+    fn from(mut deque: VecDeque<T>) -> Self {
+        let mut list = List::new();
+        while let Some(val) = deque.pop_back() {
+            list.push_front(val);
+        }
+        list
+    }
+}
+
+impl<T> From<List<T>> for Vec<T> {
+    fn from(list: List<T>) -> Self {
+        let mut vec = Vec::with_capacity(list.len());
+        vec.extend(list);
+        vec
+    }
+}
+
+impl<T> From<List<T>> for VecDeque<T> {
+    fn from(list: List<T>) -> Self {
+        let mut deque = VecDeque::with_capacity(list.len());
+        deque.extend(list);
+        deque
+    }
+}
+
+impl<T: HeapSize> HeapSize for List<T> {
+    fn heap_size(&self) -> usize {
+        self.len * size_of::<Node<T>>() + payload(self.iter())
+    }
+}
+
+impl<T: HeapSize> HeapSize for PinnedList<T> {
+    fn heap_size(&self) -> usize {
+        self.list.heap_size()
+    }
+}
+
+impl<T: Debug> PrettyPrint for List<T> {
+    fn pretty_print(&self) -> String {
+        arrows(self.iter(), " -> ")
+    }
+}
+
+#[cfg(test)]
 mod tests {
-    use super::List;
+    use std::cell::Cell;
+    use std::marker::PhantomPinned;
+    use std::rc::Rc;
+    use std::sync::{Arc, Mutex};
+
+    use crate::heap_size::HeapSize;
+    use crate::pretty::PrettyPrint;
+    use crate::rng::XorShift64;
+
+    use super::{List, ListPool, Node, PinnedList};
 
     #[test]
     fn test_new() {
@@ -283,4 +1054,321 @@ mod tests {
         }
         assert_eq!(vec, vec![3, 2, 1]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_retain() {
+        let mut list = List::new();
+        for i in 1..=6 {
+            list.push_front(i);
+        }
+        list.retain(|x| x % 2 == 0);
+        list.validate();
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), vec![6, 4, 2]);
+
+        list.retain(|_| false);
+        assert_eq!(list.len(), 0);
+        assert!(list.peek_front().is_none());
+    }
+
+    #[test]
+    fn test_remove_first() {
+        let mut list = List::new();
+        for i in [1, 2, 3, 2, 1] {
+            list.push_front(i);
+        }
+        assert_eq!(list.remove_first(|&x| x == 2), Some(2));
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), vec![1, 3, 2, 1]);
+        assert_eq!(list.remove_first(|&x| x == 1), Some(1));
+        assert_eq!(list.remove_first(|&x| x == 5), None);
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_shared_pool() {
+        let pool = Arc::new(ListPool::default());
+        let mut a = List::with_pool(pool.clone());
+        let mut b = List::with_pool(pool.clone());
+        for i in 0..10 {
+            a.push_front(i);
+        }
+        a.retain(|x| x % 2 == 0);
+        assert_eq!(pool.spare_count(), 5);
+        assert_eq!(a.remove_first(|&x| x == 4), Some(4));
+        a.clear();
+        assert_eq!(pool.spare_count(), 10);
+
+        // Pushes onto the other list come out of the pool
+        for i in 0..4 {
+            b.push_front(i);
+        }
+        assert_eq!(pool.spare_count(), 6);
+        assert_eq!(b.pop_front(), Some(3));
+        assert_eq!(pool.spare_count(), 7);
+        assert_eq!(b.iter().copied().collect::<Vec<_>>(), vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn test_send_sync() {
+        fn is_send_sync<T: Send + Sync>() {}
+        is_send_sync::<List<u32>>();
+
+        // Pooled lists can move to another thread and keep sharing the pool
+        let pool = Arc::new(ListPool::default());
+        let mut list = List::with_pool(pool.clone());
+        list.push_front(1);
+        let handle = std::thread::spawn(move || {
+            list.pop_front();
+            list
+        });
+        let mut list = handle.join().unwrap();
+        assert_eq!(pool.spare_count(), 1);
+        list.push_front(2);
+        assert_eq!(pool.spare_count(), 0);
+    }
+
+    #[test]
+    fn test_try_push_front() {
+        let pool = Arc::new(ListPool::default());
+        let mut list = List::with_pool(pool.clone());
+        list.push_front(1);
+        list.pop_front();
+        assert_eq!(list.try_push_front(2), Ok(()));
+        assert_eq!(pool.spare_count(), 0);
+        assert_eq!(list.try_push_front(3), Ok(()));
+        assert_eq!(list.len(), 2);
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), vec![3, 2]);
+    }
+
+    #[test]
+    fn test_pooled() {
+        let mut list = List::pooled();
+        for i in 0..10 {
+            list.push_front(i);
+        }
+        while list.pop_front().is_some() {}
+        assert_eq!(list.pool_spare_count(), 10);
+        list.push_front(0);
+        assert_eq!(list.pool_spare_count(), 9);
+        list.shrink_pool();
+        assert_eq!(list.pool_spare_count(), 0);
+        assert_eq!(list.peek_front(), Some(&0));
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let mut list = List::new();
+        assert_eq!(list.heap_size(), 0);
+        list.push_front(String::with_capacity(10));
+        list.push_front(String::with_capacity(20));
+        assert_eq!(list.heap_size(), 2 * size_of::<Node<String>>() + 30);
+    }
+
+    #[test]
+    fn test_split() {
+        let list = List::from(vec![0, 1, 2, 0, 0, 3]);
+        let pieces: Vec<Vec<i32>> = list.split(|&x| x == 0).map(Vec::from).collect();
+        assert_eq!(pieces, [vec![], vec![1, 2], vec![], vec![3]]);
+        for piece in List::from(vec![1, 2, 0]).split(|&x| x == 0) {
+            piece.validate();
+        }
+        assert_eq!(List::from(vec![1, 0]).split(|&x| x == 0).last().map(|l| l.len()), Some(0));
+        assert_eq!(List::<i32>::new().split(|_| true).count(), 1);
+
+        // Separators go back to the pool
+        let mut list = List::pooled();
+        (0..6).for_each(|i| list.push_front(i % 3));
+        assert_eq!(list.split(|&x| x == 0).map(|l| l.pool_spare_count()).last(), Some(2));
+    }
+
+    #[test]
+    fn test_drop_long() {
+        let list = List::from(vec![0u8; 3_000_000]);
+        drop(list);
+        let mut list = List::from(vec![0u8; 3_000_000]);
+        list.clear();
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn test_sort() {
+        let mut rng = XorShift64::with_seed(7);
+        let random: Vec<u64> = (0..1000).map(|_| rng.next_u64() % 100).collect();
+        let mut nearly: Vec<u64> = (0..1000).collect();
+        nearly.swap(10, 500);
+        for input in [random, nearly, (0..100).rev().collect(), vec![3, 3, 1, 1, 2], vec![], vec![1]] {
+            let mut list = List::from(input.clone());
+            list.sort();
+            list.validate();
+            let mut expected = input;
+            expected.sort();
+            assert_eq!(Vec::from(list), expected);
+        }
+
+        // Equal keys keep their order, in descending runs too
+        let mut list = List::from(vec![(2, 'a'), (1, 'b'), (2, 'c'), (0, 'd'), (2, 'e'), (1, 'f')]);
+        list.sort_by_key(|&(key, _)| key);
+        assert_eq!(Vec::from(list), [(0, 'd'), (1, 'b'), (1, 'f'), (2, 'a'), (2, 'c'), (2, 'e')]);
+        let mut list = List::from(vec![5, 4, 4, 3]);
+        list.sort_by(|a, b| b.cmp(a));
+        assert_eq!(Vec::from(list), [5, 4, 4, 3]);
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn test_par_sort() {
+        // Enough for a few dozen chunks, with many equal keys
+        let mut rng = XorShift64::with_seed(5);
+        let input: Vec<(u64, usize)> = (0..100_000).map(|i| (rng.next_u64() % 1000, i)).collect();
+        let mut list = List::from(input.clone());
+        list.par_sort_by(|a, b| a.0.cmp(&b.0));
+        list.validate();
+        let mut expected = input;
+        expected.sort_by_key(|&(key, _)| key);
+        assert_eq!(Vec::from(list), expected);
+
+        let mut list = List::from(vec![2, 3, 1]);
+        list.par_sort();
+        assert_eq!(Vec::from(list), [1, 2, 3]);
+        List::<i32>::new().par_sort();
+    }
+
+    #[test]
+    fn test_sort_radix() {
+        let mut rng = XorShift64::with_seed(3);
+        let input: Vec<u64> = (0..1000).map(|_| rng.next_u64() >> (rng.next_u64() % 64)).collect();
+        let mut list = List::from(input.clone());
+        list.sort_unstable_by_key_radix(|&x| x);
+        list.validate();
+        let mut expected = input;
+        expected.sort();
+        assert_eq!(Vec::from(list), expected);
+
+        let mut list = List::from(vec![3i32, -1, i32::MIN, 0, i32::MAX, -7]);
+        list.sort_unstable_by_key_radix(|&x| x);
+        assert_eq!(Vec::from(list), [i32::MIN, -7, -1, 0, 3, i32::MAX]);
+        let mut list = List::from(vec![("b", 2u8), ("a", 1), ("c", 2)]);
+        list.sort_unstable_by_key_radix(|&(_, key)| key);
+        assert_eq!(list.iter().map(|&(s, _)| s).collect::<Vec<_>>(), ["a", "b", "c"]);
+        let mut list = List::<i8>::new();
+        list.sort_unstable_by_key_radix(|&x| x);
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn test_chunk_by() {
+        let list = List::from(vec![1, 1, 2, 3, 3, 3, 1]);
+        let runs: Vec<List<i32>> = list.chunk_by(|a, b| a == b).collect();
+        runs.iter().for_each(List::validate);
+        let runs: Vec<Vec<i32>> = runs.into_iter().map(Vec::from).collect();
+        assert_eq!(runs, [vec![1, 1], vec![2], vec![3, 3, 3], vec![1]]);
+        // Ascending runs
+        let runs: Vec<usize> = List::from(vec![1, 2, 5, 0, 4, 3]).chunk_by(|a, b| a < b).map(|l| l.len()).collect();
+        assert_eq!(runs, [3, 2, 1]);
+        assert_eq!(List::<i32>::new().chunk_by(|_, _| true).count(), 0);
+    }
+
+    #[test]
+    fn test_pretty_print() {
+        let mut list = List::new();
+        assert_eq!(list.pretty_print(), "(empty)");
+        for i in (1..=3).rev() {
+            list.push_front(i);
+        }
+        assert_eq!(list.pretty_print(), "1 -> 2 -> 3");
+    }
+
+    #[test]
+    fn test_const_new() {
+        static STACK: Mutex<List<u32>> = Mutex::new(List::new());
+        std::thread::spawn(|| STACK.lock().unwrap().push_front(1)).join().unwrap();
+        assert_eq!(STACK.lock().unwrap().pop_front(), Some(1));
+    }
+
+    #[test]
+    fn test_pinned() {
+        // Remembers where it was pinned
+        struct Anchored {
+            addr: Cell<usize>,
+            _pin: PhantomPinned,
+        }
+        let anchored = || Anchored { addr: Cell::new(0), _pin: PhantomPinned };
+        let here = |val: &Anchored| val as *const Anchored as usize;
+
+        let mut list = PinnedList::new();
+        for i in 0..10 {
+            let val = if i % 2 == 0 { list.push_back_pinned(anchored()) } else { list.push_front_pinned(anchored()) };
+            val.addr.set(here(&val));
+        }
+        assert!(list.remove_front());
+        list.push_back_pinned(anchored()).addr.set(usize::MAX);
+        assert_eq!(list.len(), 10);
+        assert_eq!(list.back().map(|val| val.addr.get()), Some(usize::MAX));
+        assert!(list.iter().take(9).all(|val| val.addr.get() == here(val)));
+
+        let val = Rc::new(());
+        let mut list = PinnedList::new();
+        list.push_back_pinned(val.clone());
+        list.push_front_pinned(val.clone());
+        assert!(list.remove_front());
+        assert_eq!(Rc::strong_count(&val), 2);
+        assert!(list.remove_front());
+        assert!(!list.remove_front());
+        assert!(list.back_mut().is_none());
+        list.push_back_pinned(val.clone());
+        drop(list);
+        assert_eq!(Rc::strong_count(&val), 1);
+    }
+}
+
+// Proofs for the Kani model checker, run with `cargo kani`. Each one checks
+// every value and every choice of operation up to the given number of steps,
+// rather than the handful of cases the tests try.
+#[cfg(kani)]
+mod proofs {
+    use super::List;
+
+    #[kani::proof]
+    #[kani::unwind(5)]
+    fn push_pop_round_trip() {
+        let mut list = List::new();
+        let vals: [u32; 3] = kani::any();
+        let n: usize = kani::any();
+        kani::assume(n <= vals.len());
+        for &val in &vals[..n] {
+            list.push_front(val);
+        }
+        assert_eq!(list.len(), n);
+        for &val in vals[..n].iter().rev() {
+            assert_eq!(list.pop_front(), Some(val));
+        }
+        assert!(list.is_empty());
+        assert_eq!(list.pop_front(), None);
+    }
+
+    #[kani::proof]
+    #[kani::unwind(7)]
+    fn cursor_edits_keep_len() {
+        let mut list = List::new();
+        list.push_front(1u8);
+        list.push_front(0);
+        let mut cursor = list.cursor_front();
+        for _ in 0..3 {
+            match kani::any::<u8>() % 5 {
+                0 => {
+                    cursor.next();
+                }
+                1 => {
+                    cursor.prev();
+                }
+                2 => {
+                    cursor.take();
+                }
+                3 => cursor.insert_after(kani::any()),
+                _ => cursor.insert_before(kani::any()),
+            }
+        }
+        list.validate();
+    }
+}
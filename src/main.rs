@@ -1,5 +1,2 @@
-pub mod list;
-pub mod doubly_list;
-
 fn main() {
 }
@@ -0,0 +1,268 @@
+use std::collections::{self, VecDeque};
+
+use crate::bplus_tree::BPlusTree;
+use crate::btree::BTreeMap;
+use crate::gap_buffer::GapBuffer;
+use crate::hash_map::HashMap;
+use crate::rng::XorShift64;
+use crate::sequence::Sequence;
+use crate::sorted_vec_map::SortedVecMap;
+use crate::treap::Treap;
+
+// Differential tests: each check replays one random sequence of operations
+// on a crate container and on the std collection it should behave like, and
+// fails at the first operation whose result differs. The failure message
+// names the seed, so `with_seed` can replay the same sequence.
+
+// Operations per check
+const OPS: usize = 2000;
+
+// Keys are drawn from a small range so removes and lookups often hit
+const KEYS: u64 = 64;
+
+fn seeded() -> (u64, XorShift64) {
+    let seed = XorShift64::new().next_u64();
+    (seed, XorShift64::with_seed(seed))
+}
+
+/// Replay pushes and pops at both ends against a `VecDeque`.
+pub(crate) fn check_sequence<S: Sequence<u64> + Default>() {
+    let (seed, mut rng) = seeded();
+    let mut seq = S::default();
+    let mut model = VecDeque::new();
+    assert!(seq.is_empty());
+    for op in 0..OPS {
+        let val = rng.next_u64();
+        match val % 5 {
+            0 | 1 => {
+                seq.push_front(val);
+                model.push_front(val);
+            }
+            2 => {
+                seq.push_back(val);
+                model.push_back(val);
+            }
+            3 => assert_eq!(seq.pop_front(), model.pop_front(), "seed {seed:#x}, op {op}"),
+            _ => assert_eq!(seq.pop_back(), model.pop_back(), "seed {seed:#x}, op {op}"),
+        }
+        assert_eq!(seq.len(), model.len(), "seed {seed:#x}, op {op}");
+        seq.validate();
+    }
+    assert!(seq.iter().eq(model.iter()), "seed {seed:#x}");
+}
+
+/// The map operations the checks drive, so one check covers every map in
+/// the crate.
+pub(crate) trait ModelMap: Default {
+    fn insert(&mut self, key: u64, value: u64) -> Option<u64>;
+    fn remove(&mut self, key: u64) -> Option<u64>;
+    fn get(&self, key: u64) -> Option<u64>;
+    fn len(&self) -> usize;
+    /// Every entry, in key order.
+    fn entries(&self) -> Vec<(u64, u64)>;
+    fn validate(&self);
+}
+
+/// Replay inserts, removes and lookups against std's `BTreeMap`.
+pub(crate) fn check_map<M: ModelMap>() {
+    let (seed, mut rng) = seeded();
+    let mut map = M::default();
+    let mut model = collections::BTreeMap::new();
+    for op in 0..OPS {
+        let val = rng.next_u64();
+        let key = (val >> 8) % KEYS;
+        match val % 4 {
+            0 | 1 => assert_eq!(map.insert(key, val), model.insert(key, val), "seed {seed:#x}, op {op}"),
+            2 => assert_eq!(map.remove(key), model.remove(&key), "seed {seed:#x}, op {op}"),
+            _ => assert_eq!(map.get(key), model.get(&key).copied(), "seed {seed:#x}, op {op}"),
+        }
+        assert_eq!(map.len(), model.len(), "seed {seed:#x}, op {op}");
+        map.validate();
+    }
+    assert_eq!(map.entries(), model.into_iter().collect::<Vec<_>>(), "seed {seed:#x}");
+}
+
+/// Replay inserts, removes and lookups by index against a `Vec`.
+pub(crate) fn check_gap_buffer() {
+    let (seed, mut rng) = seeded();
+    let mut buffer = GapBuffer::new();
+    let mut model = Vec::new();
+    for op in 0..OPS {
+        let val = rng.next_u64();
+        // One past the end too, to check out-of-range indexes
+        let index = (val >> 8) as usize % (model.len() + 2);
+        match val % 4 {
+            0 | 1 => {
+                let index = index.min(model.len());
+                buffer.insert(index, val);
+                model.insert(index, val);
+            }
+            2 => {
+                let expected = (index < model.len()).then(|| model.remove(index));
+                assert_eq!(buffer.remove(index), expected, "seed {seed:#x}, op {op}");
+            }
+            _ => assert_eq!(buffer.get(index), model.get(index), "seed {seed:#x}, op {op}"),
+        }
+        assert_eq!(buffer.len(), model.len(), "seed {seed:#x}, op {op}");
+        buffer.validate();
+    }
+    assert!(buffer.iter().eq(model.iter()), "seed {seed:#x}");
+}
+
+impl ModelMap for HashMap<u64, u64> {
+    fn insert(&mut self, key: u64, value: u64) -> Option<u64> {
+        self.insert(key, value)
+    }
+
+    fn remove(&mut self, key: u64) -> Option<u64> {
+        self.remove(&key)
+    }
+
+    fn get(&self, key: u64) -> Option<u64> {
+        self.get(&key).copied()
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn entries(&self) -> Vec<(u64, u64)> {
+        let mut entries: Vec<_> = self.iter().map(|(&key, &value)| (key, value)).collect();
+        entries.sort();
+        entries
+    }
+
+    fn validate(&self) {
+        self.validate();
+    }
+}
+
+impl ModelMap for Treap<u64, u64> {
+    fn insert(&mut self, key: u64, value: u64) -> Option<u64> {
+        self.insert(key, value)
+    }
+
+    fn remove(&mut self, key: u64) -> Option<u64> {
+        self.remove(&key)
+    }
+
+    fn get(&self, key: u64) -> Option<u64> {
+        self.get(&key).copied()
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn entries(&self) -> Vec<(u64, u64)> {
+        self.iter().map(|(&key, &value)| (key, value)).collect()
+    }
+
+    fn validate(&self) {
+        self.validate();
+    }
+}
+
+impl<const B: usize> ModelMap for BTreeMap<u64, u64, B> {
+    fn insert(&mut self, key: u64, value: u64) -> Option<u64> {
+        self.insert(key, value)
+    }
+
+    fn remove(&mut self, key: u64) -> Option<u64> {
+        self.remove(&key)
+    }
+
+    fn get(&self, key: u64) -> Option<u64> {
+        self.get(&key).copied()
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn entries(&self) -> Vec<(u64, u64)> {
+        self.iter().map(|(&key, &value)| (key, value)).collect()
+    }
+
+    fn validate(&self) {
+        self.validate();
+    }
+}
+
+impl<const B: usize> ModelMap for BPlusTree<u64, u64, B> {
+    fn insert(&mut self, key: u64, value: u64) -> Option<u64> {
+        self.insert(key, value)
+    }
+
+    fn remove(&mut self, key: u64) -> Option<u64> {
+        self.remove(&key)
+    }
+
+    fn get(&self, key: u64) -> Option<u64> {
+        self.get(&key).copied()
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn entries(&self) -> Vec<(u64, u64)> {
+        self.iter().map(|(&key, &value)| (key, value)).collect()
+    }
+
+    fn validate(&self) {
+        self.validate();
+    }
+}
+
+impl ModelMap for SortedVecMap<u64, u64> {
+    fn insert(&mut self, key: u64, value: u64) -> Option<u64> {
+        self.insert(key, value)
+    }
+
+    fn remove(&mut self, key: u64) -> Option<u64> {
+        self.remove(&key)
+    }
+
+    fn get(&self, key: u64) -> Option<u64> {
+        self.get(&key).copied()
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn entries(&self) -> Vec<(u64, u64)> {
+        self.iter().map(|(&key, &value)| (key, value)).collect()
+    }
+
+    fn validate(&self) {
+        self.validate();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bplus_tree::BPlusTree;
+    use crate::btree::BTreeMap;
+    use crate::hash_map::HashMap;
+    use crate::sorted_vec_map::SortedVecMap;
+    use crate::treap::Treap;
+
+    use super::{check_gap_buffer, check_map};
+
+    #[test]
+    fn test_every_map() {
+        check_map::<HashMap<u64, u64>>();
+        check_map::<Treap<u64, u64>>();
+        check_map::<BTreeMap<u64, u64, 3>>();
+        check_map::<BTreeMap<u64, u64, 8>>();
+        check_map::<BPlusTree<u64, u64, 4>>();
+        check_map::<SortedVecMap<u64, u64>>();
+    }
+
+    #[test]
+    fn test_gap_buffer() {
+        check_gap_buffer();
+    }
+}
@@ -0,0 +1,355 @@
+use std::mem::MaybeUninit;
+
+use crate::cache_padded::CachePadded;
+use crate::heap_size::HeapSize;
+use crate::sync::shim::{AtomicUsize, Condvar, Mutex, Ordering, UnsafeCell, fence};
+
+struct Slot<T> {
+    // Equal to the push position that may fill this slot next, or that
+    // position plus one once it is full and ready to pop
+    seq: AtomicUsize,
+    val: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// A bounded multi-producer multi-consumer queue over a fixed array, using
+/// Dmitry Vyukov's per-slot sequence numbers.
+///
+/// Producers and consumers each claim a position with one CAS on their own
+/// counter, then hand the slot over through its sequence number, so there is
+/// no lock on the fast path and pushes never contend with pops. Share the
+/// queue between threads with an `Arc`.
+///
+/// `try_push` and `try_pop` return immediately. `push` and `pop` block
+/// until they succeed, sleeping on a condition variable that the fast paths
+/// only touch when someone is actually asleep.
+pub struct MpmcQueue<T> {
+    slots: Box<[Slot<T>]>,
+    mask: usize,
+    push_pos: CachePadded<AtomicUsize>,
+    pop_pos: CachePadded<AtomicUsize>,
+    // Threads blocked in `push` or `pop`
+    sleepers: AtomicUsize,
+    lock: Mutex<()>,
+    changed: Condvar,
+}
+
+// A slot's value is only touched by the one thread whose CAS claimed it,
+// and the sequence number's Release/Acquire hands it between threads.
+unsafe impl<T: Send> Sync for MpmcQueue<T> {}
+
+impl<T> MpmcQueue<T> {
+    /// A queue holding at least `capacity` elements, rounded up to a power
+    /// of two (and to at least 2).
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "queue capacity must be positive");
+        let capacity = capacity.max(2).next_power_of_two();
+        MpmcQueue {
+            slots: (0..capacity)
+                .map(|i| Slot { seq: AtomicUsize::new(i), val: UnsafeCell::new(MaybeUninit::uninit()) })
+                .collect(),
+            mask: capacity - 1,
+            push_pos: CachePadded(AtomicUsize::new(0)),
+            pop_pos: CachePadded(AtomicUsize::new(0)),
+            sleepers: AtomicUsize::new(0),
+            lock: Mutex::new(()),
+            changed: Condvar::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Number of queued elements. Only a snapshot while other threads are
+    /// pushing or popping.
+    pub fn len(&self) -> usize {
+        let pop = self.pop_pos.load(Ordering::Acquire);
+        let push = self.push_pos.load(Ordering::Acquire);
+        push.wrapping_sub(pop).min(self.capacity())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Push `val`, or hand it back if the queue is full.
+    pub fn try_push(&self, val: T) -> Result<(), T> {
+        self.push_slot(val)?;
+        self.wake();
+        Ok(())
+    }
+
+    /// Pop the oldest element, or `None` if the queue is empty.
+    pub fn try_pop(&self) -> Option<T> {
+        let val = self.pop_slot()?;
+        self.wake();
+        Some(val)
+    }
+
+    fn push_slot(&self, val: T) -> Result<(), T> {
+        let mut pos = self.push_pos.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos & self.mask];
+            let seq = slot.seq.load(Ordering::Acquire);
+            let diff = seq.wrapping_sub(pos) as isize;
+            if diff == 0 {
+                match self.push_pos.compare_exchange_weak(pos, pos.wrapping_add(1), Ordering::Relaxed, Ordering::Relaxed) {
+                    Ok(_) => {
+                        slot.val.with_mut(|ptr| unsafe { (*ptr).write(val) });
+                        slot.seq.store(pos.wrapping_add(1), Ordering::Release);
+                        return Ok(());
+                    }
+                    Err(current) => pos = current,
+                }
+            } else if diff < 0 {
+                // The slot still holds the value from one lap ago
+                return Err(val);
+            } else {
+                // Another producer got here first
+                pos = self.push_pos.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn pop_slot(&self) -> Option<T> {
+        let mut pos = self.pop_pos.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos & self.mask];
+            let seq = slot.seq.load(Ordering::Acquire);
+            let diff = seq.wrapping_sub(pos.wrapping_add(1)) as isize;
+            if diff == 0 {
+                match self.pop_pos.compare_exchange_weak(pos, pos.wrapping_add(1), Ordering::Relaxed, Ordering::Relaxed) {
+                    Ok(_) => {
+                        let val = slot.val.with_mut(|ptr| unsafe { (*ptr).assume_init_read() });
+                        // Free the slot for the push one lap ahead
+                        slot.seq.store(pos.wrapping_add(self.capacity()), Ordering::Release);
+                        return Some(val);
+                    }
+                    Err(current) => pos = current,
+                }
+            } else if diff < 0 {
+                // The slot hasn't been filled yet
+                return None;
+            } else {
+                pos = self.pop_pos.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Push `val`, blocking while the queue is full.
+    pub fn push(&self, val: T) {
+        let mut val = Some(val);
+        self.block_on(|| match self.push_slot(val.take().unwrap()) {
+            Ok(()) => Some(()),
+            Err(back) => {
+                val = Some(back);
+                None
+            }
+        })
+    }
+
+    /// Pop the oldest element, blocking while the queue is empty.
+    pub fn pop(&self) -> T {
+        self.block_on(|| self.pop_slot())
+    }
+
+    // Retry `attempt` until it succeeds, sleeping between tries, then wake
+    // anyone waiting on the change it made. Checking again under the lock
+    // before each wait means a wake-up from a thread that saw us in
+    // `sleepers` can't be missed.
+    fn block_on<R>(&self, mut attempt: impl FnMut() -> Option<R>) -> R {
+        if let Some(r) = attempt() {
+            self.wake();
+            return r;
+        }
+        self.sleepers.fetch_add(1, Ordering::SeqCst);
+        fence(Ordering::SeqCst);
+        let mut guard = self.lock.lock().unwrap();
+        let result = loop {
+            if let Some(r) = attempt() {
+                break r;
+            }
+            guard = self.changed.wait(guard).unwrap();
+        };
+        drop(guard);
+        self.sleepers.fetch_sub(1, Ordering::SeqCst);
+        self.wake();
+        result
+    }
+
+    fn wake(&self) {
+        fence(Ordering::SeqCst);
+        if self.sleepers.load(Ordering::Relaxed) > 0 {
+            let _guard = self.lock.lock().unwrap();
+            self.changed.notify_all();
+        }
+    }
+}
+
+impl<T> Drop for MpmcQueue<T> {
+    fn drop(&mut self) {
+        while self.try_pop().is_some() {}
+    }
+}
+
+/// Counts the slot buffer only: other threads may be moving elements in and
+/// out, so their payloads can't be looked at.
+impl<T> HeapSize for MpmcQueue<T> {
+    fn heap_size(&self) -> usize {
+        self.slots.len() * size_of::<Slot<T>>()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::MpmcQueue;
+
+    #[test]
+    fn test_try_push_pop() {
+        let queue = MpmcQueue::new(3);
+        assert_eq!(queue.capacity(), 4);
+        assert_eq!(queue.try_pop(), None);
+        for i in 0..4 {
+            assert_eq!(queue.try_push(i), Ok(()));
+        }
+        assert_eq!(queue.try_push(4), Err(4));
+        assert_eq!(queue.len(), 4);
+        assert_eq!(queue.try_pop(), Some(0));
+        assert_eq!(queue.try_push(4), Ok(()));
+        assert_eq!((0..4).map(|_| queue.try_pop().unwrap()).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_capacity_one() {
+        let queue = MpmcQueue::new(1);
+        assert_eq!(queue.capacity(), 2);
+        for i in 0..10 {
+            queue.try_push(i).unwrap();
+            assert_eq!(queue.try_pop(), Some(i));
+        }
+    }
+
+    #[test]
+    fn test_drop_leftovers() {
+        let val = Arc::new(());
+        let queue = MpmcQueue::new(8);
+        for _ in 0..5 {
+            queue.try_push(val.clone()).unwrap();
+        }
+        drop(queue.try_pop());
+        assert_eq!(Arc::strong_count(&val), 5);
+        drop(queue);
+        assert_eq!(Arc::strong_count(&val), 1);
+    }
+
+    #[test]
+    fn test_many_threads() {
+        const PRODUCERS: usize = 4;
+        const PER_PRODUCER: usize = 20_000;
+        let queue = Arc::new(MpmcQueue::new(16));
+        let producers: Vec<_> = (0..PRODUCERS)
+            .map(|p| {
+                let queue = queue.clone();
+                thread::spawn(move || {
+                    for i in 0..PER_PRODUCER {
+                        queue.push(p * PER_PRODUCER + i);
+                    }
+                })
+            })
+            .collect();
+        let consumers: Vec<_> = (0..3)
+            .map(|_| {
+                let queue = queue.clone();
+                thread::spawn(move || {
+                    let mut got = Vec::new();
+                    // usize::MAX tells each consumer to stop
+                    loop {
+                        match queue.pop() {
+                            usize::MAX => return got,
+                            val => got.push(val),
+                        }
+                    }
+                })
+            })
+            .collect();
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        for _ in 0..consumers.len() {
+            queue.push(usize::MAX);
+        }
+        let mut all: Vec<usize> = consumers.into_iter().flat_map(|c| c.join().unwrap()).collect();
+        all.sort();
+        assert_eq!(all, (0..PRODUCERS * PER_PRODUCER).collect::<Vec<_>>());
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use loom::thread;
+
+    use super::MpmcQueue;
+    use crate::sync::shim::Arc;
+
+    #[test]
+    fn loom_concurrent_pushes() {
+        loom::model(|| {
+            let queue = Arc::new(MpmcQueue::new(2));
+            let pushers: Vec<_> = (1..=2)
+                .map(|i| {
+                    let queue = queue.clone();
+                    thread::spawn(move || queue.try_push(i).unwrap())
+                })
+                .collect();
+            for pusher in pushers {
+                pusher.join().unwrap();
+            }
+            let mut got = vec![queue.try_pop().unwrap(), queue.try_pop().unwrap()];
+            got.sort();
+            assert_eq!(got, vec![1, 2]);
+            assert_eq!(queue.try_pop(), None);
+        });
+    }
+
+    #[test]
+    fn loom_push_pop_race() {
+        loom::model(|| {
+            let queue = Arc::new(MpmcQueue::new(2));
+            let producer = {
+                let queue = queue.clone();
+                thread::spawn(move || {
+                    queue.try_push(1).unwrap();
+                    queue.try_push(2).unwrap();
+                })
+            };
+            // Whatever the consumer sees, it sees in order
+            let mut got: Vec<_> = (0..2).filter_map(|_| queue.try_pop()).collect();
+            producer.join().unwrap();
+            got.extend(std::iter::from_fn(|| queue.try_pop()));
+            assert_eq!(got, vec![1, 2]);
+        });
+    }
+
+    #[test]
+    fn loom_blocking_push_wakes() {
+        loom::model(|| {
+            let queue = Arc::new(MpmcQueue::new(2));
+            queue.try_push(1).unwrap();
+            queue.try_push(2).unwrap();
+            let producer = {
+                let queue = queue.clone();
+                // Full, so this waits for the pop below
+                thread::spawn(move || queue.push(3))
+            };
+            assert_eq!(queue.pop(), 1);
+            producer.join().unwrap();
+            assert_eq!((queue.pop(), queue.pop()), (2, 3));
+        });
+    }
+}
@@ -0,0 +1,205 @@
+use std::fmt::Debug;
+
+use crate::btree::BTreeMap;
+use crate::heap_size::HeapSize;
+use crate::list::List;
+
+/// A map from ordered keys to one or more values each.
+///
+/// Keys are kept in a `BTreeMap` and each key's values in a `List`, so adding
+/// a value is a push onto the front of its key's list and values come back
+/// most recently inserted first. Keys with no values left are removed.
+pub struct MultiMap<K, V> {
+    map: BTreeMap<K, List<V>, 16>,
+    len: usize,
+}
+
+impl<K: Ord, V> MultiMap<K, V> {
+    pub fn new() -> Self {
+        MultiMap { map: BTreeMap::new(), len: 0 }
+    }
+
+    /// Total number of values across all keys.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of distinct keys.
+    pub fn keys_len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.len = 0;
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        match self.map.get_mut(&key) {
+            Some(values) => values.push_front(value),
+            None => {
+                let mut values = List::new();
+                values.push_front(value);
+                self.map.insert(key, values);
+            }
+        }
+        self.len += 1;
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// The values for `key`, most recent first.
+    pub fn get_all(&self, key: &K) -> impl Iterator<Item = &V> {
+        self.map.get(key).into_iter().flat_map(List::iter)
+    }
+
+    pub fn get_all_mut(&mut self, key: &K) -> impl Iterator<Item = &mut V> {
+        self.map.get_mut(key).into_iter().flat_map(List::iter_mut)
+    }
+
+    /// The most recently inserted value for `key`.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.map.get(key)?.peek_front()
+    }
+
+    pub fn count(&self, key: &K) -> usize {
+        self.map.get(key).map_or(0, List::len)
+    }
+
+    /// Remove the most recent value for `key` equal to `value`.
+    pub fn remove_one(&mut self, key: &K, value: &V) -> Option<V>
+    where
+        V: PartialEq,
+    {
+        let values = self.map.get_mut(key)?;
+        let removed = values.remove_first(|v| v == value)?;
+        if values.is_empty() {
+            self.map.remove(key);
+        }
+        self.len -= 1;
+        Some(removed)
+    }
+
+    /// Remove `key` along with all its values.
+    pub fn remove_all(&mut self, key: &K) -> Option<List<V>> {
+        let values = self.map.remove(key)?;
+        self.len -= values.len();
+        Some(values)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.map.iter().map(|(k, _)| k)
+    }
+
+    /// Each key in order with its values.
+    pub fn groups(&self) -> impl Iterator<Item = (&K, &List<V>)> {
+        self.map.iter()
+    }
+
+    /// Every key-value pair, by key and then most recent first.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.map.iter().flat_map(|(k, values)| values.iter().map(move |v| (k, v)))
+    }
+}
+
+impl<K: Ord, V> Default for MultiMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for MultiMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = MultiMap::new();
+        for (k, v) in iter {
+            map.insert(k, v);
+        }
+        map
+    }
+}
+
+impl<K: Ord + Debug, V: Debug> Debug for MultiMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.groups().map(|(k, values)| (k, values.iter().collect::<Vec<_>>())))
+            .finish()
+    }
+}
+
+impl<K: HeapSize, V: HeapSize> HeapSize for MultiMap<K, V> {
+    fn heap_size(&self) -> usize {
+        self.map.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heap_size::HeapSize;
+
+    use super::MultiMap;
+
+    #[test]
+    fn test_insert_get_all() {
+        let mut map = MultiMap::new();
+        map.insert("fruit", "apple");
+        map.insert("veg", "carrot");
+        map.insert("fruit", "banana");
+        map.insert("fruit", "apple");
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.keys_len(), 2);
+        assert_eq!(map.get_all(&"fruit").collect::<Vec<_>>(), vec![&"apple", &"banana", &"apple"]);
+        assert_eq!(map.get(&"veg"), Some(&"carrot"));
+        assert_eq!(map.count(&"fruit"), 3);
+        assert_eq!(map.get_all(&"meat").count(), 0);
+        assert_eq!(map.count(&"meat"), 0);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut map: MultiMap<i32, i32> = [(1, 10), (1, 20), (1, 10), (2, 30)].into_iter().collect();
+        assert_eq!(map.remove_one(&1, &10), Some(10));
+        assert_eq!(map.get_all(&1).copied().collect::<Vec<_>>(), vec![20, 10]);
+        assert_eq!(map.remove_one(&1, &99), None);
+        assert_eq!(map.remove_one(&3, &10), None);
+
+        assert_eq!(map.remove_one(&2, &30), Some(30));
+        assert!(!map.contains_key(&2));
+
+        let removed = map.remove_all(&1).unwrap();
+        assert_eq!(removed.len(), 2);
+        assert!(map.is_empty());
+        assert_eq!(map.keys_len(), 0);
+    }
+
+    #[test]
+    fn test_grouped_iteration() {
+        let mut map = MultiMap::new();
+        for (i, word) in ["b", "a", "c", "a", "b", "a"].into_iter().enumerate() {
+            map.insert(word, i);
+        }
+        for v in map.get_all_mut(&"c") {
+            *v *= 100;
+        }
+        let groups: Vec<_> = map.groups().map(|(k, vs)| (*k, vs.iter().copied().collect::<Vec<_>>())).collect();
+        assert_eq!(groups, vec![("a", vec![5, 3, 1]), ("b", vec![4, 0]), ("c", vec![200])]);
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec!["a", "b", "c"]);
+        assert_eq!(map.iter().count(), 6);
+        assert_eq!(map.iter().next(), Some((&"a", &5)));
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let mut map = MultiMap::new();
+        map.insert(1u32, String::from("ab"));
+        let one = map.heap_size();
+        map.insert(1, String::from("cde"));
+        // Another node in the same key's list
+        assert!(map.heap_size() >= one + size_of::<String>() + 3);
+    }
+}
@@ -0,0 +1,245 @@
+use std::borrow::Borrow;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use crate::hash_map::{Entry, HashMap};
+use crate::heap_size::HeapSize;
+
+/// A bag: a set where each item can occur more than once, stored as a count
+/// per distinct item in the crate's `HashMap`.
+pub struct MultiSet<T> {
+    counts: HashMap<T, usize>,
+    len: usize,
+}
+
+impl<T: Hash + Eq> MultiSet<T> {
+    pub fn new() -> Self {
+        MultiSet { counts: HashMap::new(), len: 0 }
+    }
+
+    /// Number of items, counting repeats.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of distinct items.
+    pub fn distinct_len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.counts.clear();
+        self.len = 0;
+    }
+
+    /// Add one occurrence of `item`, returning its new count.
+    pub fn insert(&mut self, item: T) -> usize {
+        self.insert_n(item, 1)
+    }
+
+    /// Add `n` occurrences of `item`, returning its new count.
+    pub fn insert_n(&mut self, item: T, n: usize) -> usize {
+        if n == 0 {
+            return self.count(&item);
+        }
+        self.len += n;
+        let count = self.counts.entry(item).or_insert(0);
+        *count += n;
+        *count
+    }
+
+    /// Remove one occurrence of `item`, returning whether there was one.
+    pub fn remove<Q: Hash + Eq + ?Sized>(&mut self, item: &Q) -> bool
+    where
+        T: Borrow<Q>,
+    {
+        self.remove_n(item, 1) == 1
+    }
+
+    /// Remove up to `n` occurrences of `item`, returning how many were
+    /// removed.
+    pub fn remove_n<Q: Hash + Eq + ?Sized>(&mut self, item: &Q, n: usize) -> usize
+    where
+        T: Borrow<Q>,
+    {
+        let Some(count) = self.counts.get_mut(item) else {
+            return 0;
+        };
+        let removed = n.min(*count);
+        *count -= removed;
+        if *count == 0 {
+            self.counts.remove(item);
+        }
+        self.len -= removed;
+        removed
+    }
+
+    /// Remove every occurrence of `item`, returning how many there were.
+    pub fn remove_all<Q: Hash + Eq + ?Sized>(&mut self, item: &Q) -> usize
+    where
+        T: Borrow<Q>,
+    {
+        let count = self.counts.remove(item).unwrap_or(0);
+        self.len -= count;
+        count
+    }
+
+    pub fn count<Q: Hash + Eq + ?Sized>(&self, item: &Q) -> usize
+    where
+        T: Borrow<Q>,
+    {
+        self.counts.get(item).copied().unwrap_or(0)
+    }
+
+    pub fn contains<Q: Hash + Eq + ?Sized>(&self, item: &Q) -> bool
+    where
+        T: Borrow<Q>,
+    {
+        self.counts.contains_key(item)
+    }
+
+    /// Each distinct item with its count, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&T, usize)> {
+        self.counts.iter().map(|(item, &count)| (item, count))
+    }
+
+    fn combine(&self, other: &MultiSet<T>, f: impl Fn(usize, usize) -> usize) -> MultiSet<T>
+    where
+        T: Clone,
+    {
+        let mut out = MultiSet::new();
+        for (item, count) in self.iter() {
+            out.insert_n(item.clone(), f(count, other.count(item)));
+        }
+        for (item, count) in other.iter() {
+            if let Entry::Vacant(entry) = out.counts.entry(item.clone()) {
+                let n = f(self.count(item), count);
+                if n > 0 {
+                    entry.insert(n);
+                    out.len += n;
+                }
+            }
+        }
+        out
+    }
+
+    /// Each item with the larger of its two counts.
+    pub fn union(&self, other: &MultiSet<T>) -> MultiSet<T>
+    where
+        T: Clone,
+    {
+        self.combine(other, usize::max)
+    }
+
+    /// Each item with the smaller of its two counts.
+    pub fn intersection(&self, other: &MultiSet<T>) -> MultiSet<T>
+    where
+        T: Clone,
+    {
+        self.combine(other, usize::min)
+    }
+
+    /// Each item with its two counts added together.
+    pub fn sum(&self, other: &MultiSet<T>) -> MultiSet<T>
+    where
+        T: Clone,
+    {
+        self.combine(other, |a, b| a + b)
+    }
+
+    /// Each item with its count in `other` taken away, stopping at zero.
+    pub fn difference(&self, other: &MultiSet<T>) -> MultiSet<T>
+    where
+        T: Clone,
+    {
+        self.combine(other, usize::saturating_sub)
+    }
+}
+
+impl<T: Hash + Eq> Default for MultiSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Hash + Eq> FromIterator<T> for MultiSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = MultiSet::new();
+        for item in iter {
+            set.insert(item);
+        }
+        set
+    }
+}
+
+impl<T: Debug> Debug for MultiSet<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.counts.iter()).finish()
+    }
+}
+
+impl<T: HeapSize> HeapSize for MultiSet<T> {
+    fn heap_size(&self) -> usize {
+        self.counts.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MultiSet;
+
+    fn sorted(set: &MultiSet<char>) -> Vec<(char, usize)> {
+        let mut items: Vec<_> = set.iter().map(|(&c, n)| (c, n)).collect();
+        items.sort();
+        items
+    }
+
+    #[test]
+    fn test_insert_remove_count() {
+        let mut set = MultiSet::new();
+        assert_eq!(set.insert("a"), 1);
+        assert_eq!(set.insert("a"), 2);
+        assert_eq!(set.insert_n("b", 3), 3);
+        assert_eq!(set.len(), 5);
+        assert_eq!(set.distinct_len(), 2);
+        assert_eq!(set.count("a"), 2);
+        assert_eq!(set.count("z"), 0);
+
+        assert!(set.remove("a"));
+        assert!(set.remove("a"));
+        assert!(!set.remove("a"));
+        assert!(!set.contains("a"));
+        assert_eq!(set.remove_n("b", 5), 3);
+        assert!(set.is_empty());
+
+        set.insert_n("c", 4);
+        assert_eq!(set.remove_all("c"), 4);
+        assert_eq!(set.remove_all("c"), 0);
+        assert_eq!(set.len(), 0);
+    }
+
+    #[test]
+    fn test_string_keys() {
+        let set: MultiSet<String> = "to be or not to be".split(' ').map(String::from).collect();
+        assert_eq!(set.count("to"), 2);
+        assert_eq!(set.count("be"), 2);
+        assert_eq!(set.count("or"), 1);
+        assert_eq!(set.len(), 6);
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a: MultiSet<char> = "aaabbc".chars().collect();
+        let b: MultiSet<char> = "abbbd".chars().collect();
+        assert_eq!(sorted(&a.union(&b)), vec![('a', 3), ('b', 3), ('c', 1), ('d', 1)]);
+        assert_eq!(sorted(&a.intersection(&b)), vec![('a', 1), ('b', 2)]);
+        assert_eq!(sorted(&a.sum(&b)), vec![('a', 4), ('b', 5), ('c', 1), ('d', 1)]);
+        assert_eq!(sorted(&a.difference(&b)), vec![('a', 2), ('c', 1)]);
+        assert_eq!(a.intersection(&b).len(), 3);
+        assert_eq!(a.difference(&b).len(), 3);
+    }
+}
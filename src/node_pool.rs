@@ -0,0 +1,186 @@
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::rc::Rc;
+use std::sync::Mutex;
+
+use crate::fallible::{AllocError, try_box};
+
+/// A pointer to one list node whose allocation a `NodePool` can keep for
+/// reuse once the node has been moved out of it.
+pub trait Recycle: Sized {
+    type Node;
+    /// The empty allocation left behind.
+    type Spare;
+
+    /// Move `node` into `spare`, or into a fresh allocation.
+    fn fill(spare: Option<Self::Spare>, node: Self::Node) -> Self;
+
+    /// Move the node out, along with its allocation if nothing else can
+    /// still see it. `None` if other owners keep the node itself alive.
+    fn drain(self) -> Option<(Self::Node, Option<Self::Spare>)>;
+}
+
+impl<N> Recycle for Box<N> {
+    type Node = N;
+    type Spare = Box<MaybeUninit<N>>;
+
+    fn fill(spare: Option<Self::Spare>, node: N) -> Self {
+        match spare {
+            Some(spare) => Box::write(spare, node),
+            None => Box::new(node),
+        }
+    }
+
+    fn drain(self) -> Option<(N, Option<Self::Spare>)> {
+        let raw = Box::into_raw(self);
+        // Safety: the node is read out exactly once, and the allocation then
+        // only ever holds it as uninitialized memory
+        let node = unsafe { ptr::read(raw) };
+        Some((node, Some(unsafe { Box::from_raw(raw.cast::<MaybeUninit<N>>()) })))
+    }
+}
+
+impl<N> Recycle for Rc<N> {
+    type Node = N;
+    type Spare = Rc<MaybeUninit<N>>;
+
+    fn fill(spare: Option<Self::Spare>, node: N) -> Self {
+        match spare {
+            Some(mut spare) => {
+                // Spares are only made from unshared `Rc`s
+                Rc::get_mut(&mut spare).unwrap().write(node);
+                unsafe { spare.assume_init() }
+            }
+            None => Rc::new(node),
+        }
+    }
+
+    fn drain(self) -> Option<(N, Option<Self::Spare>)> {
+        if Rc::weak_count(&self) > 0 {
+            // A `Weak` could later look at the reused allocation, so give the
+            // node back without it
+            return Rc::into_inner(self).map(|node| (node, None));
+        }
+        if Rc::strong_count(&self) > 1 {
+            return None;
+        }
+        let raw = Rc::into_raw(self);
+        // Safety: we are the only owner, and `MaybeUninit<N>` has the same
+        // layout as `N`, as `Rc::from_raw` requires
+        let node = unsafe { ptr::read(raw) };
+        Some((node, Some(unsafe { Rc::from_raw(raw.cast::<MaybeUninit<N>>()) })))
+    }
+}
+
+/// A free list of node allocations, so lists that push and pop heavily can
+/// reuse nodes instead of going back to the allocator for each one.
+///
+/// `P` is the list's node pointer, such as `list::ListPool<T>`'s
+/// `Box<list::Node<T>>`. Share one pool between several lists of the same
+/// type through an `Arc` or `Rc`, e.g. `List::with_pool`. The free list is
+/// behind a `Mutex`, so a pool of `Send` allocations can be shared across
+/// threads. The pool keeps at most `max_spare` allocations and frees any
+/// beyond that.
+pub struct NodePool<P: Recycle> {
+    spare: Mutex<Vec<P::Spare>>,
+    max_spare: usize,
+}
+
+impl<P: Recycle> NodePool<P> {
+    pub fn new(max_spare: usize) -> Self {
+        NodePool { spare: Mutex::new(Vec::new()), max_spare }
+    }
+
+    /// Number of allocations waiting to be reused.
+    pub fn spare_count(&self) -> usize {
+        self.spare.lock().unwrap().len()
+    }
+
+    /// Free every spare allocation.
+    pub fn shrink(&self) {
+        self.spare.lock().unwrap().clear();
+    }
+
+    /// Wrap `node` in a pointer, reusing a spare allocation if there is one.
+    pub fn alloc(&self, node: P::Node) -> P {
+        let spare = self.spare.lock().unwrap().pop();
+        P::fill(spare, node)
+    }
+
+    /// Move the node out of `ptr` and keep its allocation for reuse. `None`
+    /// if other owners keep the node alive.
+    pub fn free(&self, ptr: P) -> Option<P::Node> {
+        let (node, spare) = ptr.drain()?;
+        let mut spares = self.spare.lock().unwrap();
+        if let Some(spare) = spare
+            && spares.len() < self.max_spare
+        {
+            spares.push(spare);
+        }
+        Some(node)
+    }
+}
+
+impl<N> NodePool<Box<N>> {
+    /// `alloc`, but returning an error instead of aborting if there is no
+    /// spare and the allocator fails.
+    pub fn try_alloc(&self, node: N) -> Result<Box<N>, AllocError> {
+        let spare = self.spare.lock().unwrap().pop();
+        match spare {
+            Some(spare) => Ok(Box::write(spare, node)),
+            None => try_box(node),
+        }
+    }
+}
+
+impl<P: Recycle> Default for NodePool<P> {
+    fn default() -> Self {
+        Self::new(usize::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::NodePool;
+
+    #[test]
+    fn test_box_reuse() {
+        let pool = NodePool::<Box<String>>::new(1);
+        let first = pool.alloc("a".to_string());
+        let addr = &*first as *const String;
+        let second = pool.alloc("b".to_string());
+        assert_eq!(pool.free(first), Some("a".to_string()));
+        assert_eq!(pool.spare_count(), 1);
+        // Past `max_spare`, so this allocation is freed
+        assert_eq!(pool.free(second), Some("b".to_string()));
+        assert_eq!(pool.spare_count(), 1);
+
+        let third = pool.alloc("c".to_string());
+        assert_eq!(&*third as *const String, addr);
+        assert_eq!(*third, "c");
+        assert_eq!(pool.spare_count(), 0);
+    }
+
+    #[test]
+    fn test_rc_sharing() {
+        let pool = NodePool::<Rc<Vec<i32>>>::default();
+        let node = pool.alloc(vec![1, 2]);
+        let other = node.clone();
+        assert_eq!(pool.free(node), None);
+
+        let weak = Rc::downgrade(&other);
+        // Unique, but a `Weak` remains, so the allocation can't be reused
+        assert_eq!(pool.free(other), Some(vec![1, 2]));
+        assert_eq!(pool.spare_count(), 0);
+        assert!(weak.upgrade().is_none());
+
+        let node = pool.alloc(vec![3]);
+        assert_eq!(pool.free(node), Some(vec![3]));
+        assert_eq!(pool.spare_count(), 1);
+        assert_eq!(*pool.alloc(vec![4]), vec![4]);
+        pool.shrink();
+        assert_eq!(pool.spare_count(), 0);
+    }
+}
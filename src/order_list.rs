@@ -0,0 +1,380 @@
+use std::cmp::Ordering;
+use std::fmt::Debug;
+
+use crate::error::CollectionError;
+use crate::heap_size::HeapSize;
+use crate::slot_map::{SlotKey, SlotMap};
+
+// Labels are below 2^62, so ranges of up to the whole space fit in a u64
+const LABEL_BITS: u32 = 62;
+// How much denser than its parent a range may be before it is relabeled
+const DENSITY: f64 = 1.5;
+
+/// A position in an `OrderList`. Stays valid until deleted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Handle(SlotKey);
+
+struct Node {
+    label: u64,
+    prev: Option<SlotKey>,
+    next: Option<SlotKey>,
+}
+
+/// A list of positions that answers "which comes first?" in O(1).
+///
+/// Every position carries an integer label that increases along the list,
+/// so comparing two positions compares two labels. An insert takes the
+/// label halfway between its neighbors. When there is no room left between
+/// them, the smallest aligned range of labels around the insert that is
+/// sparse enough is found by doubling, and the positions in it are spread
+/// out evenly, following Dietz and Sleator as simplified by Bender et al.
+/// Each range may hold a fixed factor more positions per label than the
+/// range twice its size, which keeps inserts at O(log n) amortized.
+///
+/// The list only holds positions; callers keep their own data keyed by
+/// `Handle`. Handles are generational, so a deleted one never aliases a
+/// later position.
+pub struct OrderList {
+    nodes: SlotMap<Node>,
+    // A hidden first position, labeled 0, so every insert is after something
+    base: SlotKey,
+}
+
+impl OrderList {
+    pub fn new() -> Self {
+        let mut nodes = SlotMap::new();
+        let base = nodes.insert(Node { label: 0, prev: None, next: None });
+        OrderList { nodes, base }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Panic unless labels strictly increase along the list and every
+    /// position links back to the one before it. Only in debug builds and
+    /// tests.
+    #[cfg(any(test, debug_assertions))]
+    pub fn validate(&self) {
+        let mut count = 0;
+        let mut key = self.base;
+        while let Some(next) = self.nodes[key].next {
+            assert!(self.nodes[key].label < self.nodes[next].label, "labels out of order");
+            assert_eq!(self.nodes[next].prev, Some(key), "stale prev link");
+            count += 1;
+            key = next;
+        }
+        assert_eq!(count, self.len(), "unlinked positions left in the list");
+    }
+
+    pub fn contains(&self, handle: Handle) -> bool {
+        handle.0 != self.base && self.nodes.contains_key(handle.0)
+    }
+
+    fn node(&self, handle: Handle) -> &Node {
+        assert!(self.contains(handle), "stale order list handle");
+        &self.nodes[handle.0]
+    }
+
+    fn visible(&self, key: Option<SlotKey>) -> Option<Handle> {
+        key.filter(|&key| key != self.base).map(Handle)
+    }
+
+    pub fn first(&self) -> Option<Handle> {
+        self.visible(self.nodes[self.base].next)
+    }
+
+    pub fn last(&self) -> Option<Handle> {
+        let mut key = self.base;
+        while let Some(next) = self.nodes[key].next {
+            key = next;
+        }
+        self.visible(Some(key))
+    }
+
+    /// The position after `handle`.
+    ///
+    /// Panics if `handle` was deleted.
+    pub fn next(&self, handle: Handle) -> Option<Handle> {
+        self.visible(self.node(handle).next)
+    }
+
+    /// The position before `handle`.
+    ///
+    /// Panics if `handle` was deleted.
+    pub fn prev(&self, handle: Handle) -> Option<Handle> {
+        self.visible(self.node(handle).prev)
+    }
+
+    /// Which of two positions comes first.
+    ///
+    /// Panics if either was deleted.
+    pub fn order(&self, a: Handle, b: Handle) -> Ordering {
+        self.node(a).label.cmp(&self.node(b).label)
+    }
+
+    pub fn push_front(&mut self) -> Handle {
+        self.insert_after_key(self.base)
+    }
+
+    pub fn push_back(&mut self) -> Handle {
+        let last = self.last().map_or(self.base, |handle| handle.0);
+        self.insert_after_key(last)
+    }
+
+    /// A new position right after `handle`.
+    ///
+    /// Panics if `handle` was deleted.
+    pub fn insert_after(&mut self, handle: Handle) -> Handle {
+        self.node(handle);
+        self.insert_after_key(handle.0)
+    }
+
+    /// `insert_after`, but returning an error if `handle` was deleted.
+    pub fn try_insert_after(&mut self, handle: Handle) -> Result<Handle, CollectionError> {
+        if !self.contains(handle) {
+            return Err(CollectionError::StaleHandle);
+        }
+        Ok(self.insert_after_key(handle.0))
+    }
+
+    /// A new position right before `handle`.
+    ///
+    /// Panics if `handle` was deleted.
+    pub fn insert_before(&mut self, handle: Handle) -> Handle {
+        let prev = self.node(handle).prev.unwrap();
+        self.insert_after_key(prev)
+    }
+
+    /// `insert_before`, but returning an error if `handle` was deleted.
+    pub fn try_insert_before(&mut self, handle: Handle) -> Result<Handle, CollectionError> {
+        if !self.contains(handle) {
+            return Err(CollectionError::StaleHandle);
+        }
+        Ok(self.insert_before(handle))
+    }
+
+    fn insert_after_key(&mut self, prev: SlotKey) -> Handle {
+        let label = self.nodes[prev].label;
+        let next = self.nodes[prev].next;
+        let next_label = next.map_or(1 << LABEL_BITS, |next| self.nodes[next].label);
+        let key = self.nodes.insert(Node { label, prev: Some(prev), next });
+        self.nodes[prev].next = Some(key);
+        if let Some(next) = next {
+            self.nodes[next].prev = Some(key);
+        }
+        if next_label - label > 1 {
+            self.nodes[key].label = label + (next_label - label) / 2;
+        } else {
+            self.relabel(key);
+        }
+        Handle(key)
+    }
+
+    /// Spread out the labels around `key`, which shares its predecessor's
+    /// label, over the smallest aligned range that is sparse enough.
+    fn relabel(&mut self, key: SlotKey) {
+        let label = self.nodes[key].label;
+        for bits in 1..=LABEL_BITS {
+            let start = label >> bits << bits;
+            let end = start + (1 << bits);
+            // Walk out to both ends of the range
+            let mut first = key;
+            let mut count = 1;
+            while let Some(prev) = self.nodes[first].prev
+                && self.nodes[prev].label >= start
+            {
+                first = prev;
+                count += 1;
+            }
+            let mut last = key;
+            while let Some(next) = self.nodes[last].next
+                && self.nodes[next].label < end
+            {
+                last = next;
+                count += 1;
+            }
+            if (count as f64) < (2.0 / DENSITY).powi(bits as i32) || bits == LABEL_BITS {
+                assert!(count < 1 << bits, "order list labels exhausted");
+                let gap = (1 << bits) / count;
+                let mut curr = Some(first);
+                for i in 0..count {
+                    let node = &mut self.nodes[curr.unwrap()];
+                    node.label = start + i * gap;
+                    curr = node.next;
+                }
+                return;
+            }
+        }
+    }
+
+    /// Remove `handle`'s position, returning false if it was already gone.
+    pub fn delete(&mut self, handle: Handle) -> bool {
+        if !self.contains(handle) {
+            return false;
+        }
+        let node = self.nodes.remove(handle.0).unwrap();
+        // Every position has a predecessor, if only the hidden first one
+        self.nodes[node.prev.unwrap()].next = node.next;
+        if let Some(next) = node.next {
+            self.nodes[next].prev = node.prev;
+        }
+        true
+    }
+
+    /// Remove every position. Existing handles all become invalid.
+    pub fn clear(&mut self) {
+        let base = self.base;
+        self.nodes.retain(|key, _| key == base);
+        self.nodes[base].next = None;
+    }
+
+    /// Iterate over the positions in order.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter { list: self, curr: self.first() }
+    }
+}
+
+impl Default for OrderList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Iter<'a> {
+    list: &'a OrderList,
+    curr: Option<Handle>,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = Handle;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let handle = self.curr?;
+        self.curr = self.list.next(handle);
+        Some(handle)
+    }
+}
+
+impl<'a> IntoIterator for &'a OrderList {
+    type Item = Handle;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl Debug for OrderList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl HeapSize for Node {}
+
+impl HeapSize for OrderList {
+    fn heap_size(&self) -> usize {
+        self.nodes.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use crate::error::CollectionError;
+    use crate::rng::XorShift64;
+
+    use super::OrderList;
+
+    #[test]
+    fn test_basic_order() {
+        let mut list = OrderList::new();
+        assert!(list.is_empty());
+        let b = list.push_back();
+        let d = list.push_back();
+        let a = list.push_front();
+        let c = list.insert_after(b);
+        let c2 = list.insert_before(d);
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![a, b, c, c2, d]);
+        assert_eq!(list.order(a, d), Ordering::Less);
+        assert_eq!(list.order(c2, c), Ordering::Greater);
+        assert_eq!(list.order(b, b), Ordering::Equal);
+        assert_eq!((list.first(), list.last()), (Some(a), Some(d)));
+        assert_eq!((list.prev(a), list.next(d)), (None, None));
+
+        assert!(list.delete(c));
+        assert!(!list.delete(c));
+        assert!(!list.contains(c));
+        assert_eq!(list.next(b), Some(c2));
+        assert_eq!(list.len(), 4);
+        list.clear();
+        assert!(!list.contains(a));
+        assert_eq!(list.first(), None);
+        assert_eq!(list.try_insert_after(a), Err(CollectionError::StaleHandle));
+        assert_eq!(list.try_insert_before(a), Err(CollectionError::StaleHandle));
+        let last = list.push_back();
+        let e = list.try_insert_after(last).unwrap();
+        let f = list.try_insert_before(e).unwrap();
+        assert_eq!(list.last(), Some(e));
+        assert_eq!(list.prev(e), Some(f));
+    }
+
+    #[test]
+    #[should_panic(expected = "stale order list handle")]
+    fn test_stale_handle() {
+        let mut list = OrderList::new();
+        let a = list.push_back();
+        let b = list.push_back();
+        list.delete(a);
+        list.order(a, b);
+    }
+
+    #[test]
+    fn test_dense_inserts() {
+        // Always inserting right after the same position halves the same gap
+        // each time, forcing relabels
+        let mut list = OrderList::new();
+        let first = list.push_back();
+        let mut expected = vec![first];
+        for _ in 0..2000 {
+            expected.insert(1, list.insert_after(first));
+        }
+        // And always at the front
+        for _ in 0..2000 {
+            expected.insert(0, list.push_front());
+        }
+        list.validate();
+        assert_eq!(list.iter().collect::<Vec<_>>(), expected);
+        assert!(expected.windows(2).all(|w| list.order(w[0], w[1]) == Ordering::Less));
+    }
+
+    #[test]
+    fn test_random_ops() {
+        let mut rng = XorShift64::new();
+        let mut list = OrderList::new();
+        let mut model = Vec::new();
+        for _ in 0..20_000 {
+            if model.is_empty() || !rng.next_u64().is_multiple_of(4) {
+                let at = rng.next_u64() as usize % (model.len() + 1);
+                let handle = if at == 0 { list.push_front() } else { list.insert_after(model[at - 1]) };
+                model.insert(at, handle);
+            } else {
+                let at = rng.next_u64() as usize % model.len();
+                assert!(list.delete(model.remove(at)));
+            }
+        }
+        list.validate();
+        assert_eq!(list.len(), model.len());
+        assert_eq!(list.iter().collect::<Vec<_>>(), model);
+        for _ in 0..1000 {
+            let (i, j) = (rng.next_u64() as usize % model.len(), rng.next_u64() as usize % model.len());
+            assert_eq!(list.order(model[i], model[j]), i.cmp(&j));
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use std::fmt::Debug;
+
+use crate::dot::ToDot;
+use crate::heap_size::HeapSize;
+use crate::pretty::PrettyPrint;
+use crate::treap::{self, Treap};
+
+/// An ordered set that can also find the `k`-th smallest element and the rank
+/// of any value in O(log n).
+///
+/// It is a `Treap` with unit values: every treap node already stores the size
+/// of its subtree, which is all that `select` and `rank` need, so the set adds
+/// no bookkeeping of its own.
+pub struct OrderStatisticSet<T> {
+    tree: Treap<T, ()>,
+}
+
+impl<T: Ord> OrderStatisticSet<T> {
+    pub fn new() -> Self {
+        OrderStatisticSet { tree: Treap::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.tree.clear();
+    }
+
+    /// Add `val`, returning false if it was already present.
+    pub fn insert(&mut self, val: T) -> bool {
+        self.tree.insert(val, ()).is_none()
+    }
+
+    /// Remove `val`, returning false if it wasn't present.
+    pub fn remove(&mut self, val: &T) -> bool {
+        self.tree.remove(val).is_some()
+    }
+
+    pub fn contains(&self, val: &T) -> bool {
+        self.tree.contains_key(val)
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.tree.first_key_value().map(|(val, _)| val)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.tree.last_key_value().map(|(val, _)| val)
+    }
+
+    /// The `k`-th smallest element, counting from 0.
+    pub fn select(&self, k: usize) -> Option<&T> {
+        self.tree.select(k).map(|(val, _)| val)
+    }
+
+    /// Number of elements less than `val`, which is its index in sorted order
+    /// if it is present.
+    pub fn rank(&self, val: &T) -> usize {
+        self.tree.rank(val)
+    }
+
+    /// Number of elements in `lo..hi`.
+    pub fn count_between(&self, lo: &T, hi: &T) -> usize {
+        self.rank(hi).saturating_sub(self.rank(lo))
+    }
+
+    /// The median element, the lower one when the length is even.
+    pub fn median(&self) -> Option<&T> {
+        self.select(self.len().checked_sub(1)? / 2)
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { inner: self.tree.iter() }
+    }
+}
+
+impl<T: Ord> Default for OrderStatisticSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> FromIterator<T> for OrderStatisticSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = Self::new();
+        for val in iter {
+            set.insert(val);
+        }
+        set
+    }
+}
+
+/// In-order iterator over the elements of an `OrderStatisticSet`.
+pub struct Iter<'a, T> {
+    inner: treap::Iter<'a, T, ()>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(val, _)| val)
+    }
+}
+
+impl<'a, T: Ord> IntoIterator for &'a OrderStatisticSet<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Ord + Debug> Debug for OrderStatisticSet<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Debug> ToDot for OrderStatisticSet<T> {
+    fn write_dot(&self, out: &mut dyn std::fmt::Write) -> std::fmt::Result {
+        self.tree.write_dot(out)
+    }
+}
+
+impl<T: Debug> PrettyPrint for OrderStatisticSet<T> {
+    fn pretty_print(&self) -> String {
+        self.tree.pretty_print()
+    }
+}
+
+impl<T: HeapSize> HeapSize for OrderStatisticSet<T> {
+    fn heap_size(&self) -> usize {
+        self.tree.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rng::XorShift64;
+
+    use super::OrderStatisticSet;
+
+    #[test]
+    fn test_basic_ops() {
+        let mut set: OrderStatisticSet<_> = [50, 10, 40, 20, 30].into_iter().collect();
+        assert!(!set.insert(30));
+        assert_eq!(set.len(), 5);
+        assert_eq!(set.select(0), Some(&10));
+        assert_eq!(set.select(4), Some(&50));
+        assert_eq!(set.select(5), None);
+        assert_eq!(set.rank(&30), 2);
+        assert_eq!(set.rank(&35), 3);
+        assert_eq!(set.count_between(&15, &45), 3);
+        assert_eq!(set.median(), Some(&30));
+
+        assert!(set.remove(&30));
+        assert!(!set.remove(&30));
+        assert_eq!(set.median(), Some(&20));
+        assert_eq!(format!("{set:?}"), "{10, 20, 40, 50}");
+        set.clear();
+        assert_eq!(set.median(), None);
+    }
+
+    #[test]
+    fn test_against_sorted_vec() {
+        let mut rng = XorShift64::new();
+        let mut set = OrderStatisticSet::new();
+        let mut sorted: Vec<u64> = Vec::new();
+        for _ in 0..2000 {
+            let val = rng.next_u64() % 500;
+            match sorted.binary_search(&val) {
+                Ok(i) => {
+                    assert!(set.remove(&val));
+                    sorted.remove(i);
+                }
+                Err(i) => {
+                    assert!(set.insert(val));
+                    sorted.insert(i, val);
+                }
+            }
+            let probe = rng.next_u64() % 500;
+            assert_eq!(set.rank(&probe), sorted.partition_point(|&x| x < probe));
+            if !sorted.is_empty() {
+                let k = (rng.next_u64() % sorted.len() as u64) as usize;
+                assert_eq!(set.select(k), Some(&sorted[k]));
+            }
+        }
+        assert!(set.iter().eq(sorted.iter()));
+    }
+}
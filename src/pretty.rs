@@ -0,0 +1,109 @@
+use std::fmt::{Debug, Write};
+
+/// A plain-text picture of a structure's shape, for looking at it in a
+/// terminal or in test output.
+///
+/// Lists come out on one line with arrows between the elements, binary trees
+/// sideways with the root on the left and the right subtree above it, and
+/// trees with more children per node as an indented outline.
+pub trait PrettyPrint {
+    fn pretty_print(&self) -> String;
+}
+
+/// The elements joined by `arrow`, or `(empty)` if there are none.
+pub(crate) fn arrows<T: Debug>(elems: impl IntoIterator<Item = T>, arrow: &str) -> String {
+    let mut out = String::new();
+    for (i, elem) in elems.into_iter().enumerate() {
+        if i > 0 {
+            out.push_str(arrow);
+        }
+        write!(out, "{elem:?}").unwrap();
+    }
+    if out.is_empty() {
+        out.push_str("(empty)");
+    }
+    out
+}
+
+/// A binary tree turned a quarter turn anticlockwise: one node per line,
+/// indented by its depth, in reverse order so the right subtree sits above
+/// its parent and the left one below.
+pub(crate) fn sideways<N>(root: Option<&N>, children: fn(&N) -> [Option<&N>; 2], label: fn(&N) -> String) -> String {
+    fn write_node<N>(out: &mut String, node: &N, depth: usize, children: fn(&N) -> [Option<&N>; 2], label: fn(&N) -> String) {
+        let [left, right] = children(node);
+        if let Some(right) = right {
+            write_node(out, right, depth + 1, children, label);
+        }
+        writeln!(out, "{:indent$}{}", "", label(node), indent = 4 * depth).unwrap();
+        if let Some(left) = left {
+            write_node(out, left, depth + 1, children, label);
+        }
+    }
+
+    let mut out = String::new();
+    match root {
+        Some(root) => write_node(&mut out, root, 0, children, label),
+        None => out.push_str("(empty)\n"),
+    }
+    out
+}
+
+/// A tree top-down as an outline, each node under its parent with lines
+/// joining siblings.
+pub(crate) fn outline<N>(root: &N, children: fn(&N) -> &[N], label: fn(&N) -> String) -> String {
+    fn write_children<N>(out: &mut String, node: &N, prefix: &str, children: fn(&N) -> &[N], label: fn(&N) -> String) {
+        let kids = children(node);
+        for (i, child) in kids.iter().enumerate() {
+            let last = i + 1 == kids.len();
+            writeln!(out, "{prefix}{}{}", if last { "└── " } else { "├── " }, label(child)).unwrap();
+            let prefix = format!("{prefix}{}", if last { "    " } else { "│   " });
+            write_children(out, child, &prefix, children, label);
+        }
+    }
+
+    let mut out = format!("{}\n", label(root));
+    write_children(&mut out, root, "", children, label);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{arrows, outline, sideways};
+
+    struct Node {
+        val: i32,
+        children: Vec<Node>,
+    }
+
+    fn node(val: i32, children: Vec<Node>) -> Node {
+        Node { val, children }
+    }
+
+    #[test]
+    fn test_arrows() {
+        assert_eq!(arrows([1, 2, 3], " -> "), "1 -> 2 -> 3");
+        assert_eq!(arrows(["a"], " -> "), "\"a\"");
+        assert_eq!(arrows(Vec::<i32>::new(), " -> "), "(empty)");
+    }
+
+    #[test]
+    fn test_sideways() {
+        // 2 with children 1 and 3, where 3 has only a right child 4. A 0
+        // stands in for a missing child.
+        let tree = node(2, vec![node(1, vec![]), node(3, vec![node(0, vec![]), node(4, vec![])])]);
+        let children: fn(&Node) -> [Option<&Node>; 2] = |n| match &n.children[..] {
+            [left, right] => [(left.val != 0).then_some(left), Some(right)],
+            _ => [None, None],
+        };
+        let picture = sideways(Some(&tree), children, |n| n.val.to_string());
+        assert_eq!(picture, "        4\n    3\n2\n    1\n");
+        assert_eq!(sideways(None, children, |n| n.val.to_string()), "(empty)\n");
+    }
+
+    #[test]
+    fn test_outline() {
+        let tree = node(1, vec![node(2, vec![node(4, vec![])]), node(3, vec![node(5, vec![]), node(6, vec![])])]);
+        let picture = outline(&tree, |n| &n.children, |n| n.val.to_string());
+        assert_eq!(picture, "1\n├── 2\n│   └── 4\n└── 3\n    ├── 5\n    └── 6\n");
+    }
+}
@@ -0,0 +1,306 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::hash::Hash;
+
+use crate::doubly_list::{LinkedList, NodeRef};
+use crate::hash_map::HashMap;
+use crate::heap_size::HeapSize;
+use crate::skew_heap::SkewHeap;
+
+struct Entry<K, V> {
+    value: V,
+    priority: u64,
+    expires: u64,
+    // The key's node in its eviction bucket
+    node: NodeRef<K>,
+}
+
+// A key's expiry time, ordered by the time alone so keys need not be `Ord`
+struct Expiry<K> {
+    expires: u64,
+    key: K,
+}
+
+impl<K> PartialEq for Expiry<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.expires == other.expires
+    }
+}
+
+impl<K> Eq for Expiry<K> {}
+
+impl<K> PartialOrd for Expiry<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K> Ord for Expiry<K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.expires.cmp(&other.expires)
+    }
+}
+
+/// A bounded map whose entries each carry a priority and an expiry time.
+///
+/// Time is whatever the caller measures it in, and only moves when
+/// `advance_to` is called, which drops every entry that has expired by then.
+/// An entry expires once the time reaches its expiry. When an insert finds
+/// the map full, the entry to go is the one with the lowest priority, among
+/// those the one expiring soonest, and among those the least recently used.
+///
+/// A hash map holds the entries. Keys with the same priority and expiry sit
+/// in one of the crate's `LinkedList`s in order of use, and the lists are
+/// kept in a `BTreeMap` ordered for eviction. A `SkewHeap` of expiry times
+/// finds the expired entries; entries whose expiry has since changed are
+/// skipped when they come up rather than searched for.
+pub struct PriorityExpiryMap<K, V> {
+    map: HashMap<K, Entry<K, V>>,
+    // Keys by (priority, expiry), least recently used first in each list
+    buckets: BTreeMap<(u64, u64), LinkedList<K>>,
+    expiries: SkewHeap<Expiry<K>>,
+    capacity: usize,
+    now: u64,
+}
+
+impl<K: Hash + Eq + Clone, V> PriorityExpiryMap<K, V> {
+    /// An empty map holding at most `capacity` entries, at time 0.
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "map capacity must be positive");
+        PriorityExpiryMap { map: HashMap::new(), buckets: BTreeMap::new(), expiries: SkewHeap::new(), capacity, now: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The time last passed to `advance_to`.
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.buckets.clear();
+        self.expiries.clear();
+    }
+
+    /// Look up `key`, counting it as used.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let entry = self.map.get(key)?;
+        self.buckets.get_mut(&(entry.priority, entry.expires)).unwrap().move_to_back(&entry.node);
+        Some(&entry.value)
+    }
+
+    /// Look up `key` without counting it as used.
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.map.get(key).map(|entry| &entry.value)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// The priority and expiry time of `key`.
+    pub fn priority_and_expiry(&self, key: &K) -> Option<(u64, u64)> {
+        self.map.get(key).map(|entry| (entry.priority, entry.expires))
+    }
+
+    /// Insert or replace the entry for `key`, returning the entry evicted to
+    /// make room, if any. An entry that has already expired is handed
+    /// straight back, and any old entry for `key` is removed.
+    pub fn insert(&mut self, key: K, value: V, priority: u64, expires: u64) -> Option<(K, V)> {
+        let replaced = self.remove(&key).is_some();
+        if expires <= self.now {
+            return Some((key, value));
+        }
+        let evicted = if !replaced && self.map.len() == self.capacity { self.evict() } else { None };
+        let node = self.buckets.entry((priority, expires)).or_default().push_back_node(key.clone());
+        self.expiries.push(Expiry { expires, key: key.clone() });
+        self.map.insert(key, Entry { value, priority, expires, node });
+        self.compact();
+        evicted
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    fn remove_entry(&mut self, key: &K) -> Option<(K, V)> {
+        let (key, entry) = self.map.remove_entry(key)?;
+        let bucket_key = (entry.priority, entry.expires);
+        let bucket = self.buckets.get_mut(&bucket_key).unwrap();
+        bucket.remove_node(entry.node);
+        if bucket.front_node().is_none() {
+            self.buckets.remove(&bucket_key);
+        }
+        Some((key, entry.value))
+    }
+
+    /// Remove the entry that goes first when the map is full.
+    pub fn evict(&mut self) -> Option<(K, V)> {
+        let (_, bucket) = self.buckets.first_key_value()?;
+        let key = bucket.front_node()?.borrow().data.clone();
+        self.remove_entry(&key)
+    }
+
+    /// Move the clock forward to `now` and remove every entry that has
+    /// expired by then, returning them in order of expiry. Time never runs
+    /// backwards: an earlier `now` leaves the clock where it is.
+    pub fn advance_to(&mut self, now: u64) -> Vec<(K, V)> {
+        self.now = self.now.max(now);
+        let mut expired = Vec::new();
+        while self.expiries.peek().is_some_and(|top| top.expires <= self.now) {
+            let Expiry { expires, key } = self.expiries.pop().unwrap();
+            // Skip keys that were removed or given a new expiry since
+            if self.map.get(&key).is_some_and(|entry| entry.expires == expires) {
+                expired.extend(self.remove_entry(&key));
+            }
+        }
+        expired
+    }
+
+    // Rebuild the expiry heap once stale times make up most of it
+    fn compact(&mut self) {
+        if self.expiries.len() > 2 * self.map.len() + 16 {
+            self.expiries = self.map.iter().map(|(key, entry)| Expiry { expires: entry.expires, key: key.clone() }).collect();
+        }
+    }
+}
+
+impl<K: std::fmt::Debug, V: std::fmt::Debug> std::fmt::Debug for PriorityExpiryMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.map.iter().map(|(key, entry)| (key, &entry.value))).finish()
+    }
+}
+
+// The key's node is counted in its eviction bucket
+impl<K, V: HeapSize> HeapSize for Entry<K, V> {
+    fn heap_size(&self) -> usize {
+        self.value.heap_size()
+    }
+}
+
+impl<K: HeapSize> HeapSize for Expiry<K> {
+    fn heap_size(&self) -> usize {
+        self.key.heap_size()
+    }
+}
+
+impl<K: HeapSize, V: HeapSize> HeapSize for PriorityExpiryMap<K, V> {
+    fn heap_size(&self) -> usize {
+        self.map.heap_size() + self.buckets.heap_size() + self.expiries.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heap_size::HeapSize;
+    use crate::rng::XorShift64;
+
+    use super::PriorityExpiryMap;
+
+    #[test]
+    fn test_basic_ops() {
+        let mut map = PriorityExpiryMap::new(3);
+        assert!(map.is_empty());
+        assert_eq!(map.insert("a", 1, 5, 100), None);
+        assert_eq!(map.insert("b", 2, 5, 100), None);
+        assert_eq!(map.get(&"a"), Some(&1));
+        assert_eq!(map.peek(&"c"), None);
+        // Replacing never evicts
+        assert_eq!(map.insert("a", 10, 7, 50), None);
+        assert_eq!(map.priority_and_expiry(&"a"), Some((7, 50)));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.remove(&"b"), Some(2));
+        assert_eq!(map.remove(&"b"), None);
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(map.evict(), None);
+    }
+
+    #[test]
+    fn test_eviction_order() {
+        let mut map = PriorityExpiryMap::new(4);
+        map.insert("low", (), 1, 100);
+        map.insert("soon", (), 2, 10);
+        map.insert("late", (), 2, 20);
+        map.insert("old", (), 2, 20);
+        // Lowest priority first
+        assert_eq!(map.insert("x", (), 3, 100), Some(("low", ())));
+        // Then the earliest expiry
+        assert_eq!(map.insert("y", (), 3, 100), Some(("soon", ())));
+        // Then the least recently used, after "late" is touched
+        map.get(&"late");
+        assert_eq!(map.insert("z", (), 3, 100), Some(("old", ())));
+        assert_eq!(map.evict(), Some(("late", ())));
+        assert_eq!(map.evict(), Some(("x", ())));
+    }
+
+    #[test]
+    fn test_expiry() {
+        let mut map = PriorityExpiryMap::new(10);
+        map.insert(1, "one", 0, 30);
+        map.insert(2, "two", 0, 10);
+        map.insert(3, "three", 0, 20);
+        // Pushing 2's expiry back leaves its old time stale in the heap
+        map.insert(2, "two", 0, 40);
+        assert_eq!(map.advance_to(25), vec![(3, "three")]);
+        assert_eq!(map.advance_to(5), vec![]);
+        assert_eq!(map.now(), 25);
+        // Already expired on arrival
+        assert_eq!(map.insert(4, "four", 9, 25), Some((4, "four")));
+        assert_eq!(map.advance_to(100), vec![(1, "one"), (2, "two")]);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_random_ops() {
+        let mut rng = XorShift64::new();
+        let mut map = PriorityExpiryMap::new(32);
+        let mut now = 0;
+        for _ in 0..20_000 {
+            let key = rng.next_u64() % 64;
+            match rng.next_u64() % 4 {
+                0 => {
+                    now += rng.next_u64() % 3;
+                    for (key, expires) in map.advance_to(now) {
+                        assert!(expires <= now && !map.contains_key(&key));
+                    }
+                }
+                1 => {
+                    map.remove(&key);
+                }
+                _ => {
+                    let expires = now + 1 + rng.next_u64() % 50;
+                    if let Some((evicted, _)) = map.insert(key, expires, rng.next_u64() % 4, expires) {
+                        assert_ne!(evicted, key);
+                    }
+                }
+            }
+            assert!(map.len() <= 32);
+            // Stale expiry times are cleared out before they pile up
+            assert!(map.expiries.len() <= 2 * 32 + 16);
+            assert!(map.buckets.len() <= map.len());
+            assert!(map.buckets.values().all(|bucket| bucket.front_node().is_some()));
+        }
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let mut map = PriorityExpiryMap::new(4);
+        let empty = map.heap_size();
+        map.insert(1u32, String::from("abc"), 1, 10);
+        assert!(map.heap_size() > empty + 3);
+    }
+}
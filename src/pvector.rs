@@ -0,0 +1,431 @@
+use std::fmt::Debug;
+use std::ops::Index;
+use std::rc::Rc;
+
+use crate::error::{CollectionError, check_index};
+use crate::heap_size::{HeapSize, rc_size};
+
+const BITS: u32 = 5;
+const WIDTH: usize = 1 << BITS;
+const MASK: usize = WIDTH - 1;
+
+enum Node<T> {
+    Branch(Vec<Rc<Node<T>>>),
+    // Always exactly WIDTH elements
+    Leaf(Vec<T>),
+}
+
+impl<T: Clone> Node<T> {
+    fn empty() -> Rc<Self> {
+        Rc::new(Node::Branch(Vec::new()))
+    }
+
+    /// A chain of single-child branches from `level` down to `leaf`.
+    fn path(level: u32, leaf: Rc<Node<T>>) -> Rc<Node<T>> {
+        if level == 0 {
+            return leaf;
+        }
+        Rc::new(Node::Branch(vec![Node::path(level - BITS, leaf)]))
+    }
+
+    fn children(&self) -> &Vec<Rc<Node<T>>> {
+        match self {
+            Node::Branch(children) => children,
+            Node::Leaf(_) => unreachable!("leaf above level 0"),
+        }
+    }
+
+    /// Check that leaves are full and all at level 0, and that every branch
+    /// has 1 to 32 children, all full but the last. Returns the number of
+    /// elements in the subtree.
+    #[cfg(any(test, debug_assertions))]
+    fn validate(&self, level: u32) -> usize {
+        match self {
+            Node::Leaf(elems) => {
+                assert_eq!(level, 0, "leaf above level 0");
+                assert_eq!(elems.len(), WIDTH, "partial leaf");
+                WIDTH
+            }
+            Node::Branch(children) => {
+                assert!(level > 0, "branch at level 0");
+                assert!((1..=WIDTH).contains(&children.len()), "branch of {} children", children.len());
+                let mut count = 0;
+                for (i, child) in children.iter().enumerate() {
+                    let n = child.validate(level - BITS);
+                    assert!(i + 1 == children.len() || n == 1 << level, "gap before the last child");
+                    count += n;
+                }
+                count
+            }
+        }
+    }
+
+    /// Copy of this subtree with `leaf` appended as element block `index`.
+    fn push_leaf(&self, level: u32, index: usize, leaf: Rc<Node<T>>) -> Rc<Node<T>> {
+        let mut children = self.children().clone();
+        let sub = (index >> level) & MASK;
+        if level == BITS {
+            children.push(leaf);
+        } else if let Some(child) = children.get(sub) {
+            children[sub] = child.push_leaf(level - BITS, index, leaf);
+        } else {
+            children.push(Node::path(level - BITS, leaf));
+        }
+        Rc::new(Node::Branch(children))
+    }
+
+    /// Copy of this subtree without its last leaf, which holds element
+    /// `index`. Returns `None` if that leaves the subtree empty.
+    fn pop_leaf(&self, level: u32, index: usize) -> Option<Rc<Node<T>>> {
+        let sub = (index >> level) & MASK;
+        let mut children = self.children().clone();
+        let child = if level > BITS { children[sub].pop_leaf(level - BITS, index) } else { None };
+        match child {
+            Some(child) => children[sub] = child,
+            None if sub == 0 => return None,
+            None => children.truncate(sub),
+        }
+        Some(Rc::new(Node::Branch(children)))
+    }
+
+    /// Copy of this subtree with element `index` replaced.
+    fn update(&self, level: u32, index: usize, val: T) -> Rc<Node<T>> {
+        match self {
+            Node::Leaf(elems) => {
+                let mut elems = elems.clone();
+                elems[index & MASK] = val;
+                Rc::new(Node::Leaf(elems))
+            }
+            Node::Branch(children) => {
+                let mut children = children.clone();
+                let sub = (index >> level) & MASK;
+                children[sub] = children[sub].update(level - BITS, index, val);
+                Rc::new(Node::Branch(children))
+            }
+        }
+    }
+}
+
+/// An immutable vector: a 32-way trie over the indices, plus the last
+/// partial block of elements kept outside the trie as the "tail".
+///
+/// `get`, `update`, `push_back` and `pop_back` take O(log32 n) and never
+/// change `self`; they return a new version that shares every untouched node
+/// with the old one. Cloning is O(1). Appending usually only copies the
+/// tail, which holds at most 32 elements.
+pub struct PVector<T> {
+    len: usize,
+    // Level of the root's children; leaves are at level 0
+    shift: u32,
+    root: Rc<Node<T>>,
+    tail: Rc<Vec<T>>,
+}
+
+impl<T: Clone> PVector<T> {
+    pub fn new() -> Self {
+        PVector { len: 0, shift: BITS, root: Node::empty(), tail: Rc::new(Vec::new()) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Panic unless the tail holds 1 to 32 elements (none if empty), the
+    /// trie holds every other element in full leaves packed to the left,
+    /// and the trie is no taller than it needs to be. Only in debug builds
+    /// and tests.
+    #[cfg(any(test, debug_assertions))]
+    pub fn validate(&self) {
+        assert_eq!(self.tail.is_empty(), self.len == 0, "empty tail");
+        assert!(self.tail.len() <= WIDTH, "tail longer than a leaf");
+        if self.tail_offset() == 0 {
+            assert!(self.root.children().is_empty() && self.shift == BITS, "leftover trie");
+            return;
+        }
+        assert_eq!(self.root.validate(self.shift), self.tail_offset(), "trie length is out of date");
+        assert!(self.shift == BITS || self.root.children().len() > 1, "trie taller than needed");
+    }
+
+    // Index of the first element in the tail
+    fn tail_offset(&self) -> usize {
+        self.len - self.tail.len()
+    }
+
+    /// The block of up to 32 elements containing element `index`.
+    fn block(&self, index: usize) -> &[T] {
+        if index >= self.tail_offset() {
+            return &self.tail;
+        }
+        let mut node = &self.root;
+        let mut level = self.shift;
+        loop {
+            match node.as_ref() {
+                Node::Branch(children) => node = &children[(index >> level) & MASK],
+                Node::Leaf(elems) => return elems,
+            }
+            level -= BITS;
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len {
+            return None;
+        }
+        Some(&self.block(index)[index & MASK])
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.tail.last()
+    }
+
+    /// A new version with element `index` replaced by `val`.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn update(&self, index: usize, val: T) -> Self {
+        assert!(index < self.len, "index {index} out of bounds for length {}", self.len);
+        if index >= self.tail_offset() {
+            let mut tail = self.tail.as_ref().clone();
+            tail[index - self.tail_offset()] = val;
+            return PVector { tail: Rc::new(tail), root: self.root.clone(), ..*self };
+        }
+        PVector { root: self.root.update(self.shift, index, val), tail: self.tail.clone(), ..*self }
+    }
+
+    /// `update`, but returning an error if `index` is out of bounds.
+    pub fn try_update(&self, index: usize, val: T) -> Result<Self, CollectionError> {
+        check_index(index, self.len)?;
+        Ok(self.update(index, val))
+    }
+
+    /// A new version with `val` appended.
+    pub fn push_back(&self, val: T) -> Self {
+        if self.tail.len() < WIDTH {
+            let mut tail = Vec::with_capacity(WIDTH);
+            tail.extend_from_slice(&self.tail);
+            tail.push(val);
+            return PVector { len: self.len + 1, tail: Rc::new(tail), root: self.root.clone(), ..*self };
+        }
+        // The tail is full: move it into the trie and start a new one
+        let leaf = Rc::new(Node::Leaf(self.tail.as_ref().clone()));
+        let index = self.tail_offset();
+        let (root, shift) = if index >> BITS == 1 << self.shift {
+            // No room under the current root; grow the trie a level
+            let children = vec![self.root.clone(), Node::path(self.shift, leaf)];
+            (Rc::new(Node::Branch(children)), self.shift + BITS)
+        } else {
+            (self.root.push_leaf(self.shift, index, leaf), self.shift)
+        };
+        let mut tail = Vec::with_capacity(WIDTH);
+        tail.push(val);
+        PVector { len: self.len + 1, shift, root, tail: Rc::new(tail) }
+    }
+
+    /// A new version without the last element, or `None` if empty.
+    pub fn pop_back(&self) -> Option<Self> {
+        match self.len {
+            0 => return None,
+            1 => return Some(PVector::new()),
+            _ => {}
+        }
+        if self.tail.len() > 1 {
+            let tail = self.tail[..self.tail.len() - 1].to_vec();
+            return Some(PVector { len: self.len - 1, tail: Rc::new(tail), root: self.root.clone(), ..*self });
+        }
+        // The tail empties: the trie's last leaf becomes the new tail
+        let index = self.len - 2;
+        let tail = self.block(index).to_vec();
+        let mut root = self.root.pop_leaf(self.shift, index).unwrap_or_else(Node::empty);
+        let mut shift = self.shift;
+        if shift > BITS && root.children().len() == 1 {
+            root = root.children()[0].clone();
+            shift -= BITS;
+        }
+        Some(PVector { len: self.len - 1, shift, root, tail: Rc::new(tail) })
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { vec: self, index: 0, block: &[] }
+    }
+}
+
+impl<T> Clone for PVector<T> {
+    fn clone(&self) -> Self {
+        PVector { len: self.len, shift: self.shift, root: self.root.clone(), tail: self.tail.clone() }
+    }
+}
+
+impl<T: Clone> Default for PVector<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> Index<usize> for PVector<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        self.get(index).expect("index out of bounds")
+    }
+}
+
+pub struct Iter<'a, T> {
+    vec: &'a PVector<T>,
+    index: usize,
+    // Rest of the current block
+    block: &'a [T],
+}
+
+impl<'a, T: Clone> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.block.is_empty() {
+            if self.index >= self.vec.len {
+                return None;
+            }
+            self.block = &self.vec.block(self.index)[self.index & MASK..];
+        }
+        let (first, rest) = self.block.split_first()?;
+        self.block = rest;
+        self.index += 1;
+        Some(first)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.vec.len - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<T: Clone> ExactSizeIterator for Iter<'_, T> {}
+
+impl<'a, T: Clone> IntoIterator for &'a PVector<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Clone> FromIterator<T> for PVector<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vec = PVector::new();
+        for val in iter {
+            vec = vec.push_back(val);
+        }
+        vec
+    }
+}
+
+impl<T: Clone + PartialEq> PartialEq for PVector<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Clone + Eq> Eq for PVector<T> {}
+
+impl<T: Clone + Debug> Debug for PVector<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Node<T> {
+    fn heap_size(&self) -> usize {
+        match self {
+            Node::Branch(children) => {
+                children.capacity() * size_of::<Rc<Node<T>>>()
+                    + children.iter().map(|child| rc_size::<Node<T>>() + child.heap_size()).sum::<usize>()
+            }
+            Node::Leaf(elems) => elems.heap_size(),
+        }
+    }
+}
+
+/// Counts every node the vector can reach, including ones it shares with
+/// other versions.
+impl<T: HeapSize> HeapSize for PVector<T> {
+    fn heap_size(&self) -> usize {
+        rc_size::<Node<T>>() + self.root.heap_size() + rc_size::<Vec<T>>() + self.tail.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::CollectionError;
+
+    use super::PVector;
+
+    #[test]
+    fn test_push_get() {
+        let mut vec = PVector::new();
+        assert_eq!(vec.get(0), None);
+        // Enough elements for a three-level trie
+        for i in 0..40_000 {
+            vec = vec.push_back(i);
+        }
+        assert_eq!(vec.len(), 40_000);
+        vec.validate();
+        assert!((0..40_000).all(|i| vec[i] == i));
+        assert_eq!(vec.get(40_000), None);
+        assert_eq!(vec.first(), Some(&0));
+        assert_eq!(vec.last(), Some(&39_999));
+        assert!(vec.iter().copied().eq(0..40_000));
+        assert_eq!(vec.iter().len(), 40_000);
+    }
+
+    #[test]
+    fn test_versions_are_independent() {
+        let v1: PVector<i32> = (0..100).collect();
+        let v2 = v1.update(3, -3).update(99, -99);
+        let v3 = v1.push_back(100);
+        let v4 = v1.pop_back().unwrap();
+
+        for v in [&v1, &v2, &v3, &v4] {
+            v.validate();
+        }
+        assert!(v1.iter().copied().eq(0..100));
+        assert_eq!((v2[3], v2[99], v2[4]), (-3, -99, 4));
+        assert_eq!((v3.len(), v3[100]), (101, 100));
+        assert_eq!((v4.len(), v4.last()), (99, Some(&98)));
+        assert_ne!(v1, v2);
+        assert_eq!(v1, v1.clone());
+        assert_eq!(v1.try_update(100, 0), Err(CollectionError::IndexOutOfBounds { index: 100, len: 100 }));
+        assert_eq!(v1.try_update(0, -1).map(|v| v[0]), Ok(-1));
+    }
+
+    #[test]
+    fn test_pop_back() {
+        let mut vec: PVector<usize> = (0..33 * 32 + 5).collect();
+        let mut len = vec.len();
+        while let Some(shorter) = vec.pop_back() {
+            len -= 1;
+            vec = shorter;
+            assert_eq!(vec.len(), len);
+            assert_eq!(vec.last(), len.checked_sub(1).as_ref());
+            vec.validate();
+        }
+        assert!(vec.is_empty());
+        // A fully popped vector grows again from scratch
+        let vec: PVector<usize> = (0..1100).fold(vec, |v, i| v.push_back(i));
+        vec.validate();
+        assert!(vec.iter().copied().eq(0..1100));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_update_out_of_bounds() {
+        let vec: PVector<i32> = (0..10).collect();
+        vec.update(10, 0);
+    }
+}
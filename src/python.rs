@@ -0,0 +1,280 @@
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+use pyo3::exceptions::{PyIndexError, PyKeyError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyIterator, PyList};
+
+use crate::arc_cache::ArcCache;
+use crate::btree::BTreeMap;
+use crate::cache::Cache;
+use crate::doubly_list::LinkedList;
+use crate::sorted_vec_map::SortedVecMap;
+use crate::two_q_cache::TwoQCache;
+
+// Python classes for the `python` feature, in a module named `rust01`. They
+// hold Python objects and follow the protocols of the built-in containers,
+// so they can stand in for a `collections.deque` or a `dict`. The underlying
+// structures are `Rc`-based, so the classes are unsendable: using one from
+// another thread than the one that made it raises an error.
+
+/// A Python object used as a map or cache key, ordered, compared and hashed
+/// by Python.
+///
+/// Keys that Python can't compare or hash make the operation raise a
+/// `PanicException`, so a map's keys should all be of one comparable type.
+struct PyKey(Py<PyAny>);
+
+impl PartialEq for PyKey {
+    fn eq(&self, other: &Self) -> bool {
+        Python::attach(|py| self.0.bind(py).eq(other.0.bind(py)).expect("map keys must be comparable"))
+    }
+}
+
+impl Eq for PyKey {}
+
+impl PartialOrd for PyKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PyKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        Python::attach(|py| self.0.bind(py).compare(other.0.bind(py)).expect("map keys must be comparable"))
+    }
+}
+
+impl Hash for PyKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Python::attach(|py| self.0.bind(py).hash().expect("cache keys must be hashable")).hash(state);
+    }
+}
+
+impl Clone for PyKey {
+    fn clone(&self) -> Self {
+        Python::attach(|py| PyKey(self.0.clone_ref(py)))
+    }
+}
+
+fn key_error(key: &Bound<'_, PyAny>) -> PyErr {
+    PyKeyError::new_err(key.clone().unbind())
+}
+
+/// A `LinkedList` with the methods of `collections.deque`.
+#[pyclass(unsendable, name = "Deque")]
+#[derive(Default)]
+pub struct PyDeque {
+    list: LinkedList<Py<PyAny>>,
+}
+
+#[pymethods]
+impl PyDeque {
+    #[new]
+    fn new() -> Self {
+        PyDeque { list: LinkedList::new() }
+    }
+
+    fn __len__(&self) -> usize {
+        self.list.len()
+    }
+
+    /// Element `index`, counting from the back if it is negative. Takes
+    /// O(n).
+    fn __getitem__(&self, py: Python<'_>, index: isize) -> PyResult<Py<PyAny>> {
+        let len = self.list.len() as isize;
+        let index = if index < 0 { index + len } else { index };
+        if !(0..len).contains(&index) {
+            return Err(PyIndexError::new_err("deque index out of range"));
+        }
+        Ok(self.list.iter().nth(index as usize).unwrap().clone_ref(py))
+    }
+
+    /// Iterate over a snapshot of the elements, so the deque can be changed
+    /// while iterating.
+    fn __iter__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyIterator>> {
+        PyList::new(py, self.list.iter().map(|val| val.clone_ref(py)))?.try_iter()
+    }
+
+    fn append(&mut self, val: Py<PyAny>) {
+        self.list.push_back(val);
+    }
+
+    fn appendleft(&mut self, val: Py<PyAny>) {
+        self.list.push_front(val);
+    }
+
+    fn pop(&mut self) -> PyResult<Py<PyAny>> {
+        self.list.pop_back().ok_or_else(|| PyIndexError::new_err("pop from an empty deque"))
+    }
+
+    fn popleft(&mut self) -> PyResult<Py<PyAny>> {
+        self.list.pop_front().ok_or_else(|| PyIndexError::new_err("pop from an empty deque"))
+    }
+
+    fn clear(&mut self) {
+        self.list = LinkedList::new();
+    }
+}
+
+/// One of the crate's caches, used like a `dict`. The crate has no plain
+/// LRU cache, so the policy is `"2q"` for `TwoQCache` or `"arc"` for
+/// `ArcCache`.
+#[pyclass(unsendable, name = "Cache")]
+pub struct PyCache {
+    cache: Box<dyn Cache<PyKey, Py<PyAny>>>,
+}
+
+#[pymethods]
+impl PyCache {
+    #[new]
+    #[pyo3(signature = (capacity, policy = "2q"))]
+    fn new(capacity: usize, policy: &str) -> PyResult<Self> {
+        if capacity == 0 {
+            return Err(PyValueError::new_err("cache capacity must be positive"));
+        }
+        let cache: Box<dyn Cache<PyKey, Py<PyAny>>> = match policy {
+            "2q" => Box::new(TwoQCache::new(capacity)),
+            "arc" => Box::new(ArcCache::new(capacity)),
+            _ => return Err(PyValueError::new_err(format!("unknown cache policy {policy:?}"))),
+        };
+        Ok(PyCache { cache })
+    }
+
+    #[getter]
+    fn capacity(&self) -> usize {
+        self.cache.capacity()
+    }
+
+    fn __len__(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// The value for `key`, counting as a use.
+    fn __getitem__(&mut self, key: &Bound<'_, PyAny>) -> PyResult<Py<PyAny>> {
+        match self.cache.get(&PyKey(key.clone().unbind())) {
+            Some(val) => Ok(val.clone_ref(key.py())),
+            None => Err(key_error(key)),
+        }
+    }
+
+    fn __setitem__(&mut self, key: Py<PyAny>, val: Py<PyAny>) {
+        self.cache.insert(PyKey(key), val);
+    }
+
+    fn __delitem__(&mut self, key: &Bound<'_, PyAny>) -> PyResult<()> {
+        match self.cache.remove(&PyKey(key.clone().unbind())) {
+            Some(_) => Ok(()),
+            None => Err(key_error(key)),
+        }
+    }
+
+    /// Whether `key` is cached, without counting as a use.
+    fn __contains__(&self, key: Py<PyAny>) -> bool {
+        self.cache.contains_key(&PyKey(key))
+    }
+
+    /// The value for `key` without counting as a use, or `default`.
+    #[pyo3(signature = (key, default = None))]
+    fn peek(&self, py: Python<'_>, key: Py<PyAny>, default: Option<Py<PyAny>>) -> Option<Py<PyAny>> {
+        self.cache.peek(&PyKey(key)).map(|val| val.clone_ref(py)).or(default)
+    }
+
+    fn clear(&mut self) {
+        self.cache.clear();
+    }
+}
+
+enum SortedMapImpl {
+    BTree(BTreeMap<PyKey, Py<PyAny>, 16>),
+    Vec(SortedVecMap<PyKey, Py<PyAny>>),
+}
+
+/// One of the crate's sorted maps, used like a `dict` that iterates in key
+/// order. `kind` is `"btree"` for a `BTreeMap` or `"vec"` for a
+/// `SortedVecMap`.
+#[pyclass(unsendable, name = "SortedMap")]
+pub struct PySortedMap {
+    map: SortedMapImpl,
+}
+
+impl PySortedMap {
+    fn get(&self, key: &PyKey) -> Option<&Py<PyAny>> {
+        match &self.map {
+            SortedMapImpl::BTree(map) => map.get(key),
+            SortedMapImpl::Vec(map) => map.get(key),
+        }
+    }
+
+    fn entries(&self) -> Vec<(&PyKey, &Py<PyAny>)> {
+        match &self.map {
+            SortedMapImpl::BTree(map) => map.iter().collect(),
+            SortedMapImpl::Vec(map) => map.iter().collect(),
+        }
+    }
+}
+
+#[pymethods]
+impl PySortedMap {
+    #[new]
+    #[pyo3(signature = (kind = "btree"))]
+    fn new(kind: &str) -> PyResult<Self> {
+        let map = match kind {
+            "btree" => SortedMapImpl::BTree(BTreeMap::new()),
+            "vec" => SortedMapImpl::Vec(SortedVecMap::new()),
+            _ => return Err(PyValueError::new_err(format!("unknown sorted map kind {kind:?}"))),
+        };
+        Ok(PySortedMap { map })
+    }
+
+    fn __len__(&self) -> usize {
+        match &self.map {
+            SortedMapImpl::BTree(map) => map.len(),
+            SortedMapImpl::Vec(map) => map.len(),
+        }
+    }
+
+    fn __getitem__(&self, key: &Bound<'_, PyAny>) -> PyResult<Py<PyAny>> {
+        match self.get(&PyKey(key.clone().unbind())) {
+            Some(val) => Ok(val.clone_ref(key.py())),
+            None => Err(key_error(key)),
+        }
+    }
+
+    fn __setitem__(&mut self, key: Py<PyAny>, val: Py<PyAny>) {
+        match &mut self.map {
+            SortedMapImpl::BTree(map) => map.insert(PyKey(key), val),
+            SortedMapImpl::Vec(map) => map.insert(PyKey(key), val),
+        };
+    }
+
+    fn __delitem__(&mut self, key: &Bound<'_, PyAny>) -> PyResult<()> {
+        let removed = match &mut self.map {
+            SortedMapImpl::BTree(map) => map.remove(&PyKey(key.clone().unbind())),
+            SortedMapImpl::Vec(map) => map.remove(&PyKey(key.clone().unbind())),
+        };
+        removed.map(drop).ok_or_else(|| key_error(key))
+    }
+
+    fn __contains__(&self, key: Py<PyAny>) -> bool {
+        self.get(&PyKey(key)).is_some()
+    }
+
+    /// Iterate over a snapshot of the keys, in order.
+    fn __iter__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyIterator>> {
+        PyList::new(py, self.entries().into_iter().map(|(key, _)| key.0.clone_ref(py)))?.try_iter()
+    }
+
+    /// The `(key, value)` pairs, in key order.
+    fn items<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyList>> {
+        PyList::new(py, self.entries().into_iter().map(|(key, val)| (key.0.clone_ref(py), val.clone_ref(py))))
+    }
+}
+
+#[pymodule]
+fn rust01(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyDeque>()?;
+    module.add_class::<PyCache>()?;
+    module.add_class::<PySortedMap>()?;
+    Ok(())
+}
@@ -0,0 +1,326 @@
+use std::fmt::Debug;
+
+use crate::heap_size::HeapSize;
+
+/// An axis-aligned rectangle, bounds included.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub min: [f64; 2],
+    pub max: [f64; 2],
+}
+
+impl Rect {
+    pub fn new(min: [f64; 2], max: [f64; 2]) -> Self {
+        Rect { min, max }
+    }
+
+    pub fn contains(&self, p: &[f64; 2]) -> bool {
+        self.min[0] <= p[0] && p[0] <= self.max[0] && self.min[1] <= p[1] && p[1] <= self.max[1]
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.min[0] <= other.max[0]
+            && other.min[0] <= self.max[0]
+            && self.min[1] <= other.max[1]
+            && other.min[1] <= self.max[1]
+    }
+
+    /// The smallest rectangle containing both `self` and `other`.
+    pub fn union(&self, other: &Rect) -> Rect {
+        Rect::new(
+            [self.min[0].min(other.min[0]), self.min[1].min(other.min[1])],
+            [self.max[0].max(other.max[0]), self.max[1].max(other.max[1])],
+        )
+    }
+
+    pub fn center(&self) -> [f64; 2] {
+        [(self.min[0] + self.max[0]) / 2.0, (self.min[1] + self.max[1]) / 2.0]
+    }
+
+    /// The four quadrants, in the order `quadrant` indexes them.
+    fn split(&self) -> [Rect; 4] {
+        let [cx, cy] = self.center();
+        [
+            Rect::new(self.min, [cx, cy]),
+            Rect::new([cx, self.min[1]], [self.max[0], cy]),
+            Rect::new([self.min[0], cy], [cx, self.max[1]]),
+            Rect::new([cx, cy], self.max),
+        ]
+    }
+
+    fn quadrant(&self, p: &[f64; 2]) -> usize {
+        let [cx, cy] = self.center();
+        (p[0] >= cx) as usize + 2 * (p[1] >= cy) as usize
+    }
+}
+
+struct Node<T> {
+    bounds: Rect,
+    depth: usize,
+    // Items are only stored in leaves.
+    items: Vec<([f64; 2], T)>,
+    children: Option<Box<[Node<T>; 4]>>,
+}
+
+impl<T> Node<T> {
+    fn new(bounds: Rect, depth: usize) -> Self {
+        Node { bounds, depth, items: Vec::new(), children: None }
+    }
+
+    fn insert(&mut self, p: [f64; 2], value: T, capacity: usize, max_depth: usize) {
+        if let Some(children) = &mut self.children {
+            let q = self.bounds.quadrant(&p);
+            children[q].insert(p, value, capacity, max_depth);
+            return;
+        }
+        self.items.push((p, value));
+        if self.items.len() > capacity && self.depth < max_depth {
+            let mut children = Box::new(self.bounds.split().map(|b| Node::new(b, self.depth + 1)));
+            for (p, value) in self.items.drain(..) {
+                children[self.bounds.quadrant(&p)].insert(p, value, capacity, max_depth);
+            }
+            self.children = Some(children);
+        }
+    }
+
+    fn remove(&mut self, p: &[f64; 2], capacity: usize) -> Option<T> {
+        let Some(children) = &mut self.children else {
+            let i = self.items.iter().position(|(q, _)| q == p)?;
+            return Some(self.items.swap_remove(i).1);
+        };
+        let removed = children[self.bounds.quadrant(p)].remove(p, capacity)?;
+        // Collapse back into a leaf once the children hold few enough items
+        if children.iter().all(|c| c.children.is_none())
+            && children.iter().map(|c| c.items.len()).sum::<usize>() <= capacity
+        {
+            let children = self.children.take().unwrap();
+            for child in *children {
+                self.items.extend(child.items);
+            }
+        }
+        Some(removed)
+    }
+
+    /// Check that only leaves hold items, each inside the leaf's bounds,
+    /// that a leaf is over capacity only at `max_depth`, and that children
+    /// are this node's quadrants one level down. Returns the number of items
+    /// under the node.
+    #[cfg(any(test, debug_assertions))]
+    fn validate(&self, capacity: usize, max_depth: usize) -> usize {
+        assert!(self.depth <= max_depth, "node below max_depth");
+        let Some(children) = &self.children else {
+            assert!(self.items.iter().all(|(p, _)| self.bounds.contains(p)), "item outside its leaf");
+            assert!(self.items.len() <= capacity || self.depth == max_depth, "leaf over capacity");
+            return self.items.len();
+        };
+        assert!(self.items.is_empty(), "items in an internal node");
+        let mut count = 0;
+        for (child, bounds) in children.iter().zip(self.bounds.split()) {
+            assert!(child.bounds == bounds && child.depth == self.depth + 1, "child is not a quadrant");
+            count += child.validate(capacity, max_depth);
+        }
+        count
+    }
+
+    fn query<'a>(&'a self, region: &Rect, out: &mut Vec<(&'a [f64; 2], &'a T)>) {
+        if !self.bounds.intersects(region) {
+            return;
+        }
+        match &self.children {
+            Some(children) => children.iter().for_each(|c| c.query(region, out)),
+            None => out.extend(self.items.iter().filter(|(p, _)| region.contains(p)).map(|(p, v)| (p, v))),
+        }
+    }
+}
+
+/// A point quadtree over a fixed rectangular region.
+///
+/// A leaf holding more than `capacity` points is subdivided into four
+/// quadrants, unless it is already `max_depth` levels deep (which bounds the
+/// depth when many points share the same position).
+pub struct Quadtree<T> {
+    root: Node<T>,
+    capacity: usize,
+    max_depth: usize,
+    len: usize,
+}
+
+impl<T> Quadtree<T> {
+    pub fn new(bounds: Rect) -> Self {
+        Self::with_limits(bounds, 8, 16)
+    }
+
+    /// Panics if `capacity` is zero.
+    pub fn with_limits(bounds: Rect, capacity: usize, max_depth: usize) -> Self {
+        assert!(capacity > 0, "quadtree node capacity must be at least 1");
+        Quadtree { root: Node::new(bounds, 0), capacity, max_depth, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Panic unless every item sits in the leaf whose bounds contain it, no
+    /// leaf above `max_depth` is over capacity, and `len` is right. Only in
+    /// debug builds and tests.
+    #[cfg(any(test, debug_assertions))]
+    pub fn validate(&self) {
+        assert_eq!(self.root.validate(self.capacity, self.max_depth), self.len, "tree length is out of date");
+    }
+
+    pub fn bounds(&self) -> Rect {
+        self.root.bounds
+    }
+
+    /// Insert a value at point `p`. Gives the value back if `p` lies outside
+    /// the tree's bounds.
+    pub fn insert(&mut self, p: [f64; 2], value: T) -> Result<(), T> {
+        if !self.root.bounds.contains(&p) {
+            return Err(value);
+        }
+        self.root.insert(p, value, self.capacity, self.max_depth);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Remove one value stored at exactly point `p`.
+    pub fn remove(&mut self, p: &[f64; 2]) -> Option<T> {
+        let removed = self.root.remove(p, self.capacity)?;
+        self.len -= 1;
+        Some(removed)
+    }
+
+    /// All points inside `region`.
+    pub fn query(&self, region: &Rect) -> Vec<(&[f64; 2], &T)> {
+        let mut out = Vec::new();
+        self.root.query(region, &mut out);
+        out
+    }
+
+    /// Number of levels below the root that have been created.
+    pub fn depth(&self) -> usize {
+        fn depth<T>(node: &Node<T>) -> usize {
+            node.children.as_ref().map_or(0, |c| 1 + c.iter().map(depth).max().unwrap_or(0))
+        }
+        depth(&self.root)
+    }
+}
+
+impl<T: Debug> Debug for Quadtree<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.query(&self.root.bounds)).finish()
+    }
+}
+
+impl HeapSize for Rect {}
+
+impl<T: HeapSize> HeapSize for Node<T> {
+    fn heap_size(&self) -> usize {
+        self.items.heap_size() + self.children.heap_size()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Quadtree<T> {
+    fn heap_size(&self) -> usize {
+        self.root.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heap_size::HeapSize;
+
+    use super::{Quadtree, Rect};
+
+    fn world() -> Rect {
+        Rect::new([0.0, 0.0], [100.0, 100.0])
+    }
+
+    #[test]
+    fn test_new() {
+        let tree: Quadtree<i32> = Quadtree::new(world());
+        assert_eq!(tree.len(), 0);
+        assert!(tree.is_empty());
+        assert_eq!(tree.depth(), 0);
+        assert!(tree.query(&world()).is_empty());
+    }
+
+    #[test]
+    fn test_insert_query() {
+        let mut tree = Quadtree::with_limits(world(), 2, 8);
+        assert_eq!(tree.insert([10.0, 10.0], "a"), Ok(()));
+        assert_eq!(tree.insert([20.0, 80.0], "b"), Ok(()));
+        assert_eq!(tree.insert([70.0, 30.0], "c"), Ok(()));
+        assert_eq!(tree.insert([75.0, 35.0], "d"), Ok(()));
+        assert_eq!(tree.insert([150.0, 10.0], "e"), Err("e"));
+        assert_eq!(tree.len(), 4);
+        assert!(tree.depth() > 0);
+        tree.validate();
+
+        let mut found: Vec<_> = tree.query(&Rect::new([60.0, 20.0], [80.0, 40.0])).into_iter().map(|(_, v)| *v).collect();
+        found.sort();
+        assert_eq!(found, vec!["c", "d"]);
+        assert_eq!(tree.query(&world()).len(), 4);
+        assert!(tree.query(&Rect::new([40.0, 40.0], [60.0, 60.0])).is_empty());
+    }
+
+    #[test]
+    fn test_remove_collapses() {
+        let mut tree = Quadtree::with_limits(world(), 2, 8);
+        for i in 0..10 {
+            tree.insert([i as f64 * 10.0, i as f64 * 10.0], i).unwrap();
+        }
+        assert!(tree.depth() > 0);
+        for i in 0..8 {
+            assert_eq!(tree.remove(&[i as f64 * 10.0, i as f64 * 10.0]), Some(i));
+            tree.validate();
+        }
+        assert_eq!(tree.remove(&[0.0, 0.0]), None);
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree.depth(), 0);
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let mut tree = Quadtree::with_limits(world(), 1, 3);
+        for i in 0..20 {
+            tree.insert([42.0, 42.0], i).unwrap();
+        }
+        assert_eq!(tree.depth(), 3);
+        tree.validate();
+        assert_eq!(tree.query(&Rect::new([42.0, 42.0], [42.0, 42.0])).len(), 20);
+    }
+
+    #[test]
+    fn test_query_matches_naive() {
+        let mut tree = Quadtree::with_limits(world(), 4, 10);
+        let mut points = Vec::new();
+        for i in 0..400 {
+            let p = [((i * 37) % 100) as f64, ((i * 91) % 100) as f64];
+            tree.insert(p, i).unwrap();
+            points.push(p);
+        }
+        tree.validate();
+        for j in 0..20 {
+            let min = [(j * 5) as f64, (j * 3) as f64];
+            let region = Rect::new(min, [min[0] + 25.0, min[1] + 40.0]);
+            let expected = points.iter().filter(|p| region.contains(p)).count();
+            assert_eq!(tree.query(&region).len(), expected);
+        }
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let mut tree = Quadtree::with_limits(world(), 2, 8);
+        let empty = tree.heap_size();
+        for i in 0..10 {
+            tree.insert([i as f64 * 9.0, i as f64 * 9.0], i).unwrap();
+        }
+        // Splitting the root adds at least its four children
+        assert!(tree.heap_size() >= empty + 10 * size_of::<([f64; 2], i32)>() + 4 * size_of::<super::Node<i32>>());
+    }
+}
@@ -0,0 +1,311 @@
+use std::fmt::Debug;
+
+use crate::heap_size::HeapSize;
+
+struct Node<V> {
+    // Edge label leading into this node; empty only at the root.
+    label: Vec<u8>,
+    value: Option<V>,
+    // Sorted by the first byte of their labels, which are all distinct.
+    children: Vec<Node<V>>,
+}
+
+impl<V> Node<V> {
+    fn new(label: Vec<u8>, value: Option<V>) -> Self {
+        Node { label, value, children: Vec::new() }
+    }
+
+    fn child_index(&self, byte: u8) -> Result<usize, usize> {
+        self.children.binary_search_by_key(&byte, |child| child.label[0])
+    }
+
+    /// Absorb the only child of a valueless node, keeping paths compressed.
+    fn compress(&mut self) {
+        if self.value.is_none() && self.children.len() == 1 {
+            let child = self.children.pop().unwrap();
+            self.label.extend(child.label);
+            self.value = child.value;
+            self.children = child.children;
+        }
+    }
+}
+
+fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+/// A compressed (radix / Patricia) trie mapping byte-string keys to values.
+///
+/// Chains of single-child nodes are collapsed into one edge, so lookups touch
+/// one node per branching point rather than one per byte. The main operation
+/// on top of the usual map API is `longest_prefix_match`.
+pub struct RadixTrie<V> {
+    root: Node<V>,
+    len: usize,
+}
+
+impl<V> RadixTrie<V> {
+    pub fn new() -> Self {
+        RadixTrie { root: Node::new(Vec::new(), None), len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Insert a key-value pair, returning the previous value if the key was
+    /// already present.
+    pub fn insert(&mut self, key: &[u8], value: V) -> Option<V> {
+        let mut node = &mut self.root;
+        let mut key = key;
+        loop {
+            let Some(&first) = key.first() else {
+                let old = node.value.replace(value);
+                if old.is_none() {
+                    self.len += 1;
+                }
+                return old;
+            };
+            let i = match node.child_index(first) {
+                Ok(i) => i,
+                Err(i) => {
+                    node.children.insert(i, Node::new(key.to_vec(), Some(value)));
+                    self.len += 1;
+                    return None;
+                }
+            };
+            let common = common_prefix(&node.children[i].label, key);
+            if common < node.children[i].label.len() {
+                // Split the edge at the point where the key diverges
+                let child = &mut node.children[i];
+                let suffix = child.label.split_off(common);
+                let mut tail = Node::new(suffix, child.value.take());
+                tail.children = std::mem::take(&mut child.children);
+                child.children.push(tail);
+            }
+            node = &mut node.children[i];
+            key = &key[common..];
+        }
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<&V> {
+        let mut node = &self.root;
+        let mut key = key;
+        while let Some(&first) = key.first() {
+            let child = &node.children[node.child_index(first).ok()?];
+            key = key.strip_prefix(child.label.as_slice())?;
+            node = child;
+        }
+        node.value.as_ref()
+    }
+
+    pub fn get_mut(&mut self, key: &[u8]) -> Option<&mut V> {
+        let mut node = &mut self.root;
+        let mut key = key;
+        while let Some(&first) = key.first() {
+            let i = node.child_index(first).ok()?;
+            let child = &mut node.children[i];
+            key = key.strip_prefix(child.label.as_slice())?;
+            node = child;
+        }
+        node.value.as_mut()
+    }
+
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn remove(&mut self, key: &[u8]) -> Option<V> {
+        fn remove<V>(node: &mut Node<V>, key: &[u8]) -> Option<V> {
+            let Some(&first) = key.first() else {
+                return node.value.take();
+            };
+            let i = node.child_index(first).ok()?;
+            let child = &mut node.children[i];
+            let rest = key.strip_prefix(child.label.as_slice())?;
+            let removed = remove(child, rest)?;
+            if child.value.is_none() && child.children.is_empty() {
+                node.children.remove(i);
+            } else {
+                child.compress();
+            }
+            Some(removed)
+        }
+        let removed = remove(&mut self.root, key)?;
+        self.len -= 1;
+        Some(removed)
+    }
+
+    /// Find the longest key in the trie that is a prefix of `key`, returning
+    /// that prefix of `key` along with its value.
+    pub fn longest_prefix_match<'k>(&self, key: &'k [u8]) -> Option<(&'k [u8], &V)> {
+        let mut node = &self.root;
+        let mut depth = 0;
+        let mut best = node.value.as_ref().map(|v| (0, v));
+        while let Some(&first) = key.get(depth) {
+            let Ok(i) = node.child_index(first) else {
+                break;
+            };
+            let child = &node.children[i];
+            if !key[depth..].starts_with(&child.label) {
+                break;
+            }
+            depth += child.label.len();
+            node = child;
+            if let Some(v) = &node.value {
+                best = Some((depth, v));
+            }
+        }
+        best.map(|(len, v)| (&key[..len], v))
+    }
+
+    /// Iterate over all entries in lexicographic byte order.
+    pub fn iter(&self) -> Iter<'_, V> {
+        Iter { stack: vec![(&self.root, Vec::new())] }
+    }
+}
+
+impl<V> Default for RadixTrie<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Iterator over the entries of a radix trie, produced by a depth-first walk.
+pub struct Iter<'a, V> {
+    stack: Vec<(&'a Node<V>, Vec<u8>)>,
+}
+
+impl<'a, V> Iterator for Iter<'a, V> {
+    type Item = (Vec<u8>, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, key)) = self.stack.pop() {
+            for child in node.children.iter().rev() {
+                let mut child_key = key.clone();
+                child_key.extend_from_slice(&child.label);
+                self.stack.push((child, child_key));
+            }
+            if let Some(v) = &node.value {
+                return Some((key, v));
+            }
+        }
+        None
+    }
+}
+
+impl<'a, V> IntoIterator for &'a RadixTrie<V> {
+    type Item = (Vec<u8>, &'a V);
+    type IntoIter = Iter<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<V: Debug> Debug for RadixTrie<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<V: HeapSize> HeapSize for Node<V> {
+    fn heap_size(&self) -> usize {
+        self.label.heap_size() + self.value.heap_size() + self.children.heap_size()
+    }
+}
+
+impl<V: HeapSize> HeapSize for RadixTrie<V> {
+    fn heap_size(&self) -> usize {
+        self.root.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RadixTrie;
+
+    #[test]
+    fn test_new() {
+        let trie: RadixTrie<i32> = RadixTrie::new();
+        assert_eq!(trie.len(), 0);
+        assert!(trie.is_empty());
+        assert!(trie.get(b"").is_none());
+        assert!(trie.longest_prefix_match(b"abc").is_none());
+    }
+
+    #[test]
+    fn test_insert_get() {
+        let mut trie = RadixTrie::new();
+        assert_eq!(trie.insert(b"romane", 1), None);
+        assert_eq!(trie.insert(b"romanus", 2), None);
+        assert_eq!(trie.insert(b"romulus", 3), None);
+        assert_eq!(trie.insert(b"rom", 4), None);
+        assert_eq!(trie.insert(b"", 5), None);
+        assert_eq!(trie.insert(b"rom", 40), Some(4));
+        assert_eq!(trie.len(), 5);
+
+        assert_eq!(trie.get(b"romane"), Some(&1));
+        assert_eq!(trie.get(b"romanus"), Some(&2));
+        assert_eq!(trie.get(b"romulus"), Some(&3));
+        assert_eq!(trie.get(b"rom"), Some(&40));
+        assert_eq!(trie.get(b""), Some(&5));
+        assert_eq!(trie.get(b"roman"), None);
+        assert_eq!(trie.get(b"r"), None);
+        assert_eq!(trie.get(b"romanes"), None);
+
+        *trie.get_mut(b"romane").unwrap() += 10;
+        assert_eq!(trie.get(b"romane"), Some(&11));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut trie = RadixTrie::new();
+        trie.insert(b"test", 1);
+        trie.insert(b"team", 2);
+        trie.insert(b"toast", 3);
+        assert_eq!(trie.remove(b"te"), None);
+        assert_eq!(trie.remove(b"team"), Some(2));
+        assert_eq!(trie.remove(b"team"), None);
+        assert_eq!(trie.len(), 2);
+        assert_eq!(trie.get(b"test"), Some(&1));
+        assert_eq!(trie.get(b"toast"), Some(&3));
+
+        assert_eq!(trie.remove(b"test"), Some(1));
+        assert_eq!(trie.remove(b"toast"), Some(3));
+        assert!(trie.is_empty());
+        assert!(trie.iter().next().is_none());
+    }
+
+    #[test]
+    fn test_longest_prefix_match() {
+        let mut trie = RadixTrie::new();
+        trie.insert(b"10.", "net");
+        trie.insert(b"10.1.", "subnet");
+        trie.insert(b"10.1.2.", "host-range");
+        trie.insert(b"192.", "other");
+
+        assert_eq!(trie.longest_prefix_match(b"10.1.2.7"), Some((&b"10.1.2."[..], &"host-range")));
+        assert_eq!(trie.longest_prefix_match(b"10.1.9.1"), Some((&b"10.1."[..], &"subnet")));
+        assert_eq!(trie.longest_prefix_match(b"10.2.0.0"), Some((&b"10."[..], &"net")));
+        assert_eq!(trie.longest_prefix_match(b"10"), None);
+        assert_eq!(trie.longest_prefix_match(b"172.16.0.1"), None);
+
+        trie.insert(b"", "default");
+        assert_eq!(trie.longest_prefix_match(b"172.16.0.1"), Some((&b""[..], &"default")));
+    }
+
+    #[test]
+    fn test_iter_sorted() {
+        let mut trie = RadixTrie::new();
+        for key in ["b", "abc", "ab", "a", "abd", "ba"] {
+            trie.insert(key.as_bytes(), key.len());
+        }
+        let keys: Vec<_> = trie.iter().map(|(k, _)| String::from_utf8(k).unwrap()).collect();
+        assert_eq!(keys, vec!["a", "ab", "abc", "abd", "b", "ba"]);
+    }
+}
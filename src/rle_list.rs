@@ -0,0 +1,294 @@
+use std::fmt::Debug;
+use std::ops::Index;
+
+use crate::error::{CollectionError, check_index};
+use crate::heap_size::HeapSize;
+use crate::list::List;
+
+/// A sequence stored as runs of equal elements, each kept once with its
+/// repeat count.
+///
+/// Adjacent equal elements always share one run, so a sequence of n
+/// elements in r runs takes O(r) space. Pushing extends the last run when
+/// it can. The end position of every run is kept alongside, so indexing is
+/// a binary search, O(log r).
+#[derive(Clone, PartialEq, Eq)]
+pub struct RleList<T> {
+    runs: Vec<(T, usize)>,
+    // ends[i] is the index just past run i
+    ends: Vec<usize>,
+}
+
+impl<T: Eq> RleList<T> {
+    pub const fn new() -> Self {
+        RleList { runs: Vec::new(), ends: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ends.last().copied().unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    /// Number of runs of equal elements.
+    pub fn run_count(&self) -> usize {
+        self.runs.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.runs.clear();
+        self.ends.clear();
+    }
+
+    pub fn push(&mut self, val: T) {
+        self.push_run(val, 1);
+    }
+
+    /// Push `count` copies of `val`.
+    pub fn push_run(&mut self, val: T, count: usize) {
+        if count == 0 {
+            return;
+        }
+        let end = self.len() + count;
+        match self.runs.last_mut() {
+            Some((last, n)) if *last == val => {
+                *n += count;
+                *self.ends.last_mut().unwrap() = end;
+            }
+            _ => {
+                self.runs.push((val, count));
+                self.ends.push(end);
+            }
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        let run = self.ends.partition_point(|&end| end <= index);
+        self.runs.get(run).map(|(val, _)| val)
+    }
+
+    /// `list[index]`, but returning an error if `index` is out of bounds.
+    pub fn try_get(&self, index: usize) -> Result<&T, CollectionError> {
+        check_index(index, self.len())?;
+        Ok(&self[index])
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.runs.first().map(|(val, _)| val)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.runs.last().map(|(val, _)| val)
+    }
+
+    /// Iterate over the runs as (element, count) pairs.
+    pub fn runs(&self) -> impl ExactSizeIterator<Item = (&T, usize)> {
+        self.runs.iter().map(|(val, count)| (val, *count))
+    }
+
+    /// Iterate over every element, each run repeated out in full.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { runs: self.runs.iter(), curr: None, left: self.len() }
+    }
+}
+
+impl<T: Eq + Clone> RleList<T> {
+    pub fn pop(&mut self) -> Option<T> {
+        let (val, count) = self.runs.last_mut()?;
+        if *count > 1 {
+            *count -= 1;
+            *self.ends.last_mut().unwrap() -= 1;
+            return Some(val.clone());
+        }
+        self.ends.pop();
+        self.runs.pop().map(|(val, _)| val)
+    }
+
+    /// Every element, expanded into a `Vec`.
+    pub fn to_vec(&self) -> Vec<T> {
+        self.iter().cloned().collect()
+    }
+
+    /// Every element, expanded into a `List`.
+    pub fn to_list(&self) -> List<T> {
+        let mut list = List::new();
+        for (val, count) in self.runs.iter().rev() {
+            for _ in 0..*count {
+                list.push_front(val.clone());
+            }
+        }
+        list
+    }
+}
+
+impl<T: Eq> Default for RleList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Eq> Index<usize> for RleList<T> {
+    type Output = T;
+
+    /// Panics if `index` is out of bounds.
+    fn index(&self, index: usize) -> &T {
+        match self.get(index) {
+            Some(val) => val,
+            None => panic!("index {index} out of bounds for length {}", self.len()),
+        }
+    }
+}
+
+impl<T: Eq> FromIterator<T> for RleList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = RleList::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T: Eq> Extend<T> for RleList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for val in iter {
+            self.push(val);
+        }
+    }
+}
+
+impl<T: Eq> From<List<T>> for RleList<T> {
+    fn from(list: List<T>) -> Self {
+        list.into_iter().collect()
+    }
+}
+
+pub struct Iter<'a, T> {
+    runs: std::slice::Iter<'a, (T, usize)>,
+    // The run being repeated and how many more times
+    curr: Option<(&'a T, usize)>,
+    left: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((val, count)) = &mut self.curr
+                && *count > 0
+            {
+                *count -= 1;
+                self.left -= 1;
+                return Some(*val);
+            }
+            let (val, count) = self.runs.next()?;
+            self.curr = Some((val, *count));
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.left, Some(self.left))
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<'a, T: Eq> IntoIterator for &'a RleList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Debug> Debug for RleList<T> {
+    /// Shows the runs rather than every element.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.runs.iter()).finish()
+    }
+}
+
+impl<T: HeapSize> HeapSize for RleList<T> {
+    fn heap_size(&self) -> usize {
+        self.runs.heap_size() + self.ends.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::CollectionError;
+    use crate::heap_size::HeapSize;
+    use crate::list::List;
+
+    use super::RleList;
+
+    #[test]
+    fn test_push_and_runs() {
+        let mut list: RleList<_> = "aaabccdddd".chars().collect();
+        assert_eq!(list.len(), 10);
+        assert_eq!(list.run_count(), 4);
+        assert_eq!(list.runs().collect::<Vec<_>>(), vec![(&'a', 3), (&'b', 1), (&'c', 2), (&'d', 4)]);
+        list.push('d');
+        list.push_run('e', 0);
+        list.push_run('e', 2);
+        assert_eq!((list.len(), list.run_count()), (13, 5));
+        assert_eq!(format!("{list:?}"), "[('a', 3), ('b', 1), ('c', 2), ('d', 5), ('e', 2)]");
+        assert_eq!((list.first(), list.last()), (Some(&'a'), Some(&'e')));
+    }
+
+    #[test]
+    fn test_indexing() {
+        let list: RleList<_> = [1, 1, 2, 3, 3, 3].into_iter().collect();
+        let expected = [1, 1, 2, 3, 3, 3];
+        for (i, val) in expected.iter().enumerate() {
+            assert_eq!(list[i], *val);
+        }
+        assert_eq!(list.get(6), None);
+        assert_eq!(list.try_get(5), Ok(&3));
+        assert_eq!(list.try_get(6), Err(CollectionError::IndexOutOfBounds { index: 6, len: 6 }));
+        assert_eq!(list.iter().len(), 6);
+        assert_eq!(list.to_vec(), expected);
+        assert!(RleList::<i32>::new().get(0).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "index 3 out of bounds")]
+    fn test_index_out_of_bounds() {
+        let list: RleList<_> = [7, 7, 7].into_iter().collect();
+        let _ = list[3];
+    }
+
+    #[test]
+    fn test_pop() {
+        let mut list: RleList<_> = [1, 2, 2].into_iter().collect();
+        assert_eq!(list.pop(), Some(2));
+        assert_eq!((list.len(), list.run_count()), (2, 2));
+        assert_eq!(list.pop(), Some(2));
+        assert_eq!(list.run_count(), 1);
+        assert_eq!(list.pop(), Some(1));
+        assert_eq!(list.pop(), None);
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn test_list_conversions() {
+        let mut plain = List::new();
+        for val in [3, 3, 2, 1, 1] {
+            plain.push_front(val);
+        }
+        let list = RleList::from(plain);
+        assert_eq!(list.runs().map(|(val, count)| (*val, count)).collect::<Vec<_>>(), vec![(1, 2), (2, 1), (3, 2)]);
+        let back = list.to_list();
+        assert_eq!(back.into_iter().collect::<Vec<_>>(), vec![1, 1, 2, 3, 3]);
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let list: RleList<String> = ["ab", "ab", "c"].into_iter().map(String::from).collect();
+        let buffers = list.runs.capacity() * size_of::<(String, usize)>() + list.ends.capacity() * size_of::<usize>();
+        // Each run's element is kept once
+        assert_eq!(list.heap_size(), buffers + 3);
+    }
+}
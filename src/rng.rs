@@ -0,0 +1,31 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// Small xorshift generator for structures that need cheap, non-cryptographic
+/// randomness (treap priorities and the like) without an external dependency.
+pub(crate) struct XorShift64 {
+    state: u64,
+}
+
+impl XorShift64 {
+    /// Seed from std's per-process random hasher keys.
+    pub(crate) fn new() -> Self {
+        let seed = RandomState::new().build_hasher().finish();
+        XorShift64 { state: seed | 1 }
+    }
+
+    /// A generator that replays the same sequence for the same seed.
+    #[cfg(test)]
+    pub(crate) fn with_seed(seed: u64) -> Self {
+        XorShift64 { state: seed | 1 }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+}
@@ -0,0 +1,414 @@
+use std::fmt::{Debug, Display};
+use std::ops::Range;
+
+use crate::error::{CollectionError, check_position, check_range};
+use crate::heap_size::HeapSize;
+use crate::rng::XorShift64;
+
+/// Text longer than this is broken into several leaves when inserted.
+const MAX_CHUNK: usize = 64;
+
+type Link = Option<Box<Node>>;
+
+struct Node {
+    chunk: String,
+    // Characters in `chunk`, and in the whole subtree.
+    chars: usize,
+    total: usize,
+    priority: u64,
+    left: Link,
+    right: Link,
+}
+
+impl Node {
+    fn new(chunk: String, chars: usize, priority: u64) -> Self {
+        Node { chunk, chars, total: chars, priority, left: None, right: None }
+    }
+
+    fn update(&mut self) {
+        self.total = self.chars + total(&self.left) + total(&self.right);
+    }
+}
+
+fn total(link: &Link) -> usize {
+    link.as_ref().map_or(0, |node| node.total)
+}
+
+/// Check that every chunk is non-empty, no longer than `MAX_CHUNK` and has
+/// the right character count, that no node has a higher priority than its
+/// parent, and that totals are right. Returns the total of the subtree.
+#[cfg(any(test, debug_assertions))]
+fn validate(link: &Link, max_priority: u64) -> usize {
+    let Some(node) = link else {
+        return 0;
+    };
+    assert!(!node.chunk.is_empty(), "empty chunk");
+    assert_eq!(node.chars, node.chunk.chars().count(), "stale chunk length");
+    assert!(node.chars <= MAX_CHUNK, "chunk longer than MAX_CHUNK");
+    assert!(node.priority <= max_priority, "priority above its parent's");
+    let total = node.chars + validate(&node.left, node.priority) + validate(&node.right, node.priority);
+    assert_eq!(node.total, total, "stale subtree total");
+    total
+}
+
+fn byte_offset(s: &str, char_idx: usize) -> usize {
+    s.char_indices().nth(char_idx).map_or(s.len(), |(i, _)| i)
+}
+
+/// Split a subtree so the left part holds the first `at` characters. A leaf
+/// straddling the split point is cut in two.
+fn split(link: Link, at: usize) -> (Link, Link) {
+    let Some(mut node) = link else {
+        return (None, None);
+    };
+    let left_total = total(&node.left);
+    if at <= left_total {
+        let (less, rest) = split(node.left.take(), at);
+        node.left = rest;
+        node.update();
+        (less, Some(node))
+    } else if at >= left_total + node.chars {
+        let (less, rest) = split(node.right.take(), at - left_total - node.chars);
+        node.right = less;
+        node.update();
+        (Some(node), rest)
+    } else {
+        let k = at - left_total;
+        let tail = node.chunk.split_off(byte_offset(&node.chunk, k));
+        // The tail may end up under any of this node's ancestors, so it keeps
+        // this node's priority to stay heap-ordered
+        let tail = Box::new(Node::new(tail, node.chars - k, node.priority));
+        node.chars = k;
+        let rest = merge(Some(tail), node.right.take());
+        node.update();
+        (Some(node), rest)
+    }
+}
+
+fn merge(left: Link, right: Link) -> Link {
+    match (left, right) {
+        (None, right) => right,
+        (left, None) => left,
+        (Some(mut l), Some(mut r)) => {
+            if l.priority > r.priority {
+                l.right = merge(l.right.take(), Some(r));
+                l.update();
+                Some(l)
+            } else {
+                r.left = merge(Some(l), r.left.take());
+                r.update();
+                Some(r)
+            }
+        }
+    }
+}
+
+/// A rope: text stored as a balanced tree of string chunks, so inserting,
+/// deleting, splitting and concatenating at any character index takes
+/// expected O(log n) instead of shifting the whole buffer.
+///
+/// The tree is kept balanced the same way as `Treap`, using random
+/// priorities on the chunks.
+pub struct Rope {
+    root: Link,
+    rng: XorShift64,
+}
+
+impl Rope {
+    pub fn new() -> Self {
+        Rope { root: None, rng: XorShift64::new() }
+    }
+
+    /// Build a subtree holding `text`, split into chunks.
+    fn build(&mut self, text: &str) -> Link {
+        let mut root = None;
+        let mut rest = text;
+        while !rest.is_empty() {
+            let at = byte_offset(rest, MAX_CHUNK);
+            let (chunk, tail) = rest.split_at(at);
+            let node = Node::new(chunk.to_string(), chunk.chars().count(), self.rng.next_u64());
+            root = merge(root, Some(Box::new(node)));
+            rest = tail;
+        }
+        root
+    }
+
+    /// Length in characters.
+    pub fn len(&self) -> usize {
+        total(&self.root)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Panic unless the chunks are non-empty and within `MAX_CHUNK`, the
+    /// nodes are heap-ordered by priority, and the totals are right. Only in
+    /// debug builds and tests.
+    #[cfg(any(test, debug_assertions))]
+    pub fn validate(&self) {
+        validate(&self.root, u64::MAX);
+    }
+
+    /// Insert `text` so that it starts at character index `at`.
+    ///
+    /// Panics if `at > len`.
+    pub fn insert(&mut self, at: usize, text: &str) {
+        assert!(at <= self.len(), "insertion index (is {at}) should be <= len (is {})", self.len());
+        let (less, rest) = split(self.root.take(), at);
+        let middle = self.build(text);
+        self.root = merge(merge(less, middle), rest);
+    }
+
+    /// `insert`, but returning an error if `at > len`.
+    pub fn try_insert(&mut self, at: usize, text: &str) -> Result<(), CollectionError> {
+        check_position(at, self.len())?;
+        self.insert(at, text);
+        Ok(())
+    }
+
+    /// Delete the characters in `range`.
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn remove(&mut self, range: Range<usize>) {
+        assert!(range.start <= range.end && range.end <= self.len(), "range {range:?} out of bounds");
+        let (less, rest) = split(self.root.take(), range.start);
+        let (_, rest) = split(rest, range.end - range.start);
+        self.root = merge(less, rest);
+    }
+
+    /// `remove`, but returning an error if the range is out of bounds.
+    pub fn try_remove(&mut self, range: Range<usize>) -> Result<(), CollectionError> {
+        check_range(&range, self.len())?;
+        self.remove(range);
+        Ok(())
+    }
+
+    /// Split the rope at character index `at`, returning everything from `at`
+    /// onwards and keeping the rest in `self`.
+    ///
+    /// Panics if `at > len`.
+    pub fn split_off(&mut self, at: usize) -> Rope {
+        assert!(at <= self.len(), "split index (is {at}) should be <= len (is {})", self.len());
+        let (less, rest) = split(self.root.take(), at);
+        self.root = less;
+        Rope { root: rest, rng: XorShift64::new() }
+    }
+
+    /// `split_off`, but returning an error if `at > len`.
+    pub fn try_split_off(&mut self, at: usize) -> Result<Rope, CollectionError> {
+        check_position(at, self.len())?;
+        Ok(self.split_off(at))
+    }
+
+    /// Concatenate `other` onto the end of this rope.
+    pub fn append(&mut self, mut other: Rope) {
+        self.root = merge(self.root.take(), other.root.take());
+    }
+
+    /// The character at index `at`.
+    pub fn char_at(&self, at: usize) -> Option<char> {
+        let mut node = self.root.as_deref()?;
+        let mut at = at;
+        loop {
+            let left_total = total(&node.left);
+            if at < left_total {
+                node = node.left.as_deref()?;
+            } else if at < left_total + node.chars {
+                return node.chunk.chars().nth(at - left_total);
+            } else {
+                at -= left_total + node.chars;
+                node = node.right.as_deref()?;
+            }
+        }
+    }
+
+    /// Iterate over the chunks making up the text, in order.
+    pub fn chunks(&self) -> Chunks<'_> {
+        let mut chunks = Chunks { stack: Vec::new() };
+        chunks.push_left(self.root.as_deref());
+        chunks
+    }
+
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.chunks().flat_map(str::chars)
+    }
+}
+
+impl Default for Rope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<&str> for Rope {
+    fn from(text: &str) -> Self {
+        let mut rope = Rope::new();
+        rope.root = rope.build(text);
+        rope
+    }
+}
+
+/// In-order iterator over the chunks of a rope.
+pub struct Chunks<'a> {
+    stack: Vec<&'a Node>,
+}
+
+impl<'a> Chunks<'a> {
+    fn push_left(&mut self, mut link: Option<&'a Node>) {
+        while let Some(node) = link {
+            self.stack.push(node);
+            link = node.left.as_deref();
+        }
+    }
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.push_left(node.right.as_deref());
+        Some(&node.chunk)
+    }
+}
+
+impl Display for Rope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for chunk in self.chunks() {
+            f.write_str(chunk)?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for Rope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.to_string(), f)
+    }
+}
+
+impl HeapSize for Node {
+    fn heap_size(&self) -> usize {
+        self.chunk.heap_size() + self.left.heap_size() + self.right.heap_size()
+    }
+}
+
+impl HeapSize for Rope {
+    fn heap_size(&self) -> usize {
+        self.root.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::CollectionError;
+
+    use super::Rope;
+
+    #[test]
+    fn test_new() {
+        let rope = Rope::new();
+        assert_eq!(rope.len(), 0);
+        assert!(rope.is_empty());
+        assert_eq!(rope.to_string(), "");
+        assert!(rope.chunks().next().is_none());
+    }
+
+    #[test]
+    fn test_from_str_chunks() {
+        let text = "x".repeat(1000);
+        let rope = Rope::from(text.as_str());
+        assert_eq!(rope.len(), 1000);
+        assert!(rope.chunks().count() > 1);
+        assert!(rope.chunks().all(|c| c.chars().count() <= super::MAX_CHUNK));
+        assert_eq!(rope.to_string(), text);
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut rope = Rope::from("hello world");
+        rope.insert(5, ",");
+        rope.insert(0, ">> ");
+        rope.insert(rope.len(), "!");
+        assert_eq!(rope.to_string(), ">> hello, world!");
+        assert_eq!(rope.len(), 16);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_insert_out_of_bounds() {
+        let mut rope = Rope::from("abc");
+        rope.insert(4, "d");
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut rope = Rope::from("hello, cruel world");
+        rope.remove(5..12);
+        assert_eq!(rope.to_string(), "hello world");
+        rope.remove(0..0);
+        assert_eq!(rope.to_string(), "hello world");
+        rope.validate();
+        rope.remove(0..rope.len());
+        assert!(rope.is_empty());
+    }
+
+    #[test]
+    fn test_split_append() {
+        let mut rope = Rope::from("abcdefghij");
+        let tail = rope.split_off(4);
+        assert_eq!(rope.to_string(), "abcd");
+        assert_eq!(tail.to_string(), "efghij");
+        rope.validate();
+        tail.validate();
+
+        rope.append(tail);
+        assert_eq!(rope.to_string(), "abcdefghij");
+        rope.append(Rope::new());
+        assert_eq!(rope.len(), 10);
+        assert_eq!(rope.try_split_off(11).map(|_| ()), Err(CollectionError::IndexOutOfBounds { index: 11, len: 10 }));
+        assert_eq!(rope.try_insert(11, "k"), Err(CollectionError::IndexOutOfBounds { index: 11, len: 10 }));
+        assert_eq!(rope.try_insert(10, "k"), Ok(()));
+        assert_eq!(rope.try_split_off(8).map(|tail| tail.to_string()), Ok("ijk".to_string()));
+        assert_eq!(rope.try_remove(6..9), Err(CollectionError::IndexOutOfBounds { index: 9, len: 8 }));
+        assert_eq!(rope.try_remove(2..6), Ok(()));
+        assert_eq!(rope.to_string(), "abgh");
+    }
+
+    #[test]
+    fn test_unicode_indices() {
+        let mut rope = Rope::from("naïve café");
+        assert_eq!(rope.len(), 10);
+        assert_eq!(rope.char_at(2), Some('ï'));
+        assert_eq!(rope.char_at(9), Some('é'));
+        assert_eq!(rope.char_at(10), None);
+        rope.insert(3, "—");
+        assert_eq!(rope.to_string(), "naï—ve café");
+        rope.validate();
+        rope.remove(9..11);
+        assert_eq!(rope.to_string(), "naï—ve ca");
+    }
+
+    #[test]
+    fn test_many_edits_match_string() {
+        let mut rope = Rope::new();
+        let mut expected = String::new();
+        for i in 0..500usize {
+            let at = (i * 7919) % (expected.len() + 1);
+            let text = format!("{i},");
+            rope.insert(at, &text);
+            expected.insert_str(at, &text);
+            if i % 3 == 0 {
+                let start = (i * 31) % expected.len();
+                let end = (start + 5).min(expected.len());
+                rope.remove(start..end);
+                expected.replace_range(start..end, "");
+            }
+        }
+        rope.validate();
+        assert_eq!(rope.len(), expected.len());
+        assert_eq!(rope.to_string(), expected);
+        assert!(rope.chars().eq(expected.chars()));
+    }
+}
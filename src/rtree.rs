@@ -0,0 +1,266 @@
+use std::fmt::Debug;
+
+use crate::heap_size::HeapSize;
+use crate::quadtree::Rect;
+
+enum Node<T> {
+    Leaf(Vec<(Rect, T)>),
+    Internal(Vec<(Rect, Node<T>)>),
+}
+
+impl<T> Node<T> {
+    fn query<'a>(&'a self, region: &Rect, out: &mut Vec<(&'a Rect, &'a T)>) {
+        match self {
+            Node::Leaf(entries) => {
+                out.extend(entries.iter().filter(|(r, _)| r.intersects(region)).map(|(r, v)| (r, v)));
+            }
+            Node::Internal(children) => {
+                for (bounds, child) in children {
+                    if bounds.intersects(region) {
+                        child.query(region, out);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Check that the node is non-empty, that `bounds` is exactly the union
+    /// of its entries' boxes, and that every leaf below it is equally deep.
+    /// Returns the node's height and the number of entries under it.
+    #[cfg(any(test, debug_assertions))]
+    fn validate(&self, bounds: &Rect) -> (usize, usize) {
+        match self {
+            Node::Leaf(entries) => {
+                assert!(!entries.is_empty(), "empty leaf");
+                assert_eq!(*bounds, bounding(entries), "loose or stale bounds");
+                (1, entries.len())
+            }
+            Node::Internal(children) => {
+                assert!(!children.is_empty(), "empty internal node");
+                assert_eq!(*bounds, bounding(children), "loose or stale bounds");
+                let mut sizes = children.iter().map(|(bounds, child)| child.validate(bounds));
+                let (height, mut count) = sizes.next().unwrap();
+                for (h, n) in sizes {
+                    assert_eq!(h, height, "leaves at different depths");
+                    count += n;
+                }
+                (height + 1, count)
+            }
+        }
+    }
+}
+
+fn bounding<I>(entries: &[(Rect, I)]) -> Rect {
+    entries.iter().skip(1).fold(entries[0].0, |acc, (r, _)| acc.union(r))
+}
+
+/// Group entries into runs of at most `capacity` using Sort-Tile-Recursive
+/// packing: sort by x center into vertical slices, then each slice by y.
+fn str_pack<I>(mut entries: Vec<(Rect, I)>, capacity: usize) -> Vec<Vec<(Rect, I)>> {
+    let leaves = entries.len().div_ceil(capacity);
+    let slices = (leaves as f64).sqrt().ceil() as usize;
+    let slice_len = slices * capacity;
+    entries.sort_by(|a, b| a.0.center()[0].total_cmp(&b.0.center()[0]));
+    let mut groups = Vec::with_capacity(leaves);
+    let mut rest = entries;
+    while !rest.is_empty() {
+        let tail = rest.split_off(slice_len.min(rest.len()));
+        let mut slice = rest;
+        rest = tail;
+        slice.sort_by(|a, b| a.0.center()[1].total_cmp(&b.0.center()[1]));
+        while !slice.is_empty() {
+            let tail = slice.split_off(capacity.min(slice.len()));
+            groups.push(slice);
+            slice = tail;
+        }
+    }
+    groups
+}
+
+/// An R-tree over axis-aligned bounding boxes, bulk-loaded with
+/// Sort-Tile-Recursive packing so nodes are nearly full and spatially
+/// compact. Answers "which boxes intersect this region" by descending only
+/// into nodes whose bounds intersect it.
+pub struct RTree<T> {
+    root: Option<(Rect, Node<T>)>,
+    len: usize,
+}
+
+impl<T> RTree<T> {
+    pub const fn new() -> Self {
+        RTree { root: None, len: 0 }
+    }
+
+    pub fn bulk_load(entries: Vec<(Rect, T)>) -> Self {
+        Self::bulk_load_with_capacity(entries, 16)
+    }
+
+    /// Bulk-load with at most `capacity` entries per node.
+    ///
+    /// Panics if `capacity < 2`.
+    pub fn bulk_load_with_capacity(entries: Vec<(Rect, T)>, capacity: usize) -> Self {
+        assert!(capacity >= 2, "R-tree node capacity must be at least 2");
+        let len = entries.len();
+        if len == 0 {
+            return Self::new();
+        }
+        let mut level: Vec<(Rect, Node<T>)> = str_pack(entries, capacity)
+            .into_iter()
+            .map(|group| (bounding(&group), Node::Leaf(group)))
+            .collect();
+        while level.len() > 1 {
+            level = str_pack(level, capacity)
+                .into_iter()
+                .map(|group| (bounding(&group), Node::Internal(group)))
+                .collect();
+        }
+        RTree { root: level.pop(), len }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Panic unless no node is empty, every node's bounds are exactly those
+    /// of its entries, the leaves are all equally deep, and `len` is right.
+    /// Only in debug builds and tests.
+    #[cfg(any(test, debug_assertions))]
+    pub fn validate(&self) {
+        let (height, count) = self.root.as_ref().map_or((0, 0), |(bounds, root)| root.validate(bounds));
+        assert_eq!(height, self.height(), "leaves at different depths");
+        assert_eq!(count, self.len, "tree length is out of date");
+    }
+
+    /// Bounds of everything in the tree.
+    pub fn bounds(&self) -> Option<Rect> {
+        self.root.as_ref().map(|(r, _)| *r)
+    }
+
+    /// Number of levels, counting the leaves.
+    pub fn height(&self) -> usize {
+        let mut height = 0;
+        let mut node = self.root.as_ref().map(|(_, n)| n);
+        while let Some(n) = node {
+            height += 1;
+            node = match n {
+                Node::Leaf(_) => None,
+                Node::Internal(children) => children.first().map(|(_, c)| c),
+            };
+        }
+        height
+    }
+
+    /// All entries whose boxes intersect `region`.
+    pub fn query(&self, region: &Rect) -> Vec<(&Rect, &T)> {
+        let mut out = Vec::new();
+        if let Some((bounds, root)) = &self.root
+            && bounds.intersects(region)
+        {
+            root.query(region, &mut out);
+        }
+        out
+    }
+}
+
+impl<T> Default for RTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Debug> Debug for RTree<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.bounds() {
+            Some(bounds) => f.debug_list().entries(self.query(&bounds)).finish(),
+            None => f.debug_list().finish(),
+        }
+    }
+}
+
+impl<T: HeapSize> HeapSize for Node<T> {
+    fn heap_size(&self) -> usize {
+        match self {
+            Node::Leaf(entries) => entries.heap_size(),
+            Node::Internal(children) => children.heap_size(),
+        }
+    }
+}
+
+impl<T: HeapSize> HeapSize for RTree<T> {
+    fn heap_size(&self) -> usize {
+        self.root.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RTree;
+    use crate::quadtree::Rect;
+
+    fn boxes(n: usize) -> Vec<(Rect, usize)> {
+        (0..n)
+            .map(|i| {
+                let x = ((i * 37) % 200) as f64;
+                let y = ((i * 91) % 200) as f64;
+                let w = (i % 7 + 1) as f64;
+                (Rect::new([x, y], [x + w, y + w / 2.0]), i)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_empty() {
+        let tree: RTree<()> = RTree::bulk_load(Vec::new());
+        assert!(tree.is_empty());
+        assert_eq!(tree.height(), 0);
+        assert!(tree.bounds().is_none());
+        tree.validate();
+        assert!(tree.query(&Rect::new([0.0, 0.0], [1.0, 1.0])).is_empty());
+    }
+
+    #[test]
+    fn test_bulk_load() {
+        let tree = RTree::bulk_load_with_capacity(boxes(1000), 8);
+        assert_eq!(tree.len(), 1000);
+        assert!(tree.height() >= 4);
+        tree.validate();
+        let bounds = tree.bounds().unwrap();
+        assert_eq!(bounds.min, [0.0, 0.0]);
+        assert_eq!(tree.query(&bounds).len(), 1000);
+    }
+
+    #[test]
+    fn test_query() {
+        let tree = RTree::bulk_load(vec![
+            (Rect::new([0.0, 0.0], [2.0, 2.0]), "a"),
+            (Rect::new([1.0, 1.0], [3.0, 3.0]), "b"),
+            (Rect::new([5.0, 5.0], [6.0, 6.0]), "c"),
+        ]);
+        let mut found: Vec<_> = tree.query(&Rect::new([1.5, 1.5], [1.6, 1.6])).into_iter().map(|(_, v)| *v).collect();
+        found.sort();
+        assert_eq!(found, vec!["a", "b"]);
+        let found: Vec<_> = tree.query(&Rect::new([3.0, 3.0], [5.0, 5.0])).into_iter().map(|(_, v)| *v).collect();
+        assert_eq!(found.len(), 2);
+        assert!(tree.query(&Rect::new([10.0, 10.0], [11.0, 11.0])).is_empty());
+    }
+
+    #[test]
+    fn test_query_matches_naive() {
+        let entries = boxes(500);
+        let tree = RTree::bulk_load_with_capacity(entries.clone(), 6);
+        tree.validate();
+        for j in 0..30 {
+            let min = [(j * 7) as f64, (j * 5) as f64];
+            let region = Rect::new(min, [min[0] + 20.0, min[1] + 10.0]);
+            let mut expected: Vec<_> = entries.iter().filter(|(r, _)| r.intersects(&region)).map(|(_, i)| *i).collect();
+            let mut found: Vec<_> = tree.query(&region).into_iter().map(|(_, i)| *i).collect();
+            expected.sort();
+            found.sort();
+            assert_eq!(found, expected);
+        }
+    }
+}
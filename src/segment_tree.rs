@@ -0,0 +1,381 @@
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use crate::error::{CollectionError, check_index, check_range};
+use crate::heap_size::HeapSize;
+
+/// The operations a `SegmentTree` is built over: an associative `combine`
+/// with an `identity`, plus range updates that can be applied to an
+/// aggregate lazily and composed with each other.
+pub trait LazyOp<T> {
+    type Update: Clone;
+
+    fn identity() -> T;
+
+    fn combine(a: &T, b: &T) -> T;
+
+    /// Apply `update` to the aggregate `value` of a segment of `len` elements.
+    fn apply(value: &T, update: &Self::Update, len: usize) -> T;
+
+    /// The single update equivalent to applying `older` and then `newer`.
+    fn compose(older: &Self::Update, newer: &Self::Update) -> Self::Update;
+}
+
+/// Range sums with range additions.
+pub struct SumAdd;
+
+impl LazyOp<i64> for SumAdd {
+    type Update = i64;
+
+    fn identity() -> i64 {
+        0
+    }
+
+    fn combine(a: &i64, b: &i64) -> i64 {
+        a + b
+    }
+
+    fn apply(value: &i64, update: &i64, len: usize) -> i64 {
+        value + update * len as i64
+    }
+
+    fn compose(older: &i64, newer: &i64) -> i64 {
+        older + newer
+    }
+}
+
+/// Range minimums with range additions.
+pub struct MinAdd;
+
+impl LazyOp<i64> for MinAdd {
+    type Update = i64;
+
+    fn identity() -> i64 {
+        i64::MAX
+    }
+
+    fn combine(a: &i64, b: &i64) -> i64 {
+        *a.min(b)
+    }
+
+    fn apply(value: &i64, update: &i64, _len: usize) -> i64 {
+        value.saturating_add(*update)
+    }
+
+    fn compose(older: &i64, newer: &i64) -> i64 {
+        older.saturating_add(*newer)
+    }
+}
+
+/// Range maximums with range assignments.
+pub struct MaxAssign;
+
+impl LazyOp<i64> for MaxAssign {
+    type Update = i64;
+
+    fn identity() -> i64 {
+        i64::MIN
+    }
+
+    fn combine(a: &i64, b: &i64) -> i64 {
+        *a.max(b)
+    }
+
+    fn apply(_value: &i64, update: &i64, _len: usize) -> i64 {
+        *update
+    }
+
+    fn compose(_older: &i64, newer: &i64) -> i64 {
+        *newer
+    }
+}
+
+/// A segment tree over a fixed number of elements, answering `Op::combine`
+/// over any index range in O(log n). Range updates are recorded lazily at the
+/// highest covering nodes and only pushed down when a query or update needs
+/// to look inside them.
+pub struct SegmentTree<T, Op: LazyOp<T>> {
+    len: usize,
+    tree: Vec<T>,
+    lazy: Vec<Option<Op::Update>>,
+    _op: PhantomData<Op>,
+}
+
+impl<T: Clone, Op: LazyOp<T>> SegmentTree<T, Op> {
+    pub fn new(values: Vec<T>) -> Self {
+        let len = values.len();
+        let size = 4 * len.max(1);
+        let mut tree = SegmentTree {
+            len,
+            tree: vec![Op::identity(); size],
+            lazy: vec![None; size],
+            _op: PhantomData,
+        };
+        if len > 0 {
+            tree.build(1, 0, len, &values);
+        }
+        tree
+    }
+
+    fn build(&mut self, node: usize, lo: usize, hi: usize, values: &[T]) {
+        if hi - lo == 1 {
+            self.tree[node] = values[lo].clone();
+            return;
+        }
+        let mid = (lo + hi) / 2;
+        self.build(2 * node, lo, mid, values);
+        self.build(2 * node + 1, mid, hi, values);
+        self.tree[node] = Op::combine(&self.tree[2 * node], &self.tree[2 * node + 1]);
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn apply(&mut self, node: usize, update: &Op::Update, len: usize) {
+        self.tree[node] = Op::apply(&self.tree[node], update, len);
+        self.lazy[node] = Some(match &self.lazy[node] {
+            Some(older) => Op::compose(older, update),
+            None => update.clone(),
+        });
+    }
+
+    fn push_down(&mut self, node: usize, lo: usize, hi: usize) {
+        if let Some(update) = self.lazy[node].take() {
+            let mid = (lo + hi) / 2;
+            self.apply(2 * node, &update, mid - lo);
+            self.apply(2 * node + 1, &update, hi - mid);
+        }
+    }
+
+    fn check_range(&self, range: &Range<usize>) {
+        assert!(
+            range.start <= range.end && range.end <= self.len,
+            "range {range:?} out of bounds for length {}",
+            self.len
+        );
+    }
+
+    /// Combine the elements in `range`. Returns the identity for an empty range.
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn query(&mut self, range: Range<usize>) -> T {
+        self.check_range(&range);
+        if range.is_empty() {
+            return Op::identity();
+        }
+        self.query_node(1, 0, self.len, &range)
+    }
+
+    /// `query`, but returning an error if the range is out of bounds.
+    pub fn try_query(&mut self, range: Range<usize>) -> Result<T, CollectionError> {
+        check_range(&range, self.len)?;
+        Ok(self.query(range))
+    }
+
+    fn query_node(&mut self, node: usize, lo: usize, hi: usize, range: &Range<usize>) -> T {
+        if range.end <= lo || hi <= range.start {
+            return Op::identity();
+        }
+        if range.start <= lo && hi <= range.end {
+            return self.tree[node].clone();
+        }
+        self.push_down(node, lo, hi);
+        let mid = (lo + hi) / 2;
+        let left = self.query_node(2 * node, lo, mid, range);
+        let right = self.query_node(2 * node + 1, mid, hi, range);
+        Op::combine(&left, &right)
+    }
+
+    /// Apply `update` to every element in `range`.
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn update(&mut self, range: Range<usize>, update: Op::Update) {
+        self.check_range(&range);
+        if !range.is_empty() {
+            self.update_node(1, 0, self.len, &range, &update);
+        }
+    }
+
+    /// `update`, but returning an error if the range is out of bounds.
+    pub fn try_update(&mut self, range: Range<usize>, update: Op::Update) -> Result<(), CollectionError> {
+        check_range(&range, self.len)?;
+        self.update(range, update);
+        Ok(())
+    }
+
+    fn update_node(&mut self, node: usize, lo: usize, hi: usize, range: &Range<usize>, update: &Op::Update) {
+        if range.end <= lo || hi <= range.start {
+            return;
+        }
+        if range.start <= lo && hi <= range.end {
+            self.apply(node, update, hi - lo);
+            return;
+        }
+        self.push_down(node, lo, hi);
+        let mid = (lo + hi) / 2;
+        self.update_node(2 * node, lo, mid, range, update);
+        self.update_node(2 * node + 1, mid, hi, range, update);
+        self.tree[node] = Op::combine(&self.tree[2 * node], &self.tree[2 * node + 1]);
+    }
+
+    /// Replace the element at `index`.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set(&mut self, index: usize, value: T) {
+        assert!(index < self.len, "index (is {index}) should be < len (is {})", self.len);
+        self.set_node(1, 0, self.len, index, value);
+    }
+
+    /// `set`, but returning an error if `index` is out of bounds.
+    pub fn try_set(&mut self, index: usize, value: T) -> Result<(), CollectionError> {
+        check_index(index, self.len)?;
+        self.set(index, value);
+        Ok(())
+    }
+
+    fn set_node(&mut self, node: usize, lo: usize, hi: usize, index: usize, value: T) {
+        if hi - lo == 1 {
+            self.tree[node] = value;
+            self.lazy[node] = None;
+            return;
+        }
+        self.push_down(node, lo, hi);
+        let mid = (lo + hi) / 2;
+        if index < mid {
+            self.set_node(2 * node, lo, mid, index, value);
+        } else {
+            self.set_node(2 * node + 1, mid, hi, index, value);
+        }
+        self.tree[node] = Op::combine(&self.tree[2 * node], &self.tree[2 * node + 1]);
+    }
+
+    /// The current value of the element at `index`.
+    pub fn get(&mut self, index: usize) -> T {
+        self.query(index..index + 1)
+    }
+}
+
+impl<T: HeapSize, Op: LazyOp<T>> HeapSize for SegmentTree<T, Op>
+where
+    Op::Update: HeapSize,
+{
+    fn heap_size(&self) -> usize {
+        self.tree.heap_size() + self.lazy.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::CollectionError;
+    use crate::heap_size::HeapSize;
+
+    use super::{MaxAssign, MinAdd, SegmentTree, SumAdd};
+
+    #[test]
+    fn test_empty() {
+        let mut tree: SegmentTree<i64, SumAdd> = SegmentTree::new(Vec::new());
+        assert!(tree.is_empty());
+        assert_eq!(tree.query(0..0), 0);
+    }
+
+    #[test]
+    fn test_sum_query() {
+        let mut tree: SegmentTree<i64, SumAdd> = SegmentTree::new(vec![1, 2, 3, 4, 5]);
+        assert_eq!(tree.len(), 5);
+        assert_eq!(tree.query(0..5), 15);
+        assert_eq!(tree.query(1..4), 9);
+        assert_eq!(tree.query(2..3), 3);
+        assert_eq!(tree.query(3..3), 0);
+    }
+
+    #[test]
+    fn test_range_add() {
+        let mut tree: SegmentTree<i64, SumAdd> = SegmentTree::new(vec![0; 8]);
+        tree.update(2..6, 3);
+        tree.update(0..4, 1);
+        assert_eq!(tree.query(0..8), 4 * 3 + 4);
+        assert_eq!(tree.query(3..5), 3 + 1 + 3);
+        assert_eq!(tree.get(0), 1);
+        assert_eq!(tree.get(5), 3);
+        assert_eq!(tree.get(7), 0);
+    }
+
+    #[test]
+    fn test_point_set() {
+        let mut tree: SegmentTree<i64, MinAdd> = SegmentTree::new(vec![5, 3, 8, 6]);
+        assert_eq!(tree.query(0..4), 3);
+        tree.update(0..2, 10);
+        assert_eq!(tree.query(0..4), 6);
+        tree.set(1, -1);
+        assert_eq!(tree.query(0..4), -1);
+        assert_eq!(tree.get(0), 15);
+        assert_eq!(tree.query(2..4), 6);
+        assert_eq!(tree.try_set(4, 0), Err(CollectionError::IndexOutOfBounds { index: 4, len: 4 }));
+        assert_eq!(tree.try_set(3, 0), Ok(()));
+        assert_eq!(tree.query(2..4), 0);
+        assert_eq!(tree.try_update(3..5, 1), Err(CollectionError::IndexOutOfBounds { index: 5, len: 4 }));
+        assert_eq!(tree.try_query(3..5), Err(CollectionError::IndexOutOfBounds { index: 5, len: 4 }));
+        assert_eq!(tree.try_update(2..4, 1), Ok(()));
+        assert_eq!(tree.try_query(2..4), Ok(1));
+    }
+
+    #[test]
+    fn test_min_add_saturates() {
+        // Both adds stay pending on the root, so their tags are composed
+        // before either reaches a value
+        let mut tree: SegmentTree<i64, MinAdd> = SegmentTree::new(vec![0; 4]);
+        tree.update(0..4, i64::MAX / 2 + 1);
+        tree.update(0..4, i64::MAX / 2 + 1);
+        assert_eq!(tree.query(0..4), i64::MAX);
+        assert_eq!(tree.query(1..2), i64::MAX);
+    }
+
+    #[test]
+    fn test_max_assign() {
+        let mut tree: SegmentTree<i64, MaxAssign> = SegmentTree::new(vec![1, 9, 2, 7, 3]);
+        assert_eq!(tree.query(0..5), 9);
+        tree.update(1..3, 0);
+        assert_eq!(tree.query(0..5), 7);
+        tree.update(0..5, 4);
+        tree.update(2..3, 5);
+        assert_eq!(tree.query(0..2), 4);
+        assert_eq!(tree.query(0..5), 5);
+    }
+
+    #[test]
+    fn test_matches_naive() {
+        let n = 37;
+        let mut naive: Vec<i64> = (0..n as i64).map(|i| (i * 17) % 11).collect();
+        let mut tree: SegmentTree<i64, SumAdd> = SegmentTree::new(naive.clone());
+        for step in 0..200usize {
+            let a = (step * 7) % n;
+            let b = (step * 13) % (n + 1);
+            let range = a.min(b)..a.max(b);
+            if step % 3 == 0 {
+                let delta = step as i64 % 5 - 2;
+                tree.update(range.clone(), delta);
+                naive[range].iter_mut().for_each(|x| *x += delta);
+            } else {
+                assert_eq!(tree.query(range.clone()), naive[range].iter().sum::<i64>());
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_out_of_bounds() {
+        let mut tree: SegmentTree<i64, SumAdd> = SegmentTree::new(vec![1, 2, 3]);
+        tree.query(1..4);
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let tree: SegmentTree<i64, SumAdd> = SegmentTree::new(vec![1, 2, 3, 4]);
+        assert_eq!(tree.heap_size(), 16 * size_of::<i64>() + 16 * size_of::<Option<i64>>());
+    }
+}
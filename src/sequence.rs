@@ -0,0 +1,347 @@
+use std::collections::VecDeque;
+use std::ops::Deref;
+
+/// The operations every sequence in the crate shares, so generic code and
+/// benchmarks can be written once and run against each of them.
+///
+/// Not every implementation is O(1) at both ends: a singly linked `List`
+/// walks the whole list to reach its back. Each implementation documents
+/// its own costs.
+pub trait Sequence<T> {
+    type Iter<'a>: Iterator<Item = &'a T>
+    where
+        Self: 'a,
+        T: 'a;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn push_front(&mut self, val: T);
+
+    fn push_back(&mut self, val: T);
+
+    fn pop_front(&mut self) -> Option<T>;
+
+    fn pop_back(&mut self) -> Option<T>;
+
+    /// Iterate from front to back.
+    fn iter(&self) -> Self::Iter<'_>;
+
+    /// Panic if the sequence's internal invariants don't hold. A no-op for
+    /// sequences with none worth checking. Only in debug builds and tests.
+    #[cfg(any(test, debug_assertions))]
+    fn validate(&self) {}
+
+    /// Overlapping windows of `size` consecutive elements, front to back, as
+    /// with `slice::windows`. Panics if `size` is 0.
+    fn windows(&self, size: usize) -> Windows<'_, Self::Iter<'_>, T> {
+        Windows::new(self.iter(), size)
+    }
+}
+
+/// An iterator whose items borrow from the iterator itself, so each one has
+/// to be dropped before the next is asked for. Loop with `while let`.
+pub trait LendingIterator {
+    type Item<'a>
+    where
+        Self: 'a;
+
+    fn next(&mut self) -> Option<Self::Item<'_>>;
+}
+
+/// Overlapping windows over an iterator of references, each yielded as a
+/// slice of references into a buffer the iterator keeps, since a linked
+/// sequence has no slice of its own to lend out.
+pub struct Windows<'a, I, T> {
+    iter: I,
+    size: usize,
+    // The current window is `buf[start..]`. Kept under twice `size` long by
+    // shifting it down once `start` reaches `size`
+    buf: Vec<&'a T>,
+    start: usize,
+}
+
+impl<'a, I: Iterator<Item = &'a T>, T> Windows<'a, I, T> {
+    pub fn new(iter: I, size: usize) -> Self {
+        assert!(size > 0, "window size must be non-zero");
+        Windows { iter, size, buf: Vec::with_capacity(2 * size), start: 0 }
+    }
+}
+
+impl<'a, I: Iterator<Item = &'a T>, T> LendingIterator for Windows<'a, I, T> {
+    type Item<'w>
+        = &'w [&'a T]
+    where
+        Self: 'w;
+
+    fn next(&mut self) -> Option<Self::Item<'_>> {
+        if self.buf.len() - self.start == self.size {
+            self.start += 1;
+        }
+        if self.start == self.size {
+            self.buf.drain(..self.size);
+            self.start = 0;
+        }
+        while self.buf.len() - self.start < self.size {
+            self.buf.push(self.iter.next()?);
+        }
+        Some(&self.buf[self.start..])
+    }
+}
+
+/// A position in a sequence that can step along it and edit it in place,
+/// shared by the cursors of `List`, `LinkedList`, `GapBuffer` and
+/// `UnrolledList`, so algorithms written against a cursor work on any of
+/// them.
+///
+/// Besides pointing at an element, a cursor can sit on a "ghost" position
+/// between the back and the front. From the ghost, `next` moves to the front
+/// and `prev` to the back, `insert_after` inserts at the front and
+/// `insert_before` at the back.
+pub trait SeqCursor<T> {
+    /// Take a mutable reference to the current element
+    fn peek_mut(&mut self) -> Option<&mut T>;
+
+    /// Move one position forward (towards the back) and
+    /// return a reference to the new position
+    fn next(&mut self) -> Option<&mut T>;
+
+    /// Move one position backward (towards the front) and
+    /// return a reference to the new position
+    fn prev(&mut self) -> Option<&mut T>;
+
+    /// Remove and return the current element, moving the cursor to the next
+    /// element, or to the previous one if it was the back.
+    fn take(&mut self) -> Option<T>;
+
+    /// Insert an element after the current one. The cursor does not move.
+    fn insert_after(&mut self, element: T);
+
+    /// Insert an element before the current one. The cursor does not move.
+    fn insert_before(&mut self, element: T);
+}
+
+/// A cursor whose current element is lent out for as long as the cursor is
+/// borrowed, through a GAT, so generic code can walk any of the crate's
+/// cursors whether they hand out `&mut T` like the sequence cursors or `&T`
+/// like `ListZipper`.
+///
+/// The item derefs to `Elem`, which is what generic code bounds on: a
+/// higher-ranked bound on the item itself would force the cursor to be
+/// `'static`.
+pub trait LendingCursor {
+    type Elem;
+
+    type Item<'a>: Deref<Target = Self::Elem>
+    where
+        Self: 'a;
+
+    /// The current element, or `None` if there isn't one, such as on the
+    /// ghost position of a sequence cursor.
+    fn peek(&mut self) -> Option<Self::Item<'_>>;
+
+    /// Move one position towards the back. Returns `false`, without moving,
+    /// if there is nowhere to move to.
+    fn move_next(&mut self) -> bool;
+
+    /// Move one position towards the front. Returns `false`, without moving,
+    /// if there is nowhere to move to.
+    fn move_prev(&mut self) -> bool;
+}
+
+// The standard deque, as a reference to compare the crate's sequences with
+impl<T> Sequence<T> for VecDeque<T> {
+    type Iter<'a>
+        = std::collections::vec_deque::Iter<'a, T>
+    where
+        T: 'a;
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn push_front(&mut self, val: T) {
+        self.push_front(val);
+    }
+
+    fn push_back(&mut self, val: T) {
+        self.push_back(val);
+    }
+
+    fn pop_front(&mut self) -> Option<T> {
+        self.pop_front()
+    }
+
+    fn pop_back(&mut self) -> Option<T> {
+        self.pop_back()
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use crate::chunked_seq::ChunkedSeq;
+    use crate::cow_list::CowList;
+    use crate::doubly_list::LinkedList;
+    use crate::gap_buffer::GapBuffer;
+    use crate::index_list::IndexList;
+    use crate::list::List;
+    use crate::model::check_sequence;
+    use crate::unrolled_list::UnrolledList;
+    use crate::zipper::ListZipper;
+
+    use super::{LendingCursor, LendingIterator, SeqCursor, Sequence, Windows};
+
+    #[test]
+    fn test_every_sequence() {
+        check_sequence::<VecDeque<u64>>();
+        check_sequence::<List<u64>>();
+        check_sequence::<LinkedList<u64>>();
+        check_sequence::<UnrolledList<u64, 8>>();
+        check_sequence::<ChunkedSeq<u64>>();
+        check_sequence::<IndexList<u64>>();
+    }
+
+    // One edit script, written once against the trait, run on every cursor.
+    // Starts on the front of 1..=6.
+    fn edit<C: SeqCursor<i32>>(mut cursor: C) {
+        // Drop the even elements
+        while let Some(val) = cursor.peek_mut() {
+            if *val % 2 == 0 {
+                cursor.take();
+            }
+            if cursor.next().is_none() {
+                break;
+            }
+        }
+        // 1 3 5, on the ghost
+        cursor.insert_before(0);
+        assert_eq!(cursor.next(), Some(&mut 1));
+        cursor.insert_before(-1);
+        cursor.insert_after(2);
+        assert_eq!(cursor.peek_mut(), Some(&mut 1));
+        assert_eq!(cursor.prev(), Some(&mut -1));
+        assert_eq!(cursor.prev(), None);
+        assert_eq!(cursor.prev(), Some(&mut 0));
+        assert_eq!(cursor.take(), Some(0));
+        // Taking the back moves back
+        assert_eq!(cursor.peek_mut(), Some(&mut 5));
+        *cursor.peek_mut().unwrap() = 4;
+    }
+
+    #[test]
+    fn test_every_cursor() {
+        let expected = [-1, 1, 2, 3, 4];
+        let mut list: List<_> = List::new();
+        (1..=6).rev().for_each(|i| list.push_front(i));
+        edit(list.cursor_front());
+        assert!(list.iter().eq(expected.iter()));
+        assert_eq!(list.len(), 5);
+
+        let mut list = LinkedList::new();
+        (1..=6).for_each(|i| list.push_back(i));
+        edit(list.cursor_front());
+        assert!(list.iter().eq(expected.iter()));
+        assert_eq!(list.len(), 5);
+
+        let mut buffer: GapBuffer<_> = (1..=6).collect();
+        edit(buffer.cursor_front());
+        assert!(buffer.iter().eq(expected.iter()));
+
+        let mut list: UnrolledList<_, 4> = (1..=6).collect();
+        edit(list.cursor_front());
+        assert!(list.iter().eq(expected.iter()));
+    }
+
+    #[test]
+    fn test_windows() {
+        let mut list: List<_> = List::new();
+        (1..=5).rev().for_each(|i| list.push_front(i));
+        let mut sums = Vec::new();
+        let mut windows = list.windows(3);
+        while let Some(window) = windows.next() {
+            sums.push(window.iter().copied().sum::<i32>());
+        }
+        assert_eq!(sums, [6, 9, 12]);
+
+        // Long enough to shift the buffer down a few times
+        let mut list = LinkedList::new();
+        (0..20).for_each(|i| list.push_back(i));
+        let mut windows = list.windows(4);
+        let mut first = 0;
+        while let Some(window) = windows.next() {
+            assert!(window.iter().copied().eq(&(first..first + 4).collect::<Vec<_>>()));
+            first += 1;
+        }
+        assert_eq!(first, 17);
+
+        assert!(list.windows(20).next().is_some());
+        assert!(list.windows(21).next().is_none());
+        let mut windows = Windows::new([1, 2].iter(), 1);
+        assert_eq!(windows.next(), Some(&[&1][..]));
+        assert_eq!(windows.next(), Some(&[&2][..]));
+        assert_eq!(windows.next(), None);
+        assert_eq!(windows.next(), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty_windows() {
+        Windows::new([1].iter(), 0);
+    }
+
+    // The elements from the cursor to the back, for any kind of cursor
+    fn rest<C: LendingCursor<Elem = i32>>(mut cursor: C) -> Vec<i32> {
+        let mut rest = Vec::new();
+        loop {
+            match cursor.peek() {
+                Some(val) => rest.push(*val),
+                None => break,
+            }
+            if !cursor.move_next() {
+                break;
+            }
+        }
+        rest
+    }
+
+    #[test]
+    fn test_lending_cursors() {
+        let mut list: List<_> = List::new();
+        (1..=4).rev().for_each(|i| list.push_front(i));
+        let mut cursor = list.cursor_front();
+        assert!(cursor.move_next());
+        assert_eq!(rest(cursor), [2, 3, 4]);
+
+        let mut list = LinkedList::new();
+        (1..=4).for_each(|i| list.push_back(i));
+        let mut cursor = list.cursor_front();
+        // Around through the ghost to the back
+        assert!(cursor.move_prev() && cursor.move_prev());
+        assert_eq!(rest(cursor), [4]);
+
+        let mut buffer: GapBuffer<_> = (1..=4).collect();
+        assert_eq!(rest(buffer.cursor_front()), [1, 2, 3, 4]);
+
+        let mut list: UnrolledList<_, 2> = (1..=4).collect();
+        let mut cursor = list.cursor_back();
+        assert!(cursor.move_prev());
+        *LendingCursor::peek(&mut cursor).unwrap() = 0;
+        assert_eq!(rest(cursor), [0, 4]);
+
+        let zipper = ListZipper::new(CowList::from_iter(1..=4)).unwrap();
+        let mut cursor = zipper.clone();
+        assert!(!cursor.move_prev());
+        assert_eq!(rest(cursor), [1, 2, 3, 4]);
+        // Moving leaves the zipper it started from as it was
+        assert_eq!(*zipper.focus(), 1);
+    }
+}
@@ -0,0 +1,282 @@
+use std::borrow::Borrow;
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash, RandomState};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::hash_map::{Entry, HashMap};
+use crate::heap_size::HeapSize;
+
+const DEFAULT_SHARDS: usize = 16;
+
+/// A hash map for many threads, split into independently locked shards.
+///
+/// Each key belongs to one shard, chosen by the top bits of its hash, and
+/// each shard is one of the crate's `HashMap`s behind an `RwLock`. Readers
+/// never block each other, and writers only block threads working in the
+/// same shard, so with enough shards most operations never contend. Methods
+/// take `&self`; share the map with an `Arc`.
+///
+/// Values are returned as clones, or lent to a closure with `get_with` and
+/// `update`, since no reference can outlive the shard's lock.
+pub struct ShardedMap<K, V> {
+    shards: Box<[RwLock<HashMap<K, V>>]>,
+    // Chooses the shard; each shard hashes again with its own keys
+    hasher: RandomState,
+    shift: u32,
+}
+
+impl<K: Hash + Eq, V> ShardedMap<K, V> {
+    /// A map with at least `shards` shards, rounded up to a power of two.
+    ///
+    /// Panics if `shards` is zero.
+    pub fn new(shards: usize) -> Self {
+        assert!(shards > 0, "sharded map needs at least one shard");
+        let shards = shards.next_power_of_two();
+        ShardedMap {
+            shards: (0..shards).map(|_| RwLock::new(HashMap::new())).collect(),
+            hasher: RandomState::new(),
+            shift: u64::BITS - shards.trailing_zeros(),
+        }
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    fn shard_index<Q: Hash + ?Sized>(&self, key: &Q) -> usize {
+        // checked_shr: a single shard would shift by all 64 bits
+        self.hasher.hash_one(key).checked_shr(self.shift).unwrap_or(0) as usize
+    }
+
+    fn read<Q: Hash + ?Sized>(&self, key: &Q) -> RwLockReadGuard<'_, HashMap<K, V>> {
+        self.shards[self.shard_index(key)].read().unwrap()
+    }
+
+    fn write<Q: Hash + ?Sized>(&self, key: &Q) -> RwLockWriteGuard<'_, HashMap<K, V>> {
+        self.shards[self.shard_index(key)].write().unwrap()
+    }
+
+    /// Number of entries, counted one shard at a time, so only a snapshot
+    /// while other threads are writing.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.read().unwrap().len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| shard.read().unwrap().is_empty())
+    }
+
+    pub fn clear(&self) {
+        for shard in &self.shards {
+            shard.write().unwrap().clear();
+        }
+    }
+
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.write(&key).insert(key, value)
+    }
+
+    pub fn get<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        V: Clone,
+    {
+        self.read(key).get(key).cloned()
+    }
+
+    /// Call `f` on the value for `key` under the shard's read lock.
+    pub fn get_with<Q: Hash + Eq + ?Sized, R>(&self, key: &Q, f: impl FnOnce(&V) -> R) -> Option<R>
+    where
+        K: Borrow<Q>,
+    {
+        self.read(key).get(key).map(f)
+    }
+
+    /// Call `f` on the value for `key` under the shard's write lock.
+    pub fn update<Q: Hash + Eq + ?Sized, R>(&self, key: &Q, f: impl FnOnce(&mut V) -> R) -> Option<R>
+    where
+        K: Borrow<Q>,
+    {
+        self.write(key).get_mut(key).map(f)
+    }
+
+    /// The value for `key`, inserting `f()` first if there is none. `f` runs
+    /// under the shard's write lock, so it is called at most once per key
+    /// even when threads race.
+    pub fn get_or_insert_with(&self, key: K, f: impl FnOnce() -> V) -> V
+    where
+        V: Clone,
+    {
+        if let Some(value) = self.get(&key) {
+            return value;
+        }
+        match self.write(&key).entry(key) {
+            Entry::Occupied(entry) => entry.get().clone(),
+            Entry::Vacant(entry) => entry.insert(f()).clone(),
+        }
+    }
+
+    pub fn contains_key<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        self.read(key).contains_key(key)
+    }
+
+    pub fn remove<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+    {
+        self.write(key).remove(key)
+    }
+
+    /// Call `f` on every entry, read-locking one shard at a time.
+    pub fn for_each(&self, mut f: impl FnMut(&K, &V)) {
+        for shard in &self.shards {
+            for (key, value) in shard.read().unwrap().iter() {
+                f(key, value);
+            }
+        }
+    }
+
+    /// Remove every entry for which `f` returns false, write-locking one
+    /// shard at a time.
+    pub fn retain(&self, mut f: impl FnMut(&K, &mut V) -> bool) {
+        for shard in &self.shards {
+            shard.write().unwrap().retain(&mut f);
+        }
+    }
+}
+
+impl<K: Hash + Eq, V> Default for ShardedMap<K, V> {
+    fn default() -> Self {
+        Self::new(DEFAULT_SHARDS)
+    }
+}
+
+impl<K: Hash + Eq + Debug, V: Debug> Debug for ShardedMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut map = f.debug_map();
+        self.for_each(|key, value| {
+            map.entry(key, value);
+        });
+        map.finish()
+    }
+}
+
+/// Counts one shard at a time, so only a snapshot while other threads are
+/// writing.
+impl<K: HeapSize, V: HeapSize> HeapSize for ShardedMap<K, V> {
+    fn heap_size(&self) -> usize {
+        self.shards.len() * size_of::<RwLock<HashMap<K, V>>>()
+            + self.shards.iter().map(|shard| shard.read().unwrap().heap_size()).sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    use crate::heap_size::HeapSize;
+
+    use super::ShardedMap;
+
+    #[test]
+    fn test_basic_ops() {
+        let map = ShardedMap::new(5);
+        assert_eq!(map.shard_count(), 8);
+        assert_eq!(map.insert("a".to_string(), 1), None);
+        assert_eq!(map.insert("b".to_string(), 2), None);
+        assert_eq!(map.insert("a".to_string(), 10), Some(1));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get("a"), Some(10));
+        assert_eq!(map.get_with("b", |v| v * 100), Some(200));
+        assert_eq!(map.update("b", |v| std::mem::replace(v, 20)), Some(2));
+        assert_eq!(map.update("z", |v| *v += 1), None);
+        assert_eq!(map.get("b"), Some(20));
+
+        assert_eq!(map.remove("a"), Some(10));
+        assert!(!map.contains_key("a"));
+        map.clear();
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_single_shard() {
+        let map = ShardedMap::new(1);
+        for i in 0..100 {
+            map.insert(i, i);
+        }
+        map.retain(|k, v| {
+            *v *= 2;
+            k % 10 == 0
+        });
+        let mut entries = Vec::new();
+        map.for_each(|&k, &v| entries.push((k, v)));
+        entries.sort();
+        assert_eq!(entries, (0..100).step_by(10).map(|i| (i, i * 2)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_get_or_insert_with_once() {
+        let map = Arc::new(ShardedMap::default());
+        let calls = Arc::new(AtomicUsize::new(0));
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let map = map.clone();
+                let calls = calls.clone();
+                thread::spawn(move || {
+                    (0..500)
+                        .map(|i| {
+                            map.get_or_insert_with(i, || {
+                                calls.fetch_add(1, Ordering::Relaxed);
+                                i * i
+                            })
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), (0..500).map(|i| i * i).collect::<Vec<_>>());
+        }
+        assert_eq!(calls.load(Ordering::Relaxed), 500);
+        assert_eq!(map.len(), 500);
+    }
+
+    #[test]
+    fn test_concurrent_counters() {
+        let map = Arc::new(ShardedMap::new(4));
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    for i in 0..1000 {
+                        if map.update(&(i % 50), |count| *count += 1).is_none() {
+                            // Racing inserts of the same key: only the first wins
+                            map.get_or_insert_with(i % 50, || 0);
+                            map.update(&(i % 50), |count| *count += 1);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let mut total = 0;
+        map.for_each(|_, count| total += count);
+        assert_eq!(total, 4000);
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let map = ShardedMap::new(4);
+        let empty = map.heap_size();
+        assert!(empty >= 4 * size_of::<super::RwLock<super::HashMap<u32, String>>>());
+        map.insert(1, String::from("abc"));
+        assert!(map.heap_size() > empty + 3);
+    }
+}
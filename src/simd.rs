@@ -0,0 +1,60 @@
+// How many elements are compared per step. Wide enough to fill a vector
+// register for bytes, and the loop still unrolls for wider types.
+const LANES: usize = 16;
+
+/// Primitive element types that searches can compare many at a time.
+///
+/// `std::simd` is not yet stable, so the searches are instead written as
+/// fixed-width blocks of branch-free compares, which the compiler turns
+/// into vector compares for these types.
+pub trait Lane: Copy + PartialEq {}
+
+impl Lane for u8 {}
+impl Lane for u16 {}
+impl Lane for u32 {}
+impl Lane for u64 {}
+impl Lane for usize {}
+impl Lane for i8 {}
+impl Lane for i16 {}
+impl Lane for i32 {}
+impl Lane for i64 {}
+impl Lane for isize {}
+impl Lane for char {}
+impl Lane for f32 {}
+impl Lane for f64 {}
+
+/// Index of the first element of `slice` equal to `needle`.
+pub fn position<T: Lane>(slice: &[T], needle: T) -> Option<usize> {
+    let mut blocks = slice.chunks_exact(LANES);
+    let mut start = 0;
+    for block in &mut blocks {
+        // No early exit within a block, so it compiles to one vector compare
+        if block.iter().fold(false, |found, &val| found | (val == needle)) {
+            return block.iter().position(|&val| val == needle).map(|i| start + i);
+        }
+        start += LANES;
+    }
+    blocks.remainder().iter().position(|&val| val == needle).map(|i| start + i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::position;
+
+    #[test]
+    fn test_position() {
+        let vals: Vec<u8> = (0..100).collect();
+        for i in 0..100 {
+            assert_eq!(position(&vals, i), Some(i as usize));
+        }
+        assert_eq!(position(&vals, 100), None);
+        assert_eq!(position(&[] as &[u8], 0), None);
+        // First of several matches, in a block and in the remainder
+        assert_eq!(position(&[1i64, 2, 2, 2], 2), Some(1));
+        let mut vals = vec![0u32; 40];
+        vals[20] = 7;
+        vals[35] = 7;
+        assert_eq!(position(&vals, 7), Some(20));
+        assert_eq!(position(&[f64::NAN, 1.0], f64::NAN), None);
+    }
+}
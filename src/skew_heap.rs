@@ -0,0 +1,252 @@
+use std::fmt::Debug;
+
+use crate::heap_size::HeapSize;
+
+type Link<T> = Option<Box<Node<T>>>;
+
+struct Node<T> {
+    val: T,
+    left: Link<T>,
+    right: Link<T>,
+}
+
+/// Merge two heaps: walk down both right spines taking the smaller root each
+/// time, then hang each merged remainder on the right and swap the children.
+/// Iterative, since a single spine can be O(n) long even though the
+/// amortized length is O(log n).
+fn merge<T: Ord>(mut a: Link<T>, mut b: Link<T>) -> Link<T> {
+    let mut spine = Vec::new();
+    let mut rest = loop {
+        match (a, b) {
+            (None, rest) | (rest, None) => break rest,
+            (Some(x), Some(y)) => {
+                let (mut small, big) = if x.val <= y.val { (x, y) } else { (y, x) };
+                a = small.right.take();
+                b = Some(big);
+                spine.push(small);
+            }
+        }
+    };
+    while let Some(mut node) = spine.pop() {
+        node.right = rest;
+        std::mem::swap(&mut node.left, &mut node.right);
+        rest = Some(node);
+    }
+    rest
+}
+
+/// A min-heap implemented as a skew heap: a heap-ordered binary tree where
+/// every operation is a merge, and each merge swaps the children of the nodes
+/// it visits. Without storing any balance information, that keeps the right
+/// spines short enough for O(log n) amortized push, pop, and `append`.
+///
+/// Unlike `std::collections::BinaryHeap`, two heaps can be merged without
+/// copying either into the other.
+pub struct SkewHeap<T> {
+    root: Link<T>,
+    len: usize,
+}
+
+impl<T: Ord> SkewHeap<T> {
+    pub const fn new() -> Self {
+        SkewHeap { root: None, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Panic unless no element is smaller than its parent and `len` is
+    /// right. Only in debug builds and tests.
+    #[cfg(any(test, debug_assertions))]
+    pub fn validate(&self) {
+        // Iterative, since the tree can be O(n) deep
+        let mut count = 0;
+        let mut stack: Vec<&Node<T>> = self.root.as_deref().into_iter().collect();
+        while let Some(node) = stack.pop() {
+            count += 1;
+            for child in [&node.left, &node.right].into_iter().flatten() {
+                assert!(node.val <= child.val, "element smaller than its parent");
+                stack.push(child);
+            }
+        }
+        assert_eq!(count, self.len, "heap length is out of date");
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn push(&mut self, val: T) {
+        let node = Box::new(Node { val, left: None, right: None });
+        self.root = merge(self.root.take(), Some(node));
+        self.len += 1;
+    }
+
+    /// The smallest element.
+    pub fn peek(&self) -> Option<&T> {
+        self.root.as_ref().map(|node| &node.val)
+    }
+
+    /// Remove the smallest element.
+    pub fn pop(&mut self) -> Option<T> {
+        let mut root = self.root.take()?;
+        self.root = merge(root.left.take(), root.right.take());
+        self.len -= 1;
+        Some(root.val)
+    }
+
+    /// Move every element of `other` into `self`, leaving `other` empty.
+    pub fn append(&mut self, other: &mut Self) {
+        self.root = merge(self.root.take(), other.root.take());
+        self.len += std::mem::take(&mut other.len);
+    }
+
+    /// The elements in ascending order.
+    pub fn into_sorted_vec(mut self) -> Vec<T> {
+        let mut sorted = Vec::with_capacity(self.len);
+        while let Some(val) = self.pop() {
+            sorted.push(val);
+        }
+        sorted
+    }
+
+    /// Every element, in no particular order.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { stack: self.root.as_deref().into_iter().collect(), remaining: self.len }
+    }
+}
+
+impl<T: Ord> Default for SkewHeap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> FromIterator<T> for SkewHeap<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut heap = Self::new();
+        for val in iter {
+            heap.push(val);
+        }
+        heap
+    }
+}
+
+impl<T> Drop for SkewHeap<T> {
+    // Drop iteratively: a skew heap's paths can be as long as the heap
+    fn drop(&mut self) {
+        let mut stack: Vec<Box<Node<T>>> = self.root.take().into_iter().collect();
+        while let Some(mut node) = stack.pop() {
+            stack.extend(node.left.take());
+            stack.extend(node.right.take());
+        }
+    }
+}
+
+/// Iterator over the elements of a `SkewHeap`, in no particular order.
+pub struct Iter<'a, T> {
+    stack: Vec<&'a Node<T>>,
+    remaining: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.stack.extend(node.left.as_deref());
+        self.stack.extend(node.right.as_deref());
+        self.remaining -= 1;
+        Some(&node.val)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T: Ord + Debug> Debug for SkewHeap<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Node<T> {
+    fn heap_size(&self) -> usize {
+        self.val.heap_size() + self.left.heap_size() + self.right.heap_size()
+    }
+}
+
+impl<T: HeapSize> HeapSize for SkewHeap<T> {
+    fn heap_size(&self) -> usize {
+        self.root.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rng::XorShift64;
+
+    use super::SkewHeap;
+
+    #[test]
+    fn test_push_pop() {
+        let mut heap = SkewHeap::new();
+        assert_eq!(heap.pop(), None);
+        for i in [5, 3, 8, 1, 9, 1] {
+            heap.push(i);
+        }
+        assert_eq!(heap.len(), 6);
+        assert_eq!(heap.peek(), Some(&1));
+        assert_eq!(heap.pop(), Some(1));
+        assert_eq!(heap.pop(), Some(1));
+        assert_eq!(heap.pop(), Some(3));
+        assert_eq!(heap.len(), 3);
+        heap.clear();
+        assert!(heap.is_empty());
+        assert_eq!(heap.peek(), None);
+    }
+
+    #[test]
+    fn test_append() {
+        let mut evens: SkewHeap<_> = (0..50).map(|i| i * 2).collect();
+        let mut odds: SkewHeap<_> = (0..50).map(|i| i * 2 + 1).collect();
+        evens.append(&mut odds);
+        evens.validate();
+        assert!(odds.is_empty());
+        assert_eq!(evens.len(), 100);
+        assert_eq!(evens.iter().len(), 100);
+        assert_eq!(evens.into_sorted_vec(), (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_heap_sort_random() {
+        let mut rng = XorShift64::new();
+        let vals: Vec<u64> = (0..5000).map(|_| rng.next_u64() % 1000).collect();
+        let heap: SkewHeap<_> = vals.iter().copied().collect();
+        let mut iterated: Vec<_> = heap.iter().copied().collect();
+        iterated.sort();
+        let mut sorted = vals;
+        sorted.sort();
+        assert_eq!(iterated, sorted);
+        assert_eq!(heap.into_sorted_vec(), sorted);
+    }
+
+    #[test]
+    fn test_long_paths() {
+        // Descending pushes build one long path; neither merging nor
+        // dropping it may recurse that deep
+        let mut heap: SkewHeap<_> = (0..200_000).rev().collect();
+        assert_eq!(heap.pop(), Some(0));
+        let mut other: SkewHeap<_> = (0..200_000).collect();
+        heap.append(&mut other);
+        assert_eq!(heap.len(), 399_999);
+    }
+}
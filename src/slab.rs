@@ -0,0 +1,271 @@
+use std::fmt::Debug;
+use std::ops::{Index, IndexMut};
+
+use crate::error::CollectionError;
+use crate::heap_size::{HeapSize, payload};
+
+enum Slot<T> {
+    Occupied(T),
+    // Key of the next vacant slot in the free list
+    Vacant(Option<usize>),
+}
+
+/// Storage that hands out a `usize` key for each inserted value.
+///
+/// Values live in a `Vec` and keys are their indices, so lookups are a plain
+/// index. Removed slots are chained into a free list and reused by later
+/// inserts, making insert and remove O(1) and keeping the storage dense. A
+/// key is only meaningful until its value is removed; after that it may be
+/// handed out again (see `SlotMap` for keys that detect this).
+pub struct Slab<T> {
+    slots: Vec<Slot<T>>,
+    free: Option<usize>,
+    len: usize,
+}
+
+impl<T> Slab<T> {
+    pub const fn new() -> Self {
+        Slab { slots: Vec::new(), free: None, len: 0 }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Slab { slots: Vec::with_capacity(capacity), free: None, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Panic unless the free list visits every vacant slot exactly once and
+    /// `len` counts the occupied ones. Only in debug builds and tests.
+    #[cfg(any(test, debug_assertions))]
+    pub fn validate(&self) {
+        let occupied = self.slots.iter().filter(|slot| matches!(slot, Slot::Occupied(_))).count();
+        assert_eq!(occupied, self.len, "slab length is out of date");
+        let mut seen = vec![false; self.slots.len()];
+        let mut free = self.free;
+        while let Some(key) = free {
+            assert!(!std::mem::replace(&mut seen[key], true), "cycle in the free list");
+            let Slot::Vacant(next) = self.slots[key] else {
+                panic!("free list points at an occupied slot");
+            };
+            free = next;
+        }
+        assert_eq!(seen.iter().filter(|&&seen| seen).count(), self.slots.len() - self.len, "vacant slot off the free list");
+    }
+
+    /// Number of values the slab can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.slots.capacity()
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.free = None;
+        self.len = 0;
+    }
+
+    /// The key the next `insert` will return.
+    pub fn vacant_key(&self) -> usize {
+        self.free.unwrap_or(self.slots.len())
+    }
+
+    pub fn insert(&mut self, value: T) -> usize {
+        let key = self.vacant_key();
+        match self.free {
+            Some(key) => {
+                let Slot::Vacant(next) = self.slots[key] else {
+                    unreachable!("free list points at an occupied slot");
+                };
+                self.free = next;
+                self.slots[key] = Slot::Occupied(value);
+            }
+            None => self.slots.push(Slot::Occupied(value)),
+        }
+        self.len += 1;
+        key
+    }
+
+    pub fn remove(&mut self, key: usize) -> Option<T> {
+        let slot = self.slots.get_mut(key)?;
+        if let Slot::Vacant(_) = slot {
+            return None;
+        }
+        let Slot::Occupied(value) = std::mem::replace(slot, Slot::Vacant(self.free)) else {
+            unreachable!();
+        };
+        self.free = Some(key);
+        self.len -= 1;
+        Some(value)
+    }
+
+    pub fn contains(&self, key: usize) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn get(&self, key: usize) -> Option<&T> {
+        match self.slots.get(key)? {
+            Slot::Occupied(value) => Some(value),
+            Slot::Vacant(_) => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, key: usize) -> Option<&mut T> {
+        match self.slots.get_mut(key)? {
+            Slot::Occupied(value) => Some(value),
+            Slot::Vacant(_) => None,
+        }
+    }
+
+    /// `slab[key]`, but returning an error if `key` is vacant.
+    pub fn try_get(&self, key: usize) -> Result<&T, CollectionError> {
+        self.get(key).ok_or(CollectionError::StaleHandle)
+    }
+
+    /// `&mut slab[key]`, but returning an error if `key` is vacant.
+    pub fn try_get_mut(&mut self, key: usize) -> Result<&mut T, CollectionError> {
+        self.get_mut(key).ok_or(CollectionError::StaleHandle)
+    }
+
+    /// Every key and value in key order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.slots.iter().enumerate().filter_map(|(key, slot)| match slot {
+            Slot::Occupied(value) => Some((key, value)),
+            Slot::Vacant(_) => None,
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut T)> {
+        self.slots.iter_mut().enumerate().filter_map(|(key, slot)| match slot {
+            Slot::Occupied(value) => Some((key, value)),
+            Slot::Vacant(_) => None,
+        })
+    }
+
+    /// Remove every value for which `f` returns false.
+    pub fn retain<F: FnMut(usize, &mut T) -> bool>(&mut self, mut f: F) {
+        for key in 0..self.slots.len() {
+            if let Slot::Occupied(value) = &mut self.slots[key]
+                && !f(key, value)
+            {
+                self.remove(key);
+            }
+        }
+    }
+}
+
+impl<T> Default for Slab<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Index<usize> for Slab<T> {
+    type Output = T;
+
+    /// Panics if `key` is vacant.
+    fn index(&self, key: usize) -> &T {
+        self.get(key).expect("invalid slab key")
+    }
+}
+
+impl<T> IndexMut<usize> for Slab<T> {
+    fn index_mut(&mut self, key: usize) -> &mut T {
+        self.get_mut(key).expect("invalid slab key")
+    }
+}
+
+impl<T: Debug> Debug for Slab<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Slab<T> {
+    fn heap_size(&self) -> usize {
+        self.slots.capacity() * size_of::<Slot<T>>() + payload(self.iter().map(|(_, val)| val))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::CollectionError;
+
+    use super::Slab;
+
+    #[test]
+    fn test_insert_get_remove() {
+        let mut slab = Slab::new();
+        let a = slab.insert("a");
+        let b = slab.insert("b");
+        let c = slab.insert("c");
+        assert_eq!((a, b, c), (0, 1, 2));
+        assert_eq!(slab.len(), 3);
+        assert_eq!(slab[b], "b");
+        assert_eq!(slab.get(3), None);
+
+        assert_eq!(slab.remove(b), Some("b"));
+        assert_eq!(slab.remove(b), None);
+        assert_eq!(slab.remove(100), None);
+        slab.validate();
+        assert!(!slab.contains(b));
+        assert_eq!(slab.try_get(b), Err(CollectionError::StaleHandle));
+        assert_eq!(slab.try_get_mut(b), Err(CollectionError::StaleHandle));
+        assert_eq!(slab.try_get(a), Ok(&"a"));
+        assert_eq!(slab.len(), 2);
+
+        slab[c] = "C";
+        assert_eq!(slab.iter().collect::<Vec<_>>(), vec![(0, &"a"), (2, &"C")]);
+    }
+
+    #[test]
+    fn test_key_reuse() {
+        let mut slab = Slab::new();
+        let keys: Vec<_> = (0..5).map(|i| slab.insert(i)).collect();
+        slab.remove(keys[1]);
+        slab.remove(keys[3]);
+        slab.validate();
+        // Most recently freed first
+        assert_eq!(slab.vacant_key(), 3);
+        assert_eq!(slab.insert(30), 3);
+        assert_eq!(slab.insert(10), 1);
+        assert_eq!(slab.insert(50), 5);
+        assert_eq!(slab.iter().map(|(_, v)| *v).collect::<Vec<_>>(), vec![0, 10, 2, 30, 4, 50]);
+        slab.validate();
+    }
+
+    #[test]
+    fn test_retain_and_clear() {
+        let mut slab: Slab<i32> = Slab::with_capacity(10);
+        assert!(slab.capacity() >= 10);
+        for i in 0..10 {
+            slab.insert(i);
+        }
+        slab.retain(|key, v| {
+            *v *= 10;
+            key % 3 == 0
+        });
+        assert_eq!(slab.iter().map(|(_, v)| *v).collect::<Vec<_>>(), vec![0, 30, 60, 90]);
+        slab.validate();
+        for (_, v) in slab.iter_mut() {
+            *v += 1;
+        }
+        assert_eq!(slab[9], 91);
+        slab.clear();
+        assert!(slab.is_empty());
+        assert_eq!(slab.insert(7), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_index_vacant() {
+        let mut slab = Slab::new();
+        let key = slab.insert(1);
+        slab.remove(key);
+        let _ = slab[key];
+    }
+}
@@ -0,0 +1,229 @@
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+use crate::heap_size::HeapSize;
+
+/// An associative `combine` with an `identity`, the operation a
+/// `SlidingWindow` aggregates. It need not be commutative; the window always
+/// combines its items oldest first.
+pub trait Monoid<T> {
+    fn identity() -> T;
+
+    fn combine(a: &T, b: &T) -> T;
+}
+
+/// Sums.
+pub struct Sum;
+
+impl Monoid<i64> for Sum {
+    fn identity() -> i64 {
+        0
+    }
+
+    fn combine(a: &i64, b: &i64) -> i64 {
+        a + b
+    }
+}
+
+/// Minimums.
+pub struct Min;
+
+impl Monoid<i64> for Min {
+    fn identity() -> i64 {
+        i64::MAX
+    }
+
+    fn combine(a: &i64, b: &i64) -> i64 {
+        *a.min(b)
+    }
+}
+
+/// Maximums.
+pub struct Max;
+
+impl Monoid<i64> for Max {
+    fn identity() -> i64 {
+        i64::MIN
+    }
+
+    fn combine(a: &i64, b: &i64) -> i64 {
+        *a.max(b)
+    }
+}
+
+/// The aggregate of the last `size` items pushed onto a stream, under any
+/// monoid `Op`, in amortized O(1) per push.
+///
+/// This is the two-stack sliding window aggregator. New items go on a back
+/// stack that keeps one running aggregate. Old items leave from a front stack
+/// in which each entry stores the aggregate of itself and everything newer
+/// below it. When the front runs out, the back is flipped onto it, computing
+/// those suffix aggregates once per item. Nothing needs an inverse, so
+/// minimums and maximums work as well as sums.
+pub struct SlidingWindow<T, Op: Monoid<T>> {
+    // Oldest item on top, each with the aggregate from it to the newest
+    // item in the front stack
+    front: Vec<(T, T)>,
+    // Newest item on top
+    back: Vec<T>,
+    back_agg: T,
+    size: usize,
+    _op: PhantomData<Op>,
+}
+
+impl<T, Op: Monoid<T>> SlidingWindow<T, Op> {
+    /// A window over the last `size` items.
+    ///
+    /// Panics if `size` is zero.
+    pub fn new(size: usize) -> Self {
+        assert!(size > 0, "window size must be positive");
+        SlidingWindow { front: Vec::new(), back: Vec::new(), back_agg: Op::identity(), size, _op: PhantomData }
+    }
+
+    /// The most items the window holds.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn len(&self) -> usize {
+        self.front.len() + self.back.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() == self.size
+    }
+
+    pub fn clear(&mut self) {
+        self.front.clear();
+        self.back.clear();
+        self.back_agg = Op::identity();
+    }
+
+    /// Push `val` as the newest item, returning the oldest one if it fell out
+    /// of the window.
+    pub fn push(&mut self, val: T) -> Option<T> {
+        let evicted = if self.is_full() { self.pop() } else { None };
+        self.back_agg = Op::combine(&self.back_agg, &val);
+        self.back.push(val);
+        evicted
+    }
+
+    /// Remove the oldest item.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.front.is_empty() {
+            self.flip();
+        }
+        self.front.pop().map(|(val, _)| val)
+    }
+
+    // Move the back stack onto the front, newest first
+    fn flip(&mut self) {
+        while let Some(val) = self.back.pop() {
+            let agg = match self.front.last() {
+                Some((_, newer)) => Op::combine(&val, newer),
+                None => Op::combine(&val, &Op::identity()),
+            };
+            self.front.push((val, agg));
+        }
+        self.back_agg = Op::identity();
+    }
+
+    /// The aggregate of every item in the window, oldest first. The identity
+    /// if the window is empty.
+    pub fn aggregate(&self) -> T {
+        match self.front.last() {
+            Some((_, front_agg)) => Op::combine(front_agg, &self.back_agg),
+            None => Op::combine(&Op::identity(), &self.back_agg),
+        }
+    }
+
+    /// The items in the window, oldest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> {
+        self.front.iter().rev().map(|(val, _)| val).chain(self.back.iter())
+    }
+}
+
+impl<T: Debug, Op: Monoid<T>> Debug for SlidingWindow<T, Op> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: HeapSize, Op: Monoid<T>> HeapSize for SlidingWindow<T, Op> {
+    fn heap_size(&self) -> usize {
+        self.front.heap_size() + self.back.heap_size() + self.back_agg.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rng::XorShift64;
+
+    use super::{Max, Min, Monoid, SlidingWindow, Sum};
+
+    #[test]
+    fn test_sum() {
+        let mut window = SlidingWindow::<i64, Sum>::new(3);
+        assert_eq!(window.aggregate(), 0);
+        assert_eq!(window.push(1), None);
+        assert_eq!(window.push(2), None);
+        assert_eq!(window.push(3), None);
+        assert!(window.is_full());
+        assert_eq!(window.aggregate(), 6);
+        assert_eq!(window.push(4), Some(1));
+        assert_eq!(window.aggregate(), 9);
+        assert_eq!(window.iter().copied().collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert_eq!(window.pop(), Some(2));
+        assert_eq!(window.aggregate(), 7);
+        window.clear();
+        assert!(window.is_empty());
+        assert_eq!(window.aggregate(), 0);
+    }
+
+    #[test]
+    fn test_min_max_against_brute_force() {
+        let mut rng = XorShift64::new();
+        let mut mins = SlidingWindow::<i64, Min>::new(7);
+        let mut maxes = SlidingWindow::<i64, Max>::new(7);
+        let mut all = Vec::new();
+        for _ in 0..1000 {
+            let val = (rng.next_u64() % 1000) as i64;
+            mins.push(val);
+            maxes.push(val);
+            all.push(val);
+            let recent = &all[all.len().saturating_sub(7)..];
+            assert_eq!(mins.aggregate(), *recent.iter().min().unwrap());
+            assert_eq!(maxes.aggregate(), *recent.iter().max().unwrap());
+        }
+    }
+
+    // Concatenation, to check the order items are combined in
+    struct Concat;
+
+    impl Monoid<String> for Concat {
+        fn identity() -> String {
+            String::new()
+        }
+
+        fn combine(a: &String, b: &String) -> String {
+            format!("{a}{b}")
+        }
+    }
+
+    #[test]
+    fn test_non_commutative() {
+        let mut window = SlidingWindow::<String, Concat>::new(4);
+        for c in "abcdefghij".chars() {
+            window.push(c.to_string());
+        }
+        assert_eq!(window.aggregate(), "ghij");
+        window.pop();
+        window.push("k".to_string());
+        assert_eq!(window.aggregate(), "hijk");
+        assert_eq!(format!("{window:?}"), r#"["h", "i", "j", "k"]"#);
+    }
+}
@@ -0,0 +1,317 @@
+use std::fmt::Debug;
+use std::ops::{Index, IndexMut};
+
+use crate::error::CollectionError;
+use crate::heap_size::{HeapSize, payload};
+
+/// A handle to a value in a `SlotMap`: the slot's index plus the generation
+/// the slot was on when the value was inserted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SlotKey {
+    index: u32,
+    generation: u32,
+}
+
+impl SlotKey {
+    pub fn index(self) -> usize {
+        self.index as usize
+    }
+
+    pub fn generation(self) -> u32 {
+        self.generation
+    }
+}
+
+enum Entry<T> {
+    Occupied(T),
+    // Index of the next vacant slot in the free list
+    Vacant(Option<u32>),
+}
+
+struct Slot<T> {
+    generation: u32,
+    entry: Entry<T>,
+}
+
+/// Storage handing out generational keys.
+///
+/// Like `Slab`, freed slots are reused through a free list, but each slot
+/// also counts how many times it has been vacated. A key records the count at
+/// insertion time, so once its value is removed the key stops matching,
+/// even after the slot is reused, instead of silently aliasing the new
+/// value. The generation wraps after 2^32 reuses of one slot.
+pub struct SlotMap<T> {
+    slots: Vec<Slot<T>>,
+    free: Option<u32>,
+    len: usize,
+}
+
+impl<T> SlotMap<T> {
+    pub const fn new() -> Self {
+        SlotMap { slots: Vec::new(), free: None, len: 0 }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        SlotMap { slots: Vec::with_capacity(capacity), free: None, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Panic unless the free list visits every vacant slot exactly once and
+    /// `len` counts the occupied ones. Only in debug builds and tests.
+    #[cfg(any(test, debug_assertions))]
+    pub fn validate(&self) {
+        let occupied = self.slots.iter().filter(|slot| matches!(slot.entry, Entry::Occupied(_))).count();
+        assert_eq!(occupied, self.len, "slot map length is out of date");
+        let mut seen = vec![false; self.slots.len()];
+        let mut free = self.free;
+        while let Some(index) = free {
+            assert!(!std::mem::replace(&mut seen[index as usize], true), "cycle in the free list");
+            let Entry::Vacant(next) = self.slots[index as usize].entry else {
+                panic!("free list points at an occupied slot");
+            };
+            free = next;
+        }
+        assert_eq!(seen.iter().filter(|&&seen| seen).count(), self.slots.len() - self.len, "vacant slot off the free list");
+    }
+
+    pub fn insert(&mut self, value: T) -> SlotKey {
+        self.insert_with_key(|_| value)
+    }
+
+    /// `insert`, but returning an error if the map is full.
+    pub fn try_insert(&mut self, value: T) -> Result<SlotKey, CollectionError> {
+        self.try_insert_with_key(|_| value)
+    }
+
+    /// Insert a value built from its own key, for values that refer to
+    /// themselves.
+    ///
+    /// Panics if the map would need more than `u32::MAX` slots.
+    pub fn insert_with_key<F: FnOnce(SlotKey) -> T>(&mut self, f: F) -> SlotKey {
+        self.try_insert_with_key(f).expect("slot map is full")
+    }
+
+    /// `insert_with_key`, but returning an error if the map would need more
+    /// than `u32::MAX` slots. `f` isn't called in that case.
+    pub fn try_insert_with_key<F: FnOnce(SlotKey) -> T>(&mut self, f: F) -> Result<SlotKey, CollectionError> {
+        let index = match self.free {
+            Some(index) => {
+                let Entry::Vacant(next) = self.slots[index as usize].entry else {
+                    unreachable!("free list points at an occupied slot");
+                };
+                self.free = next;
+                index
+            }
+            None => {
+                let index = u32::try_from(self.slots.len()).map_err(|_| CollectionError::CapacityExceeded)?;
+                self.slots.push(Slot { generation: 0, entry: Entry::Vacant(None) });
+                index
+            }
+        };
+        let slot = &mut self.slots[index as usize];
+        let key = SlotKey { index, generation: slot.generation };
+        slot.entry = Entry::Occupied(f(key));
+        self.len += 1;
+        Ok(key)
+    }
+
+    fn slot(&self, key: SlotKey) -> Option<&Slot<T>> {
+        self.slots.get(key.index as usize).filter(|slot| slot.generation == key.generation)
+    }
+
+    fn slot_mut(&mut self, key: SlotKey) -> Option<&mut Slot<T>> {
+        self.slots.get_mut(key.index as usize).filter(|slot| slot.generation == key.generation)
+    }
+
+    pub fn remove(&mut self, key: SlotKey) -> Option<T> {
+        let free = self.free;
+        let slot = self.slot_mut(key)?;
+        if let Entry::Vacant(_) = slot.entry {
+            return None;
+        }
+        let Entry::Occupied(value) = std::mem::replace(&mut slot.entry, Entry::Vacant(free)) else {
+            unreachable!();
+        };
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free = Some(key.index);
+        self.len -= 1;
+        Some(value)
+    }
+
+    pub fn contains_key(&self, key: SlotKey) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn get(&self, key: SlotKey) -> Option<&T> {
+        match &self.slot(key)?.entry {
+            Entry::Occupied(value) => Some(value),
+            Entry::Vacant(_) => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, key: SlotKey) -> Option<&mut T> {
+        match &mut self.slot_mut(key)?.entry {
+            Entry::Occupied(value) => Some(value),
+            Entry::Vacant(_) => None,
+        }
+    }
+
+    /// Remove every value. Keys handed out so far all become invalid.
+    pub fn clear(&mut self) {
+        self.retain(|_, _| false);
+    }
+
+    /// Remove every value for which `f` returns false.
+    pub fn retain<F: FnMut(SlotKey, &mut T) -> bool>(&mut self, mut f: F) {
+        for index in 0..self.slots.len() {
+            let slot = &mut self.slots[index];
+            let key = SlotKey { index: index as u32, generation: slot.generation };
+            if let Entry::Occupied(value) = &mut slot.entry
+                && !f(key, value)
+            {
+                self.remove(key);
+            }
+        }
+    }
+
+    /// Every key and value in slot order.
+    pub fn iter(&self) -> impl Iterator<Item = (SlotKey, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| match &slot.entry {
+            Entry::Occupied(value) => Some((SlotKey { index: index as u32, generation: slot.generation }, value)),
+            Entry::Vacant(_) => None,
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (SlotKey, &mut T)> {
+        self.slots.iter_mut().enumerate().filter_map(|(index, slot)| match &mut slot.entry {
+            Entry::Occupied(value) => Some((SlotKey { index: index as u32, generation: slot.generation }, value)),
+            Entry::Vacant(_) => None,
+        })
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = SlotKey> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.iter().map(|(_, value)| value)
+    }
+}
+
+impl<T> Default for SlotMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Index<SlotKey> for SlotMap<T> {
+    type Output = T;
+
+    /// Panics if the key is stale or was never issued by this map.
+    fn index(&self, key: SlotKey) -> &T {
+        self.get(key).expect("invalid slot map key")
+    }
+}
+
+impl<T> IndexMut<SlotKey> for SlotMap<T> {
+    fn index_mut(&mut self, key: SlotKey) -> &mut T {
+        self.get_mut(key).expect("invalid slot map key")
+    }
+}
+
+impl<T: Debug> Debug for SlotMap<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<T: HeapSize> HeapSize for SlotMap<T> {
+    fn heap_size(&self) -> usize {
+        self.slots.capacity() * size_of::<Slot<T>>() + payload(self.values())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SlotKey, SlotMap};
+
+    #[test]
+    fn test_insert_get_remove() {
+        let mut map = SlotMap::new();
+        let a = map.insert("a");
+        let b = map.insert("b");
+        assert_eq!(map.len(), 2);
+        assert_eq!(map[a], "a");
+        assert_eq!(map.get(b), Some(&"b"));
+
+        map[b] = "B";
+        assert_eq!(map.remove(b), Some("B"));
+        assert_eq!(map.remove(b), None);
+        assert!(!map.contains_key(b));
+        assert_eq!(map.len(), 1);
+        map.validate();
+    }
+
+    #[test]
+    fn test_stale_keys_rejected() {
+        let mut map = SlotMap::new();
+        let old = map.insert(1);
+        map.remove(old);
+        let new = map.insert(2);
+        // Same slot, new generation
+        assert_eq!(new.index(), old.index());
+        assert_ne!(new.generation(), old.generation());
+        assert_eq!(map.get(old), None);
+        assert_eq!(map.get_mut(old), None);
+        assert_eq!(map.remove(old), None);
+        assert_eq!(map.get(new), Some(&2));
+        let newer = map.try_insert(3).unwrap();
+        assert_eq!(map.get(newer), Some(&3));
+        map.validate();
+    }
+
+    #[test]
+    fn test_clear_invalidates() {
+        let mut map = SlotMap::new();
+        let keys: Vec<_> = (0..5).map(|i| map.insert(i)).collect();
+        map.clear();
+        assert!(map.is_empty());
+        map.validate();
+        let fresh: Vec<_> = (10..15).map(|i| map.insert(i)).collect();
+        assert!(keys.iter().all(|&k| !map.contains_key(k)));
+        assert!(fresh.iter().all(|&k| map.contains_key(k)));
+    }
+
+    #[test]
+    fn test_insert_with_key_and_iter() {
+        let mut map: SlotMap<(SlotKey, i32)> = SlotMap::new();
+        for i in 0..6 {
+            map.insert_with_key(|key| (key, i));
+        }
+        assert!(map.iter().all(|(key, (own, _))| key == *own));
+        map.retain(|_, (_, v)| *v % 2 == 0);
+        for (_, (_, v)) in map.iter_mut() {
+            *v *= 10;
+        }
+        assert_eq!(map.values().map(|(_, v)| *v).collect::<Vec<_>>(), vec![0, 20, 40]);
+        assert_eq!(map.keys().count(), 3);
+        map.validate();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_index_stale() {
+        let mut map = SlotMap::new();
+        let key = map.insert(());
+        map.remove(key);
+        map.insert(());
+        map[key]
+    }
+}
@@ -0,0 +1,532 @@
+use std::fmt::Display;
+use std::hash::Hash;
+
+use crate::btree::BTreeMap;
+use crate::chunked_seq::ChunkedSeq;
+use crate::doubly_list::LinkedList;
+use crate::gap_buffer::GapBuffer;
+use crate::hash_map::HashMap;
+use crate::list::List;
+use crate::sorted_vec_map::SortedVecMap;
+use crate::treap::Treap;
+use crate::unrolled_list::UnrolledList;
+
+// Every snapshot starts with these, so a snapshot of something else, or of
+// a later format, is rejected up front
+const MAGIC: &[u8; 4] = b"R01S";
+const VERSION: u8 = 1;
+
+/// Why a snapshot could not be read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// The bytes don't start with a snapshot header.
+    BadHeader,
+    /// The snapshot was written by a newer format version.
+    UnsupportedVersion(u8),
+    /// The bytes end in the middle of a value.
+    Truncated,
+    /// A value that its type can't hold, such as an integer out of range or
+    /// a string that isn't UTF-8.
+    Malformed,
+    /// There are bytes left over after the structure.
+    TrailingBytes,
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::BadHeader => f.write_str("not a snapshot"),
+            SnapshotError::UnsupportedVersion(version) => write!(f, "unsupported snapshot version {version}"),
+            SnapshotError::Truncated => f.write_str("snapshot is truncated"),
+            SnapshotError::Malformed => f.write_str("snapshot holds a malformed value"),
+            SnapshotError::TrailingBytes => f.write_str("trailing bytes after snapshot"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+/// A compact binary copy of a structure, for checkpointing it and restoring
+/// it later.
+///
+/// A snapshot is a 4-byte magic number and a version byte, then the
+/// elements in order as a postcard sequence: their count as a varint, then
+/// each element as postcard encodes it. Maps are sequences of key-value
+/// pairs. Only the contents are kept, not the layout, so a snapshot can be
+/// restored into any structure of the same shape.
+pub trait Snapshot: Sized {
+    fn to_bytes(&self) -> Vec<u8>;
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError>;
+}
+
+/// A value that can be written into a snapshot.
+pub trait Encode {
+    fn encode(&self, out: &mut Vec<u8>);
+}
+
+/// A value that can be read back out of a snapshot. `decode` consumes the
+/// value's bytes from the front of `input`.
+pub trait Decode: Sized {
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError>;
+}
+
+fn write_varint(mut val: u128, out: &mut Vec<u8>) {
+    while val >= 0x80 {
+        out.push(val as u8 | 0x80);
+        val >>= 7;
+    }
+    out.push(val as u8);
+}
+
+fn read_varint(input: &mut &[u8]) -> Result<u128, SnapshotError> {
+    let mut val = 0u128;
+    for shift in (0..128).step_by(7) {
+        let (&byte, rest) = input.split_first().ok_or(SnapshotError::Truncated)?;
+        *input = rest;
+        val |= u128::from(byte & 0x7f).checked_shl(shift).ok_or(SnapshotError::Malformed)?;
+        if byte & 0x80 == 0 {
+            return Ok(val);
+        }
+    }
+    Err(SnapshotError::Malformed)
+}
+
+fn read_bytes<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], SnapshotError> {
+    if input.len() < len {
+        return Err(SnapshotError::Truncated);
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Ok(bytes)
+}
+
+fn read_len(input: &mut &[u8]) -> Result<usize, SnapshotError> {
+    usize::try_from(read_varint(input)?).map_err(|_| SnapshotError::Malformed)
+}
+
+// Unsigned integers are varints, signed ones zigzag varints, as in postcard
+impl Encode for u16 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_varint(u128::from(*self), out);
+    }
+}
+
+impl Encode for u32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_varint(u128::from(*self), out);
+    }
+}
+
+impl Encode for u64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_varint(u128::from(*self), out);
+    }
+}
+
+impl Encode for usize {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_varint(*self as u128, out);
+    }
+}
+
+impl Decode for u16 {
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+        Self::try_from(read_varint(input)?).map_err(|_| SnapshotError::Malformed)
+    }
+}
+
+impl Decode for u32 {
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+        Self::try_from(read_varint(input)?).map_err(|_| SnapshotError::Malformed)
+    }
+}
+
+impl Decode for u64 {
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+        Self::try_from(read_varint(input)?).map_err(|_| SnapshotError::Malformed)
+    }
+}
+
+impl Decode for usize {
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+        read_len(input)
+    }
+}
+
+impl Encode for i32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        i64::from(*self).encode(out);
+    }
+}
+
+impl Encode for i64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_varint(u128::from(((self << 1) ^ (self >> 63)) as u64), out);
+    }
+}
+
+impl Decode for i32 {
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+        Self::try_from(i64::decode(input)?).map_err(|_| SnapshotError::Malformed)
+    }
+}
+
+impl Decode for i64 {
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+        let zigzag = u64::decode(input)?;
+        Ok((zigzag >> 1) as i64 ^ -((zigzag & 1) as i64))
+    }
+}
+
+impl Encode for u8 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self);
+    }
+}
+
+impl Decode for u8 {
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+        Ok(read_bytes(input, 1)?[0])
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(u8::from(*self));
+    }
+}
+
+impl Decode for bool {
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+        match u8::decode(input)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SnapshotError::Malformed),
+        }
+    }
+}
+
+impl Encode for f64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Decode for f64 {
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+        Ok(f64::from_le_bytes(read_bytes(input, 8)?.try_into().unwrap()))
+    }
+}
+
+impl Encode for str {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_str().encode(out);
+    }
+}
+
+impl Decode for String {
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+        let len = read_len(input)?;
+        let bytes = read_bytes(input, len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| SnapshotError::Malformed)
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Some(val) => {
+                out.push(1);
+                val.encode(out);
+            }
+            None => out.push(0),
+        }
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+        match bool::decode(input)? {
+            true => Ok(Some(T::decode(input)?)),
+            false => Ok(None),
+        }
+    }
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (**self).encode(out);
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+        self.1.encode(out);
+    }
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+        Ok((A::decode(input)?, B::decode(input)?))
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        for val in self {
+            val.encode(out);
+        }
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+        let len = read_len(input)?;
+        // Every element takes at least a byte, so a corrupt length can't make
+        // this reserve more than the input could fill
+        let mut vals = Vec::with_capacity(len.min(input.len()));
+        for _ in 0..len {
+            vals.push(T::decode(input)?);
+        }
+        Ok(vals)
+    }
+}
+
+/// The snapshot of a structure holding `len` elements, front to back.
+fn write_snapshot<'a, T: Encode + ?Sized + 'a>(len: usize, elems: impl IntoIterator<Item = &'a T>) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+    out.push(VERSION);
+    len.encode(&mut out);
+    for elem in elems {
+        elem.encode(&mut out);
+    }
+    out
+}
+
+/// The elements of a snapshot, front to back.
+fn read_snapshot<T: Decode>(bytes: &[u8]) -> Result<Vec<T>, SnapshotError> {
+    let header = bytes.get(..MAGIC.len() + 1).ok_or(SnapshotError::BadHeader)?;
+    if header[..MAGIC.len()] != *MAGIC {
+        return Err(SnapshotError::BadHeader);
+    }
+    if header[MAGIC.len()] != VERSION {
+        return Err(SnapshotError::UnsupportedVersion(header[MAGIC.len()]));
+    }
+    let mut input = &bytes[header.len()..];
+    let elems = Vec::decode(&mut input)?;
+    if !input.is_empty() {
+        return Err(SnapshotError::TrailingBytes);
+    }
+    Ok(elems)
+}
+
+// A map's entries are written as pairs of references
+fn entries<'a, K: 'a, V: 'a>(iter: impl Iterator<Item = (&'a K, &'a V)>) -> Vec<(&'a K, &'a V)> {
+    iter.collect()
+}
+
+impl<T: Encode + Decode> Snapshot for List<T> {
+    fn to_bytes(&self) -> Vec<u8> {
+        write_snapshot(self.len(), self.iter())
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let mut list = List::new();
+        for val in read_snapshot(bytes)?.into_iter().rev() {
+            list.push_front(val);
+        }
+        Ok(list)
+    }
+}
+
+impl<T: Encode + Decode> Snapshot for LinkedList<T> {
+    fn to_bytes(&self) -> Vec<u8> {
+        write_snapshot(self.len(), self.iter())
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let mut list = LinkedList::new();
+        for val in read_snapshot(bytes)? {
+            list.push_back(val);
+        }
+        Ok(list)
+    }
+}
+
+impl<T: Encode + Decode, const B: usize> Snapshot for UnrolledList<T, B> {
+    fn to_bytes(&self) -> Vec<u8> {
+        write_snapshot(self.len(), self.iter())
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        Ok(read_snapshot(bytes)?.into_iter().collect())
+    }
+}
+
+impl<T: Encode + Decode> Snapshot for GapBuffer<T> {
+    fn to_bytes(&self) -> Vec<u8> {
+        write_snapshot(self.len(), self.iter())
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        Ok(read_snapshot(bytes)?.into_iter().collect())
+    }
+}
+
+impl<T: Encode + Decode + Clone> Snapshot for ChunkedSeq<T> {
+    fn to_bytes(&self) -> Vec<u8> {
+        write_snapshot(self.len(), self.iter())
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        Ok(read_snapshot(bytes)?.into_iter().collect())
+    }
+}
+
+impl<K: Encode + Decode + Hash + Eq, V: Encode + Decode> Snapshot for HashMap<K, V> {
+    fn to_bytes(&self) -> Vec<u8> {
+        write_snapshot(self.len(), &entries(self.iter()))
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        Ok(read_snapshot(bytes)?.into_iter().collect())
+    }
+}
+
+impl<K: Encode + Decode + Ord, V: Encode + Decode> Snapshot for SortedVecMap<K, V> {
+    fn to_bytes(&self) -> Vec<u8> {
+        write_snapshot(self.len(), &entries(self.iter()))
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        Ok(read_snapshot(bytes)?.into_iter().collect())
+    }
+}
+
+impl<K: Encode + Decode + Ord, V: Encode + Decode, const B: usize> Snapshot for BTreeMap<K, V, B> {
+    fn to_bytes(&self) -> Vec<u8> {
+        write_snapshot(self.len(), &entries(self.iter()))
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let mut map = BTreeMap::new();
+        for (key, val) in read_snapshot(bytes)? {
+            map.insert(key, val);
+        }
+        Ok(map)
+    }
+}
+
+impl<K: Encode + Decode + Ord, V: Encode + Decode> Snapshot for Treap<K, V> {
+    fn to_bytes(&self) -> Vec<u8> {
+        write_snapshot(self.len(), &entries(self.iter()))
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let mut map = Treap::new();
+        for (key, val) in read_snapshot(bytes)? {
+            map.insert(key, val);
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::btree::BTreeMap;
+    use crate::chunked_seq::ChunkedSeq;
+    use crate::doubly_list::LinkedList;
+    use crate::gap_buffer::GapBuffer;
+    use crate::hash_map::HashMap;
+    use crate::list::List;
+    use crate::sorted_vec_map::SortedVecMap;
+    use crate::treap::Treap;
+    use crate::unrolled_list::UnrolledList;
+
+    use super::{Decode, Encode, Snapshot, SnapshotError};
+
+    fn round_trip<T: Encode + Decode + PartialEq + std::fmt::Debug>(val: T) {
+        let mut out = Vec::new();
+        val.encode(&mut out);
+        let mut input = &out[..];
+        assert_eq!(T::decode(&mut input), Ok(val));
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn test_elements() {
+        round_trip(0u32);
+        round_trip(u64::MAX);
+        round_trip(i64::MIN);
+        round_trip(-1i32);
+        round_trip(String::from("héllo"));
+        round_trip(Some((true, 2.5f64)));
+        round_trip(vec![None, Some(3u16)]);
+        // Same bytes as postcard
+        let mut out = Vec::new();
+        (300u32, -2i32).encode(&mut out);
+        assert_eq!(out, [0xac, 0x02, 0x03]);
+        assert_eq!(u8::decode(&mut &[][..]), Err(SnapshotError::Truncated));
+        assert_eq!(u16::decode(&mut &[0xff, 0xff, 0x04][..]), Err(SnapshotError::Malformed));
+        assert_eq!(String::decode(&mut &[1, 0xff][..]), Err(SnapshotError::Malformed));
+    }
+
+    #[test]
+    fn test_sequences() {
+        let mut list = List::new();
+        (0..100).rev().for_each(|i| list.push_front(i));
+        let bytes = list.to_bytes();
+        assert_eq!(bytes[..7], *b"R01S\x01\x64\x00");
+        assert!(List::<i32>::from_bytes(&bytes).unwrap().iter().copied().eq(0..100));
+
+        // Any sequence reads any other's snapshot
+        let list = LinkedList::<i32>::from_bytes(&bytes).unwrap();
+        assert!(list.iter().copied().eq(0..100));
+        let list = UnrolledList::<i32, 8>::from_bytes(&list.to_bytes()).unwrap();
+        let buffer = GapBuffer::<i32>::from_bytes(&list.to_bytes()).unwrap();
+        let seq = ChunkedSeq::<i32>::from_bytes(&buffer.to_bytes()).unwrap();
+        assert!(seq.iter().copied().eq(0..100));
+    }
+
+    #[test]
+    fn test_maps() {
+        let map: HashMap<String, u64> = (0..50).map(|i| (i.to_string(), i)).collect();
+        let copy = HashMap::<String, u64>::from_bytes(&map.to_bytes()).unwrap();
+        assert_eq!(copy.len(), 50);
+        assert!(map.iter().all(|(key, val)| copy.get(key) == Some(val)));
+
+        let map: SortedVecMap<u32, bool> = (0..50).map(|i| (i, i % 2 == 0)).collect();
+        let bytes = map.to_bytes();
+        let btree = BTreeMap::<u32, bool, 4>::from_bytes(&bytes).unwrap();
+        let treap = Treap::<u32, bool>::from_bytes(&btree.to_bytes()).unwrap();
+        assert_eq!(treap.to_bytes(), bytes);
+        assert!(SortedVecMap::<u32, bool>::from_bytes(&bytes).unwrap().iter().eq(map.iter()));
+    }
+
+    #[test]
+    fn test_bad_snapshots() {
+        assert_eq!(List::<u8>::from_bytes(&[]).err(), Some(SnapshotError::BadHeader));
+        let mut bytes = {
+            let mut list = List::new();
+            list.push_front(7u8);
+            list.to_bytes()
+        };
+        assert_eq!(List::<u8>::from_bytes(&bytes[..bytes.len() - 1]).err(), Some(SnapshotError::Truncated));
+        bytes.push(0);
+        assert_eq!(List::<u8>::from_bytes(&bytes).err(), Some(SnapshotError::TrailingBytes));
+        bytes[4] = 2;
+        assert_eq!(List::<u8>::from_bytes(&bytes).err(), Some(SnapshotError::UnsupportedVersion(2)));
+        bytes[0] = b'X';
+        assert_eq!(List::<u8>::from_bytes(&bytes).err(), Some(SnapshotError::BadHeader));
+    }
+}
@@ -0,0 +1,256 @@
+use std::fmt::Debug;
+use std::ops::{Bound, RangeBounds};
+use std::slice;
+
+use crate::heap_size::HeapSize;
+
+/// An ordered map kept as two sorted vectors, keys and values, searched by
+/// binary search.
+///
+/// Lookups touch only the contiguous key array, and iteration is a plain
+/// slice walk, so for small maps this beats any of the tree maps. Inserting
+/// or removing shifts everything after the position, which makes those O(n)
+/// and the map a poor fit once it holds more than a few thousand entries.
+/// Building one from an iterator sorts once rather than inserting one by one.
+pub struct SortedVecMap<K, V> {
+    keys: Vec<K>,
+    vals: Vec<V>,
+}
+
+impl<K: Ord, V> SortedVecMap<K, V> {
+    pub const fn new() -> Self {
+        SortedVecMap { keys: Vec::new(), vals: Vec::new() }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        SortedVecMap { keys: Vec::with_capacity(capacity), vals: Vec::with_capacity(capacity) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Panic unless the keys are strictly increasing and there is a value
+    /// for each. Only in debug builds and tests.
+    #[cfg(any(test, debug_assertions))]
+    pub fn validate(&self) {
+        assert_eq!(self.keys.len(), self.vals.len(), "keys and values out of step");
+        assert!(self.keys.is_sorted_by(|a, b| a < b), "keys out of order or repeated");
+    }
+
+    pub fn clear(&mut self) {
+        self.keys.clear();
+        self.vals.clear();
+    }
+
+    /// Insert a key-value pair, returning the previous value if the key was
+    /// already present.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.keys.binary_search(&key) {
+            Ok(i) => Some(std::mem::replace(&mut self.vals[i], value)),
+            Err(i) => {
+                self.keys.insert(i, key);
+                self.vals.insert(i, value);
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let i = self.keys.binary_search(key).ok()?;
+        self.keys.remove(i);
+        Some(self.vals.remove(i))
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let i = self.keys.binary_search(key).ok()?;
+        Some(&self.vals[i])
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let i = self.keys.binary_search(key).ok()?;
+        Some(&mut self.vals[i])
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.keys.binary_search(key).is_ok()
+    }
+
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        Some((self.keys.first()?, self.vals.first()?))
+    }
+
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        Some((self.keys.last()?, self.vals.last()?))
+    }
+
+    /// The entry with the greatest key less than or equal to `key`.
+    pub fn floor(&self, key: &K) -> Option<(&K, &V)> {
+        let i = self.keys.partition_point(|k| k <= key).checked_sub(1)?;
+        Some((&self.keys[i], &self.vals[i]))
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter { keys: self.keys.iter(), vals: self.vals.iter() }
+    }
+
+    /// Iterate in key order over the entries whose keys fall within `range`.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> Iter<'_, K, V> {
+        let start = match range.start_bound() {
+            Bound::Included(start) => self.keys.partition_point(|k| k < start),
+            Bound::Excluded(start) => self.keys.partition_point(|k| k <= start),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => self.keys.partition_point(|k| k <= end),
+            Bound::Excluded(end) => self.keys.partition_point(|k| k < end),
+            Bound::Unbounded => self.keys.len(),
+        };
+        // An inverted range, like 5..3, is empty
+        let end = end.max(start);
+        Iter { keys: self.keys[start..end].iter(), vals: self.vals[start..end].iter() }
+    }
+}
+
+impl<K: Ord, V> Default for SortedVecMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for SortedVecMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut entries: Vec<(K, V)> = iter.into_iter().collect();
+        // Stable, so of several entries with the same key the last one wins,
+        // as with repeated inserts
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut map = Self::with_capacity(entries.len());
+        for (key, val) in entries {
+            if map.keys.last() == Some(&key) {
+                *map.vals.last_mut().unwrap() = val;
+            } else {
+                map.keys.push(key);
+                map.vals.push(val);
+            }
+        }
+        map
+    }
+}
+
+/// In-order iterator over the entries of a `SortedVecMap`, or of a key range
+/// of one.
+pub struct Iter<'a, K, V> {
+    keys: slice::Iter<'a, K>,
+    vals: slice::Iter<'a, V>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some((self.keys.next()?, self.vals.next()?))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for Iter<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some((self.keys.next_back()?, self.vals.next_back()?))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<'a, K: Ord, V> IntoIterator for &'a SortedVecMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K: Ord + Debug, V: Debug> Debug for SortedVecMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: HeapSize, V: HeapSize> HeapSize for SortedVecMap<K, V> {
+    fn heap_size(&self) -> usize {
+        self.keys.heap_size() + self.vals.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound;
+
+    use crate::btree::BTreeMap;
+
+    use super::SortedVecMap;
+
+    #[test]
+    fn test_insert_get_remove() {
+        let mut map = SortedVecMap::new();
+        for i in (0..100).rev() {
+            assert_eq!(map.insert(i, i * 2), None);
+        }
+        assert_eq!(map.len(), 100);
+        assert_eq!(map.get(&40), Some(&80));
+        assert_eq!(map.insert(40, 0), Some(80));
+        *map.get_mut(&40).unwrap() += 1;
+        assert_eq!(map.get(&40), Some(&1));
+        assert_eq!(map.remove(&40), Some(1));
+        assert_eq!(map.remove(&40), None);
+        assert!(!map.contains_key(&40));
+        assert_eq!(map.first_key_value(), Some((&0, &0)));
+        assert_eq!(map.last_key_value(), Some((&99, &198)));
+        assert_eq!(map.floor(&40), Some((&39, &78)));
+        assert_eq!(map.floor(&-1), None);
+        map.clear();
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_range() {
+        let map: SortedVecMap<i32, ()> = (0..50).map(|i| (i * 2, ())).collect();
+        let keys = |r: Vec<(&i32, &())>| r.into_iter().map(|(k, _)| *k).collect::<Vec<_>>();
+        assert_eq!(keys(map.range(10..20).collect()), vec![10, 12, 14, 16, 18]);
+        assert_eq!(keys(map.range(9..=20).collect()), vec![10, 12, 14, 16, 18, 20]);
+        assert_eq!(keys(map.range(95..).collect()), vec![96, 98]);
+        assert_eq!(keys(map.range(..3).rev().collect()), vec![2, 0]);
+        assert_eq!(map.range(11..12).len(), 0);
+        assert_eq!(map.range((Bound::Included(20), Bound::Excluded(10))).len(), 0);
+    }
+
+    #[test]
+    fn test_from_iter_last_wins() {
+        let map: SortedVecMap<_, _> = [(3, 'a'), (1, 'b'), (3, 'c'), (2, 'd'), (1, 'e')].into_iter().collect();
+        assert_eq!(format!("{map:?}"), "{1: 'e', 2: 'd', 3: 'c'}");
+    }
+
+    #[test]
+    fn test_agrees_with_btree() {
+        let mut sorted = SortedVecMap::new();
+        let mut tree: BTreeMap<u32, u32, 8> = BTreeMap::new();
+        for i in 0..2000u32 {
+            let k = i.wrapping_mul(2654435761) % 500;
+            if i % 3 == 0 {
+                assert_eq!(sorted.remove(&k), tree.remove(&k));
+            } else {
+                assert_eq!(sorted.insert(k, i), tree.insert(k, i));
+            }
+        }
+        sorted.validate();
+        tree.validate();
+        assert!(sorted.iter().eq(tree.iter()));
+    }
+}
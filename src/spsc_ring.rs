@@ -0,0 +1,324 @@
+use std::mem::MaybeUninit;
+
+use crate::cache_padded::CachePadded;
+use crate::heap_size::HeapSize;
+use crate::sync::shim::{Arc, AtomicUsize, Ordering, UnsafeCell};
+
+/// A bounded single-producer single-consumer queue over a fixed ring buffer.
+///
+/// `new` allocates the buffer once and `split` turns the ring into a
+/// `Producer` and a `Consumer`, which can be moved to two different threads.
+/// After that, pushing and popping never allocate, lock or retry: each is a
+/// handful of loads and one store, so both ends are wait-free. The head and
+/// tail indices sit on separate cache lines, and each end caches the other's
+/// index so it only reads the shared one when the ring looks full or empty.
+pub struct SpscRing<T> {
+    // Next slot to pop; only the consumer writes it
+    head: CachePadded<AtomicUsize>,
+    // Next slot to push; only the producer writes it
+    tail: CachePadded<AtomicUsize>,
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    mask: usize,
+}
+
+// Slots between head and tail are owned by the consumer, the rest by the
+// producer, and the Acquire/Release pairs on head and tail hand them over.
+unsafe impl<T: Send> Sync for SpscRing<T> {}
+
+impl<T: Send> SpscRing<T> {
+    /// A ring holding at least `capacity` elements, rounded up to a power
+    /// of two.
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "ring capacity must be positive");
+        let capacity = capacity.next_power_of_two();
+        SpscRing {
+            head: CachePadded(AtomicUsize::new(0)),
+            tail: CachePadded(AtomicUsize::new(0)),
+            slots: (0..capacity).map(|_| UnsafeCell::new(MaybeUninit::uninit())).collect(),
+            mask: capacity - 1,
+        }
+    }
+
+    /// Split the ring into its two ends.
+    pub fn split(self) -> (Producer<T>, Consumer<T>) {
+        let ring = Arc::new(self);
+        (Producer { ring: ring.clone(), head: 0 }, Consumer { ring, tail: 0 })
+    }
+}
+
+impl<T> SpscRing<T> {
+    fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    fn len(&self) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        self.tail.load(Ordering::Acquire).wrapping_sub(head)
+    }
+}
+
+impl<T> Drop for SpscRing<T> {
+    fn drop(&mut self) {
+        let (head, tail) = (self.head.load(Ordering::Relaxed), self.tail.load(Ordering::Relaxed));
+        let mut pos = head;
+        while pos != tail {
+            self.slots[pos & self.mask].with_mut(|ptr| unsafe { (*ptr).assume_init_drop() });
+            pos = pos.wrapping_add(1);
+        }
+    }
+}
+
+/// The pushing end of an `SpscRing`.
+pub struct Producer<T> {
+    ring: Arc<SpscRing<T>>,
+    // Last head seen; the real one can only be further along
+    head: usize,
+}
+
+impl<T> Producer<T> {
+    /// Push `val`, or hand it back if the ring is full.
+    pub fn try_push(&mut self, val: T) -> Result<(), T> {
+        let ring = &*self.ring;
+        let tail = ring.tail.load(Ordering::Relaxed);
+        if tail.wrapping_sub(self.head) == ring.capacity() {
+            self.head = ring.head.load(Ordering::Acquire);
+            if tail.wrapping_sub(self.head) == ring.capacity() {
+                return Err(val);
+            }
+        }
+        // The slot is outside head..tail, so the consumer isn't touching it
+        ring.slots[tail & ring.mask].with_mut(|ptr| unsafe { (*ptr).write(val) });
+        ring.tail.store(tail.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.ring.capacity()
+    }
+
+    /// Number of queued elements. Only a snapshot if the consumer is active.
+    pub fn len(&self) -> usize {
+        self.ring.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() == self.capacity()
+    }
+}
+
+/// The popping end of an `SpscRing`.
+pub struct Consumer<T> {
+    ring: Arc<SpscRing<T>>,
+    // Last tail seen; the real one can only be further along
+    tail: usize,
+}
+
+impl<T> Consumer<T> {
+    /// Pop the oldest element, or `None` if the ring is empty.
+    pub fn try_pop(&mut self) -> Option<T> {
+        let ring = &*self.ring;
+        let head = ring.head.load(Ordering::Relaxed);
+        if head == self.tail {
+            self.tail = ring.tail.load(Ordering::Acquire);
+            if head == self.tail {
+                return None;
+            }
+        }
+        // The slot is inside head..tail, so the producer has finished writing
+        // it and won't touch it again until head moves past
+        let val = ring.slots[head & ring.mask].with_mut(|ptr| unsafe { (*ptr).assume_init_read() });
+        ring.head.store(head.wrapping_add(1), Ordering::Release);
+        Some(val)
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.ring.capacity()
+    }
+
+    /// Number of queued elements. Only a snapshot if the producer is active.
+    pub fn len(&self) -> usize {
+        self.ring.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Counts the slot buffer only: the other end may be moving elements in and
+/// out, so their payloads can't be looked at.
+impl<T> HeapSize for SpscRing<T> {
+    fn heap_size(&self) -> usize {
+        self.slots.len() * size_of::<UnsafeCell<MaybeUninit<T>>>()
+    }
+}
+
+/// The ring this end shares with the other one.
+impl<T> HeapSize for Producer<T> {
+    fn heap_size(&self) -> usize {
+        self.ring.heap_size()
+    }
+}
+
+/// The ring this end shares with the other one.
+impl<T> HeapSize for Consumer<T> {
+    fn heap_size(&self) -> usize {
+        self.ring.heap_size()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    use crate::heap_size::HeapSize;
+
+    use super::SpscRing;
+
+    #[test]
+    fn test_push_pop() {
+        let (mut tx, mut rx) = SpscRing::new(3).split();
+        assert_eq!(tx.capacity(), 4);
+        assert_eq!(rx.try_pop(), None);
+        for i in 0..4 {
+            assert_eq!(tx.try_push(i), Ok(()));
+        }
+        assert!(tx.is_full());
+        assert_eq!(tx.try_push(4), Err(4));
+        assert_eq!(rx.try_pop(), Some(0));
+        assert_eq!(tx.try_push(4), Ok(()));
+        assert_eq!(rx.len(), 4);
+        assert_eq!((0..4).map(|_| rx.try_pop().unwrap()).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert!(rx.is_empty());
+    }
+
+    #[test]
+    fn test_wrap_around() {
+        let (mut tx, mut rx) = SpscRing::new(8).split();
+        let mut next = 0;
+        for i in 0..1000 {
+            tx.try_push(i).unwrap();
+            // Drain in bursts so the ring keeps wrapping at different fills
+            if tx.is_full() || i % 5 == 0 {
+                for _ in 0..i % 4 + 1 {
+                    assert_eq!(rx.try_pop(), Some(next));
+                    next += 1;
+                }
+            }
+        }
+        while let Some(val) = rx.try_pop() {
+            assert_eq!(val, next);
+            next += 1;
+        }
+        assert_eq!(next, 1000);
+    }
+
+    #[test]
+    fn test_drops_leftovers() {
+        struct Counted(Arc<AtomicUsize>);
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let drops = Arc::new(AtomicUsize::new(0));
+        let (mut tx, mut rx) = SpscRing::new(4).split();
+        for _ in 0..3 {
+            assert!(tx.try_push(Counted(drops.clone())).is_ok());
+        }
+        drop(rx.try_pop());
+        assert_eq!(drops.load(Ordering::Relaxed), 1);
+        drop(tx);
+        drop(rx);
+        assert_eq!(drops.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn test_threads() {
+        const N: u64 = 200_000;
+        let (mut tx, mut rx) = SpscRing::new(64).split();
+        let producer = thread::spawn(move || {
+            for i in 0..N {
+                let mut val = i;
+                while let Err(back) = tx.try_push(val) {
+                    val = back;
+                    thread::yield_now();
+                }
+            }
+        });
+        let mut expected = 0;
+        while expected < N {
+            match rx.try_pop() {
+                Some(val) => {
+                    assert_eq!(val, expected);
+                    expected += 1;
+                }
+                None => thread::yield_now(),
+            }
+        }
+        producer.join().unwrap();
+        assert_eq!(rx.try_pop(), None);
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let ring = SpscRing::<String>::new(3);
+        assert_eq!(ring.heap_size(), 4 * size_of::<String>());
+        // Both ends report the ring they share
+        let (mut tx, rx) = ring.split();
+        tx.try_push(String::from("abc")).unwrap();
+        assert_eq!(tx.heap_size(), 4 * size_of::<String>());
+        assert_eq!(rx.heap_size(), tx.heap_size());
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use loom::thread;
+
+    use super::SpscRing;
+
+    #[test]
+    fn loom_push_pop() {
+        loom::model(|| {
+            let (mut producer, mut consumer) = SpscRing::new(2).split();
+            let pusher = thread::spawn(move || {
+                // Fill the ring, then push once more after the consumer
+                // makes room
+                producer.try_push(1).unwrap();
+                producer.try_push(2).unwrap();
+                while producer.try_push(3).is_err() {
+                    thread::yield_now();
+                }
+            });
+            let mut got = Vec::new();
+            while got.len() < 3 {
+                match consumer.try_pop() {
+                    Some(val) => got.push(val),
+                    None => thread::yield_now(),
+                }
+            }
+            pusher.join().unwrap();
+            assert_eq!(got, vec![1, 2, 3]);
+        });
+    }
+
+    #[test]
+    fn loom_drop_leftovers() {
+        loom::model(|| {
+            let (mut producer, consumer) = SpscRing::new(2).split();
+            let pusher = thread::spawn(move || producer.try_push(vec![1]).unwrap());
+            drop(consumer);
+            pusher.join().unwrap();
+        });
+    }
+}
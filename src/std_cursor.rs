@@ -0,0 +1,387 @@
+use crate::doubly_list::{LinkedList, NodeRef};
+
+/// A cursor over a `LinkedList` with the methods of the standard library's
+/// unstable `linked_list::CursorMut`, behind the `std_cursor` feature.
+///
+/// Code written against the nightly API runs on stable by swapping
+/// `std::collections::LinkedList` for this crate's `LinkedList`: the
+/// methods have the same names, signatures and behavior, down to the index
+/// reported and where the cursor ends up after each edit. As there, the
+/// cursor sits either on an element or on a "ghost" position between the
+/// back and the front. Splits and splices are O(1).
+///
+/// The read-only `Cursor`, and with it `as_cursor` and `cursor_front`, is
+/// left out, since `LinkedList::cursor_front` already names the crate's own
+/// cursor. A list made by `with_chunk_size` has no node per element to
+/// sit on, so both constructors panic on one.
+pub struct CursorMut<'a, T> {
+    // Position of the current element, or the list's length on the ghost
+    index: usize,
+    current: Option<NodeRef<T>>,
+    list: &'a mut LinkedList<T>,
+}
+
+impl<T> LinkedList<T> {
+    /// A `CursorMut` on the front element, or on the ghost if the list is
+    /// empty.
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, T> {
+        assert!(self.chunk_size() == 1, "CursorMut on a chunked list");
+        CursorMut { index: 0, current: self.front_node(), list: self }
+    }
+
+    /// A `CursorMut` on the back element, or on the ghost if the list is
+    /// empty.
+    pub fn cursor_back_mut(&mut self) -> CursorMut<'_, T> {
+        assert!(self.chunk_size() == 1, "CursorMut on a chunked list");
+        let index = self.len().saturating_sub(1);
+        CursorMut { index, current: self.back_node(), list: self }
+    }
+}
+
+// The element of a node in the list. The cursor's exclusive borrow of the
+// list is what makes handing this out sound, so the result must not outlive
+// a borrow of the cursor.
+fn data<'a, T>(node: Option<NodeRef<T>>) -> Option<&'a mut T> {
+    node.map(|node| unsafe { &mut (*node.as_ptr()).data })
+}
+
+impl<'a, T> CursorMut<'a, T> {
+    /// Index of the current element, or `None` on the ghost.
+    pub fn index(&self) -> Option<usize> {
+        self.current.as_ref().map(|_| self.index)
+    }
+
+    /// Move to the next element, from the back to the ghost, or from the
+    /// ghost to the front.
+    pub fn move_next(&mut self) {
+        match self.current.take() {
+            Some(node) => {
+                self.current = node.borrow().next();
+                self.index += 1;
+            }
+            None => {
+                self.current = self.list.front_node();
+                self.index = 0;
+            }
+        }
+    }
+
+    /// Move to the previous element, from the front to the ghost, or from
+    /// the ghost to the back.
+    pub fn move_prev(&mut self) {
+        match self.current.take() {
+            Some(node) => {
+                self.current = node.borrow().prev();
+                self.index = self.index.checked_sub(1).unwrap_or(self.list.len());
+            }
+            None => {
+                self.current = self.list.back_node();
+                self.index = self.list.len().saturating_sub(1);
+            }
+        }
+    }
+
+    pub fn current(&mut self) -> Option<&mut T> {
+        data(self.current.clone())
+    }
+
+    /// The element after the current one. On the ghost that is the front.
+    pub fn peek_next(&mut self) -> Option<&mut T> {
+        match &self.current {
+            Some(node) => data(node.borrow().next()),
+            None => data(self.list.front_node()),
+        }
+    }
+
+    /// The element before the current one. On the ghost that is the back.
+    pub fn peek_prev(&mut self) -> Option<&mut T> {
+        match &self.current {
+            Some(node) => data(node.borrow().prev()),
+            None => data(self.list.back_node()),
+        }
+    }
+
+    /// Insert after the current element, or at the front on the ghost.
+    pub fn insert_after(&mut self, item: T) {
+        match &self.current {
+            Some(node) => {
+                self.list.insert_after_node(node, item);
+            }
+            None => {
+                self.list.push_front(item);
+                self.index += 1;
+            }
+        }
+    }
+
+    /// Insert before the current element, or at the back on the ghost.
+    pub fn insert_before(&mut self, item: T) {
+        let prev = match &self.current {
+            Some(node) => node.borrow().prev(),
+            None => self.list.back_node(),
+        };
+        match prev {
+            Some(prev) => {
+                self.list.insert_after_node(&prev, item);
+            }
+            None => self.list.push_front(item),
+        }
+        self.index += 1;
+    }
+
+    /// Remove the current element and move to the next one, or to the ghost
+    /// if it was the back. Does nothing on the ghost.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let node = self.current.take()?;
+        self.current = node.borrow().next();
+        self.list.remove_node(node)
+    }
+
+    /// `remove_current`, but returning the element in a list of its own.
+    pub fn remove_current_as_list(&mut self) -> Option<LinkedList<T>> {
+        let mut list = LinkedList::new();
+        list.push_back(self.remove_current()?);
+        Some(list)
+    }
+
+    /// Split off and return everything after the current element, or the
+    /// whole list on the ghost.
+    pub fn split_after(&mut self) -> LinkedList<T> {
+        let at = self.current.as_ref().map_or(0, |_| self.index + 1);
+        let back = self.list.split_after_node(self.current.as_ref(), at);
+        if self.current.is_none() {
+            self.index = 0;
+        }
+        back
+    }
+
+    /// Split off and return everything before the current element, or the
+    /// whole list on the ghost.
+    pub fn split_before(&mut self) -> LinkedList<T> {
+        let prev = match &self.current {
+            Some(node) => node.borrow().prev(),
+            None => self.list.back_node(),
+        };
+        let back = self.list.split_after_node(prev.as_ref(), self.index);
+        self.index = 0;
+        std::mem::replace(self.list, back)
+    }
+
+    /// Move every element of `list` in after the current one, or at the
+    /// front on the ghost.
+    pub fn splice_after(&mut self, list: LinkedList<T>) {
+        if self.current.is_none() {
+            self.index += list.len();
+        }
+        self.list.splice_after_node(self.current.as_ref(), list);
+    }
+
+    /// Move every element of `list` in before the current one, or at the
+    /// back on the ghost.
+    pub fn splice_before(&mut self, list: LinkedList<T>) {
+        let prev = match &self.current {
+            Some(node) => node.borrow().prev(),
+            None => self.list.back_node(),
+        };
+        self.index += list.len();
+        self.list.splice_after_node(prev.as_ref(), list);
+    }
+
+    pub fn push_front(&mut self, elt: T) {
+        self.list.push_front(elt);
+        self.index += 1;
+    }
+
+    pub fn push_back(&mut self, elt: T) {
+        self.list.push_back(elt);
+        if self.current.is_none() {
+            self.index += 1;
+        }
+    }
+
+    /// Remove the front element. A cursor on it moves to the next one.
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.list.is_empty() {
+            return None;
+        }
+        if self.current.is_some() && self.index == 0 {
+            return self.remove_current();
+        }
+        self.index -= 1;
+        self.list.pop_front()
+    }
+
+    /// Remove the back element. A cursor on it moves to the ghost.
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.list.is_empty() {
+            return None;
+        }
+        if self.current.is_some() && self.index + 1 == self.list.len() {
+            return self.remove_current();
+        }
+        if self.current.is_none() {
+            self.index -= 1;
+        }
+        self.list.pop_back()
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        data(self.list.front_node()).map(|val| &*val)
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        data(self.list.front_node())
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        data(self.list.back_node()).map(|val| &*val)
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        data(self.list.back_node())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::doubly_list::LinkedList;
+
+    fn list(vals: impl IntoIterator<Item = i32>) -> LinkedList<i32> {
+        let mut list = LinkedList::new();
+        for val in vals {
+            list.push_back(val);
+        }
+        list
+    }
+
+    fn contents(list: &LinkedList<i32>) -> Vec<i32> {
+        list.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_moves() {
+        let mut l = list(1..=3);
+        let mut cursor = l.cursor_front_mut();
+        assert_eq!((cursor.index(), cursor.current()), (Some(0), Some(&mut 1)));
+        assert_eq!(cursor.peek_prev(), None);
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!((cursor.index(), cursor.peek_next()), (Some(2), None));
+        cursor.move_next();
+        // The ghost sits between the back and the front
+        assert_eq!((cursor.index(), cursor.current()), (None, None));
+        assert_eq!(cursor.peek_next(), Some(&mut 1));
+        assert_eq!(cursor.peek_prev(), Some(&mut 3));
+        cursor.move_next();
+        assert_eq!(cursor.index(), Some(0));
+        cursor.move_prev();
+        cursor.move_prev();
+        assert_eq!((cursor.index(), cursor.current()), (Some(2), Some(&mut 3)));
+        *cursor.current().unwrap() = 30;
+        let mut cursor = l.cursor_back_mut();
+        assert_eq!(cursor.current(), Some(&mut 30));
+    }
+
+    #[test]
+    fn test_insert_remove() {
+        let mut l = list([2, 4]);
+        let mut cursor = l.cursor_front_mut();
+        cursor.insert_before(1);
+        cursor.insert_after(3);
+        assert_eq!((cursor.index(), cursor.current()), (Some(1), Some(&mut 2)));
+        cursor.move_prev();
+        cursor.move_prev();
+        // On the ghost, inserts go to the ends
+        cursor.insert_after(0);
+        cursor.insert_before(5);
+        assert_eq!(cursor.index(), None);
+        assert_eq!(cursor.remove_current(), None);
+        cursor.move_prev();
+        assert_eq!(cursor.index(), Some(5));
+        assert_eq!(cursor.remove_current(), Some(5));
+        assert_eq!(cursor.index(), None);
+        cursor.move_next();
+        let removed = cursor.remove_current_as_list().unwrap();
+        assert_eq!(contents(&removed), vec![0]);
+        assert_eq!((cursor.index(), cursor.current()), (Some(0), Some(&mut 1)));
+        assert_eq!(contents(&l), vec![1, 2, 3, 4]);
+        l.validate();
+    }
+
+    #[test]
+    fn test_split_splice() {
+        let mut l = list(0..6);
+        let mut cursor = l.cursor_front_mut();
+        cursor.move_next();
+        cursor.move_next();
+        let back = cursor.split_after();
+        let front = cursor.split_before();
+        assert_eq!((cursor.index(), cursor.current()), (Some(0), Some(&mut 2)));
+        assert_eq!((contents(&front), contents(&back)), (vec![0, 1], vec![3, 4, 5]));
+        cursor.splice_after(back);
+        cursor.splice_before(front);
+        assert_eq!((cursor.index(), cursor.current()), (Some(2), Some(&mut 2)));
+        cursor.move_prev();
+        cursor.move_prev();
+        cursor.move_prev();
+        cursor.splice_after(list([-1]));
+        cursor.splice_before(list([6, 7]));
+        assert_eq!(cursor.index(), None);
+        cursor.move_prev();
+        assert_eq!((cursor.index(), cursor.current()), (Some(8), Some(&mut 7)));
+        assert_eq!(contents(&l), (-1..8).collect::<Vec<_>>());
+        l.validate();
+
+        // On the ghost, either split takes the whole list
+        let mut cursor = l.cursor_back_mut();
+        cursor.move_next();
+        assert_eq!(cursor.split_after().len(), 9);
+        assert!(cursor.split_before().is_empty());
+        assert!(l.is_empty());
+        l.validate();
+    }
+
+    #[test]
+    fn test_chunked() {
+        let mut chunked = LinkedList::with_chunk_size(2);
+        for val in 1..=3 {
+            chunked.push_back(val);
+        }
+        let mut l = list([0, 4]);
+        let mut cursor = l.cursor_front_mut();
+        cursor.splice_after(chunked);
+        assert_eq!((cursor.index(), cursor.peek_next()), (Some(0), Some(&mut 1)));
+        assert_eq!(contents(&l), (0..5).collect::<Vec<_>>());
+        l.validate();
+    }
+
+    #[test]
+    #[should_panic(expected = "chunked")]
+    fn test_chunked_cursor_panics() {
+        LinkedList::<i32>::with_chunk_size(2).cursor_front_mut();
+    }
+
+    #[test]
+    fn test_ends() {
+        let mut l = list(1..=3);
+        let mut cursor = l.cursor_front_mut();
+        cursor.push_front(0);
+        cursor.push_back(4);
+        assert_eq!((cursor.index(), cursor.front(), cursor.back()), (Some(1), Some(&0), Some(&4)));
+        *cursor.front_mut().unwrap() = -1;
+        *cursor.back_mut().unwrap() += 1;
+        assert_eq!(cursor.pop_front(), Some(-1));
+        // Popping the current element moves the cursor on
+        assert_eq!(cursor.pop_front(), Some(1));
+        assert_eq!((cursor.index(), cursor.current()), (Some(0), Some(&mut 2)));
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.pop_back(), Some(5));
+        assert_eq!(cursor.index(), None);
+        assert_eq!(cursor.pop_back(), Some(3));
+        cursor.move_prev();
+        assert_eq!((cursor.index(), cursor.current()), (Some(0), Some(&mut 2)));
+        assert_eq!(cursor.pop_back(), Some(2));
+        assert_eq!((cursor.pop_front(), cursor.pop_back()), (None, None));
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use crate::heap_size::HeapSize;
+
+struct State {
+    // Length of the longest substring ending in this state.
+    len: usize,
+    link: Option<usize>,
+    next: HashMap<char, usize>,
+    // Number of end positions, i.e. occurrences of the state's substrings.
+    count: usize,
+}
+
+/// The minimal automaton recognizing every substring of a text.
+///
+/// Built online in O(n) for a text of n characters; afterwards substring and
+/// occurrence-count queries take O(m) for a pattern of m characters.
+pub struct SuffixAutomaton {
+    states: Vec<State>,
+    text_len: usize,
+}
+
+impl SuffixAutomaton {
+    pub fn new(text: &str) -> Self {
+        let mut states = vec![State { len: 0, link: None, next: HashMap::new(), count: 0 }];
+        let mut last = 0;
+        let mut text_len = 0;
+        for c in text.chars() {
+            text_len += 1;
+            let cur = states.len();
+            states.push(State { len: states[last].len + 1, link: None, next: HashMap::new(), count: 1 });
+            let mut p = Some(last);
+            while let Some(q) = p {
+                if states[q].next.contains_key(&c) {
+                    break;
+                }
+                states[q].next.insert(c, cur);
+                p = states[q].link;
+            }
+            states[cur].link = match p {
+                None => Some(0),
+                Some(p) => {
+                    let q = states[p].next[&c];
+                    if states[p].len + 1 == states[q].len {
+                        Some(q)
+                    } else {
+                        let clone = states.len();
+                        states.push(State {
+                            len: states[p].len + 1,
+                            link: states[q].link,
+                            next: states[q].next.clone(),
+                            count: 0,
+                        });
+                        let mut p = Some(p);
+                        while let Some(r) = p {
+                            if states[r].next.get(&c) != Some(&q) {
+                                break;
+                            }
+                            states[r].next.insert(c, clone);
+                            p = states[r].link;
+                        }
+                        states[q].link = Some(clone);
+                        Some(clone)
+                    }
+                }
+            };
+            last = cur;
+        }
+
+        // Propagate end-position counts up the suffix links, longest first
+        let mut order: Vec<usize> = (1..states.len()).collect();
+        order.sort_unstable_by_key(|&i| std::cmp::Reverse(states[i].len));
+        for i in order {
+            if let Some(link) = states[i].link {
+                states[link].count += states[i].count;
+            }
+        }
+
+        SuffixAutomaton { states, text_len }
+    }
+
+    /// Length of the indexed text in characters.
+    pub fn text_len(&self) -> usize {
+        self.text_len
+    }
+
+    fn walk(&self, pattern: &str) -> Option<usize> {
+        let mut state = 0;
+        for c in pattern.chars() {
+            state = *self.states[state].next.get(&c)?;
+        }
+        Some(state)
+    }
+
+    /// Whether `pattern` occurs anywhere in the text.
+    pub fn contains(&self, pattern: &str) -> bool {
+        self.walk(pattern).is_some()
+    }
+
+    /// Number of (possibly overlapping) occurrences of `pattern` in the text.
+    /// The empty pattern occurs at every position, including the end.
+    pub fn count(&self, pattern: &str) -> usize {
+        match self.walk(pattern) {
+            Some(0) => self.text_len + 1,
+            Some(state) => self.states[state].count,
+            None => 0,
+        }
+    }
+
+    /// Number of distinct non-empty substrings of the text.
+    pub fn distinct_substrings(&self) -> usize {
+        self.states
+            .iter()
+            .skip(1)
+            .map(|s| s.len - s.link.map_or(0, |l| self.states[l].len))
+            .sum()
+    }
+}
+
+impl HeapSize for State {
+    fn heap_size(&self) -> usize {
+        self.next.heap_size()
+    }
+}
+
+impl HeapSize for SuffixAutomaton {
+    fn heap_size(&self) -> usize {
+        self.states.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SuffixAutomaton;
+
+    #[test]
+    fn test_empty_text() {
+        let sa = SuffixAutomaton::new("");
+        assert_eq!(sa.text_len(), 0);
+        assert!(sa.contains(""));
+        assert!(!sa.contains("a"));
+        assert_eq!(sa.count(""), 1);
+        assert_eq!(sa.distinct_substrings(), 0);
+    }
+
+    #[test]
+    fn test_contains() {
+        let sa = SuffixAutomaton::new("abracadabra");
+        for pattern in ["a", "abra", "cad", "bracadabra", "abracadabra", "ra"] {
+            assert!(sa.contains(pattern), "{pattern}");
+        }
+        for pattern in ["abc", "dab ", "z", "abracadabraa"] {
+            assert!(!sa.contains(pattern), "{pattern}");
+        }
+    }
+
+    #[test]
+    fn test_count() {
+        let text = "abracadabra";
+        let sa = SuffixAutomaton::new(text);
+        assert_eq!(sa.count("a"), 5);
+        assert_eq!(sa.count("abra"), 2);
+        assert_eq!(sa.count("bra"), 2);
+        assert_eq!(sa.count("cad"), 1);
+        assert_eq!(sa.count("x"), 0);
+        assert_eq!(sa.count(""), text.len() + 1);
+
+        // Overlapping occurrences
+        let sa = SuffixAutomaton::new("aaaa");
+        assert_eq!(sa.count("aa"), 3);
+        assert_eq!(sa.count("aaa"), 2);
+    }
+
+    #[test]
+    fn test_matches_naive() {
+        let text = "mississippi river";
+        let sa = SuffixAutomaton::new(text);
+        let chars: Vec<char> = text.chars().collect();
+        let mut distinct = std::collections::HashSet::new();
+        for i in 0..chars.len() {
+            for j in i + 1..=chars.len() {
+                let pattern: String = chars[i..j].iter().collect();
+                let naive = (0..=chars.len() - (j - i))
+                    .filter(|&k| chars[k..k + (j - i)] == chars[i..j])
+                    .count();
+                assert_eq!(sa.count(&pattern), naive, "{pattern}");
+                distinct.insert(pattern);
+            }
+        }
+        assert_eq!(sa.distinct_substrings(), distinct.len());
+    }
+}
@@ -0,0 +1,3 @@
+pub mod blocking_queue;
+pub mod epoch;
+pub(crate) mod shim;
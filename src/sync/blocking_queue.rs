@@ -0,0 +1,215 @@
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::heap_size::HeapSize;
+use crate::trace;
+use crate::unrolled_list::UnrolledList;
+
+// Elements per node of the underlying list
+const NODE_SIZE: usize = 32;
+
+/// A bounded FIFO queue for passing values between threads, a plain lock
+/// around one of the crate's unrolled lists.
+///
+/// `push` blocks while the queue is full and `pop` while it is empty, each
+/// sleeping on its own condition variable until the other side makes room
+/// or adds an element. The `try_` variants return straight away, and the
+/// `_timeout` variants give up after the given time. Simpler and slower
+/// under contention than `MpmcQueue`, but with no power-of-two rounding and
+/// with real timeouts. Share it between threads with an `Arc`.
+pub struct BlockingQueue<T> {
+    items: Mutex<UnrolledList<T, NODE_SIZE>>,
+    capacity: usize,
+    not_empty: Condvar,
+    not_full: Condvar,
+}
+
+impl<T> BlockingQueue<T> {
+    /// A queue holding at most `capacity` elements.
+    ///
+    /// Panics if `capacity` is zero.
+    pub const fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "queue capacity must be positive");
+        BlockingQueue { items: Mutex::new(UnrolledList::new()), capacity, not_empty: Condvar::new(), not_full: Condvar::new() }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of queued elements. Only a snapshot while other threads are
+    /// pushing or popping.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> MutexGuard<'_, UnrolledList<T, NODE_SIZE>> {
+        // The list is consistent whenever the lock is released, even by a
+        // panicking thread, so poisoning can be ignored
+        self.items.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn push_locked(&self, mut items: MutexGuard<'_, UnrolledList<T, NODE_SIZE>>, val: T) {
+        items.push_back(val);
+        trace::event("BlockingQueue", "push", items.len());
+        drop(items);
+        self.not_empty.notify_one();
+    }
+
+    fn pop_locked(&self, mut items: MutexGuard<'_, UnrolledList<T, NODE_SIZE>>) -> Option<T> {
+        let val = items.pop_front()?;
+        trace::event("BlockingQueue", "pop", items.len());
+        drop(items);
+        self.not_full.notify_one();
+        Some(val)
+    }
+
+    /// Push `val`, blocking while the queue is full.
+    pub fn push(&self, val: T) {
+        let items = self.not_full.wait_while(self.lock(), |items| items.len() == self.capacity).unwrap_or_else(|e| e.into_inner());
+        self.push_locked(items, val);
+    }
+
+    /// Pop the oldest element, blocking while the queue is empty.
+    pub fn pop(&self) -> T {
+        let items = self.not_empty.wait_while(self.lock(), |items| items.is_empty()).unwrap_or_else(|e| e.into_inner());
+        self.pop_locked(items).unwrap()
+    }
+
+    /// Push `val`, or hand it back if the queue is full.
+    pub fn try_push(&self, val: T) -> Result<(), T> {
+        let items = self.lock();
+        if items.len() == self.capacity {
+            return Err(val);
+        }
+        self.push_locked(items, val);
+        Ok(())
+    }
+
+    /// Pop the oldest element, or `None` if the queue is empty.
+    pub fn try_pop(&self) -> Option<T> {
+        self.pop_locked(self.lock())
+    }
+
+    /// Push `val`, waiting up to `timeout` for room, or hand it back if the
+    /// queue is still full.
+    pub fn push_timeout(&self, val: T, timeout: Duration) -> Result<(), T> {
+        let (items, _) =
+            self.not_full.wait_timeout_while(self.lock(), timeout, |items| items.len() == self.capacity).unwrap_or_else(|e| e.into_inner());
+        if items.len() == self.capacity {
+            return Err(val);
+        }
+        self.push_locked(items, val);
+        Ok(())
+    }
+
+    /// Pop the oldest element, waiting up to `timeout` for one, or `None` if
+    /// the queue is still empty.
+    pub fn pop_timeout(&self, timeout: Duration) -> Option<T> {
+        let (items, _) = self.not_empty.wait_timeout_while(self.lock(), timeout, |items| items.is_empty()).unwrap_or_else(|e| e.into_inner());
+        self.pop_locked(items)
+    }
+}
+
+impl<T: HeapSize> HeapSize for BlockingQueue<T> {
+    fn heap_size(&self) -> usize {
+        self.lock().heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use super::BlockingQueue;
+
+    #[test]
+    fn test_try_push_pop() {
+        let queue = BlockingQueue::new(3);
+        assert_eq!(queue.try_pop(), None);
+        for i in 0..3 {
+            assert_eq!(queue.try_push(i), Ok(()));
+        }
+        assert_eq!(queue.try_push(3), Err(3));
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.try_pop(), Some(0));
+        assert_eq!(queue.try_push(3), Ok(()));
+        assert_eq!((0..3).map(|_| queue.pop()).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_timeouts() {
+        let queue = BlockingQueue::new(1);
+        let start = Instant::now();
+        assert_eq!(queue.pop_timeout(Duration::from_millis(20)), None);
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert_eq!(queue.push_timeout(1, Duration::from_millis(20)), Ok(()));
+        assert_eq!(queue.push_timeout(2, Duration::from_millis(20)), Err(2));
+        assert_eq!(queue.pop_timeout(Duration::from_millis(20)), Some(1));
+    }
+
+    #[test]
+    fn test_blocked_push_wakes() {
+        let queue = Arc::new(BlockingQueue::new(1));
+        queue.push(0);
+        let producer = {
+            let queue = queue.clone();
+            thread::spawn(move || queue.push_timeout(1, Duration::from_secs(10)))
+        };
+        thread::sleep(Duration::from_millis(10));
+        assert_eq!(queue.pop(), 0);
+        assert_eq!(queue.pop(), 1);
+        assert_eq!(producer.join().unwrap(), Ok(()));
+    }
+
+    #[test]
+    fn test_pipeline() {
+        const PRODUCERS: usize = 3;
+        const PER_PRODUCER: usize = 5_000;
+        let queue = Arc::new(BlockingQueue::new(8));
+        let producers: Vec<_> = (0..PRODUCERS)
+            .map(|p| {
+                let queue = queue.clone();
+                thread::spawn(move || {
+                    for i in 0..PER_PRODUCER {
+                        queue.push(p * PER_PRODUCER + i);
+                    }
+                })
+            })
+            .collect();
+        let consumers: Vec<_> = (0..2)
+            .map(|_| {
+                let queue = queue.clone();
+                thread::spawn(move || {
+                    let mut got = Vec::new();
+                    while let Some(val) = queue.pop_timeout(Duration::from_millis(200)) {
+                        got.push(val);
+                    }
+                    got
+                })
+            })
+            .collect();
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        let mut all: Vec<_> = consumers.into_iter().flat_map(|c| c.join().unwrap()).collect();
+        all.sort_unstable();
+        assert_eq!(all, (0..PRODUCERS * PER_PRODUCER).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_static() {
+        // No lazy initialization needed
+        static QUEUE: BlockingQueue<u32> = BlockingQueue::new(4);
+        let producer = thread::spawn(|| (0..10).for_each(|i| QUEUE.push(i)));
+        assert!((0..10).all(|i| QUEUE.pop() == i));
+        producer.join().unwrap();
+    }
+}
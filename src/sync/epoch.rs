@@ -0,0 +1,243 @@
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering, fence};
+use std::sync::{Arc, Mutex};
+
+// Defers between attempts to collect
+const COLLECT_EVERY: usize = 64;
+
+type Deferred = Box<dyn FnOnce() + Send>;
+
+/// One registered thread's state: the epoch it last pinned at, shifted left
+/// one, with the low bit set while it is pinned.
+struct Participant {
+    state: AtomicUsize,
+}
+
+struct Global {
+    epoch: AtomicUsize,
+    participants: Mutex<Vec<Arc<Participant>>>,
+    // Each deferred function with the epoch it was deferred in
+    garbage: Mutex<Vec<(usize, Deferred)>>,
+    defers: AtomicUsize,
+}
+
+static GLOBAL: Global = Global {
+    epoch: AtomicUsize::new(0),
+    participants: Mutex::new(Vec::new()),
+    garbage: Mutex::new(Vec::new()),
+    defers: AtomicUsize::new(0),
+};
+
+/// This thread's registration, removed again when the thread exits.
+struct Local {
+    participant: Arc<Participant>,
+    // Nesting depth of `pin` calls
+    pins: Cell<usize>,
+}
+
+impl Local {
+    fn register() -> Self {
+        let participant = Arc::new(Participant { state: AtomicUsize::new(0) });
+        GLOBAL.participants.lock().unwrap().push(participant.clone());
+        Local { participant, pins: Cell::new(0) }
+    }
+}
+
+impl Drop for Local {
+    fn drop(&mut self) {
+        GLOBAL.participants.lock().unwrap().retain(|p| !Arc::ptr_eq(p, &self.participant));
+    }
+}
+
+thread_local! {
+    static LOCAL: Local = Local::register();
+}
+
+/// Proof that the current thread is pinned. See `pin`.
+pub struct Guard {
+    // Pins are per thread, so the guard must stay on this one
+    _not_send: PhantomData<*const ()>,
+}
+
+/// Pin the current thread until the returned guard is dropped.
+///
+/// While any thread is pinned, memory it might still be reading is not
+/// freed: something unlinked from a shared structure and passed to
+/// `Guard::defer` is only destroyed once every thread that was pinned at
+/// the time has unpinned. Pins nest, and pinning is a couple of atomic
+/// operations, so it is fine to pin around each individual operation.
+///
+/// This is epoch-based reclamation: a global epoch counter advances only
+/// when every pinned thread has seen the current value, and garbage deferred
+/// in epoch `e` is safe to destroy once the counter reaches `e + 2`.
+pub fn pin() -> Guard {
+    LOCAL.with(|local| {
+        let pins = local.pins.get();
+        local.pins.set(pins + 1);
+        if pins == 0 {
+            let epoch = GLOBAL.epoch.load(Ordering::Relaxed);
+            local.participant.state.store(epoch << 1 | 1, Ordering::Relaxed);
+            // Publish the pin before any shared pointer is read
+            fence(Ordering::SeqCst);
+        }
+    });
+    Guard { _not_send: PhantomData }
+}
+
+impl Guard {
+    /// Run `f` once no thread can still be reading what it frees.
+    pub fn defer<F: FnOnce() + Send + 'static>(&self, f: F) {
+        self.push(Box::new(f));
+    }
+
+    /// Like `defer`, for closures that are not `Send` or `'static`, such as
+    /// ones freeing a raw pointer to a `T` that borrows.
+    ///
+    /// # Safety
+    ///
+    /// `f` may run on any thread, at any later time, so everything it
+    /// touches must be safe to use from there.
+    pub unsafe fn defer_unchecked<F: FnOnce()>(&self, f: F) {
+        let f: Box<dyn FnOnce() + '_> = Box::new(f);
+        // Safety: upheld by the caller
+        let f: Deferred = unsafe { std::mem::transmute(f) };
+        self.push(f);
+    }
+
+    fn push(&self, f: Deferred) {
+        let epoch = GLOBAL.epoch.load(Ordering::SeqCst);
+        GLOBAL.garbage.lock().unwrap().push((epoch, f));
+        if GLOBAL.defers.fetch_add(1, Ordering::Relaxed) % COLLECT_EVERY == COLLECT_EVERY - 1 {
+            collect();
+        }
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        // Ignore threads whose locals are already gone during shutdown
+        let _ = LOCAL.try_with(|local| {
+            let pins = local.pins.get() - 1;
+            local.pins.set(pins);
+            if pins == 0 {
+                local.participant.state.fetch_and(!1, Ordering::Release);
+            }
+        });
+    }
+}
+
+/// Advance the global epoch if every pinned thread has caught up.
+fn try_advance() -> usize {
+    let epoch = GLOBAL.epoch.load(Ordering::SeqCst);
+    fence(Ordering::SeqCst);
+    let participants = GLOBAL.participants.lock().unwrap();
+    for participant in participants.iter() {
+        let state = participant.state.load(Ordering::SeqCst);
+        if state & 1 == 1 && state >> 1 != epoch {
+            return epoch;
+        }
+    }
+    drop(participants);
+    match GLOBAL.epoch.compare_exchange(epoch, epoch + 1, Ordering::SeqCst, Ordering::SeqCst) {
+        Ok(_) => epoch + 1,
+        Err(current) => current,
+    }
+}
+
+/// Try to advance the epoch, then run every deferred function that has
+/// become safe. Called automatically every so often by `defer`.
+pub fn collect() {
+    let epoch = try_advance();
+    let ready: Vec<Deferred> = {
+        let mut garbage = GLOBAL.garbage.lock().unwrap();
+        let (ready, waiting) = std::mem::take(&mut *garbage).into_iter().partition(|(e, _)| e + 2 <= epoch);
+        *garbage = waiting;
+        ready.into_iter().map(|(_, f)| f).collect()
+    };
+    for f in ready {
+        f();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::thread;
+
+    use super::{collect, pin};
+
+    // Other tests pin briefly on their own threads, so give collection a
+    // few rounds to get past them
+    fn collect_until(done: impl Fn() -> bool) -> bool {
+        for _ in 0..10_000 {
+            collect();
+            if done() {
+                return true;
+            }
+            thread::yield_now();
+        }
+        false
+    }
+
+    #[test]
+    fn test_deferred_runs_after_unpin() {
+        let ran = Arc::new(AtomicBool::new(false));
+        {
+            let guard = pin();
+            let flag = ran.clone();
+            guard.defer(move || flag.store(true, Ordering::SeqCst));
+            for _ in 0..10 {
+                collect();
+            }
+            // Still pinned, so it can't have been freed under us
+            assert!(!ran.load(Ordering::SeqCst));
+        }
+        assert!(collect_until(|| ran.load(Ordering::SeqCst)));
+    }
+
+    #[test]
+    fn test_nested_pins() {
+        let ran = Arc::new(AtomicBool::new(false));
+        let outer = pin();
+        {
+            let inner = pin();
+            let ran = ran.clone();
+            inner.defer(move || ran.store(true, Ordering::SeqCst));
+        }
+        for _ in 0..10 {
+            collect();
+        }
+        // The outer pin still holds it back
+        assert!(!ran.load(Ordering::SeqCst));
+        drop(outer);
+        assert!(collect_until(|| ran.load(Ordering::SeqCst)));
+    }
+
+    #[test]
+    fn test_pinned_thread_blocks_collection() {
+        let ran = Arc::new(AtomicUsize::new(0));
+        let (pinned_tx, pinned_rx) = std::sync::mpsc::channel();
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let reader = thread::spawn(move || {
+            let _guard = pin();
+            pinned_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+        });
+        pinned_rx.recv().unwrap();
+        {
+            let ran = ran.clone();
+            pin().defer(move || {
+                ran.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        for _ in 0..10 {
+            collect();
+        }
+        assert_eq!(ran.load(Ordering::SeqCst), 0);
+        release_tx.send(()).unwrap();
+        reader.join().unwrap();
+        assert!(collect_until(|| ran.load(Ordering::SeqCst) == 1));
+    }
+}
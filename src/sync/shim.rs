@@ -0,0 +1,37 @@
+// The atomics, locks and cells the lock-free structures are built on, taken
+// from `loom` when the crate is built with `--cfg loom` and from `std`
+// otherwise. Under loom every load, store and lock is a point where the
+// model checker can switch threads, so a test run with
+//
+//     RUSTFLAGS="--cfg loom" cargo test --release loom_tests
+//
+// explores every interleaving of the threads it spawns.
+
+#[cfg(loom)]
+pub(crate) use loom::cell::UnsafeCell;
+#[cfg(loom)]
+pub(crate) use loom::sync::atomic::{AtomicUsize, Ordering, fence};
+#[cfg(loom)]
+pub(crate) use loom::sync::{Arc, Condvar, Mutex};
+
+#[cfg(not(loom))]
+pub(crate) use std::sync::atomic::{AtomicUsize, Ordering, fence};
+#[cfg(not(loom))]
+pub(crate) use std::sync::{Arc, Condvar, Mutex};
+
+/// `std::cell::UnsafeCell` with loom's closure-based access, so the same
+/// code compiles against both and loom can check that accesses to the
+/// contents never race.
+#[cfg(not(loom))]
+pub(crate) struct UnsafeCell<T>(std::cell::UnsafeCell<T>);
+
+#[cfg(not(loom))]
+impl<T> UnsafeCell<T> {
+    pub(crate) fn new(val: T) -> Self {
+        UnsafeCell(std::cell::UnsafeCell::new(val))
+    }
+
+    pub(crate) fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+        f(self.0.get())
+    }
+}
@@ -0,0 +1,122 @@
+#[cfg(feature = "trace")]
+use std::time::Instant;
+
+// Instrumentation for the `trace` feature. Every event carries the name of
+// the structure, the operation and the structure's length afterwards.
+// Without the feature these are empty and compile away, so call sites need
+// no `cfg` of their own.
+
+/// Report a cheap operation, such as a push or a pop, at `TRACE` level.
+#[inline]
+pub(crate) fn event(structure: &'static str, op: &'static str, len: usize) {
+    #[cfg(feature = "trace")]
+    tracing::trace!(structure, op, len);
+    #[cfg(not(feature = "trace"))]
+    let _ = (structure, op, len);
+}
+
+/// Times a structural operation, such as a rehash or a split, and reports it
+/// at `DEBUG` level with the time it took once it is finished.
+pub(crate) struct Timed {
+    #[cfg(feature = "trace")]
+    start: Instant,
+}
+
+impl Timed {
+    #[inline]
+    pub(crate) fn start() -> Self {
+        Timed {
+            #[cfg(feature = "trace")]
+            start: Instant::now(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn finish(self, structure: &'static str, op: &'static str, len: usize) {
+        #[cfg(feature = "trace")]
+        tracing::debug!(structure, op, len, elapsed_ns = self.start.elapsed().as_nanos() as u64);
+        #[cfg(not(feature = "trace"))]
+        let _ = (structure, op, len);
+    }
+}
+
+#[cfg(all(test, feature = "trace"))]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    use crate::doubly_list::LinkedList;
+    use crate::hash_map::HashMap;
+    use crate::list::List;
+
+    // Collects each event as "structure op len", leaving out the timing
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    struct Line(String);
+
+    impl Visit for Line {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            if field.name() != "elapsed_ns" {
+                if !self.0.is_empty() {
+                    self.0.push(' ');
+                }
+                self.0.push_str(format!("{value:?}").trim_matches('"'));
+            }
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut line = Line(String::new());
+            event.record(&mut line);
+            self.0.lock().unwrap().push(line.0);
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn test_events() {
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let mut list = LinkedList::new();
+            list.push_back(1);
+            list.push_front(0);
+            list.pop_back();
+            let mut map = HashMap::new();
+            map.insert("a", 1);
+        });
+        let events = recorder.0.lock().unwrap();
+        assert_eq!(events[..3], ["LinkedList push_back 1", "LinkedList push_front 2", "LinkedList pop_back 1"]);
+        assert!(events[3..].iter().any(|event| event.starts_with("HashMap rehash")));
+    }
+
+    #[test]
+    fn test_fallible_events() {
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let mut list = List::new();
+            list.try_push_front(1).unwrap();
+            list.push_front(2);
+        });
+        assert_eq!(*recorder.0.lock().unwrap(), ["List push_front 1", "List push_front 2"]);
+    }
+}
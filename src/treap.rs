@@ -0,0 +1,517 @@
+use std::cmp::Ordering;
+use std::fmt::Debug;
+
+use crate::dot::{DotWriter, ToDot};
+use crate::heap_size::HeapSize;
+use crate::pretty::{PrettyPrint, sideways};
+use crate::rng::XorShift64;
+
+type Link<K, V> = Option<Box<Node<K, V>>>;
+
+struct Node<K, V> {
+    key: K,
+    value: V,
+    priority: u64,
+    size: usize,
+    left: Link<K, V>,
+    right: Link<K, V>,
+}
+
+impl<K, V> Node<K, V> {
+    fn update(&mut self) {
+        self.size = 1 + size(&self.left) + size(&self.right);
+    }
+}
+
+fn size<K, V>(link: &Link<K, V>) -> usize {
+    link.as_ref().map_or(0, |node| node.size)
+}
+
+/// Check that keys are in order and within `lower..upper`, that no node has
+/// a higher priority than its parent, and that sizes are right. Returns the
+/// size of the subtree.
+#[cfg(any(test, debug_assertions))]
+fn validate<K: Ord, V>(link: &Link<K, V>, lower: Option<&K>, upper: Option<&K>, max_priority: u64) -> usize {
+    let Some(node) = link else {
+        return 0;
+    };
+    assert!(lower.is_none_or(|lower| *lower < node.key) && upper.is_none_or(|upper| node.key < *upper), "keys out of order");
+    assert!(node.priority <= max_priority, "priority above its parent's");
+    let size = 1 + validate(&node.left, lower, Some(&node.key), node.priority)
+        + validate(&node.right, Some(&node.key), upper, node.priority);
+    assert_eq!(node.size, size, "stale subtree size");
+    size
+}
+
+/// Split a subtree into the keys less than `key` and the keys greater than or
+/// equal to `key`.
+fn split<K: Ord, V>(link: Link<K, V>, key: &K) -> (Link<K, V>, Link<K, V>) {
+    let Some(mut node) = link else {
+        return (None, None);
+    };
+    if node.key < *key {
+        let (less, rest) = split(node.right.take(), key);
+        node.right = less;
+        node.update();
+        (Some(node), rest)
+    } else {
+        let (less, rest) = split(node.left.take(), key);
+        node.left = rest;
+        node.update();
+        (less, Some(node))
+    }
+}
+
+/// Join two subtrees where every key in `left` is less than every key in `right`.
+fn merge<K, V>(left: Link<K, V>, right: Link<K, V>) -> Link<K, V> {
+    match (left, right) {
+        (None, right) => right,
+        (left, None) => left,
+        (Some(mut l), Some(mut r)) => {
+            if l.priority > r.priority {
+                l.right = merge(l.right.take(), Some(r));
+                l.update();
+                Some(l)
+            } else {
+                r.left = merge(Some(l), r.left.take());
+                r.update();
+                Some(r)
+            }
+        }
+    }
+}
+
+fn remove<K: Ord, V>(link: &mut Link<K, V>, key: &K) -> Option<V> {
+    let node = link.as_mut()?;
+    let removed = match key.cmp(&node.key) {
+        Ordering::Less => remove(&mut node.left, key),
+        Ordering::Greater => remove(&mut node.right, key),
+        Ordering::Equal => {
+            let mut node = link.take()?;
+            *link = merge(node.left.take(), node.right.take());
+            return Some(node.value);
+        }
+    };
+    if removed.is_some() {
+        node.size -= 1;
+    }
+    removed
+}
+
+/// An ordered map backed by a treap: a binary search tree on the keys that is
+/// also a max-heap on randomly chosen priorities, which keeps the expected
+/// depth at O(log n).
+///
+/// Besides the usual map operations, `split` and `merge` are exposed so whole
+/// key ranges can be moved between treaps in O(log n).
+pub struct Treap<K, V> {
+    root: Link<K, V>,
+    rng: XorShift64,
+}
+
+impl<K: Ord, V> Treap<K, V> {
+    pub fn new() -> Self {
+        Treap { root: None, rng: XorShift64::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        size(&self.root)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Panic unless the nodes are in key order, heap-ordered by priority,
+    /// and carry the right subtree sizes. Only in debug builds and tests.
+    #[cfg(any(test, debug_assertions))]
+    pub fn validate(&self) {
+        validate(&self.root, None, None, u64::MAX);
+    }
+
+    pub fn clear(&mut self) {
+        self.root = None;
+    }
+
+    /// Insert a key-value pair, returning the previous value if the key was
+    /// already present.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(old) = self.get_mut(&key) {
+            return Some(std::mem::replace(old, value));
+        }
+        let node = Box::new(Node {
+            key,
+            value,
+            priority: self.rng.next_u64(),
+            size: 1,
+            left: None,
+            right: None,
+        });
+        let (less, rest) = split(self.root.take(), &node.key);
+        self.root = merge(merge(less, Some(node)), rest);
+        None
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        remove(&mut self.root, key)
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let mut curr = self.root.as_deref();
+        while let Some(node) = curr {
+            curr = match key.cmp(&node.key) {
+                Ordering::Less => node.left.as_deref(),
+                Ordering::Greater => node.right.as_deref(),
+                Ordering::Equal => return Some(&node.value),
+            };
+        }
+        None
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let mut curr = self.root.as_deref_mut();
+        while let Some(node) = curr {
+            curr = match key.cmp(&node.key) {
+                Ordering::Less => node.left.as_deref_mut(),
+                Ordering::Greater => node.right.as_deref_mut(),
+                Ordering::Equal => return Some(&mut node.value),
+            };
+        }
+        None
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        let mut node = self.root.as_deref()?;
+        while let Some(left) = node.left.as_deref() {
+            node = left;
+        }
+        Some((&node.key, &node.value))
+    }
+
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        let mut node = self.root.as_deref()?;
+        while let Some(right) = node.right.as_deref() {
+            node = right;
+        }
+        Some((&node.key, &node.value))
+    }
+
+    /// The entry with the greatest key less than or equal to `key`.
+    pub fn floor(&self, key: &K) -> Option<(&K, &V)> {
+        let mut node = self.root.as_deref();
+        let mut best = None;
+        while let Some(n) = node {
+            if n.key <= *key {
+                best = Some((&n.key, &n.value));
+                node = n.right.as_deref();
+            } else {
+                node = n.left.as_deref();
+            }
+        }
+        best
+    }
+
+    /// The entry with the `k`-th smallest key, counting from 0, in O(log n)
+    /// using the subtree sizes.
+    pub fn select(&self, mut k: usize) -> Option<(&K, &V)> {
+        let mut node = self.root.as_deref()?;
+        loop {
+            let left = size(&node.left);
+            node = match k.cmp(&left) {
+                Ordering::Less => node.left.as_deref()?,
+                Ordering::Equal => return Some((&node.key, &node.value)),
+                Ordering::Greater => {
+                    k -= left + 1;
+                    node.right.as_deref()?
+                }
+            };
+        }
+    }
+
+    /// Number of keys less than `key`, whether or not `key` is present.
+    pub fn rank(&self, key: &K) -> usize {
+        let mut node = self.root.as_deref();
+        let mut rank = 0;
+        while let Some(n) = node {
+            if n.key < *key {
+                rank += size(&n.left) + 1;
+                node = n.right.as_deref();
+            } else {
+                node = n.left.as_deref();
+            }
+        }
+        rank
+    }
+
+    /// Split into the entries with keys less than `key` and the entries with
+    /// keys greater than or equal to `key`.
+    pub fn split(mut self, key: &K) -> (Self, Self) {
+        let (less, rest) = split(self.root.take(), key);
+        self.root = less;
+        (self, Treap { root: rest, rng: XorShift64::new() })
+    }
+
+    /// Concatenate two treaps. Every key in `self` must be less than every key
+    /// in `other`.
+    ///
+    /// Panics if the key ranges overlap.
+    pub fn merge(mut self, mut other: Self) -> Self {
+        if let (Some((last, _)), Some((first, _))) = (self.last_key_value(), other.first_key_value()) {
+            assert!(last < first, "merged treaps must have disjoint, ordered key ranges");
+        }
+        self.root = merge(self.root.take(), other.root.take());
+        self
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        let mut iter = Iter { stack: Vec::new() };
+        iter.push_left(self.root.as_deref());
+        iter
+    }
+}
+
+impl<K: Ord, V> Default for Treap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// In-order iterator over the entries of a treap.
+pub struct Iter<'a, K, V> {
+    stack: Vec<&'a Node<K, V>>,
+}
+
+impl<'a, K, V> Iter<'a, K, V> {
+    fn push_left(&mut self, mut link: Option<&'a Node<K, V>>) {
+        while let Some(node) = link {
+            self.stack.push(node);
+            link = node.left.as_deref();
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.push_left(node.right.as_deref());
+        Some((&node.key, &node.value))
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a Treap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K: Ord + Debug, V: Debug> Debug for Treap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: HeapSize, V: HeapSize> HeapSize for Node<K, V> {
+    fn heap_size(&self) -> usize {
+        self.key.heap_size() + self.value.heap_size() + self.left.heap_size() + self.right.heap_size()
+    }
+}
+
+impl<K: HeapSize, V: HeapSize> HeapSize for Treap<K, V> {
+    fn heap_size(&self) -> usize {
+        self.root.heap_size()
+    }
+}
+
+// Write a subtree and return the id of its root
+fn write_dot<K: Debug, V>(dot: &mut DotWriter, node: &Node<K, V>) -> Result<usize, std::fmt::Error> {
+    let id = dot.node(&format!("{:?}\npriority {}\nsize {}", node.key, node.priority, node.size))?;
+    for (child, side) in [(&node.left, "L"), (&node.right, "R")] {
+        if let Some(child) = child {
+            let child = write_dot(dot, child)?;
+            dot.edge(id, child, side)?;
+        }
+    }
+    Ok(id)
+}
+
+/// Labels each node with its key, priority and subtree size.
+impl<K: Debug, V> ToDot for Treap<K, V> {
+    fn write_dot(&self, out: &mut dyn std::fmt::Write) -> std::fmt::Result {
+        let mut dot = DotWriter::new(out)?;
+        if let Some(root) = &self.root {
+            write_dot(&mut dot, root)?;
+        }
+        dot.finish()
+    }
+}
+
+impl<K: Debug, V> PrettyPrint for Treap<K, V> {
+    fn pretty_print(&self) -> String {
+        sideways(self.root.as_deref(), |node| [node.left.as_deref(), node.right.as_deref()], |node| format!("{:?}", node.key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dot::ToDot;
+
+    use super::Treap;
+
+    fn keys<V>(treap: &Treap<i32, V>) -> Vec<i32> {
+        treap.iter().map(|(k, _)| *k).collect()
+    }
+
+    #[test]
+    fn test_new() {
+        let treap: Treap<i32, i32> = Treap::new();
+        assert_eq!(treap.len(), 0);
+        assert!(treap.is_empty());
+        assert!(treap.get(&1).is_none());
+    }
+
+    #[test]
+    fn test_insert_get() {
+        let mut treap = Treap::new();
+        assert_eq!(treap.insert(2, "two"), None);
+        assert_eq!(treap.insert(1, "one"), None);
+        assert_eq!(treap.insert(3, "three"), None);
+        assert_eq!(treap.len(), 3);
+
+        assert_eq!(treap.get(&1), Some(&"one"));
+        assert_eq!(treap.get(&2), Some(&"two"));
+        assert_eq!(treap.get(&3), Some(&"three"));
+        assert_eq!(treap.get(&4), None);
+
+        assert_eq!(treap.insert(2, "TWO"), Some("two"));
+        assert_eq!(treap.len(), 3);
+        assert_eq!(treap.get(&2), Some(&"TWO"));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut treap = Treap::new();
+        for i in 0..10 {
+            treap.insert(i, i * 10);
+        }
+        assert_eq!(treap.remove(&4), Some(40));
+        assert_eq!(treap.remove(&4), None);
+        assert_eq!(treap.remove(&100), None);
+        assert_eq!(treap.len(), 9);
+        assert_eq!(keys(&treap), vec![0, 1, 2, 3, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn test_iter_sorted() {
+        let mut treap = Treap::new();
+        for i in [5, 3, 8, 1, 4, 7, 9, 2, 6, 0] {
+            treap.insert(i, ());
+        }
+        assert_eq!(keys(&treap), (0..10).collect::<Vec<_>>());
+        assert_eq!(treap.first_key_value(), Some((&0, &())));
+        assert_eq!(treap.last_key_value(), Some((&9, &())));
+    }
+
+    #[test]
+    fn test_get_mut() {
+        let mut treap = Treap::new();
+        treap.insert(1, 10);
+        *treap.get_mut(&1).unwrap() += 5;
+        assert_eq!(treap.get(&1), Some(&15));
+        assert!(treap.get_mut(&2).is_none());
+    }
+
+    #[test]
+    fn test_split() {
+        let mut treap = Treap::new();
+        for i in 0..100 {
+            treap.insert(i, i);
+        }
+        let (less, rest) = treap.split(&40);
+        less.validate();
+        rest.validate();
+        assert_eq!(less.len(), 40);
+        assert_eq!(rest.len(), 60);
+        assert_eq!(keys(&less), (0..40).collect::<Vec<_>>());
+        assert_eq!(keys(&rest), (40..100).collect::<Vec<_>>());
+
+        // Splitting at a missing key or outside the range
+        let (less, rest) = rest.split(&1000);
+        assert_eq!(less.len(), 60);
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut left = Treap::new();
+        let mut right = Treap::new();
+        for i in 0..50 {
+            left.insert(i, ());
+            right.insert(i + 50, ());
+        }
+        let merged = left.merge(right);
+        assert_eq!(merged.len(), 100);
+        assert_eq!(keys(&merged), (0..100).collect::<Vec<_>>());
+
+        let merged = merged.merge(Treap::new());
+        assert_eq!(merged.len(), 100);
+    }
+
+    #[test]
+    fn test_floor() {
+        let mut treap = Treap::new();
+        for i in [10, 20, 30] {
+            treap.insert(i, i / 10);
+        }
+        assert_eq!(treap.floor(&5), None);
+        assert_eq!(treap.floor(&10), Some((&10, &1)));
+        assert_eq!(treap.floor(&29), Some((&20, &2)));
+        assert_eq!(treap.floor(&100), Some((&30, &3)));
+    }
+
+    #[test]
+    fn test_select_rank() {
+        let mut treap = Treap::new();
+        for i in (0..100).rev() {
+            treap.insert(i * 2, i);
+        }
+        for k in 0..100 {
+            assert_eq!(treap.select(k), Some((&(k as i32 * 2), &(k as i32))));
+            assert_eq!(treap.rank(&(k as i32 * 2)), k);
+            assert_eq!(treap.rank(&(k as i32 * 2 + 1)), k + 1);
+        }
+        assert_eq!(treap.select(100), None);
+        assert_eq!(treap.rank(&-5), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_merge_overlapping() {
+        let mut left = Treap::new();
+        let mut right = Treap::new();
+        left.insert(5, ());
+        right.insert(3, ());
+        left.merge(right);
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let mut treap = Treap::new();
+        assert_eq!(treap.to_dot(), "digraph {\n    node [shape=box];\n}\n");
+        for i in 0..20 {
+            treap.insert(i, ());
+        }
+        let dot = treap.to_dot();
+        assert_eq!(dot.matches(" -> ").count(), 19);
+        assert!(dot.contains(&format!("label=\"{:?}\\npriority", treap.root.as_ref().unwrap().key)));
+        assert!(dot.contains("\\nsize 20\"]"));
+    }
+}
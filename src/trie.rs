@@ -0,0 +1,324 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+
+use crate::heap_size::HeapSize;
+
+#[derive(Default)]
+struct Node {
+    children: BTreeMap<char, Node>,
+    terminal: bool,
+    // Only meaningful on terminals
+    weight: u64,
+}
+
+/// A prefix tree over string keys, one edge per character.
+///
+/// Children are kept sorted, so keys are always produced in lexicographic
+/// order. Each key also carries a weight, 0 unless set with
+/// `insert_weighted`, which `top_k` ranks completions by.
+#[derive(Default)]
+pub struct Trie {
+    root: Node,
+    len: usize,
+}
+
+impl Trie {
+    pub fn new() -> Self {
+        Trie { root: Node::default(), len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn find_or_insert(&mut self, key: &str) -> &mut Node {
+        let mut node = &mut self.root;
+        for c in key.chars() {
+            node = node.children.entry(c).or_default();
+        }
+        if !node.terminal {
+            node.terminal = true;
+            node.weight = 0;
+            self.len += 1;
+        }
+        node
+    }
+
+    /// Insert a key, returning `false` if it was already present.
+    pub fn insert(&mut self, key: &str) -> bool {
+        let len = self.len;
+        self.find_or_insert(key);
+        self.len > len
+    }
+
+    /// Insert a key with a weight, or set the weight of an existing key.
+    /// Returns `false` if the key was already present.
+    pub fn insert_weighted(&mut self, key: &str, weight: u64) -> bool {
+        let len = self.len;
+        self.find_or_insert(key).weight = weight;
+        self.len > len
+    }
+
+    /// The weight of `key`, if present.
+    pub fn weight(&self, key: &str) -> Option<u64> {
+        self.find(key).filter(|node| node.terminal).map(|node| node.weight)
+    }
+
+    /// Remove a key, returning `false` if it was not present. Branches left
+    /// without any keys are pruned.
+    pub fn remove(&mut self, key: &str) -> bool {
+        fn remove(node: &mut Node, mut chars: std::str::Chars<'_>) -> bool {
+            let Some(c) = chars.next() else {
+                return std::mem::take(&mut node.terminal);
+            };
+            let Some(child) = node.children.get_mut(&c) else {
+                return false;
+            };
+            let removed = remove(child, chars);
+            if removed && !child.terminal && child.children.is_empty() {
+                node.children.remove(&c);
+            }
+            removed
+        }
+        let removed = remove(&mut self.root, key.chars());
+        if removed {
+            self.len -= 1;
+        }
+        removed
+    }
+
+    fn find(&self, prefix: &str) -> Option<&Node> {
+        let mut node = &self.root;
+        for c in prefix.chars() {
+            node = node.children.get(&c)?;
+        }
+        Some(node)
+    }
+
+    /// Exact lookup.
+    pub fn contains(&self, key: &str) -> bool {
+        self.find(key).is_some_and(|node| node.terminal)
+    }
+
+    /// Whether any key starts with `prefix`.
+    pub fn starts_with(&self, prefix: &str) -> bool {
+        match self.find(prefix) {
+            Some(node) => node.terminal || !node.children.is_empty(),
+            None => false,
+        }
+    }
+
+    /// Iterate in lexicographic order over all keys starting with `prefix`.
+    pub fn keys_with_prefix(&self, prefix: &str) -> Keys<'_> {
+        let stack = match self.find(prefix) {
+            Some(node) => vec![(node, prefix.to_string())],
+            None => Vec::new(),
+        };
+        Keys { stack }
+    }
+
+    /// The `k` heaviest keys starting with `prefix` with their weights,
+    /// heaviest first and equal weights in lexicographic order.
+    ///
+    /// Walks every key under `prefix`, keeping the best `k` so far in a
+    /// min-heap, so it takes O(m log k) for m matching keys.
+    pub fn top_k(&self, prefix: &str, k: usize) -> Vec<(String, u64)> {
+        let Some(node) = self.find(prefix) else {
+            return Vec::new();
+        };
+        // Ordered so the worst candidate is on top
+        let mut best = BinaryHeap::with_capacity(k + 1);
+        let mut stack = vec![(node, prefix.to_string())];
+        while let Some((node, key)) = stack.pop() {
+            for (c, child) in &node.children {
+                let mut child_key = key.clone();
+                child_key.push(*c);
+                stack.push((child, child_key));
+            }
+            if node.terminal {
+                best.push(Reverse((node.weight, Reverse(key))));
+                if best.len() > k {
+                    best.pop();
+                }
+            }
+        }
+        best.into_sorted_vec().into_iter().map(|Reverse((weight, Reverse(key)))| (key, weight)).collect()
+    }
+
+    /// Iterate in lexicographic order over all keys.
+    pub fn iter(&self) -> Keys<'_> {
+        self.keys_with_prefix("")
+    }
+}
+
+/// Iterator over the keys of a trie, produced by a depth-first walk.
+pub struct Keys<'a> {
+    stack: Vec<(&'a Node, String)>,
+}
+
+impl Iterator for Keys<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, key)) = self.stack.pop() {
+            for (c, child) in node.children.iter().rev() {
+                let mut child_key = key.clone();
+                child_key.push(*c);
+                self.stack.push((child, child_key));
+            }
+            if node.terminal {
+                return Some(key);
+            }
+        }
+        None
+    }
+}
+
+impl<'a> IntoIterator for &'a Trie {
+    type Item = String;
+    type IntoIter = Keys<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> FromIterator<&'a str> for Trie {
+    fn from_iter<I: IntoIterator<Item = &'a str>>(iter: I) -> Self {
+        let mut trie = Trie::new();
+        for key in iter {
+            trie.insert(key);
+        }
+        trie
+    }
+}
+
+impl std::fmt::Debug for Trie {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl HeapSize for Node {
+    fn heap_size(&self) -> usize {
+        self.children.heap_size()
+    }
+}
+
+impl HeapSize for Trie {
+    fn heap_size(&self) -> usize {
+        self.root.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heap_size::HeapSize;
+
+    use super::Trie;
+
+    #[test]
+    fn test_new() {
+        let trie = Trie::new();
+        assert_eq!(trie.len(), 0);
+        assert!(trie.is_empty());
+        assert!(!trie.contains(""));
+        assert!(!trie.starts_with(""));
+    }
+
+    #[test]
+    fn test_insert_contains() {
+        let mut trie = Trie::new();
+        assert!(trie.insert("car"));
+        assert!(trie.insert("cart"));
+        assert!(trie.insert("cat"));
+        assert!(!trie.insert("car"));
+        assert_eq!(trie.len(), 3);
+
+        assert!(trie.contains("car"));
+        assert!(trie.contains("cart"));
+        assert!(trie.contains("cat"));
+        assert!(!trie.contains("ca"));
+        assert!(!trie.contains("cars"));
+    }
+
+    #[test]
+    fn test_starts_with() {
+        let trie: Trie = ["apple", "apply", "banana"].into_iter().collect();
+        assert!(trie.starts_with("app"));
+        assert!(trie.starts_with("apple"));
+        assert!(trie.starts_with("b"));
+        assert!(!trie.starts_with("c"));
+        assert!(!trie.starts_with("apples"));
+    }
+
+    #[test]
+    fn test_keys_with_prefix() {
+        let trie: Trie = ["tea", "ten", "to", "inn", "tenet", "i"].into_iter().collect();
+        let keys: Vec<_> = trie.keys_with_prefix("te").collect();
+        assert_eq!(keys, vec!["tea", "ten", "tenet"]);
+        let keys: Vec<_> = trie.keys_with_prefix("x").collect();
+        assert!(keys.is_empty());
+        let keys: Vec<_> = trie.iter().collect();
+        assert_eq!(keys, vec!["i", "inn", "tea", "ten", "tenet", "to"]);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut trie: Trie = ["do", "dog", "dot"].into_iter().collect();
+        assert!(trie.remove("dog"));
+        assert!(!trie.remove("dog"));
+        assert!(!trie.remove("d"));
+        assert_eq!(trie.len(), 2);
+        assert!(trie.contains("do"));
+        assert!(!trie.starts_with("dog"));
+
+        assert!(trie.remove("do"));
+        assert!(trie.remove("dot"));
+        assert!(trie.is_empty());
+        assert!(!trie.starts_with("d"));
+    }
+
+    #[test]
+    fn test_top_k() {
+        let mut trie = Trie::new();
+        for (key, weight) in [("car", 5), ("cart", 9), ("cat", 5), ("care", 1), ("dog", 100)] {
+            assert!(trie.insert_weighted(key, weight));
+        }
+        assert!(trie.insert("ca"));
+        assert_eq!(trie.top_k("ca", 3), vec![("cart".to_string(), 9), ("car".to_string(), 5), ("cat".to_string(), 5)]);
+        assert_eq!(trie.top_k("", 1), vec![("dog".to_string(), 100)]);
+        assert!(trie.top_k("ca", 0).is_empty());
+        assert!(trie.top_k("x", 3).is_empty());
+        assert_eq!(trie.top_k("car", 10).len(), 3);
+
+        // Reweighting an existing key
+        assert!(!trie.insert_weighted("care", 50));
+        assert_eq!(trie.weight("care"), Some(50));
+        assert_eq!(trie.weight("ca"), Some(0));
+        assert_eq!(trie.weight("c"), None);
+        assert_eq!(trie.top_k("c", 1), vec![("care".to_string(), 50)]);
+    }
+
+    #[test]
+    fn test_unicode() {
+        let trie: Trie = ["über", "übel", "uber"].into_iter().collect();
+        let keys: Vec<_> = trie.keys_with_prefix("üb").collect();
+        assert_eq!(keys, vec!["übel", "über"]);
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let mut trie = Trie::new();
+        assert_eq!(trie.heap_size(), 0);
+        trie.insert("ab");
+        let one = trie.heap_size();
+        trie.insert("ac");
+        // "ac" shares the node for 'a'
+        assert_eq!(trie.heap_size() - one, one / 2);
+    }
+}
@@ -0,0 +1,280 @@
+use std::cmp::Ordering;
+use std::fmt::Debug;
+
+use crate::heap_size::HeapSize;
+
+type Link<V> = Option<Box<Node<V>>>;
+
+struct Node<V> {
+    c: char,
+    value: Option<V>,
+    lo: Link<V>,
+    eq: Link<V>,
+    hi: Link<V>,
+}
+
+impl<V> Node<V> {
+    fn new(c: char) -> Self {
+        Node { c, value: None, lo: None, eq: None, hi: None }
+    }
+}
+
+fn insert<V>(link: &mut Link<V>, key: &[char], value: V) -> Option<V> {
+    let node = link.get_or_insert_with(|| Box::new(Node::new(key[0])));
+    match key[0].cmp(&node.c) {
+        Ordering::Less => insert(&mut node.lo, key, value),
+        Ordering::Greater => insert(&mut node.hi, key, value),
+        Ordering::Equal if key.len() == 1 => node.value.replace(value),
+        Ordering::Equal => insert(&mut node.eq, &key[1..], value),
+    }
+}
+
+/// Collect every entry below `link` in order, with `prefix` holding the
+/// characters on the path so far.
+fn collect<'a, V>(link: &'a Link<V>, prefix: &mut String, out: &mut Vec<(String, &'a V)>) {
+    let Some(node) = link else {
+        return;
+    };
+    collect(&node.lo, prefix, out);
+    prefix.push(node.c);
+    if let Some(v) = &node.value {
+        out.push((prefix.clone(), v));
+    }
+    collect(&node.eq, prefix, out);
+    prefix.pop();
+    collect(&node.hi, prefix, out);
+}
+
+fn matches<'a, V>(link: &'a Link<V>, pattern: &[char], prefix: &mut String, out: &mut Vec<(String, &'a V)>) {
+    let Some(node) = link else {
+        return;
+    };
+    let p = pattern[0];
+    let wild = p == '?';
+    if wild || p < node.c {
+        matches(&node.lo, pattern, prefix, out);
+    }
+    if wild || p == node.c {
+        prefix.push(node.c);
+        if pattern.len() == 1 {
+            if let Some(v) = &node.value {
+                out.push((prefix.clone(), v));
+            }
+        } else {
+            matches(&node.eq, &pattern[1..], prefix, out);
+        }
+        prefix.pop();
+    }
+    if wild || p > node.c {
+        matches(&node.hi, pattern, prefix, out);
+    }
+}
+
+/// A ternary search tree mapping string keys to values.
+///
+/// Each node stores a single character with less/equal/greater children, so
+/// memory scales with the characters actually used rather than with the
+/// alphabet size as in a trie with per-node child tables.
+pub struct TernarySearchTree<V> {
+    root: Link<V>,
+    // The empty key has no node to live on.
+    empty: Option<V>,
+    len: usize,
+}
+
+impl<V> TernarySearchTree<V> {
+    pub const fn new() -> Self {
+        TernarySearchTree { root: None, empty: None, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Insert a key-value pair, returning the previous value if the key was
+    /// already present.
+    pub fn insert(&mut self, key: &str, value: V) -> Option<V> {
+        let chars: Vec<char> = key.chars().collect();
+        let old = if chars.is_empty() {
+            self.empty.replace(value)
+        } else {
+            insert(&mut self.root, &chars, value)
+        };
+        if old.is_none() {
+            self.len += 1;
+        }
+        old
+    }
+
+    fn find(&self, key: &str) -> Option<&Node<V>> {
+        let mut chars = key.chars();
+        let mut c = chars.next()?;
+        let mut node = self.root.as_deref()?;
+        loop {
+            node = match c.cmp(&node.c) {
+                Ordering::Less => node.lo.as_deref()?,
+                Ordering::Greater => node.hi.as_deref()?,
+                Ordering::Equal => {
+                    let Some(next) = chars.next() else {
+                        return Some(node);
+                    };
+                    c = next;
+                    node.eq.as_deref()?
+                }
+            };
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&V> {
+        if key.is_empty() {
+            return self.empty.as_ref();
+        }
+        self.find(key)?.value.as_ref()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// All entries whose keys start with `prefix`, in lexicographic order.
+    pub fn with_prefix(&self, prefix: &str) -> Vec<(String, &V)> {
+        let mut out = Vec::new();
+        if prefix.is_empty() {
+            if let Some(v) = &self.empty {
+                out.push((String::new(), v));
+            }
+            collect(&self.root, &mut String::new(), &mut out);
+            return out;
+        }
+        let Some(node) = self.find(prefix) else {
+            return out;
+        };
+        let mut prefix = prefix.to_string();
+        if let Some(v) = &node.value {
+            out.push((prefix.clone(), v));
+        }
+        collect(&node.eq, &mut prefix, &mut out);
+        out
+    }
+
+    /// All entries whose keys match `pattern`, where `?` matches any single
+    /// character, in lexicographic order.
+    pub fn wildcard(&self, pattern: &str) -> Vec<(String, &V)> {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let mut out = Vec::new();
+        if pattern.is_empty() {
+            out.extend(self.empty.as_ref().map(|v| (String::new(), v)));
+        } else {
+            matches(&self.root, &pattern, &mut String::new(), &mut out);
+        }
+        out
+    }
+
+    /// All entries in lexicographic order.
+    pub fn entries(&self) -> Vec<(String, &V)> {
+        self.with_prefix("")
+    }
+}
+
+impl<V> Default for TernarySearchTree<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: Debug> Debug for TernarySearchTree<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.entries()).finish()
+    }
+}
+
+impl<V: HeapSize> HeapSize for Node<V> {
+    fn heap_size(&self) -> usize {
+        self.value.heap_size() + self.lo.heap_size() + self.eq.heap_size() + self.hi.heap_size()
+    }
+}
+
+impl<V: HeapSize> HeapSize for TernarySearchTree<V> {
+    fn heap_size(&self) -> usize {
+        self.root.heap_size() + self.empty.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heap_size::HeapSize;
+
+    use super::TernarySearchTree;
+
+    fn keys<V>(entries: Vec<(String, &V)>) -> Vec<String> {
+        entries.into_iter().map(|(k, _)| k).collect()
+    }
+
+    #[test]
+    fn test_new() {
+        let tst: TernarySearchTree<i32> = TernarySearchTree::new();
+        assert_eq!(tst.len(), 0);
+        assert!(tst.is_empty());
+        assert!(tst.get("a").is_none());
+        assert!(tst.entries().is_empty());
+    }
+
+    #[test]
+    fn test_insert_get() {
+        let mut tst = TernarySearchTree::new();
+        assert_eq!(tst.insert("she", 1), None);
+        assert_eq!(tst.insert("sells", 2), None);
+        assert_eq!(tst.insert("sea", 3), None);
+        assert_eq!(tst.insert("shells", 4), None);
+        assert_eq!(tst.insert("by", 5), None);
+        assert_eq!(tst.insert("", 6), None);
+        assert_eq!(tst.insert("sea", 30), Some(3));
+        assert_eq!(tst.len(), 6);
+
+        assert_eq!(tst.get("she"), Some(&1));
+        assert_eq!(tst.get("sea"), Some(&30));
+        assert_eq!(tst.get("by"), Some(&5));
+        assert_eq!(tst.get(""), Some(&6));
+        assert_eq!(tst.get("sh"), None);
+        assert_eq!(tst.get("shore"), None);
+        assert!(tst.contains_key("shells"));
+    }
+
+    #[test]
+    fn test_with_prefix() {
+        let mut tst = TernarySearchTree::new();
+        for key in ["she", "sells", "sea", "shells", "by", "the", "shore"] {
+            tst.insert(key, ());
+        }
+        assert_eq!(keys(tst.with_prefix("sh")), vec!["she", "shells", "shore"]);
+        assert_eq!(keys(tst.with_prefix("she")), vec!["she", "shells"]);
+        assert_eq!(keys(tst.with_prefix("x")), Vec::<String>::new());
+        assert_eq!(keys(tst.entries()), vec!["by", "sea", "sells", "she", "shells", "shore", "the"]);
+    }
+
+    #[test]
+    fn test_wildcard() {
+        let mut tst = TernarySearchTree::new();
+        for key in ["cat", "cot", "cut", "cart", "bat", "ca"] {
+            tst.insert(key, ());
+        }
+        assert_eq!(keys(tst.wildcard("c?t")), vec!["cat", "cot", "cut"]);
+        assert_eq!(keys(tst.wildcard("?at")), vec!["bat", "cat"]);
+        assert_eq!(keys(tst.wildcard("??")), vec!["ca"]);
+        assert_eq!(keys(tst.wildcard("ca?t")), vec!["cart"]);
+        assert_eq!(keys(tst.wildcard("c?")), vec!["ca"]);
+        assert!(tst.wildcard("?????").is_empty());
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let mut tst = TernarySearchTree::new();
+        assert_eq!(tst.heap_size(), 0);
+        tst.insert("ab", String::from("xyz"));
+        assert_eq!(tst.heap_size(), 2 * size_of::<super::Node<String>>() + 3);
+    }
+}
@@ -0,0 +1,221 @@
+use std::hash::Hash;
+
+use crate::cache::{Cache, Lists};
+use crate::heap_size::HeapSize;
+use crate::trace;
+
+// The three queues, each oldest first
+const RECENT: usize = 0;
+const FREQUENT: usize = 1;
+const GHOSTS: usize = 2;
+
+/// A cache using the 2Q replacement policy.
+///
+/// New keys enter a small FIFO of recent entries. Keys pushed out of it are
+/// remembered, without their values, in a FIFO of ghosts, and only a key
+/// that comes back while it is still a ghost is admitted to the main LRU
+/// list of frequent entries. A one-off scan over many keys therefore only
+/// ever churns the recent queue, and the frequent entries survive it, where
+/// a plain LRU cache would lose all of them.
+///
+/// Each queue is one of the crate's `LinkedList`s of keys, and a hash map
+/// holds the values and each key's list node for O(1) moves.
+pub struct TwoQCache<K, V> {
+    lists: Lists<K, V, 3>,
+    capacity: usize,
+    recent_capacity: usize,
+    ghost_capacity: usize,
+}
+
+impl<K: Hash + Eq + Clone, V> TwoQCache<K, V> {
+    /// A cache of `capacity` entries, with the usual 2Q tuning: the recent
+    /// queue gets a quarter of it, and ghosts are kept for half as many keys.
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        Self::with_queue_sizes(capacity, (capacity / 4).max(1), (capacity / 2).max(1))
+    }
+
+    /// A cache of `capacity` entries that lets the recent queue grow past
+    /// `recent_capacity` only while the frequent list has nothing to evict,
+    /// and remembers up to `ghost_capacity` evicted keys.
+    ///
+    /// Panics if `capacity` or `ghost_capacity` is zero.
+    pub fn with_queue_sizes(capacity: usize, recent_capacity: usize, ghost_capacity: usize) -> Self {
+        assert!(capacity > 0, "cache capacity must be positive");
+        assert!(ghost_capacity > 0, "2Q needs room for at least one ghost");
+        TwoQCache { lists: Lists::new(), capacity, recent_capacity, ghost_capacity }
+    }
+
+    /// Number of evicted keys currently remembered.
+    pub fn ghost_count(&self) -> usize {
+        self.lists.len(GHOSTS)
+    }
+
+    fn evict(&mut self) -> Option<(K, V)> {
+        if self.lists.len(RECENT) > self.recent_capacity || self.lists.len(FREQUENT) == 0 {
+            let (key, value) = self.lists.pop_front(RECENT)?;
+            trace::event("TwoQCache", "evict", self.len());
+            if self.lists.len(GHOSTS) == self.ghost_capacity {
+                self.lists.pop_front(GHOSTS);
+            }
+            self.lists.push_back(GHOSTS, key.clone(), None);
+            Some((key, value?))
+        } else {
+            let (key, value) = self.lists.pop_front(FREQUENT)?;
+            trace::event("TwoQCache", "evict", self.len());
+            Some((key, value?))
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Cache<K, V> for TwoQCache<K, V> {
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn len(&self) -> usize {
+        self.lists.len(RECENT) + self.lists.len(FREQUENT)
+    }
+
+    fn get(&mut self, key: &K) -> Option<&V> {
+        match self.lists.list_of(key)? {
+            // A hit in the recent queue doesn't count: 2Q only promotes keys
+            // that come back after being evicted
+            RECENT => self.lists.value(key),
+            FREQUENT => self.lists.move_to_back(key, FREQUENT, None),
+            _ => None,
+        }
+    }
+
+    fn peek(&self, key: &K) -> Option<&V> {
+        self.lists.value(key)
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<(K, V)> {
+        let list = self.lists.list_of(&key);
+        match list {
+            Some(RECENT) => {
+                // Stays where it is in the FIFO
+                *self.lists.value_mut(&key).unwrap() = value;
+                return None;
+            }
+            Some(FREQUENT) => {
+                self.lists.move_to_back(&key, FREQUENT, Some(value));
+                return None;
+            }
+            Some(_) => {
+                self.lists.remove(&key);
+            }
+            None => {}
+        }
+        let evicted = if self.len() == self.capacity { self.evict() } else { None };
+        let list = if list == Some(GHOSTS) { FREQUENT } else { RECENT };
+        self.lists.push_back(list, key, Some(value));
+        evicted
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.lists.remove(key)?.1
+    }
+
+    fn clear(&mut self) {
+        self.lists = Lists::new();
+    }
+}
+
+impl<K: HeapSize, V: HeapSize> HeapSize for TwoQCache<K, V> {
+    fn heap_size(&self) -> usize {
+        self.lists.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cache::Cache;
+    use crate::heap_size::HeapSize;
+
+    use super::TwoQCache;
+
+    #[test]
+    fn test_basic_ops() {
+        let mut cache = TwoQCache::new(4);
+        assert!(cache.is_empty());
+        for i in 0..4 {
+            assert_eq!(cache.insert(i, i * 10), None);
+        }
+        assert_eq!(cache.len(), 4);
+        assert_eq!(cache.get(&2), Some(&20));
+        assert_eq!(cache.insert(2, 21), None);
+        assert_eq!(cache.peek(&2), Some(&21));
+
+        // Full: the oldest recent entry goes, and is remembered as a ghost
+        assert_eq!(cache.insert(4, 40), Some((0, 0)));
+        assert!(!cache.contains_key(&0));
+        assert_eq!(cache.ghost_count(), 1);
+
+        assert_eq!(cache.remove(&3), Some(30));
+        assert_eq!(cache.remove(&3), None);
+        assert_eq!(cache.len(), 3);
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!(cache.ghost_count(), 0);
+    }
+
+    #[test]
+    fn test_ghost_hit_promotes() {
+        let mut cache = TwoQCache::with_queue_sizes(3, 1, 2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        cache.insert("c", 3);
+        assert_eq!(cache.insert("d", 4), Some(("a", 1)));
+        // Back while still a ghost, so straight into the frequent list
+        assert_eq!(cache.insert("a", 10), Some(("b", 2)));
+        assert_eq!(cache.ghost_count(), 1);
+        // The recent queue is over its share, so it keeps paying for new keys
+        assert_eq!(cache.insert("e", 5), Some(("c", 3)));
+        assert_eq!(cache.insert("f", 6), Some(("d", 4)));
+        assert_eq!(cache.get(&"a"), Some(&10));
+    }
+
+    #[test]
+    fn test_scan_resistance() {
+        let mut cache = TwoQCache::new(20);
+        let hot = 0..5;
+        // Get the hot keys evicted once, then reused, so they are promoted
+        for key in hot.clone() {
+            cache.insert(key, key);
+        }
+        for key in 100..120 {
+            cache.insert(key, key);
+        }
+        for key in hot.clone() {
+            cache.insert(key, key);
+        }
+        // A long scan of keys used once
+        for key in 1000..5000 {
+            cache.insert(key, key);
+            if key % 10 == 0 {
+                for hot_key in hot.clone() {
+                    assert_eq!(cache.get(&hot_key), Some(&hot_key));
+                }
+            }
+        }
+        assert_eq!(cache.len(), 20);
+        assert!(hot.clone().all(|key| cache.contains_key(&key)));
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let mut cache = TwoQCache::new(4);
+        cache.insert(0, String::new());
+        let base = cache.heap_size();
+        cache.insert(1, String::with_capacity(1000));
+        assert!(cache.heap_size() >= base + 1000);
+        // Evicted values stop counting, though their keys linger as ghosts
+        for i in 2..10 {
+            cache.insert(i, String::new());
+        }
+        assert!(cache.heap_size() < base + 1000);
+    }
+}
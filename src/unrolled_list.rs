@@ -0,0 +1,901 @@
+use std::collections::VecDeque;
+use std::fmt::Debug;
+
+use crate::arena::{Arena, ArenaId};
+use crate::doubly_list::LinkedList;
+use crate::fallible::AllocError;
+use crate::heap_size::HeapSize;
+use crate::pretty::{PrettyPrint, arrows};
+use crate::sequence::{LendingCursor, SeqCursor, Sequence};
+use crate::simd::{self, Lane};
+
+struct Node<T> {
+    // Never more than the list's node capacity, and never empty while linked
+    elems: VecDeque<T>,
+    prev: Option<ArenaId>,
+    next: Option<ArenaId>,
+}
+
+// Position of one element: its node and index within the node
+type Pos = (ArenaId, usize);
+
+/// A doubly linked list that stores up to `B` elements per node.
+///
+/// Each node's elements sit next to each other in one buffer, so walking the
+/// list touches a new node only every `B` elements instead of every element.
+/// Nodes that fill up are split in half on insert, and a node that drops
+/// below half full after a removal absorbs its successor if they fit, so
+/// nodes stay at least roughly half full under cursor edits.
+pub struct UnrolledList<T, const B: usize> {
+    nodes: Arena<Node<T>>,
+    head: Option<ArenaId>,
+    tail: Option<ArenaId>,
+    len: usize,
+    // Most elements a node holds: `B`, unless `with_node_capacity` set it
+    cap: usize,
+}
+
+impl<T, const B: usize> UnrolledList<T, B> {
+    pub const fn new() -> Self {
+        const { assert!(B >= 2, "unrolled list nodes must hold at least 2 elements") };
+        UnrolledList { nodes: Arena::with_reuse(), head: None, tail: None, len: 0, cap: B }
+    }
+
+    /// A list whose nodes hold up to `cap` elements instead of `B`, for
+    /// `LinkedList`'s chunked storage, where the chunk size is only known at
+    /// runtime.
+    pub(crate) fn with_node_capacity(cap: usize) -> Self {
+        assert!(cap >= 2, "unrolled list nodes must hold at least 2 elements");
+        UnrolledList { nodes: Arena::with_reuse(), head: None, tail: None, len: 0, cap }
+    }
+
+    /// Most elements a node holds.
+    pub(crate) fn node_capacity(&self) -> usize {
+        self.cap
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of nodes the elements are spread over.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Panic unless the nodes are doubly linked from head to tail, every
+    /// one of them is linked and holds between 1 and `B` elements, and
+    /// `len` is their total. Only in debug builds and tests.
+    #[cfg(any(test, debug_assertions))]
+    pub fn validate(&self) {
+        let (mut nodes, mut elems) = (0, 0);
+        let mut prev = None;
+        let mut curr = self.head;
+        while let Some(id) = curr {
+            let node = &self.nodes[id];
+            assert_eq!(node.prev, prev, "node {nodes} has a stale prev link");
+            assert!((1..=self.cap).contains(&node.elems.len()), "node {nodes} holds {} elements", node.elems.len());
+            nodes += 1;
+            elems += node.elems.len();
+            prev = curr;
+            curr = node.next;
+        }
+        assert_eq!(self.tail, prev, "tail isn't the last node");
+        assert_eq!(nodes, self.nodes.len(), "unlinked nodes left in the arena");
+        assert_eq!(elems, self.len, "list length is out of date");
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.reset();
+        self.head = None;
+        self.tail = None;
+        self.len = 0;
+    }
+
+    /// Repack the elements into full nodes in a fresh arena, so a list that
+    /// shrank after a burst of inserts gives back the nodes and arena chunks
+    /// it no longer needs. O(n).
+    pub fn compact(&mut self) {
+        let mut old = std::mem::replace(self, Self::with_node_capacity(self.cap));
+        while let Some(val) = old.pop_front() {
+            self.push_back(val);
+        }
+    }
+
+    /// `List::par_sort` for unrolled lists. The elements are moved out into
+    /// one buffer, sorted there with rayon's parallel merge sort, and packed
+    /// back into full nodes. Stable.
+    #[cfg(feature = "rayon")]
+    pub fn par_sort(&mut self)
+    where
+        T: Ord + Send,
+    {
+        self.par_sort_by(T::cmp);
+    }
+
+    /// `par_sort`, ordering by `cmp`
+    #[cfg(feature = "rayon")]
+    pub fn par_sort_by<F: Fn(&T, &T) -> std::cmp::Ordering + Sync>(&mut self, cmp: F)
+    where
+        T: Send,
+    {
+        use rayon::slice::ParallelSliceMut;
+
+        let mut elems = Vec::with_capacity(self.len);
+        while let Some(val) = self.pop_front() {
+            elems.push(val);
+        }
+        elems.par_sort_by(cmp);
+        for val in elems {
+            self.push_back(val);
+        }
+    }
+
+    /// Allocate an empty node and link it in after `after`, or at the front
+    /// if `after` is `None`.
+    fn link_after(&mut self, after: Option<ArenaId>) -> ArenaId {
+        let node = self.node_after(after, VecDeque::with_capacity(self.cap));
+        let id = self.nodes.alloc(node);
+        self.link(id);
+        id
+    }
+
+    /// `link_after`, but returning an error if the node can't be allocated.
+    fn try_link_after(&mut self, after: Option<ArenaId>) -> Result<ArenaId, AllocError> {
+        let mut elems = VecDeque::new();
+        elems.try_reserve_exact(self.cap)?;
+        let id = self.nodes.try_alloc(self.node_after(after, elems))?;
+        self.link(id);
+        Ok(id)
+    }
+
+    // An unlinked node that belongs after `after`
+    fn node_after(&self, after: Option<ArenaId>, elems: VecDeque<T>) -> Node<T> {
+        let next = match after {
+            Some(id) => self.nodes[id].next,
+            None => self.head,
+        };
+        Node { elems, prev: after, next }
+    }
+
+    // Point the neighbours a new node names at it
+    fn link(&mut self, id: ArenaId) {
+        let Node { prev, next, .. } = self.nodes[id];
+        match prev {
+            Some(prev) => self.nodes[prev].next = Some(id),
+            None => self.head = Some(id),
+        }
+        match next {
+            Some(next) => self.nodes[next].prev = Some(id),
+            None => self.tail = Some(id),
+        }
+    }
+
+    fn unlink(&mut self, id: ArenaId) {
+        let node = self.nodes.free(id).unwrap();
+        match node.prev {
+            Some(prev) => self.nodes[prev].next = node.next,
+            None => self.head = node.next,
+        }
+        match node.next {
+            Some(next) => self.nodes[next].prev = node.prev,
+            None => self.tail = node.prev,
+        }
+    }
+
+    pub fn push_front(&mut self, val: T) {
+        let id = match self.head {
+            Some(id) if self.nodes[id].elems.len() < self.cap => id,
+            _ => self.link_after(None),
+        };
+        self.nodes[id].elems.push_front(val);
+        self.len += 1;
+    }
+
+    pub fn push_back(&mut self, val: T) {
+        let id = match self.tail {
+            Some(id) if self.nodes[id].elems.len() < self.cap => id,
+            tail => self.link_after(tail),
+        };
+        self.nodes[id].elems.push_back(val);
+        self.len += 1;
+    }
+
+    /// `push_front`, but leaving the list unchanged and returning an error if
+    /// a new node is needed and can't be allocated.
+    pub fn try_push_front(&mut self, val: T) -> Result<(), AllocError> {
+        let id = match self.head {
+            Some(id) if self.nodes[id].elems.len() < self.cap => id,
+            _ => self.try_link_after(None)?,
+        };
+        // Nodes have room for all their elements, so this doesn't allocate
+        self.nodes[id].elems.push_front(val);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// `push_back`, but leaving the list unchanged and returning an error if
+    /// a new node is needed and can't be allocated.
+    pub fn try_push_back(&mut self, val: T) -> Result<(), AllocError> {
+        let id = match self.tail {
+            Some(id) if self.nodes[id].elems.len() < self.cap => id,
+            tail => self.try_link_after(tail)?,
+        };
+        self.nodes[id].elems.push_back(val);
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        let id = self.head?;
+        let val = self.nodes[id].elems.pop_front();
+        if self.nodes[id].elems.is_empty() {
+            self.unlink(id);
+        }
+        self.len -= 1;
+        val
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        let id = self.tail?;
+        let val = self.nodes[id].elems.pop_back();
+        if self.nodes[id].elems.is_empty() {
+            self.unlink(id);
+        }
+        self.len -= 1;
+        val
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.nodes[self.head?].elems.front()
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.nodes[self.tail?].elems.back()
+    }
+
+    fn first_pos(&self) -> Option<Pos> {
+        self.head.map(|id| (id, 0))
+    }
+
+    fn last_pos(&self) -> Option<Pos> {
+        self.tail.map(|id| (id, self.nodes[id].elems.len() - 1))
+    }
+
+    fn pos_after(&self, (id, index): Pos) -> Option<Pos> {
+        let node = &self.nodes[id];
+        if index + 1 < node.elems.len() {
+            Some((id, index + 1))
+        } else {
+            node.next.map(|next| (next, 0))
+        }
+    }
+
+    fn pos_before(&self, (id, index): Pos) -> Option<Pos> {
+        if index > 0 {
+            Some((id, index - 1))
+        } else {
+            self.nodes[id].prev.map(|prev| (prev, self.nodes[prev].elems.len() - 1))
+        }
+    }
+
+    /// Insert `val` so it lands at `index` within node `id`, splitting the
+    /// node first if it is full. Returns where the element ended up.
+    fn insert_at(&mut self, (id, index): Pos, val: T) -> Pos {
+        let mut pos = (id, index);
+        if self.nodes[id].elems.len() == self.cap {
+            let mid = self.cap / 2;
+            let upper = self.nodes[id].elems.split_off(mid);
+            let new = self.link_after(Some(id));
+            self.nodes[new].elems.extend(upper);
+            if index > mid {
+                pos = (new, index - mid);
+            }
+        }
+        self.nodes[pos.0].elems.insert(pos.1, val);
+        self.len += 1;
+        pos
+    }
+
+    /// Remove the element at `pos`, merging its node with the next one if it
+    /// has become less than half full. Returns the element and the position
+    /// of the element that followed it, if any.
+    fn remove_at(&mut self, (id, index): Pos) -> (T, Option<Pos>) {
+        let val = self.nodes[id].elems.remove(index).unwrap();
+        self.len -= 1;
+        let node = &self.nodes[id];
+        if node.elems.is_empty() {
+            let next = node.next;
+            self.unlink(id);
+            return (val, next.map(|next| (next, 0)));
+        }
+        if node.elems.len() < self.cap / 2
+            && let Some(next) = node.next
+            && node.elems.len() + self.nodes[next].elems.len() <= self.cap
+        {
+            let moved = std::mem::take(&mut self.nodes[next].elems);
+            self.nodes[id].elems.extend(moved);
+            self.unlink(next);
+        }
+        if index < self.nodes[id].elems.len() {
+            (val, Some((id, index)))
+        } else {
+            (val, self.nodes[id].next.map(|next| (next, 0)))
+        }
+    }
+
+    pub fn cursor_front(&mut self) -> Cursor<'_, T, B> {
+        let current = self.first_pos();
+        Cursor { list: self, current }
+    }
+
+    pub fn cursor_back(&mut self) -> Cursor<'_, T, B> {
+        let current = self.last_pos();
+        Cursor { list: self, current }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T, B> {
+        Iter { list: self, front: self.first_pos(), back: self.last_pos(), remaining: self.len }
+    }
+}
+
+impl<T: Lane, const B: usize> UnrolledList<T, B> {
+    /// Whether any element equals `val`.
+    pub fn contains(&self, val: T) -> bool {
+        self.position(val).is_some()
+    }
+
+    /// Index of the first element equal to `val`. Each node's elements are
+    /// scanned in blocks of vector compares rather than one at a time.
+    pub fn position(&self, val: T) -> Option<usize> {
+        let mut start = 0;
+        let mut id = self.head;
+        while let Some(curr) = id {
+            let node = &self.nodes[curr];
+            let (front, back) = node.elems.as_slices();
+            for part in [front, back] {
+                if let Some(i) = simd::position(part, val) {
+                    return Some(start + i);
+                }
+                start += part.len();
+            }
+            id = node.next;
+        }
+        None
+    }
+}
+
+impl<T, const B: usize> Default for UnrolledList<T, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A cursor over an `UnrolledList`.
+///
+/// Besides pointing at an element, the cursor can sit on a "ghost" position
+/// between the back and the front: it starts there on an empty list and
+/// ends up there after stepping off either end. From the ghost, `next` moves
+/// to the front and `prev` to the back.
+pub struct Cursor<'a, T, const B: usize> {
+    list: &'a mut UnrolledList<T, B>,
+    current: Option<Pos>,
+}
+
+impl<T, const B: usize> Cursor<'_, T, B> {
+    /// Take a mutable reference to the current element
+    pub fn peek_mut(&mut self) -> Option<&mut T> {
+        let (id, index) = self.current?;
+        self.list.nodes[id].elems.get_mut(index)
+    }
+
+    /// Move one position forward (towards the back) and
+    /// return a reference to the new position
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<&mut T> {
+        self.current = match self.current {
+            Some(pos) => self.list.pos_after(pos),
+            None => self.list.first_pos(),
+        };
+        self.peek_mut()
+    }
+
+    /// Move one position backward (towards the front) and
+    /// return a reference to the new position
+    pub fn prev(&mut self) -> Option<&mut T> {
+        self.current = match self.current {
+            Some(pos) => self.list.pos_before(pos),
+            None => self.list.last_pos(),
+        };
+        self.peek_mut()
+    }
+
+    /// Remove and return the current element, moving the cursor to the next
+    /// element, or to the previous one if it was the back.
+    pub fn take(&mut self) -> Option<T> {
+        let pos = self.current?;
+        let prev = self.list.pos_before(pos);
+        let (val, next) = self.list.remove_at(pos);
+        // `prev` is unaffected by the removal: merging only pulls elements
+        // from after the removed one
+        self.current = next.or(prev);
+        Some(val)
+    }
+
+    /// Insert an element after the current one, or at the front if the
+    /// cursor is on the ghost position. The cursor does not move.
+    pub fn insert_after(&mut self, element: T) {
+        match self.current {
+            Some((id, index)) => {
+                let pos = self.list.insert_at((id, index + 1), element);
+                self.current = self.list.pos_before(pos);
+            }
+            None => self.list.push_front(element),
+        }
+    }
+
+    /// Insert an element before the current one, or at the back if the
+    /// cursor is on the ghost position. The cursor does not move.
+    pub fn insert_before(&mut self, element: T) {
+        match self.current {
+            Some(pos) => {
+                let pos = self.list.insert_at(pos, element);
+                self.current = self.list.pos_after(pos);
+            }
+            None => self.list.push_back(element),
+        }
+    }
+}
+
+impl<T, const B: usize> Sequence<T> for UnrolledList<T, B> {
+    type Iter<'a>
+        = Iter<'a, T, B>
+    where
+        T: 'a;
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn push_front(&mut self, val: T) {
+        self.push_front(val);
+    }
+
+    fn push_back(&mut self, val: T) {
+        self.push_back(val);
+    }
+
+    fn pop_front(&mut self) -> Option<T> {
+        self.pop_front()
+    }
+
+    fn pop_back(&mut self) -> Option<T> {
+        self.pop_back()
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
+    }
+
+    #[cfg(any(test, debug_assertions))]
+    fn validate(&self) {
+        self.validate();
+    }
+}
+
+impl<T, const B: usize> SeqCursor<T> for Cursor<'_, T, B> {
+    fn peek_mut(&mut self) -> Option<&mut T> {
+        self.peek_mut()
+    }
+
+    fn next(&mut self) -> Option<&mut T> {
+        self.next()
+    }
+
+    fn prev(&mut self) -> Option<&mut T> {
+        self.prev()
+    }
+
+    fn take(&mut self) -> Option<T> {
+        self.take()
+    }
+
+    fn insert_after(&mut self, element: T) {
+        self.insert_after(element);
+    }
+
+    fn insert_before(&mut self, element: T) {
+        self.insert_before(element);
+    }
+}
+
+impl<T, const B: usize> LendingCursor for Cursor<'_, T, B> {
+    type Elem = T;
+
+    type Item<'a>
+        = &'a mut T
+    where
+        Self: 'a;
+
+    fn peek(&mut self) -> Option<Self::Item<'_>> {
+        self.peek_mut()
+    }
+
+    fn move_next(&mut self) -> bool {
+        self.next();
+        true
+    }
+
+    fn move_prev(&mut self) -> bool {
+        self.prev();
+        true
+    }
+}
+
+pub struct Iter<'a, T, const B: usize> {
+    list: &'a UnrolledList<T, B>,
+    front: Option<Pos>,
+    back: Option<Pos>,
+    // Stops front and back from crossing
+    remaining: usize,
+}
+
+impl<'a, T, const B: usize> Iterator for Iter<'a, T, B> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let (id, index) = self.front?;
+        self.front = self.list.pos_after((id, index));
+        self.remaining -= 1;
+        Some(&self.list.nodes[id].elems[index])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T, const B: usize> ExactSizeIterator for Iter<'_, T, B> {}
+
+impl<T, const B: usize> DoubleEndedIterator for Iter<'_, T, B> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let (id, index) = self.back?;
+        self.back = self.list.pos_before((id, index));
+        self.remaining -= 1;
+        Some(&self.list.nodes[id].elems[index])
+    }
+}
+
+impl<'a, T, const B: usize> IntoIterator for &'a UnrolledList<T, B> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, B>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T, const B: usize> FromIterator<T> for UnrolledList<T, B> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = UnrolledList::new();
+        for val in iter {
+            list.push_back(val);
+        }
+        list
+    }
+}
+
+impl<T, const B: usize> From<LinkedList<T>> for UnrolledList<T, B> {
+    fn from(mut list: LinkedList<T>) -> Self {
+        let mut unrolled = UnrolledList::new();
+        while let Some(val) = list.pop_front() {
+            unrolled.push_back(val);
+        }
+        unrolled
+    }
+}
+
+impl<T, const B: usize> From<UnrolledList<T, B>> for LinkedList<T> {
+    fn from(mut unrolled: UnrolledList<T, B>) -> Self {
+        let mut list = LinkedList::new();
+        while let Some(val) = unrolled.pop_front() {
+            list.push_back(val);
+        }
+        list
+    }
+}
+
+impl<T: Debug, const B: usize> Debug for UnrolledList<T, B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Node<T> {
+    fn heap_size(&self) -> usize {
+        self.elems.heap_size()
+    }
+}
+
+impl<T: HeapSize, const B: usize> HeapSize for UnrolledList<T, B> {
+    fn heap_size(&self) -> usize {
+        self.nodes.heap_size()
+    }
+}
+
+/// Shows each node's elements in brackets, so how full the nodes are can be
+/// seen.
+impl<T: Debug, const B: usize> PrettyPrint for UnrolledList<T, B> {
+    fn pretty_print(&self) -> String {
+        let mut nodes = Vec::new();
+        let mut curr = self.head;
+        while let Some(id) = curr {
+            nodes.push(&self.nodes[id].elems);
+            curr = self.nodes[id].next;
+        }
+        arrows(nodes, " <-> ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::doubly_list::LinkedList;
+    use crate::pretty::PrettyPrint;
+
+    use super::UnrolledList;
+
+    fn contents<const B: usize>(list: &UnrolledList<i32, B>) -> Vec<i32> {
+        list.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_push_pop() {
+        let mut list: UnrolledList<i32, 4> = UnrolledList::new();
+        for i in 0..10 {
+            list.push_back(i);
+            list.push_front(-i - 1);
+        }
+        assert_eq!(list.len(), 20);
+        assert_eq!(list.node_count(), 5);
+        assert_eq!(list.front(), Some(&-10));
+        assert_eq!(list.back(), Some(&9));
+
+        for i in (0..10).rev() {
+            assert_eq!(list.pop_back(), Some(i));
+            assert_eq!(list.pop_front(), Some(-i - 1));
+        }
+        assert_eq!(list.pop_back(), None);
+        assert_eq!(list.pop_front(), None);
+        assert!(list.is_empty());
+        assert_eq!(list.node_count(), 0);
+    }
+
+    #[test]
+    fn test_try_push() {
+        let mut list: UnrolledList<i32, 4> = UnrolledList::new();
+        for i in 0..10 {
+            assert_eq!(list.try_push_back(i), Ok(()));
+            assert_eq!(list.try_push_front(-i - 1), Ok(()));
+        }
+        assert_eq!(list.node_count(), 5);
+        assert_eq!(contents(&list), (-10..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_iter() {
+        let list: UnrolledList<i32, 3> = (1..=8).collect();
+        assert_eq!(contents(&list), (1..=8).collect::<Vec<_>>());
+        assert_eq!(list.iter().rev().copied().collect::<Vec<_>>(), (1..=8).rev().collect::<Vec<_>>());
+
+        let mut iter = list.iter();
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.next_back(), Some(&8));
+        assert_eq!(iter.len(), 6);
+        assert_eq!(iter.by_ref().count(), 6);
+        assert_eq!(iter.next_back(), None);
+        assert_eq!(format!("{:?}", list), "[1, 2, 3, 4, 5, 6, 7, 8]");
+    }
+
+    #[test]
+    fn test_cursor_walk() {
+        let mut list: UnrolledList<i32, 2> = (1..=5).collect();
+        let mut cursor = list.cursor_front();
+        assert_eq!(cursor.peek_mut(), Some(&mut 1));
+        assert_eq!(cursor.next(), Some(&mut 2));
+        assert_eq!(cursor.next(), Some(&mut 3));
+        *cursor.peek_mut().unwrap() = 30;
+        assert_eq!(cursor.prev(), Some(&mut 2));
+        assert_eq!(cursor.prev(), Some(&mut 1));
+        assert_eq!(cursor.prev(), None);
+        // Stepping from the ghost wraps to the other end
+        assert_eq!(cursor.prev(), Some(&mut 5));
+        assert_eq!(cursor.next(), None);
+        assert_eq!(cursor.next(), Some(&mut 1));
+        assert_eq!(contents(&list), vec![1, 2, 30, 4, 5]);
+
+        let mut cursor = list.cursor_back();
+        assert_eq!(cursor.peek_mut(), Some(&mut 5));
+    }
+
+    #[test]
+    fn test_cursor_insert() {
+        let mut list: UnrolledList<i32, 4> = UnrolledList::new();
+        let mut cursor = list.cursor_front();
+        cursor.insert_after(2);
+        assert_eq!(cursor.next(), Some(&mut 2));
+        cursor.insert_before(1);
+        cursor.insert_after(3);
+        assert_eq!(cursor.peek_mut(), Some(&mut 2));
+        // Fill past one node so inserts have to split it
+        for i in (4..10).rev() {
+            cursor.insert_after(i);
+        }
+        assert_eq!(cursor.peek_mut(), Some(&mut 2));
+        for i in 10..13 {
+            cursor.insert_before(i);
+        }
+        assert_eq!(cursor.peek_mut(), Some(&mut 2));
+        assert_eq!(cursor.next(), Some(&mut 4));
+        assert_eq!(contents(&list), vec![1, 10, 11, 12, 2, 4, 5, 6, 7, 8, 9, 3]);
+        assert_eq!(list.len(), 12);
+    }
+
+    #[test]
+    fn test_cursor_take() {
+        let mut list: UnrolledList<i32, 4> = (0..12).collect();
+        let mut cursor = list.cursor_front();
+        cursor.next();
+        // Take every other element; underfull nodes merge as they shrink
+        for expected in [1, 3, 5, 7, 9] {
+            assert_eq!(cursor.take(), Some(expected));
+            cursor.next();
+        }
+        // Taking the back moves to the previous element
+        assert_eq!(cursor.take(), Some(11));
+        assert_eq!(cursor.peek_mut(), Some(&mut 10));
+        assert_eq!(cursor.take(), Some(10));
+        assert_eq!(cursor.peek_mut(), Some(&mut 8));
+        assert_eq!(contents(&list), vec![0, 2, 4, 6, 8]);
+        assert!(list.node_count() <= 3);
+
+        let mut cursor = list.cursor_front();
+        while cursor.take().is_some() {}
+        assert!(list.is_empty());
+        assert_eq!(list.node_count(), 0);
+    }
+
+    #[test]
+    fn test_from_linked_list() {
+        let mut list = LinkedList::new();
+        for i in 0..100 {
+            list.push_back(i);
+        }
+        let mut unrolled = UnrolledList::<i32, 16>::from(list);
+        assert_eq!(unrolled.node_count(), 7);
+        // Same cursor moves as on the linked list
+        let mut cursor = unrolled.cursor_front();
+        cursor.next();
+        assert_eq!(cursor.take(), Some(1));
+        cursor.insert_before(-1);
+        assert_eq!(cursor.prev(), Some(&mut -1));
+        let list = LinkedList::from(unrolled);
+        assert_eq!(list.len(), 100);
+        assert!(list.iter().copied().take(4).eq([0, -1, 2, 3]));
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn test_par_sort() {
+        let mut list: UnrolledList<(i32, usize), 8> = (0..10_000).map(|i| ((i * 7919) % 100, i as usize)).collect();
+        list.par_sort_by(|a, b| a.0.cmp(&b.0));
+        list.validate();
+        assert_eq!(list.node_count(), 10_000 / 8);
+        // Equal keys keep their order
+        assert!(list.iter().zip(list.iter().skip(1)).all(|(a, b)| a.0 < b.0 || (a.0 == b.0 && a.1 < b.1)));
+
+        let mut list: UnrolledList<i32, 4> = [3, 1, 2].into_iter().collect();
+        list.par_sort();
+        assert!(list.iter().copied().eq([1, 2, 3]));
+    }
+
+    #[test]
+    fn test_compact() {
+        let mut list: UnrolledList<i32, 8> = UnrolledList::new();
+        for i in 0..400 {
+            list.push_back(i);
+            list.push_front(-i - 1);
+        }
+        let mut cursor = list.cursor_front();
+        let mut keep = true;
+        while cursor.peek_mut().is_some() {
+            if keep {
+                cursor.next();
+            } else {
+                cursor.take();
+            }
+            keep = !keep;
+        }
+        let before = list.node_count();
+        list.compact();
+        list.validate();
+        assert_eq!(list.node_count(), 50);
+        assert!(list.node_count() < before);
+        assert!(list.iter().copied().eq((-400..400).step_by(2)));
+    }
+
+    #[test]
+    fn test_search() {
+        // Pushing at both ends of one node wraps its buffer around, so both
+        // halves get searched
+        let mut list: UnrolledList<u16, 64> = UnrolledList::new();
+        for i in 0..500 {
+            list.push_back(i);
+            list.push_front(i);
+        }
+        let expected: Vec<u16> = list.iter().copied().collect();
+        for val in [0, 1, 63, 64, 99, 100, 499, 500] {
+            assert_eq!(list.position(val), expected.iter().position(|&x| x == val));
+        }
+        assert!(list.contains(499));
+        assert!(!list.contains(500));
+        assert!(!UnrolledList::<u8, 4>::new().contains(0));
+    }
+
+    #[test]
+    fn test_against_vec() {
+        let mut rng = crate::rng::XorShift64::new();
+        let mut list: UnrolledList<u64, 8> = UnrolledList::new();
+        let mut model = Vec::new();
+        let mut cursor_at = 0usize;
+        for step in 0..2000 {
+            let mut cursor = list.cursor_front();
+            for _ in 0..cursor_at {
+                cursor.next();
+            }
+            match rng.next_u64() % 4 {
+                0 if !model.is_empty() => {
+                    assert_eq!(cursor.take(), Some(model.remove(cursor_at)));
+                    if cursor_at == model.len() && cursor_at > 0 {
+                        cursor_at -= 1;
+                    }
+                }
+                1 if !model.is_empty() => {
+                    cursor.insert_before(step);
+                    model.insert(cursor_at, step);
+                    cursor_at += 1;
+                }
+                _ if !model.is_empty() => {
+                    cursor.insert_after(step);
+                    model.insert(cursor_at + 1, step);
+                }
+                _ => {
+                    cursor.insert_after(step);
+                    model.push(step);
+                }
+            }
+            list.validate();
+            if !model.is_empty() {
+                cursor_at = (rng.next_u64() as usize) % model.len();
+            }
+        }
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), model);
+        assert!(list.node_count() * 4 <= list.len() + 8);
+    }
+
+    #[test]
+    fn test_pretty_print() {
+        let mut list: UnrolledList<i32, 4> = (1..=6).collect();
+        list.push_front(0);
+        assert_eq!(list.pretty_print(), "[0] <-> [1, 2, 3, 4] <-> [5, 6]");
+    }
+}
@@ -0,0 +1,164 @@
+use wasm_bindgen::prelude::*;
+
+use crate::arc_cache::ArcCache;
+use crate::cache::Cache;
+use crate::doubly_list::LinkedList;
+use crate::list::List;
+use crate::two_q_cache::TwoQCache;
+
+// JavaScript wrappers for the `wasm` feature. Elements are plain `JsValue`s,
+// so numbers, strings and objects can all be stored, and methods follow
+// JavaScript naming. A missing element comes back as `undefined`.
+
+/// A `List` of JavaScript values, exported as `List`.
+#[wasm_bindgen(js_name = List)]
+#[derive(Default)]
+pub struct JsList {
+    list: List<JsValue>,
+}
+
+#[wasm_bindgen(js_class = List)]
+impl JsList {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        JsList { list: List::new() }
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> usize {
+        self.list.len()
+    }
+
+    #[wasm_bindgen(js_name = pushFront)]
+    pub fn push_front(&mut self, val: JsValue) {
+        self.list.push_front(val);
+    }
+
+    #[wasm_bindgen(js_name = popFront)]
+    pub fn pop_front(&mut self) -> Option<JsValue> {
+        self.list.pop_front()
+    }
+
+    pub fn front(&self) -> Option<JsValue> {
+        self.list.peek_front().cloned()
+    }
+
+    #[wasm_bindgen(js_name = toArray)]
+    pub fn to_array(&self) -> Vec<JsValue> {
+        self.list.iter().cloned().collect()
+    }
+}
+
+/// A `LinkedList` of JavaScript values, exported as `Deque`.
+#[wasm_bindgen(js_name = Deque)]
+#[derive(Default)]
+pub struct JsDeque {
+    list: LinkedList<JsValue>,
+}
+
+#[wasm_bindgen(js_class = Deque)]
+impl JsDeque {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        JsDeque { list: LinkedList::new() }
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> usize {
+        self.list.len()
+    }
+
+    #[wasm_bindgen(js_name = pushFront)]
+    pub fn push_front(&mut self, val: JsValue) {
+        self.list.push_front(val);
+    }
+
+    #[wasm_bindgen(js_name = pushBack)]
+    pub fn push_back(&mut self, val: JsValue) {
+        self.list.push_back(val);
+    }
+
+    #[wasm_bindgen(js_name = popFront)]
+    pub fn pop_front(&mut self) -> Option<JsValue> {
+        self.list.pop_front()
+    }
+
+    #[wasm_bindgen(js_name = popBack)]
+    pub fn pop_back(&mut self) -> Option<JsValue> {
+        self.list.pop_back()
+    }
+
+    pub fn front(&self) -> Option<JsValue> {
+        self.list.iter().next().cloned()
+    }
+
+    #[wasm_bindgen(js_name = toArray)]
+    pub fn to_array(&self) -> Vec<JsValue> {
+        self.list.iter().cloned().collect()
+    }
+}
+
+/// A cache from strings to JavaScript values, exported as `Cache`.
+///
+/// The crate has no plain LRU cache, so the policy is one of its scan
+/// resistant ones, picked when the cache is made: `"2q"` for `TwoQCache` and
+/// `"arc"` for `ArcCache`.
+#[wasm_bindgen(js_name = Cache)]
+pub struct JsCache {
+    cache: Box<dyn Cache<String, JsValue>>,
+}
+
+#[wasm_bindgen(js_class = Cache)]
+impl JsCache {
+    /// Throws if `capacity` is zero or `policy` is not `"2q"` or `"arc"`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(capacity: usize, policy: &str) -> Result<JsCache, JsError> {
+        if capacity == 0 {
+            return Err(JsError::new("cache capacity must be positive"));
+        }
+        let cache: Box<dyn Cache<String, JsValue>> = match policy {
+            "2q" => Box::new(TwoQCache::new(capacity)),
+            "arc" => Box::new(ArcCache::new(capacity)),
+            _ => return Err(JsError::new(&format!("unknown cache policy {policy:?}"))),
+        };
+        Ok(JsCache { cache })
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn capacity(&self) -> usize {
+        self.cache.capacity()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// The value for `key`, counting as a use.
+    pub fn get(&mut self, key: String) -> Option<JsValue> {
+        self.cache.get(&key).cloned()
+    }
+
+    /// The value for `key`, without counting as a use.
+    pub fn peek(&self, key: String) -> Option<JsValue> {
+        self.cache.peek(&key).cloned()
+    }
+
+    pub fn has(&self, key: String) -> bool {
+        self.cache.contains_key(&key)
+    }
+
+    /// Insert or replace the value for `key`, returning the key evicted to
+    /// make room, if any.
+    pub fn set(&mut self, key: String, value: JsValue) -> Option<String> {
+        self.cache.insert(key, value).map(|(key, _)| key)
+    }
+
+    pub fn delete(&mut self, key: String) -> Option<JsValue> {
+        self.cache.remove(&key)
+    }
+
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+}
@@ -0,0 +1,271 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::fmt::Debug;
+
+use crate::heap_size::HeapSize;
+
+/// The median of the last `size` items pushed onto a stream, in O(log size)
+/// amortized per push.
+///
+/// The window is split across two heaps: a max-heap of the lower half and a
+/// min-heap of the upper half, kept within one item of each other in size,
+/// so the median sits on top of the lower half. Items leaving the window are
+/// deleted lazily: each heap entry carries its push number, and entries
+/// older than the window are dropped when they reach the top, or all at once
+/// when they come to outnumber the live ones.
+pub struct WindowMedian<T> {
+    window: VecDeque<T>,
+    // Every live entry in `lo` is <= every live entry in `hi`, ties broken
+    // by push number, and the top of each is live
+    lo: BinaryHeap<(T, usize)>,
+    hi: BinaryHeap<Reverse<(T, usize)>>,
+    // Live entries in each heap; `lo_len` is `hi_len` or one more
+    lo_len: usize,
+    hi_len: usize,
+    size: usize,
+    pushed: usize,
+}
+
+impl<T: Ord + Clone> WindowMedian<T> {
+    /// A median over the last `size` items.
+    ///
+    /// Panics if `size` is zero.
+    pub fn new(size: usize) -> Self {
+        assert!(size > 0, "window size must be positive");
+        WindowMedian {
+            window: VecDeque::with_capacity(size),
+            lo: BinaryHeap::new(),
+            hi: BinaryHeap::new(),
+            lo_len: 0,
+            hi_len: 0,
+            size,
+            pushed: 0,
+        }
+    }
+
+    /// The most items the window holds.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn len(&self) -> usize {
+        self.window.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.window.is_empty()
+    }
+
+    /// Panic unless the live heap entries are exactly the items in the
+    /// window, every live entry in the lower half is at most every one in the
+    /// upper half, the halves are balanced, and the tops are live. Only in
+    /// debug builds and tests.
+    #[cfg(any(test, debug_assertions))]
+    pub fn validate(&self) {
+        let oldest = self.oldest();
+        let lo: Vec<_> = self.lo.iter().filter(|(_, seq)| *seq >= oldest).collect();
+        let hi: Vec<_> = self.hi.iter().map(|Reverse(entry)| entry).filter(|(_, seq)| *seq >= oldest).collect();
+        assert_eq!((lo.len(), hi.len()), (self.lo_len, self.hi_len), "live counts are out of date");
+        assert!(self.lo_len == self.hi_len || self.lo_len == self.hi_len + 1, "halves out of balance");
+        assert_eq!(self.lo_len + self.hi_len, self.window.len(), "heaps and window disagree");
+        let mut seqs: Vec<_> = lo.iter().chain(&hi).map(|(_, seq)| *seq).collect();
+        seqs.sort_unstable();
+        assert!(seqs.iter().copied().eq(oldest..self.pushed), "live entries aren't the window");
+        for (val, seq) in lo.iter().chain(&hi) {
+            assert!(*val == self.window[seq - oldest], "entry doesn't match its item");
+        }
+        assert!(lo.iter().max() <= hi.iter().min() || hi.is_empty(), "halves out of order");
+        assert!(self.lo.peek().is_none_or(|(_, seq)| *seq >= oldest), "dead entry on top of the lower half");
+        assert!(self.hi.peek().is_none_or(|Reverse((_, seq))| *seq >= oldest), "dead entry on top of the upper half");
+    }
+
+    pub fn clear(&mut self) {
+        self.window.clear();
+        self.lo.clear();
+        self.hi.clear();
+        self.lo_len = 0;
+        self.hi_len = 0;
+    }
+
+    // Push number of the oldest item still in the window
+    fn oldest(&self) -> usize {
+        self.pushed - self.window.len()
+    }
+
+    /// Push `val` as the newest item, returning the oldest one if it fell out
+    /// of the window.
+    pub fn push(&mut self, val: T) -> Option<T> {
+        let evicted = if self.window.len() == self.size { Some(self.evict()) } else { None };
+
+        let entry = (val.clone(), self.pushed);
+        self.window.push_back(val);
+        self.pushed += 1;
+        // Into `hi` when `lo` is empty; rebalancing then moves the smallest over
+        if self.lo.peek().is_some_and(|top| entry <= *top) {
+            self.lo.push(entry);
+            self.lo_len += 1;
+        } else {
+            self.hi.push(Reverse(entry));
+            self.hi_len += 1;
+        }
+        self.rebalance();
+        evicted
+    }
+
+    fn evict(&mut self) -> T {
+        let seq = self.oldest();
+        let val = self.window.pop_front().unwrap();
+        // The window isn't empty, so the top of `lo` is live
+        let top = self.lo.peek().unwrap();
+        if (&val, seq) <= (&top.0, top.1) {
+            self.lo_len -= 1;
+        } else {
+            self.hi_len -= 1;
+        }
+        self.prune();
+        val
+    }
+
+    // Evicting from one half and pushing to the other can leave them two
+    // apart, so this may take two moves
+    fn rebalance(&mut self) {
+        while self.lo_len > self.hi_len + 1 {
+            let entry = self.lo.pop().unwrap();
+            self.hi.push(Reverse(entry));
+            self.lo_len -= 1;
+            self.hi_len += 1;
+            self.prune();
+        }
+        while self.hi_len > self.lo_len {
+            let Reverse(entry) = self.hi.pop().unwrap();
+            self.lo.push(entry);
+            self.hi_len -= 1;
+            self.lo_len += 1;
+            self.prune();
+        }
+    }
+
+    // Drop dead entries from the tops, and rebuild a heap that is mostly dead
+    fn prune(&mut self) {
+        let oldest = self.oldest();
+        while self.lo.peek().is_some_and(|(_, seq)| *seq < oldest) {
+            self.lo.pop();
+        }
+        while self.hi.peek().is_some_and(|Reverse((_, seq))| *seq < oldest) {
+            self.hi.pop();
+        }
+        if self.lo.len() > 2 * self.lo_len + 16 {
+            self.lo.retain(|(_, seq)| *seq >= oldest);
+        }
+        if self.hi.len() > 2 * self.hi_len + 16 {
+            self.hi.retain(|Reverse((_, seq))| *seq >= oldest);
+        }
+    }
+
+    /// The median of the window, taking the lower of the two middle items
+    /// when its length is even. `None` if the window is empty.
+    pub fn median(&self) -> Option<&T> {
+        self.lo.peek().map(|(val, _)| val)
+    }
+
+    /// The two middle items of the window, lower first. They are the same
+    /// item when its length is odd.
+    pub fn middle(&self) -> Option<(&T, &T)> {
+        let lower = self.median()?;
+        if self.lo_len > self.hi_len {
+            return Some((lower, lower));
+        }
+        let Reverse((upper, _)) = self.hi.peek().unwrap();
+        Some((lower, upper))
+    }
+
+    /// The items in the window, oldest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> + ExactSizeIterator {
+        self.window.iter()
+    }
+}
+
+impl<T: Debug> Debug for WindowMedian<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.window.iter()).finish()
+    }
+}
+
+impl<T: HeapSize> HeapSize for WindowMedian<T> {
+    fn heap_size(&self) -> usize {
+        self.window.heap_size() + self.lo.heap_size() + self.hi.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heap_size::HeapSize;
+    use crate::rng::XorShift64;
+
+    use super::WindowMedian;
+
+    #[test]
+    fn test_basic() {
+        let mut window = WindowMedian::new(4);
+        assert_eq!(window.median(), None);
+        window.push(5);
+        assert_eq!(window.median(), Some(&5));
+        window.push(1);
+        assert_eq!(window.middle(), Some((&1, &5)));
+        window.push(3);
+        assert_eq!(window.middle(), Some((&3, &3)));
+        window.push(9);
+        assert_eq!(window.middle(), Some((&3, &5)));
+        assert_eq!(window.push(7), Some(5));
+        assert_eq!(window.iter().copied().collect::<Vec<_>>(), vec![1, 3, 9, 7]);
+        assert_eq!(window.middle(), Some((&3, &7)));
+        window.validate();
+        window.clear();
+        assert!(window.is_empty());
+        assert_eq!(window.middle(), None);
+    }
+
+    #[test]
+    fn test_against_sorted_window() {
+        let mut rng = XorShift64::new();
+        for size in [1, 2, 5, 32] {
+            let mut window = WindowMedian::new(size);
+            let mut all = Vec::new();
+            for _ in 0..2000 {
+                // Few distinct values, so there are plenty of duplicates
+                let val = rng.next_u64() % 20;
+                window.push(val);
+                all.push(val);
+                let mut recent = all[all.len().saturating_sub(size)..].to_vec();
+                recent.sort();
+                let mid = recent.len() / 2;
+                let expected = if recent.len() % 2 == 1 { (recent[mid], recent[mid]) } else { (recent[mid - 1], recent[mid]) };
+                assert_eq!(window.middle(), Some((&expected.0, &expected.1)));
+                if all.len() % 50 == 0 {
+                    window.validate();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_dead_entries_are_bounded() {
+        let mut window = WindowMedian::new(3);
+        // Rising values leave old ones buried at the bottom of `lo`
+        for i in 0..10_000 {
+            window.push(i);
+        }
+        assert!(window.lo.len() + window.hi.len() < 64);
+        window.validate();
+        assert_eq!(window.median(), Some(&9998));
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let mut window = WindowMedian::new(3);
+        for val in [5u32, 1, 4, 2] {
+            window.push(val);
+        }
+        assert!(window.heap_size() >= 3 * size_of::<u32>() + 3 * size_of::<(u32, usize)>());
+    }
+}
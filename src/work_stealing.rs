@@ -0,0 +1,337 @@
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::atomic::{AtomicIsize, AtomicPtr, Ordering, fence};
+use std::sync::Arc;
+
+use crate::cache_padded::CachePadded;
+use crate::heap_size::HeapSize;
+use crate::sync::epoch;
+
+const MIN_CAPACITY: usize = 16;
+
+struct Buffer<T> {
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    mask: usize,
+}
+
+impl<T> Buffer<T> {
+    fn new(capacity: usize) -> Self {
+        Buffer {
+            slots: (0..capacity).map(|_| UnsafeCell::new(MaybeUninit::uninit())).collect(),
+            mask: capacity - 1,
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    fn slot(&self, index: isize) -> *mut MaybeUninit<T> {
+        self.slots[index as usize & self.mask].get()
+    }
+
+    unsafe fn write(&self, index: isize, val: T) {
+        unsafe { (*self.slot(index)).write(val) };
+    }
+
+    // A possibly racing copy of the slot's bits. Only treat it as a `T` once
+    // the slot has been claimed.
+    unsafe fn read(&self, index: isize) -> MaybeUninit<T> {
+        unsafe { ptr::read_volatile(self.slot(index)) }
+    }
+}
+
+struct Inner<T> {
+    // Next index to steal; only ever grows
+    top: CachePadded<AtomicIsize>,
+    // Next index to push; only the owner writes it
+    bottom: CachePadded<AtomicIsize>,
+    // Replaced when growing; a thief may still be reading the old one, so
+    // it is freed through the epoch collector
+    buffer: CachePadded<AtomicPtr<Buffer<T>>>,
+}
+
+unsafe impl<T: Send> Send for Inner<T> {}
+unsafe impl<T: Send> Sync for Inner<T> {}
+
+impl<T> Drop for Inner<T> {
+    fn drop(&mut self) {
+        let buffer = unsafe { Box::from_raw(*self.buffer.get_mut()) };
+        for index in *self.top.get_mut()..*self.bottom.get_mut() {
+            unsafe { (*buffer.slot(index)).assume_init_drop() };
+        }
+    }
+}
+
+/// The result of a `Stealer::steal`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Steal<T> {
+    Empty,
+    Success(T),
+    /// Lost a race with the owner or another thief; try again.
+    Retry,
+}
+
+/// A Chase-Lev work-stealing deque.
+///
+/// The owning thread pushes and pops tasks at the bottom like a stack, while
+/// any number of `Stealer`s take the oldest tasks from the top. The owner's
+/// operations only synchronize with thieves when the deque is down to its
+/// last element; thieves contend with each other through one CAS on `top`.
+///
+/// The buffer doubles when full. Thieves pin the epoch while stealing, and
+/// the old buffer is only freed once `sync::epoch` sees no steal still using
+/// it.
+pub struct WorkStealingDeque<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T: Send> WorkStealingDeque<T> {
+    pub fn new() -> Self {
+        let buffer = Box::into_raw(Box::new(Buffer::new(MIN_CAPACITY)));
+        WorkStealingDeque {
+            inner: Arc::new(Inner {
+                top: CachePadded(AtomicIsize::new(0)),
+                bottom: CachePadded(AtomicIsize::new(0)),
+                buffer: CachePadded(AtomicPtr::new(buffer)),
+            }),
+        }
+    }
+
+    /// A handle that steals from the top of this deque.
+    pub fn stealer(&self) -> Stealer<T> {
+        Stealer { inner: self.inner.clone() }
+    }
+
+    /// Number of tasks. Only a snapshot while thieves are active.
+    pub fn len(&self) -> usize {
+        let bottom = self.inner.bottom.load(Ordering::Relaxed);
+        let top = self.inner.top.load(Ordering::Relaxed);
+        (bottom - top).max(0) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn push(&mut self, val: T) {
+        let inner = &*self.inner;
+        let bottom = inner.bottom.load(Ordering::Relaxed);
+        let top = inner.top.load(Ordering::Acquire);
+        let mut buffer = unsafe { &*inner.buffer.load(Ordering::Relaxed) };
+        if (bottom - top) as usize >= buffer.capacity() {
+            buffer = self.grow(bottom, top);
+        }
+        unsafe { buffer.write(bottom, val) };
+        fence(Ordering::Release);
+        inner.bottom.store(bottom + 1, Ordering::Relaxed);
+    }
+
+    // Copy the live tasks into a buffer twice the size
+    fn grow(&self, bottom: isize, top: isize) -> &Buffer<T> {
+        let inner = &*self.inner;
+        let old = inner.buffer.load(Ordering::Relaxed);
+        let new = Buffer::new(unsafe { &*old }.capacity() * 2);
+        for index in top..bottom {
+            unsafe { ptr::copy_nonoverlapping((*old).slot(index), new.slot(index), 1) };
+        }
+        let new = Box::into_raw(Box::new(new));
+        inner.buffer.store(new, Ordering::Release);
+        // The old slots were copied bitwise, so only the buffer itself goes.
+        // Safety: `Buffer<T>` never drops a `T`, so freeing it from another
+        // thread, after `T`'s lifetime, is fine.
+        let old = old as usize;
+        unsafe { epoch::pin().defer_unchecked(move || drop(Box::from_raw(old as *mut Buffer<T>))) };
+        unsafe { &*new }
+    }
+
+    /// Pop the most recently pushed task.
+    pub fn pop(&mut self) -> Option<T> {
+        let inner = &*self.inner;
+        let bottom = inner.bottom.load(Ordering::Relaxed) - 1;
+        let buffer = unsafe { &*inner.buffer.load(Ordering::Relaxed) };
+        // Reserve the bottom task before looking at top, so a thief either
+        // sees the reservation or we see its steal
+        inner.bottom.store(bottom, Ordering::Relaxed);
+        fence(Ordering::SeqCst);
+        let top = inner.top.load(Ordering::Relaxed);
+        if top > bottom {
+            inner.bottom.store(bottom + 1, Ordering::Relaxed);
+            return None;
+        }
+        let val = unsafe { buffer.read(bottom) };
+        if top == bottom {
+            // The last task: race the thieves for it
+            let won = inner.top.compare_exchange(top, top + 1, Ordering::SeqCst, Ordering::Relaxed).is_ok();
+            inner.bottom.store(bottom + 1, Ordering::Relaxed);
+            if !won {
+                return None;
+            }
+        }
+        Some(unsafe { val.assume_init() })
+    }
+}
+
+impl<T: Send> Default for WorkStealingDeque<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A handle for stealing from a `WorkStealingDeque`, from any thread.
+pub struct Stealer<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T: Send> Stealer<T> {
+    /// Take the oldest task.
+    pub fn steal(&self) -> Steal<T> {
+        let inner = &*self.inner;
+        // Keeps the buffer we load below alive if the owner grows it
+        let _guard = epoch::pin();
+        let top = inner.top.load(Ordering::Acquire);
+        fence(Ordering::SeqCst);
+        let bottom = inner.bottom.load(Ordering::Acquire);
+        if top >= bottom {
+            return Steal::Empty;
+        }
+        let buffer = unsafe { &*inner.buffer.load(Ordering::Acquire) };
+        let val = unsafe { buffer.read(top) };
+        if inner.top.compare_exchange(top, top + 1, Ordering::SeqCst, Ordering::Relaxed).is_err() {
+            return Steal::Retry;
+        }
+        Steal::Success(unsafe { val.assume_init() })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        let top = self.inner.top.load(Ordering::Acquire);
+        top >= self.inner.bottom.load(Ordering::Acquire)
+    }
+}
+
+impl<T> Clone for Stealer<T> {
+    fn clone(&self) -> Self {
+        Stealer { inner: self.inner.clone() }
+    }
+}
+
+/// Counts the current buffer only: thieves may be taking tasks out of it,
+/// so their payloads can't be looked at.
+impl<T> HeapSize for WorkStealingDeque<T> {
+    fn heap_size(&self) -> usize {
+        // Only the owner replaces the buffer, and it can't while borrowed
+        let buffer = unsafe { &*self.inner.buffer.load(Ordering::Relaxed) };
+        size_of::<Buffer<T>>() + buffer.capacity() * size_of::<UnsafeCell<MaybeUninit<T>>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    use crate::heap_size::HeapSize;
+
+    use super::{Steal, WorkStealingDeque};
+
+    #[test]
+    fn test_owner_is_lifo() {
+        let mut deque = WorkStealingDeque::new();
+        for i in 0..100 {
+            deque.push(i);
+        }
+        assert_eq!(deque.len(), 100);
+        assert!((0..100).rev().all(|i| deque.pop() == Some(i)));
+        assert_eq!(deque.pop(), None);
+        assert!(deque.is_empty());
+    }
+
+    #[test]
+    fn test_thief_is_fifo() {
+        let mut deque = WorkStealingDeque::new();
+        let stealer = deque.stealer();
+        assert_eq!(stealer.steal(), Steal::Empty);
+        for i in 0..40 {
+            deque.push(i);
+        }
+        assert_eq!(stealer.steal(), Steal::Success(0));
+        assert_eq!(stealer.clone().steal(), Steal::Success(1));
+        assert_eq!(deque.pop(), Some(39));
+        for i in 2..39 {
+            assert_eq!(stealer.steal(), Steal::Success(i));
+        }
+        assert!(stealer.is_empty());
+        assert_eq!(deque.pop(), None);
+    }
+
+    #[test]
+    fn test_drop_leftovers() {
+        let val = Arc::new(());
+        let mut deque = WorkStealingDeque::new();
+        for _ in 0..50 {
+            deque.push(val.clone());
+        }
+        drop(deque.pop());
+        drop(deque.stealer().steal());
+        assert_eq!(Arc::strong_count(&val), 49);
+        drop(deque);
+        assert_eq!(Arc::strong_count(&val), 1);
+    }
+
+    #[test]
+    fn test_concurrent_steals() {
+        const TASKS: usize = 100_000;
+        let mut deque = WorkStealingDeque::new();
+        let done = Arc::new(AtomicUsize::new(0));
+        let thieves: Vec<_> = (0..3)
+            .map(|_| {
+                let stealer = deque.stealer();
+                let done = done.clone();
+                thread::spawn(move || {
+                    let mut got = Vec::new();
+                    while done.load(Ordering::Acquire) == 0 || !stealer.is_empty() {
+                        match stealer.steal() {
+                            Steal::Success(task) => got.push(task),
+                            Steal::Empty | Steal::Retry => thread::yield_now(),
+                        }
+                    }
+                    got
+                })
+            })
+            .collect();
+
+        let mut mine = Vec::new();
+        for task in 0..TASKS {
+            deque.push(task);
+            // Keep some work for ourselves so pops race the thieves
+            if task % 3 == 0
+                && let Some(task) = deque.pop()
+            {
+                mine.push(task);
+            }
+        }
+        while let Some(task) = deque.pop() {
+            mine.push(task);
+        }
+        done.store(1, Ordering::Release);
+
+        let mut all: Vec<usize> = thieves.into_iter().flat_map(|t| t.join().unwrap()).collect();
+        all.extend(mine);
+        all.sort();
+        assert_eq!(all, (0..TASKS).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let mut deque = WorkStealingDeque::new();
+        let empty = deque.heap_size();
+        for i in 0..1000u64 {
+            deque.push(i);
+        }
+        // The buffer has doubled to fit every task
+        assert!(deque.heap_size() >= 1000 * size_of::<u64>());
+        assert!(deque.heap_size() > empty);
+    }
+}
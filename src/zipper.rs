@@ -0,0 +1,456 @@
+use std::fmt::Debug;
+
+use crate::cow_list::CowList;
+use crate::heap_size::HeapSize;
+use crate::sequence::LendingCursor;
+
+// The list `left` (nearest first) reversed, then `focus`, then `right`
+fn rebuild<T: Clone>(left: &CowList<T>, focus: T, right: &CowList<T>) -> CowList<T> {
+    let mut list = right.clone();
+    list.push_front(focus);
+    for val in left {
+        list.push_front(val.clone());
+    }
+    list
+}
+
+/// A focus on one element of a `CowList`, from which it can step left and
+/// right and edit the list in O(1) at the focus.
+///
+/// The elements before the focus are kept nearest first, in a list of their
+/// own, and those after it in another. Every operation takes `&self` and
+/// returns a new zipper, sharing all but a node or two with the old one, so
+/// earlier zippers stay valid and can be returned to.
+#[derive(Clone)]
+pub struct ListZipper<T> {
+    left: CowList<T>,
+    focus: T,
+    right: CowList<T>,
+}
+
+impl<T: Clone> ListZipper<T> {
+    /// A zipper focused on the first element of `list`, or `None` if it is
+    /// empty.
+    pub fn new(mut list: CowList<T>) -> Option<Self> {
+        let focus = list.pop_front()?;
+        Some(ListZipper { left: CowList::new(), focus, right: list })
+    }
+
+    pub fn focus(&self) -> &T {
+        &self.focus
+    }
+
+    /// Position of the focus in the list.
+    pub fn index(&self) -> usize {
+        self.left.len()
+    }
+
+    /// The zipper focused one element to the left, if there is one.
+    pub fn left(&self) -> Option<Self> {
+        let mut left = self.left.clone();
+        let focus = left.pop_front()?;
+        let mut right = self.right.clone();
+        right.push_front(self.focus.clone());
+        Some(ListZipper { left, focus, right })
+    }
+
+    /// The zipper focused one element to the right, if there is one.
+    pub fn right(&self) -> Option<Self> {
+        let mut right = self.right.clone();
+        let focus = right.pop_front()?;
+        let mut left = self.left.clone();
+        left.push_front(self.focus.clone());
+        Some(ListZipper { left, focus, right })
+    }
+
+    /// The zipper with `val` in place of the focus.
+    pub fn set(&self, val: T) -> Self {
+        ListZipper { left: self.left.clone(), focus: val, right: self.right.clone() }
+    }
+
+    /// The zipper with `val` inserted before the focus, still focused on the
+    /// same element.
+    pub fn insert_left(&self, val: T) -> Self {
+        let mut zipper = self.clone();
+        zipper.left.push_front(val);
+        zipper
+    }
+
+    /// The zipper with `val` inserted after the focus, still focused on the
+    /// same element.
+    pub fn insert_right(&self, val: T) -> Self {
+        let mut zipper = self.clone();
+        zipper.right.push_front(val);
+        zipper
+    }
+
+    /// The zipper with the focus removed, focused on the element after it,
+    /// or before it at the end of the list. `None` if it was the only one.
+    pub fn remove(&self) -> Option<Self> {
+        let mut right = self.right.clone();
+        if let Some(focus) = right.pop_front() {
+            return Some(ListZipper { left: self.left.clone(), focus, right });
+        }
+        let mut left = self.left.clone();
+        let focus = left.pop_front()?;
+        Some(ListZipper { left, focus, right })
+    }
+
+    /// The whole list, edits included.
+    pub fn to_list(&self) -> CowList<T> {
+        rebuild(&self.left, self.focus.clone(), &self.right)
+    }
+}
+
+/// Steps the zipper in place, so it can go where a cursor is expected.
+impl<T: Clone> LendingCursor for ListZipper<T> {
+    type Elem = T;
+
+    type Item<'a>
+        = &'a T
+    where
+        Self: 'a;
+
+    fn peek(&mut self) -> Option<Self::Item<'_>> {
+        Some(&self.focus)
+    }
+
+    fn move_next(&mut self) -> bool {
+        self.right().map(|zipper| *self = zipper).is_some()
+    }
+
+    fn move_prev(&mut self) -> bool {
+        self.left().map(|zipper| *self = zipper).is_some()
+    }
+}
+
+impl<T: Debug> Debug for ListZipper<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let left: Vec<_> = self.left.iter().collect();
+        f.debug_struct("ListZipper")
+            .field("left", &left.into_iter().rev().collect::<Vec<_>>())
+            .field("focus", &self.focus)
+            .field("right", &self.right)
+            .finish()
+    }
+}
+
+/// A persistent tree where every node holds a value and any number of
+/// ordered children, for navigating and editing with a `TreeZipper`.
+///
+/// The crate's search trees keep their elements in an order of their own and
+/// can't be edited at an arbitrary node, so zippers work on this plain tree.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Tree<T> {
+    value: T,
+    children: CowList<Tree<T>>,
+}
+
+impl<T> Tree<T> {
+    pub fn leaf(value: T) -> Self {
+        Tree { value, children: CowList::new() }
+    }
+
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    pub fn children(&self) -> crate::cow_list::Iter<'_, Tree<T>> {
+        self.children.iter()
+    }
+
+    /// Number of nodes in the tree.
+    pub fn size(&self) -> usize {
+        1 + self.children().map(Tree::size).sum::<usize>()
+    }
+}
+
+impl<T: Clone> Tree<T> {
+    pub fn new<I: IntoIterator<Item = Tree<T>>>(value: T, children: I) -> Self {
+        Tree { value, children: children.into_iter().collect() }
+    }
+}
+
+// What the focus left behind at each level on the way down: the parent's
+// value and the focus's siblings on either side, the left ones nearest first
+#[derive(Clone)]
+struct Crumb<T> {
+    value: T,
+    left: CowList<Tree<T>>,
+    right: CowList<Tree<T>>,
+}
+
+/// A focus on one subtree of a `Tree`, from which it can move up, down to
+/// the first child, and left and right among siblings, editing the tree at
+/// the focus.
+///
+/// The path back to the root is kept as a list of the parents' values and
+/// the siblings at each level, so moves and edits only rebuild the nodes
+/// they touch. Like `ListZipper`, every operation returns a new zipper and
+/// leaves the old one valid, sharing everything else with it.
+#[derive(Clone)]
+pub struct TreeZipper<T> {
+    focus: Tree<T>,
+    path: CowList<Crumb<T>>,
+}
+
+impl<T: Clone> TreeZipper<T> {
+    /// A zipper focused on the root of `tree`.
+    pub fn new(tree: Tree<T>) -> Self {
+        TreeZipper { focus: tree, path: CowList::new() }
+    }
+
+    /// The value at the focus.
+    pub fn focus(&self) -> &T {
+        &self.focus.value
+    }
+
+    /// The subtree at the focus.
+    pub fn subtree(&self) -> &Tree<T> {
+        &self.focus
+    }
+
+    /// Number of steps up to the root.
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.path.is_empty()
+    }
+
+    /// The zipper focused on the first child, if there is one.
+    pub fn down(&self) -> Option<Self> {
+        let mut right = self.focus.children.clone();
+        let focus = right.pop_front()?;
+        let mut path = self.path.clone();
+        path.push_front(Crumb { value: self.focus.value.clone(), left: CowList::new(), right });
+        Some(TreeZipper { focus, path })
+    }
+
+    /// The zipper focused on the parent, or `None` at the root.
+    pub fn up(&self) -> Option<Self> {
+        let mut path = self.path.clone();
+        let crumb = path.pop_front()?;
+        let children = rebuild(&crumb.left, self.focus.clone(), &crumb.right);
+        Some(TreeZipper { focus: Tree { value: crumb.value, children }, path })
+    }
+
+    // Replace the siblings around the focus in the nearest crumb
+    fn with_siblings(&self, focus: Tree<T>, left: CowList<Tree<T>>, right: CowList<Tree<T>>) -> Option<Self> {
+        let mut path = self.path.clone();
+        let crumb = path.pop_front()?;
+        path.push_front(Crumb { value: crumb.value, left, right });
+        Some(TreeZipper { focus, path })
+    }
+
+    /// The zipper focused on the previous sibling, if there is one.
+    pub fn left(&self) -> Option<Self> {
+        let crumb = self.path.front()?;
+        let mut left = crumb.left.clone();
+        let focus = left.pop_front()?;
+        let mut right = crumb.right.clone();
+        right.push_front(self.focus.clone());
+        self.with_siblings(focus, left, right)
+    }
+
+    /// The zipper focused on the next sibling, if there is one.
+    pub fn right(&self) -> Option<Self> {
+        let crumb = self.path.front()?;
+        let mut right = crumb.right.clone();
+        let focus = right.pop_front()?;
+        let mut left = crumb.left.clone();
+        left.push_front(self.focus.clone());
+        self.with_siblings(focus, left, right)
+    }
+
+    /// The zipper with the focus's value replaced by `value`, keeping its
+    /// children.
+    pub fn set(&self, value: T) -> Self {
+        TreeZipper { focus: Tree { value, children: self.focus.children.clone() }, path: self.path.clone() }
+    }
+
+    /// The zipper with the whole subtree at the focus replaced by `tree`.
+    pub fn replace(&self, tree: Tree<T>) -> Self {
+        TreeZipper { focus: tree, path: self.path.clone() }
+    }
+
+    /// The zipper with `tree` added as the first child of the focus, still
+    /// focused on the same node.
+    pub fn insert_child(&self, tree: Tree<T>) -> Self {
+        let mut zipper = self.clone();
+        zipper.focus.children.push_front(tree);
+        zipper
+    }
+
+    /// The zipper with `tree` added as the sibling before the focus, still
+    /// focused on the same node. `None` at the root.
+    pub fn insert_left(&self, tree: Tree<T>) -> Option<Self> {
+        let crumb = self.path.front()?;
+        let mut left = crumb.left.clone();
+        left.push_front(tree);
+        self.with_siblings(self.focus.clone(), left, crumb.right.clone())
+    }
+
+    /// The zipper with `tree` added as the sibling after the focus, still
+    /// focused on the same node. `None` at the root.
+    pub fn insert_right(&self, tree: Tree<T>) -> Option<Self> {
+        let crumb = self.path.front()?;
+        let mut right = crumb.right.clone();
+        right.push_front(tree);
+        self.with_siblings(self.focus.clone(), crumb.left.clone(), right)
+    }
+
+    /// The zipper with the subtree at the focus removed, focused on the next
+    /// sibling, else the previous one, else the parent. `None` at the root.
+    pub fn remove(&self) -> Option<Self> {
+        let crumb = self.path.front()?;
+        let (mut left, mut right) = (crumb.left.clone(), crumb.right.clone());
+        if let Some(focus) = right.pop_front() {
+            return self.with_siblings(focus, left, right);
+        }
+        if let Some(focus) = left.pop_front() {
+            return self.with_siblings(focus, left, right);
+        }
+        let mut path = self.path.clone();
+        let crumb = path.pop_front()?;
+        Some(TreeZipper { focus: Tree::leaf(crumb.value), path })
+    }
+
+    /// The zipper focused on the root.
+    pub fn top(&self) -> Self {
+        let mut zipper = self.clone();
+        while let Some(parent) = zipper.up() {
+            zipper = parent;
+        }
+        zipper
+    }
+
+    /// The whole tree, edits included.
+    pub fn to_tree(&self) -> Tree<T> {
+        self.top().focus
+    }
+}
+
+impl<T: Debug> Debug for TreeZipper<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TreeZipper").field("focus", &self.focus).field("depth", &self.path.len()).finish()
+    }
+}
+
+impl<T: HeapSize> HeapSize for ListZipper<T> {
+    fn heap_size(&self) -> usize {
+        self.left.heap_size() + self.focus.heap_size() + self.right.heap_size()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Tree<T> {
+    fn heap_size(&self) -> usize {
+        self.value.heap_size() + self.children.heap_size()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Crumb<T> {
+    fn heap_size(&self) -> usize {
+        self.value.heap_size() + self.left.heap_size() + self.right.heap_size()
+    }
+}
+
+impl<T: HeapSize> HeapSize for TreeZipper<T> {
+    fn heap_size(&self) -> usize {
+        self.focus.heap_size() + self.path.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cow_list::CowList;
+
+    use super::{ListZipper, Tree, TreeZipper};
+
+    fn values<T: Clone>(list: &CowList<T>) -> Vec<T> {
+        list.iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_list_navigation() {
+        let list: CowList<_> = (0..4).collect();
+        assert!(ListZipper::new(CowList::<i32>::new()).is_none());
+        let zipper = ListZipper::new(list.clone()).unwrap();
+        assert_eq!(*zipper.focus(), 0);
+        assert!(zipper.left().is_none());
+        let at2 = zipper.right().unwrap().right().unwrap();
+        assert_eq!((*at2.focus(), at2.index()), (2, 2));
+        assert_eq!(*at2.left().unwrap().focus(), 1);
+        let last = at2.right().unwrap();
+        assert!(last.right().is_none());
+        assert_eq!(values(&last.to_list()), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_list_edits_are_persistent() {
+        let list: CowList<_> = (0..4).collect();
+        let at1 = ListZipper::new(list.clone()).unwrap().right().unwrap();
+        let edited = at1.set(10).insert_left(5).insert_right(15);
+        assert_eq!(values(&edited.to_list()), vec![0, 5, 10, 15, 2, 3]);
+        assert_eq!(*edited.focus(), 10);
+        // The old zipper and the original list are untouched
+        assert_eq!(values(&at1.to_list()), vec![0, 1, 2, 3]);
+        assert_eq!(values(&list), vec![0, 1, 2, 3]);
+
+        let removed = at1.remove().unwrap();
+        assert_eq!((*removed.focus(), values(&removed.to_list())), (2, vec![0, 2, 3]));
+        let last = removed.right().unwrap().remove().unwrap();
+        assert_eq!((*last.focus(), values(&last.to_list())), (2, vec![0, 2]));
+        let single = ListZipper::new((0..1).collect()).unwrap();
+        assert!(single.remove().is_none());
+    }
+
+    fn sample() -> Tree<&'static str> {
+        // a
+        // ├── b
+        // │   ├── d
+        // │   └── e
+        // └── c
+        Tree::new("a", [Tree::new("b", [Tree::leaf("d"), Tree::leaf("e")]), Tree::leaf("c")])
+    }
+
+    #[test]
+    fn test_tree_navigation() {
+        let root = TreeZipper::new(sample());
+        assert!(root.is_root() && root.up().is_none() && root.left().is_none());
+        let d = root.down().unwrap().down().unwrap();
+        assert_eq!((*d.focus(), d.depth()), ("d", 2));
+        assert!(d.down().is_none());
+        let e = d.right().unwrap();
+        assert_eq!(*e.focus(), "e");
+        assert!(e.right().is_none());
+        assert_eq!(*e.left().unwrap().focus(), "d");
+        let b = e.up().unwrap();
+        assert_eq!(b.subtree().size(), 3);
+        assert_eq!(*b.right().unwrap().focus(), "c");
+        assert_eq!(e.top().subtree(), &sample());
+    }
+
+    #[test]
+    fn test_tree_edits_are_persistent() {
+        let tree = sample();
+        let e = TreeZipper::new(tree.clone()).down().unwrap().down().unwrap().right().unwrap();
+        let edited = e.set("E").insert_left(Tree::leaf("x")).unwrap().insert_child(Tree::leaf("y"));
+        let expected = Tree::new("a", [Tree::new("b", [Tree::leaf("d"), Tree::leaf("x"), Tree::new("E", [Tree::leaf("y")])]), Tree::leaf("c")]);
+        assert_eq!(edited.to_tree(), expected);
+        assert_eq!(e.to_tree(), tree);
+
+        // Removing moves to the next sibling, then the previous, then up
+        let b = e.up().unwrap();
+        let c = b.remove().unwrap();
+        assert_eq!(*c.focus(), "c");
+        let a = c.remove().unwrap();
+        assert_eq!((*a.focus(), a.subtree().size()), ("a", 1));
+        assert!(a.remove().is_none());
+        let d = e.remove().unwrap();
+        assert_eq!(*d.focus(), "d");
+        assert_eq!(d.to_tree().size(), 4);
+        assert!(TreeZipper::new(tree).insert_right(Tree::leaf("z")).is_none());
+    }
+}